@@ -0,0 +1,122 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::collections::BTreeSet;
+use wordbox::lexicon::{
+    BuildLexicon, HashMapLexicon, Lexicon, LexiconKind, LexiconOptions, VecLexicon,
+};
+use wordbox::loader::filter_words;
+use wordbox::solver::solve_word_box;
+use wordbox::wordbox::WordBox;
+
+const WORDLIST: &str = "3esl.txt";
+
+/// Seeds solved per iteration of the solve benchmarks
+const SEEDS: usize = 20;
+
+fn construction<L: BuildLexicon>(c: &mut Criterion, name: &str, words: &[String]) {
+    c.bench_function(&format!("{}/initialize", name), |b| {
+        b.iter(|| L::initialize(black_box(words.to_vec()), BTreeSet::from([4, 5, 6])))
+    });
+}
+
+/// Construction over a pool of each size, to show how it scales with the number of threads
+fn parallel_construction<L: BuildLexicon + Send>(c: &mut Criterion, name: &str, words: &[String]) {
+    let mut group = c.benchmark_group(format!("{}/initialize/threads", name));
+    group.sample_size(10);
+    for threads in [1, 2, 4] {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(threads), &pool, |b, pool| {
+            b.iter(|| {
+                pool.install(|| L::initialize(black_box(words.to_vec()), BTreeSet::from([6, 7, 8])))
+            })
+        });
+    }
+    group.finish();
+}
+
+fn prefix_queries<L: BuildLexicon>(c: &mut Criterion, name: &str, words: &[String], len: usize) {
+    let lexicon = L::initialize(words.to_vec(), BTreeSet::from([len]));
+    let mut group = c.benchmark_group(format!("{}/words_with_prefix", name));
+    for prefix in ["", "s", "st", "stre"] {
+        group.bench_with_input(BenchmarkId::from_parameter(prefix), prefix, |b, prefix| {
+            b.iter(|| lexicon.words_with_prefix(black_box(prefix), len))
+        });
+    }
+    group.finish();
+}
+
+/// Over 100k words: the word list along with every word plus each of a few common endings
+fn large_word_list(words: &[String]) -> Vec<String> {
+    let endings = ["", "s", "ed", "er", "ing", "ly"];
+    endings
+        .iter()
+        .flat_map(|ending| words.iter().map(move |word| format!("{}{}", word, ending)))
+        .collect()
+}
+
+/// Solves over the lexicon `build` makes, which may be a `DynLexicon` to weigh dynamic
+/// dispatch against the solves over a lexicon's own type
+fn solves<L: Lexicon>(
+    c: &mut Criterion,
+    name: &str,
+    words: &[String],
+    build: impl Fn(Vec<String>, BTreeSet<usize>) -> L,
+) {
+    let mut group = c.benchmark_group(format!("{}/solve", name));
+    group.sample_size(10);
+    for dim in [4, 5, 6] {
+        let lexicon = build(words.to_vec(), BTreeSet::from([dim]));
+        let seeds: Vec<String> = lexicon
+            .words_with_prefix("", dim)
+            .into_iter()
+            .take(SEEDS)
+            .collect();
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("{}x{}", dim, dim)),
+            &seeds,
+            |b, seeds| {
+                b.iter(|| {
+                    seeds
+                        .iter()
+                        .filter_map(|seed| {
+                            let wb = WordBox::new(dim, dim, true).add_word(seed.clone()).unwrap();
+                            solve_word_box(wb, &lexicon)
+                        })
+                        .count()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn lexicon_benches(c: &mut Criterion) {
+    let words: Vec<String> =
+        (filter_words(WORDLIST).collect::<std::io::Result<_>>()).expect("Could not read word list");
+
+    construction::<VecLexicon>(c, "vec", &words);
+    construction::<HashMapLexicon>(c, "hashmap", &words);
+
+    prefix_queries::<VecLexicon>(c, "vec", &words, 6);
+    prefix_queries::<HashMapLexicon>(c, "hashmap", &words, 6);
+
+    let large = large_word_list(&words);
+    parallel_construction::<VecLexicon>(c, "vec/large", &large);
+    parallel_construction::<HashMapLexicon>(c, "hashmap/large", &large);
+    prefix_queries::<VecLexicon>(c, "vec/large", &large, 7);
+    prefix_queries::<HashMapLexicon>(c, "hashmap/large", &large, 7);
+
+    solves(c, "vec", &words, VecLexicon::initialize);
+    solves(c, "hashmap", &words, HashMapLexicon::initialize);
+    for kind in LexiconKind::ALL {
+        let name = format!("{}/dyn", kind);
+        solves(c, &name, &words, |words, lengths| {
+            kind.build(words, lengths, &LexiconOptions::default())
+        });
+    }
+}
+
+criterion_group!(benches, lexicon_benches);
+criterion_main!(benches);
@@ -0,0 +1,46 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::io::Write;
+use wordbox::loader::{read_words, WordFilter};
+
+const WORDLIST: &str = "3esl.txt";
+
+/// Lines in the generated word list
+const LINES: usize = 1_000_000;
+
+/// A word list of `LINES` lines: the words of the list, then each word again with every ending
+/// of a consonant and a vowel until there are enough
+fn write_large_word_list() -> std::path::PathBuf {
+    let words: Vec<String> = std::fs::read_to_string(WORDLIST)
+        .expect("Could not read the word list")
+        .lines()
+        .map(str::to_string)
+        .collect();
+    let endings = std::iter::once(String::new())
+        .chain(('a'..='z').flat_map(|a| "aeiou".chars().map(move |b| format!("{}{}", a, b))));
+    let path = std::env::temp_dir().join("wordbox-loader-bench.txt");
+    let mut file = std::io::BufWriter::new(std::fs::File::create(&path).unwrap());
+    let lines =
+        endings.flat_map(|ending| words.iter().map(move |word| format!("{}{}", word, ending)));
+    for line in lines.take(LINES) {
+        writeln!(file, "{}", line).unwrap();
+    }
+    path
+}
+
+fn loading(c: &mut Criterion) {
+    let path = write_large_word_list();
+    let path = path.to_str().unwrap();
+    let filter = WordFilter::default();
+
+    let mut group = c.benchmark_group("load_words/1M");
+    group.sample_size(10);
+    group.bench_function("read", |b| b.iter(|| read_words(path, &filter)));
+    #[cfg(feature = "mmap")]
+    group.bench_function("mmap", |b| {
+        b.iter(|| wordbox::loader::map_words(path, &filter))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, loading);
+criterion_main!(benches);
@@ -0,0 +1,295 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::collections::BTreeSet;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use wordbox::alphabet::Alphabet;
+use wordbox::lexicon::{BuildLexicon, HashMapLexicon, Lexicon, LexiconOptions, VecLexicon};
+use wordbox::loader::{filter_words, load_words, WordFilter};
+use wordbox::runner::{run_seeds, RunEvent, RunOptions, RunState};
+use wordbox::solver::{solve_word_box_all, SearchOrder, SolverConfig, ValueOrder};
+use wordbox::stats::CorpusFrequency;
+use wordbox::wordbox::WordBox;
+
+const WORDLIST: &str = "3esl.txt";
+
+/// The words of `dim` letters, indexed as they are read from the word list
+fn lexicon(dim: usize) -> HashMapLexicon {
+    let lengths = BTreeSet::from([dim]);
+    HashMapLexicon::try_from_words(filter_words(WORDLIST), lengths, &LexiconOptions::default())
+        .expect("Could not read word list")
+}
+
+/// Solve every 5x5 seed with a single thread and with every core
+fn threads(c: &mut Criterion) {
+    let dim = 5;
+    let lexicon = lexicon(dim);
+    let seeds = lexicon.words_with_prefix("", dim);
+
+    let mut group = c.benchmark_group("run_seeds/5x5");
+    group.sample_size(10);
+    for threads in [1, 0] {
+        let options = RunOptions {
+            threads,
+            ..RunOptions::default()
+        };
+        group.bench_with_input(
+            BenchmarkId::new("threads", threads),
+            &options,
+            |b, options| {
+                b.iter(|| {
+                    let mut solutions = 0;
+                    run_seeds(
+                        &lexicon,
+                        &seeds,
+                        |seed| {
+                            WordBox::new(dim, dim, true)
+                                .add_word(seed.to_string())
+                                .unwrap()
+                        },
+                        options,
+                        &RunState::default(),
+                        |event| {
+                            if let RunEvent::Solution(_) = event {
+                                solutions += 1;
+                            }
+                        },
+                    );
+                    solutions
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Time to the first 6x6 solution, trying seeds in order, for each candidate ordering
+fn value_order(c: &mut Criterion) {
+    let dim = 6;
+    let lexicon = lexicon(dim);
+    let seeds = lexicon.words_with_prefix("", dim);
+
+    let mut group = c.benchmark_group("first_solution/6x6");
+    group.sample_size(10);
+    for value_order in ValueOrder::ALL {
+        let config = SolverConfig {
+            value_order,
+            corpus_frequency: Some(Arc::new(CorpusFrequency::of(&lexicon, dim, dim))),
+            ..SolverConfig::default()
+        };
+        group.bench_with_input(
+            BenchmarkId::new("value_order", value_order),
+            &config,
+            |b, config| {
+                b.iter(|| {
+                    seeds.iter().position(|seed| {
+                        let wb = WordBox::new(dim, dim, true)
+                            .add_word(seed.to_string())
+                            .unwrap();
+                        solve_word_box_all(wb, &lexicon, config, |_| ControlFlow::Break(()))
+                            .solutions
+                            > 0
+                    })
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Time to the first 4x4 solution over a Spanish word list in alphabetical order, trying seeds
+/// in order, for each candidate ordering
+fn value_order_spanish(c: &mut Criterion) {
+    let dim = 4;
+    let filter = WordFilter {
+        alphabet: Alphabet::Unicode,
+        ..WordFilter::default()
+    };
+    let (words, _) = load_words("tests/fixtures/spanish-4.txt", &filter);
+    let lexicon = VecLexicon::initialize(words, BTreeSet::from([dim]));
+    let seeds = lexicon.words_with_prefix("", dim);
+
+    let mut group = c.benchmark_group("first_solution/spanish-4x4");
+    group.sample_size(10);
+    for value_order in ValueOrder::ALL {
+        let config = SolverConfig {
+            value_order,
+            corpus_frequency: Some(Arc::new(CorpusFrequency::of(&lexicon, dim, dim))),
+            ..SolverConfig::default()
+        };
+        group.bench_with_input(
+            BenchmarkId::new("value_order", value_order),
+            &config,
+            |b, config| {
+                b.iter(|| {
+                    seeds.iter().position(|seed| {
+                        let wb = WordBox::new(dim, dim, false)
+                            .add_word(seed.to_string())
+                            .unwrap();
+                        solve_word_box_all(wb, &lexicon, config, |_| ControlFlow::Break(()))
+                            .solutions
+                            > 0
+                    })
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Exhaustive 5x5 enumeration over the first seeds with and without forward checking
+fn forward_checking(c: &mut Criterion) {
+    let dim = 5;
+    let lexicon = lexicon(dim);
+    let seeds: Vec<String> = lexicon
+        .words_with_prefix("", dim)
+        .into_iter()
+        .take(100)
+        .collect();
+
+    let mut group = c.benchmark_group("all_solutions/5x5");
+    group.sample_size(10);
+    for forward_checking in [false, true] {
+        let config = SolverConfig {
+            forward_checking,
+            ..SolverConfig::default()
+        };
+        group.bench_with_input(
+            BenchmarkId::new("forward_checking", forward_checking),
+            &config,
+            |b, config| {
+                b.iter(|| {
+                    seeds
+                        .iter()
+                        .map(|seed| {
+                            let wb = WordBox::new(dim, dim, true)
+                                .add_word(seed.to_string())
+                                .unwrap();
+                            solve_word_box_all(wb, &lexicon, config, |_| ControlFlow::Continue(()))
+                                .solutions
+                        })
+                        .sum::<usize>()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Exhaustive 4x4 enumeration from the empty box with the frontier of each search order
+fn search_order(c: &mut Criterion) {
+    let dim = 4;
+    let lexicon = lexicon(dim);
+
+    let mut group = c.benchmark_group("all_solutions/4x4");
+    group.sample_size(10);
+    for order in SearchOrder::ALL {
+        let config = SolverConfig {
+            order,
+            ..SolverConfig::default()
+        };
+        group.bench_with_input(BenchmarkId::new("order", order), &config, |b, config| {
+            b.iter(|| {
+                let wb = WordBox::new(dim, dim, true);
+                solve_word_box_all(wb, &lexicon, config, |_| ControlFlow::Continue(())).solutions
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Every asymmetric 5x5 box below "aback", one of the slowest seeds, with the seed's search split
+/// into tasks at each depth; depth 0 leaves the whole seed to one worker
+fn split_depth(c: &mut Criterion) {
+    let dim = 5;
+    let lexicon = lexicon(dim);
+    let seeds = vec!["aback".to_string()];
+
+    let mut group = c.benchmark_group("slow_seed/5x5");
+    group.sample_size(10);
+    for split_depth in [0, 1, 2] {
+        let options = RunOptions {
+            exhaustive: true,
+            split_depth,
+            solver: SolverConfig {
+                symmetry_breaking: false,
+                ..SolverConfig::default()
+            },
+            ..RunOptions::default()
+        };
+        group.bench_with_input(
+            BenchmarkId::new("split_depth", split_depth),
+            &options,
+            |b, options| {
+                b.iter(|| {
+                    let mut solutions = 0;
+                    run_seeds(
+                        &lexicon,
+                        &seeds,
+                        |seed| {
+                            WordBox::new(dim, dim, false)
+                                .add_word(seed.to_string())
+                                .unwrap()
+                        },
+                        options,
+                        &RunState::default(),
+                        |event| {
+                            if let RunEvent::Solution(_) = event {
+                                solutions += 1;
+                            }
+                        },
+                    );
+                    solutions
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Asymmetric 6x6 boxes below the first seeds, each search cut off after the same number of
+/// nodes, so that what is timed is how fast candidate rows are found and checked
+fn asymmetric(c: &mut Criterion) {
+    let dim = 6;
+    let lexicon = lexicon(dim);
+    let seeds: Vec<String> = lexicon
+        .words_with_prefix("", dim)
+        .into_iter()
+        .take(10)
+        .collect();
+    let config = SolverConfig {
+        symmetry_breaking: false,
+        max_nodes: Some(20_000),
+        ..SolverConfig::default()
+    };
+
+    let mut group = c.benchmark_group("asymmetric/6x6");
+    group.sample_size(10);
+    group.bench_function("first_seeds", |b| {
+        b.iter(|| {
+            seeds
+                .iter()
+                .map(|seed| {
+                    let wb = WordBox::new(dim, dim, false)
+                        .add_word(seed.to_string())
+                        .unwrap();
+                    solve_word_box_all(wb, &lexicon, &config, |_| ControlFlow::Continue(()))
+                        .stats
+                        .nodes
+                })
+                .sum::<usize>()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    threads,
+    value_order,
+    value_order_spanish,
+    forward_checking,
+    search_order,
+    split_depth,
+    asymmetric
+);
+criterion_main!(benches);
@@ -0,0 +1,170 @@
+use crate::blocks::BLOCK;
+use crate::digraphs::Tile;
+use crate::lexicon::WILDCARD;
+use crate::template::OPEN_CELLS;
+use std::collections::BTreeSet;
+use std::fmt::Display;
+
+/// The letters words may be made of. The loader, the template parser and prefix queries all
+/// check characters against the same alphabet, so a letter one of them accepts can always end
+/// up in a box.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Alphabet {
+    /// The lowercase ASCII letters a to z
+    #[default]
+    Ascii,
+    /// Any lowercase letter of any script
+    Unicode,
+    /// Exactly the given characters
+    Custom(BTreeSet<char>),
+}
+
+/// Why a word was rejected: the class of the first character outside the alphabet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    Whitespace,
+    Punctuation,
+    Digit,
+    OutsideAlphabet,
+}
+
+impl Alphabet {
+    /// Whether `ch` is a letter of the alphabet (uppercase letters never are), or a tile (see
+    /// `Digraphs`) made of letters of the alphabet
+    pub fn contains(&self, ch: char) -> bool {
+        let letter = match self {
+            Alphabet::Ascii => ch.is_ascii_lowercase(),
+            Alphabet::Unicode => ch.is_alphabetic() && !ch.is_uppercase(),
+            Alphabet::Custom(letters) => letters.contains(&ch),
+        };
+        letter || Tile::of(ch).is_some_and(|tile| tile.as_str().chars().all(|ch| self.contains(ch)))
+    }
+
+    /// Why `word` cannot be used, or `None` if every character is in the alphabet
+    pub fn check(&self, word: &str) -> Option<Rejection> {
+        let ch = word.chars().find(|&ch| !self.contains(ch))?;
+        Some(if ch.is_whitespace() {
+            Rejection::Whitespace
+        } else if ch.is_ascii_punctuation() {
+            Rejection::Punctuation
+        } else if ch.is_numeric() {
+            Rejection::Digit
+        } else {
+            Rejection::OutsideAlphabet
+        })
+    }
+}
+
+impl Display for Alphabet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Alphabet::Ascii => write!(f, "ascii"),
+            Alphabet::Unicode => write!(f, "unicode"),
+            Alphabet::Custom(letters) => letters.iter().try_for_each(|ch| write!(f, "{}", ch)),
+        }
+    }
+}
+
+impl std::str::FromStr for Alphabet {
+    type Err = String;
+
+    /// `ascii`, `unicode`, or the letters of a custom alphabet, lowercased
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ascii" => return Ok(Alphabet::Ascii),
+            "unicode" => return Ok(Alphabet::Unicode),
+            "" => return Err("the alphabet has no letters".to_string()),
+            _ => {}
+        }
+        let mut letters = BTreeSet::new();
+        for ch in s.chars() {
+            if ch.is_whitespace() || ch == WILDCARD || ch == BLOCK || OPEN_CELLS.contains(&ch) {
+                return Err(format!("{:?} cannot be a letter of the alphabet", ch));
+            }
+            letters.extend(ch.to_lowercase());
+        }
+        Ok(Alphabet::Custom(letters))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexicon::parse_prefix;
+    use crate::loader::{DropReason, WordFilter};
+    use crate::template::Template;
+    use crate::wordbox::WordBox;
+
+    #[test]
+    fn rejections_are_classed_by_the_first_character_outside() {
+        let ascii = Alphabet::Ascii;
+        assert_eq!(ascii.check("word"), None);
+        assert_eq!(ascii.check("2nd"), Some(Rejection::Digit));
+        assert_eq!(ascii.check("b4"), Some(Rejection::Digit));
+        assert_eq!(ascii.check("no№"), Some(Rejection::OutsideAlphabet));
+        assert_eq!(ascii.check("it's"), Some(Rejection::Punctuation));
+        assert_eq!(ascii.check("ad hoc"), Some(Rejection::Whitespace));
+        assert_eq!(ascii.check("café"), Some(Rejection::OutsideAlphabet));
+        // Digits of other scripts are digits too
+        assert_eq!(Alphabet::Unicode.check("x٣"), Some(Rejection::Digit));
+    }
+
+    #[test]
+    fn mixed_scripts_are_letters_of_the_unicode_alphabet_only() {
+        let lines = ["cat", "café", "кот", "γάτα", "猫", "2nd", "CAT"].map(String::from);
+        let filter = |alphabet| WordFilter {
+            alphabet,
+            ..WordFilter::default()
+        };
+        let (words, report) = filter(Alphabet::Ascii).apply(lines.to_vec());
+        assert_eq!(words, ["cat"]);
+        assert_eq!(
+            (report.outside, report.digits, report.collisions),
+            (4, 1, 1)
+        );
+        let (words, report) = filter(Alphabet::Unicode).apply(lines.to_vec());
+        assert_eq!(words, ["cat", "café", "кот", "γάτα", "猫"]);
+        assert_eq!(
+            (report.outside, report.digits, report.collisions),
+            (0, 1, 1)
+        );
+    }
+
+    #[test]
+    fn a_custom_alphabet_is_shared_by_the_lexicon_templates_and_boxes() {
+        let alphabet: Alphabet = "ABCÑ".parse().unwrap();
+        assert_eq!(
+            alphabet,
+            Alphabet::Custom(BTreeSet::from(['a', 'b', 'c', 'ñ']))
+        );
+        assert_eq!(alphabet.to_string(), "abcñ");
+        assert!("a?c".parse::<Alphabet>().is_err());
+        assert!("".parse::<Alphabet>().is_err());
+
+        let filter = WordFilter {
+            alphabet: alphabet.clone(),
+            keep_dropped: 10,
+            ..WordFilter::default()
+        };
+        let (words, report) = filter.apply(["cab", "ñac", "cad", "c4b"].map(String::from).to_vec());
+        assert_eq!(words, ["cab", "ñac"]);
+        let reasons: Vec<DropReason> = report
+            .dropped_lines
+            .iter()
+            .map(|line| line.reason)
+            .collect();
+        assert_eq!(reasons, [DropReason::OutsideAlphabet, DropReason::Digit]);
+
+        for (word, allowed) in [("cab", true), ("ñac", true), ("cad", false)] {
+            assert_eq!(
+                Template::parse(word, &alphabet).is_ok(),
+                allowed,
+                "{}",
+                word
+            );
+            assert_eq!(parse_prefix(word, &alphabet).is_ok(), allowed, "{}", word);
+            let wb = WordBox::new(3, 3, false).with_alphabet(&alphabet);
+            assert_eq!(wb.add_word(word.to_string()).is_ok(), allowed, "{}", word);
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use crate::lexicon::{
+    BuildLexicon, HashMapLexicon, Lexicon, LexiconKind, LexiconOptions, VecLexicon,
+};
+use crate::solver::solve_word_box;
+use crate::wordbox::WordBox;
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+/// Measurements for one lexicon implementation on one word list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchResult {
+    pub kind: LexiconKind,
+    pub dynamic: bool, // queried through a `DynLexicon` rather than its own type
+    pub build_time: Duration,
+    pub memory: usize,
+    pub solve_time: Duration,
+    pub seeds_tried: usize,
+    pub solutions: usize,
+    pub timed_out: bool,
+}
+
+/// Build a lexicon of type `L` and solve symmetric boxes from up to `seeds` seed words,
+/// giving up on the remaining seeds once `timeout` has elapsed
+pub fn bench_lexicon<L: BuildLexicon>(
+    kind: LexiconKind,
+    words: &[String],
+    row_dim: usize,
+    col_dim: usize,
+    seeds: usize,
+    timeout: Duration,
+) -> BenchResult {
+    let lengths = BTreeSet::from([row_dim, col_dim]);
+    let build = || L::initialize(words.to_vec(), lengths);
+    bench_built(kind, false, build, row_dim, col_dim, seeds, timeout)
+}
+
+/// Like `bench_lexicon`, building the lexicon with `LexiconKind::build` and solving through
+/// the `DynLexicon` it returns, to weigh dynamic dispatch against the generic searches
+pub fn bench_dyn_lexicon(
+    kind: LexiconKind,
+    words: &[String],
+    row_dim: usize,
+    col_dim: usize,
+    seeds: usize,
+    timeout: Duration,
+) -> BenchResult {
+    let lengths = BTreeSet::from([row_dim, col_dim]);
+    let build = || kind.build(words.to_vec(), lengths, &LexiconOptions::default());
+    bench_built(kind, true, build, row_dim, col_dim, seeds, timeout)
+}
+
+fn bench_built<L: Lexicon>(
+    kind: LexiconKind,
+    dynamic: bool,
+    build: impl FnOnce() -> L,
+    row_dim: usize,
+    col_dim: usize,
+    seeds: usize,
+    timeout: Duration,
+) -> BenchResult {
+    let start = Instant::now();
+    let lexicon = build();
+    let build_time = start.elapsed();
+
+    let start = Instant::now();
+    let mut seeds_tried = 0;
+    let mut solutions = 0;
+    let mut timed_out = false;
+    for seed in lexicon
+        .words_with_prefix("", col_dim)
+        .into_iter()
+        .take(seeds)
+    {
+        if start.elapsed() > timeout {
+            timed_out = true;
+            break;
+        }
+        seeds_tried += 1;
+        let Ok(wb) = WordBox::new(row_dim, col_dim, row_dim == col_dim).add_word(seed) else {
+            continue;
+        };
+        if solve_word_box(wb, &lexicon).is_some() {
+            solutions += 1;
+        }
+    }
+
+    BenchResult {
+        kind,
+        dynamic,
+        build_time,
+        memory: lexicon.memory_estimate(),
+        solve_time: start.elapsed(),
+        seeds_tried,
+        solutions,
+        timed_out,
+    }
+}
+
+/// Run `bench_lexicon` and then `bench_dyn_lexicon` for every available lexicon
+/// implementation
+pub fn bench_all(
+    words: &[String],
+    row_dim: usize,
+    col_dim: usize,
+    seeds: usize,
+    timeout: Duration,
+) -> Vec<BenchResult> {
+    LexiconKind::ALL
+        .into_iter()
+        .flat_map(|kind| {
+            let generic = match kind {
+                LexiconKind::Vec => {
+                    bench_lexicon::<VecLexicon>(kind, words, row_dim, col_dim, seeds, timeout)
+                }
+                LexiconKind::HashMap => {
+                    bench_lexicon::<HashMapLexicon>(kind, words, row_dim, col_dim, seeds, timeout)
+                }
+            };
+            let dynamic = bench_dyn_lexicon(kind, words, row_dim, col_dim, seeds, timeout);
+            [generic, dynamic]
+        })
+        .collect()
+}
+
+/// Render results as an aligned table
+pub struct BenchTable<'a>(pub &'a [BenchResult]);
+
+impl Display for BenchTable<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:<14} {:>12} {:>12} {:>12} {:>8} {:>10}",
+            "lexicon", "build", "memory", "solve", "seeds", "solutions"
+        )?;
+        for result in self.0 {
+            let solve = format!("{:.2?}", result.solve_time);
+            writeln!(
+                f,
+                "{:<14} {:>12} {:>10}KB {:>12} {:>8} {:>10}{}",
+                match result.dynamic {
+                    true => format!("{} (dyn)", result.kind),
+                    false => result.kind.to_string(),
+                },
+                format!("{:.2?}", result.build_time),
+                result.memory / 1024,
+                solve,
+                result.seeds_tried,
+                result.solutions,
+                if result.timed_out { " (timed out)" } else { "" }
+            )?;
+        }
+        Ok(())
+    }
+}
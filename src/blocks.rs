@@ -0,0 +1,337 @@
+use crate::lexicon::{Lexicon, WordLengths, WILDCARD};
+use crate::masks::LetterMask;
+use std::collections::BTreeSet;
+use std::fmt::Display;
+
+/// Marks a blocked cell in templates, rows and columns; it holds no letter and splits its
+/// row and column into separate words
+pub const BLOCK: char = '#';
+
+/// Shortest run of open cells the pattern generator leaves
+const MIN_GENERATED_RUN: usize = 3;
+
+/// Which cells of a grid are blocked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockMask {
+    row_dim: usize,
+    col_dim: usize,
+    blocked: Vec<bool>, // row-major
+}
+
+impl BlockMask {
+    pub fn new(row_dim: usize, col_dim: usize, blocked: Vec<bool>) -> Self {
+        assert_eq!(
+            blocked.len(),
+            row_dim * col_dim,
+            "mask does not fit the grid"
+        );
+        BlockMask {
+            row_dim,
+            col_dim,
+            blocked,
+        }
+    }
+
+    pub fn is_blocked(&self, row: usize, col: usize) -> bool {
+        self.blocked[row * self.col_dim + col]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.blocked.contains(&true)
+    }
+
+    /// Blocked cells whose partner under a half turn of the grid is open
+    pub fn unsymmetric_cells(&self) -> Vec<(usize, usize)> {
+        (0..self.row_dim)
+            .flat_map(|i| (0..self.col_dim).map(move |j| (i, j)))
+            .filter(|&(i, j)| {
+                self.is_blocked(i, j)
+                    && !self.is_blocked(self.row_dim - 1 - i, self.col_dim - 1 - j)
+            })
+            .collect()
+    }
+
+    /// Whether the pattern looks the same after a half turn, as in standard American crosswords
+    pub fn is_rotationally_symmetric(&self) -> bool {
+        self.unsymmetric_cells().is_empty()
+    }
+
+    /// Whether some row or column has a run of open cells shorter than `min_len`
+    pub fn has_short_runs(&self, min_len: usize) -> bool {
+        self.lines()
+            .any(|line| has_short_run(line.into_iter(), min_len))
+    }
+
+    /// Up to `limit` rotationally symmetric patterns with exactly `blocks` blocked cells and
+    /// no runs of fewer than three open cells
+    pub fn symmetric_patterns(
+        row_dim: usize,
+        col_dim: usize,
+        blocks: usize,
+        limit: usize,
+    ) -> Vec<BlockMask> {
+        let mut patterns = vec![];
+        let mut mask = BlockMask::new(row_dim, col_dim, vec![false; row_dim * col_dim]);
+        if limit > 0 && row_dim * col_dim > 0 {
+            mask.extend_symmetric(0, blocks, limit, &mut patterns);
+        }
+        patterns
+    }
+
+    /// Decide `cell` and its partner, then the cells after it, for every pattern of the first
+    /// half of the grid in reading order
+    fn extend_symmetric(
+        &mut self,
+        cell: usize,
+        remaining: usize,
+        limit: usize,
+        patterns: &mut Vec<BlockMask>,
+    ) {
+        let cells = self.blocked.len();
+        let half = cells.div_ceil(2);
+        if cell == half {
+            if remaining == 0 && !self.has_short_runs(MIN_GENERATED_RUN) {
+                patterns.push(self.clone());
+            }
+            return;
+        }
+        // Every pair left can take two blocks, except the centre cell of an odd grid
+        if remaining > 2 * (half - cell) - cells % 2 {
+            return;
+        }
+
+        let partner = cells - 1 - cell;
+        let cost = if partner == cell { 1 } else { 2 };
+        for block in [false, true] {
+            if patterns.len() >= limit || (block && cost > remaining) {
+                break;
+            }
+            self.blocked[cell] = block;
+            self.blocked[partner] = block;
+            if self.is_consistent_through(cell) {
+                let remaining = if block { remaining - cost } else { remaining };
+                self.extend_symmetric(cell + 1, remaining, limit, patterns);
+            }
+        }
+        self.blocked[cell] = false;
+        self.blocked[partner] = false;
+    }
+
+    /// Whether nothing decided so far, up to and including `cell`, already forces a short run:
+    /// checked once a whole row (and so its partner) is decided, together with every run closed
+    /// off within the columns above it
+    fn is_consistent_through(&self, cell: usize) -> bool {
+        if cell % self.col_dim != self.col_dim - 1 {
+            return true;
+        }
+        let row = cell / self.col_dim;
+        let row_line = |i: usize| (0..self.col_dim).map(move |j| self.is_blocked(i, j));
+        if has_short_run(row_line(row), MIN_GENERATED_RUN)
+            || has_short_run(row_line(self.row_dim - 1 - row), MIN_GENERATED_RUN)
+        {
+            return false;
+        }
+        (0..self.col_dim).all(|j| {
+            // Only runs ended by a block are final; the last one may still grow downwards
+            let mut column: Vec<bool> = (0..=row).map(|i| self.is_blocked(i, j)).collect();
+            match column.iter().rposition(|&blocked| blocked) {
+                Some(last) => column.truncate(last + 1),
+                None => column.clear(),
+            }
+            !has_short_run(column.into_iter(), MIN_GENERATED_RUN)
+        })
+    }
+
+    /// Every row and column as a line of cells, true where blocked, the rows first
+    fn lines(&self) -> impl Iterator<Item = Vec<bool>> + '_ {
+        let rows =
+            (0..self.row_dim).map(|i| (0..self.col_dim).map(|j| self.is_blocked(i, j)).collect());
+        let cols =
+            (0..self.col_dim).map(|j| (0..self.row_dim).map(|i| self.is_blocked(i, j)).collect());
+        rows.chain(cols)
+    }
+
+    /// The lengths of every run of open cells in the rows, and in the columns
+    pub fn word_lengths(&self) -> WordLengths {
+        let runs = |lines: &[Vec<bool>]| -> BTreeSet<usize> {
+            (lines.iter())
+                .flat_map(|line| line.split(|&blocked| blocked).map(<[bool]>::len))
+                .filter(|&len| len > 0)
+                .collect()
+        };
+        let lines: Vec<Vec<bool>> = self.lines().collect();
+        let (rows, cols) = lines.split_at(self.row_dim);
+        WordLengths {
+            rows: runs(rows),
+            cols: runs(cols),
+        }
+    }
+
+    /// Open cells that are a run of their own across or down, i.e. boxed in on both sides
+    /// in their row or in their column. The columns of a grid one row tall, and the rows of one
+    /// a column wide, are single cells by the grid's shape rather than its blocks, and do not count.
+    pub fn single_letter_runs(&self) -> Vec<(usize, usize)> {
+        let open = |i: isize, j: isize| {
+            (0..self.row_dim as isize).contains(&i)
+                && (0..self.col_dim as isize).contains(&j)
+                && !self.is_blocked(i as usize, j as usize)
+        };
+        (0..self.row_dim)
+            .flat_map(|i| (0..self.col_dim).map(move |j| (i, j)))
+            .filter(|&(i, j)| {
+                let (i, j) = (i as isize, j as isize);
+                open(i, j)
+                    && ((self.col_dim > 1 && !open(i, j - 1) && !open(i, j + 1))
+                        || (self.row_dim > 1 && !open(i - 1, j) && !open(i + 1, j)))
+            })
+            .collect()
+    }
+}
+
+/// Whether a line of cells (true where blocked) has a run of open cells shorter than `min_len`
+fn has_short_run(line: impl Iterator<Item = bool>, min_len: usize) -> bool {
+    let mut run = 0;
+    for blocked in line.chain(std::iter::once(true)) {
+        if !blocked {
+            run += 1;
+            continue;
+        }
+        if run > 0 && run < min_len {
+            return true;
+        }
+        run = 0;
+    }
+    false
+}
+
+impl Display for BlockMask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for i in 0..self.row_dim {
+            for j in 0..self.col_dim {
+                write!(f, "{}", if self.is_blocked(i, j) { BLOCK } else { '.' })?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// The runs of a line of cells (letters, `?` for unknown ones and blocks), each trimmed to
+/// a prefix query and paired with its length. A line without blocks is a single run.
+pub(crate) fn runs(line: &str) -> impl Iterator<Item = (&str, usize)> {
+    line.split(BLOCK)
+        .filter(|run| !run.is_empty())
+        .map(|run| (run.trim_end_matches(WILDCARD), run.chars().count()))
+}
+
+/// Whether every run of `line` can still be completed to a word
+pub(crate) fn is_feasible<L: Lexicon>(line: &str, lexicon: &L) -> bool {
+    runs(line).all(|(pattern, len)| lexicon.has_prefix(pattern, len))
+}
+
+/// Whether every run of `line` can still be completed to a word whose letters in the open
+/// cells are ones their `mask`, looked up by the cell's position in the line, allows
+pub(crate) fn is_feasible_masked<'a, L: Lexicon>(
+    line: &str,
+    mask: impl Fn(usize) -> Option<&'a LetterMask>,
+    lexicon: &L,
+) -> bool {
+    let mut start = 0;
+    for run in line.split(BLOCK) {
+        let len = run.chars().count();
+        let cells = start..start + len;
+        start += len + 1;
+        if len == 0 {
+            continue;
+        }
+        let masks: Vec<Option<&LetterMask>> = (run.chars().zip(cells))
+            .map(|(ch, cell)| (ch == WILDCARD).then(|| mask(cell)).flatten())
+            .collect();
+        let pattern = run.trim_end_matches(WILDCARD);
+        let feasible = match masks.iter().all(Option::is_none) {
+            true => lexicon.has_prefix(pattern, len),
+            false => lexicon.words_with_prefix(pattern, len).iter().any(|word| {
+                (word.chars().zip(&masks)).all(|(ch, mask)| mask.is_none_or(|m| m.allows(ch)))
+            }),
+        };
+        if !feasible {
+            return false;
+        }
+    }
+    true
+}
+
+/// Product over the runs of `line` of the number of words completing each
+pub(crate) fn completions<L: Lexicon>(line: &str, lexicon: &L) -> f64 {
+    runs(line)
+        .map(|(pattern, len)| lexicon.count_with_prefix(pattern, len) as f64)
+        .product()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A mask drawn as `Display` draws it, rows separated by spaces
+    fn mask(rows: &str) -> BlockMask {
+        let rows: Vec<&str> = rows.split(' ').collect();
+        let blocked = rows
+            .iter()
+            .flat_map(|row| row.chars().map(|ch| ch == BLOCK));
+        BlockMask::new(rows.len(), rows[0].len(), blocked.collect())
+    }
+
+    #[test]
+    fn published_patterns_are_symmetric() {
+        let patterns = [
+            // The usual shapes of a 5x5 mini
+            "#.... ..... ..... ..... ....#",
+            "##... #.... ..... ....# ...##",
+            // A 7x7 midi
+            "...#... ...#... ....... ##...## ....... ...#... ...#...",
+            "#...### #...... ....... ...#... ....... ......# ###...#",
+        ];
+        for pattern in patterns {
+            let mask = mask(pattern);
+            assert!(mask.is_rotationally_symmetric(), "{}", mask);
+            assert!(!mask.has_short_runs(3), "{}", mask);
+        }
+    }
+
+    #[test]
+    fn unsymmetric_cells_are_the_blocks_without_a_partner() {
+        let mask = mask("#.... ..... ..#.. ..... .....");
+        assert!(!mask.is_rotationally_symmetric());
+        assert_eq!(mask.unsymmetric_cells(), [(0, 0)]);
+        let mask = self::mask("##... #.... ..... ..... ...##");
+        assert_eq!(mask.unsymmetric_cells(), [(1, 0)]);
+    }
+
+    #[test]
+    fn generated_patterns_are_symmetric_without_short_runs() {
+        let patterns = BlockMask::symmetric_patterns(5, 5, 2, usize::MAX);
+        assert!(patterns.contains(&mask("#.... ..... ..... ..... ....#")));
+        assert!(patterns.contains(&mask("....# ..... ..... ..... #....")));
+        for pattern in &patterns {
+            assert!(pattern.is_rotationally_symmetric(), "{}", pattern);
+            assert!(!pattern.has_short_runs(3), "{}", pattern);
+            assert_eq!(pattern.blocked.iter().filter(|&&b| b).count(), 2);
+        }
+        let odd = BlockMask::symmetric_patterns(7, 7, 8, usize::MAX);
+        assert!(odd.contains(&mask(
+            "...#... ...#... ....... ##...## ....... ...#... ...#..."
+        )));
+        assert_eq!(BlockMask::symmetric_patterns(5, 5, 2, 1).len(), 1);
+        assert!(BlockMask::symmetric_patterns(3, 3, 1, usize::MAX).is_empty());
+    }
+
+    #[test]
+    fn the_cells_of_a_single_row_or_column_are_not_single_letter_runs() {
+        assert!(mask(".....").single_letter_runs().is_empty());
+        assert!(mask(". . .").single_letter_runs().is_empty());
+        assert!(mask(".").single_letter_runs().is_empty());
+        // A block still cuts a run of its own
+        assert_eq!(mask("..#.#").single_letter_runs(), [(0, 3)]);
+        assert_eq!(mask(". # .").single_letter_runs(), [(0, 0), (2, 0)]);
+    }
+}
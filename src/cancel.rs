@@ -0,0 +1,42 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A handle for stopping a search from another thread. Clones share the same flag, so one can
+/// be kept by whoever may cancel and another handed to the solver (see `SolverConfig::cancellation`).
+///
+/// The solver checks the flag before expanding each node. That is a single relaxed load (one per
+/// ancestor for child tokens), which costs next to nothing beside the lexicon queries of an
+/// expansion, so checking is not batched.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    parent: Option<Arc<CancellationToken>>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A token that is cancelled along with this one, but can also be cancelled on its own
+    /// without affecting this one
+    pub fn child(&self) -> CancellationToken {
+        CancellationToken {
+            cancelled: Arc::default(),
+            parent: Some(Arc::new(self.clone())),
+        }
+    }
+
+    /// Ask every search holding this token, or a child of it, to stop at its next node
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+            || self
+                .parent
+                .as_ref()
+                .is_some_and(|parent| parent.is_cancelled())
+    }
+}
@@ -0,0 +1,528 @@
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// The first line of a chunk index, which tells it from a solution file
+pub const INDEX_TAG: &str = "# wordbox chunk index";
+
+/// How the chunks of a chunked output are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    /// As plain text
+    #[default]
+    None,
+    /// Through gzip, with `.gz` added to their names (needs the `gzip` feature)
+    Gzip,
+}
+
+impl Compression {
+    pub const ALL: [Compression; 2] = [Compression::None, Compression::Gzip];
+
+    /// What the name of a chunk written this way ends in
+    fn suffix(self) -> &'static str {
+        match self {
+            Compression::None => "",
+            Compression::Gzip => ".gz",
+        }
+    }
+}
+
+impl Display for Compression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Compression::None => write!(f, "none"),
+            Compression::Gzip => write!(f, "gzip"),
+        }
+    }
+}
+
+impl std::str::FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Compression::ALL
+            .into_iter()
+            .find(|compression| compression.to_string() == s)
+            .ok_or_else(|| {
+                let names: Vec<String> = Compression::ALL.iter().map(|c| c.to_string()).collect();
+                format!(
+                    "unknown compression {:?} (expected one of {})",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// A chunk as its index lists it: its file, beside the index, and the solutions it holds,
+/// numbered from 1 across all the chunks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkEntry {
+    pub name: String,
+    pub first: usize,
+    pub solutions: usize,
+}
+
+impl ChunkEntry {
+    /// The number of the chunk's last solution; one less than `first` for a chunk holding none
+    pub fn last(&self) -> usize {
+        self.first + self.solutions - 1
+    }
+}
+
+impl Display for ChunkEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.name,
+            self.first,
+            self.last(),
+            self.solutions
+        )
+    }
+}
+
+/// Whether a file's contents are a chunk index
+pub fn is_index(bytes: &[u8]) -> bool {
+    bytes.starts_with(INDEX_TAG.as_bytes())
+}
+
+/// The chunks a chunk index lists, in order
+pub fn read_index(text: &str) -> Result<Vec<ChunkEntry>, String> {
+    let mut lines = text.lines().enumerate();
+    if lines.next().map(|(_, line)| line) != Some(INDEX_TAG) {
+        return Err(format!(
+            "not a chunk index: it does not start with {:?}",
+            INDEX_TAG
+        ));
+    }
+    let mut chunks = vec![];
+    for (i, line) in lines {
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let numbers: Option<Vec<usize>> = (fields.get(1..))
+            .map(|numbers| numbers.iter().map(|n| n.parse().ok()).collect())
+            .unwrap_or_default();
+        let entry = match (fields.first(), numbers.as_deref()) {
+            (Some(name), Some(&[first, last, solutions])) if first + solutions == last + 1 => {
+                ChunkEntry {
+                    name: name.to_string(),
+                    first,
+                    solutions,
+                }
+            }
+            _ => {
+                return Err(format!(
+                "line {}: expected a chunk's name, first and last solutions and count, found {:?}",
+                i + 1,
+                line
+            ))
+            }
+        };
+        chunks.push(entry);
+    }
+    Ok(chunks)
+}
+
+/// The contents of a solution file, uncompressed if its name ends in `.gz`
+pub fn read_chunk(path: &Path) -> io::Result<Vec<u8>> {
+    let bytes = std::fs::read(path)?;
+    if path.extension().is_none_or(|ext| ext != "gz") {
+        return Ok(bytes);
+    }
+    #[cfg(feature = "gzip")]
+    {
+        use std::io::Read;
+        let mut text = vec![];
+        flate2::read::MultiGzDecoder::new(bytes.as_slice()).read_to_end(&mut text)?;
+        Ok(text)
+    }
+    #[cfg(not(feature = "gzip"))]
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "gzip files need the gzip feature",
+    ))
+}
+
+/// The file of the chunk being written
+enum Chunk {
+    Plain(BufWriter<File>),
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzEncoder<BufWriter<File>>),
+}
+
+impl Chunk {
+    fn create(path: &Path, compression: Compression) -> io::Result<Chunk> {
+        let file = BufWriter::new(File::create(path)?);
+        match compression {
+            Compression::None => Ok(Chunk::Plain(file)),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => Ok(Chunk::Gzip(flate2::write::GzEncoder::new(
+                file,
+                flate2::Compression::default(),
+            ))),
+            #[cfg(not(feature = "gzip"))]
+            Compression::Gzip => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "gzip chunks need the gzip feature",
+            )),
+        }
+    }
+
+    fn write_all(&mut self, bytes: &[u8]) -> io::Result<()> {
+        match self {
+            Chunk::Plain(file) => file.write_all(bytes),
+            #[cfg(feature = "gzip")]
+            Chunk::Gzip(encoder) => encoder.write_all(bytes),
+        }
+    }
+
+    /// Write out the rest of the chunk, with the gzip trailer if it has one
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Chunk::Plain(mut file) => file.flush(),
+            #[cfg(feature = "gzip")]
+            Chunk::Gzip(encoder) => encoder.finish()?.flush(),
+        }
+    }
+}
+
+/// Solutions written to a new file every `chunk_size` of them, beside an index listing the
+/// files and the solutions each holds. A chunk for `sols.txt` is `sols-0001.txt`, and so on,
+/// with `.gz` added when compressed; the index takes the name itself.
+///
+/// What is written is held until `end_record` says a solution is complete and then goes to the
+/// chunk whole, so a chunk only ever ends between records, however the run ends. The index is
+/// rewritten as each chunk is closed, and the chunk being written is closed when the output is
+/// finished or dropped.
+pub struct ChunkedOutput {
+    index: PathBuf,
+    chunk_size: usize,
+    compression: Compression,
+    chunks: Vec<ChunkEntry>, // the chunks written, the one being written last
+    current: Option<Chunk>,
+    pending: Vec<u8>, // the record being written
+}
+
+impl ChunkedOutput {
+    /// Chunks listed in a new index at `index`, or after those it lists already if `append`
+    pub fn create(
+        index: &Path,
+        chunk_size: usize,
+        compression: Compression,
+        append: bool,
+    ) -> io::Result<Self> {
+        let chunks = match append {
+            true => read_index(&std::fs::read_to_string(index)?)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?,
+            false => vec![],
+        };
+        let output = ChunkedOutput {
+            index: index.to_path_buf(),
+            chunk_size: chunk_size.max(1),
+            compression,
+            chunks,
+            current: None,
+            pending: vec![],
+        };
+        output.write_index()?;
+        Ok(output)
+    }
+
+    /// The chunks written so far
+    pub fn chunks(&self) -> &[ChunkEntry] {
+        &self.chunks
+    }
+
+    /// The file name of chunk `number`, counting from 1
+    fn chunk_name(&self, number: usize) -> String {
+        let path = Path::new(self.index.file_name().unwrap_or_default());
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let ext = match path.extension() {
+            Some(ext) => format!(".{}", ext.to_string_lossy()),
+            None => String::new(),
+        };
+        format!("{}-{:04}{}{}", stem, number, ext, self.compression.suffix())
+    }
+
+    fn chunk_path(&self, name: &str) -> PathBuf {
+        self.index.with_file_name(name)
+    }
+
+    /// The solution written since the last call is complete: add it to the chunk being
+    /// written, starting one if need be, and close the chunk once it holds `chunk_size`
+    pub fn end_record(&mut self) -> io::Result<()> {
+        self.write_pending()?;
+        let chunk = self.chunks.last_mut().expect("a chunk is open");
+        chunk.solutions += 1;
+        if chunk.solutions >= self.chunk_size {
+            self.close_chunk()?;
+        }
+        Ok(())
+    }
+
+    /// Write what is pending to the chunk being written, starting one if need be
+    fn write_pending(&mut self) -> io::Result<()> {
+        if self.current.is_none() {
+            let first = self.chunks.last().map_or(1, |chunk| chunk.last() + 1);
+            let name = self.chunk_name(self.chunks.len() + 1);
+            self.current = Some(Chunk::create(&self.chunk_path(&name), self.compression)?);
+            self.chunks.push(ChunkEntry {
+                name,
+                first,
+                solutions: 0,
+            });
+        }
+        let current = self.current.as_mut().expect("a chunk is open");
+        current.write_all(&self.pending)?;
+        self.pending.clear();
+        Ok(())
+    }
+
+    fn close_chunk(&mut self) -> io::Result<()> {
+        if let Some(chunk) = self.current.take() {
+            chunk.finish()?;
+        }
+        self.write_index()
+    }
+
+    /// Close the chunk being written, with anything written after the last solution, such as
+    /// a closing note, and bring the index up to date
+    pub fn finish(mut self) -> io::Result<()> {
+        self.close()
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        if !self.pending.is_empty() {
+            self.write_pending()?;
+        }
+        self.close_chunk()
+    }
+
+    /// Rewrite the index beside itself and rename it into place, so a reader never sees it
+    /// half written
+    fn write_index(&self) -> io::Result<()> {
+        let mut text = format!("{}\n# chunk first last solutions\n", INDEX_TAG);
+        for chunk in &self.chunks {
+            text += &format!("{}\n", chunk);
+        }
+        let mut temp = self.index.clone().into_os_string();
+        temp.push(".tmp");
+        std::fs::write(&temp, text)?;
+        std::fs::rename(&temp, &self.index)
+    }
+}
+
+impl Write for ChunkedOutput {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    /// Records only go to their chunk as they end (see `end_record`)
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for ChunkedOutput {
+    fn drop(&mut self) {
+        if self.current.is_some() || !self.pending.is_empty() {
+            self.close().ok();
+        }
+    }
+}
+
+/// Where `--output` goes: one file, or chunks listed in an index
+pub enum SolutionOutput {
+    File(BufWriter<File>),
+    Chunked(ChunkedOutput),
+}
+
+impl SolutionOutput {
+    /// A solution has been written in full (see `ChunkedOutput::end_record`)
+    pub fn end_record(&mut self) -> io::Result<()> {
+        match self {
+            SolutionOutput::File(_) => Ok(()),
+            SolutionOutput::Chunked(chunks) => chunks.end_record(),
+        }
+    }
+
+    /// Write out everything, closing the last chunk
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            SolutionOutput::File(mut file) => file.flush(),
+            SolutionOutput::Chunked(chunks) => chunks.finish(),
+        }
+    }
+}
+
+impl Write for SolutionOutput {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        match self {
+            SolutionOutput::File(file) => file.write(bytes),
+            SolutionOutput::Chunked(chunks) => chunks.write(bytes),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            SolutionOutput::File(file) => file.flush(),
+            SolutionOutput::Chunked(chunks) => chunks.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("wordbox-chunks-{}-{}", name, std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_records(output: &mut ChunkedOutput, records: std::ops::RangeInclusive<usize>) {
+        for n in records {
+            writeln!(output, "solution {}", n).unwrap();
+            output.end_record().unwrap();
+        }
+    }
+
+    fn entry(name: &str, first: usize, solutions: usize) -> ChunkEntry {
+        ChunkEntry {
+            name: name.to_string(),
+            first,
+            solutions,
+        }
+    }
+
+    #[test]
+    fn a_full_chunk_rolls_over_to_the_next() {
+        let dir = temp_dir("rollover");
+        let index = dir.join("sols.txt");
+        let mut output = ChunkedOutput::create(&index, 3, Compression::None, false).unwrap();
+        write_records(&mut output, 1..=7);
+        output.finish().unwrap();
+
+        let chunks = read_index(&std::fs::read_to_string(&index).unwrap()).unwrap();
+        assert_eq!(
+            chunks,
+            vec![
+                entry("sols-0001.txt", 1, 3),
+                entry("sols-0002.txt", 4, 3),
+                entry("sols-0003.txt", 7, 1),
+            ]
+        );
+        assert_eq!(
+            std::fs::read_to_string(&index).unwrap(),
+            "# wordbox chunk index\n# chunk first last solutions\n\
+             sols-0001.txt 1 3 3\nsols-0002.txt 4 6 3\nsols-0003.txt 7 7 1\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("sols-0002.txt")).unwrap(),
+            "solution 4\nsolution 5\nsolution 6\n"
+        );
+        assert!(!dir.join("sols-0004.txt").exists());
+        assert!(!dir.join("sols.txt.tmp").exists());
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn a_dropped_output_closes_its_chunk_and_index() {
+        let dir = temp_dir("dropped");
+        let index = dir.join("sols.txt");
+        let mut output = ChunkedOutput::create(&index, 2, Compression::None, false).unwrap();
+        write_records(&mut output, 1..=3);
+        writeln!(output, "# stopped early").unwrap();
+        drop(output);
+
+        let chunks = read_index(&std::fs::read_to_string(&index).unwrap()).unwrap();
+        assert_eq!(
+            chunks,
+            vec![entry("sols-0001.txt", 1, 2), entry("sols-0002.txt", 3, 1)]
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("sols-0002.txt")).unwrap(),
+            "solution 3\n# stopped early\n"
+        );
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn appending_carries_on_the_numbering() {
+        let dir = temp_dir("append");
+        let index = dir.join("sols");
+        let mut output = ChunkedOutput::create(&index, 2, Compression::None, false).unwrap();
+        write_records(&mut output, 1..=3);
+        output.finish().unwrap();
+        let mut output = ChunkedOutput::create(&index, 2, Compression::None, true).unwrap();
+        write_records(&mut output, 4..=5);
+        output.finish().unwrap();
+
+        let chunks = read_index(&std::fs::read_to_string(&index).unwrap()).unwrap();
+        assert_eq!(
+            chunks,
+            vec![
+                entry("sols-0001", 1, 2),
+                entry("sols-0002", 3, 1),
+                entry("sols-0003", 4, 2),
+            ]
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("sols-0003")).unwrap(),
+            "solution 4\nsolution 5\n"
+        );
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn indexes_that_do_not_add_up_are_refused() {
+        let text = |lines: &str| format!("{}\n{}", INDEX_TAG, lines);
+        assert_eq!(read_index(&text("")), Ok(vec![]));
+        assert_eq!(
+            read_index(&text("# comment\n\na 1 2 2\n")),
+            Ok(vec![entry("a", 1, 2)])
+        );
+        assert!(read_index("a 1 2 2\n")
+            .unwrap_err()
+            .starts_with("not a chunk index"));
+        assert!(read_index(&text("a 1 3 2\n"))
+            .unwrap_err()
+            .starts_with("line 2:"));
+        assert!(read_index(&text("a 1 2\n")).is_err());
+        assert!(read_index(&text("a one 2 2\n")).is_err());
+        assert!(is_index(text("").as_bytes()));
+        assert!(!is_index(b"cat\nace\ntea\n"));
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn compressed_chunks_read_back_as_written() {
+        let dir = temp_dir("gzip");
+        let index = dir.join("sols.txt");
+        let mut output = ChunkedOutput::create(&index, 2, Compression::Gzip, false).unwrap();
+        write_records(&mut output, 1..=3);
+        output.finish().unwrap();
+
+        let chunks = read_index(&std::fs::read_to_string(&index).unwrap()).unwrap();
+        assert_eq!(
+            chunks,
+            vec![
+                entry("sols-0001.txt.gz", 1, 2),
+                entry("sols-0002.txt.gz", 3, 1)
+            ]
+        );
+        let first = dir.join("sols-0001.txt.gz");
+        assert_ne!(std::fs::read(&first).unwrap(), b"solution 1\nsolution 2\n");
+        assert_eq!(read_chunk(&first).unwrap(), b"solution 1\nsolution 2\n");
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,208 @@
+use crate::lexicon::Lexicon;
+use std::collections::HashSet;
+use std::fmt::Display;
+
+/// A k×k×k cube whose every straight line is a word: the rows of each layer, the columns of
+/// each layer, and the pillars running through the layers.
+///
+/// Only row words are placed, layer by layer and top to bottom within a layer; the columns and
+/// pillars are read off them. Placing a row fixes one letter of each column in its layer and one
+/// letter of each pillar below it, so those are the prefixes a move has to keep completable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordCube {
+    pub(crate) dim: usize,
+    pub(crate) rows: Vec<String>, // the row words in the order they were placed
+}
+
+impl Display for WordCube {
+    /// Each layer as a box of rows, with a blank line between layers and `_` for open cells
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (layer, rows) in self.layers().enumerate() {
+            if layer > 0 {
+                writeln!(f)?;
+            }
+            for row in rows {
+                match row {
+                    Some(word) => writeln!(f, "{}", word)?,
+                    None => writeln!(f, "{}", "_".repeat(self.dim))?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl WordCube {
+    pub fn new(dim: usize) -> Self {
+        WordCube { dim, rows: vec![] }
+    }
+
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Whether every row of every layer has been filled in
+    pub fn is_done(&self) -> bool {
+        self.rows.len() == self.dim * self.dim
+    }
+
+    /// The rows of each layer, from the first layer to the last, `None` for rows not placed yet
+    pub fn layers(&self) -> impl Iterator<Item = Vec<Option<&str>>> + '_ {
+        (0..self.dim).map(move |layer| {
+            (0..self.dim)
+                .map(|row| self.rows.get(layer * self.dim + row).map(String::as_str))
+                .collect()
+        })
+    }
+
+    /// The letter in `col` of the row word at `index` in placement order
+    fn letter(&self, index: usize, col: usize) -> char {
+        let row = &self.rows[index];
+        if row.is_ascii() {
+            row.as_bytes()[col] as char
+        } else {
+            row.chars().nth(col).unwrap()
+        }
+    }
+
+    /// The letters of column `col` of `layer` and of the pillar through (`row`, `col`), taken
+    /// from the rows placed before the one at `layer`, `row`: the two prefixes the next row
+    /// extends in that cell
+    fn crossing_prefixes(&self, layer: usize, row: usize, col: usize) -> (String, String) {
+        let column = (0..row)
+            .map(|i| self.letter(layer * self.dim + i, col))
+            .collect();
+        let pillar = (0..layer)
+            .map(|i| self.letter(i * self.dim + row, col))
+            .collect();
+        (column, pillar)
+    }
+
+    /// The layer and row the next word is placed in
+    fn next_slot(&self) -> (usize, usize) {
+        (self.rows.len() / self.dim, self.rows.len() % self.dim)
+    }
+
+    /// Whether placing `word` as the next row keeps every column of its layer and every
+    /// pillar through it completable
+    pub(crate) fn is_valid_move<L: Lexicon>(&self, word: &str, lexicon: &L) -> bool {
+        let (layer, row) = self.next_slot();
+        word.chars().enumerate().all(|(col, ch)| {
+            let (mut column, mut pillar) = self.crossing_prefixes(layer, row, col);
+            column.push(ch);
+            pillar.push(ch);
+            lexicon.has_prefix(&column, self.dim) && lexicon.has_prefix(&pillar, self.dim)
+        })
+    }
+
+    /// For each cell of the next row, the letters that keep both its column and its pillar
+    /// completable, or `None` if some cell can take no letter at all
+    pub(crate) fn next_letters<L: Lexicon>(&self, lexicon: &L) -> Option<Vec<HashSet<char>>> {
+        let (layer, row) = self.next_slot();
+        (0..self.dim)
+            .map(|col| {
+                let (column, pillar) = self.crossing_prefixes(layer, row, col);
+                let mut letters = lexicon.letters_after(&column, self.dim);
+                if !letters.is_empty() {
+                    let below = lexicon.letters_after(&pillar, self.dim);
+                    letters.retain(|ch| below.contains(ch));
+                }
+                (!letters.is_empty()).then_some(letters)
+            })
+            .collect()
+    }
+
+    /// Every word that can be placed as the next row, either checked cell by cell against the
+    /// forward-checking letter sets or queried against the lexicon one move at a time
+    pub fn row_candidates<L: Lexicon>(&self, lexicon: &L, forward_checking: bool) -> Vec<String> {
+        let words = lexicon.words_with_prefix("", self.dim);
+        if forward_checking {
+            let Some(letters) = self.next_letters(lexicon) else {
+                return vec![];
+            };
+            words
+                .into_iter()
+                .filter(|word| {
+                    word.chars()
+                        .zip(&letters)
+                        .all(|(ch, set)| set.contains(&ch))
+                })
+                .collect()
+        } else {
+            words
+                .into_iter()
+                .filter(|word| self.is_valid_move(word, lexicon))
+                .collect()
+        }
+    }
+
+    pub fn add_word(&self, word: String) -> WordCube {
+        let mut rows = self.rows.clone();
+        rows.push(word);
+        WordCube {
+            dim: self.dim,
+            rows,
+        }
+    }
+
+    /// Every word complete so far: the rows placed, then the columns of the full layers, then
+    /// the pillars whose bottom row is placed
+    fn words(&self) -> impl Iterator<Item = String> + '_ {
+        let dim = self.dim;
+        let (full_layers, _) = self.next_slot();
+        let columns = (0..full_layers).flat_map(move |layer| {
+            (0..dim).map(move |col| {
+                (0..dim)
+                    .map(|row| self.letter(layer * dim + row, col))
+                    .collect()
+            })
+        });
+        let last_layer = (dim - 1) * dim;
+        let bottom_rows = self.rows.len().saturating_sub(last_layer);
+        let pillars = (0..bottom_rows).flat_map(move |row| {
+            (0..dim).map(move |col| {
+                (0..dim)
+                    .map(|layer| self.letter(layer * dim + row, col))
+                    .collect()
+            })
+        });
+        self.rows.iter().cloned().chain(columns).chain(pillars)
+    }
+
+    /// Whether some word appears twice among the complete rows, columns and pillars
+    pub fn has_repeated_words(&self) -> bool {
+        let mut seen = HashSet::new();
+        !self.words().all(|word| seen.insert(word))
+    }
+
+    /// The cube as a JSON object holding its size and the rows of each layer
+    pub fn to_json(&self) -> String {
+        let layers: Vec<String> = self
+            .layers()
+            .map(|rows| {
+                let rows: Vec<String> = rows
+                    .into_iter()
+                    .map(|row| row.map_or("null".to_string(), json_string))
+                    .collect();
+                format!("[{}]", rows.join(","))
+            })
+            .collect();
+        format!("{{\"dim\":{},\"layers\":[{}]}}", self.dim, layers.join(","))
+    }
+}
+
+/// `s` as a JSON string literal
+pub(crate) fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            ch if ch.is_control() => out.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,107 @@
+use std::fmt::Display;
+
+/// 64-bit FNV-1a, which unlike the standard library's hasher is fixed by its definition, so
+/// seeds and fingerprints stay the same across builds and platforms
+pub fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// A fingerprint of a word list: FNV-1a over its words in order, each followed by a newline.
+/// Two runs that print the same fingerprint searched the same words.
+pub fn fingerprint(words: &[String]) -> u64 {
+    fnv1a((words.iter()).flat_map(|word| word.bytes().chain(std::iter::once(b'\n'))))
+}
+
+/// A splitmix64 step, turning a seed into the next value of a stream everyone can reproduce
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// The day a daily puzzle is for, written `YYYY-MM-DD`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Daily {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl Daily {
+    /// The seed of the day's pick: FNV-1a (see `fnv1a`) of the bytes of `wordbox daily `
+    /// followed by the date as written by `Display`, e.g. `wordbox daily 2025-07-04`
+    pub fn seed(&self) -> u64 {
+        fnv1a(format!("wordbox daily {}", self).into_bytes())
+    }
+
+    /// The index of the day's pick among `count` candidates listed in a fixed order. With
+    /// `scores`, one per candidate, higher-scoring candidates are likelier: the candidate
+    /// ranked r-th from the top (ties keeping the listed order) weighs `count - r`, so the best
+    /// one is `count` times as likely as the worst. The first splitmix64 value after the seed,
+    /// taken modulo the total weight, picks the candidate.
+    pub fn pick(&self, count: usize, scores: Option<&[f64]>) -> Option<usize> {
+        if count == 0 {
+            return None;
+        }
+        let mut state = self.seed();
+        let draw = splitmix64(&mut state);
+        let Some(scores) = scores else {
+            return Some((draw % count as u64) as usize);
+        };
+        let mut ranked: Vec<usize> = (0..count).collect();
+        ranked.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]).then(a.cmp(&b)));
+        let total = (count as u64) * (count as u64 + 1) / 2;
+        let mut ticket = draw % total;
+        for (rank, &index) in ranked.iter().enumerate() {
+            let weight = (count - rank) as u64;
+            if ticket < weight {
+                return Some(index);
+            }
+            ticket -= weight;
+        }
+        ranked.last().copied()
+    }
+}
+
+impl std::str::FromStr for Daily {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("expected a date such as 2025-07-04, found {:?}", s);
+        let fields: Vec<&str> = s.trim().split('-').collect();
+        let [year, month, day] = fields[..] else {
+            return Err(invalid());
+        };
+        if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+            return Err(invalid());
+        }
+        let (Ok(year), Ok(month), Ok(day)) = (year.parse(), month.parse(), day.parse()) else {
+            return Err(invalid());
+        };
+        let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+        let days = match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if leap => 29,
+            2 => 28,
+            _ => return Err(format!("{:?} has no month {}", s, month)),
+        };
+        if !(1..=days).contains(&day) {
+            return Err(format!("{:?} has no day {} in its month", s, day));
+        }
+        Ok(Daily { year, month, day })
+    }
+}
+
+impl Display for Daily {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
@@ -0,0 +1,404 @@
+use crate::runner::FoundSolution;
+use rusqlite::{params, Connection};
+use std::fmt::Display;
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+
+/// Solutions a writer inserts in one transaction, at most
+const BATCH: usize = 1000;
+
+/// The tables of a solution database, created the first time it is opened
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    started_at TEXT NOT NULL DEFAULT (datetime('now')),
+    command TEXT NOT NULL,
+    wordlist TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS solutions (
+    id INTEGER PRIMARY KEY,
+    canonical TEXT NOT NULL UNIQUE,
+    row_dim INTEGER NOT NULL,
+    col_dim INTEGER NOT NULL,
+    symmetric INTEGER NOT NULL,
+    seed TEXT NOT NULL,
+    rows TEXT NOT NULL,
+    cols TEXT NOT NULL,
+    words TEXT NOT NULL,
+    theme_words INTEGER NOT NULL,
+    rare_words INTEGER NOT NULL,
+    scrabble_score INTEGER,
+    score REAL,
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    found_at TEXT NOT NULL DEFAULT (datetime('now')),
+    times_found INTEGER NOT NULL DEFAULT 1
+);
+CREATE INDEX IF NOT EXISTS solutions_by_size ON solutions (row_dim, col_dim);
+CREATE TABLE IF NOT EXISTS provenance (
+    canonical TEXT PRIMARY KEY REFERENCES solutions(canonical),
+    record TEXT NOT NULL,
+    run_id INTEGER NOT NULL REFERENCES runs(id)
+);
+";
+
+/// A box found twice, by this run or an earlier one, is noted as found again by the latest
+/// run; scores the latest run did not compute keep their earlier values
+const UPSERT: &str = "
+INSERT INTO solutions (canonical, row_dim, col_dim, symmetric, seed, rows, cols, words,
+    theme_words, rare_words, scrabble_score, score, run_id)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+ON CONFLICT (canonical) DO UPDATE SET
+    times_found = times_found + 1,
+    run_id = excluded.run_id,
+    found_at = excluded.found_at,
+    scrabble_score = coalesce(excluded.scrabble_score, scrabble_score),
+    score = coalesce(excluded.score, score)
+";
+
+/// The latest run to record where a box came from replaces any earlier record of it
+const UPSERT_PROVENANCE: &str = "
+INSERT INTO provenance (canonical, record, run_id) VALUES (?1, ?2, ?3)
+ON CONFLICT (canonical) DO UPDATE SET record = excluded.record, run_id = excluded.run_id
+";
+
+/// A database that went wrong
+#[derive(Debug)]
+pub enum DbError {
+    Sqlite(rusqlite::Error),
+    /// The writer thread panicked, losing the solutions it had not committed
+    WriterPanicked,
+}
+
+impl Display for DbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DbError::Sqlite(err) => write!(f, "{}", err),
+            DbError::WriterPanicked => write!(f, "the database writer stopped unexpectedly"),
+        }
+    }
+}
+
+impl std::error::Error for DbError {}
+
+impl From<rusqlite::Error> for DbError {
+    fn from(err: rusqlite::Error) -> Self {
+        DbError::Sqlite(err)
+    }
+}
+
+/// A solution as it is stored: its box in canonical form, so that a box and its transpose
+/// are one row, with the words and scores worth querying
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredSolution {
+    pub canonical: String, // the canonical box's rows, joined by `/`
+    pub row_dim: usize,
+    pub col_dim: usize,
+    pub symmetric: bool,
+    pub seed: String,
+    pub rows: String,  // the row words, separated by spaces
+    pub cols: String,  // the column words, separated by spaces
+    pub words: String, // every word of the box, across and down, separated by spaces
+    pub theme_words: usize,
+    pub rare_words: usize,
+    pub scrabble_score: Option<u32>,
+    pub score: Option<f64>, // the run's --score or --rank-by value, if it computed one
+    pub provenance: Option<String>, // the provenance record, if the run wrote one
+}
+
+impl StoredSolution {
+    pub fn new(solution: &FoundSolution, score: Option<f64>) -> Self {
+        let wb = &solution.word_box;
+        StoredSolution {
+            canonical: wb.canonical().row_words().join("/"),
+            row_dim: wb.row_dim,
+            col_dim: wb.col_dim,
+            symmetric: wb.is_symmetric,
+            seed: solution.seed.clone(),
+            rows: wb.row_words().join(" "),
+            cols: wb.column_words().join(" "),
+            words: wb.words().collect::<Vec<_>>().join(" "),
+            theme_words: solution.theme_words.len(),
+            rare_words: solution.rare_words.len(),
+            scrabble_score: solution.scrabble_score,
+            score: score.filter(|score| score.is_finite()),
+            provenance: (solution.provenance.as_ref()).map(|provenance| provenance.to_string()),
+        }
+    }
+}
+
+/// A SQLite database of the solutions of many runs, each solution linked to the run that last
+/// found it. For example, the 5x5 boxes using "quartz" found in the last month:
+///
+/// ```sql
+/// SELECT rows FROM solutions
+/// WHERE row_dim = 5 AND col_dim = 5
+///   AND ' ' || words || ' ' LIKE '% quartz %'
+///   AND found_at >= datetime('now', '-1 month');
+/// ```
+///
+/// the runs that found the most boxes new to the database:
+///
+/// ```sql
+/// SELECT runs.command, count(*) AS found FROM solutions JOIN runs ON runs.id = run_id
+/// WHERE times_found = 1 GROUP BY run_id ORDER BY found DESC;
+/// ```
+///
+/// and the ten highest-scoring symmetric boxes by Scrabble value:
+///
+/// ```sql
+/// SELECT rows, scrabble_score FROM solutions
+/// WHERE symmetric AND scrabble_score IS NOT NULL ORDER BY scrabble_score DESC LIMIT 10;
+/// ```
+///
+/// The boxes of runs that record where each box came from have the record in the
+/// `provenance` table, under the same canonical form.
+pub struct SolutionDb {
+    connection: Connection,
+}
+
+impl SolutionDb {
+    /// Open the database at `path`, creating it and its tables if need be
+    pub fn open(path: &str) -> Result<Self, DbError> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// A database that lasts only as long as it is open
+    pub fn open_in_memory() -> Result<Self, DbError> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self, DbError> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(SolutionDb { connection })
+    }
+
+    /// The connection, for queries of the caller's own
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Record a run started with `command` on the word lists `wordlist`, returning its id
+    pub fn start_run(&self, command: &str, wordlist: &str) -> Result<i64, DbError> {
+        (self.connection).execute(
+            "INSERT INTO runs (command, wordlist) VALUES (?1, ?2)",
+            params![command, wordlist],
+        )?;
+        Ok(self.connection.last_insert_rowid())
+    }
+
+    /// Insert solutions found by run `run` in one transaction, noting the ones already stored
+    /// as found again
+    pub fn insert(&mut self, run: i64, solutions: &[StoredSolution]) -> Result<(), DbError> {
+        let transaction = self.connection.transaction()?;
+        {
+            let mut upsert = transaction.prepare_cached(UPSERT)?;
+            let mut upsert_provenance = transaction.prepare_cached(UPSERT_PROVENANCE)?;
+            for solution in solutions {
+                upsert.execute(params![
+                    solution.canonical,
+                    solution.row_dim,
+                    solution.col_dim,
+                    solution.symmetric,
+                    solution.seed,
+                    solution.rows,
+                    solution.cols,
+                    solution.words,
+                    solution.theme_words,
+                    solution.rare_words,
+                    solution.scrabble_score,
+                    solution.score,
+                    run,
+                ])?;
+                if let Some(record) = &solution.provenance {
+                    upsert_provenance.execute(params![solution.canonical, record, run])?;
+                }
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Number of different boxes stored
+    pub fn len(&self) -> Result<usize, DbError> {
+        let count: i64 =
+            (self.connection).query_row("SELECT count(*) FROM solutions", [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    pub fn is_empty(&self) -> Result<bool, DbError> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Hand the database to a thread that inserts the solutions sent to the writer for run
+    /// `run`, a batch to a transaction, so that the search is not held up by the disk
+    pub fn writer(self, run: i64) -> DbWriter {
+        let (sender, receiver) = mpsc::sync_channel::<StoredSolution>(4 * BATCH);
+        let thread = std::thread::spawn(move || {
+            let mut db = self;
+            let mut batch = Vec::with_capacity(BATCH);
+            while let Ok(solution) = receiver.recv() {
+                batch.push(solution);
+                while batch.len() < BATCH {
+                    match receiver.try_recv() {
+                        Ok(solution) => batch.push(solution),
+                        Err(_) => break,
+                    }
+                }
+                db.insert(run, &batch)?;
+                batch.clear();
+            }
+            Ok(db)
+        });
+        DbWriter {
+            sender: Some(sender),
+            thread,
+        }
+    }
+}
+
+/// Solutions on their way to a `SolutionDb` on a thread of its own (see `SolutionDb::writer`)
+pub struct DbWriter {
+    sender: Option<mpsc::SyncSender<StoredSolution>>,
+    thread: JoinHandle<Result<SolutionDb, DbError>>,
+}
+
+impl DbWriter {
+    /// Queue a solution to be stored. Once the writer has failed solutions are dropped; `finish`
+    /// reports why.
+    pub fn send(&self, solution: StoredSolution) {
+        if let Some(sender) = &self.sender {
+            sender.send(solution).ok();
+        }
+    }
+
+    /// Wait for every solution sent to be stored, and get the database back
+    pub fn finish(mut self) -> Result<SolutionDb, DbError> {
+        self.sender.take();
+        self.thread.join().map_err(|_| DbError::WriterPanicked)?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wordbox::WordBox;
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    fn found(rows: &[&str], symmetric: bool, scrabble_score: Option<u32>) -> FoundSolution {
+        let word_box = (rows.iter()).fold(
+            WordBox::new(rows.len(), rows[0].len(), symmetric),
+            |wb, row| wb.add_word(row.to_string()).unwrap(),
+        );
+        FoundSolution {
+            word_box,
+            seed: rows[0].to_string(),
+            elapsed: Duration::ZERO,
+            transpose_implied: false,
+            required_cells: BTreeMap::new(),
+            theme_words: vec![],
+            rare_words: vec![],
+            tiers: Default::default(),
+            scrabble_score,
+            distinct_letters: None,
+            letter_counts: BTreeMap::new(),
+            invalid_columns: vec![],
+            provenance: None,
+        }
+    }
+
+    /// A stored box, how often it was found, its scores and the run that last found it
+    type Stored = (String, i64, Option<u32>, Option<f64>, i64);
+
+    fn times_found(db: &SolutionDb) -> Vec<Stored> {
+        let mut query = (db.connection())
+            .prepare(
+                "SELECT canonical, times_found, scrabble_score, score, run_id FROM solutions \
+                 ORDER BY canonical",
+            )
+            .unwrap();
+        let rows = query.query_map([], |row| {
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+            ))
+        });
+        rows.unwrap().map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn solutions_are_stored_with_their_words() {
+        let mut db = SolutionDb::open_in_memory().unwrap();
+        assert!(db.is_empty().unwrap());
+        let run = db
+            .start_run("wordbox --rows 3 --cols 3", "words.txt")
+            .unwrap();
+        let solution = StoredSolution::new(&found(&["cat", "ace", "tea"], true, Some(13)), None);
+        assert_eq!(solution.canonical, "cat/ace/tea");
+        assert_eq!(solution.words, "cat ace tea");
+        db.insert(run, &[solution]).unwrap();
+        assert_eq!(db.len().unwrap(), 1);
+        let (rows, row_dim, symmetric, seed): (String, usize, bool, String) = (db.connection())
+            .query_row(
+                "SELECT rows, row_dim, symmetric, seed FROM solutions \
+                 WHERE ' ' || words || ' ' LIKE '% ace %'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            (rows.as_str(), row_dim, symmetric),
+            ("cat ace tea", 3, true)
+        );
+        assert_eq!(seed, "cat");
+    }
+
+    #[test]
+    fn a_box_found_again_is_one_row() {
+        let mut db = SolutionDb::open_in_memory().unwrap();
+        let first = db.start_run("first", "words.txt").unwrap();
+        let second = db.start_run("second", "words.txt").unwrap();
+        // A box and its transpose are the same solution
+        let wb = found(&["bat", "are", "den"], false, None);
+        let transposed = found(&["bad", "are", "ten"], false, Some(11));
+        db.insert(first, &[StoredSolution::new(&wb, Some(2.5))])
+            .unwrap();
+        db.insert(second, &[StoredSolution::new(&transposed, None)])
+            .unwrap();
+        let other = found(&["fast", "area", "dean"], false, None);
+        db.insert(second, &[StoredSolution::new(&other, Some(f64::NAN))])
+            .unwrap();
+        assert_eq!(
+            times_found(&db),
+            [
+                ("bad/are/ten".to_string(), 2, Some(11), Some(2.5), second),
+                ("fast/area/dean".to_string(), 1, None, None, second),
+            ]
+        );
+    }
+
+    #[test]
+    fn the_writer_stores_everything_sent() {
+        let db = SolutionDb::open_in_memory().unwrap();
+        let run = db.start_run("many", "words.txt").unwrap();
+        let writer = db.writer(run);
+        let boxes = [
+            found(&["cat", "ace", "tea"], true, None),
+            found(&["rate", "area", "tear"], false, None),
+            found(&["fast", "area", "dean"], false, None),
+        ];
+        // More than a batch, every box sent many times
+        for i in 0..(3 * BATCH) {
+            writer.send(StoredSolution::new(&boxes[i % 3], None));
+        }
+        let db = writer.finish().unwrap();
+        assert_eq!(db.len().unwrap(), 3);
+        let found: Vec<i64> = (times_found(&db).into_iter())
+            .map(|(_, times, ..)| times)
+            .collect();
+        assert_eq!(found, [BATCH as i64; 3]);
+    }
+}
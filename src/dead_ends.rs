@@ -0,0 +1,179 @@
+use crate::cube::json_string;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// What cut a branch of the search short
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Cause {
+    /// This row had no word left that fits (most-constrained search only)
+    Row(usize),
+    /// This column had no completion, or no letter left for the next row
+    Column(usize),
+    /// A constraint of the config ruled the box out, for the reason given
+    Constraint(&'static str),
+}
+
+impl Display for Cause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Cause::Row(i) => write!(f, "row {}", i),
+            Cause::Column(i) => write!(f, "column {}", i),
+            Cause::Constraint(reason) => write!(f, "\"{}\"", reason),
+        }
+    }
+}
+
+/// How often a required letter could no longer be placed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LetterDeadEnds {
+    pub count: usize,
+    pub earliest: usize, // the fewest rows placed when it happened
+}
+
+/// Why the branches of a search died, counted at the points where the search already turns
+/// candidates away, so a run that finds nothing can say what stood in its way.
+///
+/// Every candidate row turned away and every box pruned counts once against its proximate
+/// cause: the column (or, for the most-constrained search, the slot) left without a word, or
+/// the constraint that ruled it out. Column and row counters are atomics, one per line; the
+/// rarer constraint and letter counts share a lock. Shared by every worker of a run.
+pub struct DeadEnds {
+    rows: Vec<AtomicUsize>,
+    columns: Vec<AtomicUsize>,
+    constraints: Mutex<BTreeMap<&'static str, usize>>,
+    letters: Mutex<BTreeMap<char, LetterDeadEnds>>,
+}
+
+impl std::fmt::Debug for DeadEnds {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeadEnds")
+            .field("total", &self.total())
+            .finish()
+    }
+}
+
+impl DeadEnds {
+    /// Counters for boxes of `row_dim` rows and `col_dim` columns
+    pub fn new(row_dim: usize, col_dim: usize) -> Self {
+        DeadEnds {
+            rows: (0..row_dim).map(|_| AtomicUsize::new(0)).collect(),
+            columns: (0..col_dim).map(|_| AtomicUsize::new(0)).collect(),
+            constraints: Mutex::default(),
+            letters: Mutex::default(),
+        }
+    }
+
+    /// Count one dead end against `cause`; lines outside the box are ignored
+    pub fn record(&self, cause: Cause) {
+        let counter = match cause {
+            Cause::Row(i) => self.rows.get(i),
+            Cause::Column(i) => self.columns.get(i),
+            Cause::Constraint(reason) => {
+                *self.constraints.lock().unwrap().entry(reason).or_default() += 1;
+                return;
+            }
+        };
+        if let Some(counter) = counter {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Count `letter` as a required letter that no longer fit a box with `depth` rows placed
+    pub fn record_letter(&self, letter: char, depth: usize) {
+        let mut letters = self.letters.lock().unwrap();
+        let entry = letters.entry(letter).or_insert(LetterDeadEnds {
+            count: 0,
+            earliest: depth,
+        });
+        entry.count += 1;
+        entry.earliest = entry.earliest.min(depth);
+    }
+
+    /// Every cause that cut a branch, most frequent first, ties in `Cause` order
+    pub fn causes(&self) -> Vec<(Cause, usize)> {
+        let rows = (self.rows.iter().enumerate()).map(|(i, count)| (Cause::Row(i), count));
+        let columns = (self.columns.iter().enumerate()).map(|(i, count)| (Cause::Column(i), count));
+        let constraints = self.constraints.lock().unwrap().clone();
+        let mut causes: Vec<(Cause, usize)> = (rows.chain(columns))
+            .map(|(cause, count)| (cause, count.load(Ordering::Relaxed)))
+            .chain(
+                (constraints.into_iter()).map(|(reason, count)| (Cause::Constraint(reason), count)),
+            )
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        causes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        causes
+    }
+
+    /// The required letters that no longer fit, in alphabetical order
+    pub fn letters(&self) -> BTreeMap<char, LetterDeadEnds> {
+        self.letters.lock().unwrap().clone()
+    }
+
+    /// How many dead ends were counted in all
+    pub fn total(&self) -> usize {
+        self.causes().iter().map(|&(_, count)| count).sum()
+    }
+
+    /// The counts as a JSON object: the total, the causes ranked, and the required letters
+    pub fn to_json(&self) -> String {
+        let causes: Vec<String> = (self.causes().into_iter())
+            .map(|(cause, count)| {
+                let (kind, what) = match cause {
+                    Cause::Row(i) => ("row", i.to_string()),
+                    Cause::Column(i) => ("column", i.to_string()),
+                    Cause::Constraint(reason) => ("constraint", json_string(reason)),
+                };
+                format!("{{\"{}\":{},\"count\":{}}}", kind, what, count)
+            })
+            .collect();
+        let letters: Vec<String> = (self.letters().into_iter())
+            .map(|(letter, dead)| {
+                format!(
+                    "{}:{{\"count\":{},\"fewest_rows\":{}}}",
+                    json_string(&letter.to_string()),
+                    dead.count,
+                    dead.earliest
+                )
+            })
+            .collect();
+        format!(
+            "{{\"total\":{},\"causes\":[{}],\"required_letters\":{{{}}}}}",
+            self.total(),
+            causes.join(","),
+            letters.join(",")
+        )
+    }
+}
+
+impl Display for DeadEnds {
+    /// One line per cause with its share of the dead ends, then one per required letter that
+    /// stopped fitting, with the fewest rows placed when it did
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let causes = self.causes();
+        let total: usize = causes.iter().map(|&(_, count)| count).sum();
+        if total == 0 {
+            writeln!(f, "  no dead ends were recorded")?;
+        }
+        for (cause, count) in causes {
+            writeln!(
+                f,
+                "  {} caused {:.0}% of dead ends ({} of {})",
+                cause,
+                100.0 * count as f64 / total as f64,
+                count,
+                total
+            )?;
+        }
+        for (letter, dead) in self.letters() {
+            writeln!(
+                f,
+                "  required letter {:?} no longer fit {} times, with as few as {} rows placed",
+                letter, dead.count, dead.earliest
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+/// A line of a definitions file that is not a word, a tab and its definition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DefinitionsError {
+    Malformed { line: usize, text: String },
+}
+
+impl Display for DefinitionsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DefinitionsError::Malformed { line, text } => write!(
+                f,
+                "line {}: expected a word, a tab and its definition, found {:?}",
+                line, text
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DefinitionsError {}
+
+/// Definitions to clue a puzzle's words with, keyed on the words as the word list normalizes
+/// them, so that a definition of "Café" clues the "café" of a box
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Definitions {
+    entries: HashMap<String, String>,
+    pub repeated: Vec<String>, // words defined more than once, the last definition kept
+    pub unusable: usize,       // lines whose word the normalization drops
+}
+
+impl Definitions {
+    /// Load a definitions file (see `from_tsv`)
+    pub fn load(
+        filename: &str,
+        normalize: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, DefinitionsError> {
+        let text = std::fs::read_to_string(filename).expect("Could not open definitions file");
+        Self::from_tsv(&text, normalize)
+    }
+
+    /// One word per line, a tab, then its definition; blank lines are skipped. Each word goes
+    /// through `normalize`, the way the word list's words did, and lines whose word it drops are
+    /// counted in `unusable`. A word defined twice keeps its last definition.
+    pub fn from_tsv(
+        text: &str,
+        normalize: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, DefinitionsError> {
+        let mut definitions = Definitions::default();
+        for (i, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let malformed = || DefinitionsError::Malformed {
+                line: i + 1,
+                text: line.to_string(),
+            };
+            let (word, definition) = line.split_once('\t').ok_or_else(malformed)?;
+            let (word, definition) = (word.trim(), definition.trim());
+            if word.is_empty() || definition.is_empty() {
+                return Err(malformed());
+            }
+            let Some(word) = normalize(word) else {
+                definitions.unusable += 1;
+                continue;
+            };
+            if (definitions.entries)
+                .insert(word.clone(), definition.to_string())
+                .is_some()
+            {
+                definitions.repeated.push(word);
+            }
+        }
+        definitions.repeated.sort();
+        definitions.repeated.dedup();
+        Ok(definitions)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The definition of a word as a box holds it
+    pub fn get(&self, word: &str) -> Option<&str> {
+        self.entries.get(word).map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alphabet::Alphabet;
+    use crate::loader::{CaseMode, WordFilter};
+    use crate::puzzle::{Puzzle, NO_CLUE};
+    use crate::wordbox::WordBox;
+
+    const DEFINITIONS: &str = "Cat\tA small feline\n\
+                               cat\tA pet that purrs\n\
+                               Café\tWhere coffee is served\n\
+                               \n\
+                               ace\tA one in cards\n\
+                               let's\tAllows\n";
+
+    /// The definitions above, normalized as --definitions does for a Unicode word list
+    fn definitions() -> Definitions {
+        let filter = WordFilter {
+            case: CaseMode::Lowercase,
+            alphabet: Alphabet::Unicode,
+            ..WordFilter::default()
+        };
+        Definitions::from_tsv(DEFINITIONS, |word| filter.normalize(word)).unwrap()
+    }
+
+    #[test]
+    fn words_are_looked_up_as_the_word_list_normalizes_them() {
+        let definitions = definitions();
+        assert_eq!(definitions.len(), 3);
+        assert_eq!(definitions.get("café"), Some("Where coffee is served"));
+        assert_eq!(definitions.get("ace"), Some("A one in cards"));
+        assert_eq!(definitions.get("tea"), None);
+        // The apostrophe is not a letter of the word list
+        assert_eq!(definitions.unusable, 1);
+    }
+
+    #[test]
+    fn the_last_definition_of_a_word_wins() {
+        let definitions = definitions();
+        assert_eq!(definitions.get("cat"), Some("A pet that purrs"));
+        assert_eq!(definitions.repeated, ["cat"]);
+    }
+
+    #[test]
+    fn lines_need_a_word_and_a_definition() {
+        for (text, line) in [("cat", 1), ("ace\tA one\n\tNo word", 2), ("tea\t ", 1)] {
+            assert_eq!(
+                Definitions::from_tsv(text, |word| Some(word.to_string())),
+                Err(DefinitionsError::Malformed {
+                    line,
+                    text: text.lines().nth(line - 1).unwrap().to_string()
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn puzzles_are_clued_where_a_definition_is_found() {
+        let wb = ["cat", "ace", "tea"]
+            .iter()
+            .fold(WordBox::new(3, 3, true), |wb, row| {
+                wb.add_word(row.to_string()).unwrap()
+            });
+        let puzzle = Puzzle::new(&wb, "0".parse().unwrap(), 7).with_clues(&definitions());
+        let unclued: Vec<&str> = puzzle.unclued().map(|entry| entry.word.as_str()).collect();
+        assert_eq!(unclued, ["tea", "tea"]);
+        let text = puzzle.to_string();
+        let clues = &text[text.find("across:").unwrap()..];
+        assert_eq!(
+            clues,
+            format!(
+                "across:\n  1. A pet that purrs\n  4. A one in cards\n  5. {0}\n\
+                 down:\n  1. A pet that purrs\n  2. A one in cards\n  3. {0}\n",
+                NO_CLUE
+            )
+        );
+    }
+}
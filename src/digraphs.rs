@@ -0,0 +1,282 @@
+use std::fmt::Display;
+
+/// Where the cells standing for multi-letter tiles start: the supplementary private use area,
+/// which no word list spells words with
+const FIRST_TILE: u32 = 0xF0000;
+
+/// Letters a tile may be made of; tiles are two or three of them
+const TILE_LETTERS: u32 = 26;
+const PAIRS: u32 = TILE_LETTERS * TILE_LETTERS;
+const TRIPLES: u32 = PAIRS * TILE_LETTERS;
+
+/// The letters of a multi-letter tile, e.g. `qu`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Tile {
+    letters: [u8; 3],
+    len: u8,
+}
+
+impl Tile {
+    /// A tile of two or three ASCII letters, lowercased
+    pub fn new(letters: &str) -> Option<Tile> {
+        let bytes = letters.as_bytes();
+        if !(2..=3).contains(&bytes.len()) || !bytes.iter().all(u8::is_ascii_alphabetic) {
+            return None;
+        }
+        let mut tile = Tile {
+            letters: [0; 3],
+            len: bytes.len() as u8,
+        };
+        for (slot, byte) in tile.letters.iter_mut().zip(bytes) {
+            *slot = byte.to_ascii_lowercase();
+        }
+        Some(tile)
+    }
+
+    /// The tile a cell holds, if it holds one rather than a single letter
+    pub fn of(cell: char) -> Option<Tile> {
+        let index = (cell as u32).checked_sub(FIRST_TILE)?;
+        let letter = |n: u32| b'a' + (n % TILE_LETTERS) as u8;
+        if index < PAIRS {
+            Some(Tile {
+                letters: [letter(index / TILE_LETTERS), letter(index), 0],
+                len: 2,
+            })
+        } else if index < PAIRS + TRIPLES {
+            let index = index - PAIRS;
+            Some(Tile {
+                letters: [
+                    letter(index / PAIRS),
+                    letter(index / TILE_LETTERS),
+                    letter(index),
+                ],
+                len: 3,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// The one character a box stores the tile as, so that its cells, prefixes and word lengths
+    /// all count the tile once
+    pub fn cell(&self) -> char {
+        let index = |byte: u8| (byte - b'a') as u32;
+        let [a, b, c] = self.letters;
+        let offset = match self.len {
+            2 => index(a) * TILE_LETTERS + index(b),
+            _ => PAIRS + index(a) * PAIRS + index(b) * TILE_LETTERS + index(c),
+        };
+        char::from_u32(FIRST_TILE + offset).expect("tiles map into the private use area")
+    }
+
+    pub fn as_str(&self) -> &str {
+        std::str::from_utf8(&self.letters[..self.len as usize]).expect("tiles are ASCII")
+    }
+
+    /// Number of letters on the tile
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Display for Tile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// How a cell is written out: its letter, or the letters of its tile
+pub fn cell_text(cell: char) -> String {
+    match Tile::of(cell) {
+        Some(tile) => tile.to_string(),
+        None => cell.to_string(),
+    }
+}
+
+/// Number of letters a cell shows
+pub fn cell_width(cell: char) -> usize {
+    Tile::of(cell).map_or(1, |tile| tile.len())
+}
+
+/// `text` with every tile cell spelled out in letters; text with no tiles comes back as it is
+pub fn decode(text: &str) -> String {
+    if text.is_ascii() {
+        return text.to_string();
+    }
+    text.chars().map(cell_text).collect()
+}
+
+/// The multi-letter tiles a word list is written with, such as `qu` in games where a q always
+/// comes with its u. Words are tokenized into cells as they are loaded, so everything after
+/// works on cells: a tile fills one cell of a box and counts once towards a word's length.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Digraphs {
+    tiles: Vec<Tile>, // longest first, so `encode` prefers `sch` over `sc`
+}
+
+impl Digraphs {
+    pub fn new(tiles: impl IntoIterator<Item = Tile>) -> Self {
+        let mut tiles: Vec<Tile> = tiles.into_iter().collect();
+        tiles.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+        tiles.dedup();
+        Digraphs { tiles }
+    }
+
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// `word` split into cells, reading left to right and taking the longest tile that fits at
+    /// each letter: with `qu`, "quiz" becomes three cells and "iraq" stays four. Words with no
+    /// tile in them come back unchanged, so ASCII words stay ASCII.
+    pub fn encode(&self, word: &str) -> String {
+        if self.tiles.is_empty() || !self.tiles.iter().any(|tile| word.contains(tile.as_str())) {
+            return word.to_string();
+        }
+        let mut cells = String::with_capacity(word.len());
+        let mut rest = word;
+        while let Some(ch) = rest.chars().next() {
+            match self
+                .tiles
+                .iter()
+                .find(|tile| rest.starts_with(tile.as_str()))
+            {
+                Some(tile) => {
+                    cells.push(tile.cell());
+                    rest = &rest[tile.len()..];
+                }
+                None => {
+                    cells.push(ch);
+                    rest = &rest[ch.len_utf8()..];
+                }
+            }
+        }
+        cells
+    }
+}
+
+impl std::str::FromStr for Digraphs {
+    type Err = String;
+
+    /// Tiles separated by commas, e.g. `qu` or `qu,th,sch`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tiles = (s.split(',').map(str::trim).filter(|text| !text.is_empty()))
+            .map(|text| {
+                Tile::new(text).ok_or_else(|| {
+                    format!(
+                        "{:?} is not a tile: tiles are two or three letters a to z",
+                        text
+                    )
+                })
+            })
+            .collect::<Result<Vec<Tile>, String>>()?;
+        if tiles.is_empty() {
+            return Err("no tiles given".to_string());
+        }
+        Ok(Digraphs::new(tiles))
+    }
+}
+
+/// The tiles separated by commas
+impl Display for Digraphs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let tiles: Vec<&str> = self.tiles.iter().map(Tile::as_str).collect();
+        write!(f, "{}", tiles.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexicon::{word_length, BuildLexicon, VecLexicon};
+    use crate::solver::{collect_word_boxes, SolverConfig};
+    use crate::wordbox::WordBox;
+    use std::collections::BTreeSet;
+
+    fn qu() -> Digraphs {
+        "qu".parse().unwrap()
+    }
+
+    #[test]
+    fn words_round_trip_through_their_cells() {
+        let digraphs = qu();
+        for (word, cells) in [
+            ("quiz", 3),
+            ("queue", 4),
+            ("iraq", 4),
+            ("equal", 4),
+            ("quq", 2),
+        ] {
+            let encoded = digraphs.encode(word);
+            assert_eq!(word_length(&encoded), cells, "{}", word);
+            assert_eq!(decode(&encoded), word);
+        }
+        // Words without a tile, and any word when there are no tiles, are left alone
+        assert_eq!(digraphs.encode("iraq"), "iraq");
+        assert_eq!(Digraphs::default().encode("quiz"), "quiz");
+        assert_eq!(decode("quiz"), "quiz");
+    }
+
+    #[test]
+    fn the_longest_tile_is_taken() {
+        let digraphs: Digraphs = "sc, sch".parse().unwrap();
+        assert_eq!(digraphs.to_string(), "sch,sc");
+        let encoded = digraphs.encode("schscs");
+        let cells: Vec<String> = encoded.chars().map(cell_text).collect();
+        assert_eq!(cells, ["sch", "sc", "s"]);
+    }
+
+    #[test]
+    fn every_tile_has_a_cell_of_its_own() {
+        let letters = "abcdefghijklmnopqrstuvwxyz";
+        for a in letters.chars() {
+            for b in letters.chars() {
+                for tile in [format!("{}{}", a, b), format!("{}{}z", a, b)] {
+                    let cell = Tile::new(&tile).unwrap().cell();
+                    assert_eq!(Tile::of(cell).unwrap().as_str(), tile);
+                    assert_eq!(cell_width(cell), tile.len());
+                }
+            }
+        }
+        assert_eq!(Tile::of('q'), None);
+        for text in ["q", "quux", "q1", "ñu"] {
+            assert_eq!(Tile::new(text), None, "{}", text);
+        }
+        assert!("".parse::<Digraphs>().is_err());
+    }
+
+    #[test]
+    fn a_tile_fills_one_cell_of_a_box() {
+        let digraphs = qu();
+        let words: Vec<String> = (["qua", "id", "qui", "ad"].iter())
+            .map(|word| digraphs.encode(word))
+            .collect();
+        let lexicon = VecLexicon::initialize(words, [2].into());
+        let config = SolverConfig {
+            symmetry_breaking: false,
+            ..SolverConfig::default()
+        };
+        let (found, _) = collect_word_boxes(WordBox::new(2, 2, false), &lexicon, &config, None);
+        let rows: BTreeSet<Vec<String>> = (found.iter())
+            .map(|wb| wb.row_words().iter().map(|row| decode(row)).collect())
+            .collect();
+        let expected = [["qua", "id"], ["qui", "ad"], ["qua", "ad"], ["qui", "id"]];
+        assert_eq!(
+            rows,
+            expected
+                .map(|rows| rows.map(str::to_string).to_vec())
+                .into()
+        );
+        // Every cell is shown as wide as the tile
+        let shown: BTreeSet<String> = found.iter().map(WordBox::to_string).collect();
+        assert!(shown.contains("qu a\ni  d\n"), "{:?}", shown);
+    }
+}
@@ -0,0 +1,429 @@
+use crate::alphabet::Alphabet;
+use crate::blocks::BLOCK;
+use crate::cancel::CancellationToken;
+use crate::digraphs;
+use crate::lexicon::Lexicon;
+use crate::solver::{solve_word_box_all, CandidateDetail, Outcome, SlotCandidates, SolverConfig};
+use crate::template::{Line, Template};
+use crate::wordbox::WordBox;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::Stylize;
+use crossterm::{cursor, execute, queue, style, terminal};
+use std::io::{self, Write};
+use std::ops::ControlFlow;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread::{self, Scope};
+use std::time::Duration;
+
+/// Nodes a solve may search before it gives up
+pub const DEFAULT_BUDGET: usize = 1_000_000;
+
+/// Completions a solve finds before it stops
+pub const DEFAULT_SHOWN: usize = 3;
+
+const KEYS: &str = "arrows move  letters fix a cell  . or space opens it  # blocks it  \
+                    enter solves  ctrl-s saves  esc quits";
+
+/// How long to wait for a key before looking for completions again
+const TICK: Duration = Duration::from_millis(50);
+
+/// What a solve running in the background reports back
+enum SolveEvent {
+    Found(WordBox),
+    Done(Outcome),
+}
+
+/// A solve running in the background, and the handle that stops it
+struct Solve {
+    events: Receiver<SolveEvent>,
+    cancellation: CancellationToken,
+}
+
+/// The terminal in raw mode on the alternate screen, as the editor draws it. Dropping it puts
+/// the terminal back the way it was, whether the editor returns, fails or panics.
+struct Screen;
+
+impl Screen {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let screen = Screen;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(screen)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen).ok();
+        terminal::disable_raw_mode().ok();
+    }
+}
+
+/// A template built in the terminal: a cursor moves around the grid, letters and blocks are
+/// typed into its cells, and each change shows how many words every open slot may still take
+/// (see `WordBox::candidates`). A solve of the template runs in the background on demand and
+/// shows its first completions as it finds them.
+pub struct Editor<'a, L> {
+    lexicon: &'a L,
+    alphabet: Alphabet,
+    template: Template,
+    symmetric: bool,
+    path: String, // where the template is saved
+    budget: usize,
+    shown: usize,
+    cursor: (usize, usize),
+    slots: Result<SlotCandidates, String>, // or why the template cannot be solved
+    completions: Vec<WordBox>,
+    solve: Option<Solve>,
+    status: String,
+}
+
+impl<'a, L: Lexicon + Sync> Editor<'a, L> {
+    /// An editor of `template` that saves it to `path`. A symmetric box reads the same across
+    /// and down, so every cell typed into is mirrored across the main diagonal.
+    pub fn new(
+        lexicon: &'a L,
+        alphabet: Alphabet,
+        template: Template,
+        symmetric: bool,
+        path: String,
+    ) -> Self {
+        let mut editor = Editor {
+            lexicon,
+            alphabet,
+            template,
+            symmetric,
+            path,
+            budget: DEFAULT_BUDGET,
+            shown: DEFAULT_SHOWN,
+            cursor: (0, 0),
+            slots: Ok(SlotCandidates::default()),
+            completions: vec![],
+            solve: None,
+            status: String::new(),
+        };
+        editor.count_candidates();
+        editor
+    }
+
+    /// Have each solve search at most `budget` nodes
+    pub fn with_budget(mut self, budget: usize) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Have each solve stop once it has found `shown` completions
+    pub fn with_shown(mut self, shown: usize) -> Self {
+        self.shown = shown.max(1);
+        self
+    }
+
+    /// Edit the template until a key asks to quit. The terminal is put back before this
+    /// returns, even if a solve is still running; the solve is then stopped at its next node.
+    pub fn run(&mut self) -> io::Result<()> {
+        thread::scope(|scope| {
+            let screen = Screen::enter()?;
+            let result = self.edit(scope);
+            drop(screen);
+            if let Some(solve) = self.solve.take() {
+                solve.cancellation.cancel();
+            }
+            result
+        })
+    }
+
+    fn edit<'scope>(&mut self, scope: &'scope Scope<'scope, '_>) -> io::Result<()>
+    where
+        'a: 'scope,
+    {
+        let mut out = io::stdout();
+        self.draw(&mut out)?;
+        loop {
+            let mut changed = self.take_solve_events();
+            if event::poll(TICK)? {
+                match event::read()? {
+                    Event::Key(key) if key.kind != KeyEventKind::Release => {
+                        if self.press(key, scope).is_break() {
+                            return Ok(());
+                        }
+                        changed = true;
+                    }
+                    Event::Resize(..) => changed = true,
+                    _ => {}
+                }
+            }
+            if changed {
+                self.draw(&mut out)?;
+            }
+        }
+    }
+
+    /// Act on a key, breaking if it asks to quit
+    fn press<'scope>(&mut self, key: KeyEvent, scope: &'scope Scope<'scope, '_>) -> ControlFlow<()>
+    where
+        'a: 'scope,
+    {
+        let (row, col) = self.cursor;
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        self.status.clear();
+        match key.code {
+            KeyCode::Esc => return ControlFlow::Break(()),
+            KeyCode::Char('c') if control => return ControlFlow::Break(()),
+            KeyCode::Char('s') if control => self.save(),
+            KeyCode::Up => self.cursor.0 = row.saturating_sub(1),
+            KeyCode::Down => self.cursor.0 = (row + 1).min(self.template.row_dim() - 1),
+            KeyCode::Left => self.cursor.1 = col.saturating_sub(1),
+            KeyCode::Right => self.cursor.1 = (col + 1).min(self.template.col_dim() - 1),
+            KeyCode::Enter => self.start_solve(scope),
+            KeyCode::Backspace => {
+                self.cursor.1 = col.saturating_sub(1);
+                self.set_cell(None);
+            }
+            KeyCode::Delete | KeyCode::Char('.' | ' ') => self.set_cell(None),
+            KeyCode::Char(BLOCK) => {
+                let blocked = self.template.get(row, col) == Some(BLOCK);
+                self.set_cell((!blocked).then_some(BLOCK));
+            }
+            KeyCode::Char(ch) => match ch.to_lowercase().next() {
+                Some(letter) if self.alphabet.contains(letter) => {
+                    self.set_cell(Some(letter));
+                    self.cursor.1 = (col + 1).min(self.template.col_dim() - 1);
+                }
+                _ => self.status = format!("{:?} is not in the alphabet", ch),
+            },
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Put `cell` in the cell under the cursor, and in its mirror image in a symmetric box.
+    /// The completions found so far no longer fit, so a running solve is stopped.
+    fn set_cell(&mut self, cell: Option<char>) {
+        let (row, col) = self.cursor;
+        if self.template.get(row, col) == cell {
+            return;
+        }
+        self.template.set(row, col, cell);
+        if self.symmetric {
+            self.template.set(col, row, cell);
+        }
+        self.stop_solve();
+        self.completions.clear();
+        self.count_candidates();
+    }
+
+    /// The empty box the template describes
+    fn empty_box(&self) -> Result<WordBox, String> {
+        let empty = WordBox::try_new(
+            self.template.row_dim(),
+            self.template.col_dim(),
+            self.symmetric,
+        )
+        .map_err(|err| err.to_string())?;
+        Ok(empty
+            .with_alphabet(&self.alphabet)
+            .with_template(&self.template))
+    }
+
+    fn count_candidates(&mut self) {
+        let config = SolverConfig::default();
+        self.slots = (self.empty_box())
+            .map(|wb| wb.candidates(self.lexicon, &config, CandidateDetail::Counts));
+    }
+
+    /// Search for completions of the template in the background, in place of any solve
+    /// running already
+    fn start_solve<'scope>(&mut self, scope: &'scope Scope<'scope, '_>)
+    where
+        'a: 'scope,
+    {
+        let wb = match self.empty_box() {
+            Ok(wb) => wb,
+            Err(err) => {
+                self.status = err;
+                return;
+            }
+        };
+        self.stop_solve();
+        self.completions.clear();
+        let cancellation = CancellationToken::new();
+        let config = SolverConfig {
+            symmetry_breaking: false,
+            max_nodes: Some(self.budget),
+            cancellation: Some(cancellation.clone()),
+            ..SolverConfig::default()
+        };
+        let (sender, events) = mpsc::channel();
+        let (lexicon, shown) = (self.lexicon, self.shown);
+        scope.spawn(move || {
+            let mut found = 0;
+            let report = solve_word_box_all(wb, lexicon, &config, |wb| {
+                found += 1;
+                sender.send(SolveEvent::Found(wb)).ok();
+                match found < shown {
+                    true => ControlFlow::Continue(()),
+                    false => ControlFlow::Break(()),
+                }
+            });
+            sender.send(SolveEvent::Done(report.outcome)).ok();
+        });
+        self.solve = Some(Solve {
+            events,
+            cancellation,
+        });
+        self.status = "solving".to_string();
+    }
+
+    fn stop_solve(&mut self) {
+        if let Some(solve) = self.solve.take() {
+            solve.cancellation.cancel();
+        }
+    }
+
+    /// Take in what the running solve has found since last asked; whether there was anything
+    fn take_solve_events(&mut self) -> bool {
+        let Some(solve) = &self.solve else {
+            return false;
+        };
+        let mut changed = false;
+        let mut outcome = None;
+        loop {
+            match solve.events.try_recv() {
+                Ok(SolveEvent::Found(wb)) => self.completions.push(wb),
+                Ok(SolveEvent::Done(done)) => outcome = Some(done),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    outcome = outcome.or(Some(Outcome::Cancelled));
+                    break;
+                }
+            }
+            changed = true;
+        }
+        if let Some(outcome) = outcome {
+            self.solve = None;
+            self.status = match (self.completions.len(), outcome) {
+                (0, Outcome::Exhausted) => "no completion".to_string(),
+                (0, _) => format!(
+                    "no completion found within {} nodes; pass --budget to search longer",
+                    self.budget
+                ),
+                (1, _) => "1 completion".to_string(),
+                (found, _) => format!("{} completions", found),
+            };
+            changed = true;
+        }
+        changed
+    }
+
+    /// Write the template to its file in the format `--template-file` reads
+    fn save(&mut self) {
+        self.status = match std::fs::write(&self.path, self.template.to_string()) {
+            Ok(()) => format!("saved to {}", self.path),
+            Err(err) => format!("could not save to {}: {}", self.path, err),
+        };
+    }
+
+    /// The lines of the grid, the cursor's cell standing out, each row followed by how many
+    /// words it may take if it is open
+    fn grid_lines(&self) -> Vec<String> {
+        let count = |line, index| match &self.slots {
+            Ok(slots) => (slots.get(line, index)).map(|candidates| candidates.len()),
+            Err(_) => None,
+        };
+        (0..self.template.row_dim())
+            .map(|row| {
+                let mut text = String::from("  ");
+                for col in 0..self.template.col_dim() {
+                    let cell = match self.template.get(row, col) {
+                        Some(cell) => digraphs::cell_text(cell),
+                        None => ".".to_string(),
+                    };
+                    match (row, col) == self.cursor {
+                        true => text += &format!("{} ", cell.reverse()),
+                        false => text += &format!("{} ", cell),
+                    }
+                }
+                if let Some(words) = count(Line::Row, row) {
+                    text += &format!("  row {}: {} words", row, words);
+                }
+                text
+            })
+            .collect()
+    }
+
+    /// The lines of the completions found, side by side
+    fn completion_lines(&self) -> Vec<String> {
+        let boxes: Vec<Vec<String>> = (self.completions.iter())
+            .map(|wb| wb.to_string().lines().map(str::to_string).collect())
+            .collect();
+        let height = boxes.iter().map(Vec::len).max().unwrap_or(0);
+        (0..height)
+            .map(|i| {
+                let cells: Vec<String> = (boxes.iter())
+                    .map(|lines| {
+                        let width = lines.iter().map(|line| line.chars().count()).max();
+                        let line = lines.get(i).map_or("", String::as_str);
+                        format!("{:<width$}", line, width = width.unwrap_or(0))
+                    })
+                    .collect();
+                format!("  {}", cells.join("   "))
+            })
+            .collect()
+    }
+
+    fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        let (row_dim, col_dim) = (self.template.row_dim(), self.template.col_dim());
+        let shape = if self.symmetric {
+            "symmetric"
+        } else {
+            "asymmetric"
+        };
+        let mut lines = vec![
+            format!(
+                "{}x{} {} template, saved to {}",
+                row_dim, col_dim, shape, self.path
+            ),
+            String::new(),
+        ];
+        lines.extend(self.grid_lines());
+        match &self.slots {
+            Ok(slots) => {
+                let cols: Vec<String> = (0..col_dim)
+                    .filter_map(|col| {
+                        let words = slots.get(Line::Col, col)?.len();
+                        Some(format!("col {}: {} words", col, words))
+                    })
+                    .collect();
+                if !cols.is_empty() {
+                    lines.push(format!("  {}", cols.join(", ")));
+                }
+                if slots.is_stuck() {
+                    lines.push("  an open slot has no word left".to_string());
+                }
+            }
+            Err(err) => lines.push(format!("  {}", err)),
+        }
+        if !self.completions.is_empty() {
+            lines.push(String::new());
+            lines.extend(self.completion_lines());
+        }
+        lines.push(String::new());
+        let status = match self.solve {
+            Some(_) => format!("{} ({} found so far)", self.status, self.completions.len()),
+            None => self.status.clone(),
+        };
+        lines.push(status);
+        lines.push(KEYS.to_string());
+
+        queue!(
+            out,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+        for line in lines {
+            // Raw mode moves down a line without going back to its start
+            queue!(out, style::Print(line), style::Print("\r\n"))?;
+        }
+        out.flush()
+    }
+}
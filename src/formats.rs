@@ -0,0 +1,470 @@
+use std::fmt::Display;
+
+/// How a word list file is laid out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InputFormat {
+    /// Guess from the file's extension and how its contents start (see `detect`)
+    #[default]
+    Auto,
+    /// One word per line
+    Text,
+    /// A header line, then one record per line; the words are one of the columns
+    Csv,
+    /// An array of strings
+    Json,
+}
+
+impl InputFormat {
+    pub const ALL: [InputFormat; 4] = [
+        InputFormat::Auto,
+        InputFormat::Text,
+        InputFormat::Csv,
+        InputFormat::Json,
+    ];
+
+    /// The format spelled out, for messages
+    pub fn name(&self) -> &'static str {
+        match self {
+            InputFormat::Auto => "undetected",
+            InputFormat::Text => "plain text",
+            InputFormat::Csv => "CSV",
+            InputFormat::Json => "JSON",
+        }
+    }
+}
+
+impl Display for InputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputFormat::Auto => write!(f, "auto"),
+            InputFormat::Text => write!(f, "text"),
+            InputFormat::Csv => write!(f, "csv"),
+            InputFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+impl std::str::FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        InputFormat::ALL
+            .into_iter()
+            .find(|format| format.to_string() == s)
+            .ok_or_else(|| {
+                let names: Vec<String> = InputFormat::ALL.iter().map(|f| f.to_string()).collect();
+                format!(
+                    "unknown input format {:?} (expected one of {})",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// The column of a CSV file holding the words: its name in the header, or its index counted
+/// from 0
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CsvColumn {
+    Index(usize),
+    Name(String),
+}
+
+impl Default for CsvColumn {
+    fn default() -> Self {
+        CsvColumn::Index(0)
+    }
+}
+
+impl Display for CsvColumn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CsvColumn::Index(index) => write!(f, "{}", index),
+            CsvColumn::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl std::str::FromStr for CsvColumn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("expected a column name or index".to_string());
+        }
+        Ok(match s.parse() {
+            Ok(index) => CsvColumn::Index(index),
+            Err(_) => CsvColumn::Name(s.to_string()),
+        })
+    }
+}
+
+/// Why a word list could not be read in its format
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatError {
+    /// The extension names one format and the contents look like another
+    Ambiguous {
+        extension: InputFormat,
+        contents: InputFormat,
+    },
+    /// A record that does not fit the format: a CSV line, or a JSON element counted from 0
+    Malformed {
+        format: InputFormat,
+        record: String,
+        text: String,
+        reason: String,
+    },
+}
+
+impl Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FormatError::Ambiguous {
+                extension,
+                contents,
+            } => write!(
+                f,
+                "the extension says {} but the contents look like {}; pass --input-format to choose",
+                extension.name(),
+                contents.name()
+            ),
+            FormatError::Malformed {
+                format,
+                record,
+                text,
+                reason,
+            } => write!(
+                f,
+                "read as {}, {} is malformed: {} (found {:?})",
+                format.name(),
+                record,
+                reason,
+                text
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+/// Bytes of a file looked at to guess its format
+pub const SNIFF_BYTES: usize = 4096;
+
+/// The format of a file named `filename` that starts with `head`. The extension `.txt`, `.csv`
+/// or `.json` decides, unless the contents look like another format: JSON if they start with
+/// `[`, CSV if their first line holds a comma, and plain text otherwise. A one-column CSV file
+/// has no commas to tell it from plain text, so its extension is trusted.
+pub fn detect(filename: &str, head: &[u8]) -> Result<InputFormat, FormatError> {
+    let head = head.strip_prefix(b"\xef\xbb\xbf").unwrap_or(head);
+    let start = head.iter().position(|byte| !byte.is_ascii_whitespace());
+    let first_line = head.split(|&byte| byte == b'\n').next().unwrap_or(&[]);
+    let contents = match start.map(|i| head[i]) {
+        Some(b'[') => InputFormat::Json,
+        _ if first_line.contains(&b',') => InputFormat::Csv,
+        _ => InputFormat::Text,
+    };
+    let extension = filename.rsplit_once('.').and_then(|(stem, extension)| {
+        let stem = stem.rsplit(['/', '\\']).next().unwrap_or(stem);
+        match extension.to_ascii_lowercase().as_str() {
+            _ if stem.is_empty() => None, // a dotfile, such as `.words`
+            "txt" => Some(InputFormat::Text),
+            "csv" => Some(InputFormat::Csv),
+            "json" => Some(InputFormat::Json),
+            _ => None,
+        }
+    });
+    match extension {
+        None => Ok(contents),
+        Some(extension) if extension == contents => Ok(extension),
+        Some(InputFormat::Csv) if contents == InputFormat::Text => Ok(InputFormat::Csv),
+        Some(extension) => Err(FormatError::Ambiguous {
+            extension,
+            contents,
+        }),
+    }
+}
+
+/// How much of a record to show in an error
+fn excerpt(text: &str) -> String {
+    const MAX: usize = 60;
+    match text.char_indices().nth(MAX) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// The `column` field of every record after the header of a CSV file. Fields may be quoted,
+/// with `""` for a quote inside them; quoted fields may span lines. Records need not have as
+/// many fields as the header, only enough to reach the column.
+pub fn csv_column(text: &str, column: &CsvColumn) -> Result<Vec<String>, FormatError> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let malformed = |line: usize, record: &str, reason: String| FormatError::Malformed {
+        format: InputFormat::Csv,
+        record: format!("line {}", line),
+        text: excerpt(record),
+        reason,
+    };
+    let mut records = CsvRecords {
+        text,
+        pos: 0,
+        line: 1,
+    };
+    let Some(header) = records.next() else {
+        return Ok(vec![]);
+    };
+    let (line, raw, header) = header.map_err(|(line, raw, reason)| malformed(line, raw, reason))?;
+    let index = match column {
+        CsvColumn::Index(index) => *index,
+        CsvColumn::Name(name) => header
+            .iter()
+            .position(|field| field.trim() == name)
+            .ok_or_else(|| malformed(line, raw, format!("the header has no column {:?}", name)))?,
+    };
+    if index >= header.len() {
+        let reason = format!(
+            "the header has {} columns, so there is no column {}",
+            header.len(),
+            index
+        );
+        return Err(malformed(line, raw, reason));
+    }
+    let mut words = vec![];
+    for record in records {
+        let (line, raw, mut fields) =
+            record.map_err(|(line, raw, reason)| malformed(line, raw, reason))?;
+        if fields.len() == 1 && fields[0].is_empty() {
+            continue; // a blank line
+        }
+        if index >= fields.len() {
+            let count = match fields.len() {
+                1 => "1 field".to_string(),
+                n => format!("{} fields", n),
+            };
+            let reason = format!("{}, too few to reach column {}", count, column);
+            return Err(malformed(line, raw, reason));
+        }
+        words.push(fields.swap_remove(index));
+    }
+    Ok(words)
+}
+
+/// The records of a CSV file, each with the line it starts on, its raw text and its fields
+struct CsvRecords<'a> {
+    text: &'a str,
+    pos: usize,
+    line: usize,
+}
+
+type CsvRecord<'a> = (usize, &'a str, Vec<String>);
+
+impl<'a> Iterator for CsvRecords<'a> {
+    type Item = Result<CsvRecord<'a>, (usize, &'a str, String)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.text.len() {
+            return None;
+        }
+        let (start, line) = (self.pos, self.line);
+        let rest = &self.text[start..];
+        let mut fields = vec![String::new()];
+        let mut quoted = false; // inside a quoted field
+        let mut was_quoted = false; // the current field was quoted and has closed
+        let mut chars = rest.char_indices().peekable();
+        let (mut end, mut next) = (rest.len(), rest.len()); // where the record and the next start
+        while let Some((i, ch)) = chars.next() {
+            let field = fields.last_mut().expect("a record has a field");
+            match ch {
+                '"' if quoted && chars.peek().is_some_and(|&(_, next)| next == '"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' if quoted => {
+                    quoted = false;
+                    was_quoted = true;
+                }
+                '"' if field.is_empty() && !was_quoted => quoted = true,
+                '\n' if quoted => {
+                    self.line += 1;
+                    field.push('\n');
+                }
+                _ if quoted => field.push(ch),
+                ',' => {
+                    fields.push(String::new());
+                    was_quoted = false;
+                }
+                '\n' => {
+                    end = i;
+                    next = i + 1;
+                    break;
+                }
+                '\r' if chars.peek().is_some_and(|&(_, next)| next == '\n') => {}
+                _ if was_quoted => {
+                    self.pos = self.text.len();
+                    let reason = "text after a closing quote".to_string();
+                    return Some(Err((line, first_line(rest), reason)));
+                }
+                '"' => {
+                    self.pos = self.text.len();
+                    let reason = "a quote inside an unquoted field".to_string();
+                    return Some(Err((line, first_line(rest), reason)));
+                }
+                _ => field.push(ch),
+            }
+        }
+        let raw = rest[..end].trim_end_matches('\r');
+        self.pos = start + next;
+        self.line += 1;
+        if quoted {
+            self.pos = self.text.len();
+            let reason = "a quoted field never closes".to_string();
+            return Some(Err((line, first_line(rest), reason)));
+        }
+        Some(Ok((line, raw, fields)))
+    }
+}
+
+/// The text up to the end of the line
+fn first_line(text: &str) -> &str {
+    text.lines().next().unwrap_or("")
+}
+
+/// The strings of a JSON array of strings
+pub fn json_strings(text: &str) -> Result<Vec<String>, FormatError> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut parser = JsonParser { text, pos: 0 };
+    let malformed = |parser: &JsonParser, record: String, reason: &str| FormatError::Malformed {
+        format: InputFormat::Json,
+        record,
+        text: excerpt(parser.text[parser.pos..].lines().next().unwrap_or("")),
+        reason: reason.to_string(),
+    };
+    parser.skip_whitespace();
+    if !parser.eat('[') {
+        let record = "the start".to_string();
+        return Err(malformed(&parser, record, "expected an array"));
+    }
+    let mut strings = vec![];
+    parser.skip_whitespace();
+    if !parser.eat(']') {
+        loop {
+            let record = format!("element {}", strings.len());
+            parser.skip_whitespace();
+            match parser.string() {
+                Ok(string) => strings.push(string),
+                Err(reason) => return Err(malformed(&parser, record, reason)),
+            }
+            parser.skip_whitespace();
+            if parser.eat(']') {
+                break;
+            }
+            if !parser.eat(',') {
+                return Err(malformed(&parser, record, "expected , or ] after it"));
+            }
+        }
+    }
+    parser.skip_whitespace();
+    if parser.pos < text.len() {
+        let record = "the end".to_string();
+        return Err(malformed(
+            &parser,
+            record,
+            "expected nothing after the array",
+        ));
+    }
+    Ok(strings)
+}
+
+struct JsonParser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl JsonParser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn eat(&mut self, ch: char) -> bool {
+        let found = self.peek() == Some(ch);
+        if found {
+            self.pos += ch.len_utf8();
+        }
+        found
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.pos..];
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+    }
+
+    /// A string literal, leaving the position on it if it is not one
+    fn string(&mut self) -> Result<String, &'static str> {
+        let start = self.pos;
+        if !self.eat('"') {
+            return Err("expected a string");
+        }
+        let mut string = String::new();
+        let result = loop {
+            let Some(ch) = self.peek() else {
+                break Err("the string never closes");
+            };
+            self.pos += ch.len_utf8();
+            match ch {
+                '"' => break Ok(string),
+                '\\' => match self.escape() {
+                    Ok(ch) => string.push(ch),
+                    Err(reason) => break Err(reason),
+                },
+                ch if ch.is_control() => break Err("a control character inside the string"),
+                ch => string.push(ch),
+            }
+        };
+        if result.is_err() {
+            self.pos = start;
+        }
+        result
+    }
+
+    /// The character a backslash escape stands for, the backslash already read
+    fn escape(&mut self) -> Result<char, &'static str> {
+        let ch = self.peek().ok_or("the string never closes")?;
+        self.pos += ch.len_utf8();
+        Ok(match ch {
+            '"' | '\\' | '/' => ch,
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'u' => {
+                let high = self.hex4()?;
+                if !(0xd800..0xdc00).contains(&high) {
+                    return char::from_u32(high).ok_or("an escape of a lone surrogate");
+                }
+                if !(self.eat('\\') && self.eat('u')) {
+                    return Err("an escape of a lone surrogate");
+                }
+                let low = self.hex4()?;
+                if !(0xdc00..0xe000).contains(&low) {
+                    return Err("an escape of a lone surrogate");
+                }
+                char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00))
+                    .ok_or("an escape of a lone surrogate")?
+            }
+            _ => return Err("an unknown escape"),
+        })
+    }
+
+    fn hex4(&mut self) -> Result<u32, &'static str> {
+        let digits = self
+            .text
+            .get(self.pos..self.pos + 4)
+            .ok_or("a short \\u escape")?;
+        let value = u32::from_str_radix(digits, 16).map_err(|_| "a malformed \\u escape")?;
+        if digits.starts_with('+') {
+            return Err("a malformed \\u escape");
+        }
+        self.pos += 4;
+        Ok(value)
+    }
+}
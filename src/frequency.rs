@@ -0,0 +1,310 @@
+use crate::blocks::BLOCK;
+use crate::lexicon::WILDCARD;
+use crate::wordbox::WordBox;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+
+/// A line of a frequency file that is not a word followed by a frequency
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrequencyError {
+    Malformed { line: usize, text: String },
+}
+
+impl Display for FrequencyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FrequencyError::Malformed { line, text } => write!(
+                f,
+                "line {}: expected a word and a non-negative frequency, found {:?}",
+                line, text
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FrequencyError {}
+
+/// How common words are, e.g. their counts in a corpus, keyed on the normalized word
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WordFrequencies {
+    frequencies: HashMap<String, f64>,
+    sorted: Vec<f64>, // every frequency listed, in increasing order, for percentiles
+}
+
+/// A frequency below which words count as rare: either a frequency itself, or `pN` for the
+/// frequency at the Nth percentile of the listed words, e.g. `p20`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrequencyThreshold {
+    Frequency(f64),
+    Percentile(f64),
+}
+
+impl std::str::FromStr for FrequencyThreshold {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (percentile, number) = match s.strip_prefix('p') {
+            Some(number) => (true, number),
+            None => (false, s),
+        };
+        let value: f64 = number
+            .parse()
+            .ok()
+            .filter(|value: &f64| value.is_finite() && *value >= 0.0)
+            .ok_or_else(|| {
+                format!(
+                    "expected a frequency or a percentile such as p20, found {:?}",
+                    s
+                )
+            })?;
+        if !percentile {
+            return Ok(FrequencyThreshold::Frequency(value));
+        }
+        if value > 100.0 {
+            return Err(format!("percentile {} is above 100", value));
+        }
+        Ok(FrequencyThreshold::Percentile(value))
+    }
+}
+
+impl Display for FrequencyThreshold {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FrequencyThreshold::Frequency(frequency) => write!(f, "{}", frequency),
+            FrequencyThreshold::Percentile(percentile) => write!(f, "p{}", percentile),
+        }
+    }
+}
+
+/// How obscure the words of a box are, by their frequencies
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difficulty {
+    pub rarest_word: String,
+    pub rarest_percentile: f64, // share of the listed words rarer than the rarest word, in percent
+    pub mean_log_frequency: f64, // mean of ln(1 + frequency) over the words
+}
+
+impl Display for Difficulty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "difficulty: rarest word {:?} at percentile {:.1}, mean log frequency {:.2}",
+            self.rarest_word, self.rarest_percentile, self.mean_log_frequency
+        )
+    }
+}
+
+impl WordFrequencies {
+    /// Load frequencies from a file of `word frequency` lines; blank lines and `#` comments are
+    /// skipped
+    pub fn load(filename: &str) -> Result<Self, FrequencyError> {
+        let file: File = File::open(filename).expect("Could not open frequency file");
+        let reader = BufReader::new(file);
+        Self::from_lines(reader.lines().map_while(Result::ok))
+    }
+
+    /// A word listed twice keeps the frequency given last
+    pub fn from_lines(lines: impl IntoIterator<Item = String>) -> Result<Self, FrequencyError> {
+        let mut frequencies = HashMap::new();
+        for (i, line) in lines.into_iter().enumerate() {
+            let text = line.trim();
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+            let malformed = || FrequencyError::Malformed {
+                line: i + 1,
+                text: text.to_string(),
+            };
+            let mut fields = text.split_whitespace();
+            let (Some(word), Some(frequency), None) = (fields.next(), fields.next(), fields.next())
+            else {
+                return Err(malformed());
+            };
+            let frequency: f64 = frequency.parse().map_err(|_| malformed())?;
+            if !(frequency >= 0.0 && frequency.is_finite()) {
+                return Err(malformed());
+            }
+            frequencies.insert(word.to_lowercase(), frequency);
+        }
+        let mut sorted: Vec<f64> = frequencies.values().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        Ok(WordFrequencies {
+            frequencies,
+            sorted,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.frequencies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frequencies.is_empty()
+    }
+
+    /// The frequency of `word`, if the file lists it
+    pub fn get(&self, word: &str) -> Option<f64> {
+        if !word.chars().any(char::is_uppercase) {
+            return self.frequencies.get(word).copied();
+        }
+        self.frequencies.get(&word.to_lowercase()).copied()
+    }
+
+    /// The share of the listed words that are rarer than `frequency`, in percent
+    pub fn percentile(&self, frequency: f64) -> f64 {
+        let rarer = self.sorted.partition_point(|&listed| listed < frequency);
+        100.0 * rarer as f64 / self.sorted.len().max(1) as f64
+    }
+
+    /// The frequency `threshold` stands for: itself, or the frequency of the listed word at that
+    /// percentile, so that about that share of the listed words fall below it
+    pub fn cutoff(&self, threshold: FrequencyThreshold) -> f64 {
+        match threshold {
+            FrequencyThreshold::Frequency(frequency) => frequency,
+            FrequencyThreshold::Percentile(_) if self.sorted.is_empty() => 0.0,
+            FrequencyThreshold::Percentile(percentile) => {
+                let rank = (percentile / 100.0 * self.sorted.len() as f64) as usize;
+                self.sorted[rank.min(self.sorted.len() - 1)]
+            }
+        }
+    }
+
+    /// How obscure `words` are: the rarest of them with its percentile, and their mean log
+    /// frequency. Words the file does not list are taken to have the frequency `missing`; 0
+    /// makes them the rarest of all. `None` if there are no words.
+    pub fn difficulty<'a>(
+        &self,
+        words: impl Iterator<Item = &'a str>,
+        missing: f64,
+    ) -> Option<Difficulty> {
+        let mut rarest: Option<(&str, f64)> = None;
+        let (mut log_sum, mut count) = (0.0, 0usize);
+        for word in words {
+            let frequency = self.get(word).unwrap_or(missing);
+            if rarest.is_none_or(|(_, lowest)| frequency < lowest) {
+                rarest = Some((word, frequency));
+            }
+            log_sum += frequency.ln_1p();
+            count += 1;
+        }
+        let (word, frequency) = rarest?;
+        Some(Difficulty {
+            rarest_word: word.to_string(),
+            rarest_percentile: self.percentile(frequency),
+            mean_log_frequency: log_sum / count as f64,
+        })
+    }
+
+    /// The frequencies of the words of a box summed, counting words the file does not list
+    /// as 0
+    pub fn total(&self, wb: &WordBox) -> f64 {
+        wb.words()
+            .filter_map(|word| self.get(word))
+            .fold(0.0, |total, frequency| total + frequency)
+    }
+}
+
+/// The words rarer than a cutoff frequency, which a solution may only use a few of
+#[derive(Debug, Clone, PartialEq)]
+pub struct RareWords {
+    frequencies: Arc<WordFrequencies>,
+    cutoff: f64,
+    missing: f64, // frequency of the words the frequency list leaves out
+}
+
+impl RareWords {
+    pub fn new(
+        frequencies: Arc<WordFrequencies>,
+        threshold: FrequencyThreshold,
+        missing: f64,
+    ) -> Self {
+        let cutoff = frequencies.cutoff(threshold);
+        RareWords {
+            frequencies,
+            cutoff,
+            missing,
+        }
+    }
+
+    /// The frequency a word has to reach not to be rare
+    pub fn cutoff(&self) -> f64 {
+        self.cutoff
+    }
+
+    pub fn is_rare(&self, word: &str) -> bool {
+        self.frequencies.get(word).unwrap_or(self.missing) < self.cutoff
+    }
+
+    /// The rare words among the words of a box (see `WordBox::words`), in the order it lists
+    /// them
+    pub fn used(&self, wb: &WordBox) -> Vec<String> {
+        wb.words()
+            .filter(|word| self.is_rare(word))
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// How many of the words a partial box has finished are rare: the words of the rows placed
+    /// and, in asymmetric boxes, the runs of columns that no open cell is left in. Once the box
+    /// is complete these are all of its words.
+    pub(crate) fn count_finished(&self, wb: &WordBox) -> usize {
+        wb.lines()
+            .iter()
+            .flat_map(|line| line.split(BLOCK))
+            .filter(|run| !run.is_empty() && !run.contains(WILDCARD) && self.is_rare(run))
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frequencies(lines: &[&str]) -> WordFrequencies {
+        WordFrequencies::from_lines(lines.iter().map(|line| line.to_string())).unwrap()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn ratings_are_the_rarest_word_and_the_mean_log_frequency() {
+        let frequencies = frequencies(&["ab 99", "bd 9", "xx 0", "yy 999"]);
+        let rating = (frequencies.difficulty(["ab", "bd"].into_iter(), 0.0)).unwrap();
+        assert_eq!(rating.rarest_word, "bd");
+        // Of 0, 9, 99 and 999, only 0 is rarer than 9
+        assert_close(rating.rarest_percentile, 25.0);
+        // (ln 100 + ln 10) / 2
+        assert_close(rating.mean_log_frequency, 1.5 * 10f64.ln());
+
+        let wb = WordBox::new(2, 2, true);
+        assert_eq!(wb.difficulty(&frequencies, 0.0), None);
+        let wb = wb.add_word("ab".to_string()).unwrap();
+        let wb = wb.add_word("bd".to_string()).unwrap();
+        assert_eq!(wb.difficulty(&frequencies, 0.0), Some(rating));
+    }
+
+    #[test]
+    fn unlisted_words_take_the_missing_frequency() {
+        let frequencies = frequencies(&["ab 99", "bd 9", "xx 0", "yy 999"]);
+        let rating = (frequencies.difficulty(["ab", "zz"].into_iter(), 0.0)).unwrap();
+        assert_eq!(rating.rarest_word, "zz");
+        assert_close(rating.rarest_percentile, 0.0);
+        // (ln 100 + ln 1) / 2
+        assert_close(rating.mean_log_frequency, 10f64.ln());
+
+        let rating = (frequencies.difficulty(["ab", "zz"].into_iter(), 500.0)).unwrap();
+        assert_eq!(rating.rarest_word, "ab");
+        assert_close(rating.rarest_percentile, 50.0);
+        assert_close(rating.mean_log_frequency, (100f64.ln() + 501f64.ln()) / 2.0);
+    }
+}
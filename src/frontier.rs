@@ -0,0 +1,237 @@
+use crate::memory::EstimatedSize;
+use crate::wordbox::WordBox;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet, VecDeque};
+
+/// A partial box waiting to be expanded, with the forward-checking letter sets if they are tracked
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub(crate) wb: WordBox,
+    pub(crate) next_letters: Option<Vec<HashSet<char>>>,
+}
+
+impl Node {
+    /// The partial box itself
+    pub fn word_box(&self) -> &WordBox {
+        &self.wb
+    }
+
+    /// The node without its letter sets, which the search works out again when it expands the
+    /// box; frontiers hold far more boxes this way under `MemoryAction::Compact`
+    pub(crate) fn compact(self) -> Node {
+        Node {
+            next_letters: None,
+            ..self
+        }
+    }
+}
+
+impl EstimatedSize for Node {
+    fn estimated_bytes(&self) -> usize {
+        self.wb.estimated_bytes() - std::mem::size_of::<WordBox>()
+            + self.next_letters.estimated_bytes()
+            - std::mem::size_of::<Option<Vec<HashSet<char>>>>()
+            + std::mem::size_of::<Node>()
+    }
+}
+
+/// The partial boxes a search has yet to expand, and the order it expands them in.
+///
+/// The solver pops a box, reports it if it is complete and pushes its children otherwise, until
+/// `pop` returns `None`. A frontier that eventually hands back every box pushed keeps the search
+/// complete. It may also drop boxes, as beam search does, but then has to own up to it through
+/// `dropped_any`, so that the search ends with `Outcome::Pruned` rather than claiming to have
+/// exhausted the search space.
+pub trait Frontier {
+    /// Add a box to expand later; `score` is the box's `WordBox::score` if `needs_scores`, or 0.0
+    fn push(&mut self, node: Node, score: f64);
+
+    /// The next box to expand, or `None` to end the search
+    fn pop(&mut self) -> Option<Node>;
+
+    /// Number of boxes waiting
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether `push` looks at scores; they cost a lexicon query per column, so the solver
+    /// only computes them when asked to
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    /// Whether the box pushed last is popped first, in which case the children of a box are
+    /// pushed in reverse so that they are still expanded in the order they were tried
+    fn is_lifo(&self) -> bool {
+        false
+    }
+
+    /// Whether some box pushed will never be popped
+    fn dropped_any(&self) -> bool {
+        false
+    }
+
+    /// How many boxes, and how many bytes of them, went to disk so far (see
+    /// `spill::SpillingBreadthFirst`)
+    fn spilled(&self) -> (usize, usize) {
+        (0, 0)
+    }
+}
+
+/// Expands the box pushed last first
+#[derive(Debug, Default)]
+pub struct DepthFirst {
+    stack: Vec<Node>,
+}
+
+impl Frontier for DepthFirst {
+    fn push(&mut self, node: Node, _score: f64) {
+        self.stack.push(node);
+    }
+
+    fn pop(&mut self) -> Option<Node> {
+        self.stack.pop()
+    }
+
+    fn len(&self) -> usize {
+        self.stack.len()
+    }
+
+    fn is_lifo(&self) -> bool {
+        true
+    }
+}
+
+/// Expands boxes in the order they were pushed
+#[derive(Debug, Default)]
+pub struct BreadthFirst {
+    queue: VecDeque<Node>,
+}
+
+impl Frontier for BreadthFirst {
+    fn push(&mut self, node: Node, _score: f64) {
+        self.queue.push_back(node);
+    }
+
+    fn pop(&mut self) -> Option<Node> {
+        self.queue.pop_front()
+    }
+
+    fn len(&self) -> usize {
+        self.queue.len()
+    }
+}
+
+/// A box in the best-first heap; ties on score go to the box pushed first, so runs are reproducible
+struct ScoredBox {
+    score: f64,
+    seq: u64,
+    node: Node,
+}
+
+impl PartialEq for ScoredBox {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for ScoredBox {}
+
+impl PartialOrd for ScoredBox {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ScoredBox {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .total_cmp(&other.score)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+/// Expands the highest-scoring box first
+#[derive(Default)]
+pub struct BestFirst {
+    heap: BinaryHeap<ScoredBox>,
+    seq: u64,
+}
+
+impl Frontier for BestFirst {
+    fn push(&mut self, node: Node, score: f64) {
+        self.heap.push(ScoredBox {
+            score,
+            seq: self.seq,
+            node,
+        });
+        self.seq += 1;
+    }
+
+    fn pop(&mut self) -> Option<Node> {
+        self.heap.pop().map(|scored| scored.node)
+    }
+
+    fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    fn needs_scores(&self) -> bool {
+        true
+    }
+}
+
+/// Expands boxes level by level, keeping only the `width` highest-scoring children of a level
+/// as the next one, ties going to the child pushed first
+#[derive(Debug)]
+pub struct Beam {
+    width: usize,
+    level: VecDeque<Node>,
+    next: Vec<(f64, Node)>,
+    pruned: bool,
+}
+
+impl Beam {
+    pub fn new(width: usize) -> Self {
+        Beam {
+            width,
+            level: VecDeque::new(),
+            next: vec![],
+            pruned: false,
+        }
+    }
+}
+
+impl Frontier for Beam {
+    fn push(&mut self, node: Node, score: f64) {
+        self.next.push((score, node));
+    }
+
+    fn pop(&mut self) -> Option<Node> {
+        if self.level.is_empty() {
+            // The level is done, so every child of it has been pushed
+            let mut next = std::mem::take(&mut self.next);
+            if next.len() > self.width {
+                next.sort_by(|a, b| b.0.total_cmp(&a.0));
+                next.truncate(self.width);
+                self.pruned = true;
+            }
+            self.level = next.into_iter().map(|(_, node)| node).collect();
+        }
+        self.level.pop_front()
+    }
+
+    fn len(&self) -> usize {
+        self.level.len() + self.next.len()
+    }
+
+    fn needs_scores(&self) -> bool {
+        true
+    }
+
+    fn dropped_any(&self) -> bool {
+        self.pruned
+    }
+}
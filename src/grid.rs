@@ -0,0 +1,239 @@
+use crate::alphabet::Alphabet;
+use crate::lexicon::{Lexicon, Lexicons, WILDCARD};
+use crate::memory::EstimatedSize;
+use crate::template::Template;
+use crate::wordbox::WordBox;
+use std::sync::Arc;
+
+/// A row or column of the grid that takes one word
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Slot {
+    Row(usize),
+    Col(usize),
+}
+
+/// Why `Grid::place_candidate` turned a word down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Refusal {
+    Mismatched,    // a cell's mask does not allow its letter
+    Repeated,      // the word already fills another slot
+    DeadEnd(Slot), // this slot it crosses can no longer be completed
+}
+
+/// Search state where rows and columns can be filled in any order.
+///
+/// Unlike `WordBox`, which only grows row by row, this tracks every cell and which row and
+/// column words are already placed, so a step can fill whichever slot is most constrained.
+/// In symmetric mode only row slots exist and every placement is mirrored across the diagonal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Grid {
+    row_dim: usize,
+    col_dim: usize,
+    is_symmetric: bool,
+    is_rotational: bool, // handed back too; placements are not mirrored, so only the box checks it
+    cells: Vec<Option<char>>, // row-major
+    rows: Vec<Option<String>>,
+    cols: Vec<Option<String>>,
+    template: Option<Arc<Template>>,
+    alphabet: Option<Arc<Alphabet>>, // handed back to the box the grid turns into
+}
+
+impl EstimatedSize for Grid {
+    fn estimated_bytes(&self) -> usize {
+        std::mem::size_of::<Grid>() - 2 * std::mem::size_of::<Vec<Option<String>>>()
+            + self.cells.capacity() * std::mem::size_of::<Option<char>>()
+            + self.rows.estimated_bytes()
+            + self.cols.estimated_bytes()
+    }
+}
+
+impl Grid {
+    pub(crate) fn from_word_box(wb: &WordBox) -> Self {
+        let mut grid = Grid {
+            row_dim: wb.row_dim,
+            col_dim: wb.col_dim,
+            is_symmetric: wb.is_symmetric,
+            is_rotational: wb.is_rotational,
+            cells: vec![None; wb.row_dim * wb.col_dim],
+            rows: vec![None; wb.row_dim],
+            cols: vec![None; wb.col_dim],
+            template: wb.template.clone(),
+            alphabet: wb.alphabet.clone(),
+        };
+        if let Some(template) = &wb.template {
+            for i in 0..wb.row_dim {
+                for j in 0..wb.col_dim {
+                    grid.cells[i * wb.col_dim + j] = template.get(i, j);
+                }
+            }
+        }
+        for (i, word) in wb.rows.iter().enumerate() {
+            grid.write(Slot::Row(i), word);
+        }
+        if !wb.is_symmetric {
+            for (i, word) in wb.cols.iter().enumerate() {
+                grid.write(Slot::Col(i), word);
+            }
+        }
+        grid
+    }
+
+    pub(crate) fn into_word_box(self) -> WordBox {
+        let rows: Vec<Arc<str>> = self.rows.into_iter().flatten().map(Arc::from).collect();
+        let cols = if self.is_symmetric {
+            rows.clone()
+        } else {
+            self.cols.into_iter().flatten().map(Arc::from).collect()
+        };
+        WordBox {
+            row_dim: self.row_dim,
+            col_dim: self.col_dim,
+            col_letters: cols.iter().flat_map(|col| col.chars()).collect(),
+            rows,
+            cols,
+            is_symmetric: self.is_symmetric,
+            is_rotational: self.is_rotational,
+            template: self.template,
+            alphabet: self.alphabet,
+        }
+    }
+
+    fn len(&self, slot: Slot) -> usize {
+        match slot {
+            Slot::Row(_) => self.col_dim,
+            Slot::Col(_) => self.row_dim,
+        }
+    }
+
+    fn cell(&self, slot: Slot, k: usize) -> usize {
+        match slot {
+            Slot::Row(i) => i * self.col_dim + k,
+            Slot::Col(j) => k * self.col_dim + j,
+        }
+    }
+
+    /// Slots still waiting for a word
+    pub(crate) fn open_slots(&self) -> Vec<Slot> {
+        let rows = (0..self.row_dim)
+            .filter(|&i| self.rows[i].is_none())
+            .map(Slot::Row);
+        let cols = (0..self.col_dim)
+            .filter(|&j| !self.is_symmetric && self.cols[j].is_none())
+            .map(Slot::Col);
+        rows.chain(cols).collect()
+    }
+
+    /// Number of slots holding a word
+    pub(crate) fn filled_slots(&self) -> usize {
+        self.rows.iter().chain(&self.cols).flatten().count()
+    }
+
+    /// The slot's known letters as a prefix query, with `?` for empty cells before the last known one
+    fn pattern(&self, slot: Slot) -> String {
+        let mut pattern: String = (0..self.len(slot))
+            .map(|k| self.cells[self.cell(slot, k)].unwrap_or(WILDCARD))
+            .collect();
+        let known = pattern.trim_end_matches(WILDCARD).len();
+        pattern.truncate(known);
+        pattern
+    }
+
+    /// The lexicon the words of `slot` come from
+    pub(crate) fn lexicon<L: Lexicons>(slot: Slot, lexicons: &L) -> &L::Lexicon {
+        match slot {
+            Slot::Row(_) => lexicons.rows(),
+            Slot::Col(_) => lexicons.cols(),
+        }
+    }
+
+    pub(crate) fn count_candidates<L: Lexicons>(&self, slot: Slot, lexicons: &L) -> usize {
+        Self::lexicon(slot, lexicons).count_with_prefix(&self.pattern(slot), self.len(slot))
+    }
+
+    /// Whether `word` has one letter per cell of `slot`, each one its cell's mask allows
+    pub(crate) fn fits(&self, slot: Slot, word: &str) -> bool {
+        word.chars().count() == self.len(slot)
+            && self.template.as_ref().is_none_or(|template| {
+                (word.chars().enumerate()).all(|(k, ch)| {
+                    let cell = self.cell(slot, k);
+                    template.allows(cell / self.col_dim, cell % self.col_dim, ch)
+                })
+            })
+    }
+
+    pub(crate) fn candidates<L: Lexicons>(&self, slot: Slot, lexicons: &L) -> Vec<String> {
+        Self::lexicon(slot, lexicons).words_with_prefix(&self.pattern(slot), self.len(slot))
+    }
+
+    /// Product of the candidate counts of every open slot
+    pub(crate) fn open_completions<L: Lexicons>(&self, lexicons: &L) -> f64 {
+        self.open_slots()
+            .into_iter()
+            .map(|slot| self.count_candidates(slot, lexicons) as f64)
+            .product()
+    }
+
+    /// Whether `word` already fills some row or column
+    pub(crate) fn contains_word(&self, word: &str) -> bool {
+        self.rows
+            .iter()
+            .chain(&self.cols)
+            .any(|placed| placed.as_deref() == Some(word))
+    }
+
+    fn write(&mut self, slot: Slot, word: &str) {
+        for (k, ch) in word.chars().enumerate() {
+            let cell = self.cell(slot, k);
+            self.cells[cell] = Some(ch);
+            if self.is_symmetric {
+                let (i, j) = (cell / self.col_dim, cell % self.col_dim);
+                self.cells[j * self.col_dim + i] = Some(ch);
+            }
+        }
+        match slot {
+            Slot::Row(i) => self.rows[i] = Some(word.to_string()),
+            Slot::Col(j) => self.cols[j] = Some(word.to_string()),
+        }
+    }
+
+    /// Place a candidate word of `slot` as the search does: if it fits the slot's masks, and,
+    /// when `distinct_words`, fills no other slot yet, see `place`
+    pub(crate) fn place_candidate<L: Lexicons>(
+        &self,
+        slot: Slot,
+        word: &str,
+        lexicons: &L,
+        distinct_words: bool,
+    ) -> Result<Grid, Refusal> {
+        if !self.fits(slot, word) {
+            return Err(Refusal::Mismatched);
+        }
+        if distinct_words && self.contains_word(word) {
+            return Err(Refusal::Repeated);
+        }
+        self.place(slot, word, lexicons).map_err(Refusal::DeadEnd)
+    }
+
+    /// Place `word` in `slot`, checking that every open slot it crosses can still be completed,
+    /// or return the first that cannot. Crossing slots whose letters are now all known are
+    /// filled with the word they spell.
+    pub(crate) fn place<L: Lexicons>(
+        &self,
+        slot: Slot,
+        word: &str,
+        lexicons: &L,
+    ) -> Result<Grid, Slot> {
+        let mut grid = self.clone();
+        grid.write(slot, word);
+        for open in grid.open_slots() {
+            if grid.count_candidates(open, lexicons) == 0 {
+                return Err(open);
+            }
+            let pattern = grid.pattern(open);
+            if !pattern.contains(WILDCARD) && pattern.chars().count() == grid.len(open) {
+                grid.write(open, &pattern);
+            }
+        }
+        Ok(grid)
+    }
+}
@@ -0,0 +1,115 @@
+use crate::blocks::BLOCK;
+use crate::digraphs::{cell_text, cell_width};
+use crate::terminal::{self, Color};
+use crate::wordbox::WordBox;
+
+/// How a cell of a printed box stands out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CellStyle {
+    #[default]
+    Plain,
+    /// A cell of the seed row, bold cyan
+    Seed,
+    /// A cell of the row placed last, bold yellow
+    Latest,
+    /// A cell of a word picked out for the reader, bold green
+    Word,
+}
+
+impl CellStyle {
+    fn paint(self, text: String) -> String {
+        match self {
+            CellStyle::Plain => text,
+            CellStyle::Seed => terminal::bold(text, Color::Cyan),
+            CellStyle::Latest => terminal::bold(text, Color::Yellow),
+            CellStyle::Word => terminal::bold(text, Color::Green),
+        }
+    }
+}
+
+/// A box together with a style for each of its cells (see `WordBox::cells`), printed the way
+/// the box displays itself
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StyledBox {
+    cells: Vec<Vec<char>>,
+    styles: Vec<Vec<CellStyle>>,
+}
+
+impl StyledBox {
+    /// Every cell plain
+    pub fn plain(wb: &WordBox) -> Self {
+        let cells = wb.cells();
+        let styles = (cells.iter())
+            .map(|row| vec![CellStyle::Plain; row.len()])
+            .collect();
+        StyledBox { cells, styles }
+    }
+
+    /// The seed row, the first placed, and the row placed last (if it is another) styled apart
+    /// from the rest of the grid, to follow where a search is
+    pub fn live(wb: &WordBox) -> Self {
+        let mut styled = Self::plain(wb);
+        let placed = wb.rows.len();
+        if placed > 1 {
+            styled.styles[placed - 1].fill(CellStyle::Latest);
+        }
+        if placed > 0 {
+            styled.styles[0].fill(CellStyle::Seed);
+        }
+        styled
+    }
+
+    /// The cells of the words across and down that `picked` picks out styled as words, every
+    /// other cell plain; a word is a run of cells between blocks
+    pub fn words(wb: &WordBox, picked: impl Fn(&str) -> bool) -> Self {
+        let mut styled = Self::plain(wb);
+        let (rows, cols) = (styled.cells.len(), styled.cells.first().map_or(0, Vec::len));
+        let across = (0..rows).map(|i| (0..cols).map(|j| (i, j)).collect::<Vec<_>>());
+        let down = (0..cols).map(|j| (0..rows).map(|i| (i, j)).collect::<Vec<_>>());
+        for line in across.chain(down) {
+            for run in line.split(|&(i, j)| styled.cells[i][j] == BLOCK) {
+                let word: String = run.iter().map(|&(i, j)| styled.cells[i][j]).collect();
+                if !word.is_empty() && picked(&word) {
+                    for &(i, j) in run {
+                        styled.styles[i][j] = CellStyle::Word;
+                    }
+                }
+            }
+        }
+        styled
+    }
+
+    /// The style of the cell at `row` and `col`
+    pub fn style(&self, row: usize, col: usize) -> CellStyle {
+        self.styles[row][col]
+    }
+
+    /// The box as `WordBox` displays it, one line per row, with each cell in its style if
+    /// `styled`; plain output (e.g. to a file or a pipe) leaves the styles out
+    pub fn render(&self, styled: bool) -> String {
+        let width = self.cells.iter().flatten().map(|&ch| cell_width(ch)).max();
+        let width = width.unwrap_or(1);
+        let mut out = String::new();
+        for (cells, styles) in self.cells.iter().zip(&self.styles) {
+            let last = cells.len().saturating_sub(1);
+            let texts: Vec<String> = (cells.iter().zip(styles).enumerate())
+                .map(|(j, (&ch, &style))| {
+                    // Multi-letter tiles widen every cell to the widest one, as in `Display`
+                    let text = match width {
+                        1 => ch.to_string(),
+                        _ if j == last => cell_text(ch),
+                        _ => format!("{:<width$}", cell_text(ch)),
+                    };
+                    match styled {
+                        true => style.paint(text),
+                        false => text,
+                    }
+                })
+                .collect();
+            let separator = if width > 1 { " " } else { "" };
+            out += &texts.join(separator);
+            out.push('\n');
+        }
+        out
+    }
+}
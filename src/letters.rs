@@ -0,0 +1,498 @@
+use crate::blocks::BLOCK;
+use crate::digraphs::Tile;
+use crate::lexicon::{Lexicon, WILDCARD};
+use crate::masks::VOWELS;
+use crate::wordbox::WordBox;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt::Display;
+
+/// Letters every solution has to contain, each at least as many times as it is listed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequiredLetters {
+    counts: BTreeMap<char, usize>,
+}
+
+/// The letters of `s`, lowercased, or an error naming the first character that is not a letter
+fn parse_letters(s: &str) -> Result<Vec<char>, String> {
+    let mut letters = vec![];
+    for ch in s.chars() {
+        if !ch.is_alphabetic() {
+            return Err(format!(
+                "unsupported character {:?} (only letters are allowed)",
+                ch
+            ));
+        }
+        letters.extend(ch.to_lowercase());
+    }
+    Ok(letters)
+}
+
+impl std::str::FromStr for RequiredLetters {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut counts = BTreeMap::new();
+        for ch in parse_letters(s)? {
+            *counts.entry(ch).or_default() += 1;
+        }
+        Ok(RequiredLetters { counts })
+    }
+}
+
+impl Display for RequiredLetters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (&ch, &count) in &self.counts {
+            for _ in 0..count {
+                write!(f, "{}", ch)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl RequiredLetters {
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// How many times `letter` is required
+    pub fn count(&self, letter: char) -> usize {
+        self.counts.get(&letter).copied().unwrap_or(0)
+    }
+
+    /// Each required letter once, in alphabetical order
+    pub fn letters(&self) -> impl Iterator<Item = char> + '_ {
+        self.counts.keys().copied()
+    }
+
+    /// The cells of a complete box holding each required letter, row by row
+    pub fn cells(&self, wb: &WordBox) -> BTreeMap<char, Vec<(usize, usize)>> {
+        let mut cells: BTreeMap<char, Vec<(usize, usize)>> = BTreeMap::new();
+        for (i, row) in wb.rows.iter().enumerate() {
+            for (j, ch) in row.chars().enumerate() {
+                if self.counts.contains_key(&ch) {
+                    cells.entry(ch).or_default().push((i, j));
+                }
+            }
+        }
+        cells
+    }
+
+    /// How many times each required letter is still missing from the rows placed so far
+    fn missing(&self, wb: &WordBox) -> Vec<(char, usize)> {
+        let mut placed: BTreeMap<char, usize> = BTreeMap::new();
+        for ch in wb.rows.iter().flat_map(|row| row.chars()) {
+            *placed.entry(ch).or_default() += 1;
+        }
+        self.counts
+            .iter()
+            .filter_map(|(&ch, &needed)| {
+                let have = placed.get(&ch).copied().unwrap_or(0);
+                (needed > have).then_some((ch, needed - have))
+            })
+            .collect()
+    }
+
+    /// Whether a complete box holds every required letter often enough
+    pub(crate) fn is_satisfied(&self, wb: &WordBox) -> bool {
+        self.missing(wb).is_empty()
+    }
+
+    /// The first missing letter the rows still to be placed could not supply, if there is one.
+    /// Each column can give at most as many of a letter as the best of its remaining completions
+    /// holds, so a branch is abandoned once those maxima together fall short; when the open
+    /// cells could not hold every missing letter, the first one missing is blamed.
+    pub(crate) fn unplaceable<L: Lexicon>(&self, wb: &WordBox, lexicon: &L) -> Option<char> {
+        let missing = self.missing(wb);
+        let &(first, _) = missing.first()?;
+        let placed = wb.rows.len();
+        let open_cells = (wb.row_dim - placed) * wb.col_dim;
+        if missing.iter().map(|&(_, count)| count).sum::<usize>() > open_cells {
+            return Some(first);
+        }
+
+        // The cells each column could still end up with below the rows placed
+        let completions: Vec<Vec<String>> = (0..wb.col_dim)
+            .map(|i| {
+                let line = wb.column_line(i);
+                if line.contains(BLOCK) {
+                    // Every open cell could hold any letter as far as this bound is concerned
+                    vec![line.chars().skip(placed).collect()]
+                } else {
+                    let pattern = line.trim_end_matches(WILDCARD);
+                    (lexicon.ids_with_prefix(pattern, wb.row_dim).into_iter())
+                        .map(|id| lexicon.word(id, wb.row_dim).chars().skip(placed).collect())
+                        .collect()
+                }
+            })
+            .collect();
+        missing.iter().find_map(|&(ch, needed)| {
+            let supply: usize = completions
+                .iter()
+                .map(|tails| {
+                    tails
+                        .iter()
+                        .map(|tail| tail.chars().filter(|&c| c == ch || c == WILDCARD).count())
+                        .max()
+                        .unwrap_or(0)
+                })
+                .sum();
+            (supply < needed).then_some(ch)
+        })
+    }
+}
+
+/// How many cells of a box a letter may fill: at least `min`, and at most `max` if there is one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LetterBound {
+    pub min: usize,
+    pub max: Option<usize>,
+}
+
+impl Display for LetterBound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.min, self.max) {
+            (min, Some(max)) if min == max => write!(f, "={}", min),
+            (0, Some(max)) => write!(f, "<={}", max),
+            (min, Some(max)) => write!(f, "={}..{}", min, max),
+            (min, None) => write!(f, ">={}", min),
+        }
+    }
+}
+
+/// Bounds on how often letters appear across a box's cells, e.g. `q<=1,e=2..4`: at most one q,
+/// and from two to four e's. A letter can be bounded with `<=N`, `>=N`, `=N` or `=A..B`, and
+/// bounds given for the same letter twice must all hold.
+///
+/// Caps are checked as the search goes, against the cells already settled, and minimums like
+/// required letters: a branch is abandoned once its open cells could no longer make up the
+/// shortfall.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LetterBounds {
+    bounds: BTreeMap<char, LetterBound>,
+    minimums: RequiredLetters, // each letter as many times as its minimum
+}
+
+impl std::str::FromStr for LetterBounds {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bounds: BTreeMap<char, LetterBound> = BTreeMap::new();
+        for item in s.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let expected = || {
+                format!(
+                    "expected a bound such as q<=1, e>=2, e=2 or e=2..4, found {:?}",
+                    item
+                )
+            };
+            let op = item.find(['<', '>', '=']).ok_or_else(expected)?;
+            let letter = match parse_letters(&item[..op])?.as_slice() {
+                &[letter] => letter,
+                _ => return Err(expected()),
+            };
+            let count = |n: &str| n.trim().parse::<usize>().map_err(|_| expected());
+            let rest = &item[op..];
+            let bound = if let Some(max) = rest.strip_prefix("<=") {
+                LetterBound {
+                    min: 0,
+                    max: Some(count(max)?),
+                }
+            } else if let Some(min) = rest.strip_prefix(">=") {
+                LetterBound {
+                    min: count(min)?,
+                    max: None,
+                }
+            } else if let Some(range) = rest.strip_prefix('=') {
+                let (min, max) = match range.split_once("..=").or_else(|| range.split_once("..")) {
+                    Some((min, max)) => (count(min)?, count(max)?),
+                    None => (count(range)?, count(range)?),
+                };
+                if min > max {
+                    return Err(format!(
+                        "{:?} asks for at least {} {:?} but at most {}",
+                        item, min, letter, max
+                    ));
+                }
+                LetterBound {
+                    min,
+                    max: Some(max),
+                }
+            } else {
+                return Err(expected());
+            };
+            let merged = match bounds.get(&letter) {
+                Some(old) => LetterBound {
+                    min: old.min.max(bound.min),
+                    max: match (old.max, bound.max) {
+                        (Some(a), Some(b)) => Some(a.min(b)),
+                        (a, b) => a.or(b),
+                    },
+                },
+                None => bound,
+            };
+            if let Some(max) = merged.max.filter(|&max| merged.min > max) {
+                return Err(format!(
+                    "the bounds on {:?} conflict: at least {} but at most {}",
+                    letter, merged.min, max
+                ));
+            }
+            bounds.insert(letter, merged);
+        }
+        let minimums = RequiredLetters {
+            counts: (bounds.iter())
+                .filter(|(_, bound)| bound.min > 0)
+                .map(|(&letter, bound)| (letter, bound.min))
+                .collect(),
+        };
+        Ok(LetterBounds { bounds, minimums })
+    }
+}
+
+impl Display for LetterBounds {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bounds: Vec<String> = (self.bounds.iter())
+            .map(|(letter, bound)| format!("{}{}", letter, bound))
+            .collect();
+        write!(f, "{}", bounds.join(","))
+    }
+}
+
+impl LetterBounds {
+    pub fn is_empty(&self) -> bool {
+        self.bounds.is_empty()
+    }
+
+    /// Each bounded letter with its bound, in alphabetical order
+    pub fn bounds(&self) -> impl Iterator<Item = (char, LetterBound)> + '_ {
+        self.bounds.iter().map(|(&letter, &bound)| (letter, bound))
+    }
+
+    /// How many cells of a complete box hold each bounded letter
+    pub fn counts(&self, wb: &WordBox) -> BTreeMap<char, usize> {
+        let counts = wb.settled_letter_counts();
+        (self.bounds.keys())
+            .map(|&letter| (letter, counts.get(&letter).copied().unwrap_or(0)))
+            .collect()
+    }
+
+    /// Whether every bounded letter fills an allowed number of cells of a complete box
+    pub(crate) fn is_satisfied(&self, wb: &WordBox) -> bool {
+        self.minimums.is_satisfied(wb) && self.over_cap(wb).is_none()
+    }
+
+    /// The first letter the cells already settled hold more often than its cap allows
+    pub(crate) fn over_cap(&self, wb: &WordBox) -> Option<char> {
+        if self.bounds.values().all(|bound| bound.max.is_none()) {
+            return None;
+        }
+        let counts = wb.settled_letter_counts();
+        (self.bounds.iter()).find_map(|(&letter, bound)| {
+            let count = counts.get(&letter).copied().unwrap_or(0);
+            bound.max.is_some_and(|max| count > max).then_some(letter)
+        })
+    }
+
+    /// The first letter short of its minimum that the rows still to be placed could not make
+    /// up for (see `RequiredLetters::unplaceable`)
+    pub(crate) fn unreachable<L: Lexicon>(&self, wb: &WordBox, lexicon: &L) -> Option<char> {
+        self.minimums.unplaceable(wb, lexicon)
+    }
+}
+
+/// Letters no solution may contain; words holding any of them are dropped from the word list
+/// before the lexicon is built, so the solver never sees them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExcludedLetters {
+    letters: BTreeSet<char>,
+}
+
+impl std::str::FromStr for ExcludedLetters {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let letters = parse_letters(s)?.into_iter().collect();
+        Ok(ExcludedLetters { letters })
+    }
+}
+
+impl Display for ExcludedLetters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for ch in &self.letters {
+            write!(f, "{}", ch)?;
+        }
+        Ok(())
+    }
+}
+
+impl ExcludedLetters {
+    pub fn is_empty(&self) -> bool {
+        self.letters.is_empty()
+    }
+
+    pub fn contains(&self, ch: char) -> bool {
+        self.letters.contains(&ch)
+    }
+
+    /// The letters both required and excluded, which no box can satisfy
+    pub fn conflicts<'a>(
+        &'a self,
+        required: &'a RequiredLetters,
+    ) -> impl Iterator<Item = char> + 'a {
+        required.letters().filter(|&ch| self.contains(ch))
+    }
+
+    /// The words without any excluded letter, along with the number of words removed
+    pub fn remove_from(&self, words: Vec<String>) -> (Vec<String>, usize) {
+        let total = words.len();
+        let kept: Vec<String> = words
+            .into_iter()
+            .filter(|word| !word.chars().any(|ch| self.contains(ch)))
+            .collect();
+        let removed = total - kept.len();
+        (kept, removed)
+    }
+}
+
+/// The letters counted as vowels by `VowelRule`, lowercased; English `aeiou` by default
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vowels {
+    letters: BTreeSet<char>,
+}
+
+impl Default for Vowels {
+    fn default() -> Self {
+        Vowels {
+            letters: VOWELS.chars().collect(),
+        }
+    }
+}
+
+impl std::str::FromStr for Vowels {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let letters: BTreeSet<char> = parse_letters(s)?.into_iter().collect();
+        if letters.is_empty() {
+            return Err("no vowels given".to_string());
+        }
+        Ok(Vowels { letters })
+    }
+}
+
+impl Display for Vowels {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for ch in &self.letters {
+            write!(f, "{}", ch)?;
+        }
+        Ok(())
+    }
+}
+
+impl Vowels {
+    /// Whether a cell holds a vowel; a multi-letter tile does if any of its letters is one
+    pub fn contains(&self, cell: char) -> bool {
+        self.letters.contains(&cell)
+            || Tile::of(cell)
+                .is_some_and(|tile| tile.as_str().chars().any(|ch| self.letters.contains(&ch)))
+    }
+}
+
+/// How vowels have to be spread over a box's lines, its rows and its columns, so that people
+/// can solve it: each line holding a vowel, and no line running through more consonants in a
+/// row than allowed. Blocked cells end a run of consonants; a line of blocked cells only needs
+/// no vowel.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VowelRule {
+    pub vowels: Vowels,
+    pub per_line: bool,
+    pub max_consonant_run: Option<usize>,
+}
+
+/// What a line's cells so far say about its vowels
+struct LineVowels {
+    has_letters: bool,
+    has_vowel: bool,
+    longest_run: usize,  // consonants in a row anywhere so far
+    trailing_run: usize, // consonants in a row at the end
+}
+
+impl VowelRule {
+    pub fn is_active(&self) -> bool {
+        self.per_line || self.max_consonant_run.is_some()
+    }
+
+    fn scan(&self, cells: impl IntoIterator<Item = char>) -> LineVowels {
+        let mut line = LineVowels {
+            has_letters: false,
+            has_vowel: false,
+            longest_run: 0,
+            trailing_run: 0,
+        };
+        for cell in cells {
+            if cell == BLOCK {
+                line.trailing_run = 0;
+            } else if self.vowels.contains(cell) {
+                line.has_letters = true;
+                line.has_vowel = true;
+                line.trailing_run = 0;
+            } else {
+                line.has_letters = true;
+                line.trailing_run += 1;
+                line.longest_run = line.longest_run.max(line.trailing_run);
+            }
+        }
+        line
+    }
+
+    fn runs_ok(&self, line: &LineVowels) -> bool {
+        self.max_consonant_run
+            .is_none_or(|max| line.longest_run <= max)
+    }
+
+    /// Whether a finished line keeps to the rule
+    fn line_ok(&self, cells: impl IntoIterator<Item = char>) -> bool {
+        let line = self.scan(cells);
+        self.runs_ok(&line) && (!self.per_line || line.has_vowel || !line.has_letters)
+    }
+
+    /// Whether every row and column of a complete box keeps to the rule
+    pub(crate) fn is_satisfied(&self, wb: &WordBox) -> bool {
+        !self.is_active()
+            || ((wb.rows.iter()).all(|row| self.line_ok(row.chars()))
+                && (0..wb.col_dim).all(|i| self.line_ok(wb.column_prefix(i).iter().copied())))
+    }
+
+    /// Whether a partial box can still keep to the rule: the rows placed so far keep to it, and
+    /// no column has already run through too many consonants
+    pub(crate) fn may_be_satisfied(&self, wb: &WordBox) -> bool {
+        !self.is_active()
+            || ((wb.rows.iter()).all(|row| self.line_ok(row.chars()))
+                && (0..wb.col_dim)
+                    .all(|i| self.runs_ok(&self.scan(wb.column_prefix(i).iter().copied()))))
+    }
+
+    /// Narrow forward checking's letter sets for the next row to vowels in the columns that
+    /// need one there: those at the end of their consonant run, and under `per_line` those with
+    /// no vowel yet and only the next row left; `None` once a set has no letter left.
+    pub(crate) fn restrict(
+        &self,
+        wb: &WordBox,
+        mut letters: Vec<HashSet<char>>,
+    ) -> Option<Vec<HashSet<char>>> {
+        if !self.is_active() {
+            return Some(letters);
+        }
+        let last_row = wb.rows.len() + 1 == wb.row_dim;
+        for (i, set) in letters.iter_mut().enumerate() {
+            let line = self.scan(wb.column_prefix(i).iter().copied());
+            let run_ends = (self.max_consonant_run).is_some_and(|max| line.trailing_run >= max);
+            if run_ends || (self.per_line && last_row && !line.has_vowel) {
+                set.retain(|&ch| self.vowels.contains(ch));
+                if set.is_empty() {
+                    return None;
+                }
+            }
+        }
+        Some(letters)
+    }
+}
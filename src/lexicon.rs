@@ -0,0 +1,1231 @@
+use crate::alphabet::Alphabet;
+use crate::parallel::{self, prelude::*};
+use crate::tags::WordTags;
+use crate::tiers::{TieredLexicon, WordTiers};
+use rand::{Rng, RngCore};
+use std::cell::Cell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::hash::Hash;
+
+pub mod conformance;
+
+/// Length of a word in grid cells, i.e. Unicode scalar values rather than bytes
+pub fn word_length(word: &str) -> usize {
+    if word.is_ascii() {
+        word.len()
+    } else {
+        word.chars().count()
+    }
+}
+
+/// Byte offsets of every prefix of `word` that ends on a char boundary, including "" and the word itself
+fn prefix_ends(word: &str) -> impl Iterator<Item = usize> + '_ {
+    word.char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(word.len()))
+}
+
+/// Words given to each thread when an index is built in parallel
+const CHUNK_WORDS: usize = 1 << 14;
+
+/// The words of each length in `lengths`, in the order they were given. The words are taken a
+/// chunk at a time, so no more than a chunk of words of other lengths is ever held; the lengths
+/// of each chunk are measured in parallel, but its words are sorted into place in a single pass
+/// to keep their order.
+fn group_by_length(
+    words: impl IntoIterator<Item = String>,
+    lengths: &BTreeSet<usize>,
+) -> BTreeMap<usize, Vec<String>> {
+    let mut words = words.into_iter();
+    let mut by_length: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+    loop {
+        let chunk: Vec<String> = words.by_ref().take(CHUNK_WORDS).collect();
+        if chunk.is_empty() {
+            return by_length;
+        }
+        let word_lengths: Vec<usize> = chunk.par_iter().map(|word| word_length(word)).collect();
+        for (word, len) in chunk.into_iter().zip(word_lengths) {
+            if lengths.contains(&len) {
+                by_length.entry(len).or_default().push(word);
+            }
+        }
+    }
+}
+
+/// Build an index over each chunk of `words` in parallel, then merge them chunk by chunk so that
+/// every bucket lists its ids in the same order as an index built in one pass. `build` is given
+/// each chunk along with the id of its first word.
+fn index_in_chunks<K: Eq + Hash + Send>(
+    words: &[String],
+    build: impl Fn(WordId, &[String]) -> HashMap<K, Vec<WordId>> + Sync,
+) -> HashMap<K, Vec<WordId>> {
+    let mut chunks: Vec<HashMap<K, Vec<WordId>>> = (words.par_chunks(CHUNK_WORDS).enumerate())
+        .map(|(i, chunk)| build((i * CHUNK_WORDS) as WordId, chunk))
+        .collect();
+    if chunks.len() <= 1 {
+        return chunks.pop().unwrap_or_default();
+    }
+    let mut merged: HashMap<K, Vec<WordId>> = HashMap::new();
+    for chunk in chunks {
+        for (key, ids) in chunk {
+            merged.entry(key).or_default().extend(ids);
+        }
+    }
+    merged
+}
+
+thread_local! {
+    static QUERIES: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Number of queries made so far by this thread to any lexicon, so that a search can tell how
+/// many it issued. Both implementations count each prefix and suffix query once.
+pub fn queries_on_this_thread() -> usize {
+    QUERIES.with(Cell::get)
+}
+
+fn count_query() {
+    QUERIES.with(|queries| queries.set(queries.get() + 1));
+}
+
+/// Matches any single letter in a prefix query
+pub const WILDCARD: char = '?';
+
+/// Longest prefix `HashMapLexicon` keeps a bucket for unless told otherwise
+pub const DEFAULT_PREFIX_DEPTH: usize = 3;
+
+/// Tuning for the lexicon implementations that have any; the others ignore it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LexiconOptions {
+    /// Longest prefix `HashMapLexicon` keeps a bucket for. Every word's id is stored once per
+    /// bucket, so memory grows with the depth; queries for longer prefixes filter the bucket of their
+    /// first `max_prefix_depth` letters instead.
+    pub max_prefix_depth: usize,
+}
+
+impl Default for LexiconOptions {
+    fn default() -> Self {
+        LexiconOptions {
+            max_prefix_depth: DEFAULT_PREFIX_DEPTH,
+        }
+    }
+}
+
+/// The first `depth` letters of `key`, or all of it if it is shorter
+fn truncate_letters(key: &str, depth: usize) -> &str {
+    prefix_ends(key).nth(depth).map_or(key, |end| &key[..end])
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrefixError {
+    UnsupportedCharacter { ch: char, position: usize },
+}
+
+impl Display for PrefixError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PrefixError::UnsupportedCharacter { ch, position } => write!(
+                f,
+                "unsupported character {:?} at position {} (only letters of the alphabet and '{}' are allowed)",
+                ch, position, WILDCARD
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PrefixError {}
+
+/// Validate a user-supplied prefix, rejecting every character other than the wildcard and the
+/// letters of `alphabet`
+pub fn parse_prefix(prefix: &str, alphabet: &Alphabet) -> Result<String, PrefixError> {
+    for (position, ch) in prefix.chars().enumerate() {
+        if ch != WILDCARD && !alphabet.contains(ch) {
+            return Err(PrefixError::UnsupportedCharacter { ch, position });
+        }
+    }
+    Ok(prefix.to_string())
+}
+
+/// Whether `word` starts with `prefix`, where each `?` in the prefix matches any letter
+pub fn matches_prefix(word: &str, prefix: &str) -> bool {
+    if !prefix.contains(WILDCARD) {
+        return word.starts_with(prefix);
+    }
+    let mut letters = word.chars();
+    prefix
+        .chars()
+        .all(|p| letters.next().is_some_and(|ch| p == WILDCARD || p == ch))
+}
+
+/// Whether `word` ends with `suffix`, where each `?` in the suffix matches any letter
+pub fn matches_suffix(word: &str, suffix: &str) -> bool {
+    if !suffix.contains(WILDCARD) {
+        return word.ends_with(suffix);
+    }
+    let mut letters = word.chars().rev();
+    suffix
+        .chars()
+        .rev()
+        .all(|p| letters.next().is_some_and(|ch| p == WILDCARD || p == ch))
+}
+
+/// Pick a uniformly random item by counting first and then walking to it, without collecting
+fn sample_matching<'a>(
+    mut matching: impl Iterator<Item = &'a String> + Clone,
+    rng: &mut dyn RngCore,
+) -> Option<String> {
+    let count = matching.clone().count();
+    if count == 0 {
+        return None;
+    }
+    matching.nth(rng.gen_range(0..count)).cloned()
+}
+
+/// Bytes held by a vector of words, counting both the vector and each string's buffer
+fn words_memory(words: &Vec<String>) -> usize {
+    words.capacity() * std::mem::size_of::<String>()
+        + words.iter().map(|word| word.capacity()).sum::<usize>()
+}
+
+/// The letter at `position` of a word known to be `len` letters long. A word with as many bytes
+/// as letters is ASCII, so its bytes are indexed directly instead of walking its chars.
+pub(crate) fn letter_at(word: &str, position: usize, len: usize) -> Option<char> {
+    if word.len() == len {
+        word.as_bytes().get(position).map(|&b| b as char)
+    } else {
+        word.chars().nth(position)
+    }
+}
+
+/// The distinct letters found at `position` in `words`, all `len` letters long
+fn letters_at<'a>(
+    words: impl Iterator<Item = &'a String>,
+    position: usize,
+    len: usize,
+) -> HashSet<char> {
+    words
+        .filter_map(|word| letter_at(word, position, len))
+        .collect()
+}
+
+/// A word's place among the words of its length in a lexicon, so that a search can carry and
+/// compare a `u32` where it would otherwise copy the word. Ids are stable for as long as the
+/// lexicon lives, and run from 0 to `Lexicon::word_count` for each length.
+pub type WordId = u32;
+
+/// The queries the searches ask of a word list. Every method takes `&self` and plain
+/// arguments, so a lexicon can be used as a `dyn Lexicon` (see `DynLexicon`) as well as through
+/// generics; building one is left to `BuildLexicon`.
+pub trait Lexicon {
+    /// Get a list of words of the given length matching the prefix; `?` matches any letter.
+    /// Lengths and prefixes count letters rather than bytes, so a word like "café" has four
+    /// letters and matches "caf?" just as "cafe" does.
+    fn words_with_prefix(&self, prefix: &str, word_len: usize) -> Vec<String>;
+
+    /// The ids of the words `words_with_prefix` would return, in the same order, without
+    /// copying the words; `word` turns each back into its word
+    fn ids_with_prefix(&self, prefix: &str, word_len: usize) -> Vec<WordId>;
+
+    /// The word of the given length with the id `id`, which must come from this lexicon
+    fn word(&self, id: WordId, word_len: usize) -> &str;
+
+    /// Number of words of the given length, each counted once per time it was given
+    fn word_count(&self, word_len: usize) -> usize;
+
+    /// Number of words `words_with_prefix` would return, without collecting them
+    fn count_with_prefix(&self, prefix: &str, word_len: usize) -> usize {
+        self.words_with_prefix(prefix, word_len).len()
+    }
+
+    /// Whether any word of the given length matches the prefix, as soon as one is found
+    fn has_prefix(&self, prefix: &str, word_len: usize) -> bool {
+        self.count_with_prefix(prefix, word_len) > 0
+    }
+
+    /// The letters that can follow `prefix` in words of the given length
+    fn letters_after(&self, prefix: &str, word_len: usize) -> HashSet<char> {
+        let position = word_length(prefix);
+        self.words_with_prefix(prefix, word_len)
+            .iter()
+            .filter_map(|word| word.chars().nth(position))
+            .collect()
+    }
+
+    /// Get a list of words of the given length ending with the suffix; `?` matches any letter
+    fn words_with_suffix(&self, suffix: &str, word_len: usize) -> Vec<String>;
+
+    /// Rough number of bytes held by the lexicon's index, including the words themselves
+    fn memory_estimate(&self) -> usize;
+
+    /// Pick one word uniformly at random among those `words_with_prefix` would return
+    fn sample_with_prefix(
+        &self,
+        prefix: &str,
+        word_len: usize,
+        rng: &mut dyn RngCore,
+    ) -> Option<String> {
+        let words = self.words_with_prefix(prefix, word_len);
+        if words.is_empty() {
+            return None;
+        }
+        let i = rng.gen_range(0..words.len());
+        Some(words[i].clone())
+    }
+
+    /// Number of tiers the words are split into; a lexicon without tiers has one
+    fn tiers(&self) -> usize {
+        1
+    }
+
+    /// The tier `word` belongs to, counted from 0 for the most preferred
+    fn tier(&self, _word: &str) -> usize {
+        0
+    }
+}
+
+/// How a lexicon of a known type is built from a word list. Kept apart from `Lexicon`, whose
+/// methods all work on a `dyn Lexicon`; to build one whose type is only chosen at runtime, see
+/// `LexiconKind::build`.
+pub trait BuildLexicon: Lexicon + Sized {
+    /// The lexicon of the words of `lengths` among `words`, tuned by `options` for
+    /// implementations that take any. The words are sorted by length as they arrive and the
+    /// others dropped straight away, so they can be streamed from a reader (see
+    /// `loader::stream_words`) rather than collected first.
+    fn from_words(
+        words: impl IntoIterator<Item = String>,
+        lengths: BTreeSet<usize>,
+        options: &LexiconOptions,
+    ) -> Self;
+
+    /// Like `from_words`, over words read from a source that can fail, such as the lines of a
+    /// file; the first error ends the reading and is returned in place of the lexicon
+    fn try_from_words<E>(
+        words: impl IntoIterator<Item = Result<String, E>>,
+        lengths: BTreeSet<usize>,
+        options: &LexiconOptions,
+    ) -> Result<Self, E> {
+        let mut error = None;
+        let words = words.into_iter().map_while(|word| match word {
+            Ok(word) => Some(word),
+            Err(err) => {
+                error = Some(err);
+                None
+            }
+        });
+        let lexicon = Self::from_words(words, lengths, options);
+        match error {
+            Some(err) => Err(err),
+            None => Ok(lexicon),
+        }
+    }
+
+    /// `from_words` over a word list already in memory, with the default options
+    fn initialize(words: Vec<String>, lengths: BTreeSet<usize>) -> Self {
+        Self::from_words(words, lengths, &LexiconOptions::default())
+    }
+
+    /// `from_words` over a word list already in memory
+    fn initialize_with_options(
+        words: Vec<String>,
+        lengths: BTreeSet<usize>,
+        options: &LexiconOptions,
+    ) -> Self {
+        Self::from_words(words, lengths, options)
+    }
+
+    /// Like `initialize_with_options`, sorting the words into the tiers given for
+    /// implementations that keep tiers (see `TieredLexicon`); the others ignore them
+    fn initialize_with_tiers(
+        words: Vec<String>,
+        lengths: BTreeSet<usize>,
+        _tiers: &WordTiers,
+        options: &LexiconOptions,
+    ) -> Self {
+        Self::initialize_with_options(words, lengths, options)
+    }
+
+    /// Initialize the lexicon without any word carrying one of the `blocked` tags,
+    /// returning the number of words removed per tag alongside it
+    fn initialize_with_tags(
+        words: Vec<String>,
+        lengths: BTreeSet<usize>,
+        tags: &WordTags,
+        blocked: &[String],
+        options: &LexiconOptions,
+    ) -> (Self, BTreeMap<String, usize>) {
+        let (kept, removed) = tags.remove_blocked(words, blocked);
+        (
+            Self::initialize_with_options(kept, lengths, options),
+            removed,
+        )
+    }
+}
+
+/// A lexicon whose type is chosen at runtime, e.g. from `--lexicon`. It answers every query by
+/// dynamic dispatch, so the searches are compiled once for it rather than once per type.
+pub type DynLexicon = Box<dyn Lexicon + Send + Sync>;
+
+impl<L: Lexicon + ?Sized> Lexicon for Box<L> {
+    fn words_with_prefix(&self, prefix: &str, word_len: usize) -> Vec<String> {
+        (**self).words_with_prefix(prefix, word_len)
+    }
+
+    fn ids_with_prefix(&self, prefix: &str, word_len: usize) -> Vec<WordId> {
+        (**self).ids_with_prefix(prefix, word_len)
+    }
+
+    fn word(&self, id: WordId, word_len: usize) -> &str {
+        (**self).word(id, word_len)
+    }
+
+    fn word_count(&self, word_len: usize) -> usize {
+        (**self).word_count(word_len)
+    }
+
+    fn count_with_prefix(&self, prefix: &str, word_len: usize) -> usize {
+        (**self).count_with_prefix(prefix, word_len)
+    }
+
+    fn has_prefix(&self, prefix: &str, word_len: usize) -> bool {
+        (**self).has_prefix(prefix, word_len)
+    }
+
+    fn letters_after(&self, prefix: &str, word_len: usize) -> HashSet<char> {
+        (**self).letters_after(prefix, word_len)
+    }
+
+    fn words_with_suffix(&self, suffix: &str, word_len: usize) -> Vec<String> {
+        (**self).words_with_suffix(suffix, word_len)
+    }
+
+    fn memory_estimate(&self) -> usize {
+        (**self).memory_estimate()
+    }
+
+    fn sample_with_prefix(
+        &self,
+        prefix: &str,
+        word_len: usize,
+        rng: &mut dyn RngCore,
+    ) -> Option<String> {
+        (**self).sample_with_prefix(prefix, word_len, rng)
+    }
+
+    fn tiers(&self) -> usize {
+        (**self).tiers()
+    }
+
+    fn tier(&self, word: &str) -> usize {
+        (**self).tier(word)
+    }
+}
+
+/// The word lengths a box needs across and down, which the lexicon of its rows and the lexicon
+/// of its columns are built for. They only differ in a rectangle: the rows of a 3x4 box are
+/// 4-letter words and its columns 3-letter ones.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WordLengths {
+    pub rows: BTreeSet<usize>, // the lengths of the words across
+    pub cols: BTreeSet<usize>, // the lengths of the words down
+}
+
+impl WordLengths {
+    /// The lengths of a `row_dim` by `col_dim` box without blocks: its rows are as long as it is
+    /// wide and its columns as it is tall
+    pub fn of_box(row_dim: usize, col_dim: usize) -> Self {
+        WordLengths {
+            rows: BTreeSet::from([col_dim]),
+            cols: BTreeSet::from([row_dim]),
+        }
+    }
+
+    /// Every length either way, for one lexicon serving both the rows and the columns
+    pub fn all(&self) -> BTreeSet<usize> {
+        self.rows.union(&self.cols).copied().collect()
+    }
+}
+
+/// Where the words of a box come from: the lexicon its rows are drawn from and the one its
+/// columns are checked against. Every lexicon is a pair of itself, so one word list can be
+/// passed wherever a pair is expected.
+pub trait Lexicons {
+    type Lexicon: Lexicon;
+
+    fn rows(&self) -> &Self::Lexicon;
+
+    fn cols(&self) -> &Self::Lexicon;
+}
+
+impl<L: Lexicon> Lexicons for L {
+    type Lexicon = L;
+
+    fn rows(&self) -> &L {
+        self
+    }
+
+    fn cols(&self) -> &L {
+        self
+    }
+}
+
+/// Rows drawn from one lexicon and columns checked against another, e.g. for boxes whose rows
+/// are English and whose columns are French. Only asymmetric boxes can tell the two apart.
+#[derive(Debug, Clone, Copy)]
+pub struct RowColumnLexicons<'a, L> {
+    pub rows: &'a L,
+    pub cols: &'a L,
+}
+
+impl<L: Lexicon> Lexicons for RowColumnLexicons<'_, L> {
+    type Lexicon = L;
+
+    fn rows(&self) -> &L {
+        self.rows
+    }
+
+    fn cols(&self) -> &L {
+        self.cols
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VecLexicon {
+    words: BTreeMap<usize, Vec<String>>, // the words of each length, sorted
+}
+
+impl VecLexicon {
+    /// The sorted words of a length, or none
+    fn of_length(&self, word_len: usize) -> &[String] {
+        self.words.get(&word_len).map_or(&[], Vec::as_slice)
+    }
+
+    /// The words matching `prefix`, each with its id: its place among the sorted words
+    fn matching<'a>(
+        &'a self,
+        prefix: &'a str,
+        word_len: usize,
+    ) -> impl Iterator<Item = (WordId, &'a String)> + Clone + 'a {
+        count_query();
+        // Words sharing the literal part of the prefix are contiguous once sorted, so two
+        // binary searches find them; only the letters after a wildcard still need checking
+        let literal_len = prefix.find(WILDCARD).unwrap_or(prefix.len());
+        let literal = &prefix[..literal_len];
+        let words = self.of_length(word_len);
+        let start = words.partition_point(|word| word.as_str() < literal);
+        let end = start + words[start..].partition_point(|word| word.starts_with(literal));
+        (start..end)
+            .zip(&words[start..end])
+            .map(|(id, word)| (id as WordId, word))
+            .filter(move |(_, word)| literal_len == prefix.len() || matches_prefix(word, prefix))
+    }
+}
+
+impl BuildLexicon for VecLexicon {
+    fn from_words(
+        words: impl IntoIterator<Item = String>,
+        lengths: BTreeSet<usize>,
+        _options: &LexiconOptions,
+    ) -> Self {
+        let mut by_length = group_by_length(words, &lengths);
+        for words in by_length.values_mut() {
+            words.par_sort();
+        }
+        VecLexicon { words: by_length }
+    }
+}
+
+impl Lexicon for VecLexicon {
+    fn words_with_prefix(&self, prefix: &str, word_len: usize) -> Vec<String> {
+        (self.matching(prefix, word_len))
+            .map(|(_, word)| word.clone())
+            .collect()
+    }
+
+    fn ids_with_prefix(&self, prefix: &str, word_len: usize) -> Vec<WordId> {
+        self.matching(prefix, word_len).map(|(id, _)| id).collect()
+    }
+
+    fn word(&self, id: WordId, word_len: usize) -> &str {
+        &self.of_length(word_len)[id as usize]
+    }
+
+    fn word_count(&self, word_len: usize) -> usize {
+        self.of_length(word_len).len()
+    }
+
+    fn count_with_prefix(&self, prefix: &str, word_len: usize) -> usize {
+        self.matching(prefix, word_len).count()
+    }
+
+    fn has_prefix(&self, prefix: &str, word_len: usize) -> bool {
+        self.matching(prefix, word_len).next().is_some()
+    }
+
+    fn letters_after(&self, prefix: &str, word_len: usize) -> HashSet<char> {
+        letters_at(
+            self.matching(prefix, word_len).map(|(_, word)| word),
+            word_length(prefix),
+            word_len,
+        )
+    }
+
+    fn sample_with_prefix(
+        &self,
+        prefix: &str,
+        word_len: usize,
+        rng: &mut dyn RngCore,
+    ) -> Option<String> {
+        sample_matching(self.matching(prefix, word_len).map(|(_, word)| word), rng)
+    }
+
+    fn words_with_suffix(&self, suffix: &str, word_len: usize) -> Vec<String> {
+        count_query();
+        self.of_length(word_len)
+            .iter()
+            .filter(|word| matches_suffix(word, suffix))
+            .cloned()
+            .collect()
+    }
+
+    fn memory_estimate(&self) -> usize {
+        self.words.values().map(words_memory).sum()
+    }
+}
+
+/// The words of one length, in the order they were given, and the indexes over them, which
+/// list each word by its id: its place in `words`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LengthIndex {
+    words: Vec<String>,
+    prefixes: HashMap<String, Vec<WordId>>,
+    suffixes: Option<HashMap<String, Vec<WordId>>>, // reversed suffix -> ids, if indexed
+    positions: HashMap<(usize, char), Vec<WordId>>, // (position, letter) -> ids, for wildcard queries
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashMapLexicon {
+    max_prefix_depth: usize, // longest prefix (or reversed suffix) with a bucket
+    by_length: BTreeMap<usize, LengthIndex>,
+}
+
+impl HashMapLexicon {
+    /// Initialize the lexicon with an additional index over reversed words,
+    /// so that suffix queries are lookups instead of scans
+    pub fn with_suffix_index(words: Vec<String>, lengths: BTreeSet<usize>) -> Self {
+        let mut lexicon = Self::initialize(words, lengths);
+        for index in lexicon.by_length.values_mut() {
+            index.suffixes = Some(Self::index(
+                &index.words,
+                |word| word.chars().rev().collect(),
+                DEFAULT_PREFIX_DEPTH,
+            ));
+        }
+        lexicon
+    }
+
+    /// Map every prefix of each word's key, up to `depth` letters long, to the ids of the words
+    /// having it
+    fn index(
+        words: &[String],
+        key: impl Fn(&str) -> String + Sync,
+        depth: usize,
+    ) -> HashMap<String, Vec<WordId>> {
+        index_in_chunks(words, |first, chunk| {
+            let mut ids: HashMap<String, Vec<WordId>> = HashMap::new();
+            for (id, word) in (first..).zip(chunk) {
+                let key = key(word);
+                for i in prefix_ends(&key).take(depth.saturating_add(1)) {
+                    ids.entry(key[..i].to_string()).or_default().push(id);
+                }
+            }
+            ids
+        })
+    }
+
+    /// Map each letter at each position to the ids of the words having it there
+    fn position_index(words: &[String]) -> HashMap<(usize, char), Vec<WordId>> {
+        index_in_chunks(words, |first, chunk| {
+            let mut positions: HashMap<(usize, char), Vec<WordId>> = HashMap::new();
+            for (id, word) in (first..).zip(chunk) {
+                for (i, ch) in word.chars().enumerate() {
+                    positions.entry((i, ch)).or_default().push(id);
+                }
+            }
+            positions
+        })
+    }
+
+    /// The words matching `prefix`, each with its id
+    fn matching<'a>(
+        &'a self,
+        prefix: &'a str,
+        word_len: usize,
+    ) -> impl Iterator<Item = (WordId, &'a String)> + Clone + 'a {
+        count_query();
+        // Wildcards can't be looked up directly, so scan the bucket of the literal part or
+        // the words having one of the later letters in place, whichever is smaller
+        let literal_len = prefix.find(WILDCARD).unwrap_or(prefix.len());
+        let key = truncate_letters(&prefix[..literal_len], self.max_prefix_depth);
+        let exact = key.len() == prefix.len();
+        let index = self.by_length.get(&word_len);
+        let words = index.map_or(&[][..], |index| index.words.as_slice());
+        let bucket = index.and_then(|index| {
+            let bucket = index.prefixes.get(key);
+            if literal_len == prefix.len() {
+                return bucket;
+            }
+            let placed = prefix
+                .chars()
+                .enumerate()
+                .filter(|&(_, ch)| ch != WILDCARD)
+                .map(|key| index.positions.get(&key));
+            std::iter::once(bucket)
+                .chain(placed)
+                .min_by_key(|words| words.map_or(0, Vec::len))
+                .flatten()
+        });
+        bucket
+            .into_iter()
+            .flatten()
+            .map(move |&id| (id, &words[id as usize]))
+            .filter(move |(_, w)| exact || matches_prefix(w, prefix))
+    }
+}
+
+impl BuildLexicon for HashMapLexicon {
+    fn from_words(
+        words: impl IntoIterator<Item = String>,
+        lengths: BTreeSet<usize>,
+        options: &LexiconOptions,
+    ) -> Self {
+        // Each length is indexed on its own, and each index in chunks, all in parallel; the
+        // chunks are merged in order, so the result is the same whatever the number of threads
+        let depth = options.max_prefix_depth;
+        let by_length = group_by_length(words, &lengths)
+            .into_par_iter()
+            .map(|(len, words)| {
+                let (prefixes, positions) = parallel::join(
+                    || Self::index(&words, |word| word.to_string(), depth),
+                    || Self::position_index(&words),
+                );
+                let index = LengthIndex {
+                    words,
+                    prefixes,
+                    suffixes: None,
+                    positions,
+                };
+                (len, index)
+            })
+            .collect();
+        HashMapLexicon {
+            max_prefix_depth: depth,
+            by_length,
+        }
+    }
+}
+
+impl Lexicon for HashMapLexicon {
+    fn words_with_prefix(&self, prefix: &str, word_len: usize) -> Vec<String> {
+        (self.matching(prefix, word_len))
+            .map(|(_, word)| word.clone())
+            .collect()
+    }
+
+    fn ids_with_prefix(&self, prefix: &str, word_len: usize) -> Vec<WordId> {
+        self.matching(prefix, word_len).map(|(id, _)| id).collect()
+    }
+
+    fn word(&self, id: WordId, word_len: usize) -> &str {
+        &self.by_length[&word_len].words[id as usize]
+    }
+
+    fn word_count(&self, word_len: usize) -> usize {
+        (self.by_length.get(&word_len)).map_or(0, |index| index.words.len())
+    }
+
+    fn count_with_prefix(&self, prefix: &str, word_len: usize) -> usize {
+        self.matching(prefix, word_len).count()
+    }
+
+    fn has_prefix(&self, prefix: &str, word_len: usize) -> bool {
+        self.matching(prefix, word_len).next().is_some()
+    }
+
+    fn letters_after(&self, prefix: &str, word_len: usize) -> HashSet<char> {
+        letters_at(
+            self.matching(prefix, word_len).map(|(_, word)| word),
+            word_length(prefix),
+            word_len,
+        )
+    }
+
+    fn sample_with_prefix(
+        &self,
+        prefix: &str,
+        word_len: usize,
+        rng: &mut dyn RngCore,
+    ) -> Option<String> {
+        sample_matching(self.matching(prefix, word_len).map(|(_, word)| word), rng)
+    }
+
+    fn words_with_suffix(&self, suffix: &str, word_len: usize) -> Vec<String> {
+        count_query();
+        let Some(index) = self.by_length.get(&word_len) else {
+            return vec![];
+        };
+        let matching = |w: &&String| matches_suffix(w, suffix);
+        match &index.suffixes {
+            Some(suffixes) => {
+                // Only the literal tail after the last wildcard can be looked up
+                let literal = &suffix[suffix.rfind(WILDCARD).map_or(0, |i| i + 1)..];
+                let key: String = literal.chars().rev().take(self.max_prefix_depth).collect();
+                (suffixes.get(&key).into_iter().flatten())
+                    .map(|&id| &index.words[id as usize])
+                    .filter(matching)
+                    .cloned()
+                    .collect()
+            }
+            None => index.words.iter().filter(matching).cloned().collect(),
+        }
+    }
+
+    fn memory_estimate(&self) -> usize {
+        let ids_memory = |ids: &Vec<WordId>| ids.capacity() * std::mem::size_of::<WordId>();
+        let index_memory = |map: &HashMap<String, Vec<WordId>>| -> usize {
+            map.iter()
+                .map(|(key, ids)| key.capacity() + ids_memory(ids))
+                .sum::<usize>()
+                + map.capacity() * std::mem::size_of::<(String, Vec<WordId>)>()
+        };
+        let positions_memory = |positions: &HashMap<(usize, char), Vec<WordId>>| -> usize {
+            positions.values().map(ids_memory).sum::<usize>()
+                + positions.capacity() * std::mem::size_of::<((usize, char), Vec<WordId>)>()
+        };
+        self.by_length
+            .values()
+            .map(|index| {
+                words_memory(&index.words)
+                    + index_memory(&index.prefixes)
+                    + index.suffixes.as_ref().map_or(0, index_memory)
+                    + positions_memory(&index.positions)
+            })
+            .sum()
+    }
+}
+
+impl Display for VecLexicon {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let words: Vec<&str> = self.words.values().flatten().map(String::as_str).collect();
+        write!(f, "{}", words.join(", "))
+    }
+}
+
+/// The available lexicon implementations, for choosing one at runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LexiconKind {
+    Vec,
+    #[default]
+    HashMap,
+}
+
+impl LexiconKind {
+    pub const ALL: [LexiconKind; 2] = [LexiconKind::Vec, LexiconKind::HashMap];
+
+    /// A lexicon of this kind over `words`, behind dynamic dispatch
+    pub fn build(
+        self,
+        words: Vec<String>,
+        lengths: BTreeSet<usize>,
+        options: &LexiconOptions,
+    ) -> DynLexicon {
+        match self {
+            LexiconKind::Vec => {
+                Box::new(VecLexicon::initialize_with_options(words, lengths, options))
+            }
+            LexiconKind::HashMap => Box::new(HashMapLexicon::initialize_with_options(
+                words, lengths, options,
+            )),
+        }
+    }
+
+    /// Like `build`, with each of the `tiers` indexed by a lexicon of this kind (see
+    /// `TieredLexicon`)
+    pub fn build_with_tiers(
+        self,
+        words: Vec<String>,
+        lengths: BTreeSet<usize>,
+        tiers: &WordTiers,
+        options: &LexiconOptions,
+    ) -> DynLexicon {
+        match self {
+            LexiconKind::Vec => Box::new(TieredLexicon::<VecLexicon>::initialize_with_tiers(
+                words, lengths, tiers, options,
+            )),
+            LexiconKind::HashMap => {
+                Box::new(TieredLexicon::<HashMapLexicon>::initialize_with_tiers(
+                    words, lengths, tiers, options,
+                ))
+            }
+        }
+    }
+}
+
+impl Display for LexiconKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LexiconKind::Vec => write!(f, "vec"),
+            LexiconKind::HashMap => write!(f, "hashmap"),
+        }
+    }
+}
+
+impl std::str::FromStr for LexiconKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LexiconKind::ALL
+            .into_iter()
+            .find(|kind| kind.to_string() == s)
+            .ok_or_else(|| {
+                let names: Vec<String> = LexiconKind::ALL.iter().map(|k| k.to_string()).collect();
+                format!(
+                    "unknown lexicon {:?} (expected one of {})",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    fn sorted(mut words: Vec<String>) -> Vec<String> {
+        words.sort();
+        words
+    }
+
+    const FIVES: [&str; 7] = [
+        "abcde", "abcxy", "axcde", "bbcde", "abdde", "zzcde", "abcdz",
+    ];
+
+    fn lexicons() -> Vec<(LexiconKind, DynLexicon)> {
+        let lengths = BTreeSet::from([4, 5]);
+        let mut all = words(&FIVES);
+        all.push("abcd".to_string());
+        (LexiconKind::ALL.into_iter())
+            .map(|kind| {
+                let lexicon = kind.build(all.clone(), lengths.clone(), &LexiconOptions::default());
+                (kind, lexicon)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn wildcards_match_any_letter_wherever_they_are() {
+        let cases: [(&str, &[&str]); 4] = [
+            ("?bc", &["abcde", "abcdz", "abcxy", "bbcde"]),
+            ("a?c", &["abcde", "abcdz", "abcxy", "axcde"]),
+            ("abc?", &["abcde", "abcdz", "abcxy"]),
+            ("?b?d?", &["abcde", "abcdz", "abdde", "bbcde"]),
+        ];
+        for (kind, lexicon) in lexicons() {
+            for (prefix, expected) in cases {
+                let found = sorted(lexicon.words_with_prefix(prefix, 5));
+                assert_eq!(
+                    found,
+                    words(expected),
+                    "{} lexicon, prefix {:?}",
+                    kind,
+                    prefix
+                );
+                assert_eq!(lexicon.count_with_prefix(prefix, 5), expected.len());
+                assert!(lexicon.has_prefix(prefix, 5));
+            }
+            assert!(!lexicon.has_prefix("?q", 5), "{} lexicon", kind);
+        }
+    }
+
+    #[test]
+    fn all_wildcards_match_every_word_of_the_length() {
+        for (kind, lexicon) in lexicons() {
+            for len in [4, 5] {
+                let unconstrained = sorted(lexicon.words_with_prefix("", len));
+                for wildcards in 1..=len {
+                    let prefix = WILDCARD.to_string().repeat(wildcards);
+                    let found = sorted(lexicon.words_with_prefix(&prefix, len));
+                    assert_eq!(
+                        found, unconstrained,
+                        "{} lexicon, prefix {:?}",
+                        kind, prefix
+                    );
+                }
+            }
+            let longer = WILDCARD.to_string().repeat(5);
+            assert!(
+                lexicon.words_with_prefix(&longer, 4).is_empty(),
+                "{} lexicon",
+                kind
+            );
+        }
+    }
+
+    #[test]
+    fn suffix_and_prefix_queries_agree_with_filtering() {
+        let fixture = words(&conformance::FIXTURE);
+        let lengths: BTreeSet<usize> = fixture.iter().map(|word| word_length(word)).collect();
+        let lexicons: [(&str, Box<dyn Lexicon>); 3] = [
+            (
+                "vec",
+                Box::new(VecLexicon::initialize(fixture.clone(), lengths.clone())),
+            ),
+            (
+                "hashmap",
+                Box::new(HashMapLexicon::initialize(fixture.clone(), lengths.clone())),
+            ),
+            (
+                "hashmap with a suffix index",
+                Box::new(HashMapLexicon::with_suffix_index(
+                    fixture.clone(),
+                    lengths.clone(),
+                )),
+            ),
+        ];
+        let filtered = |len: usize, matches: &dyn Fn(&str) -> bool| {
+            let found = fixture
+                .iter()
+                .filter(|word| word_length(word) == len && matches(word));
+            sorted(found.cloned().collect())
+        };
+        let affixes = [
+            "", "e", "ee", "es", "t?", "?e", "té", "ace", "cafe", "abcdef", "q",
+        ];
+        for (name, lexicon) in &lexicons {
+            for &len in &lengths {
+                for affix in affixes {
+                    assert_eq!(
+                        sorted(lexicon.words_with_suffix(affix, len)),
+                        filtered(len, &|word| matches_suffix(word, affix)),
+                        "{} lexicon, suffix {:?} of {}-letter words",
+                        name,
+                        affix,
+                        len
+                    );
+                    assert_eq!(
+                        sorted(lexicon.words_with_prefix(affix, len)),
+                        filtered(len, &|word| matches_prefix(word, affix)),
+                        "{} lexicon, prefix {:?} of {}-letter words",
+                        name,
+                        affix,
+                        len
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn samples_are_spread_evenly_over_the_matching_words() {
+        const DRAWS: usize = 6000;
+        for (kind, lexicon) in lexicons() {
+            let matching = sorted(lexicon.words_with_prefix("?b", 5));
+            let mut rng = StdRng::seed_from_u64(7);
+            let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+            for _ in 0..DRAWS {
+                let word = lexicon.sample_with_prefix("?b", 5, &mut rng).unwrap();
+                *counts.entry(word).or_default() += 1;
+            }
+            assert_eq!(counts.keys().cloned().collect::<Vec<_>>(), matching);
+            let expected = DRAWS as f64 / matching.len() as f64;
+            let chi_squared: f64 = (counts.values())
+                .map(|&count| (count as f64 - expected).powi(2) / expected)
+                .sum();
+            // The 0.1% critical value of chi-squared with 4 degrees of freedom
+            assert!(chi_squared < 18.47, "{} lexicon: {}", kind, chi_squared);
+            assert_eq!(lexicon.sample_with_prefix("q", 5, &mut rng), None);
+        }
+    }
+
+    #[test]
+    fn samples_repeat_under_the_same_seed() {
+        for (kind, lexicon) in lexicons() {
+            let draw = |seed| {
+                let mut rng = StdRng::seed_from_u64(seed);
+                (0..20)
+                    .map(|_| lexicon.sample_with_prefix("", 5, &mut rng))
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(draw(1), draw(1), "{} lexicon", kind);
+            assert_ne!(draw(1), draw(2), "{} lexicon", kind);
+        }
+    }
+
+    #[test]
+    fn accented_words_leave_ascii_queries_as_they_were() {
+        let ascii = words(&["cafe", "care", "cart", "dent", "tea", "ace"]);
+        let mut mixed = ascii.clone();
+        mixed.extend(words(&[
+            "café", "éclat", "naïve", "çava", "añil", "тест", "ñu",
+        ]));
+        let lengths = BTreeSet::from([2, 3, 4, 5]);
+        for options in [
+            LexiconOptions::default(),
+            LexiconOptions {
+                max_prefix_depth: 1,
+            },
+        ] {
+            let plain =
+                HashMapLexicon::initialize_with_options(ascii.clone(), lengths.clone(), &options);
+            let accented =
+                HashMapLexicon::initialize_with_options(mixed.clone(), lengths.clone(), &options);
+            for prefix in ["", "c", "ca", "car", "cafe", "d", "?a", "t", "x"] {
+                for len in [3, 4] {
+                    let found = accented.words_with_prefix(prefix, len);
+                    let ascii_found = found.into_iter().filter(|word| word.is_ascii());
+                    assert_eq!(
+                        sorted(ascii_found.collect()),
+                        sorted(plain.words_with_prefix(prefix, len)),
+                        "prefix {:?} of {}-letter words",
+                        prefix,
+                        len
+                    );
+                }
+            }
+            assert_eq!(accented.words_with_prefix("caf", 4), ["cafe", "café"]);
+            assert_eq!(accented.words_with_prefix("é", 5), ["éclat"]);
+            assert_eq!(accented.words_with_prefix("т", 4), ["тест"]);
+            assert_eq!(accented.words_with_prefix("ñ", 2), ["ñu"]);
+            assert_eq!(accented.letters_after("caf", 4), HashSet::from(['e', 'é']));
+        }
+    }
+
+    #[test]
+    fn sorted_vec_lexicon_agrees_with_a_scan() {
+        // Out of order, with repeats, words that are prefixes of others and wildcard-worthy gaps
+        let unsorted = words(&[
+            "tern", "stare", "rat", "ant", "star", "arts", "tsar", "rats", "tar", "rant", "star",
+            "tea", "ate", "eat", "aster", "rates", "stern", "tears", "art", "tarn", "ear",
+        ]);
+        let lengths = BTreeSet::from([3, 4, 5]);
+        let lexicon = VecLexicon::initialize(unsorted.clone(), lengths.clone());
+        let prefixes: BTreeSet<String> = (unsorted.iter())
+            .flat_map(|word| (0..=word.len()).map(|end| word[..end].to_string()))
+            .chain(["?a", "s?a", "??r", "t?", "z", "starz"].map(String::from))
+            .collect();
+        for prefix in &prefixes {
+            for &len in &lengths {
+                let scanned: Vec<String> = (unsorted.iter())
+                    .filter(|word| word.len() == len && matches_prefix(word, prefix))
+                    .cloned()
+                    .collect();
+                let found = lexicon.words_with_prefix(prefix, len);
+                assert_eq!(
+                    sorted(found.clone()),
+                    sorted(scanned.clone()),
+                    "{:?}",
+                    prefix
+                );
+                assert_eq!(found, sorted(found.clone()), "the words come sorted");
+                assert_eq!(lexicon.count_with_prefix(prefix, len), scanned.len());
+                assert_eq!(lexicon.has_prefix(prefix, len), !scanned.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn lexicons_bucketed_by_length_conform() {
+        conformance::assert_lexicon_conformance(VecLexicon::initialize);
+        conformance::assert_lexicon_conformance(HashMapLexicon::initialize);
+        let letters: Vec<String> = (include_str!("../tests/fixtures/letters.txt").lines())
+            .map(String::from)
+            .collect();
+        for (name, report) in [
+            (
+                "vec",
+                conformance::check_lexicon_conformance_on(&letters, VecLexicon::initialize),
+            ),
+            (
+                "hashmap",
+                conformance::check_lexicon_conformance_on(&letters, HashMapLexicon::initialize),
+            ),
+        ] {
+            assert!(report.is_conforming(), "{}: {:?}", name, report.failures);
+        }
+    }
+
+    #[test]
+    fn streamed_words_build_the_same_lexicons() {
+        let text = include_str!("../tests/fixtures/letters.txt");
+        let filter = crate::loader::WordFilter::default();
+        let (words, _) = filter.apply(text.lines().map(str::to_string).collect());
+        let lengths = BTreeSet::from([3, 4]);
+        let options = LexiconOptions::default();
+        let stream = || crate::loader::stream_words(text.as_bytes(), &filter);
+        let streamed = VecLexicon::try_from_words(stream(), lengths.clone(), &options);
+        assert!(streamed.unwrap() == VecLexicon::initialize(words.clone(), lengths.clone()));
+        let streamed = HashMapLexicon::try_from_words(stream(), lengths.clone(), &options);
+        assert!(streamed.unwrap() == HashMapLexicon::initialize(words.clone(), lengths.clone()));
+
+        // A read error ends the build
+        let failing = (words.into_iter().map(Ok))
+            .take(10)
+            .chain([Err("read failed")]);
+        let built = VecLexicon::try_from_words(failing, lengths, &options);
+        assert_eq!(built.err(), Some("read failed"));
+    }
+
+    #[test]
+    fn lexicons_build_the_same_on_any_number_of_threads() {
+        // Every string of 3 to 5 of the letters a to h, in an order that is not sorted, so that
+        // the words fill several chunks and each length's words are spread over all of them
+        let mut words = vec![];
+        for len in 3..=5u32 {
+            for i in 0..8usize.pow(len) {
+                let word: String = (0..len)
+                    .map(|place| (b'a' + (i / 8usize.pow(place) % 8) as u8) as char)
+                    .collect();
+                words.push(word);
+            }
+        }
+        words.rotate_left(7919);
+        assert!(words.len() > 2 * CHUNK_WORDS);
+        let lengths = BTreeSet::from([3, 5]);
+        let build = |threads: usize| {
+            let pool = parallel::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            pool.install(|| {
+                (
+                    VecLexicon::initialize(words.clone(), lengths.clone()),
+                    HashMapLexicon::initialize(words.clone(), lengths.clone()),
+                    HashMapLexicon::with_suffix_index(words.clone(), lengths.clone()),
+                )
+            })
+        };
+        let sequential = build(1);
+        for threads in [2, 8] {
+            assert!(build(threads) == sequential, "{} threads", threads);
+        }
+    }
+
+    #[test]
+    fn prefixes_reject_characters_outside_the_alphabet() {
+        assert_eq!(parse_prefix("a?c", &Alphabet::Ascii), Ok("a?c".to_string()));
+        for (prefix, ch, position) in [("a*c", '*', 1), ("ab.", '.', 2), ("[a]", '[', 0)] {
+            assert_eq!(
+                parse_prefix(prefix, &Alphabet::Ascii),
+                Err(PrefixError::UnsupportedCharacter { ch, position })
+            );
+        }
+    }
+}
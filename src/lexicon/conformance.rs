@@ -0,0 +1,440 @@
+use super::{
+    matches_prefix, matches_suffix, word_length, BuildLexicon, HashMapLexicon, Lexicon,
+    LexiconOptions, VecLexicon, WILDCARD,
+};
+use crate::query_cache::CachedLexicon;
+use crate::tiers::{TieredLexicon, WordTiers};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::{BTreeSet, HashSet};
+use std::fmt::Display;
+
+/// The words the suite builds its lexicons from unless given others: words of several lengths,
+/// a word listed twice, words a letter apart (one of them only by an accent), a word and its
+/// plural, a word of one letter and words of more than `DEFAULT_PREFIX_DEPTH` letters sharing
+/// a long prefix
+pub const FIXTURE: [&str; 16] = [
+    "ace", "ace", "acé", "act", "aces", "bee", "been", "café", "cafe", "x", "abcde", "abcdf",
+    "abcdef", "zygote", "tee", "eta",
+];
+
+/// Queries the suite asks of a word list it is given, at most, drawn from its first words
+const MAX_SOURCE_WORDS: usize = 200;
+
+/// An answer a lexicon gave that the contract of `Lexicon` rules out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nonconformance {
+    pub case: String,  // which lexicon of the suite was asked, e.g. "every length"
+    pub query: String, // the query, e.g. `count_with_prefix("ac", 3)`
+    pub expected: String,
+    pub found: String,
+}
+
+impl Display for Nonconformance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} gave {}, expected {}",
+            self.case, self.query, self.found, self.expected
+        )
+    }
+}
+
+/// What a run of the suite found
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConformanceReport {
+    pub queries: usize, // queries checked
+    pub failures: Vec<Nonconformance>,
+}
+
+impl ConformanceReport {
+    pub fn is_conforming(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Run the suite over a lexicon implementation, panicking with every answer it got wrong.
+/// This is the supported way to validate a custom backend: `build` makes a lexicon of the
+/// words whose lengths are among the lengths given, as `BuildLexicon::from_words` does, e.g.
+/// `assert_lexicon_conformance(|words, lengths| MyLexicon::new(words, lengths))`.
+pub fn assert_lexicon_conformance<L: Lexicon>(build: impl Fn(Vec<String>, BTreeSet<usize>) -> L) {
+    let report = check_lexicon_conformance(build);
+    if !report.is_conforming() {
+        let failures: Vec<String> = (report.failures.iter())
+            .map(|failure| format!("  {}", failure))
+            .collect();
+        panic!(
+            "the lexicon got {} of {} queries wrong:\n{}",
+            report.failures.len(),
+            report.queries,
+            failures.join("\n")
+        );
+    }
+}
+
+/// Run the suite over a lexicon implementation with the words of `FIXTURE`
+pub fn check_lexicon_conformance<L: Lexicon>(
+    build: impl Fn(Vec<String>, BTreeSet<usize>) -> L,
+) -> ConformanceReport {
+    let words: Vec<String> = FIXTURE.iter().map(|word| word.to_string()).collect();
+    check_lexicon_conformance_on(&words, build)
+}
+
+/// Run the suite over a lexicon implementation with `words`, asking about the first
+/// `MAX_SOURCE_WORDS` of them. Each lexicon is checked against a plain scan of the words it
+/// was built from; the contract is that:
+///
+/// - `words_with_prefix` lists every word of the length asked for, once per time it was
+///   given, whose letters start with the prefix, `?` matching any letter; the empty prefix
+///   matches every word of the length and a full word matches itself
+/// - the words come in any order, but the same one for the same query every time
+/// - no word of a length left out of the lengths the lexicon was built for is ever listed
+/// - `count_with_prefix`, `has_prefix`, `letters_after` and `sample_with_prefix` agree with
+///   `words_with_prefix`, and `words_with_suffix` matches the end of the word the same way
+/// - `ids_with_prefix` lists the ids of the words `words_with_prefix` does, in the same order,
+///   as `word` reads them back, and `word_count` counts every word of the length
+/// - there is at least one tier, and every word's tier is one of them
+pub fn check_lexicon_conformance_on<L: Lexicon>(
+    words: &[String],
+    build: impl Fn(Vec<String>, BTreeSet<usize>) -> L,
+) -> ConformanceReport {
+    let mut report = ConformanceReport::default();
+    let all: BTreeSet<usize> = words.iter().map(|word| word_length(word)).collect();
+    // Every other length, so that some of the words given are left out
+    let some: BTreeSet<usize> = all.iter().copied().step_by(2).collect();
+    let sources = &words[..words.len().min(MAX_SOURCE_WORDS)];
+    let queries = queries(sources, &all);
+    let cases = [
+        ("every length", words, all),
+        ("every other length", words, some),
+        ("no words", &[][..], BTreeSet::new()),
+    ];
+    for (case, words, lengths) in cases {
+        let lexicon = build(words.to_vec(), lengths.clone());
+        let reference = Reference { words, lengths };
+        check_queries(case, &lexicon, &reference, &queries, &mut report);
+        check_tiers(case, &lexicon, words, &mut report);
+    }
+    report
+}
+
+/// The words a lexicon should hold, to check its answers against by scanning them
+struct Reference<'a> {
+    words: &'a [String],
+    lengths: BTreeSet<usize>,
+}
+
+impl Reference<'_> {
+    fn matching(&self, word_len: usize, matches: impl Fn(&str) -> bool) -> Vec<String> {
+        if !self.lengths.contains(&word_len) {
+            return vec![];
+        }
+        (self.words.iter())
+            .filter(|word| word_length(word) == word_len && matches(word))
+            .cloned()
+            .collect()
+    }
+}
+
+/// The prefixes to ask about, each with a length: the empty prefix, every prefix of each
+/// source word (the word itself included), each with a wildcard in place of one of its
+/// letters, prefixes no word has, and prefixes longer than the words asked for, at every
+/// length the words have and at the lengths either side of them
+fn queries(sources: &[String], lengths: &BTreeSet<usize>) -> Vec<(String, usize)> {
+    let mut prefixes: BTreeSet<String> = BTreeSet::from([String::new()]);
+    for word in sources {
+        let letters: Vec<char> = word.chars().collect();
+        for end in 1..=letters.len() {
+            let prefix: String = letters[..end].iter().collect();
+            for i in 0..end {
+                let mut wild = letters[..end].to_vec();
+                wild[i] = WILDCARD;
+                prefixes.insert(wild.into_iter().collect());
+            }
+            prefixes.insert(format!("{}\u{2603}", prefix)); // a letter no word list has
+            prefixes.insert(prefix);
+        }
+    }
+    let most = lengths.last().copied().unwrap_or(0);
+    prefixes.insert(WILDCARD.to_string().repeat(most + 1));
+    let lengths: BTreeSet<usize> = (lengths.iter())
+        .flat_map(|&len| [len.saturating_sub(1), len, len + 1])
+        .collect();
+    (prefixes.iter())
+        .flat_map(|prefix| lengths.iter().map(move |&len| (prefix.clone(), len)))
+        .collect()
+}
+
+fn check_queries<L: Lexicon>(
+    case: &str,
+    lexicon: &L,
+    reference: &Reference,
+    queries: &[(String, usize)],
+    report: &mut ConformanceReport,
+) {
+    let mut rng = StdRng::seed_from_u64(0);
+    for (prefix, len) in queries {
+        let (prefix, len) = (prefix.as_str(), *len);
+        let mut check = |name: &str, expected: String, found: String| {
+            report.queries += 1;
+            if expected != found {
+                report.failures.push(Nonconformance {
+                    case: case.to_string(),
+                    query: format!("{}({:?}, {})", name, prefix, len),
+                    expected,
+                    found,
+                });
+            }
+        };
+        let expected = reference.matching(len, |word| matches_prefix(word, prefix));
+        let found = lexicon.words_with_prefix(prefix, len);
+        check(
+            "words_with_prefix",
+            format!("{:?}", sorted(&expected)),
+            format!("{:?}", sorted(&found)),
+        );
+        check(
+            "words_with_prefix, asked again,",
+            format!("{:?}", found),
+            format!("{:?}", lexicon.words_with_prefix(prefix, len)),
+        );
+        let resolved: Vec<&str> = (lexicon.ids_with_prefix(prefix, len).into_iter())
+            .map(|id| lexicon.word(id, len))
+            .collect();
+        check(
+            "ids_with_prefix",
+            format!("{:?}", found),
+            format!("{:?}", resolved),
+        );
+        if prefix.is_empty() {
+            check(
+                "word_count",
+                expected.len().to_string(),
+                lexicon.word_count(len).to_string(),
+            );
+        }
+        check(
+            "count_with_prefix",
+            expected.len().to_string(),
+            lexicon.count_with_prefix(prefix, len).to_string(),
+        );
+        check(
+            "has_prefix",
+            (!expected.is_empty()).to_string(),
+            lexicon.has_prefix(prefix, len).to_string(),
+        );
+        let position = word_length(prefix);
+        let letters: HashSet<char> = (expected.iter())
+            .filter_map(|word| word.chars().nth(position))
+            .collect();
+        check(
+            "letters_after",
+            format!("{:?}", sorted(&letters)),
+            format!("{:?}", sorted(&lexicon.letters_after(prefix, len))),
+        );
+        let sample = lexicon.sample_with_prefix(prefix, len, &mut rng);
+        let sampled = match &sample {
+            Some(word) if expected.contains(word) => "one of the words".to_string(),
+            Some(word) => format!("{:?}", word),
+            None => "nothing".to_string(),
+        };
+        let expected_sample = match expected.is_empty() {
+            true => "nothing",
+            false => "one of the words",
+        };
+        check("sample_with_prefix", expected_sample.to_string(), sampled);
+        let expected = reference.matching(len, |word| matches_suffix(word, prefix));
+        check(
+            "words_with_suffix",
+            format!("{:?}", sorted(&expected)),
+            format!("{:?}", sorted(&lexicon.words_with_suffix(prefix, len))),
+        );
+    }
+}
+
+fn check_tiers<L: Lexicon>(
+    case: &str,
+    lexicon: &L,
+    words: &[String],
+    report: &mut ConformanceReport,
+) {
+    let tiers = lexicon.tiers();
+    let mut failure = |query: String, expected: String, found: String| {
+        report.failures.push(Nonconformance {
+            case: case.to_string(),
+            query,
+            expected,
+            found,
+        })
+    };
+    report.queries += 1;
+    if tiers == 0 {
+        failure("tiers()".to_string(), "at least 1".to_string(), "0".into());
+    }
+    for word in words {
+        report.queries += 1;
+        let tier = lexicon.tier(word);
+        if tier >= tiers.max(1) {
+            let expected = format!("a tier below {}", tiers.max(1));
+            failure(format!("tier({:?})", word), expected, tier.to_string());
+        }
+    }
+}
+
+fn sorted<T: Ord + Clone>(items: impl IntoIterator<Item = T>) -> Vec<T> {
+    let mut items: Vec<T> = items.into_iter().collect();
+    items.sort();
+    items
+}
+
+/// A way to build one of the lexicons this crate provides, for the suite
+pub type BuildFn = fn(Vec<String>, BTreeSet<usize>) -> Box<dyn Lexicon>;
+
+/// Every lexicon implementation of the crate, in each configuration whose queries take a
+/// different path, with a name for it
+pub fn builtin_lexicons() -> Vec<(&'static str, BuildFn)> {
+    vec![
+        ("vec", |words, lengths| {
+            Box::new(VecLexicon::initialize(words, lengths))
+        }),
+        ("hashmap", |words, lengths| {
+            Box::new(HashMapLexicon::initialize(words, lengths))
+        }),
+        ("hashmap, prefix depth 1", |words, lengths| {
+            let options = LexiconOptions {
+                max_prefix_depth: 1,
+            };
+            Box::new(HashMapLexicon::initialize_with_options(
+                words, lengths, &options,
+            ))
+        }),
+        ("hashmap with a suffix index", |words, lengths| {
+            Box::new(HashMapLexicon::with_suffix_index(words, lengths))
+        }),
+        ("tiered vec", |words, lengths| {
+            Box::new(TieredLexicon::<VecLexicon>::initialize_with_tiers(
+                words.clone(),
+                lengths,
+                &two_tiers(&words),
+                &LexiconOptions::default(),
+            ))
+        }),
+        ("tiered hashmap", |words, lengths| {
+            Box::new(TieredLexicon::<HashMapLexicon>::initialize_with_tiers(
+                words.clone(),
+                lengths,
+                &two_tiers(&words),
+                &LexiconOptions::default(),
+            ))
+        }),
+        ("cached vec", |words, lengths| {
+            Box::new(CachedLexicon::<'static, VecLexicon>::initialize(
+                words, lengths,
+            ))
+        }),
+        ("cached hashmap", |words, lengths| {
+            Box::new(CachedLexicon::<'static, HashMapLexicon>::initialize(
+                words, lengths,
+            ))
+        }),
+    ]
+}
+
+/// Tiers splitting `words` in two, every other distinct word in the second
+fn two_tiers(words: &[String]) -> WordTiers {
+    let distinct: BTreeSet<&String> = words.iter().collect();
+    let (first, second): (Vec<_>, Vec<_>) = distinct
+        .into_iter()
+        .enumerate()
+        .partition(|(i, _)| i % 2 == 0);
+    let list =
+        |half: Vec<(usize, &String)>| half.into_iter().map(|(_, word)| word.clone()).collect();
+    WordTiers::new(vec![list(first), list(second)]).0
+}
+
+/// Run the suite over every lexicon of `builtin_lexicons`, with `FIXTURE` and then with `words`
+/// if there are any
+pub fn check_builtin_lexicons(words: &[String]) -> Vec<(&'static str, ConformanceReport)> {
+    let fixture: Vec<String> = FIXTURE.iter().map(|word| word.to_string()).collect();
+    (builtin_lexicons().into_iter())
+        .map(|(name, build)| {
+            let mut report = check_lexicon_conformance_on(&fixture, build);
+            if !words.is_empty() {
+                let more = check_lexicon_conformance_on(words, build);
+                report.queries += more.queries;
+                report.failures.extend(more.failures);
+            }
+            (name, report)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexicon::WordId;
+
+    #[test]
+    fn every_builtin_lexicon_conforms() {
+        for (name, build) in builtin_lexicons() {
+            let report = check_lexicon_conformance(build);
+            assert!(report.is_conforming(), "{}: {:?}", name, report.failures);
+        }
+        let letters: Vec<String> = (include_str!("../../tests/fixtures/letters.txt").lines())
+            .map(String::from)
+            .collect();
+        for (name, report) in check_builtin_lexicons(&letters) {
+            assert!(report.is_conforming(), "{}: {:?}", name, report.failures);
+            assert!(report.queries > 0);
+        }
+    }
+
+    /// A lexicon that forgets the last word of every list it gives
+    struct Forgetful(VecLexicon);
+
+    impl Lexicon for Forgetful {
+        fn words_with_prefix(&self, prefix: &str, word_len: usize) -> Vec<String> {
+            let mut words = self.0.words_with_prefix(prefix, word_len);
+            words.pop();
+            words
+        }
+
+        fn ids_with_prefix(&self, prefix: &str, word_len: usize) -> Vec<WordId> {
+            self.0.ids_with_prefix(prefix, word_len)
+        }
+
+        fn word(&self, id: WordId, word_len: usize) -> &str {
+            self.0.word(id, word_len)
+        }
+
+        fn word_count(&self, word_len: usize) -> usize {
+            self.0.word_count(word_len)
+        }
+
+        fn words_with_suffix(&self, suffix: &str, word_len: usize) -> Vec<String> {
+            self.0.words_with_suffix(suffix, word_len)
+        }
+
+        fn memory_estimate(&self) -> usize {
+            self.0.memory_estimate()
+        }
+    }
+
+    #[test]
+    fn a_lexicon_that_drifts_is_caught() {
+        let report = check_lexicon_conformance(|words, lengths| {
+            Forgetful(VecLexicon::initialize(words, lengths))
+        });
+        assert!(!report.is_conforming());
+        assert!(report
+            .failures
+            .iter()
+            .any(|failure| failure.query.starts_with("words_with_prefix")));
+        let panicked = std::panic::catch_unwind(|| {
+            assert_lexicon_conformance(|words, lengths| {
+                Forgetful(VecLexicon::initialize(words, lengths))
+            })
+        });
+        assert!(panicked.is_err());
+    }
+}
@@ -0,0 +1,53 @@
+pub mod alphabet;
+pub mod bench;
+pub mod blocks;
+pub mod cancel;
+pub mod chunks;
+pub mod cube;
+pub mod daily;
+#[cfg(feature = "db")]
+pub mod db;
+pub mod dead_ends;
+pub mod definitions;
+pub mod digraphs;
+#[cfg(feature = "tui")]
+pub mod editor;
+pub mod formats;
+pub mod frequency;
+pub mod frontier;
+mod grid;
+pub mod highlight;
+pub mod letters;
+pub mod lexicon;
+pub mod loader;
+pub mod masks;
+pub mod max_size;
+pub mod memory;
+pub mod merge;
+pub mod naming;
+pub mod nogood;
+pub mod parallel;
+#[cfg(feature = "pdf")]
+pub mod pdf;
+pub mod provenance;
+pub mod puzzle;
+pub mod query_cache;
+pub mod rank;
+pub mod repl;
+pub mod runner;
+pub mod scoring;
+pub mod scrabble;
+pub mod seed_report;
+pub mod solver;
+pub mod spill;
+pub mod stats;
+pub mod status;
+pub mod tags;
+pub mod template;
+pub mod terminal;
+pub mod theme;
+pub mod tiers;
+pub mod trace;
+pub mod usage;
+pub mod watch;
+pub mod wordbox;
@@ -0,0 +1,660 @@
+use crate::alphabet::{Alphabet, Rejection};
+use crate::formats::{self, CsvColumn, FormatError, InputFormat};
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashSet, VecDeque};
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Split};
+
+/// What becomes of a line holding uppercase letters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CaseMode {
+    /// Lowercase it and keep it, so that ALL CAPS and capitalized word lists work as they are
+    #[default]
+    Lowercase,
+    /// Drop capitalized words like "Paris", which are usually names, and lowercase the rest
+    DropProperNouns,
+    /// Drop every line holding an uppercase letter
+    DropUppercase,
+}
+
+/// Which lines of a word list become words
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WordFilter {
+    pub case: CaseMode,
+    pub alphabet: Alphabet, // words with any other character are dropped, after lowercasing
+    pub strip: BTreeSet<char>, // removed from every line first, e.g. to keep "don't" as "dont"
+    pub keep_dropped: usize, // dropped lines the report lists (see `FilterReport::dropped_lines`)
+    /// Treat a dropped line as an error: `load_word_list` fails with `LoadError::Dropped`, and a
+    /// stream of words ends with an error of kind `InvalidData` wrapping the `DroppedLine`
+    pub strict: bool,
+}
+
+/// Why a `WordFilter` dropped a line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropReason {
+    InvalidUtf8,
+    ProperNoun, // with `CaseMode::DropProperNouns`
+    Uppercase,  // with `CaseMode::DropUppercase`
+    Repeat,     // a word the list has already, once lowercased or stripped, or nothing at all
+    Whitespace,
+    Punctuation,
+    Digit,
+    OutsideAlphabet,
+}
+
+impl From<Rejection> for DropReason {
+    fn from(rejection: Rejection) -> Self {
+        match rejection {
+            Rejection::Whitespace => DropReason::Whitespace,
+            Rejection::Punctuation => DropReason::Punctuation,
+            Rejection::Digit => DropReason::Digit,
+            Rejection::OutsideAlphabet => DropReason::OutsideAlphabet,
+        }
+    }
+}
+
+impl Display for DropReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DropReason::InvalidUtf8 => write!(f, "is not valid UTF-8"),
+            DropReason::ProperNoun => write!(f, "is a proper noun"),
+            DropReason::Uppercase => write!(f, "has uppercase letters"),
+            DropReason::Repeat => write!(f, "repeats a word once lowercased or stripped"),
+            DropReason::Whitespace => write!(f, "holds whitespace"),
+            DropReason::Punctuation => write!(f, "holds punctuation"),
+            DropReason::Digit => write!(f, "holds a digit"),
+            DropReason::OutsideAlphabet => write!(f, "holds a character outside the alphabet"),
+        }
+    }
+}
+
+/// A line a `WordFilter` dropped, and why
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DroppedLine {
+    pub line: usize, // counted from 1; the element, counted from 1, of a CSV or JSON list
+    pub text: String, // the line as read, any invalid UTF-8 replaced
+    pub reason: DropReason,
+}
+
+impl Display for DroppedLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, {:?}, {}", self.line, self.text, self.reason)
+    }
+}
+
+impl std::error::Error for DroppedLine {}
+
+/// Why a word list could not be loaded
+#[derive(Debug)]
+pub enum LoadError {
+    Format(FormatError),
+    /// The first line a strict filter dropped (see `WordFilter::strict`)
+    Dropped(DroppedLine),
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Format(err) => write!(f, "{}", err),
+            LoadError::Dropped(dropped) => write!(f, "{}, and strict mode drops no lines", dropped),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<FormatError> for LoadError {
+    fn from(err: FormatError) -> Self {
+        LoadError::Format(err)
+    }
+}
+
+/// What a `WordFilter` did to the lines it was given
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FilterReport {
+    pub kept: usize,                     // words returned, normalized or not
+    pub normalized: usize,               // words kept after lowercasing
+    pub stripped: usize,                 // words kept after removing characters listed in `strip`
+    pub proper_nouns: usize, // capitalized lines dropped with `CaseMode::DropProperNouns`
+    pub uppercase: usize,    // lines dropped with `CaseMode::DropUppercase`
+    pub collisions: usize,   // altered lines dropped as repeats, or as nothing was left of them
+    pub whitespace: usize,   // lines dropped for holding a space or tab
+    pub punctuation: usize,  // lines dropped for holding ASCII punctuation
+    pub digits: usize,       // lines dropped for holding a digit
+    pub outside: usize,      // lines dropped for holding any other character outside the alphabet
+    pub invalid_utf8: usize, // lines dropped for not being valid UTF-8
+    pub dropped_lines: Vec<DroppedLine>, // the first `WordFilter::keep_dropped` lines dropped
+}
+
+impl FilterReport {
+    /// Whether the filter did more than drop phrases and abbreviations, which every word list
+    /// has plenty of: it lowercased words, or dropped some for their case or characters
+    pub fn is_notable(&self) -> bool {
+        self.normalized + self.stripped + self.proper_nouns + self.uppercase + self.collisions > 0
+            || self.digits + self.outside + self.invalid_utf8 > 0
+    }
+
+    /// Number of lines dropped, for any reason
+    pub fn dropped(&self) -> usize {
+        self.proper_nouns
+            + self.uppercase
+            + self.collisions
+            + self.whitespace
+            + self.punctuation
+            + self.digits
+            + self.outside
+            + self.invalid_utf8
+    }
+
+    /// Count line `line`, `text`, as dropped for `reason`, listing it if fewer than `keep` are
+    fn drop_line(&mut self, reason: DropReason, line: usize, text: &[u8], keep: usize) {
+        match reason {
+            DropReason::InvalidUtf8 => self.invalid_utf8 += 1,
+            DropReason::ProperNoun => self.proper_nouns += 1,
+            DropReason::Uppercase => self.uppercase += 1,
+            DropReason::Repeat => self.collisions += 1,
+            DropReason::Whitespace => self.whitespace += 1,
+            DropReason::Punctuation => self.punctuation += 1,
+            DropReason::Digit => self.digits += 1,
+            DropReason::OutsideAlphabet => self.outside += 1,
+        }
+        if self.dropped_lines.len() < keep {
+            self.dropped_lines.push(DroppedLine {
+                line,
+                text: String::from_utf8_lossy(text).into_owned(),
+                reason,
+            });
+        }
+    }
+}
+
+impl Display for FilterReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} words kept", self.kept)?;
+        let altered: Vec<String> = [(self.normalized, "lowercased"), (self.stripped, "stripped")]
+            .into_iter()
+            .filter(|&(count, _)| count > 0)
+            .map(|(count, how)| format!("{} {}", count, how))
+            .collect();
+        if !altered.is_empty() {
+            write!(f, " ({})", altered.join(", "))?;
+        }
+        let dropped: Vec<String> = [
+            (self.proper_nouns, "proper nouns"),
+            (self.uppercase, "uppercase lines"),
+            (self.collisions, "repeats after lowercasing or stripping"),
+            (self.whitespace, "lines with whitespace"),
+            (self.punctuation, "lines with punctuation"),
+            (self.digits, "lines with digits"),
+            (self.outside, "lines with characters outside the alphabet"),
+            (self.invalid_utf8, "lines that are not valid UTF-8"),
+        ]
+        .into_iter()
+        .filter(|&(count, _)| count > 0)
+        .map(|(count, reason)| format!("{} {}", count, reason))
+        .collect();
+        if !dropped.is_empty() {
+            write!(f, "; dropped {}", dropped.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether a word is capitalized but not all caps, as names usually are
+fn is_proper_noun(word: &str) -> bool {
+    word.chars().next().is_some_and(char::is_uppercase) && word.chars().any(char::is_lowercase)
+}
+
+impl WordFilter {
+    /// The words among `lines`: the `strip` characters are removed and uppercase letters treated
+    /// according to `case`, then words with a character outside the alphabet are dropped. A word
+    /// the list already has after stripping or lowercasing is dropped too, while repeats of a line
+    /// as it is are kept.
+    pub fn apply(&self, lines: Vec<String>) -> (Vec<String>, FilterReport) {
+        self.apply_to_bytes(lines.iter().map(String::as_bytes))
+    }
+
+    /// Like `apply`, over lines that may not be valid UTF-8; those are dropped. Only the words
+    /// kept are allocated, so the lines can be borrowed straight from the file's contents.
+    pub fn apply_to_bytes<'a>(
+        &self,
+        lines: impl Iterator<Item = &'a [u8]> + Clone,
+    ) -> (Vec<String>, FilterReport) {
+        let mut report = FilterReport::default();
+        let has_uppercase = |line: &str| line.chars().any(char::is_uppercase);
+        let has_stripped = |line: &str| line.chars().any(|ch| self.strip.contains(&ch));
+        let mut seen: HashSet<Cow<str>> = lines
+            .clone()
+            .filter_map(|line| std::str::from_utf8(line).ok())
+            .filter(|line| {
+                !has_uppercase(line) && !has_stripped(line) && self.alphabet.check(line).is_none()
+            })
+            .map(Cow::Borrowed)
+            .collect();
+
+        let keep = self.dropped_to_keep();
+        let mut words = vec![];
+        for (i, line) in lines.enumerate() {
+            let candidate = match self.candidate(line) {
+                Ok(candidate) => candidate,
+                Err(reason) => {
+                    report.drop_line(reason, i + 1, line, keep);
+                    continue;
+                }
+            };
+            if !candidate.is_altered() {
+                words.push(candidate.word.into_owned());
+            } else if !candidate.word.is_empty() && seen.insert(candidate.word.clone()) {
+                report.normalized += usize::from(candidate.lowercased);
+                report.stripped += usize::from(candidate.stripped);
+                words.push(candidate.word.into_owned());
+            } else {
+                report.drop_line(DropReason::Repeat, i + 1, line, keep);
+            }
+        }
+        report.kept = words.len();
+        (words, report)
+    }
+
+    /// The word a single line makes under the filter, as `apply` would keep it unless the list
+    /// holds it already, or none if the filter drops it
+    pub fn normalize(&self, line: &str) -> Option<String> {
+        let candidate = self.candidate(line.as_bytes()).ok()?;
+        Some(candidate.word.into_owned()).filter(|word| !word.is_empty())
+    }
+
+    /// Count a line of a stream as dropped, and under a strict filter the error that ends it
+    fn drop_streamed(
+        &self,
+        report: &mut FilterReport,
+        reason: DropReason,
+        line: usize,
+        text: &[u8],
+    ) -> Option<std::io::Error> {
+        report.drop_line(reason, line, text, self.dropped_to_keep());
+        let dropped = report.dropped_lines.first().filter(|_| self.strict)?;
+        Some(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            dropped.clone(),
+        ))
+    }
+
+    /// Dropped lines to list in the report: a strict filter needs the first one for its error
+    fn dropped_to_keep(&self) -> usize {
+        self.keep_dropped.max(usize::from(self.strict))
+    }
+
+    /// The word `line` makes once stripped and lowercased, or why the filter drops it
+    fn candidate<'l>(&self, line: &'l [u8]) -> Result<Candidate<'l>, DropReason> {
+        let Ok(line) = std::str::from_utf8(line) else {
+            return Err(DropReason::InvalidUtf8);
+        };
+        let stripped = line.chars().any(|ch| self.strip.contains(&ch));
+        let line: Cow<str> = if stripped {
+            Cow::Owned(line.chars().filter(|ch| !self.strip.contains(ch)).collect())
+        } else {
+            Cow::Borrowed(line)
+        };
+        let lowercased = line.chars().any(char::is_uppercase);
+        let word = match self.case {
+            _ if !lowercased => line,
+            CaseMode::DropUppercase => return Err(DropReason::Uppercase),
+            CaseMode::DropProperNouns if is_proper_noun(&line) => {
+                return Err(DropReason::ProperNoun)
+            }
+            CaseMode::Lowercase | CaseMode::DropProperNouns => Cow::Owned(line.to_lowercase()),
+        };
+        if let Some(rejection) = self.alphabet.check(&word) {
+            return Err(rejection.into());
+        }
+        Ok(Candidate {
+            word,
+            lowercased,
+            stripped,
+        })
+    }
+
+    /// Whether the filter can change a line into another word, rather than only keep or drop it
+    fn can_alter(&self) -> bool {
+        !self.strip.is_empty() || self.case != CaseMode::DropUppercase
+    }
+
+    /// The words among `lines`, filtered one line at a time as they are read; see
+    /// `FilteredWords`
+    pub fn stream<I>(&self, lines: I) -> FilteredWords<I::IntoIter>
+    where
+        I: IntoIterator<Item = std::io::Result<Vec<u8>>>,
+    {
+        FilteredWords {
+            filter: self.clone(),
+            lines: lines.into_iter(),
+            plain: HashSet::new(),
+            altered: HashSet::new(),
+            held: VecDeque::new(),
+            lines_read: 0,
+            report: FilterReport::default(),
+            ended: false,
+        }
+    }
+}
+
+/// A line that passed the filter, and how it was changed on the way
+struct Candidate<'l> {
+    word: Cow<'l, str>,
+    lowercased: bool,
+    stripped: bool,
+}
+
+impl Candidate<'_> {
+    fn is_altered(&self) -> bool {
+        self.lowercased || self.stripped
+    }
+}
+
+/// The words of a stream of lines, keeping the same words as `WordFilter::apply` without the
+/// lines being collected first. A line that is a word as it stands comes out as soon as it is
+/// read. A word only made by stripping or lowercasing a line waits until every line is read,
+/// since the list may still hold it as it stands further down, so those words come after the
+/// rest. Read errors are passed on as they come, as is the first line dropped by a strict filter.
+pub struct FilteredWords<I> {
+    filter: WordFilter,
+    lines: I,
+    plain: HashSet<String>, // words kept as they stand, which no altered word may repeat
+    altered: HashSet<String>, // altered words held so far, each kept once
+    held: VecDeque<HeldWord>,
+    lines_read: usize,
+    report: FilterReport,
+    ended: bool, // a strict filter dropped a line, so no more words come
+}
+
+/// A word made by stripping or lowercasing a line, waiting for the end of the stream
+struct HeldWord {
+    word: String,
+    lowercased: bool,
+    stripped: bool,
+    line: usize,
+    text: Option<Vec<u8>>, // the line, if the report may still list it as dropped
+}
+
+impl<I> FilteredWords<I> {
+    /// What the filter did to the lines read so far; complete once the words run out
+    pub fn report(&self) -> &FilterReport {
+        &self.report
+    }
+
+    /// End the stream with the error of a line a strict filter dropped
+    fn end(&mut self, err: std::io::Error) -> std::io::Error {
+        self.ended = true;
+        err
+    }
+}
+
+impl<I: Iterator<Item = std::io::Result<Vec<u8>>>> Iterator for FilteredWords<I> {
+    type Item = std::io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.ended {
+            return None;
+        }
+        for line in self.lines.by_ref() {
+            let line = match line {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err)),
+            };
+            self.lines_read += 1;
+            let text = trim_line_ending(&line);
+            let candidate = match self.filter.candidate(text) {
+                Ok(candidate) => candidate,
+                Err(reason) => match (self.filter).drop_streamed(
+                    &mut self.report,
+                    reason,
+                    self.lines_read,
+                    text,
+                ) {
+                    Some(err) => return Some(Err(self.end(err))),
+                    None => continue,
+                },
+            };
+            let altered = candidate.is_altered();
+            let word = candidate.word.into_owned();
+            if !altered {
+                if self.filter.can_alter() {
+                    self.plain.insert(word.clone());
+                }
+                self.report.kept += 1;
+                return Some(Ok(word));
+            }
+            if word.is_empty() || self.plain.contains(&word) || !self.altered.insert(word.clone()) {
+                match (self.filter).drop_streamed(
+                    &mut self.report,
+                    DropReason::Repeat,
+                    self.lines_read,
+                    text,
+                ) {
+                    Some(err) => return Some(Err(self.end(err))),
+                    None => continue,
+                }
+            }
+            let listed = self.report.dropped_lines.len() < self.filter.dropped_to_keep();
+            self.held.push_back(HeldWord {
+                word,
+                lowercased: candidate.lowercased,
+                stripped: candidate.stripped,
+                line: self.lines_read,
+                text: listed.then(|| text.to_vec()),
+            });
+        }
+        // Every line is read, so the altered words no line had as it stands can come out
+        while let Some(held) = self.held.pop_front() {
+            if self.plain.contains(&held.word) {
+                let text = held.text.unwrap_or_default();
+                match (self.filter).drop_streamed(
+                    &mut self.report,
+                    DropReason::Repeat,
+                    held.line,
+                    &text,
+                ) {
+                    Some(err) => return Some(Err(self.end(err))),
+                    None => continue,
+                }
+            }
+            self.report.normalized += usize::from(held.lowercased);
+            self.report.stripped += usize::from(held.stripped);
+            self.report.kept += 1;
+            return Some(Ok(held.word));
+        }
+        None
+    }
+}
+
+/// A line without its "\r\n" or "\n" ending
+fn trim_line_ending(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+/// The lines of a file's contents, without their line endings; the last line needs no newline
+/// after it
+#[cfg(feature = "mmap")]
+fn split_lines(contents: &[u8]) -> impl Iterator<Item = &[u8]> + Clone {
+    contents
+        .split_inclusive(|&byte| byte == b'\n')
+        .map(trim_line_ending)
+}
+
+/// The words of a word list file, one per line, filtered by `filter`. Memory-maps the file
+/// when built with the `mmap` feature, and reads it line by line otherwise.
+pub fn load_words(filename: &str, filter: &WordFilter) -> (Vec<String>, FilterReport) {
+    #[cfg(feature = "mmap")]
+    return map_words(filename, filter);
+    #[cfg(not(feature = "mmap"))]
+    read_words(filename, filter)
+}
+
+/// The words of a word list file, read into memory line by line
+pub fn read_words(filename: &str, filter: &WordFilter) -> (Vec<String>, FilterReport) {
+    let file: File = File::open(filename).expect("Could not open file");
+    let lines: Vec<Vec<u8>> = BufReader::new(file)
+        .split(b'\n')
+        .map(|line| line.expect("Could not read file"))
+        .collect();
+    filter.apply_to_bytes(lines.iter().map(|line| trim_line_ending(line)))
+}
+
+/// The words of a word list file, filtered straight from a memory map of it so that only the
+/// words kept are copied
+#[cfg(feature = "mmap")]
+pub fn map_words(filename: &str, filter: &WordFilter) -> (Vec<String>, FilterReport) {
+    let file: File = File::open(filename).expect("Could not open file");
+    if file.metadata().is_ok_and(|metadata| metadata.len() == 0) {
+        return filter.apply_to_bytes(std::iter::empty()); // empty files cannot be mapped
+    }
+    // Safety: the map is only read while the words are filtered out of it, and a word list is
+    // not expected to change under a running search
+    let map = unsafe { memmap2::Mmap::map(&file) }.expect("Could not map file");
+    filter.apply_to_bytes(split_lines(&map))
+}
+
+/// The words of a word list file in `format`, detected if it is `InputFormat::Auto`, with the
+/// format they were read in. CSV and JSON files are parsed first, and their words filtered like
+/// the lines of a plain list. A strict filter fails on the first line it drops.
+pub fn load_word_list(
+    filename: &str,
+    format: InputFormat,
+    column: &CsvColumn,
+    filter: &WordFilter,
+) -> Result<(Vec<String>, FilterReport, InputFormat), LoadError> {
+    let format = match format {
+        InputFormat::Auto => {
+            let file: File = File::open(filename).expect("Could not open file");
+            let mut head = Vec::with_capacity(formats::SNIFF_BYTES);
+            (file.take(formats::SNIFF_BYTES as u64))
+                .read_to_end(&mut head)
+                .expect("Could not read file");
+            formats::detect(filename, &head)?
+        }
+        format => format,
+    };
+    let (words, report, format) = match format {
+        InputFormat::Auto | InputFormat::Text => {
+            let (words, report) = load_words(filename, filter);
+            (words, report, InputFormat::Text)
+        }
+        InputFormat::Csv => {
+            let words = formats::csv_column(&read_text(filename, format)?, column)?;
+            let (words, report) = filter.apply(words);
+            (words, report, format)
+        }
+        InputFormat::Json => {
+            let words = formats::json_strings(&read_text(filename, format)?)?;
+            let (words, report) = filter.apply(words);
+            (words, report, format)
+        }
+    };
+    match report.dropped_lines.first() {
+        Some(dropped) if filter.strict => Err(LoadError::Dropped(dropped.clone())),
+        _ => Ok((words, report, format)),
+    }
+}
+
+/// The contents of a file that has to be valid UTF-8 to be read in `format`
+fn read_text(filename: &str, format: InputFormat) -> Result<String, FormatError> {
+    let bytes = std::fs::read(filename).expect("Could not read file");
+    String::from_utf8(bytes).map_err(|err| {
+        let valid = &err.as_bytes()[..err.utf8_error().valid_up_to()];
+        let line = valid.iter().filter(|&&byte| byte == b'\n').count() + 1;
+        let start = valid
+            .iter()
+            .rposition(|&byte| byte == b'\n')
+            .map_or(0, |i| i + 1);
+        FormatError::Malformed {
+            format,
+            record: format!("line {}", line),
+            text: String::from_utf8_lossy(&valid[start..]).into_owned(),
+            reason: "not valid UTF-8".to_string(),
+        }
+    })
+}
+
+/// The words of the lines `reader` gives, filtered by `filter` as they are read (see
+/// `FilteredWords`), e.g. to build a lexicon from stdin with `BuildLexicon::try_from_words`
+pub fn stream_words<R: BufRead>(reader: R, filter: &WordFilter) -> FilteredWords<Split<R>> {
+    filter.stream(reader.split(b'\n'))
+}
+
+/// The words of a word list file with the default filter, read lazily: uppercase letters are
+/// lowercased and words with anything but the letters a to z dropped
+pub fn filter_words(filename: &str) -> FilteredWords<Split<BufReader<File>>> {
+    let file: File = File::open(filename).expect("Could not open file");
+    stream_words(BufReader::new(file), &WordFilter::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    const CASES: [&str; 6] = ["cat", "DOG", "Paris", "NASA", "Cat", "emu"];
+
+    #[test]
+    fn uppercase_words_are_lowercased_by_default() {
+        let (words, report) = WordFilter::default().apply(lines(&CASES));
+        // "Cat" lowercased is "cat", which the list has already
+        assert_eq!(words, ["cat", "dog", "paris", "nasa", "emu"]);
+        assert_eq!(report.kept, 5);
+        assert_eq!(report.normalized, 3);
+        assert_eq!(report.collisions, 1);
+        assert_eq!(report.dropped(), 1);
+    }
+
+    #[test]
+    fn proper_nouns_can_be_dropped_alone() {
+        let filter = WordFilter {
+            case: CaseMode::DropProperNouns,
+            ..WordFilter::default()
+        };
+        let (words, report) = filter.apply(lines(&CASES));
+        assert_eq!(words, ["cat", "dog", "nasa", "emu"]);
+        assert_eq!(report.proper_nouns, 2);
+        assert_eq!(report.normalized, 2);
+        assert_eq!(filter.normalize("Paris"), None);
+        assert_eq!(filter.normalize("TWL"), Some("twl".to_string()));
+    }
+
+    #[test]
+    fn uppercase_lines_can_be_dropped_as_before() {
+        let filter = WordFilter {
+            case: CaseMode::DropUppercase,
+            ..WordFilter::default()
+        };
+        let (words, report) = filter.apply(lines(&CASES));
+        assert_eq!(words, ["cat", "emu"]);
+        assert_eq!(report.uppercase, 4);
+        assert_eq!(report.normalized, 0);
+    }
+
+    /// A stream keeps the same words, though those it alters come after the rest
+    #[test]
+    fn streamed_words_match_the_words_applied() {
+        let text = CASES.join("\n");
+        for case in [
+            CaseMode::Lowercase,
+            CaseMode::DropProperNouns,
+            CaseMode::DropUppercase,
+        ] {
+            let filter = WordFilter {
+                case,
+                ..WordFilter::default()
+            };
+            let mut streamed = stream_words(text.as_bytes(), &filter);
+            let words: BTreeSet<String> = streamed.by_ref().map(Result::unwrap).collect();
+            let (applied, report) = filter.apply(lines(&CASES));
+            assert_eq!(words, applied.into_iter().collect(), "{:?}", case);
+            assert_eq!(*streamed.report(), report, "{:?}", case);
+        }
+    }
+}
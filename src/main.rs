@@ -1,265 +1,3833 @@
-use crossterm::{
-    cursor, execute,
-    style::{self, Stylize},
-    terminal::{self},
-};
-use rayon::prelude::*;
-use std::collections::BTreeMap;
-use std::collections::VecDeque;
-use std::fmt::Display;
+use clap::parser::ValueSource;
+use clap::{CommandFactory, Parser, Subcommand};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs::File;
-use std::io::{stdout, BufRead, BufReader};
-use std::time::Instant;
-use std::{collections::HashMap, vec};
-use tqdm::tqdm;
+use std::io::{stdout, BufWriter, IsTerminal, Write};
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use wordbox::alphabet::Alphabet;
+use wordbox::bench::{bench_all, BenchTable};
+use wordbox::blocks::BlockMask;
+use wordbox::cancel::CancellationToken;
+#[cfg(feature = "gzip")]
+use wordbox::chunks::Compression;
+use wordbox::chunks::{self, ChunkedOutput, SolutionOutput};
+use wordbox::cube::WordCube;
+use wordbox::daily::Daily;
+#[cfg(feature = "db")]
+use wordbox::db::{DbError, DbWriter, SolutionDb, StoredSolution};
+use wordbox::dead_ends::DeadEnds;
+use wordbox::definitions::Definitions;
+use wordbox::digraphs::{self, Digraphs};
+#[cfg(feature = "tui")]
+use wordbox::editor::{self, Editor};
+use wordbox::formats::{CsvColumn, InputFormat};
+use wordbox::frequency::{Difficulty, FrequencyThreshold, RareWords, WordFrequencies};
+use wordbox::highlight::StyledBox;
+use wordbox::letters::{ExcludedLetters, LetterBounds, RequiredLetters, VowelRule, Vowels};
+use wordbox::lexicon::conformance::check_builtin_lexicons;
+use wordbox::lexicon::{
+    parse_prefix, word_length, DynLexicon, Lexicon, LexiconKind, LexiconOptions, RowColumnLexicons,
+    WordLengths, DEFAULT_PREFIX_DEPTH,
+};
+use wordbox::loader::{load_word_list, CaseMode, WordFilter};
+use wordbox::masks::CellMasks;
+use wordbox::max_size::{
+    count_sizes, search_sizes, with_time_limit, CountOptions, MaxSizeOptions, SizeRange, SizeTable,
+    SizeVerdict,
+};
+use wordbox::memory::{Consumer, EstimatedSize, MemoryAction, MemoryGauge, MemoryLimit};
+use wordbox::merge::{read_solutions, MergeOrder, MergedSolutions, RecordError, RecordProblem};
+use wordbox::naming::{NameField, NameTemplate, NameValues};
+use wordbox::nogood::NogoodCache;
+#[cfg(feature = "pdf")]
+use wordbox::pdf::{PdfBook, PdfLayout};
+use wordbox::provenance::{self, Provenance, RunProvenance, WordListId};
+use wordbox::puzzle::{Puzzle, Reveal, Uniqueness, NO_CLUE, UNIQUENESS_BUDGET};
+use wordbox::rank::{LetterScores, RankBy, SortBy, TopK};
+use wordbox::repl::Repl;
+use wordbox::runner::{
+    parse_duration, productive_order, run_seeds, FoundSolution, InconclusiveSeed, RetryFactor,
+    RunEvent, RunOptions, RunState, Schedule, SeedBudget, SeedOrder, Shard,
+};
+use wordbox::scoring::{
+    rank_value, DistinctLetters, Metric, ScoreFormula, Scorer, TierPreference, WeightedScore,
+};
+use wordbox::scrabble::LetterValues;
+use wordbox::seed_report::SeedReport;
+use wordbox::solver::{
+    estimate_solutions, solve_word_cube, BestPartial, Heuristic, Outcome, SearchOrder,
+    SolverConfig, ValueOrder,
+};
+use wordbox::spill::SpillOptions;
+use wordbox::stats::{CorpusFrequency, LexiconStats, SearchStats};
+use wordbox::status::{StatusReporter, StatusSink};
+use wordbox::tags::WordTags;
+use wordbox::template::{FixedWord, Line, Template};
+use wordbox::terminal::{self, Progress};
+use wordbox::theme::ThemeWords;
+use wordbox::tiers::{line_tier, TierCounts, WordTiers};
+use wordbox::trace::SearchTrace;
+use wordbox::usage::{LetterHeatmap, WordUsage};
+use wordbox::watch::FileWatcher;
+use wordbox::wordbox::WordBox;
+
+#[derive(Parser, Debug, Clone)]
+#[command(about = "Find word boxes: grids whose rows and columns are all words")]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Word list to build the lexicon from, one word per line
+    #[arg(long, default_value = "../3esl.txt", global = true)]
+    wordlist: String,
+
+    /// Build the lexicon from several word lists instead, most preferred first, e.g. `--tier
+    /// common.txt --tier full.txt`: rows are tried from the first list before the next, which
+    /// the search only reaches for when the words of the first lead nowhere, and each solution
+    /// says how many of its words came from each list
+    #[arg(long, value_name = "FILE", conflicts_with = "cube")]
+    tier: Vec<String>,
+
+    /// Check the column words against this word list instead, e.g. to find boxes whose rows are
+    /// English and whose columns are French (asymmetric boxes only)
+    #[arg(long, requires = "asymmetric", conflicts_with = "cube")]
+    col_wordlist: Option<String>,
+
+    /// Layout of the word lists: text (one word per line), csv (a header, then the words in
+    /// --csv-column), json (an array of strings), or auto to tell from each file's extension
+    /// and contents
+    #[arg(long, default_value_t = InputFormat::Auto, global = true)]
+    input_format: InputFormat,
+
+    /// Column of a CSV word list holding the words: its name in the header, or its index
+    /// counted from 0
+    #[arg(long, value_name = "COLUMN", default_value_t = CsvColumn::Index(0), global = true)]
+    csv_column: CsvColumn,
+
+    /// Letters words may use: ascii (a to z), unicode (letters of any script), or the letters
+    /// themselves, e.g. abcdefghijklmnopqrstuvwxyzåäö; words with any other character are dropped
+    #[arg(long, default_value_t = Alphabet::Ascii, global = true)]
+    alphabet: Alphabet,
+
+    /// Remove these characters from every line of the word lists before filtering, e.g. "'-" to
+    /// keep "don't" as "dont" and "ice-cream" as "icecream" instead of dropping them
+    #[arg(long, value_name = "CHARS", default_value = "", global = true)]
+    strip_chars: String,
+
+    /// Letter groups that fill one cell as a single tile, e.g. `qu` or `qu,th`: "quit" is then a
+    /// three-letter word. Word lists, templates, --fix words and cell masks are all read with
+    /// the tiles in them, and boxes holding a tile are shown with wider cells
+    #[arg(long, value_name = "TILES", global = true)]
+    digraphs: Option<Digraphs>,
+
+    /// Drop capitalized words such as names ("Paris") instead of lowercasing them; ALL CAPS
+    /// words are still lowercased and kept
+    #[arg(long, global = true, conflicts_with = "drop_uppercase")]
+    drop_proper_nouns: bool,
+
+    /// Drop every line holding an uppercase letter instead of lowercasing it, as older versions did
+    #[arg(long, global = true)]
+    drop_uppercase: bool,
+
+    /// Also list the first N lines of each word list that were dropped, with their line numbers
+    /// and why, after the summary of what was dropped
+    #[arg(long, value_name = "N", default_value_t = 0, global = true)]
+    show_rejects: usize,
+
+    /// Stop with an error at the first line of a word list that would be dropped, rather than
+    /// skip it
+    #[arg(long, global = true)]
+    strict: bool,
+
+    /// Number of rows in the box, or a range such as 4..=6 to solve or count every size in it
+    /// in turn, one word list loaded for them all. A box one row tall is allowed, its columns
+    /// then being one-letter words that the word list has to hold; a size the word list has no
+    /// words for is searched all the same, after a warning
+    #[arg(long, value_name = "ROWS", default_value = "6", global = true)]
+    rows: SizeRange,
+
+    /// Number of columns in the box, or a range such as 4..=6 (see --rows)
+    #[arg(long, value_name = "COLS", default_value = "6", global = true)]
+    cols: SizeRange,
+
+    /// Seconds each box size may search for before the run moves on to the next size
+    #[arg(long, value_name = "SECS", conflicts_with = "cube")]
+    size_timeout: Option<u64>,
+
+    /// Time each seed's search may take, e.g. 5s or 500ms, before the run gives up on the seed as
+    /// inconclusive and moves on to the next; --size-timeout still bounds the run as a whole.
+    /// Under --schedule fair only the seed's own turns count.
+    #[arg(long, value_name = "TIME", value_parser = parse_duration, conflicts_with = "cube")]
+    per_seed_timeout: Option<Duration>,
+
+    /// Nodes each seed's search may expand before the run gives up on the seed as inconclusive
+    /// and moves on to the next; with --split-depth the seed's subtrees share them
+    #[arg(long, value_name = "N", conflicts_with = "cube")]
+    per_seed_max_nodes: Option<usize>,
+
+    /// Search the seeds left inconclusive by --per-seed-timeout or --per-seed-max-nodes again
+    /// once the others are done, with budgets this many times bigger, e.g. 2x. The solutions
+    /// found are remembered, even under --no-dedup, so a seed's second try does not report or
+    /// count the boxes of its first again.
+    #[arg(long, value_name = "FACTOR")]
+    retry_inconclusive: Option<RetryFactor>,
+
+    /// Let the column words differ from the row words (required for non-square boxes)
+    #[arg(long)]
+    asymmetric: bool,
+
+    /// Lexicon implementation backing the queries (vec, hashmap)
+    #[arg(long, default_value = "hashmap", global = true)]
+    lexicon: LexiconKind,
+
+    /// Longest prefix the hashmap lexicon keeps a bucket of words for; longer prefix queries
+    /// filter such a bucket instead. Lower depths save memory on big word lists.
+    #[arg(long, value_name = "LETTERS", default_value_t = DEFAULT_PREFIX_DEPTH, global = true)]
+    prefix_depth: usize,
+
+    /// Print statistics about the filtered word list before solving
+    #[arg(long)]
+    dict_stats: bool,
+
+    /// Worker threads for the seed search; 0 uses every core, 1 runs sequentially, as does every
+    /// count in a build without the parallel feature
+    #[arg(long, default_value_t = 0)]
+    threads: usize,
+
+    /// Stop after this many solutions, counted afresh for each size of a --rows/--cols range
+    #[arg(long)]
+    limit: Option<usize>,
+
+    /// Find every solution for each seed word instead of only the first
+    #[arg(long)]
+    all: bool,
+
+    /// Order in which partial boxes are expanded (dfs, bfs, best)
+    #[arg(long, default_value = "dfs")]
+    order: SearchOrder,
+
+    /// How to choose the next slot to fill (rows, mcv)
+    #[arg(long, default_value = "rows")]
+    heuristic: Heuristic,
+
+    /// Order in which the candidates for a slot are tried (lexicon, lcv, corpus-frequency: words
+    /// whose letters are common where they cross first, by the word lists' own letter frequencies)
+    #[arg(long, default_value = "lexicon")]
+    value_order: ValueOrder,
+
+    /// Prune row candidates with per-column allowed-letter sets
+    #[arg(long)]
+    forward_checking: bool,
+
+    /// Remember up to this many partial boxes proven unsolvable and skip them when they recur
+    /// (depth-first row search only)
+    #[arg(long, value_name = "MAX_ENTRIES")]
+    nogood_cache: Option<usize>,
+
+    /// Remember the answers to up to this many lexicon queries of each kind during every search,
+    /// for lexicons that are slow to query (--lexicon vec)
+    #[arg(long, value_name = "MAX_ENTRIES")]
+    query_cache: Option<usize>,
+
+    /// Keep an estimate of the memory the search takes up (frontiers, the nogood cache, the
+    /// solutions remembered for deduplication or held for sorting), and once it goes over this
+    /// size, e.g. 4G or 512M, take the --memory-action
+    #[arg(long, value_name = "SIZE", conflicts_with = "cube")]
+    memory_limit: Option<MemoryLimit>,
+
+    /// What to do over the --memory-limit: `stop` the run, keeping the solutions found so far;
+    /// `compact` the frontier by dropping the letter sets of the boxes waiting in it; or
+    /// `clear-caches`, emptying the nogood cache
+    #[arg(long, default_value_t = MemoryAction::Stop, requires = "memory_limit")]
+    memory_action: MemoryAction,
+
+    /// Keep only about this many boxes of each breadth-first search's frontier in memory and
+    /// write the rest to files in --spill-dir, reading them back in turn; for exhaustive runs
+    /// whose frontier would not fit in memory (--order bfs, rows heuristic, without --beam-width)
+    #[arg(long, value_name = "BOXES", conflicts_with = "cube")]
+    spill_threshold: Option<usize>,
+
+    /// Where --spill-threshold writes its files, which are deleted as they are read back and
+    /// when the run ends [default: the system's temporary directory]
+    #[arg(long, value_name = "DIR", requires = "spill_threshold")]
+    spill_dir: Option<PathBuf>,
+
+    /// For square asymmetric boxes, also search for the transpose of every solution instead of
+    /// reporting only one orientation
+    #[arg(long)]
+    no_symmetry_breaking: bool,
+
+    /// Never use the same word twice in a box
+    #[arg(long)]
+    distinct_words: bool,
+
+    /// Never put this word in a row or column, though it stays in the word list for the
+    /// crossings; may be given several times
+    #[arg(long, value_name = "WORD", conflicts_with = "cube")]
+    forbid_word: Vec<String>,
+
+    /// Forbid the words of this file too, one per line and filtered like the word list
+    #[arg(long, value_name = "FILE", conflicts_with = "cube")]
+    forbid_file: Option<String>,
+
+    /// Require the main diagonal of a square box to be a word too
+    #[arg(long)]
+    diagonal_word: bool,
+
+    /// Require the anti-diagonal, read from the top right, to be a word too
+    #[arg(long)]
+    anti_diagonal_word: bool,
+
+    /// Letters every box has to contain; a letter listed twice has to appear twice
+    #[arg(long, default_value = "")]
+    require_letters: RequiredLetters,
+
+    /// How often letters may appear across a box's cells, e.g. "q<=1,e=2..4" for at most one q
+    /// and two to four e's; each letter takes <=N, >=N, =N or =A..B. The counts of the bounded
+    /// letters are printed under each box.
+    #[arg(long, value_name = "BOUNDS", default_value = "")]
+    letter_bounds: LetterBounds,
+
+    /// Only keep boxes holding at least N different letters across their cells, and print the
+    /// number under each; the search gives up on a partial box once its open cells could no
+    /// longer bring in enough new letters
+    #[arg(long, value_name = "N")]
+    min_distinct_letters: Option<usize>,
+
+    /// Only keep boxes with a vowel in every row and every column
+    #[arg(long)]
+    require_vowel_per_line: bool,
+
+    /// Only keep boxes with no more than K consonants in a row anywhere in a row or column
+    #[arg(long, value_name = "K")]
+    max_consonant_run: Option<usize>,
+
+    /// The letters --require-vowel-per-line and --max-consonant-run count as vowels, e.g.
+    /// aeiouyäöü
+    #[arg(long, default_value_t = Vowels::default())]
+    vowels: Vowels,
+
+    /// Only keep boxes in which no letter fills two cells. Needs --asymmetric: a symmetric box
+    /// repeats every letter off its diagonal, once across and once down, so none qualifies.
+    #[arg(long)]
+    isogram: bool,
+
+    /// Also keep near misses: boxes whose rows are all words but up to K of whose columns are
+    /// not. The columns that are not words are listed under each near miss, which ranks after
+    /// every box without any under --top and --sort-by. Needs --asymmetric, and sets aside
+    /// --forward-checking, --nogood-cache and --heuristic mcv, which take every column to be a
+    /// word.
+    #[arg(long, value_name = "K", default_value_t = 0, conflicts_with = "cube")]
+    allow_invalid_cols: usize,
+
+    /// Only keep boxes that read the same turned upside down: each cell holds the letter of the
+    /// cell opposite it through the centre, so every row placed fixes the row as far from the
+    /// bottom, backwards (solving, counting and estimating boxes only)
+    #[arg(long, conflicts_with = "cube")]
+    rotational: bool,
+
+    /// Letters no box may contain; words holding any of them are dropped from the word list
+    #[arg(long, default_value = "")]
+    exclude_letters: ExcludedLetters,
+
+    /// Letters to lock into the box, one row per line with `.` for open cells (e.g. "c..t\n....");
+    /// the template's shape sets the box size
+    #[arg(long, conflicts_with = "template_file")]
+    template: Option<String>,
+
+    /// Read the template from this file instead
+    #[arg(long)]
+    template_file: Option<String>,
+
+    /// Limit cells to some letters, read from this file as lines of `ROW COL LETTERS`, counted
+    /// from 0: e.g. `0 3 vowels`, `2 2 s/t/r`, `1 1 consonants` or `4 0 ^xyz` (any letter but
+    /// those). Letters locked by the template or --fix must be ones their masks allow.
+    #[arg(long, value_name = "FILE", conflicts_with = "cube")]
+    cell_masks: Option<String>,
+
+    /// Put a word in a given row or column, counted from 0, e.g. row:2:planet or col:0:stones;
+    /// may be given several times, and is locked into the template if there is one
+    #[arg(long, value_name = "LINE:INDEX:WORD", conflicts_with = "cube")]
+    fix: Vec<FixedWord>,
+
+    /// Hang every box off this word in its first column, e.g. `--asymmetric --spine stones`
+    /// finds boxes whose first row starts with s, second row with t and so on; in a symmetric
+    /// box the spine is the first row as well
+    #[arg(long, value_name = "WORD", conflicts_with = "cube")]
+    spine: Option<String>,
+
+    /// Allow blocks (`#` in a template) to leave single open cells, which must then be
+    /// one-letter words
+    #[arg(long)]
+    allow_single_letter_runs: bool,
+
+    /// Refuse templates whose blocks don't look the same after a half turn of the grid
+    #[arg(long)]
+    require_symmetry: bool,
+
+    /// Search for K×K×K word cubes instead of boxes: every row, column and pillar is a word.
+    /// Only --forward-checking and --distinct-words apply
+    #[arg(long, value_name = "K", conflicts_with_all = ["template", "template_file"])]
+    cube: Option<usize>,
+
+    /// Print each cube as a line of JSON instead of its layers (cubes only)
+    #[arg(long, requires = "cube")]
+    json: bool,
+
+    /// Try the seed words, and the candidates for every slot, in a shuffled order
+    #[arg(long)]
+    random_order: bool,
+
+    /// Seed for --random-order or --seed-order random, to repeat the order of an earlier run;
+    /// picked at random and printed if omitted
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Order to try the seed words in: lexicon (as the word list gives them), lexicographic,
+    /// random, or productive (the seeds whose letters start the most column words first, which
+    /// finds the first solutions sooner when only a few are wanted)
+    #[arg(long, default_value = "lexicon", conflicts_with = "cube")]
+    seed_order: SeedOrder,
+
+    /// Keep only this many of the most promising partial boxes at each row; much faster on big
+    /// grids, but solutions outside the beam are missed
+    #[arg(long, value_name = "WIDTH")]
+    beam_width: Option<usize>,
+
+    /// Also split each seed's search: expand this many rows below the seed, then search the
+    /// resulting boxes in parallel; helps when a few hard seeds outlast the rest
+    #[arg(long, default_value_t = 0)]
+    split_depth: usize,
+
+    /// How the seeds share the workers: sequential searches each seed to the end before taking
+    /// the next; fair keeps many seeds' searches open and gives each --slice nodes in turn, so
+    /// that a slow seed early in the list holds back no solutions of the easy ones after it
+    #[arg(long, default_value = "sequential", conflicts_with_all = ["cube", "split_depth"])]
+    schedule: Schedule,
+
+    /// Nodes each seed's search expands per turn under --schedule fair
+    #[arg(long, default_value_t = 1000)]
+    slice: usize,
+
+    /// Search only one share of the seed words, to split a run across machines: `2/8` takes the
+    /// seeds whose index in the sorted seed list leaves 2 when divided by 8. The statistics and
+    /// the --output file record the shard, for merging the shards' solutions afterwards
+    #[arg(long, value_name = "K/N", conflicts_with = "cube")]
+    shard: Option<Shard>,
+
+    /// Report the solutions in the order of the seed words, and of the search from each, however
+    /// the threads finish, so that repeated runs write identical output; each seed's solutions
+    /// wait until the seeds before it are done
+    #[arg(long)]
+    deterministic: bool,
+
+    /// Print the search statistics of each seed on stderr as it finishes, and their totals at
+    /// the end
+    #[arg(long)]
+    verbose: bool,
+
+    /// Print the totals of the search statistics as a line of JSON once the run is over
+    #[arg(long)]
+    stats: bool,
+
+    /// Report every solution as it is found, even one identical to a solution already reported
+    /// from another seed
+    #[arg(long)]
+    no_dedup: bool,
+
+    /// Also write every solution to this file, each size of a --rows/--cols range after the last
+    #[arg(long)]
+    output: Option<String>,
+
+    /// Write --output in chunks of this many solutions, numbered from sols-0001.txt for
+    /// --output sols.txt, which instead lists the chunks and the solutions each holds; merge
+    /// reads the list back. A run that stops midway still leaves every chunk whole.
+    #[arg(long, value_name = "N", requires = "output", conflicts_with = "cube")]
+    output_chunk_size: Option<usize>,
+
+    /// How to write the chunks of --output-chunk-size: none, or gzip, adding .gz to their names
+    #[cfg(feature = "gzip")]
+    #[arg(long, default_value = "none", requires = "output_chunk_size")]
+    compress: Compression,
+
+    /// Note under every solution, and in --db, where it came from: the word list's fingerprint,
+    /// the options that read and narrowed it, the box's size, the random seed, and the seed word
+    /// and rows chosen on the way to the box. `replay` builds the box again from the note.
+    #[arg(long, conflicts_with = "cube")]
+    provenance: bool,
+
+    /// Store every solution in this SQLite database, created if need be, with the run's command
+    /// line and a timestamp; a box found again, by this run or an earlier one, is updated rather
+    /// than stored twice. Each size of a --rows/--cols range is a run of its own.
+    #[cfg(feature = "db")]
+    #[arg(long, value_name = "FILE", conflicts_with = "cube")]
+    db: Option<String>,
+
+    /// Also lay out every solution in this PDF for printing, as many grids to a page as fit;
+    /// with --make-puzzle the puzzles come first and their answer key starts on a page of its own
+    #[cfg(feature = "pdf")]
+    #[arg(long, value_name = "FILE", conflicts_with = "cube")]
+    pdf: Option<String>,
+
+    /// The side of a grid cell in the --pdf, in points (1/72 inch); grids too big for the page
+    /// are shrunk to fit
+    #[cfg(feature = "pdf")]
+    #[arg(long, value_name = "PT", default_value_t = 24.0, requires = "pdf")]
+    pdf_cell_size: f64,
+
+    /// The blank border around each page of the --pdf, in points
+    #[cfg(feature = "pdf")]
+    #[arg(long, value_name = "PT", default_value_t = 36.0, requires = "pdf")]
+    pdf_margin: f64,
+
+    /// Also write every solution to a file of its own in this directory, created if need be,
+    /// named by --name-template
+    #[arg(long, value_name = "DIR", conflicts_with = "cube")]
+    output_dir: Option<String>,
+
+    /// The file names of --output-dir: {seed} is the seed word, {index} the solution's number
+    /// among the seed's, {global} its number among all the files written (both from 1), {rows}
+    /// and {cols} the box's size and {ext} the format's extension, txt; {index:04} pads to four
+    /// digits. When --rows or --cols is a range, a template without both {rows} and {cols}
+    /// gets `{rows}x{cols}_` in front, so the sizes do not overwrite each other's files
+    #[arg(
+        long,
+        default_value = "{seed}_{index:04}.{ext}",
+        requires = "output_dir"
+    )]
+    name_template: NameTemplate,
+
+    /// Replace files already in --output-dir instead of stopping at the first
+    #[arg(long, requires = "output_dir")]
+    overwrite: bool,
+
+    /// Once the run is over, print which words its solutions used: how many solutions used each
+    /// word, what share of all the solutions that is, and which rows and columns it filled
+    #[arg(long, conflicts_with = "cube")]
+    word_usage_report: bool,
+
+    /// Also write the word usage report to this file as CSV
+    #[arg(long, value_name = "FILE", requires = "word_usage_report")]
+    word_usage_csv: Option<String>,
+
+    /// Count the letters filling each cell across the solutions and write the counts to this file
+    /// as JSON once the run is over, also printing each cell's most common letter and its share
+    #[arg(long, value_name = "FILE", conflicts_with = "cube")]
+    letter_heatmap: Option<String>,
+
+    /// Once the run is over, write how each seed's search went to this file as CSV: its
+    /// solutions, nodes and time, and whether a per-seed budget cut it short. The most
+    /// productive seeds come first, and totals and medians close the file (single box sizes
+    /// only).
+    #[arg(long, value_name = "FILE", conflicts_with = "cube")]
+    seed_report: Option<String>,
+
+    /// Print each solution as a puzzle too: a grid showing some of its letters, with clue
+    /// numbers in the blank cells that start a word, and a word bank of the hidden words. The
+    /// solution itself is the answer key.
+    #[arg(long, conflicts_with = "cube")]
+    make_puzzle: bool,
+
+    /// The share of each puzzle's letters left showing, from 0 to 1; below 1, every word keeps
+    /// at least one blank cell, so a high share may show fewer letters
+    #[arg(
+        long,
+        value_name = "FRACTION",
+        default_value = "0.3",
+        requires = "make_puzzle"
+    )]
+    reveal: Reveal,
+
+    /// Seed choosing which letters the puzzles show (default: random, printed at the end)
+    #[arg(long, value_name = "N", requires = "make_puzzle")]
+    puzzle_seed: Option<u64>,
+
+    /// Check that no other box of the word lists fits the letters each puzzle shows, and show
+    /// more letters, one at a time, until none does or --max-extra-reveals is reached; each
+    /// check searches a limited number of nodes, so a puzzle may also come out undecided
+    #[arg(long, requires = "make_puzzle")]
+    verify_unique: bool,
+
+    /// Letters --verify-unique may show beyond the --reveal
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 8,
+        requires = "verify_unique"
+    )]
+    max_extra_reveals: usize,
+
+    /// Clue each puzzle's words across and down from this file of words and their definitions,
+    /// one per line with a tab between; words it lacks are clued "(no clue)" and listed once
+    /// the run is over
+    #[arg(long, value_name = "FILE", requires = "make_puzzle")]
+    definitions: Option<String>,
+
+    /// Write the search tree to this file in Graphviz DOT format: the boxes expanded, the
+    /// solutions, and why each dead end was abandoned (row search only, not --heuristic mcv)
+    #[arg(long, value_name = "FILE", conflicts_with = "cube")]
+    trace_dot: Option<String>,
+
+    /// Stop recording the search tree once it holds this many nodes
+    #[arg(long, value_name = "N", default_value_t = 5000, requires = "trace_dot")]
+    trace_limit: usize,
+
+    /// When a search runs to the end without a solution, explain why: the columns, slots and
+    /// constraints that cut its branches short, ranked by their share of the dead ends, and the
+    /// required letters that stopped fitting (also in --stats as "dead_ends")
+    #[arg(long)]
+    explain_failure: bool,
+
+    /// Keep only the K best solutions by --rank-by, and print them best first with their scores
+    /// once the run is over instead of each one as it is found
+    #[arg(long, value_name = "K")]
+    top: Option<usize>,
+
+    /// What --top ranks solutions by (score: how common the box's letters are in the word list;
+    /// theme: how many theme words from --theme-file the box uses; tier: how few words the box
+    /// takes from the later --tier lists; scrabble: the Scrabble value of its letters, see
+    /// --letter-values)
+    #[arg(long, default_value = "score", requires = "top")]
+    rank_by: RankBy,
+
+    /// Hold every solution until the run is over, then print them sorted: by score (as for
+    /// --rank-by), frequency (summed word frequencies, from --frequencies), theme (theme words
+    /// used, from --theme-file), tier (fewest words from the later --tier lists), scrabble
+    /// (highest Scrabble value, as for --rank-by), distinct-letters (most different letters),
+    /// alphabetical (the rows read one after another), seed (the order of the seed words) or
+    /// custom (highest --score first)
+    #[arg(long, conflicts_with_all = ["top", "cube"])]
+    sort_by: Option<SortBy>,
+
+    /// Rank by a weighted sum of metrics, e.g. `0.7*frequency + 0.3*theme`: --top keeps the
+    /// highest sums, --sort-by custom sorts by them and --order best and --beam-width expand the
+    /// partial boxes that sum highest first. The metrics are score, frequency, theme, rare,
+    /// tier, scrabble and distinct-letters, as for --sort-by (rare: how many words fall under
+    /// --rare-threshold); give one a negative weight to avoid it.
+    #[arg(
+        long,
+        value_name = "FORMULA",
+        allow_hyphen_values = true,
+        conflicts_with_all = ["rank_by", "cube"]
+    )]
+    score: Option<ScoreFormula>,
+
+    /// Read the --score formula from the `score = "..."` line of a TOML file
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["score", "rank_by", "cube"])]
+    score_file: Option<String>,
+
+    /// Pick the puzzle of a day, e.g. 2025-07-04: the first --daily-budget solutions, found in
+    /// the order of --deterministic, are held and one is drawn with a seed hashed from the date
+    /// (FNV-1a of `wordbox daily 2025-07-04`), favouring boxes of more frequent words when
+    /// --frequencies is given. The same word list, options and date pick the same box on any
+    /// machine and with any number of threads.
+    #[arg(
+        long,
+        value_name = "DATE",
+        conflicts_with_all = ["top", "sort_by", "limit", "random_order", "seed_order", "cube"]
+    )]
+    daily: Option<Daily>,
+
+    /// Solutions the daily puzzle is drawn from
+    #[arg(long, value_name = "N", default_value_t = 1000, requires = "daily")]
+    daily_budget: usize,
+
+    /// Word frequencies, one `word frequency` line per word, e.g. counts from a corpus
+    #[arg(long, value_name = "FILE")]
+    frequencies: Option<String>,
+
+    /// Rate how obscure each solution's words are and print it under the box: the rarest word
+    /// and its frequency percentile, and the mean log frequency of all the words
+    #[arg(long, requires = "frequencies")]
+    difficulty: bool,
+
+    /// Print each solution's Scrabble score under it: the values of its tiles summed over its
+    /// cells, each cell counted once
+    #[arg(long)]
+    scrabble_score: bool,
+
+    /// Tile values for Scrabble scores, as a TOML table of `letter = value` lines, e.g. for
+    /// another alphabet; the standard English values are used without it
+    #[arg(long, value_name = "FILE")]
+    letter_values: Option<String>,
+
+    /// Frequency taken for words the --frequencies file does not list, when rating difficulty
+    /// or telling rare words apart; the default of 0 makes them rarer than any word it lists
+    #[arg(
+        long,
+        value_name = "FREQUENCY",
+        default_value_t = 0.0,
+        requires = "frequencies"
+    )]
+    missing_frequency: f64,
+
+    /// Count the words below this frequency as rare and list them under each solution; either a
+    /// frequency or a percentile of the --frequencies list, e.g. p20
+    #[arg(long, value_name = "THRESHOLD", requires = "frequencies")]
+    rare_threshold: Option<FrequencyThreshold>,
+
+    /// Reject boxes using more than N rare words (see --rare-threshold)
+    #[arg(long, value_name = "N", requires = "rare_threshold")]
+    max_rare_words: Option<usize>,
+
+    /// Drop the words below this frequency from the word lists before the search, given like
+    /// --rare-threshold; a hard floor where --max-rare-words keeps a budget
+    #[arg(long, value_name = "THRESHOLD", requires = "frequencies")]
+    min_word_freq: Option<FrequencyThreshold>,
+
+    /// Theme words the solutions should use as many of as they can, one per line and filtered
+    /// like the word list: the search tries them before other words, and every solution lists
+    /// the ones it uses
+    #[arg(long, value_name = "FILE")]
+    theme_file: Option<String>,
+
+    /// Only accept boxes using at least N theme words
+    #[arg(long, value_name = "N", default_value_t = 0, requires = "theme_file")]
+    min_theme_words: usize,
+
+    /// Show a progress bar over the seed words on stderr
+    #[arg(long)]
+    progress: bool,
+
+    /// Every so often, e.g. 30s, report the run's status on a line of its own on stderr: seeds
+    /// done out of all of them, solutions, nodes per second, time taken and a naive ETA, as
+    /// `key=value` pairs in a fixed order. It works without a terminal, unlike --progress.
+    #[arg(long, value_name = "TIME", value_parser = parse_duration, conflicts_with = "cube")]
+    status_interval: Option<Duration>,
+
+    /// Write the status line to this file instead of stderr, replacing the last one each time
+    /// (every 30s unless --status-interval says otherwise)
+    #[arg(long, value_name = "FILE", conflicts_with = "cube")]
+    status_file: Option<PathBuf>,
+
+    /// Once the run is over, watch the word lists and --template-file for changes, and reload
+    /// them and run the same search again whenever they are saved; Ctrl-C stops watching. Only
+    /// for solving or counting a single box size, and not with --db or --daily, which record or
+    /// pick once; --output-dir needs --overwrite, as every run writes the same files
+    #[arg(long)]
+    watch: bool,
+
+    /// Print boxes without colours, as when stdout is not a terminal
+    #[arg(long)]
+    plain: bool,
+
+    /// Pick out the --fix and --spine words and the theme words of each solution printed, in
+    /// place of the seed row and the row placed last
+    #[arg(long)]
+    highlight_words: bool,
+
+    /// File of word categories, one `word tag[,tag...]` entry per line
+    #[arg(long)]
+    tag_file: Option<String>,
+
+    /// Remove words carrying any of these tags (requires --tag-file)
+    #[arg(long, value_delimiter = ',', requires = "tag_file")]
+    block_tags: Vec<String>,
+}
+
+impl Args {
+    /// The box size of a run of one size; a range of sizes only reaches `solve_sizes`, which
+    /// hands `solve` a copy of the arguments for each size in it
+    fn size(&self) -> (usize, usize) {
+        (self.rows.min, self.cols.min)
+    }
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    /// List the words of a given length starting with a prefix (`?` matches any letter)
+    Query { prefix: String, len: usize },
+
+    /// Count the boxes the options describe without printing any (implies --all); only one of
+    /// each box and its transpose is counted unless --no-symmetry-breaking is given
+    Count {
+        /// Count n×n boxes for every n in this range instead, e.g. 2..=5, and print a table of
+        /// the counts; only the search options (--order, --heuristic, --forward-checking and
+        /// the constraints on words and letters) apply
+        #[arg(long, value_name = "RANGE")]
+        sizes: Option<SizeRange>,
+
+        /// Nodes each size may search before its count is reported as a lower bound
+        #[arg(long, requires = "sizes")]
+        max_nodes: Option<usize>,
+
+        /// Seconds each size may take before its count is reported as a lower bound
+        #[arg(long, requires = "sizes")]
+        timeout: Option<u64>,
+
+        /// Also write the table to this file as CSV
+        #[arg(long, value_name = "FILE", requires = "sizes")]
+        csv: Option<String>,
+    },
+
+    /// Estimate how many boxes the options describe from random probes down the search tree,
+    /// without searching it all; counted like `count`, one of each box and its transpose unless
+    /// --no-symmetry-breaking is given
+    Estimate {
+        /// Number of probes; the standard error shrinks with its square root
+        #[arg(long, default_value_t = 1000)]
+        samples: usize,
+
+        /// Seed for the choices the probes make, to repeat an estimate; picked at random and
+        /// printed if omitted
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+
+    /// Compare build time, memory and solve time of every lexicon implementation
+    Bench {
+        /// Number of seed words to solve from per implementation
+        #[arg(long, default_value_t = 100)]
+        seeds: usize,
+
+        /// Seconds after which an implementation stops trying further seeds
+        #[arg(long, default_value_t = 10)]
+        timeout: u64,
+    },
+
+    /// Check every built-in lexicon implementation against the conformance suite (see
+    /// `lexicon::conformance`), on the suite's own words and a sample of the word list
+    Conformance {
+        /// Number of words of the word list to sample, spread evenly over it; 0 checks the
+        /// suite's own words only
+        #[arg(long, default_value_t = 100)]
+        words: usize,
+    },
+
+    /// Find the largest n for which the word list has an n×n box (symmetric unless --asymmetric),
+    /// trying each size in turn and printing a box for every size that has one
+    MaxSize {
+        /// Smallest size to try
+        #[arg(long, default_value_t = 2)]
+        min: usize,
+
+        /// Largest size to try
+        #[arg(long, default_value_t = 10)]
+        max: usize,
+
+        /// Nodes each size may search before it is reported as inconclusive
+        #[arg(long, default_value_t = 10_000_000)]
+        max_nodes: usize,
+
+        /// Seconds each size may take before it is reported as inconclusive
+        #[arg(long, default_value_t = 60)]
+        timeout: u64,
+    },
+
+    /// Load the word list once, then answer queries typed one per line (`prefix qu 6`,
+    /// `match s..ne.`, `complete ROW...`, `set rows 5`; `help` lists them all)
+    Repl,
+
+    /// Build a template in the terminal: move around a grid of --rows by --cols, or the one FILE
+    /// holds, typing letters and blocks into its cells, with the number of words each open slot
+    /// may still take shown as you go. Enter solves the template in the background and shows
+    /// its first completions; ctrl-s saves it to FILE in the format --template-file reads.
+    #[cfg(feature = "tui")]
+    Edit {
+        /// The template file to save to, and to start from if it exists
+        file: String,
+
+        /// Nodes each solve may search before giving up
+        #[arg(long, default_value_t = editor::DEFAULT_BUDGET)]
+        budget: usize,
+
+        /// Completions each solve shows before it stops
+        #[arg(long, default_value_t = editor::DEFAULT_SHOWN)]
+        show: usize,
+    },
+
+    /// Read a solved box from a file, one row per line, and list the boxes one row and one
+    /// column bigger that hold it in their top left corner (symmetric ones unless --asymmetric)
+    Grow {
+        /// The grid file
+        file: String,
+    },
+
+    /// Build a solution again from the provenance a run with --provenance noted under it,
+    /// checking each row against the word list instead of searching. Fails if the word list
+    /// is not the one the run read; the options that narrow the words, like
+    /// --exclude-letters, have to be given as they were for the run.
+    Replay {
+        /// The record, or a file holding records, such as an --output file, to replay them all
+        record: String,
+    },
+
+    /// Propose rotationally symmetric block patterns without runs of fewer than three letters
+    Patterns {
+        /// Number of blocked cells
+        #[arg(long)]
+        blocks: usize,
+
+        /// Maximum number of patterns to print
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+    },
+
+    /// Merge solution files written by --output, such as those of the shards of a --shard run,
+    /// into one, keeping each box once: a box and its transpose count as one, as the search
+    /// counts them. The merged boxes keep their notes but not their headings, and go to
+    /// --output if given, else to stdout; with --pdf they are also laid out for printing.
+    /// Records that cannot be read are reported and skipped.
+    Merge {
+        /// The solution files; an index of --output-chunk-size chunks stands for the chunks it
+        /// lists, and gzipped chunks need the gzip feature
+        #[arg(required = true)]
+        files: Vec<String>,
+
+        /// Skip the boxes with a word, across or down, that is not in --wordlist
+        #[arg(long)]
+        check: bool,
+
+        /// The order of the merged boxes: input (the files in the order given), alphabetical
+        /// (the rows read one after another), distinct-letters (most different letters first)
+        /// or size (fewest rows, then fewest columns, first)
+        #[arg(long, default_value = "alphabetical")]
+        sort: MergeOrder,
+
+        /// Keep a box and its transpose as two solutions, as from a run with
+        /// --no-symmetry-breaking or --col-wordlist
+        #[arg(long)]
+        keep_transposes: bool,
+    },
+}
+
+/// Draw a solution as it is found over the last one on a styled terminal; plain output, piped
+/// or under --plain, gets no escapes and each box simply follows the last
+fn print_clear(highlighting: &Highlighting, solution: &FoundSolution) {
+    match highlighting.styled {
+        true => {
+            terminal::clear();
+            terminal::redraw(&highlighting.live(solution));
+        }
+        false => print!("{}", highlighting.live(solution)),
+    }
+}
+
+/// Run every built-in lexicon through the conformance suite, exiting with an error if one fails
+fn conformance(words: &[String], sample: usize) {
+    let step = words.len().div_ceil(sample.max(1)).max(1);
+    let sampled: Vec<String> = match sample {
+        0 => vec![],
+        _ => words.iter().step_by(step).cloned().collect(),
+    };
+    let mut conforming = true;
+    for (name, report) in check_builtin_lexicons(&sampled) {
+        match report.is_conforming() {
+            true => println!("{}: ok ({} queries)", name, report.queries),
+            false => {
+                conforming = false;
+                println!(
+                    "{}: {} of {} queries wrong",
+                    name,
+                    report.failures.len(),
+                    report.queries
+                );
+                for failure in report.failures.iter().take(20) {
+                    println!("  {}", failure);
+                }
+            }
+        }
+    }
+    if !conforming {
+        std::process::exit(1);
+    }
+}
+
+/// How the boxes printed to the terminal are styled
+struct Highlighting {
+    styled: bool,               // false when stdout is not a terminal or --plain asks
+    words: Option<Vec<String>>, // the --fix and --spine words, under --highlight-words
+}
+
+impl Highlighting {
+    fn new(args: &Args) -> Self {
+        Highlighting {
+            styled: !args.plain && stdout().is_terminal(),
+            words: (args.highlight_words)
+                .then(|| fixed_words(args).into_iter().map(|fix| fix.word).collect()),
+        }
+    }
+
+    /// The fixed and theme words of `solution`, if --highlight-words asks for them
+    fn words(&self, solution: &FoundSolution) -> Option<StyledBox> {
+        let fixed = self.words.as_ref()?;
+        let picked =
+            |word: &str| (fixed.iter().chain(&solution.theme_words)).any(|picked| picked == word);
+        Some(StyledBox::words(&solution.word_box, picked))
+    }
+
+    /// A solution as it is found: its seed row and the row placed last stand out
+    fn live(&self, solution: &FoundSolution) -> String {
+        let styled = (self.words(solution)).unwrap_or_else(|| StyledBox::live(&solution.word_box));
+        styled.render(self.styled)
+    }
+
+    /// A solution printed once the run is over, plain but for the words picked out
+    fn held(&self, solution: &FoundSolution) -> String {
+        let styled = (self.words(solution)).unwrap_or_else(|| StyledBox::plain(&solution.word_box));
+        styled.render(self.styled)
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+    let start = Instant::now();
+    if let Some(digraphs) = &args.digraphs {
+        let outside = (digraphs.tiles().iter())
+            .find(|tile| !tile.as_str().chars().all(|ch| args.alphabet.contains(ch)));
+        if let Some(tile) = outside {
+            eprintln!(
+                "The tile {:?} has letters outside the alphabet",
+                tile.as_str()
+            );
+            std::process::exit(1);
+        }
+    }
+    if args.seed.is_some() && !args.random_order && args.seed_order != SeedOrder::Random {
+        eprintln!("--seed only applies to --random-order or --seed-order random");
+        std::process::exit(1);
+    }
+    // --digraphs is global, so clap cannot check it against --cube, which subcommands lack
+    if args.digraphs.is_some() && args.cube.is_some() {
+        eprintln!("--digraphs cannot be used with --cube");
+        std::process::exit(1);
+    }
+    if args.cube == Some(0) {
+        eprintln!("--cube needs cubes at least one letter wide");
+        std::process::exit(1);
+    }
+    if args.output_chunk_size == Some(0) {
+        eprintln!("--output-chunk-size needs chunks of at least one solution");
+        std::process::exit(1);
+    }
+    #[cfg(feature = "pdf")]
+    if !(args.pdf_cell_size > 0.0 && args.pdf_margin >= 0.0) {
+        eprintln!("--pdf-cell-size must be positive and --pdf-margin not negative");
+        std::process::exit(1);
+    }
+    if args.isogram && !args.asymmetric {
+        eprintln!(
+            "--isogram needs --asymmetric: a symmetric box holds every letter off its diagonal twice, once across and once down"
+        );
+        std::process::exit(1);
+    }
+    if args.allow_invalid_cols > 0 && !args.asymmetric {
+        eprintln!(
+            "--allow-invalid-cols needs --asymmetric: the columns of a symmetric box are its rows, which are always words"
+        );
+        std::process::exit(1);
+    }
+    let sweep = args.rows.min != args.rows.max || args.cols.min != args.cols.max;
+    let solves = args.cube.is_none()
+        && matches!(
+            args.command,
+            None | Some(Command::Count { sizes: None, .. })
+        );
+    if sweep && !solves {
+        eprintln!("--rows and --cols take a range only when solving or counting boxes");
+        std::process::exit(1);
+    }
+    if sweep && args.seed_report.is_some() {
+        eprintln!("--seed-report reports the seeds of a single box size; it cannot be combined with a range of --rows or --cols");
+        std::process::exit(1);
+    }
+    if sweep && (args.template.is_some() || args.template_file.is_some()) {
+        eprintln!(
+            "A template sets the box size; it cannot be combined with a range of --rows or --cols"
+        );
+        std::process::exit(1);
+    }
+    let other_command = matches!(
+        args.command,
+        Some(
+            Command::MaxSize { .. } | Command::Grow { .. } | Command::Count { sizes: Some(_), .. }
+        )
+    );
+    if args.rotational && other_command {
+        eprintln!("--rotational only applies to solving, counting and estimating single boxes");
+        std::process::exit(1);
+    }
+    if args.retry_inconclusive.is_some() && !seed_budget(&args).is_limited() {
+        eprintln!("--retry-inconclusive needs --per-seed-timeout or --per-seed-max-nodes");
+        std::process::exit(1);
+    }
+    if args.watch {
+        let one_size = solves && !sweep;
+        if !one_size || records_db(&args) || args.daily.is_some() {
+            eprintln!(
+                "--watch only re-runs solving or counting a single box size, without --db or --daily"
+            );
+            std::process::exit(1);
+        }
+        if args.output_dir.is_some() && !args.overwrite {
+            eprintln!("--watch writes the same --output-dir files on every run; pass --overwrite");
+            std::process::exit(1);
+        }
+    }
+    // Merging only reads the word list to check the boxes against it
+    let (tiers, words) = match &args.command {
+        Some(Command::Merge { check: false, .. }) => (None, vec![]),
+        _ => load_words(&args),
+    };
+
+    match &args.command {
+        Some(Command::Query { prefix, len }) => query(&args, words, prefix, *len),
+        Some(Command::Bench { seeds, timeout }) => {
+            let (rows, cols) = args.size();
+            let results = bench_all(&words, rows, cols, *seeds, Duration::from_secs(*timeout));
+            print!("{}", BenchTable(&results));
+        }
+        Some(Command::Conformance { words: sample }) => conformance(&words, *sample),
+        Some(Command::MaxSize { .. }) => max_size(&args, words),
+        Some(Command::Estimate { .. }) => estimate(&args, words),
+        Some(Command::Repl) => repl(&args, words),
+        #[cfg(feature = "tui")]
+        Some(Command::Edit { .. }) => edit(&args, words),
+        Some(Command::Grow { .. }) => grow(&args, words),
+        Some(Command::Replay { .. }) => replay(&args, words, tiers.as_ref()),
+        Some(Command::Patterns { blocks, limit }) => {
+            let (rows, cols) = args.size();
+            for mask in BlockMask::symmetric_patterns(rows, cols, *blocks, *limit) {
+                println!("{}", mask);
+            }
+        }
+        Some(Command::Merge { .. }) => merge(&args, words),
+        None if args.cube.is_some() => solve_cube(&args, words),
+        Some(Command::Count { sizes: Some(_), .. }) => count_sizes_table(&args, words),
+        None | Some(Command::Count { .. }) if sweep => solve_sizes(&args, words, tiers.as_ref()),
+        None | Some(Command::Count { .. }) if args.watch => watch(&args, words, tiers),
+        None | Some(Command::Count { .. }) => {
+            solve(&args, words, tiers.as_ref(), None, None);
+        }
+    }
+
+    let duration = start.elapsed();
+    println!("Time Duration: {:?}", duration);
+}
+
+fn query(args: &Args, words: Vec<String>, prefix: &str, len: usize) {
+    let prefix = parse_prefix(&encode(args, prefix), &args.alphabet).unwrap_or_else(|err| {
+        eprintln!("Invalid prefix: {}", err);
+        std::process::exit(1);
+    });
+    let lexicon = args
+        .lexicon
+        .build(words, BTreeSet::from([len]), &lexicon_options(args));
+    for word in lexicon.words_with_prefix(&prefix, len) {
+        println!("{}", digraphs::decode(&word));
+    }
+}
+
+fn grow(args: &Args, words: Vec<String>) {
+    let Some(Command::Grow { ref file }) = args.command else {
+        unreachable!()
+    };
+    let text = std::fs::read_to_string(file).expect("Could not open grid file");
+    // Boxes with tiles are written with a space between cells
+    let rows: Vec<String> = (text.lines().map(str::trim))
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            encode(
+                args,
+                &line.split_whitespace().collect::<String>().to_lowercase(),
+            )
+        })
+        .collect();
+    let Some(first) = rows.first() else {
+        eprintln!("Invalid grid: {} has no rows", file);
+        std::process::exit(1);
+    };
+    let (row_dim, col_dim) = (rows.len(), word_length(first));
+    fn invalid(err: impl std::fmt::Display) -> ! {
+        eprintln!("Invalid grid: {}", err);
+        std::process::exit(1);
+    }
+    let mut wb = WordBox::try_new(row_dim, col_dim, !args.asymmetric)
+        .unwrap_or_else(|err| invalid(err))
+        .with_alphabet(&args.alphabet);
+    for row in rows {
+        wb = wb.add_word(row).unwrap_or_else(|err| invalid(err));
+    }
+    if let Some(conflict) = wb.check_consistency().first() {
+        invalid(format!(
+            "a symmetric box reads the same down as across, but {} (pass --asymmetric for boxes that do not)",
+            conflict
+        ));
+    }
+    let lengths = BTreeSet::from([row_dim, col_dim, row_dim + 1, col_dim + 1]);
+    let lexicon = build_lexicon(args, exclude_letters(args, words), lengths, None);
+    if let Some(word) = wb.words().find(|word| {
+        !lexicon
+            .words_with_prefix(word, word_length(word))
+            .contains(&word.to_string())
+    }) {
+        invalid(format!("{:?} is not in the word list", word));
+    }
+
+    let grown = wb.try_grow(&lexicon);
+    if grown.is_empty() {
+        println!(
+            "Not extendable: no {}x{} box holds this one in its top left corner",
+            row_dim + 1,
+            col_dim + 1
+        );
+        return;
+    }
+    let boxes = match grown.len() {
+        1 => "1 box".to_string(),
+        count => format!("{} boxes", count),
+    };
+    println!("Extends to {} of {}x{}:", boxes, row_dim + 1, col_dim + 1);
+    for grown in &grown {
+        println!();
+        print!("{}", grown);
+    }
+}
+
+/// Build the boxes of the provenance records of `replay` again, exiting with an error if one
+/// cannot be
+fn replay(args: &Args, words: Vec<String>, tiers: Option<&WordTiers>) {
+    let Some(Command::Replay { ref record }) = args.command else {
+        unreachable!()
+    };
+    let parse = |place: String, line: &str| match line.parse::<Provenance>() {
+        Ok(provenance) => (place, provenance),
+        Err(err) => {
+            eprintln!("Invalid provenance record at {}: {}", place, err);
+            std::process::exit(1);
+        }
+    };
+    let records: Vec<(String, Provenance)> = match std::path::Path::new(record).is_file() {
+        true => {
+            let text = std::fs::read_to_string(record).expect("Could not open record file");
+            (text.lines().enumerate())
+                .filter(|(_, line)| line.contains(provenance::TAG))
+                .map(|(i, line)| parse(format!("{} line {}", record, i + 1), line))
+                .collect()
+        }
+        false => vec![parse("the command line".to_string(), record)],
+    };
+    if records.is_empty() {
+        eprintln!("No provenance records in {}", record);
+        std::process::exit(1);
+    }
+
+    let word_list = WordListId::of(&words);
+    let frequencies = load_frequencies(args);
+    let words = drop_infrequent(args, frequencies.as_deref(), exclude_letters(args, words));
+    let col_words = (args.col_wordlist.as_ref()).map(|path| {
+        let words = exclude_letters(args, read_word_list(args, path));
+        drop_infrequent(args, frequencies.as_deref(), words)
+    });
+    let lengths: BTreeSet<usize> = (records.iter())
+        .flat_map(|(_, record)| [record.row_dim, record.col_dim])
+        .collect();
+    // The rows are chosen by their place in the lexicon, so it has to be of the run's kind
+    let mut lexicons: Vec<(LexiconKind, DynLexicon, Option<DynLexicon>)> = vec![];
+    let mut failed = 0;
+    for (place, record) in &records {
+        let kind = record.run.lexicon;
+        if !lexicons.iter().any(|(built, ..)| *built == kind) {
+            let args = Args {
+                lexicon: kind,
+                ..args.clone()
+            };
+            let lexicon = build_lexicon(&args, words.clone(), lengths.clone(), tiers);
+            let col_lexicon =
+                (col_words.clone()).map(|words| build_lexicon(&args, words, lengths.clone(), None));
+            lexicons.push((kind, lexicon, col_lexicon));
+        }
+        let (_, lexicon, col_lexicon) = (lexicons.iter())
+            .find(|(built, ..)| *built == kind)
+            .expect("built above");
+        let lexicons = RowColumnLexicons {
+            rows: lexicon,
+            cols: col_lexicon.as_ref().unwrap_or(lexicon),
+        };
+        match record.replay(word_list, &lexicons) {
+            Ok(wb) => print!("{}:\n{}\n", place, wb),
+            Err(err) => {
+                failed += 1;
+                eprintln!("Cannot replay the record at {}: {}", place, err);
+                let run = &record.run;
+                let options: Vec<&String> =
+                    run.normalization.iter().chain(&run.constraints).collect();
+                if !options.is_empty() {
+                    let options: Vec<String> =
+                        options.iter().map(|option| shell_quote(option)).collect();
+                    eprintln!("The run was given {}", options.join(" "));
+                }
+            }
+        }
+    }
+    if failed > 0 {
+        eprintln!(
+            "{} of {} records could not be replayed",
+            failed,
+            records.len()
+        );
+        std::process::exit(1);
+    }
+}
+
+/// Merge the files of `merge` into one, reporting the records skipped and the duplicates
+fn merge(args: &Args, words: Vec<String>) {
+    let Some(Command::Merge {
+        ref files,
+        check,
+        sort,
+        keep_transposes,
+    }) = args.command
+    else {
+        unreachable!()
+    };
+    let words: Option<HashSet<String>> = check.then(|| words.into_iter().collect());
+    let mut merged = MergedSolutions::new(keep_transposes);
+    let (mut read, mut skipped) = (0, 0);
+    for (file, bytes) in files.iter().flat_map(|file| merge_inputs(file)) {
+        // A file that is not all UTF-8 loses only the records with the bad bytes
+        let text = match bytes {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(err) => {
+                eprintln!("Could not read {}: {}; skipping it", file, err);
+                continue;
+            }
+        };
+        let (records, mut problems) =
+            read_solutions(&text, &args.alphabet, |row| encode(args, row));
+        let mut kept = 0;
+        for record in records {
+            if let Some(word) = (words.as_ref()).and_then(|words| record.missing_word(words)) {
+                problems.push(RecordProblem {
+                    line: record.line,
+                    error: RecordError::NotAWord(word.to_string()),
+                });
+                continue;
+            }
+            kept += 1;
+            merged.add(record);
+        }
+        problems.sort_by_key(|problem| problem.line);
+        for problem in &problems {
+            eprintln!("{}: {}", file, problem);
+        }
+        eprintln!(
+            "{}: {} boxes read, {} records skipped",
+            file,
+            kept,
+            problems.len()
+        );
+        read += kept;
+        skipped += problems.len();
+    }
+    let summary = format!(
+        "Merged {} files: {} boxes read, {} records skipped, {} duplicates removed, {} unique solutions",
+        files.len(),
+        read,
+        skipped,
+        merged.duplicates(),
+        merged.len()
+    );
+    let solutions = merged.sorted(sort);
+
+    #[cfg(feature = "pdf")]
+    if let Some(path) = &args.pdf {
+        let mut pdf = PdfBook::new(PdfLayout {
+            cell_size: args.pdf_cell_size,
+            margin: args.pdf_margin,
+        });
+        for record in &solutions {
+            pdf.add(None, &record.word_box, None);
+        }
+        std::fs::write(path, pdf.to_bytes()).expect("Could not write PDF file");
+        println!(
+            "Laid out {} solutions on {} pages in {}",
+            pdf.len(),
+            pdf.page_count(),
+            path
+        );
+    }
+    match solution_output(args, false) {
+        Some(mut output) => {
+            writeln!(output, "# merged from {} files\n", files.len())
+                .expect("Could not write solution");
+            for record in &solutions {
+                writeln!(output, "{}", record).expect("Could not write solution");
+                output.end_record().expect("Could not write solution");
+            }
+            output.finish().expect("Could not write solution");
+        }
+        None => {
+            let mut output = stdout().lock();
+            writeln!(output, "# merged from {} files\n", files.len())
+                .expect("Could not write solution");
+            for record in &solutions {
+                writeln!(output, "{}", record).expect("Could not write solution");
+            }
+            output.flush().expect("Could not write solution");
+        }
+    }
+    // The summary goes to stderr when the solutions take stdout
+    match args.output {
+        Some(_) => println!("{}", summary),
+        None => eprintln!("{}", summary),
+    }
+}
+
+/// The files merge reads for `file`: the chunks it lists if it is a chunk index, else the file
+/// itself, uncompressed if it is gzipped, each named as the messages about it call it
+fn merge_inputs(file: &str) -> Vec<(String, std::io::Result<Vec<u8>>)> {
+    let bytes = match chunks::read_chunk(file.as_ref()) {
+        Ok(bytes) if chunks::is_index(&bytes) => bytes,
+        read => return vec![(file.to_string(), read)],
+    };
+    match chunks::read_index(&String::from_utf8_lossy(&bytes)) {
+        Ok(entries) => (entries.iter())
+            .map(|entry| {
+                let path = std::path::Path::new(file).with_file_name(&entry.name);
+                (path.display().to_string(), chunks::read_chunk(&path))
+            })
+            .collect(),
+        Err(err) => {
+            let err = std::io::Error::new(std::io::ErrorKind::InvalidData, err);
+            vec![(file.to_string(), Err(err))]
+        }
+    }
+}
+
+fn repl(args: &Args, words: Vec<String>) {
+    let words = exclude_letters(args, words);
+    // Queries may ask about words of any length
+    let lengths: BTreeSet<usize> = words.iter().map(|word| word_length(word)).collect();
+    let lexicon = build_lexicon(args, words, lengths, None);
+    let (rows, cols) = args.size();
+    let mut repl = Repl::new(
+        &lexicon,
+        args.alphabet.clone(),
+        rows,
+        cols,
+        !args.asymmetric,
+    )
+    .with_styles(!args.plain && stdout().is_terminal());
+    let stdin = std::io::stdin();
+    let prompt = stdin.is_terminal().then_some("wordbox> ");
+    if prompt.is_some() {
+        println!("Type help for the list of commands");
+    }
+    repl.run(stdin.lock(), &mut stdout(), prompt)
+        .expect("Could not read commands");
+}
+
+#[cfg(feature = "tui")]
+fn edit(args: &Args, words: Vec<String>) {
+    let Some(Command::Edit {
+        ref file,
+        budget,
+        show,
+    }) = args.command
+    else {
+        unreachable!()
+    };
+    let template = match std::fs::read_to_string(file) {
+        Ok(text) => Template::parse(&encode(args, &text), &args.alphabet).unwrap_or_else(|err| {
+            eprintln!("Invalid template in {}: {}", file, err);
+            std::process::exit(1);
+        }),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            let (rows, cols) = args.size();
+            Template::open(rows, cols)
+        }
+        Err(err) => {
+            eprintln!("Could not read {}: {}", file, err);
+            std::process::exit(1);
+        }
+    };
+    let (rows, cols) = (template.row_dim(), template.col_dim());
+    if !args.asymmetric && rows != cols {
+        eprintln!(
+            "A {}x{} template cannot be symmetric; pass --asymmetric to edit it",
+            rows, cols
+        );
+        std::process::exit(1);
+    }
+    let words = exclude_letters(args, words);
+    // Blocks split rows and columns into words of any length up to the box's
+    let lengths: BTreeSet<usize> = (1..=rows.max(cols)).collect();
+    let lexicon = build_lexicon(args, words, lengths, None);
+    let mut editor = Editor::new(
+        &lexicon,
+        args.alphabet.clone(),
+        template,
+        !args.asymmetric,
+        file.clone(),
+    )
+    .with_budget(budget)
+    .with_shown(show);
+    editor.run().unwrap_or_else(|err| {
+        eprintln!("Could not draw the editor: {}", err);
+        std::process::exit(1);
+    });
+}
+
+fn max_size(args: &Args, words: Vec<String>) {
+    let Some(Command::MaxSize {
+        min,
+        max,
+        max_nodes,
+        timeout,
+    }) = args.command
+    else {
+        unreachable!()
+    };
+    if min == 0 {
+        eprintln!("--min must be at least 1: a box needs at least one row and one column");
+        std::process::exit(1);
+    }
+    let words = exclude_letters(args, words);
+    let options = MaxSizeOptions {
+        symmetric: !args.asymmetric,
+        lexicon: args.lexicon,
+        max_nodes,
+        timeout: Duration::from_secs(timeout),
+        cancellation: CancellationToken::new(),
+    };
+    let interrupt = options.cancellation.clone();
+    ctrlc::set_handler(move || interrupt.cancel()).ok();
+
+    let mut largest = None;
+    let mut inconclusive = vec![];
+    search_sizes(&words, min..=max, &options, |result| {
+        print!("{}", result);
+        match result.verdict {
+            SizeVerdict::Solvable(_) => largest = Some(result.size),
+            SizeVerdict::Impossible => {}
+            SizeVerdict::Inconclusive => inconclusive.push(result.size),
+        }
+    });
+    match largest {
+        Some(size) => println!("Largest box found: {}x{}", size, size),
+        None => println!("No box found between {0}x{0} and {1}x{1}", min, max),
+    }
+    let undecided: Vec<String> = inconclusive
+        .iter()
+        .filter(|&&size| largest.is_none_or(|largest| size > largest))
+        .map(|size| format!("{0}x{0}", size))
+        .collect();
+    if !undecided.is_empty() {
+        println!(
+            "Not settled within the budget: {}; boxes of those sizes may still exist",
+            undecided.join(", ")
+        );
+    }
+}
+
+fn count_sizes_table(args: &Args, words: Vec<String>) {
+    let Some(Command::Count {
+        sizes: Some(sizes),
+        max_nodes,
+        timeout,
+        ref csv,
+    }) = args.command
+    else {
+        unreachable!()
+    };
+    if args.template.is_some()
+        || args.template_file.is_some()
+        || args.cell_masks.is_some()
+        || !fixed_words(args).is_empty()
+        || args.col_wordlist.is_some()
+    {
+        eprintln!(
+            "--sizes counts plain boxes of every size; it cannot be combined with a template, cell masks, fixed words or a column word list"
+        );
+        std::process::exit(1);
+    }
+    let words = exclude_letters(args, words);
+    let config = SolverConfig {
+        order: args.order,
+        heuristic: args.heuristic,
+        value_order: args.value_order,
+        forward_checking: args.forward_checking,
+        symmetry_breaking: !args.no_symmetry_breaking,
+        distinct_words: args.distinct_words,
+        diagonal_word: args.diagonal_word,
+        anti_diagonal_word: args.anti_diagonal_word,
+        required_letters: args.require_letters.clone(),
+        letter_bounds: args.letter_bounds.clone(),
+        min_distinct_letters: args.min_distinct_letters.unwrap_or(0),
+        vowel_rule: vowel_rule(args),
+        forbidden_words: forbidden_words(args),
+        isogram: args.isogram,
+        query_cache: args.query_cache,
+        ..SolverConfig::default()
+    };
+    let options = CountOptions {
+        symmetric: !args.asymmetric,
+        lexicon: args.lexicon,
+        max_nodes,
+        timeout: timeout.map(Duration::from_secs),
+        cancellation: CancellationToken::new(),
+    };
+    let interrupt = options.cancellation.clone();
+    ctrlc::set_handler(move || interrupt.cancel()).ok();
+
+    let mut counts = vec![];
+    count_sizes(&words, sizes.sizes(), &config, &options, |count| {
+        if args.verbose {
+            eprintln!("{0}x{0}: {1}", count.size, count.report.stats);
+        }
+        counts.push(count);
+    });
+    let table = SizeTable(&counts);
+    print!("{}", table);
+    if let Some(path) = csv {
+        std::fs::write(path, table.to_csv()).expect("Could not write CSV file");
+    }
+}
+
+fn estimate(args: &Args, words: Vec<String>) {
+    let Some(Command::Estimate { samples, seed }) = args.command else {
+        unreachable!()
+    };
+    if args.template.is_some()
+        || args.template_file.is_some()
+        || args.cell_masks.is_some()
+        || !fixed_words(args).is_empty()
+        || args.col_wordlist.is_some()
+    {
+        eprintln!(
+            "estimate samples plain boxes; it cannot be combined with a template, cell masks, fixed words or a column word list"
+        );
+        std::process::exit(1);
+    }
+    let (rows, cols) = args.size();
+    let mut empty = WordBox::try_new(rows, cols, !args.asymmetric).unwrap_or_else(|err| {
+        eprintln!("Cannot estimate: {}", err);
+        std::process::exit(1);
+    });
+    if args.rotational {
+        empty = empty.with_rotation();
+    }
+    let words = exclude_letters(args, words);
+    let lengths = BTreeSet::from([rows, cols]);
+    let stats = LexiconStats::from_words(&words, cols);
+    warn_missing_lengths(&args.wordlist, &stats, &lengths, (rows, cols));
+    let lexicon = build_lexicon(args, words, lengths, None);
+    let cancellation = CancellationToken::new();
+    let interrupt = cancellation.clone();
+    ctrlc::set_handler(move || interrupt.cancel()).ok();
+    let config = SolverConfig {
+        forward_checking: args.forward_checking,
+        symmetry_breaking: !args.no_symmetry_breaking,
+        distinct_words: args.distinct_words,
+        diagonal_word: args.diagonal_word,
+        anti_diagonal_word: args.anti_diagonal_word,
+        required_letters: args.require_letters.clone(),
+        letter_bounds: args.letter_bounds.clone(),
+        min_distinct_letters: args.min_distinct_letters.unwrap_or(0),
+        vowel_rule: vowel_rule(args),
+        forbidden_words: forbidden_words(args),
+        isogram: args.isogram,
+        cancellation: Some(cancellation),
+        ..SolverConfig::default()
+    };
+    let seed = seed.unwrap_or_else(rand::random);
+    let estimate = estimate_solutions(empty.clone(), &lexicon, &config, samples, seed);
+    let symmetry = match args.asymmetric {
+        true => "asymmetric",
+        false => "symmetric",
+    };
+    println!("{}x{} {}: {}", rows, cols, symmetry, estimate);
+    if config.breaks_transpose_symmetry() && empty.has_transpose_twin() {
+        println!("(one of each box and its transpose; pass --no-symmetry-breaking to count both)");
+    }
+    if estimate.samples < samples {
+        println!(
+            "Interrupted after {} of {} probes",
+            estimate.samples, samples
+        );
+    }
+    println!(
+        "Seed: {} (pass --seed {} to repeat this estimate)",
+        seed, seed
+    );
+    print_search_stats(
+        args,
+        &estimate.stats,
+        &CellMasks::default(),
+        None,
+        None,
+        None,
+        None,
+    );
+}
+
+/// The words of --forbid-word and --forbid-file, if any
+fn forbidden_words(args: &Args) -> Option<Arc<HashSet<String>>> {
+    let mut forbidden: HashSet<String> = (args.forbid_word.iter())
+        .map(|word| encode(args, &word.to_lowercase()))
+        .collect();
+    if let Some(path) = &args.forbid_file {
+        forbidden.extend(read_word_list(args, path));
+    }
+    (!forbidden.is_empty()).then(|| Arc::new(forbidden))
+}
+
+/// The words --fix and --spine put in given rows and columns
+fn fixed_words(args: &Args) -> Vec<FixedWord> {
+    let spine = args.spine.as_ref().map(|word| FixedWord {
+        // Column 0 of a symmetric box is its first row
+        line: if args.asymmetric {
+            Line::Col
+        } else {
+            Line::Row
+        },
+        index: 0,
+        word: word.to_lowercase(),
+    });
+    (args.fix.iter().cloned().chain(spine))
+        .map(|fix| FixedWord {
+            word: encode(args, &fix.word),
+            ..fix
+        })
+        .collect()
+}
+
+fn load_template(args: &Args) -> Option<Template> {
+    let text = match (&args.template, &args.template_file) {
+        (Some(text), _) => Some(text.clone()),
+        (None, Some(path)) => {
+            Some(std::fs::read_to_string(path).expect("Could not open template file"))
+        }
+        (None, None) if !fixed_words(args).is_empty() || args.cell_masks.is_some() => None,
+        (None, None) => return None,
+    };
+    let mut template = match text {
+        Some(text) => Template::parse(&encode(args, &text), &args.alphabet).unwrap_or_else(|err| {
+            eprintln!("Invalid template: {}", err);
+            std::process::exit(1);
+        }),
+        // Fixed words and cell masks need a template to be kept in
+        None => {
+            let (rows, cols) = args.size();
+            Template::open(rows, cols)
+        }
+    };
+    for fix in &fixed_words(args) {
+        template = template
+            .with_fixed(fix, &args.alphabet)
+            .unwrap_or_else(|err| {
+                eprintln!("Cannot fix a word in place: {}", err);
+                std::process::exit(1);
+            });
+    }
+    if let Some(path) = &args.cell_masks {
+        let text = std::fs::read_to_string(path).expect("Could not open cell mask file");
+        let masks = CellMasks::parse(&encode(args, &text), &args.alphabet).unwrap_or_else(|err| {
+            eprintln!("Invalid cell masks: {}", err);
+            std::process::exit(1);
+        });
+        template = template.with_masks(&masks).unwrap_or_else(|err| {
+            eprintln!("Cannot apply cell masks: {}", err);
+            std::process::exit(1);
+        });
+    }
+    if !args.asymmetric {
+        if let Some((row, col)) = template.mirror_conflict() {
+            eprintln!(
+                "No solutions: a symmetric box cannot have {:?} at row {}, column {} and {:?} opposite it",
+                template.get(row, col).unwrap(),
+                row,
+                col,
+                template.get(col, row).unwrap()
+            );
+            std::process::exit(1);
+        }
+        return Some(template.mirrored());
+    }
+    Some(template)
+}
+
+/// The words of the list at `path`, with uppercase letters treated as the flags ask; says on
+/// stderr what became of the lines that were not plain lowercase words
+/// `text` with the --digraphs tiles in it turned into their cells
+fn encode(args: &Args, text: &str) -> String {
+    match &args.digraphs {
+        Some(digraphs) => digraphs.encode(text),
+        None => text.to_string(),
+    }
+}
+
+/// The filter every word list of the run is read through
+fn word_filter(args: &Args) -> WordFilter {
+    let case = if args.drop_uppercase {
+        CaseMode::DropUppercase
+    } else if args.drop_proper_nouns {
+        CaseMode::DropProperNouns
+    } else {
+        CaseMode::Lowercase
+    };
+    WordFilter {
+        case,
+        alphabet: args.alphabet.clone(),
+        strip: args.strip_chars.chars().collect(),
+        keep_dropped: args.show_rejects,
+        strict: args.strict,
+    }
+}
+
+/// The words of --wordlist, or of every --tier list along with the tier of each word
+fn load_words(args: &Args) -> (Option<WordTiers>, Vec<String>) {
+    match args.tier.is_empty() {
+        true => (None, read_word_list(args, &args.wordlist)),
+        false => {
+            let lists = args.tier.iter().map(|path| read_word_list(args, path));
+            let (tiers, words) = WordTiers::new(lists.collect());
+            (Some(tiers), words)
+        }
+    }
+}
+
+fn read_word_list(args: &Args, path: &str) -> Vec<String> {
+    let filter = word_filter(args);
+    let loaded = load_word_list(path, args.input_format, &args.csv_column, &filter);
+    let (words, report, format) = loaded.unwrap_or_else(|err| {
+        eprintln!("Cannot read word list {}: {}", path, err);
+        std::process::exit(1);
+    });
+    if report.is_notable() || args.show_rejects > 0 {
+        eprintln!("{}: {}", path, report);
+    }
+    for dropped in &report.dropped_lines {
+        eprintln!("  {}", dropped);
+    }
+    let unlisted = report.dropped() - report.dropped_lines.len();
+    if args.show_rejects > 0 && unlisted > 0 {
+        eprintln!("  and {} more", unlisted);
+    }
+    if format != InputFormat::Text && args.input_format == InputFormat::Auto {
+        eprintln!("{}: read as {}", path, format.name());
+    }
+    match &args.digraphs {
+        Some(digraphs) => words.iter().map(|word| digraphs.encode(word)).collect(),
+        None => words,
+    }
+}
+
+/// The definitions at --definitions, their words normalized as the word list's are except that
+/// capitalized headwords are lowercased rather than dropped, with a warning about the words
+/// defined twice
+fn load_definitions(args: &Args, path: &str) -> Definitions {
+    let filter = WordFilter {
+        case: CaseMode::Lowercase,
+        ..word_filter(args)
+    };
+    let normalize = |word: &str| filter.normalize(word).map(|word| encode(args, &word));
+    let definitions = Definitions::load(path, normalize).unwrap_or_else(|err| {
+        eprintln!("Invalid definitions file {}: {}", path, err);
+        std::process::exit(1);
+    });
+    if !definitions.repeated.is_empty() {
+        eprintln!(
+            "Warning: {} defines {} more than once; the last definition of each is used",
+            path,
+            spelled_out(&definitions.repeated)
+        );
+    }
+    if definitions.unusable > 0 {
+        eprintln!(
+            "Warning: {} of the words in {} can never be in a box and were skipped",
+            definitions.unusable, path
+        );
+    }
+    definitions
+}
+
+/// The theme words listed at `path`, warning about the ones no box in the search can use as
+/// they are missing from the word list or have none of the `lengths` it needs
+fn load_theme_words(
+    args: &Args,
+    path: &str,
+    words: &[String],
+    lengths: &BTreeSet<usize>,
+) -> ThemeWords {
+    let theme = ThemeWords::new(read_word_list(args, path));
+    let usable: HashSet<&str> = words
+        .iter()
+        .filter(|word| theme.contains(word) && lengths.contains(&word_length(word)))
+        .map(String::as_str)
+        .collect();
+    if usable.len() < theme.len() {
+        eprintln!(
+            "Warning: {} of the {} theme words in {} are not in the word list at the lengths the box needs and can never be used",
+            theme.len() - usable.len(),
+            theme.len(),
+            path
+        );
+    }
+    if usable.len() < args.min_theme_words && args.distinct_words {
+        eprintln!(
+            "Warning: only {} theme words can be used, fewer than --min-theme-words {}",
+            usable.len(),
+            args.min_theme_words
+        );
+    }
+    theme
+}
+
+/// Drop the words below --min-word-freq, reporting how many were removed
+/// The word frequencies at --frequencies, read with the tiles of --digraphs
+fn load_frequencies(args: &Args) -> Option<Arc<WordFrequencies>> {
+    args.frequencies.as_ref().map(|path| {
+        let frequencies = match &args.digraphs {
+            Some(digraphs) => {
+                let text = std::fs::read_to_string(path).expect("Could not open frequency file");
+                WordFrequencies::from_lines(text.lines().map(|line| digraphs.encode(line)))
+            }
+            None => WordFrequencies::load(path),
+        };
+        Arc::new(frequencies.unwrap_or_else(|err| {
+            eprintln!("Invalid frequency file {}: {}", path, err);
+            std::process::exit(1);
+        }))
+    })
+}
+
+fn drop_infrequent(
+    args: &Args,
+    frequencies: Option<&WordFrequencies>,
+    words: Vec<String>,
+) -> Vec<String> {
+    let (Some(frequencies), Some(threshold)) = (frequencies, args.min_word_freq) else {
+        return words;
+    };
+    let floor = frequencies.cutoff(threshold);
+    let total = words.len();
+    let words: Vec<String> = words
+        .into_iter()
+        .filter(|word| frequencies.get(word).unwrap_or(args.missing_frequency) >= floor)
+        .collect();
+    println!(
+        "Dropped {} words below the frequency {} ({})",
+        total - words.len(),
+        floor,
+        threshold
+    );
+    words
+}
+
+fn vowel_rule(args: &Args) -> VowelRule {
+    VowelRule {
+        vowels: args.vowels.clone(),
+        per_line: args.require_vowel_per_line,
+        max_consonant_run: args.max_consonant_run,
+    }
+}
+
+fn exclude_letters(args: &Args, words: Vec<String>) -> Vec<String> {
+    let conflicts: String = args
+        .exclude_letters
+        .conflicts(&args.require_letters)
+        .collect();
+    if !conflicts.is_empty() {
+        eprintln!(
+            "Cannot both require and exclude the letters {:?}",
+            conflicts
+        );
+        std::process::exit(1);
+    }
+    let outside: String = args
+        .require_letters
+        .letters()
+        .filter(|&ch| !args.alphabet.contains(ch))
+        .collect();
+    if !outside.is_empty() {
+        eprintln!(
+            "Cannot require the letters {:?}, which are not in the alphabet",
+            outside
+        );
+        std::process::exit(1);
+    }
+    for (letter, bound) in args.letter_bounds.bounds() {
+        if !args.alphabet.contains(letter) {
+            eprintln!(
+                "Cannot bound the letter {:?}, which is not in the alphabet",
+                letter
+            );
+            std::process::exit(1);
+        }
+        if bound.min > 0 && args.exclude_letters.contains(letter) {
+            eprintln!(
+                "Cannot both exclude the letter {:?} and ask for at least {} of it",
+                letter, bound.min
+            );
+            std::process::exit(1);
+        }
+        let required = args.require_letters.count(letter);
+        if let Some(max) = bound.max.filter(|&max| required > max) {
+            eprintln!(
+                "Cannot require the letter {:?} {} times and allow at most {}",
+                letter, required, max
+            );
+            std::process::exit(1);
+        }
+    }
+    if args.exclude_letters.is_empty() {
+        return words;
+    }
+    let (words, removed) = args.exclude_letters.remove_from(words);
+    println!(
+        "Excluded {} words containing any of {:?}",
+        removed,
+        args.exclude_letters.to_string()
+    );
+    words
+}
+
+/// Inconclusive seeds listed at the end of a run; the rest are only counted
+const INCONCLUSIVE_SHOWN: usize = 10;
+
+fn solve(
+    args: &Args,
+    words: Vec<String>,
+    tiers: Option<&WordTiers>,
+    sweep: Option<&SweepSize>,
+    interrupt: Option<&CancellationToken>,
+) -> SizeSolutions {
+    // Find all word boxes of row_dim x col_dim, or only count them
+    let start = Instant::now();
+    let count_only = matches!(args.command, Some(Command::Count { .. }));
+    if count_only && (args.top.is_some() || args.sort_by.is_some() || args.daily.is_some()) {
+        eprintln!(
+            "Cannot rank, sort or pick solutions that are only counted; drop --top, --sort-by, --daily or the count command"
+        );
+        std::process::exit(1);
+    }
+    let word_list = WordListId::of(&words);
+    let frequencies = load_frequencies(args);
+    let rare_words = frequencies
+        .as_ref()
+        .zip(args.rare_threshold)
+        .map(|(freqs, threshold)| {
+            Arc::new(RareWords::new(
+                freqs.clone(),
+                threshold,
+                args.missing_frequency,
+            ))
+        });
+    let formula = score_formula(args);
+    if args.sort_by == Some(SortBy::Custom) && formula.is_none() {
+        eprintln!("Sorting by custom needs a formula; pass --score or --score-file");
+        std::process::exit(1);
+    }
+    let best_first = args.order == SearchOrder::BestFirst || args.beam_width.is_some();
+    if formula.is_some()
+        && args.top.is_none()
+        && args.sort_by != Some(SortBy::Custom)
+        && !best_first
+        && !records_db(args)
+    {
+        eprintln!(
+            "A --score formula ranks solutions for --top, --sort-by custom, --order best or --beam-width; pass one of them"
+        );
+        std::process::exit(1);
+    }
+    let scores = |metric: Metric| formula.as_ref().is_some_and(|f| f.uses(metric));
+    if (args.sort_by == Some(SortBy::Frequency) || scores(Metric::Frequency))
+        && frequencies.is_none()
+    {
+        eprintln!("Scoring by frequency needs word frequencies; pass --frequencies FILE");
+        std::process::exit(1);
+    }
+    if scores(Metric::Rare) && rare_words.is_none() {
+        eprintln!("Scoring by rare words needs --frequencies FILE and --rare-threshold");
+        std::process::exit(1);
+    }
+    let by_theme = args.sort_by == Some(SortBy::Theme)
+        || (args.top.is_some() && args.rank_by == RankBy::Theme && formula.is_none())
+        || scores(Metric::Theme);
+    if by_theme && args.theme_file.is_none() {
+        eprintln!("Ranking by theme words needs a list of them; pass --theme-file FILE");
+        std::process::exit(1);
+    }
+    let by_scrabble = args.sort_by == Some(SortBy::Scrabble)
+        || (args.top.is_some() && args.rank_by == RankBy::Scrabble && formula.is_none())
+        || scores(Metric::Scrabble);
+    let letter_values = (args.scrabble_score || by_scrabble).then(|| {
+        Arc::new(match &args.letter_values {
+            Some(path) => LetterValues::load(path).unwrap_or_else(|err| {
+                eprintln!("Invalid letter value file {}: {}", path, err);
+                std::process::exit(1);
+            }),
+            None => LetterValues::default(),
+        })
+    });
+    if args.sort_by.is_some() && args.all && args.limit.is_none() {
+        eprintln!(
+            "Warning: --sort-by holds every solution in memory until the run is over; add --limit or use --top on big runs"
+        );
+    }
+    let words = drop_infrequent(args, frequencies.as_deref(), exclude_letters(args, words));
+    let template = load_template(args);
+    let masks = template
+        .as_ref()
+        .map(Template::cell_masks)
+        .unwrap_or_default();
+    let (row_dim, col_dim) = match &template {
+        Some(template) => (template.row_dim(), template.col_dim()),
+        None => args.size(),
+    };
+    let mut empty = WordBox::try_new(row_dim, col_dim, !args.asymmetric).unwrap_or_else(|err| {
+        eprintln!("Cannot search: {}", err);
+        std::process::exit(1);
+    });
+    let shape = SolverConfig {
+        diagonal_word: args.diagonal_word,
+        anti_diagonal_word: args.anti_diagonal_word,
+        ..SolverConfig::default()
+    };
+    if let Some(conflict) = shape.shape_conflict(row_dim, col_dim) {
+        eprintln!("Cannot search: {}", conflict);
+        std::process::exit(1);
+    }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct WordBox {
-    row_dim: usize,    // number of rows
-    col_dim: usize,    // number of columns
-    rows: Vec<String>, // the words for each row
-    cols: Vec<String>, // the words for each column
-    is_symmetric: bool,
-}
+    let mut lengths = WordLengths::of_box(row_dim, col_dim);
+    if let Some(mask) = template.as_ref().map(Template::block_mask) {
+        let unsymmetric = mask.unsymmetric_cells();
+        if args.require_symmetry && !unsymmetric.is_empty() {
+            let cells: Vec<String> = unsymmetric
+                .iter()
+                .map(|(row, col)| format!("({}, {})", row, col))
+                .collect();
+            eprintln!(
+                "The blocks are not rotationally symmetric; nothing is blocked opposite {}",
+                cells.join(", ")
+            );
+            std::process::exit(1);
+        }
+        let singles = mask.single_letter_runs();
+        if !singles.is_empty() && !args.allow_single_letter_runs {
+            let cells: Vec<String> = singles
+                .iter()
+                .map(|(row, col)| format!("({}, {})", row, col))
+                .collect();
+            eprintln!(
+                "The blocks leave single open cells at {}; pass --allow-single-letter-runs to fill them with one-letter words",
+                cells.join(", ")
+            );
+            std::process::exit(1);
+        }
+        if !mask.is_empty() {
+            lengths = mask.word_lengths();
+            if args.diagonal_word || args.anti_diagonal_word {
+                // Blocks on a diagonal split it into words of any length, checked as rows are
+                lengths.rows.extend(1..=row_dim);
+            }
+        }
+    }
 
-impl Display for WordBox {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut grid: Vec<Vec<char>> = vec![vec!['_'; self.col_dim]; self.row_dim];
+    let stats = LexiconStats::from_words(&words, col_dim);
+    if args.dict_stats {
+        print!("{}", stats);
+    }
+    // With a list of their own for the columns, the main list only needs words across
+    let main_lengths = match args.col_wordlist {
+        Some(_) => lengths.rows.clone(),
+        None => lengths.all(),
+    };
+    warn_missing_lengths(&args.wordlist, &stats, &main_lengths, (row_dim, col_dim));
+    let letter_scores =
+        ((args.top.is_some() && args.rank_by == RankBy::Score && formula.is_none())
+            || args.sort_by == Some(SortBy::Score)
+            || scores(Metric::Score))
+        .then(|| Arc::new(LetterScores::from_words(&words)));
+    let theme = args
+        .theme_file
+        .as_ref()
+        .map(|path| Arc::new(load_theme_words(args, path, &words, &lengths.all())));
+    // A box can only be reached twice through a word listed twice
+    let mut repeats = has_repeats(&words);
+    let lexicon = build_lexicon(args, words, main_lengths, tiers);
+    // Column words come from their own list if one is given, filtered the same way
+    let col_lexicon: Option<DynLexicon> = args.col_wordlist.as_ref().map(|path| {
+        let words = exclude_letters(args, read_word_list(args, path));
+        let words = drop_infrequent(args, frequencies.as_deref(), words);
+        let stats = LexiconStats::from_words(&words, row_dim);
+        warn_missing_lengths(path, &stats, &lengths.cols, (row_dim, col_dim));
+        repeats |= has_repeats(&words);
+        build_lexicon(args, words, lengths.cols.clone(), None)
+    });
+    let lexicons = RowColumnLexicons {
+        rows: &lexicon,
+        cols: col_lexicon.as_ref().unwrap_or(&lexicon),
+    };
+    let sources = MetricSources {
+        letter_scores: letter_scores.clone(),
+        frequencies: frequencies.clone(),
+        theme: theme.clone(),
+        rare_words: rare_words.clone(),
+        letter_values: letter_values.clone(),
+    };
+    for fix in &fixed_words(args) {
+        let lexicon = match fix.line {
+            Line::Row => lexicons.rows,
+            Line::Col => lexicons.cols,
+        };
+        let len = word_length(&fix.word);
+        if !lexicon
+            .words_with_prefix(&fix.word, len)
+            .contains(&fix.word)
+        {
+            eprintln!(
+                "No solutions: {} fixes {:?}, which is not in the word list",
+                fix, fix.word
+            );
+            std::process::exit(1);
+        }
+    }
 
-        for (i, word) in self.rows.iter().enumerate() {
-            for (j, ch) in word.chars().enumerate() {
-                grid[i][j] = ch;
+    empty = empty.with_alphabet(&args.alphabet);
+    if let Some(template) = &template {
+        empty = empty.with_template(template);
+    }
+    if args.rotational {
+        empty = empty.with_rotation();
+    }
+    let random_seed = (args.random_order || args.seed_order == SeedOrder::Random)
+        .then(|| args.seed.unwrap_or_else(rand::random));
+    let mut seeds = match args.allow_invalid_cols {
+        0 => empty.row_candidates(&lexicons),
+        allowed => empty.near_row_candidates(&lexicons, allowed),
+    };
+    let all_seeds = seeds.len();
+    if let Some(shard) = args.shard {
+        seeds = shard.select(seeds);
+    }
+    if let Some(random_seed) = random_seed {
+        seeds.shuffle(&mut StdRng::seed_from_u64(random_seed));
+    }
+    match args.seed_order {
+        SeedOrder::Lexicographic => seeds.sort(),
+        SeedOrder::Productive => {
+            let ranking = Instant::now();
+            let count = seeds.len();
+            seeds = productive_order(&lexicons, seeds, |seed| {
+                empty
+                    .add_word(seed.to_string())
+                    .expect("seeds are row candidates, which fit the box")
+            });
+            if args.verbose {
+                eprintln!(
+                    "Ranked {} seeds by productivity in {:?}",
+                    count,
+                    ranking.elapsed()
+                );
             }
         }
+        SeedOrder::Lexicon | SeedOrder::Random => {}
+    }
+    // The seeds of the most preferred tier go first, in the same order as before
+    if lexicon.tiers() > 1 {
+        seeds.sort_by_cached_key(|seed| line_tier(&lexicon, seed));
+    }
+    // Only kept when something reports it or acts on it
+    let memory = match args.memory_limit {
+        Some(limit) => Some(Arc::new(MemoryGauge::with_limit(limit, args.memory_action))),
+        None => (args.verbose || args.stats).then(|| Arc::new(MemoryGauge::new())),
+    };
+    let options = RunOptions {
+        threads: args.threads,
+        limit: args.daily.map(|_| args.daily_budget).or(args.limit),
+        exhaustive: args.all || count_only || args.daily.is_some(),
+        split_depth: args.split_depth,
+        schedule: args.schedule,
+        slice: args.slice.max(1),
+        seed_budget: seed_budget(args),
+        // Counting skips remembering every solution when none can be repeated, unless a seed
+        // retried after running out of budget would find its first try's solutions again
+        dedup: args.retry_inconclusive.is_some() || (!args.no_dedup && (repeats || !count_only)),
+        count_only,
+        deterministic: args.deterministic || args.daily.is_some(),
+        word_usage: (args.word_usage_report).then(|| Arc::new(WordUsage::new(!args.asymmetric))),
+        letter_heatmap: (args.letter_heatmap.as_ref())
+            .map(|_| Arc::new(LetterHeatmap::new(row_dim, col_dim).with_masks(masks.clone()))),
+        letter_values,
+        provenance: (args.provenance).then(|| {
+            Arc::new(RunProvenance {
+                word_list,
+                lexicon: args.lexicon,
+                normalization: given_options(NORMALIZATION_OPTIONS),
+                constraints: given_options(CONSTRAINT_OPTIONS),
+                random_seed,
+            })
+        }),
+        solver: SolverConfig {
+            order: args.order,
+            heuristic: args.heuristic,
+            value_order: args.value_order,
+            corpus_frequency: (args.value_order == ValueOrder::CorpusFrequency)
+                .then(|| Arc::new(CorpusFrequency::of(&lexicons, row_dim, col_dim))),
+            forward_checking: args.forward_checking,
+            nogoods: args.nogood_cache.map(|max| Arc::new(NogoodCache::new(max))),
+            // The transpose of a box is only a solution too when both lists are the same, and
+            // its columns are all words
+            symmetry_breaking: !args.no_symmetry_breaking
+                && args.col_wordlist.is_none()
+                && args.allow_invalid_cols == 0,
+            distinct_words: args.distinct_words,
+            diagonal_word: args.diagonal_word,
+            anti_diagonal_word: args.anti_diagonal_word,
+            allow_invalid_cols: args.allow_invalid_cols,
+            required_letters: args.require_letters.clone(),
+            letter_bounds: args.letter_bounds.clone(),
+            min_distinct_letters: args.min_distinct_letters.unwrap_or(0),
+            vowel_rule: vowel_rule(args),
+            forbidden_words: forbidden_words(args),
+            isogram: args.isogram,
+            cancellation: None, // run_seeds ties every solve to the run state
+            max_nodes: None,
+            deadline: None, // run_seeds sets each seed's from --per-seed-timeout
+            // --seed-order random shuffles the seeds only
+            random_seed: random_seed.filter(|_| args.random_order),
+            query_cache: args.query_cache,
+            beam_width: args.beam_width,
+            scorer: (formula.as_ref())
+                .filter(|_| best_first)
+                .map(|formula| Arc::new(sources.formula(formula, None)) as Arc<dyn Scorer>),
+            theme_words: theme.clone(),
+            min_theme_words: args.min_theme_words,
+            rare_words: rare_words.clone(),
+            max_rare_words: args.max_rare_words,
+            trace: (args.trace_dot.as_ref()).map(|_| Arc::new(SearchTrace::new(args.trace_limit))),
+            dead_ends: (args.explain_failure || args.stats)
+                .then(|| Arc::new(DeadEnds::new(row_dim, col_dim))),
+            // Kept in case the run comes up empty, which a count reports as 0 instead
+            best_partial: !count_only,
+            memory: memory.clone(),
+            spill: args.spill_threshold.map(|threshold| SpillOptions {
+                threshold,
+                dir: (args.spill_dir.clone()).unwrap_or_else(std::env::temp_dir),
+            }),
+        },
+        ..RunOptions::default()
+    };
+    if options.schedule == Schedule::Fair && !options.solver.can_pause() {
+        eprintln!(
+            "--schedule fair needs a search that can pause: the rows heuristic or --beam-width, without --query-cache, and --nogood-cache only with --order bfs or best"
+        );
+        std::process::exit(1);
+    }
+    if let Some(spill) = &options.solver.spill {
+        let breadth_first = args.order == SearchOrder::BreadthFirst
+            && args.heuristic == Heuristic::NextRow
+            && args.beam_width.is_none();
+        if !breadth_first {
+            eprintln!(
+                "--spill-threshold needs a breadth-first row search: --order bfs with the rows heuristic, without --beam-width"
+            );
+            std::process::exit(1);
+        }
+        if !spill.dir.is_dir() {
+            eprintln!("--spill-dir {} is not a directory", spill.dir.display());
+            std::process::exit(1);
+        }
+    }
+    // A seed holding a forbidden word would only be turned away once its boxes are complete
+    seeds.retain(|seed| !options.solver.forbids(seed));
+    let state = Arc::new(match interrupt {
+        // The caller's handler stops this run and every later one of a sweep or watch
+        Some(interrupt) => RunState::with_cancellation(interrupt),
+        None => {
+            let state = RunState::default();
+            let interrupt = state.cancellation_token();
+            ctrlc::set_handler(move || interrupt.cancel()).ok();
+            state
+        }
+    });
 
-        for (i, word) in self.cols.iter().enumerate() {
-            for (j, ch) in word.chars().enumerate() {
-                grid[j][i] = ch;
+    let mut pruned_seeds = 0;
+    let mut totals = SearchStats::default();
+    let mut best_partial: Option<BestPartial> = None;
+    let mut pbar = args.progress.then(|| Progress::new(seeds.len()));
+    let status = (args.status_interval.is_some() || args.status_file.is_some()).then(|| {
+        let sink = match &args.status_file {
+            Some(path) => StatusSink::File(path.clone()),
+            None => StatusSink::Stderr,
+        };
+        let interval = args.status_interval.unwrap_or(Duration::from_secs(30));
+        StatusReporter::start(state.clone(), seeds.len(), interval, sink)
+    });
+    // The sizes of a sweep after the first add their solutions to the end of the file
+    let append = sweep.is_some_and(|sweep| !sweep.first);
+    let mut output = solution_output(args, append);
+    let mut files = SolutionFiles::new(args, row_dim, col_dim);
+    #[cfg(feature = "pdf")]
+    let mut pdf = (args.pdf.as_ref()).map(|_| {
+        PdfBook::new(PdfLayout {
+            cell_size: args.pdf_cell_size,
+            margin: args.pdf_margin,
+        })
+    });
+    if let (Some(output), Some(shard)) = (output.as_mut(), args.shard) {
+        writeln!(output, "# shard {} of {} seeds\n", shard, all_seeds)
+            .expect("Could not write solution");
+    }
+    // Each solution of a sweep is tagged with its size, in front of any heading it has
+    let size_tag = sweep.map(|_| format!("{}x{}", row_dim, col_dim));
+    let tagged = |heading: Option<&str>| match (&size_tag, heading) {
+        (Some(tag), Some(heading)) => Some(format!("{} {}", tag, heading)),
+        (Some(tag), None) => Some(tag.clone()),
+        (None, heading) => heading.map(str::to_string),
+    };
+    // Solutions are keyed on their rows and seed, so ties on score rank the same on every run
+    let mut top = args.top.map(TopK::new);
+    // Near misses rank after every box whose columns are all words, so they are kept apart and
+    // only take the places those leave
+    let mut near_misses = args.top.map(TopK::new);
+    let top_scorer: Option<Box<dyn Scorer>> = args.top.map(|_| match &formula {
+        Some(formula) => Box::new(sources.formula(formula, Some(&lexicons))),
+        None => sources.scorer(args.rank_by.metric(), Some(&lexicons)),
+    });
+    let mut sorted: Option<Vec<FoundSolution>> = args.sort_by.map(|_| vec![]);
+    let mut drawn: Option<Vec<FoundSolution>> = args.daily.map(|_| vec![]);
+    let rate = |wb: &WordBox| {
+        let frequencies = frequencies.as_ref().filter(|_| args.difficulty)?;
+        wb.difficulty(frequencies, args.missing_frequency)
+    };
+    let puzzle_seed = (args.make_puzzle).then(|| args.puzzle_seed.unwrap_or_else(rand::random));
+    let definitions = (args.definitions.as_ref()).map(|path| load_definitions(args, path));
+    let unclued: RefCell<BTreeSet<String>> = RefCell::default();
+    let puzzle = |wb: &WordBox| {
+        let puzzle = Puzzle::new(wb, args.reveal, puzzle_seed?);
+        let puzzle = match args.verify_unique {
+            true => puzzle.make_unique(&lexicons, args.max_extra_reveals, UNIQUENESS_BUDGET),
+            false => puzzle,
+        };
+        let Some(definitions) = &definitions else {
+            return Some(puzzle);
+        };
+        let puzzle = puzzle.with_clues(definitions);
+        (unclued.borrow_mut()).extend(puzzle.unclued().map(|entry| entry.word.clone()));
+        Some(puzzle)
+    };
+    #[cfg(feature = "db")]
+    let db = open_db(args, count_only);
+    #[cfg(feature = "db")]
+    let db_scorer = (formula.as_ref()).map(|formula| sources.formula(formula, Some(&lexicons)));
+    let highlighting = Highlighting::new(args);
+    let time_limit = args.size_timeout.map(Duration::from_secs);
+    let build = |seed: &str| {
+        empty
+            .add_word(seed.to_string())
+            .expect("seeds are row candidates, which fit the box")
+    };
+    // The seeds that ran out of their --per-seed-timeout or --per-seed-max-nodes
+    let inconclusive: RefCell<Vec<InconclusiveSeed>> = RefCell::default();
+    let mut seed_report = args.seed_report.as_ref().map(|_| SeedReport::new());
+    let retrying = Cell::new(false);
+    let mut on_event = |event: RunEvent| match event {
+        RunEvent::Solution(solution) => {
+            #[cfg(feature = "db")]
+            if let Some(db) = &db {
+                let score = (db_scorer.as_ref())
+                    .map(|scorer| scorer.score(&solution.word_box, lexicons.rows));
+                db.send(StoredSolution::new(&solution, score));
+            }
+            if let (Some(top), Some(near_misses), Some(scorer)) =
+                (top.as_mut(), near_misses.as_mut(), &top_scorer)
+            {
+                let score = scorer.score(&solution.word_box, lexicons.rows);
+                let score = rank_value(score);
+                let key = (solution.word_box.row_words(), solution.seed.clone());
+                match solution.is_near_miss() {
+                    true => near_misses.push(score, key, solution),
+                    false => top.push(score, key, solution),
+                }
+                return;
+            }
+            if let Some(held) = sorted.as_mut().or(drawn.as_mut()) {
+                if let Some(gauge) = &memory {
+                    gauge.add(Consumer::Buffered, solution.estimated_bytes());
+                }
+                held.push(solution);
+                return;
+            }
+            print_clear(&highlighting, &solution);
+            let difficulty = rate(&solution.word_box);
+            let puzzle = puzzle(&solution.word_box);
+            if let Some(tag) = &size_tag {
+                println!("size: {}", tag);
+            }
+            print_solution_notes(&solution, difficulty.as_ref(), puzzle.as_ref());
+            // Without a terminal to clear, a blank line tells one box from the next
+            if !highlighting.styled {
+                println!();
+            }
+            let heading = tagged(None);
+            if let Some(output) = output.as_mut() {
+                write_solution(
+                    output,
+                    heading.as_deref(),
+                    &solution,
+                    difficulty.as_ref(),
+                    puzzle.as_ref(),
+                );
+                output.end_record().expect("Could not write solution");
+            }
+            if let Some(files) = files.as_mut() {
+                let (difficulty, puzzle) = (difficulty.as_ref(), puzzle.as_ref());
+                files.write(heading.as_deref(), &solution, difficulty, puzzle);
+            }
+            #[cfg(feature = "pdf")]
+            if let Some(pdf) = pdf.as_mut() {
+                pdf.add(heading.as_deref(), &solution.word_box, puzzle.as_ref());
             }
         }
-
-        for row in &grid {
-            for ch in row {
-                write!(f, "{}", ch)?;
+        RunEvent::SeedDone { seed, report, .. } => {
+            if report.outcome == Outcome::Pruned {
+                pruned_seeds += 1;
+            }
+            let out_of_budget = InconclusiveSeed::from_report(&seed, &report);
+            if args.verbose {
+                let memory = match &memory {
+                    Some(gauge) => format!(", memory {}", gauge.usage()),
+                    None => String::new(),
+                };
+                let note = match out_of_budget.is_some() {
+                    true => " (inconclusive)",
+                    false => "",
+                };
+                eprintln!(
+                    "{}: {} solutions, {}{}{}",
+                    seed, report.solutions, report.stats, memory, note
+                );
+            }
+            inconclusive.borrow_mut().extend(out_of_budget);
+            if let Some(seed_report) = seed_report.as_mut() {
+                seed_report.record(&seed, &report);
+            }
+            totals = totals.merge(report.stats);
+            best_partial = BestPartial::better(best_partial.take(), report.best_partial);
+            if let Some(pbar) = pbar.as_mut().filter(|_| !retrying.get()) {
+                pbar.update(1);
+            }
+        }
+    };
+    let mut retried = 0;
+    let (_, timed_out) = with_time_limit(state.cancellation_token(), time_limit, || {
+        run_seeds(&lexicons, &seeds, build, &options, &state, &mut on_event);
+        // The seeds left inconclusive get one more try, with a bigger budget, once the rest
+        // are done
+        let Some(factor) = args.retry_inconclusive else {
+            return;
+        };
+        let again: Vec<String> = (inconclusive.take().into_iter())
+            .map(|seed| seed.seed)
+            .collect();
+        if again.is_empty() || state.is_cancelled() {
+            return;
+        }
+        retried = again.len();
+        retrying.set(true);
+        let options = RunOptions {
+            seed_budget: options.seed_budget.scaled(factor.0),
+            ..options.clone()
+        };
+        run_seeds(&lexicons, &again, build, &options, &state, &mut on_event);
+    });
+    // One last status line, with the run's final counts
+    drop(status);
+    let inconclusive = inconclusive.into_inner();
+    if let (Some(top), Some(near_misses)) = (top, near_misses) {
+        let mut ranked = top.into_sorted();
+        let room = args.top.unwrap_or(0) - ranked.len();
+        ranked.extend(near_misses.into_sorted().into_iter().take(room));
+        for (i, (score, solution)) in ranked.into_iter().enumerate() {
+            let heading = match (&formula, args.rank_by) {
+                (Some(formula), _) => format!("#{} ({} = {:.4})", i + 1, formula, score),
+                (None, RankBy::Score) => format!("#{} ({} {:.4})", i + 1, args.rank_by, score),
+                (None, RankBy::Theme) => {
+                    format!("#{} ({} words {})", i + 1, args.rank_by, score)
+                }
+                (None, RankBy::Tier) => format!("#{} ({})", i + 1, solution.tiers),
+                (None, RankBy::Scrabble) => format!("#{} ({} {})", i + 1, args.rank_by, score),
+            };
+            let heading = tagged(Some(&heading)).unwrap_or(heading);
+            let difficulty = rate(&solution.word_box);
+            let puzzle = puzzle(&solution.word_box);
+            print_held_solution(
+                &highlighting,
+                Some(&heading),
+                &solution,
+                difficulty.as_ref(),
+                puzzle.as_ref(),
+            );
+            let (difficulty, puzzle) = (difficulty.as_ref(), puzzle.as_ref());
+            if let Some(output) = output.as_mut() {
+                write_solution(output, Some(&heading), &solution, difficulty, puzzle);
+                output.end_record().expect("Could not write solution");
+            }
+            if let Some(files) = files.as_mut() {
+                files.write(Some(&heading), &solution, difficulty, puzzle);
+            }
+            #[cfg(feature = "pdf")]
+            if let Some(pdf) = pdf.as_mut() {
+                pdf.add(Some(&heading), &solution.word_box, puzzle);
             }
-            writeln!(f)?;
         }
-
-        Ok(())
     }
-}
-/*
-impl Ord for WordBox {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        // self.score().cmp(&other.score())
-        unimplemented!()
+    if let (Some(sort_by), Some(solutions)) = (args.sort_by, sorted) {
+        let scorer = match (sort_by.metric(), &formula) {
+            (Some(metric), _) => Some(sources.scorer(metric, Some(&lexicons))),
+            (None, Some(formula)) if sort_by == SortBy::Custom => {
+                Some(Box::new(sources.formula(formula, Some(&lexicons))) as Box<dyn Scorer>)
+            }
+            (None, _) => None,
+        };
+        let sort = SortKeys {
+            scorer,
+            formula: formula.as_ref(),
+            lexicon: lexicons.rows,
+            seeds: &seeds,
+        };
+        for (heading, solution) in sort.sorted(solutions, sort_by) {
+            let heading = tagged(heading.as_deref());
+            let difficulty = rate(&solution.word_box);
+            let puzzle = puzzle(&solution.word_box);
+            let (difficulty, puzzle) = (difficulty.as_ref(), puzzle.as_ref());
+            print_held_solution(
+                &highlighting,
+                heading.as_deref(),
+                &solution,
+                difficulty,
+                puzzle,
+            );
+            if let Some(output) = output.as_mut() {
+                write_solution(output, heading.as_deref(), &solution, difficulty, puzzle);
+                output.end_record().expect("Could not write solution");
+            }
+            if let Some(files) = files.as_mut() {
+                files.write(heading.as_deref(), &solution, difficulty, puzzle);
+            }
+            #[cfg(feature = "pdf")]
+            if let Some(pdf) = pdf.as_mut() {
+                pdf.add(heading.as_deref(), &solution.word_box, puzzle);
+            }
+        }
+    }
+    if let (Some(daily), Some(solutions)) = (args.daily, drawn) {
+        let scores: Option<Vec<f64>> = (frequencies.as_ref()).map(|frequencies| {
+            (solutions.iter())
+                .map(|solution| frequencies.total(&solution.word_box))
+                .collect()
+        });
+        if let Some(pick) = daily.pick(solutions.len(), scores.as_deref()) {
+            let solution = &solutions[pick];
+            let heading = format!(
+                "Daily puzzle for {}: box {} of the first {} found",
+                daily,
+                pick + 1,
+                solutions.len()
+            );
+            let heading = tagged(Some(&heading)).unwrap_or(heading);
+            let difficulty = rate(&solution.word_box);
+            let puzzle = puzzle(&solution.word_box);
+            let (difficulty, puzzle) = (difficulty.as_ref(), puzzle.as_ref());
+            print_held_solution(&highlighting, Some(&heading), solution, difficulty, puzzle);
+            if let Some(output) = output.as_mut() {
+                write_solution(output, Some(&heading), solution, difficulty, puzzle);
+                output.end_record().expect("Could not write solution");
+            }
+            if let Some(files) = files.as_mut() {
+                files.write(Some(&heading), solution, difficulty, puzzle);
+            }
+            #[cfg(feature = "pdf")]
+            if let Some(pdf) = pdf.as_mut() {
+                pdf.add(Some(&heading), &solution.word_box, puzzle);
+            }
+            let weighting = match scores {
+                Some(_) => "weighted by word frequency",
+                None => "uniform",
+            };
+            println!(
+                "Drawn with seed {:#018x} ({}) from a budget of {} solutions",
+                daily.seed(),
+                weighting,
+                args.daily_budget
+            );
+            println!("Word list: {} ({})", word_list_names(args), word_list);
+            let command: Vec<String> = std::env::args().map(|arg| shell_quote(&arg)).collect();
+            println!("Reproduce with: {}", command.join(" "));
+        }
+    }
+    #[cfg(feature = "db")]
+    if let (Some(db), Some(path)) = (db, &args.db) {
+        match db.finish() {
+            Ok(db) if args.verbose => {
+                let stored = db.len().map_or("?".to_string(), |len| len.to_string());
+                eprintln!("{} now holds {} boxes", path, stored);
+            }
+            Ok(_) => {}
+            Err(err) => eprintln!("Could not store the solutions in {}: {}", path, err),
+        }
+    }
+    let memory_stopped = memory.as_ref().is_some_and(|gauge| gauge.is_stopped());
+    if let Some(mut output) = output {
+        if memory_stopped {
+            writeln!(output, "# partial: the run stopped at its memory limit")
+                .expect("Could not write solution");
+        }
+        output.finish().expect("Could not write solution");
+    }
+    if let (true, Some(gauge)) = (memory_stopped, &memory) {
+        println!(
+            "Stopped at the memory limit of {} after {} solutions; these results are partial, \
+             as the rest of the search space was not explored",
+            gauge
+                .limit()
+                .map_or(String::new(), |limit| limit.to_string()),
+            state.solutions()
+        );
+        if count_only {
+            println!("Search: {}", totals);
+        }
+    } else if timed_out {
+        println!(
+            "Stopped at the time limit after {} solutions; the rest of the search space was not explored",
+            state.solutions()
+        );
+        if count_only {
+            println!("Search: {}", totals);
+        }
+    } else if state.is_cancelled() && !state.limit_reached() {
+        println!("Interrupted after {} solutions", state.solutions());
+    } else if count_only {
+        println!("{} solutions", state.solutions());
+        println!("Search: {}", totals);
+        if options.solver.breaks_transpose_symmetry() && empty.has_transpose_twin() {
+            println!(
+                "(one of each box and its transpose; pass --no-symmetry-breaking to count both)"
+            );
+        }
+        if state.limit_reached() {
+            println!("Stopped at the limit; the rest of the search space was not explored");
+        }
+    } else if state.solutions() == 0 {
+        println!("No solutions found");
+        if let Some(best) = &best_partial {
+            let rows = best.word_box.row_words().len();
+            println!("Best partial box ({}/{} rows):", rows, row_dim);
+            print!("{}", best.word_box);
+        }
+    } else if state.limit_reached() && args.daily.is_none() {
+        println!(
+            "Stopped at the limit of {} solutions; the rest of the search space was not explored",
+            state.solutions()
+        );
+    }
+    if !inconclusive.is_empty() {
+        let retry = match args.retry_inconclusive {
+            Some(factor) if retried > 0 => format!(
+                " ({} of {} were still inconclusive with {} the budget)",
+                inconclusive.len(),
+                retried,
+                factor
+            ),
+            _ => String::new(),
+        };
+        println!(
+            "{} seeds were inconclusive: their searches ran out of their budget before they were over, so boxes from them may still exist{}",
+            inconclusive.len(),
+            retry
+        );
+        for seed in inconclusive.iter().take(INCONCLUSIVE_SHOWN) {
+            println!("  {}", seed);
+        }
+        if inconclusive.len() > INCONCLUSIVE_SHOWN {
+            println!("  and {} more", inconclusive.len() - INCONCLUSIVE_SHOWN);
+        }
+    } else if retried > 0 {
+        println!(
+            "Every one of the {} inconclusive seeds was settled on a second try",
+            retried
+        );
+    }
+    let exhausted =
+        !memory_stopped && !timed_out && !state.is_cancelled() && inconclusive.is_empty();
+    if let (true, true, Some(dead_ends)) = (
+        args.explain_failure,
+        exhausted && state.solutions() == 0,
+        &options.solver.dead_ends,
+    ) {
+        println!("Why nothing was found:");
+        print!("{}", dead_ends);
+    }
+    #[cfg(feature = "pdf")]
+    if let (Some(pdf), Some(path)) = (&pdf, &args.pdf) {
+        std::fs::write(path, pdf.to_bytes()).expect("Could not write PDF file");
+        println!(
+            "Laid out {} solutions on {} pages in {}",
+            pdf.len(),
+            pdf.page_count(),
+            path
+        );
+    }
+    if let Some(files) = &files {
+        println!(
+            "Wrote {} solution files to {}",
+            files.names.len(),
+            files.dir
+        );
+    }
+    if state.duplicates() > 0 {
+        let (seen, bytes) = state.dedup_memory();
+        println!(
+            "Dropped {} duplicate solutions ({} distinct solutions remembered, about {} KiB)",
+            state.duplicates(),
+            seen,
+            bytes / 1024
+        );
+    }
+    if pruned_seeds > 0 {
+        println!(
+            "The beam dropped partial boxes for {} seeds, so the search was incomplete; a wider beam may find more",
+            pruned_seeds
+        );
+    }
+    if let Some(usage) = &options.word_usage {
+        let report = usage.report();
+        print!("{}", report);
+        if options.solver.breaks_transpose_symmetry() && empty.has_transpose_twin() {
+            println!("(the transposes of the solutions are not counted; pass --no-symmetry-breaking to count them)");
+        }
+        if let Some(path) = &args.word_usage_csv {
+            std::fs::write(path, report.to_csv()).expect("Could not write CSV file");
+        }
+    }
+    if let (Some(seed_report), Some(path)) = (&seed_report, &args.seed_report) {
+        std::fs::write(path, seed_report.to_csv()).expect("Could not write seed report");
+    }
+    if let (Some(heatmap), Some(path)) = (&options.letter_heatmap, &args.letter_heatmap) {
+        println!(
+            "Most common letter in each cell across {} solutions:",
+            heatmap.solutions()
+        );
+        print!("{}", heatmap);
+        std::fs::write(path, heatmap.to_json() + "\n").expect("Could not write heatmap file");
+    }
+    if let (Some(trace), Some(path)) = (&options.solver.trace, &args.trace_dot) {
+        std::fs::write(path, trace.to_dot()).expect("Could not write trace file");
+        let stopped = match trace.is_truncated() {
+            true => format!(" (stopped at --trace-limit {})", args.trace_limit),
+            false => String::new(),
+        };
+        println!(
+            "Search trace: {} nodes written to {}{}",
+            trace.len(),
+            path,
+            stopped
+        );
+    }
+    if let Some(random_seed) = random_seed {
+        println!(
+            "Random seed: {} (pass --seed {} to repeat this order)",
+            random_seed, random_seed
+        );
+    }
+    if let Some(definitions) = &definitions {
+        let unclued: Vec<String> = unclued.into_inner().into_iter().collect();
+        match unclued.is_empty() {
+            true => println!(
+                "Clues: every word had a definition ({} loaded)",
+                definitions.len()
+            ),
+            false => println!(
+                "Clues: {} words had no definition and show {}: {}",
+                unclued.len(),
+                NO_CLUE,
+                spelled_out(&unclued)
+            ),
+        }
+    }
+    if let (Some(seed), None) = (puzzle_seed, args.puzzle_seed) {
+        println!(
+            "Puzzle seed: {} (pass --puzzle-seed {} to make the same puzzles)",
+            seed, seed
+        );
+    }
+    if let Some(shard) = args.shard {
+        println!(
+            "Shard {}: searched {} of {} seeds",
+            shard,
+            seeds.len(),
+            all_seeds
+        );
+    }
+    if let Some(gauge) = memory.as_ref().filter(|gauge| gauge.times_over() > 0) {
+        let limit = gauge
+            .limit()
+            .map_or(String::new(), |limit| limit.to_string());
+        match gauge.action() {
+            MemoryAction::Compact => println!(
+                "Went over the memory limit of {}: kept the frontier's boxes compact from then on",
+                limit
+            ),
+            MemoryAction::ClearCaches => println!(
+                "Went over the memory limit of {} {} times, clearing the nogood cache each time",
+                limit,
+                gauge.times_over()
+            ),
+            MemoryAction::Stop => {}
+        }
+    }
+    print_search_stats(
+        args,
+        &totals,
+        &masks,
+        args.shard,
+        memory.as_deref(),
+        options.solver.dead_ends.as_deref(),
+        (options.seed_budget.is_limited()).then_some(&inconclusive[..]),
+    );
+    if let Some(nogoods) = &options.solver.nogoods {
+        let lookups = nogoods.hits() + nogoods.misses();
+        eprintln!(
+            "Nogood cache: {} hits in {} lookups ({:.1}%), {} of {} entries used",
+            nogoods.hits(),
+            lookups,
+            100.0 * nogoods.hits() as f64 / lookups.max(1) as f64,
+            nogoods.len(),
+            nogoods.max_entries()
+        );
+    }
+    let stopped = if memory_stopped {
+        Some("memory limit")
+    } else if timed_out {
+        Some("timed out")
+    } else if state.is_cancelled() && !state.limit_reached() {
+        Some("interrupted")
+    } else if state.limit_reached() {
+        Some("limit")
+    } else if pruned_seeds > 0 {
+        Some("beam")
+    } else {
+        None
+    };
+    SizeSolutions {
+        dims: (row_dim, col_dim),
+        solutions: state.solutions(),
+        nodes: totals.nodes,
+        elapsed: start.elapsed(),
+        stopped,
     }
 }
-*/
-pub trait Lexicon {
-    fn initialize(words: Vec<String>, lengths: Vec<usize>) -> Self;
 
-    fn words_with_prefix(&self, prefix: &str, word_len: usize) -> Vec<String>;
+/// Where one size of a sweep over --rows and --cols ranges stands (see `solve_sizes`)
+struct SweepSize {
+    first: bool, // the first size searched, which starts the --output file afresh
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct VecLexicon {
-    words: Vec<String>,
+/// How the search of one box size came out
+struct SizeSolutions {
+    dims: (usize, usize),
+    solutions: usize,
+    nodes: usize,
+    elapsed: Duration,
+    stopped: Option<&'static str>, // why the search stopped short, if it did
 }
 
-impl Lexicon for VecLexicon {
-    /// Get a list of words that start with the given prefix and are of the given length
-    fn initialize(words: Vec<String>, lengths: Vec<usize>) -> Self {
-        VecLexicon {
-            words: words
-                .iter()
-                .filter(|word| lengths.contains(&word.len()))
-                .cloned()
-                .collect(),
+/// How often --watch looks at the files it watches
+const WATCH_POLL: Duration = Duration::from_millis(200);
+/// How long the files must be left alone after a change before the search runs again, so that
+/// a burst of saves makes one run
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// The files --watch runs the search again for: the word lists and the template file
+fn watched_files(args: &Args) -> Vec<PathBuf> {
+    let lists = match args.tier.is_empty() {
+        true => vec![args.wordlist.clone()],
+        false => args.tier.clone(),
+    };
+    (lists.into_iter())
+        .chain(args.col_wordlist.clone())
+        .chain(args.template_file.clone())
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// The file names, separated by commas
+fn file_names(paths: &[PathBuf]) -> String {
+    (paths.iter())
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Solve as without --watch, then run the same search again on the word lists and template as
+/// they are each time they change, each run under a header, until Ctrl-C
+fn watch(args: &Args, mut words: Vec<String>, mut tiers: Option<WordTiers>) {
+    let cancellation = CancellationToken::new();
+    let interrupt = cancellation.clone();
+    ctrlc::set_handler(move || interrupt.cancel()).ok();
+    let mut watcher = FileWatcher::new(watched_files(args), WATCH_POLL, WATCH_DEBOUNCE);
+    let mut changed: Vec<PathBuf> = vec![];
+    for run in 1.. {
+        match run {
+            1 => println!("== run 1 =="),
+            _ => println!("== run {} ({} changed) ==", run, file_names(&changed)),
+        }
+        solve(args, words, tiers.as_ref(), None, Some(&cancellation));
+        if cancellation.is_cancelled() {
+            return;
+        }
+        println!(
+            "Watching {} for changes (Ctrl-C to stop)",
+            file_names(watcher.paths())
+        );
+        loop {
+            let Some(saved) = watcher.wait(&cancellation) else {
+                return;
+            };
+            if watcher.contents_changed() {
+                changed = saved;
+                break;
+            }
+            println!(
+                "{} saved without changes; not searching again",
+                file_names(&saved)
+            );
+        }
+        (tiers, words) = load_words(args);
+    }
+}
+
+/// Solve or count every size in the --rows and --cols ranges in turn, fewest rows first, from
+/// the one word list that was loaded; each size has limits of its own. Boxes that are not
+/// square cannot be symmetric, so they are searched as --asymmetric instead. A table of the
+/// solutions of each size ends the run.
+fn solve_sizes(args: &Args, words: Vec<String>, tiers: Option<&WordTiers>) {
+    let cancellation = CancellationToken::new();
+    let interrupt = cancellation.clone();
+    ctrlc::set_handler(move || interrupt.cancel()).ok();
+    let mut results: Vec<SizeSolutions> = vec![];
+    'sizes: for rows in args.rows.sizes() {
+        for cols in args.cols.sizes() {
+            if cancellation.is_cancelled() {
+                break 'sizes;
+            }
+            let mut size_args = args.clone();
+            size_args.rows = SizeRange {
+                min: rows,
+                max: rows,
+            };
+            size_args.cols = SizeRange {
+                min: cols,
+                max: cols,
+            };
+            println!("== {}x{} ==", rows, cols);
+            if rows != cols && (args.diagonal_word || args.anti_diagonal_word) {
+                println!("Skipped: a {}x{} box has no diagonal word", rows, cols);
+                continue;
+            }
+            if rows != cols && !args.asymmetric {
+                println!(
+                    "A {}x{} box cannot be symmetric; searching it as --asymmetric",
+                    rows, cols
+                );
+                size_args.asymmetric = true;
+            }
+            let template = &args.name_template;
+            if !(template.uses(NameField::Rows) && template.uses(NameField::Cols)) {
+                size_args.name_template = format!("{{rows}}x{{cols}}_{}", template)
+                    .parse()
+                    .expect("a prefix of fields keeps a template valid");
+            }
+            let sweep = SweepSize {
+                first: results.is_empty(),
+            };
+            let solved = solve(
+                &size_args,
+                words.clone(),
+                tiers,
+                Some(&sweep),
+                Some(&cancellation),
+            );
+            results.push(solved);
+        }
+    }
+    print!("{}", SweepTable(&results));
+}
+
+/// The solutions of each size of a sweep as an aligned table
+struct SweepTable<'a>(&'a [SizeSolutions]);
+
+impl std::fmt::Display for SweepTable<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:<8} {:>14} {:>14} {:>12}",
+            "size", "solutions", "nodes", "time"
+        )?;
+        for size in self.0 {
+            let solutions = match size.stopped {
+                Some(_) => format!("≥ {}", size.solutions),
+                None => size.solutions.to_string(),
+            };
+            writeln!(
+                f,
+                "{:<8} {:>14} {:>14} {:>12}{}",
+                format!("{}x{}", size.dims.0, size.dims.1),
+                solutions,
+                size.nodes,
+                format!("{:.2?}", size.elapsed),
+                size.stopped
+                    .map_or(String::new(), |reason| format!(" ({})", reason))
+            )?;
         }
+        Ok(())
     }
+}
 
-    fn words_with_prefix(&self, prefix: &str, word_len: usize) -> Vec<String> {
-        self.words
+/// What `--sort-by` needs to compute each key
+struct SortKeys<'a> {
+    scorer: Option<Box<dyn Scorer + 'a>>, // the metric sorted by, if it is one
+    formula: Option<&'a ScoreFormula>,    // the --score formula, for `SortBy::Custom` headings
+    lexicon: &'a dyn Lexicon,
+    seeds: &'a [String],
+}
+
+impl SortKeys<'_> {
+    /// The solutions in the order `sort_by` asks for, near misses after the rest and ties going
+    /// to the rows that sort first, each with a heading giving its key if the key is a number
+    fn sorted(
+        &self,
+        solutions: Vec<FoundSolution>,
+        sort_by: SortBy,
+    ) -> Vec<(Option<String>, FoundSolution)> {
+        let seed_index: HashMap<&str, usize> = self
+            .seeds
             .iter()
-            .filter(|word| word.starts_with(prefix) && word.len() == word_len)
-            .cloned()
+            .enumerate()
+            .map(|(i, seed)| (seed.as_str(), i))
+            .collect();
+        let mut keyed: Vec<(f64, usize, String, FoundSolution)> = solutions
+            .into_iter()
+            .map(|solution| {
+                let wb = &solution.word_box;
+                let value = (self.scorer.as_ref())
+                    .map_or(0.0, |scorer| rank_value(scorer.score(wb, self.lexicon)));
+                let seed = match sort_by {
+                    SortBy::Seed => seed_index.get(solution.seed.as_str()).copied(),
+                    _ => None,
+                };
+                let rows = wb.row_words().concat();
+                (value, seed.unwrap_or(0), rows, solution)
+            })
+            .collect();
+        keyed.sort_by(|a, b| {
+            (a.3.is_near_miss().cmp(&b.3.is_near_miss()))
+                .then(b.0.total_cmp(&a.0))
+                .then(a.1.cmp(&b.1))
+                .then_with(|| a.2.cmp(&b.2))
+        });
+        keyed
+            .into_iter()
+            .map(|(value, _, _, solution)| {
+                let heading = match sort_by {
+                    SortBy::Score => Some(format!("({} {:.4})", sort_by, value)),
+                    SortBy::Frequency | SortBy::Scrabble | SortBy::DistinctLetters => {
+                        Some(format!("({} {})", sort_by, value))
+                    }
+                    SortBy::Theme => Some(format!("({} words {})", sort_by, value)),
+                    SortBy::Tier => Some(format!("({})", solution.tiers)),
+                    SortBy::Custom => {
+                        (self.formula).map(|formula| format!("({} = {:.4})", formula, value))
+                    }
+                    SortBy::Alphabetical | SortBy::Seed => None,
+                };
+                (heading, solution)
+            })
             .collect()
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct HashMapLexicon {
-    words: HashMap<String, Vec<String>>,
+/// What the metrics of --rank-by, --sort-by and --score are computed from; a metric whose
+/// source the run did not load scores every box 0
+#[derive(Clone, Default)]
+struct MetricSources {
+    letter_scores: Option<Arc<LetterScores>>,
+    frequencies: Option<Arc<WordFrequencies>>,
+    theme: Option<Arc<ThemeWords>>,
+    rare_words: Option<Arc<RareWords>>,
+    letter_values: Option<Arc<LetterValues>>,
 }
 
-impl Lexicon for HashMapLexicon {
-    /// Get a list of words that start with the given prefix and are of the given length
-    fn initialize(words: Vec<String>, lengths: Vec<usize>) -> Self {
-        let mut words_map: HashMap<String, Vec<String>> = HashMap::new();
-        for word in words.iter() {
-            if lengths.contains(&word.len()) {
-                for i in 0..=word.len() {
-                    words_map
-                        .entry(word[..i].to_string())
-                        .or_default()
-                        .push(word.clone());
-                }
+impl MetricSources {
+    /// A scorer for `metric`. Tiers are counted as `FoundSolution::tiers` counts them, in
+    /// `lexicons` if given and otherwise in the lexicon the scorer is handed.
+    fn scorer<'a>(
+        &self,
+        metric: Metric,
+        lexicons: Option<&'a RowColumnLexicons<'a, DynLexicon>>,
+    ) -> Box<dyn Scorer + 'a> {
+        fn shared<S: Scorer + 'static>(source: &Option<Arc<S>>) -> Box<dyn Scorer> {
+            match source.clone() {
+                Some(source) => Box::new(move |wb: &WordBox, lexicon: &dyn Lexicon| {
+                    Scorer::score(&*source, wb, lexicon)
+                }),
+                None => Box::new(|_: &WordBox, _: &dyn Lexicon| 0.0),
             }
         }
-        HashMapLexicon { words: words_map }
+        match metric {
+            Metric::Score => shared(&self.letter_scores),
+            Metric::Frequency => shared(&self.frequencies),
+            Metric::Theme => shared(&self.theme),
+            Metric::Rare => shared(&self.rare_words),
+            Metric::Scrabble => shared(&self.letter_values),
+            Metric::DistinctLetters => Box::new(DistinctLetters),
+            Metric::Tier => match lexicons {
+                Some(lexicons) => Box::new(move |wb: &WordBox, _: &dyn Lexicon| {
+                    TierCounts::of(wb, lexicons).score()
+                }),
+                None => Box::new(TierPreference),
+            },
+        }
     }
 
-    fn words_with_prefix(&self, prefix: &str, word_len: usize) -> Vec<String> {
-        self.words
-            .get(prefix)
-            .unwrap_or(&vec![])
-            .iter()
-            .filter(|w| w.len() == word_len)
-            .cloned()
-            .collect()
+    /// A scorer summing the metrics of `formula`, each computed as `scorer` computes it
+    fn formula<'a>(
+        &self,
+        formula: &ScoreFormula,
+        lexicons: Option<&'a RowColumnLexicons<'a, DynLexicon>>,
+    ) -> WeightedScore<'a> {
+        WeightedScore {
+            terms: (formula.terms.iter())
+                .map(|&(weight, metric)| (weight, self.scorer(metric, lexicons)))
+                .collect(),
+        }
     }
 }
 
-impl Display for VecLexicon {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.words.join(", "))
-    }
+/// Whether the run stores its solutions in a --db database
+#[cfg(feature = "db")]
+fn records_db(args: &Args) -> bool {
+    args.db.is_some()
 }
 
-/// Filter out words that contain uppercase letters, punctuation, or whitespace
-fn filter_words(filename: &str) -> Vec<String> {
-    let file: File = File::open(filename).expect("Could not open file");
-    let reader = BufReader::new(file);
-    reader
-        .lines()
-        .map_while(Result::ok)
-        .filter(|line| {
-            line.chars()
-                .all(|c| !c.is_uppercase() && !c.is_ascii_punctuation() && !c.is_whitespace())
-        })
-        .collect()
+/// Whether the run stores its solutions in a --db database, which this build has no support for
+#[cfg(not(feature = "db"))]
+fn records_db(_: &Args) -> bool {
+    false
+}
+
+/// How the chunks of --output-chunk-size are written
+#[cfg(feature = "gzip")]
+fn compression(args: &Args) -> Compression {
+    args.compress
+}
+
+/// How the chunks of --output-chunk-size are written, which is uncompressed without the gzip
+/// feature
+#[cfg(not(feature = "gzip"))]
+fn compression(_: &Args) -> chunks::Compression {
+    chunks::Compression::None
 }
 
-impl WordBox {
-    /*
-    fn score(&self) -> f64 {
-        let mut prod = 1.0;
-        for i in 0..self.col_dim {
-            let prefix = Self::take_ith_characters(&self.rows, i);
-            let choices = lexicon.words_with_prefix(&prefix, self.row_dim);
-            prod *= choices.len() as f64;
+/// The --output file, or the index of its chunks under --output-chunk-size, adding to what
+/// is there already if `append`
+fn solution_output(args: &Args, append: bool) -> Option<SolutionOutput> {
+    let path = args.output.as_ref()?;
+    let output = match args.output_chunk_size {
+        Some(chunk_size) => {
+            ChunkedOutput::create(path.as_ref(), chunk_size, compression(args), append)
+                .map(SolutionOutput::Chunked)
+        }
+        None => match append {
+            true => File::options().append(true).open(path),
+            false => File::create(path),
         }
+        .map(|file| SolutionOutput::File(BufWriter::new(file))),
+    };
+    Some(output.expect("Could not create output file"))
+}
 
-        (100 * self.rows.len()) as f64 + prod
+/// What each seed's search may take, from --per-seed-timeout and --per-seed-max-nodes
+fn seed_budget(args: &Args) -> SeedBudget {
+    SeedBudget {
+        timeout: args.per_seed_timeout,
+        max_nodes: args.per_seed_max_nodes,
     }
-    */
+}
 
-    fn is_done(&self) -> bool {
-        self.rows.len() == self.row_dim
+/// A writer to the --db database, with the run recorded in it
+#[cfg(feature = "db")]
+fn open_db(args: &Args, count_only: bool) -> Option<DbWriter> {
+    let path = args.db.as_ref()?;
+    if count_only {
+        eprintln!("Cannot store solutions that are only counted; drop --db or the count command");
+        std::process::exit(1);
     }
+    let fail = |err: DbError| -> ! {
+        eprintln!("Could not open the database {}: {}", path, err);
+        std::process::exit(1);
+    };
+    let db = SolutionDb::open(path).unwrap_or_else(|err| fail(err));
+    let command: Vec<String> = std::env::args().map(|arg| shell_quote(&arg)).collect();
+    let run =
+        (db.start_run(&command.join(" "), &word_list_names(args))).unwrap_or_else(|err| fail(err));
+    Some(db.writer(run))
+}
 
-    fn take_ith_characters(words: &[String], i: usize) -> String {
-        words
-            .iter()
-            .map(|word| word.chars().nth(i).unwrap())
-            .collect()
+/// The --score formula, or the one in --score-file
+fn score_formula(args: &Args) -> Option<ScoreFormula> {
+    if let Some(formula) = &args.score {
+        return Some(formula.clone());
     }
+    let path = args.score_file.as_ref()?;
+    let text = std::fs::read_to_string(path).expect("Could not open score file");
+    Some(ScoreFormula::from_toml(&text).unwrap_or_else(|err| {
+        eprintln!("Invalid score file {}: {}", path, err);
+        std::process::exit(1);
+    }))
+}
 
-    fn is_valid_move<L: Lexicon>(&self, word: &str, lexicon: &L) -> bool {
-        let mut rows: Vec<String> = self.rows.clone();
-        rows.push(word.to_string());
-        for i in 0..self.col_dim {
-            let prefix = Self::take_ith_characters(&rows, i);
-            let choices = lexicon.words_with_prefix(&prefix, self.row_dim);
-            if choices.is_empty() {
-                return false;
+/// The word lists a run read its words from, for telling which files a reproduction needs
+/// The options that change how the word lists are read, noted in provenance records
+const NORMALIZATION_OPTIONS: &[&str] = &[
+    "input_format",
+    "csv_column",
+    "alphabet",
+    "strip_chars",
+    "digraphs",
+    "drop_proper_nouns",
+    "drop_uppercase",
+];
+
+/// The options that narrow the words a run searches or the boxes it keeps, noted in provenance
+/// records
+const CONSTRAINT_OPTIONS: &[&str] = &[
+    "tier",
+    "col_wordlist",
+    "exclude_letters",
+    "tag_file",
+    "block_tags",
+    "frequencies",
+    "min_word_freq",
+    "missing_frequency",
+    "template",
+    "template_file",
+    "cell_masks",
+    "fix",
+    "spine",
+    "allow_single_letter_runs",
+    "rotational",
+    "no_symmetry_breaking",
+    "distinct_words",
+    "forbid_word",
+    "forbid_file",
+    "diagonal_word",
+    "anti_diagonal_word",
+    "require_letters",
+    "letter_bounds",
+    "min_distinct_letters",
+    "require_vowel_per_line",
+    "max_consonant_run",
+    "vowels",
+    "isogram",
+    "allow_invalid_cols",
+    "theme_file",
+    "min_theme_words",
+    "rare_threshold",
+    "max_rare_words",
+];
+
+/// The options of `ids` given on the command line, in that order, as `--name=value` with each
+/// value as written, or just `--name` for a flag
+fn given_options(ids: &[&str]) -> Vec<String> {
+    let command = Args::command();
+    let matches = command.clone().get_matches();
+    (ids.iter().copied())
+        .filter(|id| matches.value_source(id) == Some(ValueSource::CommandLine))
+        .flat_map(|id| {
+            let arg = (command.get_arguments())
+                .find(|arg| arg.get_id() == id)
+                .expect("the options noted are all arguments");
+            let name = format!("--{}", arg.get_long().unwrap_or(id));
+            match arg.get_action().takes_values() {
+                true => (matches.get_raw(id).into_iter().flatten())
+                    .map(|value| format!("{}={}", name, value.to_string_lossy()))
+                    .collect(),
+                false => vec![name],
             }
-        }
-        true
+        })
+        .collect()
+}
+
+fn word_list_names(args: &Args) -> String {
+    let mut names = match args.tier.is_empty() {
+        true => args.wordlist.clone(),
+        false => args.tier.join(", "),
+    };
+    if let Some(col_wordlist) = &args.col_wordlist {
+        names += &format!(" (columns from {})", col_wordlist);
     }
+    names
+}
 
-    fn add_word(&self, word: String) -> WordBox {
-        let mut rows: Vec<String> = self.rows.clone();
-        rows.push(word.clone());
-        let mut cols = self.cols.clone();
-        if self.is_symmetric {
-            cols.push(word.clone());
-        }
-        WordBox {
-            row_dim: self.row_dim,
-            col_dim: self.col_dim,
-            rows,
-            cols,
-            is_symmetric: self.is_symmetric,
-        }
+/// `arg` as a shell would need it typed, quoted unless it is plain
+fn shell_quote(arg: &str) -> String {
+    let plain = |ch: char| ch.is_ascii_alphanumeric() || "-_./=:,+@%".contains(ch);
+    match !arg.is_empty() && arg.chars().all(plain) {
+        true => arg.to_string(),
+        false => format!("'{}'", arg.replace('\'', "'\\''")),
     }
 }
 
-fn print_clear(wb: &WordBox) {
-    execute!(
-        stdout(),
-        cursor::RestorePosition,
-        style::PrintStyledContent(wb.to_string().cyan().bold())
-    );
+/// A solution printed once the run is over, under `heading` if there is one
+fn print_held_solution(
+    highlighting: &Highlighting,
+    heading: Option<&str>,
+    solution: &FoundSolution,
+    difficulty: Option<&Difficulty>,
+    puzzle: Option<&Puzzle>,
+) {
+    if let Some(heading) = heading {
+        println!("{}", heading);
+    }
+    print!("{}", highlighting.held(solution));
+    print_solution_notes(solution, difficulty, puzzle);
+    // A blank line tells one held box from the next, headed or not
+    println!();
+}
+
+/// How a puzzle is introduced under its answer: how many letters it shows, how many were
+/// asked for if keeping a blank cell in every word held it back, and whether its answer is the
+/// only box that fits if that was checked
+fn puzzle_heading(puzzle: &Puzzle) -> String {
+    let mut notes = vec![match puzzle.shown() < puzzle.wanted {
+        true => format!(
+            "{} letters showing, {} asked for",
+            puzzle.shown(),
+            puzzle.wanted
+        ),
+        false => format!("{} letters showing", puzzle.shown()),
+    }];
+    if puzzle.shown() < puzzle.wanted {
+        notes.push("every word keeps a blank cell".to_string());
+    }
+    let extra = match puzzle.extra_reveals {
+        0 => String::new(),
+        n => format!(" after showing {} more", n),
+    };
+    match &puzzle.uniqueness {
+        None => {}
+        Some(Uniqueness::Unique) => notes.push(format!("unique{}", extra)),
+        Some(Uniqueness::Ambiguous(other)) => notes.push(format!(
+            "not unique{}: {} fits too",
+            extra,
+            spelled_out(&other.row_words())
+        )),
+        Some(Uniqueness::Unknown) => notes.push(format!("uniqueness unknown{}", extra)),
+    }
+    format!("puzzle ({}):", notes.join("; "))
+}
+
+/// Words separated by commas, with their tiles spelled out
+fn spelled_out(words: &[String]) -> String {
+    let words: Vec<String> = words.iter().map(|word| digraphs::decode(word)).collect();
+    words.join(", ")
+}
+
+/// Write a solution to the output file: its heading if any, the box, then the columns that
+/// are not words if it is a near miss, its difficulty if it was rated, its theme and rare words if it has any and its puzzle if one was made,
+/// followed by a blank line
+fn write_solution(
+    output: &mut impl Write,
+    heading: Option<&str>,
+    solution: &FoundSolution,
+    difficulty: Option<&Difficulty>,
+    puzzle: Option<&Puzzle>,
+) {
+    let mut text = String::new();
+    if let Some(heading) = heading {
+        text += &format!("{}\n", heading);
+    }
+    text += &solution.word_box.to_string();
+    for line in near_miss_notes(solution) {
+        text += &format!("{}\n", line);
+    }
+    if let Some(difficulty) = difficulty {
+        text += &format!("{}\n", difficulty);
+    }
+    if !solution.theme_words.is_empty() {
+        text += &format!("theme words: {}\n", spelled_out(&solution.theme_words));
+    }
+    if !solution.rare_words.is_empty() {
+        text += &format!("rare words: {}\n", spelled_out(&solution.rare_words));
+    }
+    if !solution.tiers.is_empty() {
+        text += &format!("tiers: {}\n", solution.tiers);
+    }
+    if let Some(score) = solution.scrabble_score {
+        text += &format!("scrabble score: {}\n", score);
+    }
+    if let Some(letters) = solution.distinct_letters {
+        text += &format!("distinct letters: {}\n", letters);
+    }
+    if !solution.letter_counts.is_empty() {
+        text += &format!(
+            "letter counts: {}\n",
+            letter_counts(&solution.letter_counts)
+        );
+    }
+    if let Some(provenance) = &solution.provenance {
+        text += &format!("provenance: {}\n", provenance);
+    }
+    if let Some(puzzle) = puzzle {
+        text += &format!("{}\n{}", puzzle_heading(puzzle), puzzle);
+    }
+    writeln!(output, "{}", text).expect("Could not write solution");
+}
+
+/// Every solution written to a file of its own in --output-dir, named by --name-template
+struct SolutionFiles<'a> {
+    dir: &'a str,
+    template: &'a NameTemplate,
+    overwrite: bool,
+    dims: (usize, usize),
+    per_seed: HashMap<String, usize>, // solutions written so far from each seed
+    names: HashSet<String>,           // the names of the files written
 }
 
-fn solve_word_box<L: Lexicon>(wb: WordBox, lexicon: &L) -> Option<WordBox> {
-    let mut boxes: VecDeque<WordBox> = VecDeque::from([wb]);
-    while !boxes.is_empty() {
-        let wb = boxes.pop_front().unwrap();
-        // execute!(stdout(), terminal::Clear(terminal::ClearType::All)).ok();
-        // print_clear(&wb);
-        if wb.is_done() {
-            return Some(wb);
+impl<'a> SolutionFiles<'a> {
+    /// The files of a run of `row_dim`x`col_dim` boxes, creating the directory if it is missing
+    fn new(args: &'a Args, row_dim: usize, col_dim: usize) -> Option<Self> {
+        let dir = args.output_dir.as_deref()?;
+        std::fs::create_dir_all(dir).unwrap_or_else(|err| {
+            eprintln!("Could not create {}: {}", dir, err);
+            std::process::exit(1);
+        });
+        Some(SolutionFiles {
+            dir,
+            template: &args.name_template,
+            overwrite: args.overwrite,
+            dims: (row_dim, col_dim),
+            per_seed: HashMap::new(),
+            names: HashSet::new(),
+        })
+    }
+
+    /// Write a solution as `write_solution` does to the next name, stopping the run if the
+    /// template names two solutions alike or, unless --overwrite, the file is there already.
+    /// Solutions arrive one at a time on the main thread, so the numbering has no races.
+    fn write(
+        &mut self,
+        heading: Option<&str>,
+        solution: &FoundSolution,
+        difficulty: Option<&Difficulty>,
+        puzzle: Option<&Puzzle>,
+    ) {
+        let index = self.per_seed.entry(solution.seed.clone()).or_default();
+        *index += 1;
+        let name = self.template.render(&NameValues {
+            seed: &digraphs::decode(&solution.seed),
+            index: *index,
+            global: self.names.len() + 1,
+            rows: self.dims.0,
+            cols: self.dims.1,
+            ext: "txt",
+        });
+        if !self.names.insert(name.clone()) {
+            eprintln!(
+                "--name-template gives two solutions the name {}; add {{index}} or {{global}}",
+                name
+            );
+            std::process::exit(1);
         }
+        let path = std::path::Path::new(self.dir).join(&name);
+        let file = match self.overwrite {
+            true => File::create(&path),
+            false => File::create_new(&path),
+        };
+        let file = file.unwrap_or_else(|err| {
+            match err.kind() {
+                std::io::ErrorKind::AlreadyExists => eprintln!(
+                    "{} already exists; pass --overwrite to replace it",
+                    path.display()
+                ),
+                _ => eprintln!("Could not create {}: {}", path.display(), err),
+            }
+            std::process::exit(1);
+        });
+        let mut output = BufWriter::new(file);
+        write_solution(&mut output, heading, solution, difficulty, puzzle);
+        output.flush().expect("Could not write solution");
+    }
+}
 
-        let prefix = WordBox::take_ith_characters(&wb.cols, wb.rows.len());
-        let binding = lexicon.words_with_prefix(&prefix, wb.col_dim);
-        let choices = binding
+/// The notes printed under a solution: the columns that are not words if it is a near miss,
+/// how difficult its words are if it was rated, which of them are theme words or rare, its Scrabble score if it was scored, its number of different
+/// letters if the run asked for a minimum, whether its transpose is one
+/// too, where the required letters are, and its puzzle if one was made
+fn print_solution_notes(
+    solution: &FoundSolution,
+    difficulty: Option<&Difficulty>,
+    puzzle: Option<&Puzzle>,
+) {
+    for line in near_miss_notes(solution) {
+        println!("{}", line);
+    }
+    if let Some(difficulty) = difficulty {
+        println!("{}", difficulty);
+    }
+    if !solution.theme_words.is_empty() {
+        println!("theme words: {}", spelled_out(&solution.theme_words));
+    }
+    if !solution.rare_words.is_empty() {
+        println!("rare words: {}", spelled_out(&solution.rare_words));
+    }
+    if !solution.tiers.is_empty() {
+        println!("tiers: {}", solution.tiers);
+    }
+    if let Some(score) = solution.scrabble_score {
+        println!("scrabble score: {}", score);
+    }
+    if let Some(letters) = solution.distinct_letters {
+        println!("distinct letters: {}", letters);
+    }
+    if !solution.letter_counts.is_empty() {
+        println!("letter counts: {}", letter_counts(&solution.letter_counts));
+    }
+    if solution.transpose_implied {
+        println!("(its transpose is a solution too)");
+    }
+    for (letter, cells) in &solution.required_cells {
+        let cells: Vec<String> = cells
             .iter()
-            .filter(|word| wb.is_valid_move(word, lexicon));
+            .map(|(row, col)| format!("({}, {})", row, col))
+            .collect();
+        println!("{} at {}", letter, cells.join(", "));
+    }
+    if let Some(provenance) = &solution.provenance {
+        println!("provenance: {}", provenance);
+    }
+    if let Some(puzzle) = puzzle {
+        println!("{}", puzzle_heading(puzzle));
+        print!("{}", puzzle);
+    }
+}
+
+/// A line for each column of a near miss that is not a word, giving what it reads instead
+fn near_miss_notes(solution: &FoundSolution) -> impl Iterator<Item = String> + '_ {
+    (solution.invalid_columns.iter()).map(|(col, line)| {
+        format!(
+            "near miss: column {} reads {:?}, not a word",
+            col,
+            digraphs::decode(line)
+        )
+    })
+}
+
+/// Each letter with the cells holding it, e.g. `e=3, q=1`
+fn letter_counts(counts: &BTreeMap<char, usize>) -> String {
+    (counts.iter())
+        .map(|(letter, count)| format!("{}={}", letter, count))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
 
-        for choice in choices {
-            boxes.push_front(wb.add_word(choice.to_string()));
+/// Whether some word appears twice in `words`
+fn has_repeats(words: &[String]) -> bool {
+    let mut seen = HashSet::new();
+    !words.iter().all(|word| seen.insert(word))
+}
+
+/// Warn about every word length the box needs that the word list at `path` has no words of
+fn warn_missing_lengths(
+    path: &str,
+    stats: &LexiconStats,
+    lengths: &BTreeSet<usize>,
+    (row_dim, col_dim): (usize, usize),
+) {
+    for &dim in lengths {
+        if stats.words_of_length(dim) == 0 {
+            eprintln!(
+                "Warning: no {}-letter words in {}; no {}x{} boxes can be found",
+                dim, path, row_dim, col_dim
+            );
         }
     }
-    None
 }
-fn main() {
-    let start = Instant::now();
-    let words = filter_words("../3esl.txt");
 
-    // Find all word boxes of row_dim x col_dim
-    let row_dim = 6;
-    let col_dim = 6;
+/// The --lexicon of `words` of the given lengths, without the words carrying a blocked tag
+fn build_lexicon(
+    args: &Args,
+    words: Vec<String>,
+    lengths: BTreeSet<usize>,
+    tiers: Option<&WordTiers>,
+) -> DynLexicon {
+    // Blocked words are taken out before the rest are sorted into their tiers
+    let words = match &args.tag_file {
+        Some(tag_file) => {
+            let tags = WordTags::load(tag_file);
+            let (kept, removed) = tags.remove_blocked(words, &args.block_tags);
+            report_blocked(args, &removed);
+            kept
+        }
+        None => words,
+    };
+    let options = lexicon_options(args);
+    match tiers {
+        Some(tiers) => (args.lexicon).build_with_tiers(words, lengths, tiers, &options),
+        None => args.lexicon.build(words, lengths, &options),
+    }
+}
+
+/// Say how many words each blocked tag took out of the word list
+fn report_blocked(args: &Args, removed: &BTreeMap<String, usize>) {
+    for tag in &args.block_tags {
+        println!(
+            "Blocked {} words tagged {}",
+            removed.get(&tag.to_lowercase()).unwrap_or(&0),
+            tag
+        );
+    }
+}
 
-    let lexicon = HashMapLexicon::initialize(words, vec![row_dim, col_dim]);
+fn lexicon_options(args: &Args) -> LexiconOptions {
+    LexiconOptions {
+        max_prefix_depth: args.prefix_depth,
+    }
+}
 
-    lexicon
-        .words_with_prefix("", col_dim)
-        .iter()
-        .for_each(|word| {
-            let word_box_option = solve_word_box(
-                WordBox {
-                    row_dim,
-                    col_dim,
-                    rows: vec![word.to_string()],
-                    cols: vec![word.to_string()],
-                    is_symmetric: true,
-                },
-                &lexicon,
-            );
+fn solve_cube(args: &Args, words: Vec<String>) {
+    // Find k x k x k word cubes, one after another on this thread
+    let dim = args.cube.unwrap();
+    let words = exclude_letters(args, words);
+    let lexicon = args
+        .lexicon
+        .build(words, BTreeSet::from([dim]), &lexicon_options(args));
+    if lexicon.count_with_prefix("", dim) == 0 {
+        eprintln!(
+            "Warning: no {}-letter words in {}; no cubes can be found",
+            dim, args.wordlist
+        );
+    }
+    let interrupt = CancellationToken::new();
+    let config = SolverConfig {
+        forward_checking: args.forward_checking,
+        distinct_words: args.distinct_words,
+        cancellation: Some(interrupt.clone()),
+        ..SolverConfig::default()
+    };
+    ctrlc::set_handler(move || interrupt.cancel()).ok();
+    let limit = match (args.limit, args.all) {
+        (Some(limit), _) => limit,
+        (None, true) => usize::MAX,
+        (None, false) => 1,
+    };
 
-            match word_box_option {
-                Some(word_box) => {
-                    execute!(stdout(), terminal::Clear(terminal::ClearType::All)).ok();
-                    print_clear(&word_box);
-                    // println!("{}", word_box);
-                }
-                None => (),
-            }
-        });
-    let duration = start.elapsed();
-    println!("Time Duration: {:?}", duration);
+    let mut output = args
+        .output
+        .as_ref()
+        .map(|path| BufWriter::new(File::create(path).expect("Could not create output file")));
+    let mut found = 0;
+    let report = solve_word_cube(WordCube::new(dim), &lexicon, &config, |cube| {
+        let text = if args.json {
+            format!("{}\n", cube.to_json())
+        } else {
+            format!("{}\n", cube)
+        };
+        print!("{}", text);
+        if let Some(output) = output.as_mut() {
+            write!(output, "{}", text).expect("Could not write solution");
+        }
+        found += 1;
+        if found < limit {
+            ControlFlow::Continue(())
+        } else {
+            ControlFlow::Break(())
+        }
+    });
+    if let Some(mut output) = output {
+        output.flush().expect("Could not write solution");
+    }
+    if report.outcome == Outcome::Cancelled {
+        println!("Interrupted after {} solutions", found);
+    } else if found == 0 {
+        println!("No solutions found");
+    }
+    print_search_stats(
+        args,
+        &report.stats,
+        &CellMasks::default(),
+        None,
+        None,
+        None,
+        None,
+    );
+}
+
+/// The statistics of a whole run: a summary on stderr with --verbose, JSON with --stats, led by
+/// the shard of the seeds searched and the cell masks the run was constrained by, if any, and
+/// ending with the peak of the memory estimate and the causes of the dead ends if they were kept
+fn print_search_stats(
+    args: &Args,
+    stats: &SearchStats,
+    masks: &CellMasks,
+    shard: Option<Shard>,
+    memory: Option<&MemoryGauge>,
+    dead_ends: Option<&DeadEnds>,
+    inconclusive: Option<&[InconclusiveSeed]>,
+) {
+    if args.verbose {
+        eprintln!("Search totals: {}", stats);
+        if let Some(gauge) = memory {
+            eprintln!("Memory peak: {}", gauge.peak());
+        }
+    }
+    if args.stats {
+        let mut json = stats.to_json();
+        if let Some(gauge) = memory {
+            let limit = gauge
+                .limit()
+                .map_or("null".to_string(), |limit| limit.0.to_string());
+            json = format!(
+                "{},\"memory\":{{\"peak\":{},\"limit\":{},\"limit_reached\":{}}}}}",
+                &json[..json.len() - 1],
+                gauge.peak().to_json(),
+                limit,
+                gauge.times_over() > 0
+            );
+        }
+        if let Some(dead_ends) = dead_ends {
+            json = format!(
+                "{},\"dead_ends\":{}}}",
+                &json[..json.len() - 1],
+                dead_ends.to_json()
+            );
+        }
+        if let Some(inconclusive) = inconclusive {
+            let seeds: Vec<String> = inconclusive.iter().map(InconclusiveSeed::to_json).collect();
+            json = format!(
+                "{},\"inconclusive\":[{}]}}",
+                &json[..json.len() - 1],
+                seeds.join(",")
+            );
+        }
+        if !masks.is_empty() {
+            json = format!("{{\"masks\":{},{}", masks.to_json(), &json[1..]);
+        }
+        if let Some(shard) = shard {
+            json = format!("{{\"shard\":\"{}\",{}", shard, &json[1..]);
+        }
+        println!("{}", json);
+    }
 }
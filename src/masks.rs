@@ -0,0 +1,188 @@
+use crate::alphabet::Alphabet;
+use crate::cube::json_string;
+use crate::digraphs;
+use std::collections::BTreeSet;
+use std::fmt::Display;
+
+/// The letters written `vowels` in a mask; `consonants` is every other letter
+pub const VOWELS: &str = "aeiou";
+
+/// A line of a cell mask file that cannot be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MaskError {
+    Malformed { line: usize, text: String },
+    NoLetters { line: usize },
+    OutsideAlphabet { line: usize, ch: char },
+}
+
+impl Display for MaskError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MaskError::Malformed { line, text } => write!(
+                f,
+                "line {}: expected a row, a column and the letters allowed there, such as \
+                 `0 3 vowels` or `2 2 s/t/r`, found {:?}",
+                line, text
+            ),
+            MaskError::NoLetters { line } => write!(f, "line {}: no letters given", line),
+            MaskError::OutsideAlphabet { line, ch } => {
+                write!(f, "line {}: {:?} is not in the alphabet", line, ch)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MaskError {}
+
+/// The letters a cell may take: the ones listed, or every letter but those
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LetterMask {
+    letters: BTreeSet<char>,
+    except: bool,
+}
+
+impl LetterMask {
+    /// A mask allowing only `letters`
+    pub fn only(letters: impl IntoIterator<Item = char>) -> Self {
+        LetterMask {
+            letters: letters.into_iter().collect(),
+            except: false,
+        }
+    }
+
+    /// A mask allowing every letter but `letters`
+    pub fn except(letters: impl IntoIterator<Item = char>) -> Self {
+        LetterMask {
+            letters: letters.into_iter().collect(),
+            except: true,
+        }
+    }
+
+    pub fn allows(&self, ch: char) -> bool {
+        self.letters.contains(&ch) != self.except
+    }
+
+    /// The letters both masks allow
+    pub fn intersect(&self, other: &LetterMask) -> LetterMask {
+        match (self.except, other.except) {
+            (false, false) => LetterMask::only(self.letters.intersection(&other.letters).copied()),
+            (false, true) => LetterMask::only(self.letters.difference(&other.letters).copied()),
+            (true, false) => LetterMask::only(other.letters.difference(&self.letters).copied()),
+            (true, true) => LetterMask::except(self.letters.union(&other.letters).copied()),
+        }
+    }
+
+    /// Whether the mask allows no letter at all
+    pub fn is_empty(&self) -> bool {
+        !self.except && self.letters.is_empty()
+    }
+
+    /// Read a mask such as `vowels`, `consonants`, `str`, `s/t/r` or `^aeiou` (any letter but
+    /// those), with letters lowercased and checked against `alphabet`; `None` names the letter
+    /// outside it or, for no letters at all, nothing
+    fn parse(text: &str, alphabet: &Alphabet) -> Result<Self, Option<char>> {
+        match text {
+            "vowel" | "vowels" => return Ok(LetterMask::only(VOWELS.chars())),
+            "consonant" | "consonants" => return Ok(LetterMask::except(VOWELS.chars())),
+            _ => {}
+        }
+        let (except, letters) = match text.strip_prefix(['^', '!']) {
+            Some(letters) => (true, letters),
+            None => (false, text),
+        };
+        let letters: BTreeSet<char> = letters
+            .chars()
+            .filter(|ch| !matches!(ch, '/' | ',' | '|'))
+            .flat_map(char::to_lowercase)
+            .collect();
+        if let Some(&ch) = letters.iter().find(|&&ch| !alphabet.contains(ch)) {
+            return Err(Some(ch));
+        }
+        if letters.is_empty() {
+            return Err(None);
+        }
+        Ok(LetterMask { letters, except })
+    }
+}
+
+/// The letters listed, or `^` followed by the letters excluded
+impl Display for LetterMask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.except {
+            write!(f, "^")?;
+        }
+        (self.letters.iter()).try_for_each(|&ch| write!(f, "{}", digraphs::cell_text(ch)))
+    }
+}
+
+/// A mask on one cell, counted from 0
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellMask {
+    pub row: usize,
+    pub col: usize,
+    pub mask: LetterMask,
+}
+
+impl Display for CellMask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}, {}) {}", self.row, self.col, self.mask)
+    }
+}
+
+/// Softer constraints than a template's letters: the cells that may only take some letters,
+/// such as a vowel, or one of s, t and r
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CellMasks {
+    pub masks: Vec<CellMask>,
+}
+
+impl CellMasks {
+    /// Read masks from lines of `ROW COL LETTERS`, e.g. `0 3 vowels` or `2 2 s/t/r`; blank
+    /// lines and `#` comments are skipped. A cell given twice takes the letters both allow.
+    pub fn parse(text: &str, alphabet: &Alphabet) -> Result<Self, MaskError> {
+        let mut masks = vec![];
+        for (i, line) in text.lines().enumerate() {
+            let text = line.trim();
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+            let malformed = || MaskError::Malformed {
+                line: i + 1,
+                text: text.to_string(),
+            };
+            let fields: Vec<&str> = text.split_whitespace().collect();
+            let [row, col, letters] = fields[..] else {
+                return Err(malformed());
+            };
+            let (Ok(row), Ok(col)) = (row.parse(), col.parse()) else {
+                return Err(malformed());
+            };
+            let mask = LetterMask::parse(letters, alphabet).map_err(|ch| match ch {
+                Some(ch) => MaskError::OutsideAlphabet { line: i + 1, ch },
+                None => MaskError::NoLetters { line: i + 1 },
+            })?;
+            masks.push(CellMask { row, col, mask });
+        }
+        Ok(CellMasks { masks })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.masks.is_empty()
+    }
+
+    /// The masks as a JSON array of `{"row", "col", "letters"}` objects, `letters` written as
+    /// in the mask file
+    pub fn to_json(&self) -> String {
+        let masks: Vec<String> = (self.masks.iter())
+            .map(|cell| {
+                format!(
+                    "{{\"row\":{},\"col\":{},\"letters\":{}}}",
+                    cell.row,
+                    cell.col,
+                    json_string(&cell.mask.to_string())
+                )
+            })
+            .collect();
+        format!("[{}]", masks.join(","))
+    }
+}
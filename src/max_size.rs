@@ -0,0 +1,347 @@
+use crate::cancel::CancellationToken;
+use crate::lexicon::{LexiconKind, LexiconOptions};
+use crate::nogood::NogoodCache;
+use crate::solver::{solve_word_box_all, Outcome, SearchReport, SolverConfig};
+use crate::wordbox::WordBox;
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::ops::{ControlFlow, RangeInclusive};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Partial boxes remembered as dead ends while searching one size
+const NOGOOD_ENTRIES: usize = 1 << 20;
+
+/// What the search for one size of box settled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SizeVerdict {
+    /// A box of this size, proving that one exists
+    Solvable(WordBox),
+    /// Every box of this size was ruled out
+    Impossible,
+    /// The node budget or the time limit ran out before either was shown
+    Inconclusive,
+}
+
+/// How the search for an n×n box went
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizeResult {
+    pub size: usize,
+    pub verdict: SizeVerdict,
+    pub report: SearchReport,
+    pub timed_out: bool, // the time limit cut the search short
+}
+
+impl Display for SizeResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let size = format!("{}x{}", self.size, self.size);
+        let effort = format!(
+            "{} nodes, {:.2?}",
+            self.report.stats.nodes, self.report.stats.elapsed
+        );
+        match &self.verdict {
+            SizeVerdict::Solvable(wb) => write!(f, "{}: solvable ({})\n{}", size, effort, wb),
+            SizeVerdict::Impossible => writeln!(f, "{}: proved impossible ({})", size, effort),
+            SizeVerdict::Inconclusive => {
+                let reason = match self.report.outcome {
+                    Outcome::BudgetExhausted => "budget exhausted",
+                    _ if self.timed_out => "timed out",
+                    _ => "interrupted",
+                };
+                writeln!(f, "{}: inconclusive, {} ({})", size, reason, effort)
+            }
+        }
+    }
+}
+
+/// Limits and settings for the search at each size
+#[derive(Debug, Clone)]
+pub struct MaxSizeOptions {
+    pub symmetric: bool,
+    pub lexicon: LexiconKind, // the lexicon each size builds over its words
+    pub max_nodes: usize,     // nodes each size may search before it is inconclusive
+    pub timeout: Duration,    // time each size may take before it is inconclusive
+    pub cancellation: CancellationToken, // stops the size being searched and every later one
+}
+
+/// Look for one n×n box built from `words`, with forward checking and a nogood cache, giving
+/// up once the node budget or the time limit runs out
+pub fn search_size(words: &[String], size: usize, options: &MaxSizeOptions) -> SizeResult {
+    let lexicon = (options.lexicon).build(
+        words.to_vec(),
+        BTreeSet::from([size]),
+        &LexiconOptions::default(),
+    );
+    let token = options.cancellation.child();
+    let config = SolverConfig {
+        forward_checking: true,
+        nogoods: Some(Arc::new(NogoodCache::new(NOGOOD_ENTRIES))),
+        cancellation: Some(token.clone()),
+        max_nodes: Some(options.max_nodes),
+        ..SolverConfig::default()
+    };
+
+    let mut witness = None;
+    let empty = WordBox::new(size, size, options.symmetric);
+    let (report, timed_out) = with_time_limit(token, Some(options.timeout), || {
+        solve_word_box_all(empty, &lexicon, &config, |wb| {
+            witness = Some(wb);
+            ControlFlow::Break(())
+        })
+    });
+
+    let verdict = match (witness, report.outcome) {
+        (Some(wb), _) => SizeVerdict::Solvable(wb),
+        (None, Outcome::Exhausted) => SizeVerdict::Impossible,
+        (None, _) => SizeVerdict::Inconclusive,
+    };
+    SizeResult {
+        size,
+        verdict,
+        report,
+        timed_out,
+    }
+}
+
+/// Search every size in `sizes` in increasing order, handing each result to `on_result` as soon
+/// as it is settled; stops early once the options' token is cancelled
+pub fn search_sizes(
+    words: &[String],
+    sizes: RangeInclusive<usize>,
+    options: &MaxSizeOptions,
+    mut on_result: impl FnMut(SizeResult),
+) {
+    for size in sizes {
+        if options.cancellation.is_cancelled() {
+            break;
+        }
+        on_result(search_size(words, size, options));
+    }
+}
+
+/// Run `search`, cancelling `token` if it is still going once `timeout` has passed; also says
+/// whether the time ran out
+pub fn with_time_limit<T>(
+    token: CancellationToken,
+    timeout: Option<Duration>,
+    search: impl FnOnce() -> T,
+) -> (T, bool) {
+    let Some(timeout) = timeout else {
+        return (search(), false);
+    };
+    let (done, finished) = mpsc::channel::<()>();
+    let timer = std::thread::spawn(move || {
+        // The sender is dropped as soon as the search returns, which ends the wait early
+        let timed_out = finished.recv_timeout(timeout) == Err(RecvTimeoutError::Timeout);
+        if timed_out {
+            token.cancel();
+        }
+        timed_out
+    });
+    let result = search();
+    drop(done);
+    (result, timer.join().unwrap_or(false))
+}
+
+/// The sizes of a table of counts, written `2..=5`, `2-5` or just `4`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeRange {
+    pub min: usize,
+    pub max: usize,
+}
+
+impl std::str::FromStr for SizeRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (min, max) = match s.split_once("..=").or_else(|| s.split_once('-')) {
+            Some((min, max)) => (min, max),
+            None => (s, s),
+        };
+        let parse = |n: &str| match n.trim().parse::<usize>() {
+            Ok(0) => Err("a box needs at least one row and one column".to_string()),
+            Ok(n) => Ok(n),
+            Err(_) => Err(format!("expected sizes such as 2..=5, found {:?}", s)),
+        };
+        let (min, max) = (parse(min)?, parse(max)?);
+        if min > max {
+            return Err(format!("the range {:?} is empty", s));
+        }
+        Ok(SizeRange { min, max })
+    }
+}
+
+impl Display for SizeRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..={}", self.min, self.max)
+    }
+}
+
+impl SizeRange {
+    pub fn sizes(&self) -> RangeInclusive<usize> {
+        self.min..=self.max
+    }
+}
+
+/// How many n×n boxes there are, or how many were found before the limits ran out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizeCount {
+    pub size: usize,
+    pub report: SearchReport,
+    pub timed_out: bool, // the time limit cut the search short
+}
+
+impl SizeCount {
+    /// Whether every box of this size was counted
+    pub fn is_exact(&self) -> bool {
+        self.report.outcome == Outcome::Exhausted
+    }
+
+    /// Why the count stopped short, if it did
+    pub fn shortfall(&self) -> Option<&'static str> {
+        match self.report.outcome {
+            Outcome::Exhausted => None,
+            Outcome::BudgetExhausted => Some("budget exhausted"),
+            _ if self.timed_out => Some("timed out"),
+            _ => Some("interrupted"),
+        }
+    }
+
+    /// The number of boxes, with `≥` in front when more may exist
+    fn solutions(&self) -> String {
+        match self.is_exact() {
+            true => self.report.solutions.to_string(),
+            false => format!("≥ {}", self.report.solutions),
+        }
+    }
+}
+
+/// Limits and settings for counting the boxes of each size
+#[derive(Debug, Clone, Default)]
+pub struct CountOptions {
+    pub symmetric: bool,
+    pub lexicon: LexiconKind, // the lexicon each size builds over its words
+    pub max_nodes: Option<usize>, // nodes each size may search before its count stops short
+    pub timeout: Option<Duration>, // time each size may take before its count stops short
+    pub cancellation: CancellationToken, // stops the size being counted and every later one
+}
+
+/// Count every n×n box built from `words` that `config` accepts until the options' node
+/// budget or time limit runs out. `config` decides everything else about the search; its
+/// budget and cancellation are replaced by the options'.
+pub fn count_size(
+    words: &[String],
+    size: usize,
+    config: &SolverConfig,
+    options: &CountOptions,
+) -> SizeCount {
+    let lexicon = (options.lexicon).build(
+        words.to_vec(),
+        BTreeSet::from([size]),
+        &LexiconOptions::default(),
+    );
+    let token = options.cancellation.child();
+    let config = SolverConfig {
+        cancellation: Some(token.clone()),
+        max_nodes: options.max_nodes,
+        ..config.clone()
+    };
+    let empty = WordBox::new(size, size, options.symmetric);
+    let (report, timed_out) = with_time_limit(token, options.timeout, || {
+        solve_word_box_all(empty, &lexicon, &config, |_| ControlFlow::Continue(()))
+    });
+    SizeCount {
+        size,
+        report,
+        timed_out,
+    }
+}
+
+/// Count the boxes of every size in `sizes` in increasing order, handing each count to
+/// `on_count` as soon as it is done; stops early once the options' token is cancelled
+pub fn count_sizes(
+    words: &[String],
+    sizes: RangeInclusive<usize>,
+    config: &SolverConfig,
+    options: &CountOptions,
+    mut on_count: impl FnMut(SizeCount),
+) {
+    for size in sizes {
+        if options.cancellation.is_cancelled() {
+            break;
+        }
+        on_count(count_size(words, size, config, options));
+    }
+}
+
+/// Render counts as an aligned table with a grand total, which is a lower bound when some
+/// count stopped short
+pub struct SizeTable<'a>(pub &'a [SizeCount]);
+
+impl SizeTable<'_> {
+    fn total(&self) -> SizeCount {
+        let mut report = SearchReport::default();
+        for count in self.0 {
+            report.solutions += count.report.solutions;
+            report.stats = report.stats.merge(count.report.stats);
+            report.outcome = report.outcome.combine(count.report.outcome);
+        }
+        SizeCount {
+            size: 0,
+            report,
+            timed_out: self.0.iter().any(|count| count.timed_out),
+        }
+    }
+
+    /// The table as CSV, one row per size and a last row for the total
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("size,solutions,exact,status,nodes,seconds\n");
+        let total = self.total();
+        let rows = self
+            .0
+            .iter()
+            .map(|count| (format!("{0}x{0}", count.size), count));
+        for (size, count) in rows.chain([("total".to_string(), &total)]) {
+            csv += &format!(
+                "{},{},{},{},{},{:.6}\n",
+                size,
+                count.report.solutions,
+                count.is_exact(),
+                count.shortfall().unwrap_or("exhausted"),
+                count.report.stats.nodes,
+                count.report.stats.elapsed.as_secs_f64()
+            );
+        }
+        csv
+    }
+}
+
+impl Display for SizeTable<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:<8} {:>14} {:>14} {:>12}",
+            "size", "solutions", "nodes", "time"
+        )?;
+        let total = self.total();
+        let rows = self
+            .0
+            .iter()
+            .map(|count| (format!("{0}x{0}", count.size), count));
+        for (size, count) in rows.chain([("total".to_string(), &total)]) {
+            writeln!(
+                f,
+                "{:<8} {:>14} {:>14} {:>12}{}",
+                size,
+                count.solutions(),
+                count.report.stats.nodes,
+                format!("{:.2?}", count.report.stats.elapsed),
+                count
+                    .shortfall()
+                    .map_or(String::new(), |reason| format!(" ({})", reason))
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,509 @@
+use crate::cancel::CancellationToken;
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::mem::size_of;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Rough number of bytes a value takes up, counting its heap allocations along with itself.
+///
+/// Estimates leave out allocator overhead, and data behind an `Arc` only counts where it was
+/// made rather than wherever it is shared; they are meant for keeping a run within a budget,
+/// not for exact accounting.
+pub trait EstimatedSize {
+    fn estimated_bytes(&self) -> usize;
+}
+
+impl EstimatedSize for usize {
+    fn estimated_bytes(&self) -> usize {
+        size_of::<usize>()
+    }
+}
+
+impl EstimatedSize for char {
+    fn estimated_bytes(&self) -> usize {
+        size_of::<char>()
+    }
+}
+
+impl EstimatedSize for String {
+    fn estimated_bytes(&self) -> usize {
+        size_of::<String>() + self.capacity()
+    }
+}
+
+/// The elements, plus the room reserved for more
+impl<T: EstimatedSize> EstimatedSize for Vec<T> {
+    fn estimated_bytes(&self) -> usize {
+        size_of::<Vec<T>>()
+            + self.iter().map(T::estimated_bytes).sum::<usize>()
+            + (self.capacity() - self.len()) * size_of::<T>()
+    }
+}
+
+impl<T: EstimatedSize> EstimatedSize for Option<T> {
+    fn estimated_bytes(&self) -> usize {
+        match self {
+            Some(value) => value.estimated_bytes() - size_of::<T>() + size_of::<Option<T>>(),
+            None => size_of::<Option<T>>(),
+        }
+    }
+}
+
+impl<A: EstimatedSize, B: EstimatedSize> EstimatedSize for (A, B) {
+    fn estimated_bytes(&self) -> usize {
+        self.0.estimated_bytes() - size_of::<A>() + self.1.estimated_bytes() - size_of::<B>()
+            + size_of::<(A, B)>()
+    }
+}
+
+/// Each slot of the table holds an element and a control byte, used or not
+impl<T: EstimatedSize> EstimatedSize for HashSet<T> {
+    fn estimated_bytes(&self) -> usize {
+        size_of::<HashSet<T>>()
+            + self.capacity() * (size_of::<T>() + 1)
+            + (self.iter())
+                .map(|value| value.estimated_bytes() - size_of::<T>())
+                .sum::<usize>()
+    }
+}
+
+/// The bytes of a string shared as `Arc<str>`, with the reference counts in front
+pub(crate) fn shared_str_bytes(text: &str) -> usize {
+    size_of::<Arc<str>>() + 2 * size_of::<usize>() + text.len()
+}
+
+/// A number of bytes with a binary unit, e.g. `1.5 GiB`
+pub fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// How much memory a run may use by its own estimate, written as bytes or with a binary suffix:
+/// `4G`, `512M`, `1.5g`, `64KiB`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryLimit(pub usize);
+
+impl std::str::FromStr for MemoryLimit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("expected a size such as 4G, 512M or 65536, found {:?}", s);
+        let text = s.trim();
+        let split = text
+            .find(|ch: char| !ch.is_ascii_digit() && ch != '.')
+            .unwrap_or(text.len());
+        let (number, unit) = text.split_at(split);
+        let scale: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+            "" | "b" => 1,
+            "k" | "kb" | "kib" => 1 << 10,
+            "m" | "mb" | "mib" => 1 << 20,
+            "g" | "gb" | "gib" => 1 << 30,
+            "t" | "tb" | "tib" => 1 << 40,
+            _ => return Err(invalid()),
+        };
+        let number: f64 = number.parse().map_err(|_| invalid())?;
+        let bytes = number * scale as f64;
+        if bytes < 1.0 || bytes >= usize::MAX as f64 {
+            return Err(format!("{:?} is not a usable memory limit", s));
+        }
+        Ok(MemoryLimit(bytes as usize))
+    }
+}
+
+impl Display for MemoryLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", format_bytes(self.0))
+    }
+}
+
+/// What a run does once its memory estimate crosses `--memory-limit`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MemoryAction {
+    /// Keep frontier boxes without their forward-checking letter sets from then on, working
+    /// them out again when a box is expanded; slower, but the boxes take far less room (only
+    /// the breadth-first, best-first and beam frontiers hold enough boxes for it to matter)
+    Compact,
+    /// Empty the nogood cache, which then fills up again from scratch
+    ClearCaches,
+    /// End the run as if interrupted, keeping the solutions found so far
+    #[default]
+    Stop,
+}
+
+impl MemoryAction {
+    pub const ALL: [MemoryAction; 3] = [
+        MemoryAction::Compact,
+        MemoryAction::ClearCaches,
+        MemoryAction::Stop,
+    ];
+}
+
+impl Display for MemoryAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MemoryAction::Compact => write!(f, "compact"),
+            MemoryAction::ClearCaches => write!(f, "clear-caches"),
+            MemoryAction::Stop => write!(f, "stop"),
+        }
+    }
+}
+
+impl std::str::FromStr for MemoryAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        MemoryAction::ALL
+            .into_iter()
+            .find(|action| action.to_string() == s)
+            .ok_or_else(|| {
+                let names: Vec<String> = MemoryAction::ALL.iter().map(|a| a.to_string()).collect();
+                format!(
+                    "unknown memory action {:?} (expected one of {})",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// The structures a run's memory goes to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Consumer {
+    Frontier, // partial boxes waiting to be expanded, across every search in progress
+    Caches,   // the nogood cache
+    Dedup,    // the solutions remembered to drop duplicates
+    Buffered, // solutions held back to be reported in order, or sorted at the end
+}
+
+impl Consumer {
+    pub const ALL: [Consumer; 4] = [
+        Consumer::Frontier,
+        Consumer::Caches,
+        Consumer::Dedup,
+        Consumer::Buffered,
+    ];
+}
+
+impl Display for Consumer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Consumer::Frontier => write!(f, "frontier"),
+            Consumer::Caches => write!(f, "caches"),
+            Consumer::Dedup => write!(f, "dedup"),
+            Consumer::Buffered => write!(f, "buffered"),
+        }
+    }
+}
+
+/// The estimated bytes of each consumer at some point of a run, in the order of `Consumer::ALL`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryUsage(pub [usize; 4]);
+
+impl MemoryUsage {
+    pub fn of(&self, consumer: Consumer) -> usize {
+        self.0[consumer as usize]
+    }
+
+    pub fn total(&self) -> usize {
+        self.0.iter().sum()
+    }
+
+    /// The usage as a JSON object of bytes by consumer, with the total
+    pub fn to_json(&self) -> String {
+        let fields: Vec<String> = (Consumer::ALL.iter())
+            .map(|&consumer| format!("\"{}\":{}", consumer, self.of(consumer)))
+            .collect();
+        format!("{{{},\"total\":{}}}", fields.join(","), self.total())
+    }
+}
+
+/// The total, then the consumers using any memory, e.g. `~12.3 MiB (frontier 11.9 MiB, dedup
+/// 409.6 KiB)`
+impl Display for MemoryUsage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let parts: Vec<String> = (Consumer::ALL.iter())
+            .filter(|&&consumer| self.of(consumer) > 0)
+            .map(|&consumer| format!("{} {}", consumer, format_bytes(self.of(consumer))))
+            .collect();
+        write!(f, "~{}", format_bytes(self.total()))?;
+        if !parts.is_empty() {
+            write!(f, " ({})", parts.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Memory accounting shared by every worker of a run: the estimated bytes of each consumer,
+/// the peak of their total, and what to do once that total crosses the limit.
+///
+/// The searches report their frontiers every so often rather than on every push, so the
+/// figures lag behind a little; the limit is a budget, not a hard cap.
+#[derive(Debug, Default)]
+pub struct MemoryGauge {
+    limit: Option<MemoryLimit>,
+    action: MemoryAction,
+    bytes: [AtomicUsize; 4],
+    peak: [AtomicUsize; 4], // each consumer at the peak of the total
+    peak_total: AtomicUsize,
+    times_over: AtomicUsize, // checks that found the total over the limit
+    compact: AtomicBool,
+    stop: CancellationToken,
+}
+
+impl MemoryGauge {
+    /// A gauge that only keeps count
+    pub fn new() -> Self {
+        MemoryGauge::default()
+    }
+
+    /// A gauge that takes `action` whenever a check finds the total over `limit`
+    pub fn with_limit(limit: MemoryLimit, action: MemoryAction) -> Self {
+        MemoryGauge {
+            limit: Some(limit),
+            action,
+            ..MemoryGauge::default()
+        }
+    }
+
+    pub fn limit(&self) -> Option<MemoryLimit> {
+        self.limit
+    }
+
+    pub fn action(&self) -> MemoryAction {
+        self.action
+    }
+
+    pub fn add(&self, consumer: Consumer, bytes: usize) {
+        self.bytes[consumer as usize].fetch_add(bytes, Ordering::Relaxed);
+        self.note_peak();
+    }
+
+    pub fn sub(&self, consumer: Consumer, bytes: usize) {
+        // Saturating, as a consumer reset by `set` may still hand back bytes added before
+        let counter = &self.bytes[consumer as usize];
+        counter
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |held| {
+                Some(held.saturating_sub(bytes))
+            })
+            .ok();
+    }
+
+    pub fn set(&self, consumer: Consumer, bytes: usize) {
+        self.bytes[consumer as usize].store(bytes, Ordering::Relaxed);
+        self.note_peak();
+    }
+
+    fn note_peak(&self) {
+        let usage = self.usage();
+        let total = usage.total();
+        if self.peak_total.fetch_max(total, Ordering::Relaxed) < total {
+            for (peak, bytes) in self.peak.iter().zip(usage.0) {
+                peak.store(bytes, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// The estimates as they stand
+    pub fn usage(&self) -> MemoryUsage {
+        MemoryUsage(
+            self.bytes
+                .each_ref()
+                .map(|bytes| bytes.load(Ordering::Relaxed)),
+        )
+    }
+
+    /// The estimates when their total was highest
+    pub fn peak(&self) -> MemoryUsage {
+        MemoryUsage(
+            self.peak
+                .each_ref()
+                .map(|bytes| bytes.load(Ordering::Relaxed)),
+        )
+    }
+
+    /// Whether the total is over the limit, counting each time it is found to be. The caller
+    /// takes the gauge's action; compacting and stopping are recorded here as well.
+    pub fn check(&self) -> bool {
+        let Some(limit) = self.limit else {
+            return false;
+        };
+        if self.usage().total() <= limit.0 {
+            return false;
+        }
+        self.times_over.fetch_add(1, Ordering::Relaxed);
+        match self.action {
+            MemoryAction::Compact => self.compact.store(true, Ordering::Relaxed),
+            MemoryAction::ClearCaches => {}
+            MemoryAction::Stop => self.stop.cancel(),
+        }
+        true
+    }
+
+    /// How many checks found the total over the limit
+    pub fn times_over(&self) -> usize {
+        self.times_over.load(Ordering::Relaxed)
+    }
+
+    /// Whether frontiers should keep their boxes compact
+    pub fn is_compact(&self) -> bool {
+        self.compact.load(Ordering::Relaxed)
+    }
+
+    /// Whether the run was stopped for going over the limit
+    pub fn is_stopped(&self) -> bool {
+        self.stop.is_cancelled()
+    }
+}
+
+/// One search's share of a consumer, reported to a gauge as it changes and handed back when
+/// the meter is dropped
+#[derive(Debug)]
+pub struct Meter<'a> {
+    gauge: Option<&'a MemoryGauge>,
+    consumer: Consumer,
+    reported: usize,
+}
+
+impl<'a> Meter<'a> {
+    pub fn new(gauge: Option<&'a MemoryGauge>, consumer: Consumer) -> Self {
+        Meter {
+            gauge,
+            consumer,
+            reported: 0,
+        }
+    }
+
+    pub fn gauge(&self) -> Option<&'a MemoryGauge> {
+        self.gauge
+    }
+
+    /// Replace the share reported before with `bytes`
+    pub fn report(&mut self, bytes: usize) {
+        let Some(gauge) = self.gauge else {
+            return;
+        };
+        if bytes > self.reported {
+            gauge.add(self.consumer, bytes - self.reported);
+        } else {
+            gauge.sub(self.consumer, self.reported - bytes);
+        }
+        self.reported = bytes;
+    }
+}
+
+impl Drop for Meter<'_> {
+    fn drop(&mut self) {
+        self.report(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(capacity: usize) -> String {
+        String::with_capacity(capacity)
+    }
+
+    #[test]
+    fn sizes_add_up_the_heap_and_the_value() {
+        let s = size_of::<String>();
+        assert_eq!(string(10).estimated_bytes(), s + 10);
+
+        // Room reserved for two more strings counts as their bare size
+        let mut strings = Vec::with_capacity(4);
+        strings.extend([string(3), string(5)]);
+        assert_eq!(
+            strings.estimated_bytes(),
+            size_of::<Vec<String>>() + (s + 3) + (s + 5) + 2 * s
+        );
+
+        assert_eq!(
+            Some(string(7)).estimated_bytes(),
+            size_of::<Option<String>>() + 7
+        );
+        assert_eq!(
+            None::<String>.estimated_bytes(),
+            size_of::<Option<String>>()
+        );
+        assert_eq!(
+            (string(4), 9usize).estimated_bytes(),
+            size_of::<(String, usize)>() + 4
+        );
+
+        let set: HashSet<String> = [string(6), "ab".to_string()].into_iter().collect();
+        let heap: usize = set.iter().map(String::capacity).sum();
+        assert_eq!(
+            set.estimated_bytes(),
+            size_of::<HashSet<String>>() + set.capacity() * (s + 1) + heap
+        );
+        // Two reference counts sit in front of the text
+        assert_eq!(
+            shared_str_bytes("abc"),
+            size_of::<Arc<str>>() + 2 * size_of::<usize>() + 3
+        );
+    }
+
+    #[test]
+    fn limits_read_binary_units() {
+        for (text, bytes) in [
+            ("65536", 65536),
+            ("64KiB", 64 << 10),
+            ("512M", 512 << 20),
+            ("1.5g", 3 << 29),
+            ("4G", 4 << 30),
+        ] {
+            assert_eq!(text.parse(), Ok(MemoryLimit(bytes)), "{}", text);
+        }
+        for text in ["", "0", "4X", "G", "0.1"] {
+            assert!(text.parse::<MemoryLimit>().is_err(), "{}", text);
+        }
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(3 << 29), "1.5 GiB");
+    }
+
+    #[test]
+    fn gauges_track_the_total_and_its_peak() {
+        let gauge = MemoryGauge::with_limit(MemoryLimit(1000), MemoryAction::Compact);
+        gauge.add(Consumer::Frontier, 600);
+        gauge.set(Consumer::Dedup, 300);
+        assert!(!gauge.check());
+        {
+            let mut meter = Meter::new(Some(&gauge), Consumer::Buffered);
+            meter.report(200);
+            assert_eq!(gauge.usage(), MemoryUsage([600, 0, 300, 200]));
+            assert!(gauge.check());
+            meter.report(50);
+            assert_eq!(gauge.usage().total(), 950);
+        }
+        // A dropped meter hands its share back, and the gauge never goes below 0
+        gauge.sub(Consumer::Frontier, 700);
+        assert_eq!(gauge.usage(), MemoryUsage([0, 0, 300, 0]));
+        assert_eq!(gauge.peak(), MemoryUsage([600, 0, 300, 200]));
+        assert_eq!(gauge.times_over(), 1);
+        assert!(gauge.is_compact() && !gauge.is_stopped());
+        assert_eq!(
+            gauge.peak().to_json(),
+            "{\"frontier\":600,\"caches\":0,\"dedup\":300,\"buffered\":200,\"total\":1100}"
+        );
+        assert_eq!(
+            gauge.peak().to_string(),
+            "~1.1 KiB (frontier 600 B, dedup 300 B, buffered 200 B)"
+        );
+
+        let gauge = MemoryGauge::with_limit(MemoryLimit(10), MemoryAction::Stop);
+        gauge.add(Consumer::Caches, 11);
+        assert!(gauge.check() && gauge.is_stopped() && !gauge.is_compact());
+    }
+}
@@ -0,0 +1,274 @@
+use crate::alphabet::Alphabet;
+use crate::blocks::BLOCK;
+use crate::lexicon::word_length;
+use crate::wordbox::{WordBox, WordBoxError};
+use std::collections::HashSet;
+use std::fmt::Display;
+
+/// A box read back from a solution file, with the notes written under it
+#[derive(Debug, Clone)]
+pub struct SolutionRecord {
+    pub word_box: WordBox, // asymmetric, so that its columns are words of their own
+    pub notes: Vec<String>, // the lines under the box, as written
+    pub line: usize,       // the line of the file the record starts on, counting from 1
+}
+
+impl SolutionRecord {
+    /// The first word of the box, across or down, that is not in `words`
+    pub fn missing_word(&self, words: &HashSet<String>) -> Option<&str> {
+        (self.word_box.words()).find(|word| !words.contains(*word))
+    }
+}
+
+impl Display for SolutionRecord {
+    /// The box and its notes, as a run writes them, without the heading it had
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.word_box)?;
+        for note in &self.notes {
+            writeln!(f, "{}", note)?;
+        }
+        Ok(())
+    }
+}
+
+/// Why a record of a solution file was skipped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordError {
+    /// No line of the record reads as the row of a box
+    NoBox,
+    /// A row does not fit the rows above it: it has a different length, or a letter outside the
+    /// alphabet
+    BadRow(WordBoxError),
+    /// The puzzle under the box has fewer lines than the box has rows, or no word bank
+    CutPuzzle,
+    /// The file ends without the blank line that closes every record
+    Unterminated,
+    /// A word of the box is not in the word list it was checked against
+    NotAWord(String),
+}
+
+impl Display for RecordError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecordError::NoBox => write!(f, "no box in this record"),
+            RecordError::BadRow(err) => write!(f, "{}", err),
+            RecordError::CutPuzzle => write!(f, "the puzzle under the box is cut short"),
+            RecordError::Unterminated => write!(
+                f,
+                "the file ends in the middle of this record, which may have been cut short"
+            ),
+            RecordError::NotAWord(word) => write!(f, "{:?} is not in the word list", word),
+        }
+    }
+}
+
+/// A record skipped, and the line of the file it starts on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordProblem {
+    pub line: usize,
+    pub error: RecordError,
+}
+
+impl Display for RecordProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.error)
+    }
+}
+
+/// Whether a line can be the row of a box: lowercase letters and blocks, with spaces between
+/// the cells of a box with multi-letter tiles. Headings, notes and puzzles all have something
+/// else, a capital, a digit, a colon or a dot.
+fn is_row(line: &str) -> bool {
+    (line.chars()).all(|ch| ch.is_lowercase() || ch == BLOCK || ch == ' ')
+        && !line.starts_with("# ")
+}
+
+/// The box of the record made of `lines`, and the notes under it
+fn read_record(
+    lines: &[&str],
+    alphabet: &Alphabet,
+    encode: &impl Fn(&str) -> String,
+) -> Result<(WordBox, Vec<String>), RecordError> {
+    let start = lines.iter().position(|line| is_row(line));
+    let start = start.ok_or(RecordError::NoBox)?;
+    let rows: Vec<String> = (lines[start..].iter())
+        .take_while(|line| is_row(line))
+        .map(|line| encode(&line.split_whitespace().collect::<String>()))
+        .collect();
+    let (row_dim, col_dim) = (rows.len(), word_length(&rows[0]));
+    let wb = WordBox::try_new(row_dim, col_dim, false).map_err(RecordError::BadRow)?;
+    let wb = (rows.into_iter())
+        .try_fold(wb.with_alphabet(alphabet), |wb, row| wb.add_word(row))
+        .map_err(RecordError::BadRow)?;
+    let notes = &lines[start + row_dim..];
+    // A puzzle has a line per row of the box, then the word bank
+    if let Some(puzzle) = notes.iter().position(|line| line.starts_with("puzzle (")) {
+        let bank = notes.get(puzzle + 1 + row_dim);
+        if !bank.is_some_and(|line| line.starts_with("word bank: ")) {
+            return Err(RecordError::CutPuzzle);
+        }
+    }
+    Ok((wb, notes.iter().map(|note| note.to_string()).collect()))
+}
+
+/// The records of a solution file, as the --output of a run writes them: each a heading if it
+/// has one, the rows of its box and the notes under them, closed by a blank line. Lines of
+/// their own starting `# `, like the shard a file came from, are skipped. A record that cannot
+/// be read is skipped too, and the problem returned along with the line it starts on, so that
+/// one corrupt or partly written record costs only itself. `encode` turns a row as written into
+/// the box's letters, as for boxes with multi-letter tiles.
+pub fn read_solutions(
+    text: &str,
+    alphabet: &Alphabet,
+    encode: impl Fn(&str) -> String,
+) -> (Vec<SolutionRecord>, Vec<RecordProblem>) {
+    let lines: Vec<&str> = text.lines().map(str::trim_end).collect();
+    let (mut records, mut problems) = (vec![], vec![]);
+    let mut start = 0;
+    while start < lines.len() {
+        if lines[start].trim().is_empty() || lines[start].starts_with("# ") {
+            start += 1;
+            continue;
+        }
+        let len = (lines[start..].iter())
+            .take_while(|line| !line.trim().is_empty())
+            .count();
+        let line = start + 1;
+        let closed = start + len < lines.len();
+        match read_record(&lines[start..start + len], alphabet, &encode) {
+            Ok(_) if !closed => problems.push(RecordProblem {
+                line,
+                error: RecordError::Unterminated,
+            }),
+            Ok((word_box, notes)) => records.push(SolutionRecord {
+                word_box,
+                notes,
+                line,
+            }),
+            Err(error) => problems.push(RecordProblem { line, error }),
+        }
+        start += len;
+    }
+    (records, problems)
+}
+
+/// The order merged solutions are written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeOrder {
+    /// The files in the order given, and the records in the order they are written
+    Input,
+    /// By the rows read one after another
+    Alphabetical,
+    /// Most different letters first (see `WordBox::distinct_letters`)
+    DistinctLetters,
+    /// Fewest rows first, then fewest columns, then alphabetically
+    Size,
+}
+
+impl MergeOrder {
+    pub const ALL: [MergeOrder; 4] = [
+        MergeOrder::Input,
+        MergeOrder::Alphabetical,
+        MergeOrder::DistinctLetters,
+        MergeOrder::Size,
+    ];
+}
+
+impl Display for MergeOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MergeOrder::Input => write!(f, "input"),
+            MergeOrder::Alphabetical => write!(f, "alphabetical"),
+            MergeOrder::DistinctLetters => write!(f, "distinct-letters"),
+            MergeOrder::Size => write!(f, "size"),
+        }
+    }
+}
+
+impl std::str::FromStr for MergeOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        MergeOrder::ALL
+            .into_iter()
+            .find(|order| order.to_string() == s)
+            .ok_or_else(|| {
+                let names: Vec<String> = MergeOrder::ALL.iter().map(|o| o.to_string()).collect();
+                format!(
+                    "unknown merge order {:?} (expected one of {})",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// The solutions of many files, each box kept once: the first time it is read, with the notes
+/// written under it then. A box and its transpose are one solution, as the search counts them,
+/// unless transposes are kept apart.
+#[derive(Debug, Default)]
+pub struct MergedSolutions {
+    solutions: Vec<SolutionRecord>,
+    seen: HashSet<Vec<String>>, // the rows of each box kept, in canonical form
+    keep_transposes: bool,
+    duplicates: usize,
+}
+
+impl MergedSolutions {
+    pub fn new(keep_transposes: bool) -> Self {
+        MergedSolutions {
+            keep_transposes,
+            ..Self::default()
+        }
+    }
+
+    /// Keep `record` unless its box, or the box's transpose, was kept already; whether it was
+    pub fn add(&mut self, record: SolutionRecord) -> bool {
+        let wb = &record.word_box;
+        let key = match self.keep_transposes {
+            true => wb.row_words(),
+            false => wb.canonical().row_words(),
+        };
+        if !self.seen.insert(key) {
+            self.duplicates += 1;
+            return false;
+        }
+        self.solutions.push(record);
+        true
+    }
+
+    /// The records left out as boxes kept already
+    pub fn duplicates(&self) -> usize {
+        self.duplicates
+    }
+
+    /// The solutions kept
+    pub fn len(&self) -> usize {
+        self.solutions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.solutions.is_empty()
+    }
+
+    /// The solutions kept, in `order`; ties keep the order they were read in
+    pub fn sorted(self, order: MergeOrder) -> Vec<SolutionRecord> {
+        let mut solutions = self.solutions;
+        let rows = |record: &SolutionRecord| record.word_box.row_words().concat();
+        match order {
+            MergeOrder::Input => {}
+            MergeOrder::Alphabetical => solutions.sort_by_cached_key(rows),
+            MergeOrder::DistinctLetters => solutions.sort_by_cached_key(|record| {
+                (
+                    std::cmp::Reverse(record.word_box.distinct_letters()),
+                    rows(record),
+                )
+            }),
+            MergeOrder::Size => solutions.sort_by_cached_key(|record| {
+                let wb = &record.word_box;
+                (wb.row_dim, wb.col_dim, rows(record))
+            }),
+        }
+        solutions
+    }
+}
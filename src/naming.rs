@@ -0,0 +1,204 @@
+use std::fmt::Display;
+
+/// What a name template can put in a solution's file name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameField {
+    Seed,   // the seed word the solution was found from
+    Index,  // its number among the seed's solutions, from 1
+    Global, // its number among every solution written, from 1
+    Rows,
+    Cols,
+    Ext, // the file extension of the output format
+}
+
+impl NameField {
+    pub const ALL: [NameField; 6] = [
+        NameField::Seed,
+        NameField::Index,
+        NameField::Global,
+        NameField::Rows,
+        NameField::Cols,
+        NameField::Ext,
+    ];
+}
+
+impl Display for NameField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NameField::Seed => write!(f, "seed"),
+            NameField::Index => write!(f, "index"),
+            NameField::Global => write!(f, "global"),
+            NameField::Rows => write!(f, "rows"),
+            NameField::Cols => write!(f, "cols"),
+            NameField::Ext => write!(f, "ext"),
+        }
+    }
+}
+
+/// A piece of a name template: text copied as it is, or a field padded to a width
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Text(String),
+    Field {
+        field: NameField,
+        width: usize,
+        zeros: bool,
+    },
+}
+
+/// The values of a solution's fields
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameValues<'a> {
+    pub seed: &'a str,
+    pub index: usize,
+    pub global: usize,
+    pub rows: usize,
+    pub cols: usize,
+    pub ext: &'a str,
+}
+
+/// A file name with fields in braces, e.g. `{seed}_{index:04}.{ext}`. A field may be padded to
+/// a width, with zeros if the width starts with 0 and with spaces otherwise; `{{` and `}}`
+/// stand for braces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameTemplate {
+    pieces: Vec<Piece>,
+}
+
+impl std::str::FromStr for NameTemplate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut pieces = vec![];
+        let mut text = String::new();
+        let mut chars = s.chars().peekable();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut spec = String::new();
+                    let mut closed = false;
+                    for ch in chars.by_ref() {
+                        if ch == '}' {
+                            closed = true;
+                            break;
+                        }
+                        spec.push(ch);
+                    }
+                    if !closed {
+                        return Err(format!("unclosed {{ in {:?}", s));
+                    }
+                    let (name, width) = spec.split_once(':').unwrap_or((&spec, ""));
+                    let field = (NameField::ALL.iter())
+                        .find(|field| field.to_string() == name)
+                        .copied()
+                        .ok_or_else(|| {
+                            let names: Vec<String> = NameField::ALL
+                                .iter()
+                                .map(|field| field.to_string())
+                                .collect();
+                            format!(
+                                "unknown field {{{}}} (expected one of {})",
+                                name,
+                                names.join(", ")
+                            )
+                        })?;
+                    let zeros = width.starts_with('0');
+                    let width = match width {
+                        "" => 0,
+                        _ => width
+                            .parse()
+                            .map_err(|_| format!("{:?} is not a width in {{{}}}", width, spec))?,
+                    };
+                    if !text.is_empty() {
+                        pieces.push(Piece::Text(std::mem::take(&mut text)));
+                    }
+                    pieces.push(Piece::Field {
+                        field,
+                        width,
+                        zeros,
+                    });
+                }
+                '}' => return Err(format!("unmatched }} in {:?}", s)),
+                _ => text.push(ch),
+            }
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+        Ok(NameTemplate { pieces })
+    }
+}
+
+impl Display for NameTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for piece in &self.pieces {
+            match piece {
+                Piece::Text(text) => write!(f, "{}", text.replace('{', "{{").replace('}', "}}"))?,
+                Piece::Field {
+                    field,
+                    width: 0,
+                    zeros: _,
+                } => write!(f, "{{{}}}", field)?,
+                Piece::Field {
+                    field,
+                    width,
+                    zeros,
+                } => write!(
+                    f,
+                    "{{{}:{}{}}}",
+                    field,
+                    if *zeros { "0" } else { "" },
+                    width
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl NameTemplate {
+    /// Whether the template puts `field` in the names it renders
+    pub fn uses(&self, field: NameField) -> bool {
+        (self.pieces.iter())
+            .any(|piece| matches!(piece, Piece::Field { field: used, .. } if *used == field))
+    }
+
+    /// The file name for a solution with these values
+    pub fn render(&self, values: &NameValues) -> String {
+        let mut name = String::new();
+        for piece in &self.pieces {
+            let (field, width, zeros) = match piece {
+                Piece::Text(text) => {
+                    name += text;
+                    continue;
+                }
+                Piece::Field {
+                    field,
+                    width,
+                    zeros,
+                } => (*field, *width, *zeros),
+            };
+            let value = match field {
+                NameField::Seed => values.seed.to_string(),
+                NameField::Index => values.index.to_string(),
+                NameField::Global => values.global.to_string(),
+                NameField::Rows => values.rows.to_string(),
+                NameField::Cols => values.cols.to_string(),
+                NameField::Ext => values.ext.to_string(),
+            };
+            name += &match zeros {
+                true => format!("{:0>width$}", value, width = width),
+                false => format!("{:>width$}", value, width = width),
+            };
+        }
+        name
+    }
+}
@@ -0,0 +1,146 @@
+use crate::memory::EstimatedSize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+const SHARDS: usize = 16;
+
+/// What a partial box still has to satisfy: the rows left to place and the prefixes of every
+/// column that is not complete yet. Two boxes with the same key have exactly the same completions,
+/// whatever the rows that produced those prefixes.
+pub type NogoodKey = (usize, Vec<String>);
+
+/// Two generations of keys; when the young one fills up it replaces the old one, so the
+/// entries that are still being hit survive eviction and the rest are dropped in bulk
+#[derive(Default)]
+struct Shard {
+    young: HashSet<NogoodKey>,
+    old: HashSet<NogoodKey>,
+    young_bytes: usize, // estimated bytes of the keys in each generation
+    old_bytes: usize,
+}
+
+/// Partial boxes proven to have no completion, shared by every worker of a run.
+///
+/// Sharing lets a dead end proven while solving one seed prune the search from every other seed,
+/// which is where most hits come from: seeds with the same tail leave the same open columns.
+/// The price is a lock per lookup; the table is split into shards so workers rarely contend.
+/// A cache per thread would avoid locking entirely but re-prove every dead end once per thread.
+pub struct NogoodCache {
+    shards: Vec<Mutex<Shard>>,
+    generation_capacity: usize, // keys per shard generation
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl std::fmt::Debug for NogoodCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NogoodCache")
+            .field("max_entries", &self.max_entries())
+            .field("len", &self.len())
+            .field("hits", &self.hits())
+            .field("misses", &self.misses())
+            .finish()
+    }
+}
+
+impl NogoodCache {
+    /// A cache holding about `max_entries` keys, split evenly over the shards and their generations
+    pub fn new(max_entries: usize) -> Self {
+        NogoodCache {
+            shards: (0..SHARDS).map(|_| Mutex::default()).collect(),
+            generation_capacity: (max_entries / SHARDS / 2).max(1),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    fn shard(&self, key: &NogoodKey) -> &Mutex<Shard> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % SHARDS]
+    }
+
+    /// Whether `key` is known to have no completion; a key found in the old generation is
+    /// moved back to the young one
+    pub fn contains(&self, key: &NogoodKey) -> bool {
+        let mut shard = self.shard(key).lock().unwrap();
+        let found = if shard.young.contains(key) {
+            true
+        } else if shard.old.remove(key) {
+            shard.old_bytes -= key.estimated_bytes();
+            Self::insert_into(&mut shard, key.clone(), self.generation_capacity);
+            true
+        } else {
+            false
+        };
+        let counter = if found { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        found
+    }
+
+    /// Record that `key` has no completion
+    pub fn insert(&self, key: NogoodKey) {
+        let mut shard = self.shard(&key).lock().unwrap();
+        Self::insert_into(&mut shard, key, self.generation_capacity);
+    }
+
+    fn insert_into(shard: &mut Shard, key: NogoodKey, capacity: usize) {
+        if shard.young.len() >= capacity {
+            shard.old = std::mem::take(&mut shard.young);
+            shard.old_bytes = std::mem::take(&mut shard.young_bytes);
+        }
+        let bytes = key.estimated_bytes();
+        if shard.young.insert(key) {
+            shard.young_bytes += bytes;
+        }
+    }
+
+    /// Forget every key, e.g. to give the memory back; hits and misses keep counting
+    pub fn clear(&self) {
+        for shard in &self.shards {
+            *shard.lock().unwrap() = Shard::default();
+        }
+    }
+
+    /// Rough number of bytes held by the keys and the tables they are in
+    pub fn estimated_bytes(&self) -> usize {
+        let table =
+            |keys: &HashSet<NogoodKey>| keys.capacity() * (std::mem::size_of::<NogoodKey>() + 1);
+        (self.shards.iter())
+            .map(|shard| {
+                let shard = shard.lock().unwrap();
+                shard.young_bytes + shard.old_bytes + table(&shard.young) + table(&shard.old)
+                    - (shard.young.len() + shard.old.len()) * std::mem::size_of::<NogoodKey>()
+            })
+            .sum()
+    }
+
+    pub fn max_entries(&self) -> usize {
+        self.generation_capacity * 2 * SHARDS
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| {
+                let shard = shard.lock().unwrap();
+                shard.young.len() + shard.old.len()
+            })
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> usize {
+        self.misses.load(Ordering::Relaxed)
+    }
+}
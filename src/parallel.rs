@@ -0,0 +1,235 @@
+// The parts of rayon the index and the runner use. With the `parallel` feature they are rayon's
+// own; without it they take the items one after another on the calling thread, in order, so
+// the code that uses them reads the same either way.
+
+#[cfg(feature = "parallel")]
+pub use rayon::{current_num_threads, join, ThreadPoolBuilder};
+
+#[cfg(feature = "parallel")]
+pub mod prelude {
+    pub use rayon::prelude::*;
+}
+
+#[cfg(not(feature = "parallel"))]
+pub use sequential::{current_num_threads, join, ThreadPoolBuilder};
+
+#[cfg(not(feature = "parallel"))]
+pub mod prelude {
+    pub use super::sequential::{
+        IntoParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelSlice,
+        ParallelSliceMut, Sequential,
+    };
+}
+
+#[cfg(not(feature = "parallel"))]
+mod sequential {
+    use std::convert::Infallible;
+
+    /// How many threads share the work: only the one calling
+    pub fn current_num_threads() -> usize {
+        1
+    }
+
+    /// Run `a` and then `b`
+    pub fn join<A, B, RA, RB>(a: A, b: B) -> (RA, RB)
+    where
+        A: FnOnce() -> RA,
+        B: FnOnce() -> RB,
+    {
+        (a(), b())
+    }
+
+    /// Takes a thread count for the sake of the callers, and builds a pool that has none
+    #[derive(Debug, Default)]
+    pub struct ThreadPoolBuilder;
+
+    impl ThreadPoolBuilder {
+        pub fn new() -> Self {
+            ThreadPoolBuilder
+        }
+
+        pub fn num_threads(self, _threads: usize) -> Self {
+            self
+        }
+
+        pub fn build(self) -> Result<ThreadPool, Infallible> {
+            Ok(ThreadPool)
+        }
+    }
+
+    #[derive(Debug)]
+    pub struct ThreadPool;
+
+    impl ThreadPool {
+        /// Run `op` on the calling thread
+        pub fn install<R>(&self, op: impl FnOnce() -> R) -> R {
+            op()
+        }
+    }
+
+    /// An iterator walked in order in place of a parallel one. It is not an `Iterator` itself,
+    /// so its `reduce` can take rayon's identity and operation without clashing with the
+    /// standard one.
+    #[derive(Debug)]
+    pub struct Sequential<I>(I);
+
+    impl<I: Iterator> Sequential<I> {
+        pub fn map<R, F: FnMut(I::Item) -> R>(self, f: F) -> Sequential<std::iter::Map<I, F>> {
+            Sequential(self.0.map(f))
+        }
+
+        pub fn enumerate(self) -> Sequential<std::iter::Enumerate<I>> {
+            Sequential(self.0.enumerate())
+        }
+
+        /// Map each item along with one `init` the items share, as each rayon job gets a clone
+        pub fn map_with<T, R, F>(self, mut init: T, mut f: F) -> Sequential<impl Iterator<Item = R>>
+        where
+            F: FnMut(&mut T, I::Item) -> R,
+        {
+            Sequential(self.0.map(move |item| f(&mut init, item)))
+        }
+
+        pub fn for_each<F: FnMut(I::Item)>(self, f: F) {
+            self.0.for_each(f)
+        }
+
+        pub fn for_each_with<T, F: FnMut(&mut T, I::Item)>(self, mut init: T, mut f: F) {
+            self.0.for_each(|item| f(&mut init, item))
+        }
+
+        pub fn reduce<ID, OP>(self, identity: ID, op: OP) -> I::Item
+        where
+            ID: Fn() -> I::Item,
+            OP: Fn(I::Item, I::Item) -> I::Item,
+        {
+            self.0.fold(identity(), op)
+        }
+
+        pub fn collect<C: FromIterator<I::Item>>(self) -> C {
+            self.0.collect()
+        }
+    }
+
+    pub trait IntoParallelIterator: IntoIterator + Sized {
+        fn into_par_iter(self) -> Sequential<Self::IntoIter> {
+            Sequential(self.into_iter())
+        }
+    }
+
+    impl<T: IntoIterator> IntoParallelIterator for T {}
+
+    pub trait IntoParallelRefIterator<'a> {
+        type Iter: Iterator;
+        fn par_iter(&'a self) -> Sequential<Self::Iter>;
+    }
+
+    impl<'a, T: 'a + ?Sized> IntoParallelRefIterator<'a> for T
+    where
+        &'a T: IntoIterator,
+    {
+        type Iter = <&'a T as IntoIterator>::IntoIter;
+        fn par_iter(&'a self) -> Sequential<Self::Iter> {
+            Sequential(self.into_iter())
+        }
+    }
+
+    pub trait IntoParallelRefMutIterator<'a> {
+        type Iter: Iterator;
+        fn par_iter_mut(&'a mut self) -> Sequential<Self::Iter>;
+    }
+
+    impl<'a, T: 'a + ?Sized> IntoParallelRefMutIterator<'a> for T
+    where
+        &'a mut T: IntoIterator,
+    {
+        type Iter = <&'a mut T as IntoIterator>::IntoIter;
+        fn par_iter_mut(&'a mut self) -> Sequential<Self::Iter> {
+            Sequential(self.into_iter())
+        }
+    }
+
+    pub trait ParallelSlice<T> {
+        fn par_chunks(&self, chunk_size: usize) -> Sequential<std::slice::Chunks<'_, T>>;
+    }
+
+    impl<T> ParallelSlice<T> for [T] {
+        fn par_chunks(&self, chunk_size: usize) -> Sequential<std::slice::Chunks<'_, T>> {
+            Sequential(self.chunks(chunk_size))
+        }
+    }
+
+    pub trait ParallelSliceMut<T> {
+        fn par_sort(&mut self)
+        where
+            T: Ord;
+    }
+
+    impl<T> ParallelSliceMut<T> for [T] {
+        fn par_sort(&mut self)
+        where
+            T: Ord,
+        {
+            self.sort()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::prelude::*;
+    use super::*;
+    use std::sync::mpsc;
+
+    /// The same work on pools of one thread and of several, with or without the `parallel`
+    /// feature, comes to the same results
+    #[test]
+    fn pools_of_any_size_give_the_same_results() {
+        let numbers: Vec<u64> = (1..=1000).collect();
+        let work = || {
+            let squares: Vec<u64> = numbers.par_iter().map(|n| n * n).collect();
+            let sums: Vec<u64> = (numbers.par_chunks(64))
+                .map(|chunk| chunk.iter().sum())
+                .collect();
+            let total = (numbers.clone().into_par_iter())
+                .map(|n| n % 7)
+                .reduce(|| 0, |a, b| a + b);
+            let (sender, receiver) = mpsc::channel();
+            (numbers.par_iter().enumerate())
+                .map_with(sender.clone(), |sender, (i, &n)| {
+                    sender.send((i, n)).unwrap()
+                })
+                .for_each(drop);
+            let mut doubled = numbers.clone();
+            (doubled.par_iter_mut()).for_each_with(sender, |_, n| *n *= 2);
+            let mut sent: Vec<(usize, u64)> = receiver.iter().collect();
+            sent.sort();
+            let mut reversed: Vec<u64> = numbers.iter().rev().copied().collect();
+            reversed.par_sort();
+            let halves = join(|| numbers[..500].len(), || numbers[500..].len());
+            (squares, sums, total, sent, doubled, reversed, halves)
+        };
+        let sequential = ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap()
+            .install(work);
+        for threads in [2, 8] {
+            let pool = ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            assert_eq!(pool.install(work), sequential);
+        }
+        let (squares, sums, total, sent, doubled, reversed, halves) = sequential;
+        assert_eq!(squares[9], 100);
+        assert_eq!(sums.len(), 16);
+        assert_eq!(sums.iter().sum::<u64>(), 500_500);
+        assert_eq!(total, numbers.iter().map(|n| n % 7).sum());
+        assert_eq!(sent.len(), 1000);
+        assert_eq!(doubled[999], 2000);
+        assert_eq!(reversed, numbers);
+        assert_eq!(halves, (500, 500));
+        assert!(current_num_threads() >= 1);
+    }
+}
@@ -0,0 +1,515 @@
+use crate::blocks::BLOCK;
+use crate::digraphs;
+use crate::puzzle::Puzzle;
+use crate::wordbox::WordBox;
+
+/// Width and height of an A4 page, in points
+const PAGE: (f64, f64) = (595.28, 841.89);
+/// Size of the captions over the grids and of the section headings, in points
+const CAPTION_SIZE: f64 = 9.0;
+const HEADING_SIZE: f64 = 14.0;
+/// Size of the word bank under a puzzle, in points; Courier's glyphs are all 0.6 em wide
+const BANK_SIZE: f64 = 8.0;
+const COURIER_WIDTH: f64 = 0.6;
+
+/// Where the grids go on a page: the side of a cell and the blank border around the page, both
+/// in points. A grid too big for the page at this cell size is shrunk to fit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PdfLayout {
+    pub cell_size: f64,
+    pub margin: f64,
+}
+
+impl Default for PdfLayout {
+    fn default() -> Self {
+        PdfLayout {
+            cell_size: 24.0,
+            margin: 36.0,
+        }
+    }
+}
+
+/// What a cell of a printed grid shows
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Cell {
+    Block,
+    Letter(String),
+    Blank(Option<usize>), // with the clue number of a word starting there, if one does
+}
+
+/// One grid to print: its caption, its cells row by row, and the lines of a word bank under it
+#[derive(Debug, Clone, PartialEq)]
+struct Grid {
+    caption: String,
+    cells: Vec<Vec<Cell>>,
+    bank: Vec<String>,
+}
+
+impl Grid {
+    fn answer(caption: String, wb: &WordBox) -> Self {
+        let cells = (wb.rows.iter())
+            .map(|row| {
+                (row.chars())
+                    .map(|ch| match ch {
+                        BLOCK => Cell::Block,
+                        ch => Cell::Letter(digraphs::cell_text(ch)),
+                    })
+                    .collect()
+            })
+            .collect();
+        Grid {
+            caption,
+            cells,
+            bank: vec![],
+        }
+    }
+
+    fn puzzle(caption: String, puzzle: &Puzzle) -> Self {
+        let cells = (puzzle.answer.rows.iter().enumerate())
+            .map(|(i, row)| {
+                (row.chars().enumerate())
+                    .map(|(j, ch)| match (ch, puzzle.is_revealed(i, j)) {
+                        (BLOCK, _) => Cell::Block,
+                        (ch, true) => Cell::Letter(digraphs::cell_text(ch)),
+                        (_, false) => Cell::Blank(puzzle.number(i, j)),
+                    })
+                    .collect()
+            })
+            .collect();
+        let bank = match puzzle.bank.is_empty() {
+            true => vec!["word bank: (every word is showing)".to_string()],
+            false => vec![format!("word bank: {}", puzzle.bank.join(", "))],
+        };
+        Grid {
+            caption,
+            cells,
+            bank,
+        }
+    }
+
+    fn rows(&self) -> usize {
+        self.cells.len()
+    }
+
+    fn cols(&self) -> usize {
+        self.cells.first().map_or(0, Vec::len)
+    }
+}
+
+/// A run of grids starting on a page of its own, under a heading
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Section {
+    heading: String,
+    grids: Vec<Grid>,
+}
+
+/// Solutions, or puzzles and their answer key, laid out for printing as a PDF.
+///
+/// Grids are placed left to right and top to bottom, as many to a page as fit, and a new page is
+/// started when the next one does not. Puzzles go in a section of their own, their answers in an
+/// answer key starting on a fresh page and numbered the same way. Text is set in Helvetica and
+/// Courier, two of the fonts every PDF reader has built in, so nothing needs embedding and the
+/// letters look the same wherever the file is opened.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdfBook {
+    layout: PdfLayout,
+    boxes: Section,
+    puzzles: Section,
+    answers: Section,
+}
+
+impl PdfBook {
+    pub fn new(layout: PdfLayout) -> Self {
+        PdfBook {
+            layout,
+            boxes: Section {
+                heading: "Word boxes".to_string(),
+                grids: vec![],
+            },
+            puzzles: Section {
+                heading: "Puzzles".to_string(),
+                grids: vec![],
+            },
+            answers: Section {
+                heading: "Answer key".to_string(),
+                grids: vec![],
+            },
+        }
+    }
+
+    /// Add a solution under `heading`, if it has one; with its puzzle, the puzzle goes in the
+    /// puzzles and the solution in the answer key
+    pub fn add(&mut self, heading: Option<&str>, wb: &WordBox, puzzle: Option<&Puzzle>) {
+        let section = match puzzle {
+            Some(_) => &self.puzzles,
+            None => &self.boxes,
+        };
+        let number = section.grids.len() + 1;
+        let caption = match heading {
+            Some(heading) => format!("{}. {}", number, heading),
+            None => format!("{}.", number),
+        };
+        match puzzle {
+            Some(puzzle) => {
+                let puzzle = Grid::puzzle(caption.clone(), puzzle);
+                self.puzzles.grids.push(puzzle);
+                self.answers.grids.push(Grid::answer(caption, wb));
+            }
+            None => self.boxes.grids.push(Grid::answer(caption, wb)),
+        }
+    }
+
+    /// How many solutions were added
+    pub fn len(&self) -> usize {
+        self.boxes.grids.len() + self.puzzles.grids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The content stream of every page, in order
+    fn pages(&self) -> Vec<String> {
+        let mut pages = vec![];
+        for section in [&self.boxes, &self.puzzles, &self.answers] {
+            if !section.grids.is_empty() {
+                pages.extend(self.section_pages(section));
+            }
+        }
+        if pages.is_empty() {
+            pages.push(String::new());
+        }
+        pages
+    }
+
+    /// How many pages the PDF has
+    pub fn page_count(&self) -> usize {
+        self.pages().len()
+    }
+
+    /// The pages of one section: the heading at the top of the first, then the grids
+    fn section_pages(&self, section: &Section) -> Vec<String> {
+        let (width, height) = PAGE;
+        let margin = self.layout.margin;
+        let (left, right, top, bottom) = (margin, width - margin, height - margin, margin);
+        let gap = self.layout.cell_size.max(12.0);
+        let mut pages = vec![];
+        let mut page = text(
+            left,
+            top - HEADING_SIZE,
+            "F1",
+            HEADING_SIZE,
+            &section.heading,
+        );
+        let mut y = top - 2.0 * HEADING_SIZE; // the top of the current line of grids
+        let mut x = left;
+        let mut line_height: f64 = 0.0;
+        for grid in &section.grids {
+            let cell = self.cell_size(grid);
+            let grid_width = cell * grid.cols() as f64;
+            let bank = wrap(&grid.bank, grid_width.max(120.0), BANK_SIZE);
+            let item_width = grid_width.max(if bank.is_empty() { 0.0 } else { 120.0 });
+            let item_height = 1.5 * CAPTION_SIZE
+                + cell * grid.rows() as f64
+                + 1.4 * BANK_SIZE * (bank.len() as f64 + 0.5);
+            if x > left && x + item_width > right {
+                x = left;
+                y -= line_height + gap;
+                line_height = 0.0;
+            }
+            if y - item_height < bottom && y < top {
+                pages.push(std::mem::take(&mut page));
+                x = left;
+                y = top;
+                line_height = 0.0;
+            }
+            page += &draw(grid, &bank, x, y, cell);
+            x += item_width + gap;
+            line_height = line_height.max(item_height);
+        }
+        pages.push(page);
+        pages
+    }
+
+    /// The side of a cell of `grid`: the layout's, or less if the grid would not fit on a page
+    fn cell_size(&self, grid: &Grid) -> f64 {
+        let (width, height) = PAGE;
+        let margin = self.layout.margin;
+        let room_across = (width - 2.0 * margin) / grid.cols().max(1) as f64;
+        let room_down = (height - 2.0 * margin - 2.0 * HEADING_SIZE - 4.0 * CAPTION_SIZE)
+            / grid.rows().max(1) as f64;
+        self.layout
+            .cell_size
+            .min(room_across)
+            .min(room_down)
+            .max(1.0)
+    }
+
+    /// The PDF file
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let pages = self.pages();
+        // Objects 1 to 5 are the catalog, the page tree and the fonts; each page then takes two,
+        // the page and its content stream
+        let kids: Vec<String> = (0..pages.len())
+            .map(|i| format!("{} 0 R", 6 + 2 * i))
+            .collect();
+        let mut objects: Vec<Vec<u8>> = vec![
+            b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                kids.join(" "),
+                pages.len()
+            )
+            .into_bytes(),
+            font("Helvetica"),
+            font("Courier-Bold"),
+            font("Courier"),
+        ];
+        for (i, content) in pages.iter().enumerate() {
+            objects.push(
+                format!(
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] \
+                     /Resources << /Font << /F1 3 0 R /F2 4 0 R /F3 5 0 R >> >> /Contents {} 0 R >>",
+                    PAGE.0,
+                    PAGE.1,
+                    7 + 2 * i
+                )
+                .into_bytes(),
+            );
+            let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+            stream.extend(content.as_bytes());
+            stream.extend(b"\nendstream");
+            objects.push(stream);
+        }
+
+        let mut pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = vec![];
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend(format!("{} 0 obj\n", i + 1).as_bytes());
+            pdf.extend(object);
+            pdf.extend(b"\nendobj\n");
+        }
+        let xref = pdf.len();
+        pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+        for offset in offsets {
+            pdf.extend(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        pdf.extend(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+                objects.len() + 1,
+                xref
+            )
+            .as_bytes(),
+        );
+        pdf
+    }
+}
+
+/// A standard font, in the Latin-1 superset PDF readers call WinAnsiEncoding
+fn font(name: &str) -> Vec<u8> {
+    format!(
+        "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+        name
+    )
+    .into_bytes()
+}
+
+/// `text` as a PDF string: parentheses and backslashes escaped, and letters outside Latin-1,
+/// which the standard fonts do not have, shown as `?`
+fn pdf_string(text: &str) -> String {
+    let mut escaped = String::from("(");
+    for ch in text.chars() {
+        match ch {
+            '(' | ')' | '\\' => {
+                escaped.push('\\');
+                escaped.push(ch);
+            }
+            ' '..='~' => escaped.push(ch),
+            '\u{a0}'..='\u{ff}' => escaped += &format!("\\{:03o}", ch as u32),
+            _ => escaped.push('?'),
+        }
+    }
+    escaped.push(')');
+    escaped
+}
+
+/// Set `text` with its baseline starting at (`x`, `y`)
+fn text(x: f64, y: f64, font: &str, size: f64, text: &str) -> String {
+    format!(
+        "BT /{} {:.2} Tf {:.2} {:.2} Td {} Tj ET\n",
+        font,
+        size,
+        x,
+        y,
+        pdf_string(text)
+    )
+}
+
+/// `lines` broken at spaces into lines of Courier at `size` no wider than `width`
+fn wrap(lines: &[String], width: f64, size: f64) -> Vec<String> {
+    let chars = ((width / (COURIER_WIDTH * size)) as usize).max(1);
+    let mut wrapped = vec![];
+    for line in lines {
+        let mut current = String::new();
+        for word in line.split(' ') {
+            let len = current.chars().count();
+            if len > 0 && len + 1 + word.chars().count() > chars {
+                wrapped.push(std::mem::take(&mut current));
+            }
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current += word;
+        }
+        wrapped.push(current);
+    }
+    wrapped
+}
+
+/// Draw `grid` with its caption over it and `bank` under it, its top left corner at (`x`, `y`)
+fn draw(grid: &Grid, bank: &[String], x: f64, y: f64, cell: f64) -> String {
+    let mut out = text(x, y - CAPTION_SIZE, "F1", CAPTION_SIZE, &grid.caption);
+    let top = y - 1.5 * CAPTION_SIZE;
+    out += &format!("{:.2} w\n", (cell / 32.0).clamp(0.25, 1.0));
+    for (i, row) in grid.cells.iter().enumerate() {
+        for (j, content) in row.iter().enumerate() {
+            let (left, bottom) = (x + cell * j as f64, top - cell * (i + 1) as f64);
+            let square = format!("{:.2} {:.2} {:.2} {:.2} re", left, bottom, cell, cell);
+            match content {
+                Cell::Block => out += &format!("{} f\n", square),
+                Cell::Letter(letters) => {
+                    out += &format!("{} S\n", square);
+                    let letters = letters.to_uppercase();
+                    let count = letters.chars().count().max(1) as f64;
+                    // Courier's glyphs are all as wide, so a tile of several letters is
+                    // centred by its length alone
+                    let size = (0.6 * cell).min(0.9 * cell / (COURIER_WIDTH * count));
+                    let across = left + (cell - COURIER_WIDTH * size * count) / 2.0;
+                    let up = bottom + (cell - 0.6 * size) / 2.0;
+                    out += &text(across, up, "F2", size, &letters);
+                }
+                Cell::Blank(number) => {
+                    out += &format!("{} S\n", square);
+                    if let Some(number) = number {
+                        let size = 0.3 * cell;
+                        let (across, up) = (left + 0.08 * cell, bottom + cell - 1.05 * size);
+                        out += &text(across, up, "F1", size, &number.to_string());
+                    }
+                }
+            }
+        }
+    }
+    let mut line = top - cell * grid.rows() as f64 - 1.4 * BANK_SIZE;
+    for bank_line in bank {
+        out += &text(x, line, "F3", BANK_SIZE, bank_line);
+        line -= 1.4 * BANK_SIZE;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filled(rows: &[&str]) -> WordBox {
+        (rows.iter()).fold(WordBox::new(rows.len(), rows[0].len(), true), |wb, row| {
+            wb.add_word(row.to_string()).unwrap()
+        })
+    }
+
+    fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        haystack
+            .windows(needle.len())
+            .position(|window| window == needle)
+    }
+
+    /// Read `pdf` as a PDF reader would start to, through its trailer and cross-reference
+    /// table, failing the test where it does not hold together, and return its page count
+    fn parsed_pages(pdf: &[u8]) -> usize {
+        assert!(pdf.starts_with(b"%PDF-1.4\n"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+        // Byte for byte, so that offsets into it are offsets into the file
+        let text: String = (pdf.iter())
+            .map(|&byte| if byte.is_ascii() { byte as char } else { '?' })
+            .collect();
+        let tail = &text[text.rfind("startxref\n").unwrap()..];
+        let xref: usize = tail.lines().nth(1).unwrap().parse().unwrap();
+        assert!(text[xref..].starts_with("xref\n0 "));
+        let mut lines = text[xref..].lines().skip(1);
+        let size: usize = lines.next().unwrap()[2..].parse().unwrap();
+        let offsets: Vec<usize> = (lines.skip(1).take(size - 1))
+            .map(|line| line[..10].parse().unwrap())
+            .collect();
+        let mut pages = 0;
+        for (i, &offset) in offsets.iter().enumerate() {
+            let object = &pdf[offset..];
+            assert!(object.starts_with(format!("{} 0 obj\n", i + 1).as_bytes()));
+            let object = &object[..find(object, b"\nendobj\n").unwrap()];
+            if find(object, b"/Type /Page ").is_some() {
+                pages += 1;
+            }
+            if let Some(start) = find(object, b"stream\n") {
+                let header = String::from_utf8_lossy(&object[..start]);
+                let length: usize = (header.split("/Length ").nth(1).unwrap())
+                    .split(' ')
+                    .next()
+                    .unwrap()
+                    .parse()
+                    .unwrap();
+                assert_eq!(&object[start + 7 + length..], b"\nendstream");
+            }
+        }
+        let count = format!("/Count {} ", pages);
+        assert!(text.contains(&count), "{}", count);
+        pages
+    }
+
+    #[test]
+    fn grids_share_a_page_while_they_fit() {
+        let mut book = PdfBook::new(PdfLayout::default());
+        assert_eq!(parsed_pages(&book.to_bytes()), 1);
+        let wb = filled(&["cat", "ace", "tea"]);
+        for _ in 0..3 {
+            book.add(None, &wb, None);
+        }
+        assert_eq!(book.len(), 3);
+        assert_eq!((book.page_count(), parsed_pages(&book.to_bytes())), (1, 1));
+        for _ in 0..200 {
+            book.add(Some("cat (3x3)"), &wb, None);
+        }
+        let pages = parsed_pages(&book.to_bytes());
+        assert!(pages > 1);
+        assert_eq!(book.page_count(), pages);
+    }
+
+    #[test]
+    fn grids_too_big_to_share_a_page_take_one_each() {
+        let layout = PdfLayout {
+            cell_size: 150.0,
+            margin: 36.0,
+        };
+        let mut book = PdfBook::new(layout);
+        let wb = filled(&["cat", "ace", "tea"]);
+        for _ in 0..3 {
+            book.add(None, &wb, None);
+        }
+        assert_eq!(parsed_pages(&book.to_bytes()), 3);
+    }
+
+    #[test]
+    fn the_answer_key_starts_a_page_of_its_own() {
+        let mut book = PdfBook::new(PdfLayout::default());
+        let wb = filled(&["cat", "ace", "tea"]);
+        let puzzle = Puzzle::new(&wb, "0.3".parse().unwrap(), 7);
+        book.add(Some("(a) \\ test"), &wb, Some(&puzzle));
+        book.add(None, &wb, Some(&puzzle));
+        assert_eq!(book.len(), 2);
+        let pdf = book.to_bytes();
+        assert_eq!(parsed_pages(&pdf), 2);
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.contains("(Puzzles) Tj") && text.contains("(Answer key) Tj"));
+        assert!(text.contains("(1. \\(a\\) \\\\ test) Tj"));
+    }
+}
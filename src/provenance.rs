@@ -0,0 +1,356 @@
+use crate::daily::{fingerprint, fnv1a};
+use crate::digraphs;
+use crate::lexicon::{LexiconKind, Lexicons};
+use crate::memory::EstimatedSize;
+use crate::wordbox::WordBox;
+use std::fmt::Display;
+use std::sync::Arc;
+
+/// The first word of every record, naming the format and its version
+pub const TAG: &str = "wordbox-provenance/1";
+
+/// A word list as a record names it: how many words it has and their fingerprint (see
+/// `daily::fingerprint`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WordListId {
+    pub words: usize,
+    pub fingerprint: u64,
+}
+
+impl WordListId {
+    pub fn of(words: &[String]) -> Self {
+        WordListId {
+            words: words.len(),
+            fingerprint: fingerprint(words),
+        }
+    }
+}
+
+impl Display for WordListId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} words, fingerprint {:016x}",
+            self.words, self.fingerprint
+        )
+    }
+}
+
+/// What the solutions of a run have in common: the words it searched and the options it read
+/// and narrowed them with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunProvenance {
+    pub word_list: WordListId, // the word list as read, before any constraint narrows it
+    pub lexicon: LexiconKind,
+    pub normalization: Vec<String>, // the options given that change how word lists are read
+    pub constraints: Vec<String>,   // the options given that narrow the words or the boxes
+    pub random_seed: Option<u64>,
+}
+
+impl RunProvenance {
+    /// The record of `word_box`, found from `seed` by a run searching `lexicons`
+    pub fn record<L: Lexicons>(
+        self: &Arc<Self>,
+        word_box: &WordBox,
+        seed: &str,
+        lexicons: &L,
+    ) -> Provenance {
+        Provenance {
+            run: self.clone(),
+            row_dim: word_box.row_dim,
+            col_dim: word_box.col_dim,
+            symmetric: word_box.is_symmetric,
+            seed: digraphs::decode(seed),
+            path: word_box.choice_path(lexicons),
+            check: check(word_box),
+        }
+    }
+}
+
+/// Where a solution came from, enough to build it again without a search: the run that found
+/// it, the seed it was found from, and the place of each of its rows among the rows the
+/// lexicons allowed at that point (see `WordBox::choice_path`). Written on one line as
+/// `key=value` pairs after a tag naming the format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Provenance {
+    pub run: Arc<RunProvenance>,
+    pub row_dim: usize,
+    pub col_dim: usize,
+    pub symmetric: bool,
+    pub seed: String,             // as written, with its tiles spelled out
+    pub path: Option<Vec<usize>>, // none for a box whose rows the lexicons alone do not lead to
+    pub check: u64,               // FNV-1a of the rows, to tell a replay that went astray
+}
+
+impl Provenance {
+    /// Build the box again from its path through `lexicons`, a row at a time, checking that
+    /// `word_list` is the one the run read, that each row is among the choices the lexicons
+    /// allow, that the first is the seed, and that the box comes out as the run found it
+    pub fn replay<L: Lexicons>(
+        &self,
+        word_list: WordListId,
+        lexicons: &L,
+    ) -> Result<WordBox, ReplayError> {
+        if word_list != self.run.word_list {
+            return Err(ReplayError::WordListChanged {
+                recorded: self.run.word_list,
+                current: word_list,
+            });
+        }
+        let path = self.path.as_ref().ok_or(ReplayError::NoPath)?;
+        let mut wb = WordBox::try_new(self.row_dim, self.col_dim, self.symmetric)
+            .map_err(|err| ReplayError::Invalid(err.to_string()))?;
+        for (step, &index) in path.iter().enumerate() {
+            let choices = wb.row_choices(lexicons).unwrap_or_default();
+            let Some(row) = choices.get(index) else {
+                return Err(ReplayError::NoSuchChoice {
+                    step,
+                    index,
+                    choices: choices.len(),
+                });
+            };
+            if step == 0 && digraphs::decode(row) != self.seed {
+                return Err(ReplayError::NotTheSeed {
+                    seed: self.seed.clone(),
+                    row: digraphs::decode(row),
+                });
+            }
+            wb = (wb.add_word(row.to_string()))
+                .map_err(|err| ReplayError::Invalid(err.to_string()))?;
+        }
+        if !wb.is_done() || check(&wb) != self.check {
+            return Err(ReplayError::Diverged);
+        }
+        Ok(wb)
+    }
+}
+
+/// The rows of a box, fingerprinted
+fn check(wb: &WordBox) -> u64 {
+    fnv1a(wb.row_words().join("\n").bytes())
+}
+
+impl EstimatedSize for Provenance {
+    fn estimated_bytes(&self) -> usize {
+        std::mem::size_of::<Provenance>()
+            + self.seed.capacity()
+            + (self.path.as_ref()).map_or(0, |path| path.capacity() * std::mem::size_of::<usize>())
+    }
+}
+
+impl Display for Provenance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let run = &self.run;
+        let rng = match run.random_seed {
+            Some(seed) => seed.to_string(),
+            None => "-".to_string(),
+        };
+        let path = match &self.path {
+            Some(path) => list(path.iter().map(usize::to_string)),
+            None => "-".to_string(),
+        };
+        write!(
+            f,
+            "{} words={}:{:016x} lexicon={} size={}x{} symmetric={} normalize={} constraints={} rng={} seed={} path={} check={:016x}",
+            TAG,
+            run.word_list.words,
+            run.word_list.fingerprint,
+            run.lexicon,
+            self.row_dim,
+            self.col_dim,
+            self.symmetric,
+            list(run.normalization.iter().map(|option| escape(option))),
+            list(run.constraints.iter().map(|option| escape(option))),
+            rng,
+            escape(&self.seed),
+            path,
+            self.check
+        )
+    }
+}
+
+impl std::str::FromStr for Provenance {
+    type Err = String;
+
+    /// A record as `Display` writes it; anything before the tag, like the `provenance: ` a
+    /// solution file writes in front of it, is skipped
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let start = s.find(TAG).ok_or_else(|| format!("no {} record", TAG))?;
+        let mut fields = s[start + TAG.len()..].split_whitespace();
+        let mut field = |key: &str| {
+            let (name, value) = (fields.next())
+                .and_then(|field| field.split_once('='))
+                .ok_or_else(|| format!("the record ends before its {} field", key))?;
+            match name == key {
+                true => Ok(value),
+                false => Err(format!("expected the {} field, found {:?}", key, name)),
+            }
+        };
+        let number = |key: &str, value: &str| {
+            (value.parse::<usize>()).map_err(|_| format!("invalid {} {:?}", key, value))
+        };
+        let hex = |key: &str, value: &str| {
+            u64::from_str_radix(value, 16).map_err(|_| format!("invalid {} {:?}", key, value))
+        };
+
+        let words = field("words")?;
+        let (count, fingerprint) = (words.split_once(':'))
+            .ok_or_else(|| format!("invalid words {:?} (expected COUNT:FINGERPRINT)", words))?;
+        let word_list = WordListId {
+            words: number("word count", count)?,
+            fingerprint: hex("fingerprint", fingerprint)?,
+        };
+        let lexicon = field("lexicon")?.parse()?;
+        let size = field("size")?;
+        let (rows, cols) = (size.split_once('x'))
+            .ok_or_else(|| format!("invalid size {:?} (expected ROWSxCOLS)", size))?;
+        let (row_dim, col_dim) = (number("size", rows)?, number("size", cols)?);
+        let symmetric = field("symmetric")?;
+        let symmetric = (symmetric.parse::<bool>())
+            .map_err(|_| format!("invalid symmetric {:?} (expected true or false)", symmetric))?;
+        let normalization = unlist(field("normalize")?)?;
+        let constraints = unlist(field("constraints")?)?;
+        let random_seed = match field("rng")? {
+            "-" => None,
+            rng => Some((rng.parse::<u64>()).map_err(|_| format!("invalid rng {:?}", rng))?),
+        };
+        let seed = unescape(field("seed")?)?;
+        let path = match field("path")? {
+            "-" => None,
+            path => Some(
+                (path.split(','))
+                    .map(|index| number("path", index))
+                    .collect::<Result<Vec<usize>, String>>()?,
+            ),
+        };
+        let check = hex("check", field("check")?)?;
+        Ok(Provenance {
+            run: Arc::new(RunProvenance {
+                word_list,
+                lexicon,
+                normalization,
+                constraints,
+                random_seed,
+            }),
+            row_dim,
+            col_dim,
+            symmetric,
+            seed,
+            path,
+            check,
+        })
+    }
+}
+
+/// Why a record could not be replayed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The word list read now is not the one the run read
+    WordListChanged {
+        recorded: WordListId,
+        current: WordListId,
+    },
+    /// The record has no path, as for a box with blocks or a near miss
+    NoPath,
+    /// A step of the path picks a row past the end of the choices the lexicons allow
+    NoSuchChoice {
+        step: usize,
+        index: usize,
+        choices: usize,
+    },
+    /// The path starts with a row other than the seed the record names
+    NotTheSeed { seed: String, row: String },
+    /// The path led to a different box than the run found
+    Diverged,
+    /// The path does not make a box of the size the record gives
+    Invalid(String),
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayError::WordListChanged { recorded, current } => write!(
+                f,
+                "the word list has changed: the run read {}, but this one has {}",
+                recorded, current
+            ),
+            ReplayError::NoPath => write!(
+                f,
+                "the record has no path to replay; boxes with blocks and near misses have none"
+            ),
+            ReplayError::NoSuchChoice {
+                step,
+                index,
+                choices,
+            } => write!(
+                f,
+                "row {} is choice {} of the rows the lexicon allows there, which number {}",
+                step + 1,
+                index,
+                choices
+            ),
+            ReplayError::NotTheSeed { seed, row } => write!(
+                f,
+                "the path starts with {:?}, not the seed {:?}",
+                row, seed
+            ),
+            ReplayError::Diverged => write!(
+                f,
+                "the path leads to a different box than the run found; the options that narrow the words must match the run's"
+            ),
+            ReplayError::Invalid(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+/// Items joined by commas, or `-` for none
+fn list(items: impl Iterator<Item = String>) -> String {
+    let items: Vec<String> = items.collect();
+    match items.is_empty() {
+        true => "-".to_string(),
+        false => items.join(","),
+    }
+}
+
+fn unlist(value: &str) -> Result<Vec<String>, String> {
+    match value {
+        "-" => Ok(vec![]),
+        _ => value.split(',').map(unescape).collect(),
+    }
+}
+
+/// `text` with the characters that would split a field or a list, and `%` itself, written as
+/// `%` and the hex of their bytes
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if ch.is_whitespace() || ch == ',' || ch == '%' {
+            let mut bytes = [0; 4];
+            for byte in ch.encode_utf8(&mut bytes).bytes() {
+                escaped += &format!("%{:02X}", byte);
+            }
+        } else {
+            escaped.push(ch);
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> Result<String, String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&byte, after)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = (after.get(..2))
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| format!("invalid escape in {:?}", text))?;
+            bytes.push(hex);
+            rest = &after[2..];
+        } else {
+            bytes.push(byte);
+            rest = after;
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| format!("invalid escape in {:?}", text))
+}
@@ -0,0 +1,488 @@
+use crate::blocks::BLOCK;
+use crate::definitions::Definitions;
+use crate::digraphs;
+use crate::lexicon::{Lexicon, Lexicons};
+use crate::solver::{solve_word_box_all, Outcome, SolverConfig};
+use crate::template::{Line, Template};
+use crate::wordbox::WordBox;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::Display;
+use std::hash::{Hash, Hasher};
+use std::ops::ControlFlow;
+
+/// The share of a puzzle's letters left showing, from 0 (none) to 1 (all)
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct Reveal(f64);
+
+impl Reveal {
+    pub fn fraction(self) -> f64 {
+        self.0
+    }
+}
+
+impl std::str::FromStr for Reveal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse()
+            .ok()
+            .filter(|fraction: &f64| (0.0..=1.0).contains(fraction))
+            .map(Reveal)
+            .ok_or_else(|| format!("expected a fraction from 0 to 1, found {:?}", s))
+    }
+}
+
+impl Display for Reveal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A puzzle made from a solved box: some of its letters showing, the other cells blank, the
+/// cells that start a word numbered as in a crossword, and the hidden words in a word bank.
+///
+/// Unless every letter is revealed, each word keeps at least one blank cell, so no word is given
+/// away outright; a high reveal may therefore show fewer letters than asked for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Puzzle {
+    pub answer: WordBox,
+    revealed: Vec<bool>,                // row by row
+    numbers: Vec<Option<usize>>,        // clue number of each cell that starts a word
+    pub bank: Vec<String>,              // the words with a blank cell, tiles spelled out
+    pub wanted: usize,                  // letters the reveal asked to show
+    pub entries: Vec<Entry>,            // across, then down, each by number
+    clues: Option<Vec<Option<String>>>, // each entry's definition, once clued
+    pub uniqueness: Option<Uniqueness>, // once checked by `make_unique`
+    pub extra_reveals: usize,           // letters `make_unique` showed on top of the reveal
+}
+
+/// Nodes the uniqueness check of a puzzle expands looking for another box before giving up
+pub const UNIQUENESS_BUDGET: usize = 200_000;
+
+/// Whether the letters a puzzle shows lead to its answer alone
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Uniqueness {
+    /// No other box fits them
+    Unique,
+    /// This box fits them too
+    Ambiguous(WordBox),
+    /// The check ran out of nodes before finding another box or ruling every other one out
+    Unknown,
+}
+
+/// What a clue-less entry shows in the list of clues
+pub const NO_CLUE: &str = "(no clue)";
+
+/// A word of a puzzle to clue: the number of its first cell, which way it reads, and its cells
+/// as the box holds them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub number: usize,
+    pub line: Line,
+    pub word: String,
+}
+
+/// The cells of every word of a `row_dim`×`col_dim` grid whose cells are `letters` row by row,
+/// rows first: each run of letters along a row or column between blocks
+fn slots(letters: &[char], row_dim: usize, col_dim: usize) -> Vec<(Line, Vec<usize>)> {
+    let row = |i| {
+        (0..col_dim)
+            .map(move |j| i * col_dim + j)
+            .collect::<Vec<usize>>()
+    };
+    let col = |j| {
+        (0..row_dim)
+            .map(move |i| i * col_dim + j)
+            .collect::<Vec<usize>>()
+    };
+    let rows = (0..row_dim).map(|i| (Line::Row, row(i)));
+    let cols = (0..col_dim).map(|j| (Line::Col, col(j)));
+    let mut slots = vec![];
+    for (line, cells) in rows.chain(cols) {
+        for run in cells.split(|&cell| letters[cell] == BLOCK) {
+            if !run.is_empty() {
+                slots.push((line, run.to_vec()));
+            }
+        }
+    }
+    slots
+}
+
+impl Puzzle {
+    /// A puzzle from `wb` showing about `reveal` of its letters. Which ones depends on `seed` and
+    /// the box, so the same seed gives each box its own puzzle, and the same one on every run.
+    pub fn new(wb: &WordBox, reveal: Reveal, seed: u64) -> Self {
+        let (row_dim, col_dim) = (wb.row_dim, wb.col_dim);
+        let letters: Vec<char> = wb.rows.iter().flat_map(|row| row.chars()).collect();
+        let slots: Vec<Vec<usize>> = (slots(&letters, row_dim, col_dim).into_iter())
+            .map(|(_, cells)| cells)
+            .collect();
+        let open: Vec<usize> = (0..letters.len())
+            .filter(|&cell| letters[cell] != BLOCK)
+            .collect();
+        let wanted = (reveal.fraction() * open.len() as f64).round() as usize;
+
+        let mut revealed = vec![false; letters.len()];
+        if wanted == open.len() {
+            revealed = letters.iter().map(|&ch| ch != BLOCK).collect();
+        } else {
+            let mut hidden: Vec<usize> = slots.iter().map(Vec::len).collect();
+            let mut order = open.clone();
+            let mut hasher = DefaultHasher::new();
+            wb.rows.hash(&mut hasher);
+            order.shuffle(&mut StdRng::seed_from_u64(seed ^ hasher.finish()));
+            let mut shown = 0;
+            for cell in order {
+                if shown == wanted {
+                    break;
+                }
+                let crossing: Vec<usize> = (0..slots.len())
+                    .filter(|&slot| slots[slot].contains(&cell))
+                    .collect();
+                // Showing the last blank cell of a word would give the word away
+                if crossing.iter().all(|&slot| hidden[slot] > 1) {
+                    for slot in crossing {
+                        hidden[slot] -= 1;
+                    }
+                    revealed[cell] = true;
+                    shown += 1;
+                }
+            }
+        }
+
+        Puzzle::with_revealed(wb, revealed, wanted)
+    }
+
+    /// The puzzle of `wb` showing the letters of the cells `revealed`, row by row
+    fn with_revealed(wb: &WordBox, revealed: Vec<bool>, wanted: usize) -> Self {
+        let (row_dim, col_dim) = (wb.row_dim, wb.col_dim);
+        let letters: Vec<char> = wb.rows.iter().flat_map(|row| row.chars()).collect();
+        let (lines, slots): (Vec<Line>, Vec<Vec<usize>>) =
+            slots(&letters, row_dim, col_dim).into_iter().unzip();
+
+        let mut numbers = vec![None; letters.len()];
+        let mut starts: Vec<usize> = slots.iter().map(|slot| slot[0]).collect();
+        starts.sort_unstable();
+        starts.dedup();
+        for (n, cell) in starts.into_iter().enumerate() {
+            numbers[cell] = Some(n + 1);
+        }
+
+        let mut entries: Vec<Entry> = (slots.iter().zip(&lines))
+            .map(|(slot, &line)| Entry {
+                number: numbers[slot[0]].expect("every slot's first cell is numbered"),
+                line,
+                word: slot.iter().map(|&cell| letters[cell]).collect(),
+            })
+            .collect();
+        entries.sort_by_key(|entry| (entry.line, entry.number));
+
+        // A symmetric box's columns are its rows again, so only its rows go in the bank
+        let mut bank: Vec<String> = (slots.iter().zip(&lines))
+            .filter(|&(_, &line)| !wb.is_symmetric || line == Line::Row)
+            .map(|(slot, _)| slot)
+            .filter(|slot| slot.iter().any(|&cell| !revealed[cell]))
+            .map(|slot| {
+                slot.iter()
+                    .map(|&cell| digraphs::cell_text(letters[cell]))
+                    .collect()
+            })
+            .collect();
+        bank.sort();
+
+        Puzzle {
+            answer: wb.clone(),
+            revealed,
+            numbers,
+            bank,
+            wanted,
+            entries,
+            clues: None,
+            uniqueness: None,
+            extra_reveals: 0,
+        }
+    }
+
+    /// The letters showing and the blocks, locked into an otherwise open template
+    pub fn template(&self) -> Template {
+        let mut template = Template::open(self.answer.row_dim, self.answer.col_dim);
+        for (i, row) in self.answer.rows.iter().enumerate() {
+            for (j, ch) in row.chars().enumerate() {
+                if ch == BLOCK || self.is_revealed(i, j) {
+                    template.set(i, j, Some(ch));
+                }
+            }
+        }
+        template
+    }
+
+    /// Look for a box of `lexicons` other than the answer that fits the letters showing,
+    /// expanding at most `budget` nodes; the search stops at the first one
+    pub fn check_unique<L: Lexicons>(&self, lexicons: &L, budget: usize) -> Uniqueness {
+        let mut empty = self.answer.clone();
+        while empty.remove_last_word().is_some() {}
+        let config = SolverConfig {
+            // The transpose of the answer is another answer if it fits too
+            symmetry_breaking: false,
+            max_nodes: Some(budget),
+            ..SolverConfig::default()
+        };
+        let mut other = None;
+        let wb = empty.with_template(&self.template());
+        let report = solve_word_box_all(wb, lexicons, &config, |wb| {
+            if wb.rows == self.answer.rows {
+                return ControlFlow::Continue(());
+            }
+            other = Some(wb);
+            ControlFlow::Break(())
+        });
+        match (other, report.outcome) {
+            (Some(other), _) => Uniqueness::Ambiguous(other),
+            (None, Outcome::Exhausted) => Uniqueness::Unique,
+            (None, _) => Uniqueness::Unknown,
+        }
+    }
+
+    /// The puzzle with more letters showing, one at a time, for as long as `check_unique` finds
+    /// another box that fits and at most `max_extra` times. Each letter shown rules out the box
+    /// found, being one it has wrong; among those, it is the one leaving the fewest words that
+    /// fit its row and column, preferring letters that leave every word a blank cell.
+    pub fn make_unique<L: Lexicons>(self, lexicons: &L, max_extra: usize, budget: usize) -> Self {
+        let mut puzzle = self;
+        let mut extra_reveals = 0;
+        loop {
+            let uniqueness = puzzle.check_unique(lexicons, budget);
+            let other = match &uniqueness {
+                Uniqueness::Ambiguous(other) if extra_reveals < max_extra => other,
+                _ => {
+                    return Puzzle {
+                        uniqueness: Some(uniqueness),
+                        extra_reveals,
+                        ..puzzle
+                    }
+                }
+            };
+            let mut revealed = puzzle.revealed.clone();
+            revealed[puzzle.next_reveal(other, lexicons)] = true;
+            puzzle = Puzzle::with_revealed(&puzzle.answer, revealed, puzzle.wanted);
+            extra_reveals += 1;
+        }
+    }
+
+    /// The cell to show next to rule out `other` (see `make_unique`)
+    fn next_reveal<L: Lexicons>(&self, other: &WordBox, lexicons: &L) -> usize {
+        let letters: Vec<char> = (self.answer.rows.iter())
+            .flat_map(|row| row.chars())
+            .collect();
+        let others: Vec<char> = other.rows.iter().flat_map(|row| row.chars()).collect();
+        let slots = slots(&letters, self.answer.row_dim, self.answer.col_dim);
+        (0..letters.len())
+            .filter(|&cell| letters[cell] != others[cell])
+            .min_by_key(|&cell| {
+                let crossing = slots.iter().filter(|(_, slot)| slot.contains(&cell));
+                let shown = |c: usize| c == cell || self.revealed[c];
+                let gives_away = (crossing.clone()).any(|(_, slot)| slot.iter().all(|&c| shown(c)));
+                let fitting: usize = crossing
+                    .map(|(line, slot)| {
+                        let pattern: String = (slot.iter())
+                            .map(|&c| if shown(c) { letters[c] } else { '?' })
+                            .collect();
+                        match line {
+                            Line::Row => lexicons.rows().count_with_prefix(&pattern, slot.len()),
+                            Line::Col => lexicons.cols().count_with_prefix(&pattern, slot.len()),
+                        }
+                    })
+                    .sum();
+                (gives_away, fitting, cell)
+            })
+            .expect("a box other than the answer differs from it in some cell")
+    }
+
+    /// The puzzle with each entry clued by its definition, if it has one; the list of clues is
+    /// printed under the word bank
+    pub fn with_clues(self, definitions: &Definitions) -> Self {
+        let clues = (self.entries.iter())
+            .map(|entry| definitions.get(&entry.word).map(str::to_string))
+            .collect();
+        Puzzle {
+            clues: Some(clues),
+            ..self
+        }
+    }
+
+    /// The entries `with_clues` found no definition for
+    pub fn unclued(&self) -> impl Iterator<Item = &Entry> {
+        let clues = self.clues.as_deref().unwrap_or_default();
+        (self.entries.iter().zip(clues))
+            .filter(|(_, clue)| clue.is_none())
+            .map(|(entry, _)| entry)
+    }
+
+    /// Letters showing
+    pub fn shown(&self) -> usize {
+        self.revealed.iter().filter(|&&shown| shown).count()
+    }
+
+    /// Whether the letter of cell (`row`, `col`) is showing
+    pub fn is_revealed(&self, row: usize, col: usize) -> bool {
+        self.revealed[row * self.answer.col_dim + col]
+    }
+
+    /// The clue number of cell (`row`, `col`), if a word starts there
+    pub fn number(&self, row: usize, col: usize) -> Option<usize> {
+        self.numbers[row * self.answer.col_dim + col]
+    }
+}
+
+/// The grid, one row per line: letters showing, clue numbers in the blank cells that start a
+/// word, `.` in the other blank cells and `#` for blocks; then the word bank, and the clues
+/// across and down if the puzzle was clued
+impl Display for Puzzle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let letters: Vec<char> = (self.answer.rows.iter())
+            .flat_map(|row| row.chars())
+            .collect();
+        let width = self
+            .numbers
+            .iter()
+            .flatten()
+            .max()
+            .map_or(1, |n| n.to_string().len())
+            .max(
+                letters
+                    .iter()
+                    .map(|&ch| digraphs::cell_width(ch))
+                    .max()
+                    .unwrap_or(1),
+            );
+        for (i, row) in letters.chunks(self.answer.col_dim.max(1)).enumerate() {
+            let cells: Vec<String> = (row.iter().enumerate())
+                .map(|(j, &ch)| {
+                    let cell = match (ch, self.is_revealed(i, j), self.number(i, j)) {
+                        (BLOCK, _, _) => BLOCK.to_string(),
+                        (ch, true, _) => digraphs::cell_text(ch),
+                        (_, false, Some(n)) => n.to_string(),
+                        (_, false, None) => ".".to_string(),
+                    };
+                    format!("{:>width$}", cell)
+                })
+                .collect();
+            writeln!(f, "{}", cells.join(" "))?;
+        }
+        match self.bank.is_empty() {
+            true => writeln!(f, "word bank: (every word is showing)")?,
+            false => writeln!(f, "word bank: {}", self.bank.join(", "))?,
+        }
+        let Some(clues) = &self.clues else {
+            return Ok(());
+        };
+        for (line, heading) in [(Line::Row, "across:"), (Line::Col, "down:")] {
+            writeln!(f, "{}", heading)?;
+            for (entry, clue) in self.entries.iter().zip(clues) {
+                if entry.line == line {
+                    let clue = clue.as_deref().unwrap_or(NO_CLUE);
+                    writeln!(f, "{:>3}. {}", entry.number, clue)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexicon::{BuildLexicon, VecLexicon};
+
+    fn filled(row_dim: usize, col_dim: usize, symmetric: bool, rows: &[&str]) -> WordBox {
+        (rows.iter()).fold(WordBox::new(row_dim, col_dim, symmetric), |wb, row| {
+            wb.add_word(row.to_string()).unwrap()
+        })
+    }
+
+    fn reveal(fraction: &str) -> Reveal {
+        fraction.parse().unwrap()
+    }
+
+    #[test]
+    fn reveals_are_fractions() {
+        assert_eq!(reveal("0.3").fraction(), 0.3);
+        for fraction in ["-0.1", "1.5", "NaN", "half"] {
+            assert!(fraction.parse::<Reveal>().is_err(), "{}", fraction);
+        }
+    }
+
+    #[test]
+    fn a_reveal_of_none_shows_only_the_clue_numbers() {
+        let wb = filled(3, 3, true, &["cat", "ace", "tea"]);
+        let puzzle = Puzzle::new(&wb, reveal("0"), 7);
+        assert_eq!((puzzle.shown(), puzzle.wanted), (0, 0));
+        assert_eq!(
+            puzzle.to_string(),
+            "1 2 3\n4 . .\n5 . .\nword bank: ace, cat, tea\n"
+        );
+    }
+
+    #[test]
+    fn a_reveal_of_all_shows_every_letter() {
+        let wb = filled(3, 3, true, &["cat", "ace", "tea"]);
+        let puzzle = Puzzle::new(&wb, reveal("1"), 7);
+        assert_eq!((puzzle.shown(), puzzle.wanted), (9, 9));
+        assert!(puzzle.bank.is_empty());
+        assert_eq!(
+            puzzle.to_string(),
+            "c a t\na c e\nt e a\nword bank: (every word is showing)\n"
+        );
+    }
+
+    #[test]
+    fn no_word_is_given_away() {
+        let wb = filled(3, 4, false, &["fast", "area", "dean"]);
+        for seed in 0..20 {
+            let puzzle = Puzzle::new(&wb, reveal("0.9"), seed);
+            assert_eq!(puzzle, Puzzle::new(&wb, reveal("0.9"), seed));
+            assert_eq!(puzzle.wanted, 11);
+            // Every column needs a blank cell, which leaves at most 8 of the 12 to show
+            assert!(puzzle.shown() <= 8, "seed {}: {}", seed, puzzle);
+            assert!((0..3).all(|i| (0..4).any(|j| !puzzle.is_revealed(i, j))));
+            assert!((0..4).all(|j| (0..3).any(|i| !puzzle.is_revealed(i, j))));
+            assert_eq!(puzzle.bank.len(), 7);
+        }
+    }
+
+    #[test]
+    fn extra_reveals_resolve_an_ambiguous_puzzle() {
+        // c or b in the corner and a or e at the far end make four boxes
+        let words = ["cat", "bat", "ace", "tea", "tee"]
+            .map(String::from)
+            .to_vec();
+        let lexicon = VecLexicon::initialize(words, [3].into());
+        let wb = filled(3, 3, true, &["cat", "ace", "tea"]);
+        let puzzle = Puzzle::new(&wb, reveal("0"), 7);
+        assert!(matches!(
+            puzzle.check_unique(&lexicon, UNIQUENESS_BUDGET),
+            Uniqueness::Ambiguous(other) if other.rows != wb.rows
+        ));
+
+        let unique = puzzle.clone().make_unique(&lexicon, 5, UNIQUENESS_BUDGET);
+        assert_eq!(unique.uniqueness, Some(Uniqueness::Unique));
+        assert_eq!(unique.extra_reveals, 2);
+        assert!(unique.is_revealed(0, 0) && unique.is_revealed(2, 2));
+        assert_eq!(unique.shown(), 2);
+        assert_eq!(
+            unique.check_unique(&lexicon, UNIQUENESS_BUDGET),
+            Uniqueness::Unique
+        );
+
+        // One letter more is not enough
+        let capped = puzzle.clone().make_unique(&lexicon, 1, UNIQUENESS_BUDGET);
+        assert!(matches!(capped.uniqueness, Some(Uniqueness::Ambiguous(_))));
+        assert_eq!(capped.extra_reveals, 1);
+        // Nor is a budget too small to rule the other boxes out
+        let rushed = puzzle.make_unique(&lexicon, 5, 1);
+        assert_eq!(rushed.uniqueness, Some(Uniqueness::Unknown));
+    }
+}
@@ -0,0 +1,251 @@
+use crate::lexicon::{BuildLexicon, Lexicon, LexiconOptions, Lexicons, WordId};
+use rand::RngCore;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Answers indexed by word length and then keyed by prefix, so a lookup needs no key of its own
+type Answers<V> = Vec<HashMap<String, V>>;
+
+/// Two generations of answers, evicted the way `NogoodCache` evicts keys: when the young one
+/// fills up it replaces the old one, so answers still being asked for survive and the rest are
+/// dropped in bulk
+struct Generations<V> {
+    young: Answers<V>,
+    old: Answers<V>,
+    young_len: usize,
+    capacity: usize, // answers per generation
+}
+
+impl<V: Clone> Generations<V> {
+    fn new(capacity: usize) -> Self {
+        Generations {
+            young: vec![],
+            old: vec![],
+            young_len: 0,
+            capacity,
+        }
+    }
+
+    /// The answer for `prefix` and `word_len`, moving it back to the young generation if it
+    /// was found in the old one
+    fn get(&mut self, prefix: &str, word_len: usize) -> Option<V> {
+        if let Some(answer) = self.young.get(word_len).and_then(|by| by.get(prefix)) {
+            return Some(answer.clone());
+        }
+        let answer = self.old.get_mut(word_len)?.remove(prefix)?;
+        self.insert(prefix, word_len, answer.clone());
+        Some(answer)
+    }
+
+    fn insert(&mut self, prefix: &str, word_len: usize, answer: V) {
+        if self.young_len >= self.capacity {
+            self.old = std::mem::take(&mut self.young);
+            self.young_len = 0;
+        }
+        if self.young.len() <= word_len {
+            self.young.resize_with(word_len + 1, HashMap::new);
+        }
+        self.young[word_len].insert(prefix.to_string(), answer);
+        self.young_len += 1;
+    }
+}
+
+/// Where a cached lexicon gets its answers from
+enum Source<'a, L> {
+    Owned(L),
+    Borrowed(&'a L),
+}
+
+/// A lexicon that remembers the answers to its prefix queries.
+///
+/// Siblings in a search ask the column lexicon about the same prefixes over and over, as their
+/// columns only differ in the letter just placed. Every kind of prefix query keeps up to about
+/// `max_entries` answers; the other queries go straight to the lexicon underneath. The cache is
+/// not shared between threads, so each search builds its own.
+pub struct CachedLexicon<'a, L> {
+    lexicon: Source<'a, L>,
+    has: RefCell<Generations<bool>>,
+    counts: RefCell<Generations<usize>>,
+    words: RefCell<Generations<Vec<String>>>,
+    ids: RefCell<Generations<Vec<WordId>>>,
+    letters: RefCell<Generations<HashSet<char>>>,
+    hits: Cell<usize>,
+    misses: Cell<usize>,
+}
+
+impl<'a, L: Lexicon> CachedLexicon<'a, L> {
+    /// Capacity of the caches built by `BuildLexicon::from_words`
+    pub const DEFAULT_MAX_ENTRIES: usize = 1 << 16;
+
+    /// A cache in front of `lexicon` keeping about `max_entries` answers per kind of query
+    pub fn new(lexicon: &'a L, max_entries: usize) -> Self {
+        Self::with_source(Source::Borrowed(lexicon), max_entries)
+    }
+
+    fn with_source(lexicon: Source<'a, L>, max_entries: usize) -> Self {
+        let capacity = (max_entries / 2).max(1);
+        CachedLexicon {
+            lexicon,
+            has: RefCell::new(Generations::new(capacity)),
+            counts: RefCell::new(Generations::new(capacity)),
+            words: RefCell::new(Generations::new(capacity)),
+            ids: RefCell::new(Generations::new(capacity)),
+            letters: RefCell::new(Generations::new(capacity)),
+            hits: Cell::new(0),
+            misses: Cell::new(0),
+        }
+    }
+
+    fn inner(&self) -> &L {
+        match &self.lexicon {
+            Source::Owned(lexicon) => lexicon,
+            Source::Borrowed(lexicon) => lexicon,
+        }
+    }
+
+    /// Queries answered from the cache
+    pub fn hits(&self) -> usize {
+        self.hits.get()
+    }
+
+    /// Queries passed on to the lexicon underneath
+    pub fn misses(&self) -> usize {
+        self.misses.get()
+    }
+
+    /// The answer `cache` holds for the query, or the one `ask` gets from the lexicon
+    fn lookup<V: Clone>(
+        &self,
+        cache: &RefCell<Generations<V>>,
+        prefix: &str,
+        word_len: usize,
+        ask: impl FnOnce(&L) -> V,
+    ) -> V {
+        if let Some(answer) = cache.borrow_mut().get(prefix, word_len) {
+            self.hits.set(self.hits.get() + 1);
+            return answer;
+        }
+        self.misses.set(self.misses.get() + 1);
+        let answer = ask(self.inner());
+        cache.borrow_mut().insert(prefix, word_len, answer.clone());
+        answer
+    }
+}
+
+impl<L: BuildLexicon> BuildLexicon for CachedLexicon<'_, L> {
+    fn from_words(
+        words: impl IntoIterator<Item = String>,
+        lengths: BTreeSet<usize>,
+        options: &LexiconOptions,
+    ) -> Self {
+        Self::with_source(
+            Source::Owned(L::from_words(words, lengths, options)),
+            Self::DEFAULT_MAX_ENTRIES,
+        )
+    }
+}
+
+impl<L: Lexicon> Lexicon for CachedLexicon<'_, L> {
+    fn words_with_prefix(&self, prefix: &str, word_len: usize) -> Vec<String> {
+        self.lookup(&self.words, prefix, word_len, |lexicon| {
+            lexicon.words_with_prefix(prefix, word_len)
+        })
+    }
+
+    fn ids_with_prefix(&self, prefix: &str, word_len: usize) -> Vec<WordId> {
+        self.lookup(&self.ids, prefix, word_len, |lexicon| {
+            lexicon.ids_with_prefix(prefix, word_len)
+        })
+    }
+
+    fn word(&self, id: WordId, word_len: usize) -> &str {
+        self.inner().word(id, word_len)
+    }
+
+    fn word_count(&self, word_len: usize) -> usize {
+        self.inner().word_count(word_len)
+    }
+
+    fn count_with_prefix(&self, prefix: &str, word_len: usize) -> usize {
+        self.lookup(&self.counts, prefix, word_len, |lexicon| {
+            lexicon.count_with_prefix(prefix, word_len)
+        })
+    }
+
+    fn has_prefix(&self, prefix: &str, word_len: usize) -> bool {
+        self.lookup(&self.has, prefix, word_len, |lexicon| {
+            lexicon.has_prefix(prefix, word_len)
+        })
+    }
+
+    fn letters_after(&self, prefix: &str, word_len: usize) -> HashSet<char> {
+        self.lookup(&self.letters, prefix, word_len, |lexicon| {
+            lexicon.letters_after(prefix, word_len)
+        })
+    }
+
+    fn words_with_suffix(&self, suffix: &str, word_len: usize) -> Vec<String> {
+        self.inner().words_with_suffix(suffix, word_len)
+    }
+
+    fn memory_estimate(&self) -> usize {
+        self.inner().memory_estimate()
+    }
+
+    fn sample_with_prefix(
+        &self,
+        prefix: &str,
+        word_len: usize,
+        rng: &mut dyn RngCore,
+    ) -> Option<String> {
+        self.inner().sample_with_prefix(prefix, word_len, rng)
+    }
+
+    fn tiers(&self) -> usize {
+        self.inner().tiers()
+    }
+
+    fn tier(&self, word: &str) -> usize {
+        self.inner().tier(word)
+    }
+}
+
+/// A cache in front of the row lexicon and one in front of the column lexicon, or a single one
+/// when rows and columns come from the same lexicon
+pub struct CachedLexicons<'a, L> {
+    rows: CachedLexicon<'a, L>,
+    cols: Option<CachedLexicon<'a, L>>,
+}
+
+impl<'a, L: Lexicon> CachedLexicons<'a, L> {
+    pub fn new<P: Lexicons<Lexicon = L>>(lexicons: &'a P, max_entries: usize) -> Self {
+        let cols = (!std::ptr::eq(lexicons.rows(), lexicons.cols()))
+            .then(|| CachedLexicon::new(lexicons.cols(), max_entries));
+        CachedLexicons {
+            rows: CachedLexicon::new(lexicons.rows(), max_entries),
+            cols,
+        }
+    }
+
+    /// Queries answered from either cache
+    pub fn hits(&self) -> usize {
+        self.rows.hits() + self.cols.as_ref().map_or(0, CachedLexicon::hits)
+    }
+
+    /// Queries passed on to either lexicon
+    pub fn misses(&self) -> usize {
+        self.rows.misses() + self.cols.as_ref().map_or(0, CachedLexicon::misses)
+    }
+}
+
+impl<'a, L: Lexicon> Lexicons for CachedLexicons<'a, L> {
+    type Lexicon = CachedLexicon<'a, L>;
+
+    fn rows(&self) -> &CachedLexicon<'a, L> {
+        &self.rows
+    }
+
+    fn cols(&self) -> &CachedLexicon<'a, L> {
+        self.cols.as_ref().unwrap_or(&self.rows)
+    }
+}
@@ -0,0 +1,268 @@
+use crate::blocks::BLOCK;
+use crate::scoring::Metric;
+use crate::wordbox::WordBox;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt::Display;
+
+/// What completed boxes are ranked by, highest first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RankBy {
+    /// How common the box's letters are in the word list (see `LetterScores`)
+    #[default]
+    Score,
+    /// How many theme words the box uses (see `ThemeWords::count`)
+    Theme,
+    /// How few words the box takes from the less preferred tiers (see `TierCounts::score`)
+    Tier,
+    /// The Scrabble value of the box's letters (see `WordBox::scrabble_score`)
+    Scrabble,
+}
+
+impl RankBy {
+    pub const ALL: [RankBy; 4] = [RankBy::Score, RankBy::Theme, RankBy::Tier, RankBy::Scrabble];
+
+    /// The metric ranked by
+    pub fn metric(self) -> Metric {
+        match self {
+            RankBy::Score => Metric::Score,
+            RankBy::Theme => Metric::Theme,
+            RankBy::Tier => Metric::Tier,
+            RankBy::Scrabble => Metric::Scrabble,
+        }
+    }
+}
+
+impl Display for RankBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RankBy::Score => write!(f, "score"),
+            RankBy::Theme => write!(f, "theme"),
+            RankBy::Tier => write!(f, "tier"),
+            RankBy::Scrabble => write!(f, "scrabble"),
+        }
+    }
+}
+
+impl std::str::FromStr for RankBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RankBy::ALL
+            .into_iter()
+            .find(|rank| rank.to_string() == s)
+            .ok_or_else(|| {
+                let names: Vec<String> = RankBy::ALL.iter().map(|r| r.to_string()).collect();
+                format!(
+                    "unknown ranking {:?} (expected one of {})",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// The order solutions are written in when they are sorted once the run is over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortBy {
+    /// Highest `RankBy::Score` first
+    Score,
+    /// Highest summed word frequency first (see `WordFrequencies::total`)
+    Frequency,
+    /// Most theme words first (see `ThemeWords::count`)
+    Theme,
+    /// Fewest words from the less preferred tiers first (see `TierCounts::score`)
+    Tier,
+    /// Highest Scrabble value first (see `WordBox::scrabble_score`)
+    Scrabble,
+    /// Most different letters first (see `WordBox::distinct_letters`)
+    DistinctLetters,
+    /// By the rows read one after another
+    Alphabetical,
+    /// In the order of the seed words they were found from
+    Seed,
+    /// Highest first by a scorer of the caller's, e.g. a `ScoreFormula`
+    Custom,
+}
+
+impl SortBy {
+    pub const ALL: [SortBy; 9] = [
+        SortBy::Score,
+        SortBy::Frequency,
+        SortBy::Theme,
+        SortBy::Tier,
+        SortBy::Scrabble,
+        SortBy::DistinctLetters,
+        SortBy::Alphabetical,
+        SortBy::Seed,
+        SortBy::Custom,
+    ];
+
+    /// The built-in metric sorted by, if the order is one
+    pub fn metric(self) -> Option<Metric> {
+        match self {
+            SortBy::Score => Some(Metric::Score),
+            SortBy::Frequency => Some(Metric::Frequency),
+            SortBy::Theme => Some(Metric::Theme),
+            SortBy::Tier => Some(Metric::Tier),
+            SortBy::Scrabble => Some(Metric::Scrabble),
+            SortBy::DistinctLetters => Some(Metric::DistinctLetters),
+            SortBy::Alphabetical | SortBy::Seed | SortBy::Custom => None,
+        }
+    }
+}
+
+impl Display for SortBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SortBy::Score => write!(f, "score"),
+            SortBy::Frequency => write!(f, "frequency"),
+            SortBy::Theme => write!(f, "theme"),
+            SortBy::Tier => write!(f, "tier"),
+            SortBy::Scrabble => write!(f, "scrabble"),
+            SortBy::DistinctLetters => write!(f, "distinct-letters"),
+            SortBy::Alphabetical => write!(f, "alphabetical"),
+            SortBy::Seed => write!(f, "seed"),
+            SortBy::Custom => write!(f, "custom"),
+        }
+    }
+}
+
+impl std::str::FromStr for SortBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SortBy::ALL
+            .into_iter()
+            .find(|sort| sort.to_string() == s)
+            .ok_or_else(|| {
+                let names: Vec<String> = SortBy::ALL.iter().map(|s| s.to_string()).collect();
+                format!(
+                    "unknown sort key {:?} (expected one of {})",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// The natural log of each letter's share of all the letters in a word list. A box scores the
+/// mean over its cells, so boxes of common letters like `e` and `s` rank above boxes that need
+/// a `q` or a `z`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LetterScores {
+    scores: HashMap<char, f64>,
+    unseen: f64, // score of a letter the list never uses, below every other
+}
+
+impl LetterScores {
+    pub fn from_words(words: &[String]) -> Self {
+        let mut counts: HashMap<char, usize> = HashMap::new();
+        for ch in words.iter().flat_map(|word| word.chars()) {
+            *counts.entry(ch).or_default() += 1;
+        }
+        let total = counts.values().sum::<usize>().max(1) as f64;
+        LetterScores {
+            scores: counts
+                .into_iter()
+                .map(|(ch, count)| (ch, (count as f64 / total).ln()))
+                .collect(),
+            unseen: (0.5 / total).ln(),
+        }
+    }
+
+    /// The mean score of the letters in the box's rows, leaving out blocked cells
+    pub fn score(&self, wb: &WordBox) -> f64 {
+        let letters = wb.rows.iter().flat_map(|row| row.chars());
+        let (sum, cells) =
+            letters
+                .filter(|&ch| ch != BLOCK)
+                .fold((0.0, 0usize), |(sum, cells), ch| {
+                    let score = self.scores.get(&ch).copied().unwrap_or(self.unseen);
+                    (sum + score, cells + 1)
+                });
+        sum / cells.max(1) as f64
+    }
+}
+
+/// An item in a `TopK`, ordered by score and then by key, the smaller key ranking higher
+struct Ranked<K, T> {
+    score: f64,
+    key: K,
+    item: T,
+}
+
+impl<K: Ord, T> PartialEq for Ranked<K, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K: Ord, T> Eq for Ranked<K, T> {}
+
+impl<K: Ord, T> PartialOrd for Ranked<K, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, T> Ord for Ranked<K, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .total_cmp(&other.score)
+            .then_with(|| other.key.cmp(&self.key))
+    }
+}
+
+/// The `k` highest-scoring items pushed so far, in a min-heap that never holds more than `k`.
+/// Ties on score go to the item with the smaller key, so the same items come out whatever
+/// order they were pushed in, and heaps filled by separate threads can be merged.
+pub struct TopK<K, T> {
+    k: usize,
+    heap: BinaryHeap<Reverse<Ranked<K, T>>>,
+}
+
+impl<K: Ord, T> TopK<K, T> {
+    pub fn new(k: usize) -> Self {
+        TopK {
+            k,
+            heap: BinaryHeap::with_capacity(k.saturating_add(1).min(1 << 16)),
+        }
+    }
+
+    /// Keep `item` if it ranks among the best `k`, dropping the worst item kept if need be
+    pub fn push(&mut self, score: f64, key: K, item: T) {
+        if self.k == 0 {
+            return;
+        }
+        self.heap.push(Reverse(Ranked { score, key, item }));
+        if self.heap.len() > self.k {
+            self.heap.pop();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// The best `k` items of both heaps
+    pub fn merge(mut self, other: Self) -> Self {
+        for Reverse(ranked) in other.heap {
+            self.push(ranked.score, ranked.key, ranked.item);
+        }
+        self
+    }
+
+    /// The items kept, best first, with their scores
+    pub fn into_sorted(self) -> Vec<(f64, T)> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(ranked)| (ranked.score, ranked.item))
+            .collect()
+    }
+}
@@ -0,0 +1,404 @@
+use crate::alphabet::Alphabet;
+use crate::highlight::StyledBox;
+use crate::lexicon::{parse_prefix, word_length, Lexicon, WILDCARD};
+use crate::solver::{solve_word_box_all, CandidateDetail, Candidates, Outcome, SolverConfig};
+use crate::template::Template;
+use crate::wordbox::{WordBox, WordBoxError};
+use std::io::{self, BufRead, Write};
+use std::ops::ControlFlow;
+
+/// Nodes `complete` may search before it gives up, so the prompt comes back quickly
+pub const DEFAULT_BUDGET: usize = 100_000;
+
+/// Results printed at once; `more` prints the next page
+pub const DEFAULT_PAGE: usize = 40;
+
+const HELP: &str = "\
+prefix PREFIX [LEN]   words of length LEN (default: the box width) starting with PREFIX
+match PATTERN         words matching PATTERN, with . or ? for any letter, e.g. s..ne.
+complete ROW...       complete a box whose first rows are given, . marking open cells
+place ROW             place ROW as the next row of the box being built
+undo                  take back the last row placed
+slots [ROW...]        the words that may go in each open row and column after these rows,
+                      or of the box being built
+more                  the next page of results
+set rows|cols N       the size of the box `complete` fills and `place` builds
+set symmetric on|off  whether the box reads the same across and down; both start a new box
+set budget N          nodes `complete` may search before giving up
+set page N            results printed at once
+help                  this list
+quit                  leave";
+
+/// A setting changed with `set`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    Rows(usize),
+    Cols(usize),
+    Symmetric(bool),
+    Budget(usize),
+    Page(usize),
+}
+
+/// A command read at the prompt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Request {
+    Prefix { prefix: String, len: Option<usize> },
+    Match { pattern: String },
+    Complete { rows: Vec<String> },
+    Slots { rows: Vec<String> },
+    Place { row: String },
+    Undo,
+    More,
+    Set(Setting),
+    Help,
+    Quit,
+}
+
+/// A pattern typed with `.` for open cells, as the lexicon takes it
+fn to_wildcards(pattern: &str) -> String {
+    pattern.replace('.', &WILDCARD.to_string())
+}
+
+impl Request {
+    /// The command on a line, `None` for a blank line, or why it could not be read
+    pub fn parse(line: &str) -> Result<Option<Request>, String> {
+        let mut fields = line.split_whitespace();
+        let Some(command) = fields.next() else {
+            return Ok(None);
+        };
+        let args: Vec<&str> = fields.collect();
+        let number = |text: &str| {
+            text.parse::<usize>()
+                .map_err(|_| format!("expected a number, found {:?}", text))
+        };
+        let dim = |text: &str| match number(text)? {
+            0 => Err("a box needs at least one row and one column".to_string()),
+            n => Ok(n),
+        };
+        let request = match (command, &args[..]) {
+            ("prefix", [prefix]) => Request::Prefix {
+                prefix: to_wildcards(prefix),
+                len: None,
+            },
+            ("prefix", [prefix, len]) => Request::Prefix {
+                prefix: to_wildcards(prefix),
+                len: Some(number(len)?),
+            },
+            ("match", [pattern]) => Request::Match {
+                pattern: to_wildcards(pattern),
+            },
+            ("complete", rows) if !rows.is_empty() => Request::Complete {
+                rows: rows.iter().map(|row| row.to_string()).collect(),
+            },
+            ("more", []) => Request::More,
+            ("set", [name, value]) => Request::Set(match *name {
+                "rows" => Setting::Rows(dim(value)?),
+                "cols" => Setting::Cols(dim(value)?),
+                "budget" => Setting::Budget(number(value)?),
+                "page" => Setting::Page(number(value)?.max(1)),
+                "symmetric" => Setting::Symmetric(match *value {
+                    "on" => true,
+                    "off" => false,
+                    _ => return Err(format!("expected on or off, found {:?}", value)),
+                }),
+                _ => return Err(format!("unknown setting {:?}", name)),
+            }),
+            ("help", []) => Request::Help,
+            ("place", [row]) => Request::Place {
+                row: row.to_string(),
+            },
+            ("undo", []) => Request::Undo,
+            ("slots", rows) => Request::Slots {
+                rows: rows.iter().map(|row| row.to_string()).collect(),
+            },
+            ("quit" | "exit", []) => Request::Quit,
+            (
+                "prefix" | "match" | "complete" | "place" | "undo" | "more" | "set" | "help"
+                | "quit" | "exit",
+                _,
+            ) => return Err(format!("wrong arguments for {}; type help", command)),
+            _ => return Err(format!("unknown command {:?}; type help", command)),
+        };
+        Ok(Some(request))
+    }
+}
+
+/// A session answering questions about one lexicon, built once for the whole session
+pub struct Repl<'a, L> {
+    lexicon: &'a L,
+    alphabet: Alphabet,
+    row_dim: usize,
+    col_dim: usize,
+    symmetric: bool,
+    budget: usize,
+    page: usize,
+    pending: Vec<String>, // results of the last query not printed yet, last one first
+    building: Option<WordBox>, // the box `place` and `undo` edit, once a row is placed
+    styled: bool,         // whether the box being built shows its seed and latest rows
+}
+
+impl<'a, L: Lexicon> Repl<'a, L> {
+    /// A session filling `row_dim`×`col_dim` boxes until `set` says otherwise
+    pub fn new(
+        lexicon: &'a L,
+        alphabet: Alphabet,
+        row_dim: usize,
+        col_dim: usize,
+        symmetric: bool,
+    ) -> Self {
+        Repl {
+            lexicon,
+            alphabet,
+            row_dim,
+            col_dim,
+            symmetric,
+            budget: DEFAULT_BUDGET,
+            page: DEFAULT_PAGE,
+            pending: vec![],
+            building: None,
+            styled: false,
+        }
+    }
+
+    /// Style the box `place` and `undo` print, its seed row and the row placed last standing
+    /// out (see `StyledBox::live`); for a terminal, not for a file or a pipe
+    pub fn with_styles(mut self, styled: bool) -> Self {
+        self.styled = styled;
+        self
+    }
+
+    /// An empty box of the current size
+    fn empty_box(&self) -> Result<WordBox, WordBoxError> {
+        let empty = WordBox::try_new(self.row_dim, self.col_dim, self.symmetric)?;
+        Ok(empty.with_alphabet(&self.alphabet))
+    }
+
+    /// Answer every command read from `input` until it ends or asks to quit, printing
+    /// `prompt` before each one if given
+    pub fn run(
+        &mut self,
+        input: impl BufRead,
+        out: &mut impl Write,
+        prompt: Option<&str>,
+    ) -> io::Result<()> {
+        let mut lines = input.lines();
+        loop {
+            if let Some(prompt) = prompt {
+                write!(out, "{}", prompt)?;
+                out.flush()?;
+            }
+            let Some(line) = lines.next() else {
+                return Ok(());
+            };
+            match Request::parse(&line?) {
+                Ok(None) => {}
+                Ok(Some(Request::Quit)) => return Ok(()),
+                Ok(Some(request)) => self.execute(request, out)?,
+                Err(err) => writeln!(out, "error: {}", err)?,
+            }
+        }
+    }
+
+    /// Answer one command other than `quit`
+    pub fn execute(&mut self, request: Request, out: &mut impl Write) -> io::Result<()> {
+        match request {
+            Request::Prefix { prefix, len } => {
+                let len = len.unwrap_or(self.col_dim);
+                match parse_prefix(&prefix, &self.alphabet) {
+                    Ok(prefix) => {
+                        let words = self.lexicon.words_with_prefix(&prefix, len);
+                        self.show(words, out)?;
+                    }
+                    Err(err) => writeln!(out, "error: {}", err)?,
+                }
+            }
+            Request::Match { pattern } => match parse_prefix(&pattern, &self.alphabet) {
+                Ok(pattern) => {
+                    let words = self
+                        .lexicon
+                        .words_with_prefix(&pattern, word_length(&pattern));
+                    self.show(words, out)?;
+                }
+                Err(err) => writeln!(out, "error: {}", err)?,
+            },
+            Request::Complete { rows } => self.complete(&rows, out)?,
+            Request::Slots { rows } => self.slots(&rows, out)?,
+            Request::Place { row } => self.place(&row, out)?,
+            Request::Undo => match self.building.as_mut() {
+                Some(wb) => match wb.remove_last_word() {
+                    Some(word) => {
+                        let shown = StyledBox::live(wb).render(self.styled);
+                        write!(out, "took back {}\n{}", word, shown)?
+                    }
+                    None => writeln!(out, "no row to take back")?,
+                },
+                None => writeln!(out, "no row to take back")?,
+            },
+            Request::More => self.next_page(out)?,
+            Request::Set(setting) => {
+                self.building = None;
+                match setting {
+                    Setting::Rows(n) => self.row_dim = n,
+                    Setting::Cols(n) => self.col_dim = n,
+                    Setting::Symmetric(on) => self.symmetric = on,
+                    Setting::Budget(n) => self.budget = n,
+                    Setting::Page(n) => self.page = n,
+                }
+                writeln!(
+                    out,
+                    "{}x{} {}, budget {} nodes, {} results a page",
+                    self.row_dim,
+                    self.col_dim,
+                    if self.symmetric {
+                        "symmetric"
+                    } else {
+                        "asymmetric"
+                    },
+                    self.budget,
+                    self.page
+                )?;
+            }
+            Request::Help => writeln!(out, "{}", HELP)?,
+            Request::Quit => {}
+        }
+        Ok(())
+    }
+
+    /// Print the first page of `words` with their number, keeping the rest for `more`
+    fn show(&mut self, words: Vec<String>, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "{} words", words.len())?;
+        self.pending = words;
+        self.pending.reverse();
+        self.next_page(out)
+    }
+
+    fn next_page(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let start = self.pending.len().saturating_sub(self.page);
+        for word in self.pending.drain(start..).rev() {
+            writeln!(out, "{}", word)?;
+        }
+        if !self.pending.is_empty() {
+            writeln!(out, "-- {} more; type more --", self.pending.len())?;
+        }
+        Ok(())
+    }
+
+    /// Place `row` as the next row of the box being built and print the box
+    fn place(&mut self, row: &str, out: &mut impl Write) -> io::Result<()> {
+        let wb = match self.building.take().map_or_else(|| self.empty_box(), Ok) {
+            Ok(wb) => wb,
+            Err(err) => return writeln!(out, "error: {}", err),
+        };
+        let placed = parse_prefix(row, &self.alphabet).map(|row| wb.add_word(row));
+        self.building = Some(wb);
+        match placed {
+            Ok(Ok(wb)) => {
+                write!(out, "{}", StyledBox::live(&wb).render(self.styled))?;
+                self.building = Some(wb);
+            }
+            Ok(Err(err)) => writeln!(out, "error: {}", err)?,
+            Err(err) => writeln!(out, "error: {}", err)?,
+        }
+        Ok(())
+    }
+
+    /// List how many words may go in each open slot of a box of the current size whose first
+    /// rows are `rows`, or of the box being built if none are given, then the words of the
+    /// first of them
+    fn slots(&mut self, rows: &[String], out: &mut impl Write) -> io::Result<()> {
+        let built = self.building.clone().filter(|_| rows.is_empty());
+        let mut wb = match built.map_or_else(|| self.empty_box(), Ok) {
+            Ok(wb) => wb,
+            Err(err) => return writeln!(out, "error: {}", err),
+        };
+        for row in rows {
+            wb = match parse_prefix(row, &self.alphabet).map(|row| wb.add_word(row)) {
+                Ok(Ok(wb)) => wb,
+                Ok(Err(err)) => return writeln!(out, "error: {}", err),
+                Err(err) => return writeln!(out, "error: {}", err),
+            };
+        }
+        let config = SolverConfig::default();
+        let slots = wb.candidates(self.lexicon, &config, CandidateDetail::Words);
+        if slots.slots.is_empty() {
+            return writeln!(out, "no open slots");
+        }
+        for slot in &slots.slots {
+            writeln!(
+                out,
+                "{} {}: {} words",
+                slot.line,
+                slot.index,
+                slot.candidates.len()
+            )?;
+        }
+        match &slots.slots[0].candidates {
+            Candidates::Words(words) => self.show(words.clone(), out),
+            Candidates::Count(_) => Ok(()),
+        }
+    }
+
+    /// Look for one box of the current size whose first rows match `rows`, searching at most
+    /// the budget's worth of nodes
+    fn complete(&mut self, rows: &[String], out: &mut impl Write) -> io::Result<()> {
+        if rows.len() > self.row_dim {
+            return writeln!(
+                out,
+                "error: {} rows given for a box of {}",
+                rows.len(),
+                self.row_dim
+            );
+        }
+        if let Some(row) = rows.iter().find(|row| word_length(row) != self.col_dim) {
+            return writeln!(
+                out,
+                "error: {:?} does not fill a row of {} cells",
+                row, self.col_dim
+            );
+        }
+        let open = ".".repeat(self.col_dim);
+        let lines: Vec<&str> = (0..self.row_dim)
+            .map(|i| rows.get(i).map_or(open.as_str(), String::as_str))
+            .collect();
+        let mut template = match Template::parse(&lines.join("\n"), &self.alphabet) {
+            Ok(template) => template,
+            Err(err) => return writeln!(out, "error: {}", err),
+        };
+        let empty = match WordBox::try_new(self.row_dim, self.col_dim, self.symmetric) {
+            Ok(empty) => empty,
+            Err(err) => return writeln!(out, "error: {}", err),
+        };
+        if self.symmetric {
+            if let Some((row, col)) = template.mirror_conflict() {
+                return writeln!(
+                    out,
+                    "no completion: a symmetric box cannot differ at row {}, column {} and opposite it",
+                    row, col
+                );
+            }
+            template = template.mirrored();
+        }
+
+        let config = SolverConfig {
+            symmetry_breaking: false,
+            max_nodes: Some(self.budget),
+            ..SolverConfig::default()
+        };
+        let wb = empty.with_alphabet(&self.alphabet).with_template(&template);
+        let mut found = None;
+        let report = solve_word_box_all(wb, self.lexicon, &config, |wb| {
+            found = Some(wb);
+            ControlFlow::Break(())
+        });
+        match (found, report.outcome) {
+            (Some(wb), _) => write!(out, "{}", wb)?,
+            (None, Outcome::Exhausted) => writeln!(out, "no completion")?,
+            (None, _) => writeln!(
+                out,
+                "no completion found within {} nodes; set budget to search longer",
+                self.budget
+            )?,
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,1013 @@
+use crate::cancel::CancellationToken;
+use crate::cube::json_string;
+use crate::lexicon::Lexicons;
+use crate::memory::{shared_str_bytes, Consumer, EstimatedSize};
+use crate::parallel::{self, prelude::*};
+use crate::provenance::{Provenance, RunProvenance};
+use crate::scrabble::LetterValues;
+use crate::solver::{
+    solve_word_box_all, split_word_box, Outcome, RowSearch, SearchReport, SolverConfig,
+};
+use crate::tiers::TierCounts;
+use crate::usage::{LetterHeatmap, WordUsage};
+use crate::wordbox::WordBox;
+use std::collections::{BTreeMap, HashSet};
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How a run over many seed words is carried out
+#[derive(Debug, Clone)]
+pub struct RunOptions {
+    pub threads: usize,       // worker threads; 0 uses every core, 1 runs sequentially
+    pub limit: Option<usize>, // stop once this many solutions have been found
+    pub exhaustive: bool,     // find every solution per seed instead of only the first
+    pub channel_capacity: usize, // events buffered before workers block on the consumer
+    /// Also split each seed's search: expand this many rows below the seed on the worker, then
+    /// search the subtree of each resulting box as a task of its own; 0 searches a seed whole.
+    /// Keeps every core busy when a few hard seeds outlast the rest (ignored with one thread).
+    pub split_depth: usize,
+    /// Drop solutions identical to one already reported, as when the word list holds a word twice
+    /// (or, with symmetry breaking, the transpose of one already reported)
+    pub dedup: bool,
+    /// Only count the solutions: none is built into a `FoundSolution` or sent to the consumer,
+    /// which sees nothing but `SeedDone` events; the count is `RunState::solutions`
+    pub count_only: bool,
+    /// Deliver the solutions in the order of the seeds, and of the search within each seed,
+    /// whatever order the workers find them in, so that runs with the same inputs report the
+    /// same solutions in the same order; each seed's solutions are held until every seed before
+    /// it is done. Duplicates and the limit are then settled in that order too, so a seed whose
+    /// first solution repeats an earlier seed's reports none rather than searching on. Ignored
+    /// when only counting.
+    pub deterministic: bool,
+    /// Count the words of every solution reported here, as the workers find them
+    pub word_usage: Option<Arc<WordUsage>>,
+    /// Count the letters of every solution reported here, cell by cell
+    pub letter_heatmap: Option<Arc<LetterHeatmap>>,
+    /// Score every solution with these tile values (see `WordBox::scrabble_score`)
+    pub letter_values: Option<Arc<LetterValues>>,
+    /// Record where every solution came from, so that it can be replayed without a search
+    pub provenance: Option<Arc<RunProvenance>>,
+    /// How the seeds share the workers; a fair schedule falls back to the sequential one when
+    /// the solver's search cannot pause, and searches every seed whole (`split_depth` is ignored)
+    pub schedule: Schedule,
+    pub slice: usize, // nodes each search may expand per turn of a fair schedule
+    /// What each seed's search may take before the run moves on, leaving the seed inconclusive
+    pub seed_budget: SeedBudget,
+    pub solver: SolverConfig,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        RunOptions {
+            threads: 0,
+            limit: None,
+            exhaustive: false,
+            channel_capacity: 1024,
+            split_depth: 0,
+            dedup: true,
+            count_only: false,
+            deterministic: false,
+            word_usage: None,
+            letter_heatmap: None,
+            letter_values: None,
+            provenance: None,
+            schedule: Schedule::Sequential,
+            slice: 1000,
+            seed_budget: SeedBudget::default(),
+            solver: SolverConfig::default(),
+        }
+    }
+}
+
+/// Progress and cancellation shared by every worker of a run
+#[derive(Debug, Default)]
+pub struct RunState {
+    solutions: AtomicUsize,
+    seeds_done: AtomicUsize,
+    nodes: AtomicUsize,              // nodes searched by the seeds done
+    cancellation: CancellationToken, // handed to every solve, which checks it at each node
+    limit_reached: AtomicBool,
+    // The rows of every solution reported so far; solutions are rare next to the nodes searched,
+    // so a single lock is enough
+    seen: Mutex<HashSet<Vec<String>>>,
+    seen_bytes: AtomicUsize,
+    duplicates: AtomicUsize,
+}
+
+impl RunState {
+    /// A run that also stops when `token` is cancelled, e.g. one size of a sweep over sizes
+    /// that Ctrl-C should end as a whole
+    pub fn with_cancellation(token: &CancellationToken) -> Self {
+        RunState {
+            cancellation: token.child(),
+            ..RunState::default()
+        }
+    }
+
+    /// Ask the workers to stop; searches in progress return at their next node and the
+    /// remaining seeds are skipped
+    pub fn cancel(&self) {
+        self.cancellation.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+
+    /// A handle that cancels this run when triggered, e.g. from a signal handler
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
+    pub fn solutions(&self) -> usize {
+        self.solutions.load(Ordering::Relaxed)
+    }
+
+    pub fn seeds_done(&self) -> usize {
+        self.seeds_done.load(Ordering::Relaxed)
+    }
+
+    /// Nodes searched so far, counting each seed's once its search is over
+    pub fn nodes(&self) -> usize {
+        self.nodes.load(Ordering::Relaxed)
+    }
+
+    /// Whether the run was cut short because the solution limit was reached
+    pub fn limit_reached(&self) -> bool {
+        self.limit_reached.load(Ordering::Relaxed)
+    }
+
+    /// Solutions dropped because an identical one had already been reported
+    pub fn duplicates(&self) -> usize {
+        self.duplicates.load(Ordering::Relaxed)
+    }
+
+    /// Number of solutions remembered for deduplication, and roughly how many bytes they hold
+    pub fn dedup_memory(&self) -> (usize, usize) {
+        let seen = self.seen.lock().unwrap().len();
+        (seen, self.seen_bytes.load(Ordering::Relaxed))
+    }
+
+    /// Remember the rows of a solution, returning false if they had been seen before
+    fn first_sighting(&self, rows: Vec<String>) -> bool {
+        let bytes = std::mem::size_of::<Vec<String>>()
+            + rows.capacity() * std::mem::size_of::<String>()
+            + rows.iter().map(String::capacity).sum::<usize>();
+        if self.seen.lock().unwrap().insert(rows) {
+            self.seen_bytes.fetch_add(bytes, Ordering::Relaxed);
+            true
+        } else {
+            self.duplicates.fetch_add(1, Ordering::Relaxed);
+            false
+        }
+    }
+
+    /// Claim a slot for a new solution, returning false if the limit has already been reached.
+    /// Claiming the last slot cancels the run, so no more than `limit` solutions are ever reported.
+    fn record_solution(&self, limit: Option<usize>) -> bool {
+        let limit = limit.unwrap_or(usize::MAX);
+        let claimed = self
+            .solutions
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |found| {
+                (found < limit).then_some(found + 1)
+            });
+        match claimed {
+            Ok(found) if found + 1 == limit => {
+                self.limit_reached.store(true, Ordering::Relaxed);
+                self.cancel();
+                true
+            }
+            Ok(_) => true,
+            Err(_) => false,
+        }
+    }
+}
+
+/// One of `count` equal shares of a run's seeds, for splitting a search across machines: the
+/// seeds whose index in the sorted seed list leaves `index` when divided by `count`. Written
+/// `index/count`, e.g. `2/8`, counting from 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Shard {
+    pub index: usize,
+    pub count: usize,
+}
+
+impl Shard {
+    /// The seeds of this shard, sorted. The seeds are numbered in sorted order, so every shard
+    /// agrees on the numbering whatever order the lexicon lists them in, and the shards of a run
+    /// between them take each seed exactly once.
+    pub fn select(&self, mut seeds: Vec<String>) -> Vec<String> {
+        seeds.sort();
+        (seeds.into_iter().enumerate())
+            .filter(|(i, _)| i % self.count == self.index)
+            .map(|(_, seed)| seed)
+            .collect()
+    }
+}
+
+impl std::str::FromStr for Shard {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "expected a shard such as 2/8 (the third of eight), found {:?}",
+                s
+            )
+        };
+        let (index, count) = s.split_once('/').ok_or_else(invalid)?;
+        let (Ok(index), Ok(count)) = (index.trim().parse::<usize>(), count.trim().parse::<usize>())
+        else {
+            return Err(invalid());
+        };
+        if count == 0 {
+            return Err("a run splits into at least one shard".to_string());
+        }
+        if index >= count {
+            return Err(format!(
+                "shard {} is out of range: shards are counted from 0 to {}",
+                index,
+                count - 1
+            ));
+        }
+        Ok(Shard { index, count })
+    }
+}
+
+impl std::fmt::Display for Shard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+/// The order a run tries its seed words in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SeedOrder {
+    /// The order the lexicon lists them in
+    #[default]
+    Lexicon,
+    /// Alphabetical order
+    Lexicographic,
+    /// A shuffled order (see `--seed`)
+    Random,
+    /// The seeds whose letters start the most column words first (see `productive_order`)
+    Productive,
+}
+
+impl SeedOrder {
+    pub const ALL: [SeedOrder; 4] = [
+        SeedOrder::Lexicon,
+        SeedOrder::Lexicographic,
+        SeedOrder::Random,
+        SeedOrder::Productive,
+    ];
+}
+
+impl std::fmt::Display for SeedOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SeedOrder::Lexicon => write!(f, "lexicon"),
+            SeedOrder::Lexicographic => write!(f, "lexicographic"),
+            SeedOrder::Random => write!(f, "random"),
+            SeedOrder::Productive => write!(f, "productive"),
+        }
+    }
+}
+
+impl std::str::FromStr for SeedOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SeedOrder::ALL
+            .into_iter()
+            .find(|order| order.to_string() == s)
+            .ok_or_else(|| {
+                let names: Vec<String> = SeedOrder::ALL.iter().map(|o| o.to_string()).collect();
+                format!(
+                    "unknown seed order {:?} (expected one of {})",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// How a run shares its workers among the seeds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Schedule {
+    /// A worker searches a seed to the end before it takes the next
+    #[default]
+    Sequential,
+    /// The searches of many seeds are open at once and take turns of `RunOptions::slice` nodes
+    /// each, so that a seed that is slow to search holds back no solutions of the easy seeds
+    /// after it. Needs a search that can pause (see `SolverConfig::can_pause`).
+    Fair,
+}
+
+impl Schedule {
+    pub const ALL: [Schedule; 2] = [Schedule::Sequential, Schedule::Fair];
+}
+
+impl std::fmt::Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Schedule::Sequential => write!(f, "sequential"),
+            Schedule::Fair => write!(f, "fair"),
+        }
+    }
+}
+
+impl std::str::FromStr for Schedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Schedule::ALL
+            .into_iter()
+            .find(|schedule| schedule.to_string() == s)
+            .ok_or_else(|| {
+                let names: Vec<String> = Schedule::ALL.iter().map(|s| s.to_string()).collect();
+                format!(
+                    "unknown schedule {:?} (expected one of {})",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// What one seed's search may take, apart from any limit on the run as a whole. A seed whose
+/// search runs out of it is inconclusive: its search ends with `Outcome::BudgetExhausted`, having
+/// neither found nor ruled out its boxes, and the run moves on to the next seed.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SeedBudget {
+    /// Time spent searching the seed; under a fair schedule, the time of its turns only, so a
+    /// seed waiting for its turn loses none
+    pub timeout: Option<Duration>,
+    /// Nodes the seed's search may expand; a seed split into subtrees (see
+    /// `RunOptions::split_depth`) shares them out evenly among its subtrees
+    pub max_nodes: Option<usize>,
+}
+
+impl SeedBudget {
+    /// Whether the budget limits anything
+    pub fn is_limited(&self) -> bool {
+        self.timeout.is_some() || self.max_nodes.is_some()
+    }
+
+    /// The budget `factor` times as big, to try the seeds it left inconclusive again
+    pub fn scaled(&self, factor: f64) -> SeedBudget {
+        SeedBudget {
+            timeout: self.timeout.map(|timeout| timeout.mul_f64(factor)),
+            max_nodes: (self.max_nodes).map(|max| (max as f64 * factor).ceil() as usize),
+        }
+    }
+
+    /// `config` held to this budget for a search of `parts` subtrees starting now, along with
+    /// any node budget and deadline it has of its own; the deadline is left out if `timed` is
+    /// false, for a search whose time is measured by its turns instead
+    fn limit(&self, config: &SolverConfig, parts: usize, timed: bool) -> SolverConfig {
+        let max_nodes = (self.max_nodes).map(|max| max.div_ceil(parts.max(1)));
+        let deadline = (self.timeout)
+            .filter(|_| timed)
+            .map(|timeout| Instant::now() + timeout);
+        SolverConfig {
+            max_nodes: [config.max_nodes, max_nodes].into_iter().flatten().min(),
+            deadline: [config.deadline, deadline].into_iter().flatten().min(),
+            ..config.clone()
+        }
+    }
+}
+
+/// A seed whose search ran out of its `SeedBudget`, and how far it got
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InconclusiveSeed {
+    pub seed: String,
+    pub nodes: usize,     // nodes its search expanded
+    pub max_depth: usize, // most rows (or slots, with mcv) it filled in any box
+    pub elapsed: Duration,
+}
+
+impl InconclusiveSeed {
+    /// The seed if its search, ending with `report`, ran out of its budget
+    pub fn from_report(seed: &str, report: &SearchReport) -> Option<Self> {
+        (report.outcome == Outcome::BudgetExhausted).then(|| InconclusiveSeed {
+            seed: seed.to_string(),
+            nodes: report.stats.nodes,
+            max_depth: report.stats.max_depth,
+            elapsed: report.stats.elapsed,
+        })
+    }
+
+    /// The seed as a JSON object, with the time in seconds
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"seed\":{},\"nodes\":{},\"max_depth\":{},\"elapsed_secs\":{}}}",
+            json_string(&self.seed),
+            self.nodes,
+            self.max_depth,
+            self.elapsed.as_secs_f64()
+        )
+    }
+}
+
+impl std::fmt::Display for InconclusiveSeed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({} nodes, {} rows deep, {:.2?})",
+            self.seed, self.nodes, self.max_depth, self.elapsed
+        )
+    }
+}
+
+/// A duration written with a unit, as `500ms`, `5s`, `2m` or `1h`, or as bare seconds
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s
+        .find(|ch: char| !ch.is_ascii_digit() && ch != '.')
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let scale = match unit.trim() {
+        "ms" => 0.001,
+        "" | "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        _ => return Err(format!("unknown unit in {:?} (expected ms, s, m or h)", s)),
+    };
+    match number.parse::<f64>() {
+        Ok(number) if number.is_finite() => Ok(Duration::from_secs_f64(number * scale)),
+        _ => Err(format!(
+            "expected a duration such as 5s or 500ms, found {:?}",
+            s
+        )),
+    }
+}
+
+/// How much bigger a second try's budget is than the first's, written `2x` or `2`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryFactor(pub f64);
+
+impl std::str::FromStr for RetryFactor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = s.trim().strip_suffix('x').unwrap_or(s.trim());
+        match number.parse::<f64>() {
+            Ok(factor) if factor.is_finite() && factor > 1.0 => Ok(RetryFactor(factor)),
+            Ok(_) => Err(format!(
+                "a retry needs a bigger budget than the first try, so more than 1x, not {:?}",
+                s
+            )),
+            Err(_) => Err(format!("expected a factor such as 2x, found {:?}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for RetryFactor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x", self.0)
+    }
+}
+
+/// Searches a fair schedule keeps open per worker; a seed waits for one of them to finish
+/// before its search starts
+const OPEN_SEARCHES_PER_THREAD: usize = 16;
+
+/// The seeds sorted by how many boxes each is likely to lead to, most first: the product over
+/// the columns of the words completing each once the seed is placed (see
+/// `WordBox::column_completions`), which is 0 for a seed leaving some column without a word.
+/// The scores are counted in parallel; seeds scoring the same keep their order.
+pub fn productive_order<L, F>(lexicons: &L, seeds: Vec<String>, build: F) -> Vec<String>
+where
+    L: Lexicons + Sync,
+    F: Fn(&str) -> WordBox + Sync,
+{
+    let scores: Vec<f64> = (seeds.par_iter())
+        .map(|seed| build(seed).column_completions(lexicons))
+        .collect();
+    let mut ranked: Vec<(f64, String)> = scores.into_iter().zip(seeds).collect();
+    ranked.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    ranked.into_iter().map(|(_, seed)| seed).collect()
+}
+
+/// A completed box along with where and when it was found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundSolution {
+    pub word_box: WordBox,
+    pub seed: String,
+    pub elapsed: Duration,       // time since the start of the run
+    pub transpose_implied: bool, // the transpose is a different solution that was not searched for
+    pub required_cells: BTreeMap<char, Vec<(usize, usize)>>, // where each required letter appears
+    pub theme_words: Vec<String>, // the words of the box that are theme words
+    pub rare_words: Vec<String>, // the words of the box that count as rare
+    pub tiers: TierCounts,       // how many of its words each tier gave, if the lexicon has tiers
+    pub scrabble_score: Option<u32>, // the box's Scrabble score, if the run has letter values
+    pub distinct_letters: Option<usize>, // its different letters, if the run asks for a minimum
+    pub letter_counts: BTreeMap<char, usize>, // the cells holding each letter the run bounds
+    pub invalid_columns: Vec<(usize, String)>, // the columns that are not words, in a near miss
+    pub provenance: Option<Provenance>, // how to find the box again, if the run records it
+}
+
+impl FoundSolution {
+    /// Whether some columns of the box are not words (see `SolverConfig::allow_invalid_cols`)
+    pub fn is_near_miss(&self) -> bool {
+        !self.invalid_columns.is_empty()
+    }
+}
+
+/// The box with all of its rows, which it no longer shares with the search once held, and the
+/// words noted alongside; the cells holding required letters are left out, as they are few
+impl EstimatedSize for FoundSolution {
+    fn estimated_bytes(&self) -> usize {
+        let words = self.theme_words.estimated_bytes() + self.rare_words.estimated_bytes();
+        std::mem::size_of::<FoundSolution>() - std::mem::size_of::<WordBox>()
+            + self.word_box.estimated_bytes()
+            + (self.word_box.rows.iter().rev().skip(1))
+                .map(|row| shared_str_bytes(row))
+                .sum::<usize>()
+            + self.seed.capacity()
+            + words
+            - 2 * std::mem::size_of::<Vec<String>>()
+            + self.tiers.0.capacity() * std::mem::size_of::<usize>()
+            + (self.provenance.as_ref()).map_or(0, |p| {
+                p.estimated_bytes() - std::mem::size_of::<Provenance>()
+            })
+    }
+}
+
+/// Something that happened during a run, reported back to the calling thread
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunEvent {
+    Solution(FoundSolution),
+    SeedDone {
+        seed: String,
+        elapsed: Duration,
+        report: SearchReport, // how the seed's search ended, and what it took
+    },
+}
+
+/// Solve the box built by `build` from each seed, in parallel unless `options.threads == 1`.
+///
+/// Workers send their results over a bounded channel and the calling thread is the only
+/// consumer, so `on_event` can own stdout and any output files without locking. When the
+/// consumer falls behind, workers block instead of buffering without limit. The channel is
+/// drained before returning, so every solution found before a cancellation is delivered.
+pub fn run_seeds<L, F>(
+    lexicon: &L,
+    seeds: &[String],
+    build: F,
+    options: &RunOptions,
+    state: &RunState,
+    mut on_event: impl FnMut(RunEvent),
+) where
+    L: Lexicons + Sync,
+    F: Fn(&str) -> WordBox + Sync,
+{
+    let start = Instant::now();
+    let budget = options.seed_budget;
+    let (sender, receiver) = mpsc::sync_channel(options.channel_capacity);
+    let solver = SolverConfig {
+        cancellation: Some(state.cancellation_token()),
+        ..options.solver.clone()
+    };
+
+    // Solutions are reported as soon as they are found, unless the consumer is to put them in
+    // order: then it makes every decision that depends on the order itself
+    let ordered = options.deterministic && !options.count_only;
+
+    // Each seed word gets its own shuffle, whichever worker happens to solve it
+    let seeded = |index: usize| {
+        (solver.random_seed).map(|random_seed| SolverConfig {
+            random_seed: Some(sub_seed(random_seed, index)),
+            ..solver.clone()
+        })
+    };
+    let found = |solver: &SolverConfig, seed: &str, word_box: WordBox| {
+        let transpose_implied = solver.breaks_transpose_symmetry()
+            && word_box.has_transpose_twin()
+            && word_box.transpose() != word_box;
+        FoundSolution {
+            transpose_implied,
+            required_cells: solver.required_letters.cells(&word_box),
+            theme_words: (solver.theme_words.as_ref())
+                .map_or(vec![], |theme| theme.used(&word_box)),
+            rare_words: (solver.rare_words.as_ref()).map_or(vec![], |rare| rare.used(&word_box)),
+            tiers: TierCounts::of(&word_box, lexicon),
+            scrabble_score: (options.letter_values.as_ref())
+                .map(|values| word_box.scrabble_score(values)),
+            distinct_letters: (solver.min_distinct_letters > 0)
+                .then(|| word_box.distinct_letters()),
+            letter_counts: solver.letter_bounds.counts(&word_box),
+            invalid_columns: match solver.allow_invalid_cols {
+                0 => vec![],
+                _ => (word_box.invalid_columns(lexicon).into_iter())
+                    .map(|i| (i, word_box.column_line(i)))
+                    .collect(),
+            },
+            provenance: (options.provenance.as_ref())
+                .map(|run| run.record(&word_box, seed, lexicon)),
+            word_box,
+            seed: seed.to_string(),
+            elapsed: start.elapsed(),
+        }
+    };
+    // Report a solution of the seed at `index`, returning whether its search goes on
+    let emit = |sender: &mpsc::SyncSender<Tagged>,
+                solver: &SolverConfig,
+                (index, seed): (usize, &str),
+                duplicates: &AtomicUsize,
+                word_box: WordBox,
+                order: (usize, usize)| {
+        if ordered {
+            let event = RunEvent::Solution(found(solver, seed, word_box));
+            sender
+                .send(Tagged {
+                    seed: index,
+                    order,
+                    event,
+                })
+                .ok();
+        } else {
+            match admit(state, options, solver, &word_box) {
+                Admission::Duplicate => {
+                    duplicates.fetch_add(1, Ordering::Relaxed);
+                    return ControlFlow::Continue(());
+                }
+                Admission::LimitReached => return ControlFlow::Break(()),
+                Admission::Reported if options.count_only => {}
+                Admission::Reported => {
+                    let event = RunEvent::Solution(found(solver, seed, word_box));
+                    sender
+                        .send(Tagged {
+                            seed: index,
+                            order,
+                            event,
+                        })
+                        .ok();
+                }
+            }
+        }
+        if options.exhaustive && !state.is_cancelled() {
+            ControlFlow::Continue(())
+        } else {
+            ControlFlow::Break(())
+        }
+    };
+    let seed_done = |sender: &mpsc::SyncSender<Tagged>,
+                     solver: &SolverConfig,
+                     (index, seed): (usize, &str),
+                     mut report: SearchReport,
+                     duplicates: usize| {
+        report.stats.duplicates = duplicates;
+        // Going over the memory limit ends the whole run, not just this seed
+        if solver
+            .memory
+            .as_ref()
+            .is_some_and(|gauge| gauge.is_stopped())
+        {
+            state.cancel();
+        }
+        state.seeds_done.fetch_add(1, Ordering::Relaxed);
+        state.nodes.fetch_add(report.stats.nodes, Ordering::Relaxed);
+        let done = RunEvent::SeedDone {
+            seed: seed.to_string(),
+            elapsed: start.elapsed(),
+            report,
+        };
+        sender
+            .send(Tagged {
+                seed: index,
+                order: (usize::MAX, 0),
+                event: done,
+            })
+            .ok();
+    };
+    let solve_seed = |sender: &mut mpsc::SyncSender<Tagged>, (index, seed): (usize, &String)| {
+        if state.is_cancelled() {
+            return;
+        }
+        let seeded = seeded(index);
+        let solver = seeded.as_ref().unwrap_or(&solver);
+        let budgeted = (budget.is_limited()).then(|| budget.limit(solver, 1, true));
+        let solver = budgeted.as_ref().unwrap_or(solver);
+        let duplicates = AtomicUsize::new(0);
+        let emit = |sender: &mpsc::SyncSender<Tagged>, word_box: WordBox, order: (usize, usize)| {
+            emit(sender, solver, (index, seed), &duplicates, word_box, order)
+        };
+        let report = if options.split_depth == 0 || options.threads == 1 {
+            let mut solutions = 0;
+            solve_word_box_all(build(seed), lexicon, solver, |word_box| {
+                solutions += 1;
+                emit(sender, word_box, (0, solutions))
+            })
+        } else {
+            // A subtree that stops the seed's search (its first solution, unless exhaustive)
+            // cancels its siblings through the seed's own token; not when the solutions are put in
+            // order, since the first in order need not be the first found
+            let seed_token = state.cancellation_token().child();
+            let solver = SolverConfig {
+                cancellation: Some(seed_token.clone()),
+                ..solver.clone()
+            };
+            let (subtrees, split) =
+                split_word_box(build(seed), lexicon, &solver, options.split_depth);
+            // The subtrees share out the seed's node budget, not its time
+            let solver = match budget.max_nodes {
+                Some(_) => SolverConfig {
+                    max_nodes: budget.limit(&solver, subtrees.len(), false).max_nodes,
+                    ..solver
+                },
+                None => solver,
+            };
+            let report = subtrees
+                .into_par_iter()
+                .enumerate()
+                .map_with(sender.clone(), |sender, (subtree, wb)| {
+                    let mut solutions = 0;
+                    solve_word_box_all(wb, lexicon, &solver, |word_box| {
+                        solutions += 1;
+                        let flow = emit(sender, word_box, (subtree, solutions));
+                        if flow.is_break() && !ordered {
+                            seed_token.cancel();
+                        }
+                        flow
+                    })
+                })
+                .reduce(SearchReport::default, SearchReport::merge)
+                .merge(split);
+            match report.outcome {
+                Outcome::Cancelled if !state.is_cancelled() => SearchReport {
+                    outcome: Outcome::Stopped,
+                    ..report
+                },
+                _ => report,
+            }
+        };
+        seed_done(
+            sender,
+            solver,
+            (index, seed),
+            report,
+            duplicates.into_inner(),
+        );
+    };
+
+    // Under a fair schedule the searches of the first seeds are opened together and take
+    // turns, in parallel, until each is over and the next seed's search takes its place
+    let fair = options.schedule == Schedule::Fair && solver.can_pause();
+    let search_fairly = |sender: &mpsc::SyncSender<Tagged>| {
+        // A seed's time is that of its turns, checked between them, not a deadline
+        let configs: Vec<Option<SolverConfig>> = (0..seeds.len())
+            .map(|index| match (seeded(index), budget.max_nodes) {
+                (seeded, Some(_)) => {
+                    Some(budget.limit(seeded.as_ref().unwrap_or(&solver), 1, false))
+                }
+                (seeded, None) => seeded,
+            })
+            .collect();
+        let config = |index: usize| configs[index].as_ref().unwrap_or(&solver);
+        let capacity = match options.threads {
+            1 => OPEN_SEARCHES_PER_THREAD,
+            _ => OPEN_SEARCHES_PER_THREAD * parallel::current_num_threads(),
+        };
+        let mut waiting = seeds.iter().enumerate();
+        let mut open: Vec<OpenSeed<L>> = vec![];
+        loop {
+            while open.len() < capacity && !state.is_cancelled() {
+                let Some((index, seed)) = waiting.next() else {
+                    break;
+                };
+                open.push(OpenSeed {
+                    index,
+                    seed,
+                    search: RowSearch::new(build(seed), lexicon, config(index)),
+                    duplicates: AtomicUsize::new(0),
+                    solutions: 0,
+                });
+            }
+            if open.is_empty() {
+                break;
+            }
+            let turn = |sender: &mpsc::SyncSender<Tagged>, seed: &mut OpenSeed<L>| {
+                let solutions = &mut seed.solutions;
+                let (index, word, duplicates) = (seed.index, seed.seed.as_str(), &seed.duplicates);
+                seed.search.resume(options.slice, |word_box| {
+                    *solutions += 1;
+                    let order = (0, *solutions);
+                    emit(
+                        sender,
+                        config(index),
+                        (index, word),
+                        duplicates,
+                        word_box,
+                        order,
+                    )
+                });
+            };
+            match options.threads {
+                1 => open.iter_mut().for_each(|seed| turn(sender, seed)),
+                _ => (open.par_iter_mut())
+                    .for_each_with(sender.clone(), |sender, seed| turn(sender, seed)),
+            }
+            let out_of_time = |seed: &OpenSeed<L>| {
+                (budget.timeout)
+                    .is_some_and(|timeout| seed.search.report().stats.elapsed >= timeout)
+            };
+            let (done, still_open): (Vec<_>, Vec<_>) =
+                (open.into_iter()).partition(|seed| seed.search.is_finished() || out_of_time(seed));
+            open = still_open;
+            for seed in done {
+                let finished = seed.search.is_finished();
+                let mut report = seed.search.into_report();
+                if !finished {
+                    report.outcome = Outcome::BudgetExhausted;
+                }
+                let duplicates = seed.duplicates.into_inner();
+                let id = (seed.index, seed.seed.as_str());
+                seed_done(sender, config(seed.index), id, report, duplicates);
+            }
+        }
+    };
+
+    std::thread::scope(|scope| {
+        scope.spawn(move || {
+            let mut sender = sender;
+            if options.threads == 1 {
+                match fair {
+                    true => search_fairly(&sender),
+                    false => (seeds.iter())
+                        .enumerate()
+                        .for_each(|seed| solve_seed(&mut sender, seed)),
+                }
+            } else {
+                let pool = parallel::ThreadPoolBuilder::new()
+                    .num_threads(options.threads)
+                    .build()
+                    .expect("Could not build thread pool");
+                pool.install(|| match fair {
+                    true => search_fairly(&sender),
+                    false => seeds
+                        .par_iter()
+                        .enumerate()
+                        .for_each_with(sender, |sender, seed| solve_seed(sender, seed)),
+                });
+            }
+        });
+
+        if !ordered {
+            for tagged in receiver {
+                on_event(tagged.event);
+            }
+            return;
+        }
+        // The events of each seed, held until every seed before it is done
+        let mut held: BTreeMap<usize, Vec<Tagged>> = BTreeMap::new();
+        let gauge = solver.memory.as_deref();
+        let mut next = 0;
+        for tagged in receiver {
+            let done = matches!(tagged.event, RunEvent::SeedDone { .. });
+            if let (Some(gauge), RunEvent::Solution(solution)) = (gauge, &tagged.event) {
+                gauge.add(Consumer::Buffered, solution.estimated_bytes());
+            }
+            held.entry(tagged.seed).or_default().push(tagged);
+            while done && held.get(&next).is_some_and(|events| has_finished(events)) {
+                let events = held.remove(&next).unwrap_or_default();
+                if let Some(gauge) = gauge {
+                    gauge.sub(Consumer::Buffered, held_bytes(&events));
+                }
+                deliver_in_order(events, state, options, &solver, &mut on_event);
+                next += 1;
+            }
+        }
+        // Seeds skipped once the run was cancelled leave gaps; what the rest found still counts
+        for events in held.into_values() {
+            if let Some(gauge) = gauge {
+                gauge.sub(Consumer::Buffered, held_bytes(&events));
+            }
+            deliver_in_order(events, state, options, &solver, &mut on_event);
+        }
+    });
+}
+
+/// The search of a seed under a fair schedule, between its turns
+struct OpenSeed<'a, L> {
+    index: usize,
+    seed: &'a String,
+    search: RowSearch<'a, L>,
+    duplicates: AtomicUsize, // solutions dropped as repeats of ones reported before
+    solutions: usize,        // solutions found, in the order of the search
+}
+
+/// An event along with where it falls in the order of `RunOptions::deterministic`: the index of
+/// its seed, then the subtree of the seed's search it came from and how many solutions that
+/// subtree had found with it; a seed's `SeedDone` comes after all of its solutions
+struct Tagged {
+    seed: usize,
+    order: (usize, usize),
+    event: RunEvent,
+}
+
+fn has_finished(events: &[Tagged]) -> bool {
+    (events.iter()).any(|tagged| matches!(tagged.event, RunEvent::SeedDone { .. }))
+}
+
+/// Estimated bytes of the solutions among `events`
+fn held_bytes(events: &[Tagged]) -> usize {
+    (events.iter())
+        .map(|tagged| match &tagged.event {
+            RunEvent::Solution(solution) => solution.estimated_bytes(),
+            RunEvent::SeedDone { .. } => 0,
+        })
+        .sum()
+}
+
+/// What becomes of a solution once it is found
+enum Admission {
+    Duplicate,    // an identical solution was reported before
+    LimitReached, // the solution limit has been reached, so the run is over
+    Reported,     // counted, tallied and reported
+}
+
+/// Decide whether a solution is reported, and if so count it and tally its words and letters
+fn admit(
+    state: &RunState,
+    options: &RunOptions,
+    solver: &SolverConfig,
+    word_box: &WordBox,
+) -> Admission {
+    if options.dedup {
+        let key = if solver.breaks_transpose_symmetry() {
+            word_box.canonical().row_words()
+        } else {
+            word_box.row_words()
+        };
+        if !state.first_sighting(key) {
+            return Admission::Duplicate;
+        }
+        if let Some(gauge) = &solver.memory {
+            gauge.set(Consumer::Dedup, state.seen_bytes.load(Ordering::Relaxed));
+        }
+    }
+    if !state.record_solution(options.limit) {
+        return Admission::LimitReached;
+    }
+    if let Some(usage) = &options.word_usage {
+        usage.record(word_box);
+    }
+    if let Some(heatmap) = &options.letter_heatmap {
+        heatmap.record(word_box);
+    }
+    Admission::Reported
+}
+
+/// Hand the events of one seed to the consumer in search order, settling duplicates and the
+/// limit in that order. A run that is not exhaustive reports the first solution that repeats none
+/// reported before, out of those the search stopped at: the seed's first, or each subtree's.
+fn deliver_in_order(
+    mut events: Vec<Tagged>,
+    state: &RunState,
+    options: &RunOptions,
+    solver: &SolverConfig,
+    on_event: &mut impl FnMut(RunEvent),
+) {
+    events.sort_by_key(|tagged| tagged.order);
+    let (mut reported, mut duplicates) = (0, 0);
+    for tagged in events {
+        match tagged.event {
+            RunEvent::Solution(solution) if options.exhaustive || reported == 0 => {
+                match admit(state, options, solver, &solution.word_box) {
+                    Admission::Duplicate => duplicates += 1,
+                    Admission::LimitReached => {}
+                    Admission::Reported => {
+                        reported += 1;
+                        on_event(RunEvent::Solution(solution));
+                    }
+                }
+            }
+            RunEvent::Solution(_) => {}
+            RunEvent::SeedDone {
+                seed,
+                elapsed,
+                mut report,
+            } => {
+                report.stats.duplicates = duplicates;
+                on_event(RunEvent::SeedDone {
+                    seed,
+                    elapsed,
+                    report,
+                });
+            }
+        }
+    }
+}
+
+/// The random seed for the search from the seed word at `index`, mixed from the run's seed
+/// (a splitmix64 step) so that neighbouring words get unrelated shuffles
+fn sub_seed(seed: u64, index: usize) -> u64 {
+    let mut z = seed.wrapping_add((index as u64 + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,270 @@
+use crate::frequency::{RareWords, WordFrequencies};
+use crate::lexicon::Lexicon;
+use crate::rank::LetterScores;
+use crate::scrabble::LetterValues;
+use crate::theme::ThemeWords;
+use crate::tiers::TierCounts;
+use crate::wordbox::WordBox;
+use std::fmt::Display;
+
+/// Something to rank boxes by, higher first. The built-in metrics are scorers, and so is any
+/// `Fn(&WordBox, &dyn Lexicon) -> f64`, so a library user can rank by an objective of their own.
+/// A score that is not finite, NaN or an overflow to infinity, ranks below every other (see
+/// `rank_value`).
+pub trait Scorer: Send + Sync {
+    /// How good `wb` is; `lexicon` is the one its rows were drawn from
+    fn score(&self, wb: &WordBox, lexicon: &dyn Lexicon) -> f64;
+}
+
+impl std::fmt::Debug for dyn Scorer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Scorer")
+    }
+}
+
+impl<F: Fn(&WordBox, &dyn Lexicon) -> f64 + Send + Sync> Scorer for F {
+    fn score(&self, wb: &WordBox, lexicon: &dyn Lexicon) -> f64 {
+        self(wb, lexicon)
+    }
+}
+
+/// A score as it is ranked: itself if it is finite, else below every finite score
+pub fn rank_value(score: f64) -> f64 {
+    match score.is_finite() {
+        true => score,
+        false => f64::NEG_INFINITY,
+    }
+}
+
+impl Scorer for LetterScores {
+    fn score(&self, wb: &WordBox, _: &dyn Lexicon) -> f64 {
+        LetterScores::score(self, wb)
+    }
+}
+
+impl Scorer for WordFrequencies {
+    fn score(&self, wb: &WordBox, _: &dyn Lexicon) -> f64 {
+        self.total(wb)
+    }
+}
+
+impl Scorer for ThemeWords {
+    fn score(&self, wb: &WordBox, _: &dyn Lexicon) -> f64 {
+        self.count(wb) as f64
+    }
+}
+
+impl Scorer for RareWords {
+    fn score(&self, wb: &WordBox, _: &dyn Lexicon) -> f64 {
+        self.used(wb).len() as f64
+    }
+}
+
+impl Scorer for LetterValues {
+    fn score(&self, wb: &WordBox, _: &dyn Lexicon) -> f64 {
+        wb.scrabble_score(self) as f64
+    }
+}
+
+/// How many different letters a box uses (see `WordBox::distinct_letters`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DistinctLetters;
+
+impl Scorer for DistinctLetters {
+    fn score(&self, wb: &WordBox, _: &dyn Lexicon) -> f64 {
+        wb.distinct_letters() as f64
+    }
+}
+
+/// How few of a box's words come from the less preferred tiers of the lexicon (see
+/// `TierCounts::score`); every word, across or down, is looked up in the lexicon given
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TierPreference;
+
+impl Scorer for TierPreference {
+    fn score(&self, wb: &WordBox, lexicon: &dyn Lexicon) -> f64 {
+        let tiers = lexicon.tiers();
+        if tiers <= 1 {
+            return 0.0;
+        }
+        let mut counts = vec![0; tiers];
+        for word in wb.words() {
+            counts[lexicon.tier(word).min(tiers - 1)] += 1;
+        }
+        TierCounts(counts).score()
+    }
+}
+
+/// A built-in metric a score formula can weigh
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    /// How common the box's letters are (see `LetterScores`)
+    Score,
+    /// The summed frequency of the box's words (see `WordFrequencies::total`)
+    Frequency,
+    /// How many theme words it uses
+    Theme,
+    /// How many rare words it uses; give it a negative weight to avoid them
+    Rare,
+    /// How few words it takes from the less preferred tiers (see `TierPreference`)
+    Tier,
+    /// The Scrabble value of its letters
+    Scrabble,
+    /// How many different letters it uses
+    DistinctLetters,
+}
+
+impl Metric {
+    pub const ALL: [Metric; 7] = [
+        Metric::Score,
+        Metric::Frequency,
+        Metric::Theme,
+        Metric::Rare,
+        Metric::Tier,
+        Metric::Scrabble,
+        Metric::DistinctLetters,
+    ];
+}
+
+impl Display for Metric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Metric::Score => write!(f, "score"),
+            Metric::Frequency => write!(f, "frequency"),
+            Metric::Theme => write!(f, "theme"),
+            Metric::Rare => write!(f, "rare"),
+            Metric::Tier => write!(f, "tier"),
+            Metric::Scrabble => write!(f, "scrabble"),
+            Metric::DistinctLetters => write!(f, "distinct-letters"),
+        }
+    }
+}
+
+impl std::str::FromStr for Metric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Metric::ALL
+            .into_iter()
+            .find(|metric| metric.to_string() == s)
+            .ok_or_else(|| {
+                let names: Vec<String> = Metric::ALL.iter().map(|m| m.to_string()).collect();
+                format!(
+                    "unknown metric {:?} (expected one of {})",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// A weighted sum of metrics, written e.g. `0.7*frequency + 0.3*theme`; a metric without a
+/// weight weighs 1, and `- rare` subtracts one
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreFormula {
+    pub terms: Vec<(f64, Metric)>,
+}
+
+impl ScoreFormula {
+    /// Whether some term of the formula weighs `metric`
+    pub fn uses(&self, metric: Metric) -> bool {
+        self.terms.iter().any(|&(_, used)| used == metric)
+    }
+
+    /// A formula from a TOML file: the `score` key of its top level, e.g.
+    /// `score = "0.7*frequency + 0.3*theme"`; `#` starts a comment
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.starts_with('[') {
+                break;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            if key.trim().trim_matches('"') != "score" {
+                continue;
+            }
+            let value = value.trim();
+            let value = (value.strip_prefix('"').and_then(|v| v.strip_suffix('"')))
+                .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+                .unwrap_or(value);
+            return value.parse();
+        }
+        Err("no `score = \"...\"` line at the top level".to_string())
+    }
+}
+
+impl std::str::FromStr for ScoreFormula {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // A `-` right after a letter belongs to a name like `distinct-letters` (or a weight
+        // like `1e-3`); every other one starts a negative term
+        let mut signed = String::new();
+        let mut after_letter = false;
+        for ch in s.chars() {
+            if ch == '-' && !after_letter {
+                signed.push('+');
+            }
+            signed.push(ch);
+            after_letter = ch.is_alphabetic();
+        }
+        let mut terms = vec![];
+        for term in signed.split('+').map(str::trim) {
+            if term.is_empty() {
+                // The gap before a leading sign
+                continue;
+            }
+            let (sign, term) = match term.strip_prefix('-') {
+                Some(term) => (-1.0, term.trim()),
+                None => (1.0, term),
+            };
+            let (weight, metric) = match term.split_once('*') {
+                Some((weight, metric)) => {
+                    let weight: f64 = (weight.trim().parse().ok())
+                        .filter(|weight: &f64| weight.is_finite())
+                        .ok_or_else(|| format!("{:?} is not a weight in {:?}", weight.trim(), s))?;
+                    (weight, metric.trim())
+                }
+                None => (1.0, term),
+            };
+            terms.push((sign * weight, metric.parse()?));
+        }
+        if terms.is_empty() {
+            return Err(format!("the formula {:?} has no terms", s));
+        }
+        Ok(ScoreFormula { terms })
+    }
+}
+
+impl Display for ScoreFormula {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, (weight, metric)) in self.terms.iter().enumerate() {
+            match (i, weight.is_sign_negative()) {
+                (0, false) => {}
+                (0, true) => write!(f, "-")?,
+                (_, false) => write!(f, " + ")?,
+                (_, true) => write!(f, " - ")?,
+            }
+            write!(f, "{}*{}", weight.abs(), metric)?;
+        }
+        Ok(())
+    }
+}
+
+/// Scorers weighted and summed, e.g. the metrics of a `ScoreFormula`. A score that is not
+/// finite makes the sum the worst there is.
+#[derive(Default)]
+pub struct WeightedScore<'a> {
+    pub terms: Vec<(f64, Box<dyn Scorer + 'a>)>,
+}
+
+impl Scorer for WeightedScore<'_> {
+    fn score(&self, wb: &WordBox, lexicon: &dyn Lexicon) -> f64 {
+        let sum = (self.terms.iter())
+            .map(|(weight, scorer)| weight * scorer.score(wb, lexicon))
+            .sum();
+        rank_value(sum)
+    }
+}
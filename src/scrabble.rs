@@ -0,0 +1,189 @@
+use crate::digraphs::{cell_text, Tile};
+use std::collections::HashMap;
+use std::fmt::Display;
+
+/// The values of the standard English Scrabble tiles
+const ENGLISH: [(&str, u32); 26] = [
+    ("a", 1),
+    ("b", 3),
+    ("c", 3),
+    ("d", 2),
+    ("e", 1),
+    ("f", 4),
+    ("g", 2),
+    ("h", 4),
+    ("i", 1),
+    ("j", 8),
+    ("k", 5),
+    ("l", 1),
+    ("m", 3),
+    ("n", 1),
+    ("o", 1),
+    ("p", 3),
+    ("q", 10),
+    ("r", 1),
+    ("s", 1),
+    ("t", 1),
+    ("u", 1),
+    ("v", 4),
+    ("w", 4),
+    ("x", 8),
+    ("y", 4),
+    ("z", 10),
+];
+
+/// A line of a letter value table that is not a letter and its value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LetterValuesError {
+    Malformed { line: usize, text: String },
+}
+
+impl Display for LetterValuesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LetterValuesError::Malformed { line, text } => write!(
+                f,
+                "line {}: expected a letter, `=` and a whole number, found {:?}",
+                line, text
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LetterValuesError {}
+
+/// What each tile is worth to a box's Scrabble score, keyed on the letters it shows. The
+/// standard English values are the default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LetterValues {
+    values: HashMap<String, u32>,
+}
+
+impl Default for LetterValues {
+    fn default() -> Self {
+        LetterValues {
+            values: (ENGLISH.iter())
+                .map(|&(letter, value)| (letter.to_string(), value))
+                .collect(),
+        }
+    }
+}
+
+impl LetterValues {
+    /// Load a table of letter values for another alphabet from a TOML file (see `from_toml`)
+    pub fn load(filename: &str) -> Result<Self, LetterValuesError> {
+        let text = std::fs::read_to_string(filename).expect("Could not open letter value file");
+        Self::from_toml(&text)
+    }
+
+    /// A table of `letter = value` lines in the TOML way, e.g. `ä = 6` or `"ch" = 5` for a
+    /// multi-letter tile; keys may be quoted, `#` starts a comment and `[table]` headers are
+    /// skipped. Only the letters listed are worth anything: the table replaces the English
+    /// values rather than adding to them.
+    pub fn from_toml(text: &str) -> Result<Self, LetterValuesError> {
+        let mut values = HashMap::new();
+        for (i, line) in text.lines().enumerate() {
+            let text = line.split('#').next().unwrap_or("").trim();
+            if text.is_empty() || (text.starts_with('[') && text.ends_with(']')) {
+                continue;
+            }
+            let malformed = || LetterValuesError::Malformed {
+                line: i + 1,
+                text: line.trim().to_string(),
+            };
+            let (key, value) = text.split_once('=').ok_or_else(malformed)?;
+            let key = key.trim();
+            let key = (key.strip_prefix('"').and_then(|key| key.strip_suffix('"')))
+                .or_else(|| {
+                    key.strip_prefix('\'')
+                        .and_then(|key| key.strip_suffix('\''))
+                })
+                .unwrap_or(key);
+            if key.is_empty() || key.chars().any(char::is_whitespace) {
+                return Err(malformed());
+            }
+            let value: u32 = value.trim().parse().map_err(|_| malformed())?;
+            values.insert(key.to_lowercase(), value);
+        }
+        Ok(LetterValues { values })
+    }
+
+    /// What a cell is worth: its letter's value, or for a multi-letter tile the value listed
+    /// for the tile, else its letters' values summed. Letters the table leaves out are worth 0.
+    pub fn value(&self, cell: char) -> u32 {
+        let text = cell_text(cell);
+        if let Some(&value) = self.values.get(&text) {
+            return value;
+        }
+        match Tile::of(cell) {
+            Some(tile) => (tile.as_str().chars())
+                .map(|letter| self.values.get(&letter.to_string()).copied().unwrap_or(0))
+                .sum(),
+            None => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::digraphs::Digraphs;
+    use crate::wordbox::WordBox;
+
+    fn filled(row_dim: usize, col_dim: usize, symmetric: bool, rows: &[String]) -> WordBox {
+        (rows.iter()).fold(WordBox::new(row_dim, col_dim, symmetric), |wb, row| {
+            wb.add_word(row.clone()).unwrap()
+        })
+    }
+
+    fn rows(rows: &[&str]) -> Vec<String> {
+        rows.iter().map(|row| row.to_string()).collect()
+    }
+
+    #[test]
+    fn each_cell_counts_once() {
+        let values = LetterValues::default();
+        // c a t / a c e / t e a: 3 + 1 + 1, 1 + 3 + 1, 1 + 1 + 1
+        let wb = filled(3, 3, true, &rows(&["cat", "ace", "tea"]));
+        assert_eq!(wb.scrabble_score(&values), 13);
+        // f a s t / a r e a / d e a n: 4 + 1 + 1 + 1, 1 + 1 + 1 + 1, 2 + 1 + 1 + 1
+        let wb = filled(3, 4, false, &rows(&["fast", "area", "dean"]));
+        assert_eq!(wb.scrabble_score(&values), 16);
+    }
+
+    #[test]
+    fn tiles_are_worth_their_letters_unless_listed() {
+        let digraphs: Digraphs = "qu".parse().unwrap();
+        let cells: Vec<String> = (["qua", "id"].iter())
+            .map(|word| digraphs.encode(word))
+            .collect();
+        let wb = filled(2, 2, false, &cells);
+        // qu is worth q and u, 10 + 1
+        assert_eq!(wb.scrabble_score(&LetterValues::default()), 11 + 1 + 1 + 2);
+        let values = LetterValues::from_toml("\"qu\" = 8\na = 1\nd = 2\ni = 1").unwrap();
+        assert_eq!(wb.scrabble_score(&values), 8 + 1 + 1 + 2);
+    }
+
+    #[test]
+    fn tables_replace_the_english_values() {
+        let values = LetterValues::from_toml(
+            "# German tiles\n[values]\nä = 6\n'ch' = 5  # one tile\nE = 1\n",
+        )
+        .unwrap();
+        assert_eq!(values.value('ä'), 6);
+        assert_eq!(values.value('e'), 1);
+        assert_eq!(values.value('z'), 0);
+        let ch = Digraphs::new([Tile::new("ch").unwrap()]).encode("ch");
+        assert_eq!(values.value(ch.chars().next().unwrap()), 5);
+
+        for (text, line) in [("ä 6", 1), ("a = 1\nb = -3", 2), ("x y = 2", 1), ("= 4", 1)] {
+            let err = LetterValues::from_toml(text).unwrap_err();
+            assert!(
+                matches!(err, LetterValuesError::Malformed { line: found, .. } if found == line),
+                "{:?}: {:?}",
+                text,
+                err
+            );
+        }
+    }
+}
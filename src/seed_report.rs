@@ -0,0 +1,147 @@
+use crate::digraphs;
+use crate::solver::{Outcome, SearchReport};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// How the search of one seed went
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeedRow {
+    pub seed: String,
+    pub solutions: usize,
+    pub nodes: usize,
+    pub elapsed: Duration,
+    pub outcome: Outcome,
+}
+
+impl SeedRow {
+    /// Whether the seed's search ran out of its budget before it could finish
+    pub fn is_truncated(&self) -> bool {
+        self.outcome == Outcome::BudgetExhausted
+    }
+}
+
+/// Which seeds of a run were productive: the solutions, nodes and time of each seed's search,
+/// recorded as the seeds are done. A seed searched again, as --retry-inconclusive does, keeps
+/// the solutions and outcome of its last search and adds up the nodes and time of both.
+#[derive(Debug, Clone, Default)]
+pub struct SeedReport {
+    rows: HashMap<String, SeedRow>,
+}
+
+impl SeedReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that the search of `seed` ended with `report`
+    pub fn record(&mut self, seed: &str, report: &SearchReport) {
+        self.add(SeedRow {
+            seed: seed.to_string(),
+            solutions: report.solutions,
+            nodes: report.stats.nodes,
+            elapsed: report.stats.elapsed,
+            outcome: report.outcome,
+        });
+    }
+
+    fn add(&mut self, row: SeedRow) {
+        match self.rows.get_mut(&row.seed) {
+            Some(kept) => {
+                kept.solutions = row.solutions;
+                kept.nodes += row.nodes;
+                kept.elapsed += row.elapsed;
+                kept.outcome = row.outcome;
+            }
+            None => {
+                self.rows.insert(row.seed.clone(), row);
+            }
+        }
+    }
+
+    /// The seeds of both reports, such as those of two workers; `other` counts as the later
+    pub fn merge(mut self, other: SeedReport) -> SeedReport {
+        for (_, row) in other.rows {
+            self.add(row);
+        }
+        self
+    }
+
+    /// Number of seeds recorded
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// The seeds, the most solutions first, then the fewest nodes, then alphabetically
+    pub fn sorted(&self) -> Vec<&SeedRow> {
+        let mut rows: Vec<&SeedRow> = self.rows.values().collect();
+        rows.sort_by(|a, b| {
+            (b.solutions.cmp(&a.solutions))
+                .then(a.nodes.cmp(&b.nodes))
+                .then_with(|| a.seed.cmp(&b.seed))
+        });
+        rows
+    }
+
+    /// The report as CSV, one row per seed as `sorted` orders them, then a row of totals, whose
+    /// truncated column counts the seeds truncated, and a row of medians
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("seed,solutions,nodes,seconds,truncated,outcome\n");
+        let rows = self.sorted();
+        for row in &rows {
+            csv += &format!(
+                "{},{},{},{:.6},{},{}\n",
+                digraphs::decode(&row.seed),
+                row.solutions,
+                row.nodes,
+                row.elapsed.as_secs_f64(),
+                row.is_truncated(),
+                outcome_name(row.outcome)
+            );
+        }
+        let solutions: Vec<f64> = rows.iter().map(|row| row.solutions as f64).collect();
+        let nodes: Vec<f64> = rows.iter().map(|row| row.nodes as f64).collect();
+        let seconds: Vec<f64> = (rows.iter()).map(|row| row.elapsed.as_secs_f64()).collect();
+        csv += &format!(
+            "total,{},{},{:.6},{},\n",
+            rows.iter().map(|row| row.solutions).sum::<usize>(),
+            rows.iter().map(|row| row.nodes).sum::<usize>(),
+            seconds.iter().sum::<f64>(),
+            rows.iter().filter(|row| row.is_truncated()).count()
+        );
+        csv += &format!(
+            "median,{},{},{:.6},,\n",
+            median(solutions),
+            median(nodes),
+            median(seconds)
+        );
+        csv
+    }
+}
+
+/// How a seed's search ended, as the report's outcome column writes it
+fn outcome_name(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Exhausted => "exhausted",
+        Outcome::Stopped => "stopped",
+        Outcome::Cancelled => "cancelled",
+        Outcome::BudgetExhausted => "budget",
+        Outcome::Pruned => "pruned",
+    }
+}
+
+/// The middle value, or the mean of the two middle values of an even number; 0 for none
+fn median(mut values: Vec<f64>) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    match values.len() % 2 {
+        0 => (values[mid - 1] + values[mid]) / 2.0,
+        _ => values[mid],
+    }
+}
@@ -0,0 +1,2037 @@
+use crate::blocks;
+use crate::cancel::CancellationToken;
+use crate::cube::WordCube;
+use crate::dead_ends::{Cause, DeadEnds};
+use crate::frequency::RareWords;
+use crate::frontier::{Beam, BestFirst, BreadthFirst, Frontier, Node};
+use crate::grid::{Grid, Refusal, Slot};
+use crate::letters::{LetterBounds, RequiredLetters, VowelRule};
+use crate::lexicon::{self, Lexicon, Lexicons, WordId};
+use crate::memory::{Consumer, EstimatedSize, MemoryAction, MemoryGauge, Meter};
+use crate::nogood::NogoodCache;
+use crate::query_cache::CachedLexicons;
+use crate::scoring::{rank_value, Scorer};
+use crate::spill::{SpillOptions, SpillingBreadthFirst};
+use crate::stats::{CorpusFrequency, SearchStats};
+use crate::template::Line;
+use crate::theme::ThemeWords;
+use crate::tiers::line_tier;
+use crate::trace::{SearchTrace, TraceKind};
+use crate::wordbox::{NextColumns, WordBox};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Display;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// The order in which partial boxes are expanded.
+///
+/// Depth-first keeps at most (rows × branching factor) boxes in memory and reaches complete
+/// boxes quickly. Breadth-first finishes each row count before moving on, so its frontier holds
+/// an entire level of the search tree at once, which grows exponentially with the number of rows.
+/// Best-first always expands the highest-scoring box (see `WordBox::score`), which tends to
+/// reach a first solution sooner on sparse grids; its heap can grow as large as breadth-first's.
+/// All orders visit exactly the same boxes and find the same solutions, in a different order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SearchOrder {
+    #[default]
+    DepthFirst,
+    BreadthFirst,
+    BestFirst,
+}
+
+impl SearchOrder {
+    pub const ALL: [SearchOrder; 3] = [
+        SearchOrder::DepthFirst,
+        SearchOrder::BreadthFirst,
+        SearchOrder::BestFirst,
+    ];
+}
+
+impl Display for SearchOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SearchOrder::DepthFirst => write!(f, "dfs"),
+            SearchOrder::BreadthFirst => write!(f, "bfs"),
+            SearchOrder::BestFirst => write!(f, "best"),
+        }
+    }
+}
+
+impl std::str::FromStr for SearchOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SearchOrder::ALL
+            .into_iter()
+            .find(|order| order.to_string() == s)
+            .ok_or_else(|| {
+                let names: Vec<String> = SearchOrder::ALL.iter().map(|o| o.to_string()).collect();
+                format!(
+                    "unknown search order {:?} (expected one of {})",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// How the solver picks the next slot to fill
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Heuristic {
+    /// Always fill the next row, top to bottom
+    #[default]
+    NextRow,
+    /// Fill whichever open row or column has the fewest candidate words (always depth-first;
+    /// boxes with blocked cells are still filled row by row)
+    MostConstrained,
+}
+
+impl Heuristic {
+    pub const ALL: [Heuristic; 2] = [Heuristic::NextRow, Heuristic::MostConstrained];
+}
+
+impl Display for Heuristic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Heuristic::NextRow => write!(f, "rows"),
+            Heuristic::MostConstrained => write!(f, "mcv"),
+        }
+    }
+}
+
+impl std::str::FromStr for Heuristic {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Heuristic::ALL
+            .into_iter()
+            .find(|heuristic| heuristic.to_string() == s)
+            .ok_or_else(|| {
+                let names: Vec<String> = Heuristic::ALL.iter().map(|h| h.to_string()).collect();
+                format!(
+                    "unknown heuristic {:?} (expected one of {})",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// The order in which the candidate words for a slot are tried
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ValueOrder {
+    /// The order the lexicon returns them in
+    #[default]
+    Lexicon,
+    /// Words leaving the most completions for the crossing slots first; costs extra counting
+    LeastConstraining,
+    /// Words whose letters are most common at the positions where they cross the other slots
+    /// first, by the letter frequencies of the lexicons themselves, whatever their language;
+    /// costs next to nothing, but needs `SolverConfig::corpus_frequency`
+    CorpusFrequency,
+}
+
+impl ValueOrder {
+    pub const ALL: [ValueOrder; 3] = [
+        ValueOrder::Lexicon,
+        ValueOrder::LeastConstraining,
+        ValueOrder::CorpusFrequency,
+    ];
+}
+
+impl Display for ValueOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValueOrder::Lexicon => write!(f, "lexicon"),
+            ValueOrder::LeastConstraining => write!(f, "lcv"),
+            ValueOrder::CorpusFrequency => write!(f, "corpus-frequency"),
+        }
+    }
+}
+
+impl std::str::FromStr for ValueOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ValueOrder::ALL
+            .into_iter()
+            .find(|order| order.to_string() == s)
+            .ok_or_else(|| {
+                let names: Vec<String> = ValueOrder::ALL.iter().map(|o| o.to_string()).collect();
+                format!(
+                    "unknown value order {:?} (expected one of {})",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// Nodes a search expands between reports of its frontier to the memory gauge; the first node
+/// is reported too, so that every search checks the limit at least once
+const MEMORY_CHECK_INTERVAL: usize = 1024;
+
+// Reasons a box is ruled out, named as the dead-end counts give or look for them too
+const REPEATS_WORD: &str = "it repeats a word";
+const NO_DIAGONAL: &str = "a rectangle has no diagonal";
+const FORBIDS_WORD: &str = "it holds a forbidden word";
+const REPEATS_LETTER: &str = "it repeats a letter";
+const LACKS_LETTER: &str = "it lacks a required letter";
+const LETTER_GONE: &str = "a required letter no longer fits";
+const OUT_OF_BOUNDS: &str = "a letter appears too few or too many times";
+const OVER_CAP: &str = "a letter appears more often than its cap";
+const SHORT_OF_MINIMUM: &str = "a letter can no longer reach its minimum";
+
+/// Options controlling how a single box is solved
+#[derive(Debug, Clone)]
+pub struct SolverConfig {
+    pub order: SearchOrder,
+    pub heuristic: Heuristic,
+    pub value_order: ValueOrder,
+    /// The letter frequencies `ValueOrder::CorpusFrequency` orders candidates by, built once for
+    /// the lexicons searched; without them that order is the lexicon's
+    pub corpus_frequency: Option<Arc<CorpusFrequency>>,
+    /// Track which letters each column can take next and reject row words against those sets
+    /// instead of querying the lexicon for every candidate (row heuristic, boxes without blocks,
+    /// searches without near misses)
+    pub forward_checking: bool,
+    /// Skip partial boxes already proven to have no completion and record newly proven ones
+    /// (depth-first row search only; other orders and heuristics ignore it)
+    pub nogoods: Option<Arc<NogoodCache>>,
+    /// For square asymmetric boxes, only find the canonical one of each box and its transpose
+    /// (see `WordBox::is_canonical`). Turn it off when rows and columns come from different
+    /// lexicons, as the transpose of a solution is then no solution at all.
+    pub symmetry_breaking: bool,
+    /// Reject boxes that use a word twice (see `WordBox::has_repeated_words`). Which words are
+    /// still allowed then depends on the whole box, so the nogood cache is not used. The rows
+    /// and columns of a rectangle differ in length, so there no row can repeat a column.
+    pub distinct_words: bool,
+    /// Require the main diagonal of a square box, read from the top left, to be a word too.
+    /// A rectangle has no diagonal, so no rectangle is a solution (see `shape_conflict`).
+    pub diagonal_word: bool,
+    /// Require the anti-diagonal, read from the top right, to be a word too. A box's transpose
+    /// reverses its anti-diagonal, so this turns off symmetry breaking. As with `diagonal_word`,
+    /// no rectangle is a solution.
+    pub anti_diagonal_word: bool,
+    /// Accept asymmetric boxes with up to this many columns that are not words, as near misses
+    /// (see `WordBox::invalid_columns`); 0 asks for a word in every column. Row search only
+    /// abandons a partial box once more columns than this have no completion left. Forward
+    /// checking, the nogood cache and the most-constrained heuristic take every column to be a
+    /// word and are set aside, as is looking ahead for the required letters and letter minimums,
+    /// which are then only checked on complete boxes. The transpose of a near miss is not one, so
+    /// turn off symmetry breaking too.
+    pub allow_invalid_cols: usize,
+    /// Letters every solution has to contain
+    pub required_letters: RequiredLetters,
+    /// How often letters may appear across a solution's cells, each cell counted once whatever
+    /// the box's shape; row search abandons a partial box as soon as a letter goes over its cap
+    /// or can no longer reach its minimum
+    pub letter_bounds: LetterBounds,
+    /// Reject boxes with fewer different letters than this (see `WordBox::distinct_letters`);
+    /// row search abandons a partial box once its open cells could no longer make up the
+    /// difference. 0 accepts any box.
+    pub min_distinct_letters: usize,
+    /// Where vowels have to go in every row and column (see `VowelRule`). Rows are checked as
+    /// they are placed, columns as they grow; under forward checking a column that needs a
+    /// vowel in the next row only lets vowels through.
+    pub vowel_rule: VowelRule,
+    /// Words no solution may hold, though the lexicon keeps them: rows holding one between
+    /// their blocks are never tried, and the columns of asymmetric boxes are checked once the
+    /// box is complete. Each word turned away counts in `SearchStats::forbidden`.
+    pub forbidden_words: Option<Arc<HashSet<String>>>,
+    /// Reject boxes that hold a letter in two cells (see `WordBox::is_isogram`), so a rectangle
+    /// needs as many different letters as it has cells. Row words repeating a letter already
+    /// placed are never tried, and under forward checking the letters placed are struck from
+    /// the next row's letter sets.
+    pub isogram: bool,
+    /// Once this token is cancelled the search returns at the next node it would expand,
+    /// reporting `Outcome::Cancelled`
+    pub cancellation: Option<CancellationToken>,
+    /// Give up after expanding this many nodes (boxes taken off the frontier), reporting
+    /// `Outcome::BudgetExhausted`: the search neither found nor ruled out further solutions
+    pub max_nodes: Option<usize>,
+    /// Give up at the first node expanded after this moment, reporting `Outcome::BudgetExhausted`
+    /// as for `max_nodes` (see `runner::SeedBudget`)
+    pub deadline: Option<Instant>,
+    /// Shuffle the candidates for every slot with an RNG seeded from this value before they are
+    /// ordered, so the same seed always visits boxes in the same order (box search only)
+    pub random_seed: Option<u64>,
+    /// Remember the answers to about this many lexicon queries of each kind for the length of a
+    /// search, as siblings keep asking about the same column prefixes (see `CachedLexicon`)
+    pub query_cache: Option<usize>,
+    /// Search row by row, keeping only this many of the best-scoring boxes (see `WordBox::score`)
+    /// at each depth. Fast on big grids but incomplete: anything beyond the beam is never
+    /// searched, which `Outcome::Pruned` reports. Overrides `order` and `heuristic`.
+    pub beam_width: Option<usize>,
+    /// What best-first and beam search rank partial boxes by, in place of `WordBox::score`;
+    /// a score that is not finite ranks last (see `rank_value`)
+    pub scorer: Option<Arc<dyn Scorer>>,
+    /// Words to try before the others in every slot, so boxes using many of them are found first
+    pub theme_words: Option<Arc<ThemeWords>>,
+    /// Reject boxes holding fewer theme words than this; row search abandons a partial box as
+    /// soon as its open slots could no longer make up the difference
+    pub min_theme_words: usize,
+    /// Words too rare to use more than `max_rare_words` of
+    pub rare_words: Option<Arc<RareWords>>,
+    /// Reject boxes holding more rare words than this; the search abandons a partial box as soon
+    /// as the words it has finished go over
+    pub max_rare_words: Option<usize>,
+    /// Record the boxes the search expands, the solutions and why each dead end was abandoned,
+    /// up to the trace's limit (row search only; the most-constrained heuristic ignores it)
+    pub trace: Option<Arc<SearchTrace>>,
+    /// Count every candidate turned away and every box pruned against the column, slot or
+    /// constraint that caused it, so a run that finds nothing can explain why
+    pub dead_ends: Option<Arc<DeadEnds>>,
+    /// Report the deepest partial box the search expands before finding a solution, the one
+    /// scoring highest (see `WordBox::score`) among those as deep, so a search that comes up
+    /// empty can show how close it got (row search only)
+    pub best_partial: bool,
+    /// Count the memory each search's frontier and the nogood cache take up here, and act on
+    /// the gauge's limit: a frontier keeps its boxes compact once told to, and every search
+    /// stops with `Outcome::Cancelled` once the gauge stops the run
+    pub memory: Option<Arc<MemoryGauge>>,
+    /// Keep only about this many boxes of a breadth-first row search's frontier in memory and
+    /// write the rest to disk (see `SpillingBreadthFirst`); beam search ignores it
+    pub spill: Option<SpillOptions>,
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        SolverConfig {
+            order: SearchOrder::default(),
+            heuristic: Heuristic::default(),
+            value_order: ValueOrder::default(),
+            corpus_frequency: None,
+            forward_checking: false,
+            nogoods: None,
+            symmetry_breaking: true,
+            distinct_words: false,
+            diagonal_word: false,
+            anti_diagonal_word: false,
+            allow_invalid_cols: 0,
+            required_letters: RequiredLetters::default(),
+            letter_bounds: LetterBounds::default(),
+            min_distinct_letters: 0,
+            vowel_rule: VowelRule::default(),
+            forbidden_words: None,
+            isogram: false,
+            cancellation: None,
+            max_nodes: None,
+            deadline: None,
+            random_seed: None,
+            query_cache: None,
+            beam_width: None,
+            scorer: None,
+            theme_words: None,
+            min_theme_words: 0,
+            rare_words: None,
+            max_rare_words: None,
+            trace: None,
+            dead_ends: None,
+            best_partial: false,
+            memory: None,
+            spill: None,
+        }
+    }
+}
+
+impl SolverConfig {
+    /// Whether only one of each box and its transpose is searched for
+    pub fn breaks_transpose_symmetry(&self) -> bool {
+        self.symmetry_breaking && !self.anti_diagonal_word
+    }
+
+    /// Why no `row_dim` by `col_dim` box can satisfy this config, if none can: a rectangle has
+    /// no diagonal to be a word. A search of such a box ends at once without solutions.
+    pub fn shape_conflict(&self, row_dim: usize, col_dim: usize) -> Option<String> {
+        (row_dim != col_dim && self.diagonals().next().is_some())
+            .then(|| format!("a {}x{} box has no diagonal word", row_dim, col_dim))
+    }
+
+    /// Whether a search under this config can be paused and resumed (see `RowSearch`): a row
+    /// search without the nogood cache, whose depth-first search is recursive, and without a
+    /// query cache, which lives only as long as one uninterrupted search
+    pub fn can_pause(&self) -> bool {
+        let row_search = self.beam_width.is_some() || self.heuristic == Heuristic::NextRow;
+        let recursive = self.beam_width.is_none()
+            && self.order == SearchOrder::DepthFirst
+            && self.nogoods.is_some();
+        row_search && !recursive && self.query_cache.is_none()
+    }
+
+    /// The generator a seeded random order draws from
+    fn rng(&self) -> Option<StdRng> {
+        self.random_seed.map(StdRng::seed_from_u64)
+    }
+
+    /// Why a search that has expanded `nodes` nodes must stop before expanding another, if it must
+    fn interruption(&self, nodes: usize) -> Option<Outcome> {
+        if self
+            .cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+            || self.memory.as_ref().is_some_and(|gauge| gauge.is_stopped())
+        {
+            Some(Outcome::Cancelled)
+        } else if self.max_nodes.is_some_and(|max| nodes >= max)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            Some(Outcome::BudgetExhausted)
+        } else {
+            None
+        }
+    }
+
+    /// Every `MEMORY_CHECK_INTERVAL` nodes, if the run keeps a memory gauge: report the
+    /// search's frontier through `meter` as `bytes` works it out, along with the nogood cache,
+    /// and empty the cache if the run is over its limit and that is the gauge's action
+    fn check_memory(&self, meter: &mut Meter, nodes: usize, bytes: impl FnOnce() -> usize) {
+        let Some(gauge) = meter.gauge() else {
+            return;
+        };
+        if !nodes.is_multiple_of(MEMORY_CHECK_INTERVAL) {
+            return;
+        }
+        meter.report(bytes());
+        if let Some(nogoods) = &self.nogoods {
+            gauge.set(Consumer::Caches, nogoods.estimated_bytes());
+        }
+        if gauge.check() && gauge.action() == MemoryAction::ClearCaches {
+            if let Some(nogoods) = &self.nogoods {
+                nogoods.clear();
+                gauge.set(Consumer::Caches, 0);
+            }
+        }
+    }
+
+    /// Whether the frontier should keep its boxes without their letter sets
+    fn compact_frontier(&self) -> bool {
+        self.memory.as_ref().is_some_and(|gauge| gauge.is_compact())
+    }
+
+    /// Whether the nogood cache can be used: its keys only capture the open columns, so every
+    /// constraint has to depend on nothing else
+    fn allows_nogoods(&self) -> bool {
+        !self.distinct_words
+            && !self.diagonal_word
+            && !self.anti_diagonal_word
+            && self.required_letters.is_empty()
+            && self.letter_bounds.is_empty()
+            && self.min_distinct_letters == 0
+            && self.min_theme_words == 0
+            && self.max_rare_words.is_none()
+            && self.allow_invalid_cols == 0
+    }
+
+    /// The diagonals that have to be words, as `anti` flags for `WordBox::diagonal_line`
+    fn diagonals(&self) -> impl Iterator<Item = bool> {
+        [(self.diagonal_word, false), (self.anti_diagonal_word, true)]
+            .into_iter()
+            .filter_map(|(required, anti)| required.then_some(anti))
+    }
+
+    /// Whether a complete box should be reported as a solution; the column words of asymmetric
+    /// boxes are only checked here, once they are read off the finished rows
+    fn accepts<L: Lexicons>(&self, wb: &WordBox, lexicon: &L) -> bool {
+        let rejection = self.rejection(wb, lexicon);
+        if let Some(reason) = rejection {
+            self.note_ruled_out(wb, reason, lexicon);
+        }
+        rejection.is_none()
+    }
+
+    /// Why a complete box is not a solution, if it is not (see `accepts`)
+    fn rejection<L: Lexicons>(&self, wb: &WordBox, lexicon: &L) -> Option<&'static str> {
+        let diagonals: Vec<String> = self
+            .diagonals()
+            .map(|anti| wb.diagonal_line(anti))
+            .collect();
+        let columns_valid = match self.allow_invalid_cols {
+            0 => wb.is_valid(lexicon),
+            allowed => wb.is_near_valid(lexicon, allowed),
+        };
+        if self.shape_conflict(wb.row_dim, wb.col_dim).is_some() {
+            Some(NO_DIAGONAL)
+        } else if !wb.is_symmetric && !columns_valid {
+            Some("a column is not a word")
+        } else if wb.words().any(|word| self.forbids(word)) {
+            Some(FORBIDS_WORD)
+        } else if self.breaks_transpose_symmetry() && !wb.is_canonical() {
+            Some("its transpose comes first")
+        } else if wb.is_rotational && !wb.is_rotation_symmetric() {
+            Some("it does not read the same turned upside down")
+        } else if !(diagonals.iter()).all(|diagonal| blocks::is_feasible(diagonal, lexicon.rows()))
+        {
+            Some("a diagonal is not a word")
+        } else if self.distinct_words && wb.has_repeated_words(&diagonals) {
+            Some(REPEATS_WORD)
+        } else if !self.required_letters.is_satisfied(wb) {
+            Some(LACKS_LETTER)
+        } else if !self.letter_bounds.is_satisfied(wb) {
+            Some(OUT_OF_BOUNDS)
+        } else if wb.distinct_letters() < self.min_distinct_letters {
+            Some("too few distinct letters")
+        } else if !self.vowel_rule.is_satisfied(wb) {
+            Some("a line breaks the vowel rule")
+        } else if self.isogram && !wb.is_isogram() {
+            Some(REPEATS_LETTER)
+        } else if self.min_theme_words > 0
+            && (self.theme_words.as_ref())
+                .is_none_or(|theme| theme.count(wb) < self.min_theme_words)
+        {
+            Some("too few theme words")
+        } else if !self.within_rare_budget(wb) {
+            Some("too many rare words")
+        } else {
+            None
+        }
+    }
+
+    /// `accepts` for a box the row search has completed, recording the verdict in the trace
+    /// and counting a forbidden word in `stats`
+    fn accepts_leaf<L: Lexicons>(
+        &self,
+        wb: &WordBox,
+        lexicon: &L,
+        stats: &mut SearchStats,
+    ) -> bool {
+        let rejection = self.rejection(wb, lexicon);
+        if let Some(reason) = rejection {
+            self.note_ruled_out(wb, reason, lexicon);
+        }
+        stats.forbidden += usize::from(rejection == Some(FORBIDS_WORD));
+        if let Some(trace) = &self.trace {
+            let kind = match rejection {
+                Some(reason) => TraceKind::Pruned(reason.to_string()),
+                None => TraceKind::Solution,
+            };
+            trace.record(wb, kind);
+        }
+        rejection.is_none()
+    }
+
+    /// Why the search should not continue below a partial box, if it should not
+    fn dead_end<L: Lexicons>(&self, wb: &WordBox, lexicon: &L) -> Option<&'static str> {
+        if self.breaks_transpose_symmetry() && !wb.may_be_canonical() {
+            Some("its transpose comes first")
+        } else if !(self.diagonals())
+            .all(|anti| blocks::is_feasible(&wb.diagonal_line(anti), lexicon.rows()))
+        {
+            Some("a diagonal cannot be completed")
+        } else if self.looks_ahead()
+            && (self.required_letters.unplaceable(wb, lexicon.cols())).is_some()
+        {
+            Some(LETTER_GONE)
+        } else if self.letter_bounds.over_cap(wb).is_some() {
+            Some(OVER_CAP)
+        } else if self.looks_ahead()
+            && (self.letter_bounds.unreachable(wb, lexicon.cols())).is_some()
+        {
+            Some(SHORT_OF_MINIMUM)
+        } else if wb.max_distinct_letters() < self.min_distinct_letters {
+            Some("too few distinct letters are still possible")
+        } else if !self.vowel_rule.may_be_satisfied(wb) {
+            Some("a line breaks the vowel rule")
+        } else if self.min_theme_words > 0
+            && (self.theme_words.as_ref())
+                .is_none_or(|theme| !theme.may_reach(wb, self.min_theme_words))
+        {
+            Some("too few theme words are still possible")
+        } else if !self.within_rare_budget(wb) {
+            Some("too many rare words")
+        } else {
+            None
+        }
+    }
+
+    /// Whether the letters a partial box's columns can still take are read off the words that
+    /// complete them, which a column that is allowed not to be a word has none of
+    fn looks_ahead(&self) -> bool {
+        self.allow_invalid_cols == 0
+    }
+
+    /// Forward checking's letter sets for the next row of `wb`, narrowed by the vowel rule and
+    /// by `isogram`, or why some column has no letter left
+    fn narrow(
+        &self,
+        wb: &WordBox,
+        letters: Vec<HashSet<char>>,
+    ) -> Result<Vec<HashSet<char>>, &'static str> {
+        let letters = (self.vowel_rule.restrict(wb, letters))
+            .ok_or("a column needs a vowel the next row cannot give")?;
+        match self.isogram {
+            true => (wb.without_placed_letters(letters))
+                .ok_or("the next row cannot avoid repeating a letter"),
+            false => Ok(letters),
+        }
+    }
+
+    /// The trace, unless there is none or it can take no more
+    fn open_trace(&self) -> Option<&SearchTrace> {
+        self.trace.as_deref().filter(|trace| !trace.is_full())
+    }
+
+    /// Count a dead end against `cause`, if dead ends are counted
+    fn note_dead_end(&self, cause: Cause) {
+        if let Some(dead_ends) = &self.dead_ends {
+            dead_ends.record(cause);
+        }
+    }
+
+    /// Count `wb`, ruled out for `reason` by `rejection` or `dead_end`, as a dead end, along
+    /// with the required letter it can no longer hold if that was the reason
+    fn note_ruled_out<L: Lexicons>(&self, wb: &WordBox, reason: &'static str, lexicon: &L) {
+        let Some(dead_ends) = &self.dead_ends else {
+            return;
+        };
+        dead_ends.record(Cause::Constraint(reason));
+        let letter = match reason {
+            LACKS_LETTER | LETTER_GONE => self.required_letters.unplaceable(wb, lexicon.cols()),
+            SHORT_OF_MINIMUM => self.letter_bounds.unreachable(wb, lexicon.cols()),
+            _ => None,
+        };
+        if let Some(letter) = letter {
+            dead_ends.record_letter(letter, wb.rows.len());
+        }
+    }
+
+    /// Whether the words a box has finished hold no more rare words than allowed
+    fn within_rare_budget(&self, wb: &WordBox) -> bool {
+        match (&self.rare_words, self.max_rare_words) {
+            (Some(rare), Some(max)) => rare.count_finished(wb) <= max,
+            _ => true,
+        }
+    }
+
+    /// Whether a row or column holds a forbidden word between its blocks
+    pub fn forbids(&self, line: &str) -> bool {
+        (self.forbidden_words.as_ref()).is_some_and(|forbidden| {
+            line.split(blocks::BLOCK)
+                .any(|word| forbidden.contains(word))
+        })
+    }
+
+    /// The letter frequencies to order candidates by, under `ValueOrder::CorpusFrequency`
+    fn corpus_order(&self) -> Option<&CorpusFrequency> {
+        let frequency = self.corpus_frequency.as_deref();
+        frequency.filter(|_| self.value_order == ValueOrder::CorpusFrequency)
+    }
+
+    /// Whether `word` holds a theme word, which is tried before the words that do not
+    fn is_theme(&self, word: &str) -> bool {
+        self.theme_words
+            .as_ref()
+            .is_some_and(|theme| theme.in_line(word))
+    }
+}
+
+/// Reorder `items` so the one with the most `completions` is expanded first, given whether the
+/// frontier pops the most recently pushed item first
+fn sort_least_constraining<T>(
+    items: Vec<T>,
+    mut completions: impl FnMut(&T) -> f64,
+    lifo: bool,
+) -> Vec<T> {
+    let mut scored: Vec<(f64, T)> = items
+        .into_iter()
+        .map(|item| (completions(&item), item))
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    if lifo {
+        scored.reverse();
+    }
+    scored.into_iter().map(|(_, item)| item).collect()
+}
+
+/// Reorder `items` so the words of the most preferred `tier` are expanded first and those of the
+/// least preferred last, keeping the order within each tier, given whether the frontier pops the
+/// most recently pushed item first
+fn prefer_low_tiers<T>(mut items: Vec<T>, tier: impl Fn(&T) -> usize, lifo: bool) -> Vec<T> {
+    if lifo {
+        items.sort_by_cached_key(|item| Reverse(tier(item)));
+    } else {
+        items.sort_by_cached_key(tier);
+    }
+    items
+}
+
+/// Reorder `items` so the theme words are expanded before the rest, keeping the order within
+/// each group, given whether the frontier pops the most recently pushed item first
+fn prefer_theme_words<T>(items: Vec<T>, is_theme: impl Fn(&T) -> bool, lifo: bool) -> Vec<T> {
+    let (theme, other): (Vec<T>, Vec<T>) = items.into_iter().partition(is_theme);
+    if lifo {
+        other.into_iter().chain(theme).collect()
+    } else {
+        theme.into_iter().chain(other).collect()
+    }
+}
+
+/// How a search ended
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Outcome {
+    /// Every box reachable from the start was searched, so there are no solutions beyond
+    /// the ones reported
+    #[default]
+    Exhausted,
+    /// `on_solution` asked to stop
+    Stopped,
+    /// The cancellation token was triggered before the search could finish
+    Cancelled,
+    /// The node budget (`SolverConfig::max_nodes`) or the time allowed (`SolverConfig::deadline`)
+    /// ran out; whether more solutions exist is unknown
+    BudgetExhausted,
+    /// The search ran to the end but its frontier dropped boxes along the way, as beam search
+    /// does, so it may have missed solutions
+    Pruned,
+}
+
+impl Outcome {
+    /// How a search split into parts ended, given how two of the parts did: stopping or being
+    /// cut short anywhere decides the whole
+    pub fn combine(self, other: Outcome) -> Outcome {
+        let rank = |outcome: Outcome| match outcome {
+            Outcome::Exhausted => 0,
+            Outcome::Pruned => 1,
+            Outcome::BudgetExhausted => 2,
+            Outcome::Stopped => 3,
+            Outcome::Cancelled => 4,
+        };
+        if rank(other) > rank(self) {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+/// The deepest partial box a search expanded, and its score (see `WordBox::score`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct BestPartial {
+    pub word_box: WordBox,
+    pub score: f64,
+}
+
+// Scores are products of counts, never NaN
+impl Eq for BestPartial {}
+
+impl BestPartial {
+    /// The rows placed, then the score, for comparing boxes
+    fn rank(&self) -> (usize, f64) {
+        (self.word_box.rows.len(), self.score)
+    }
+
+    /// The better of two best partial boxes, such as those of two seeds: the deeper one, or of
+    /// two as deep the one scoring higher, `a` on a tie
+    pub fn better(a: Option<BestPartial>, b: Option<BestPartial>) -> Option<BestPartial> {
+        match (a, b) {
+            (Some(a), Some(b)) if b.rank() > a.rank() => Some(b),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+/// How much `WordBox::candidates` says about each open slot
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CandidateDetail {
+    #[default]
+    Words,
+    Counts,
+}
+
+/// The candidates of one open slot: the words themselves, or only how many there are
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Candidates {
+    Words(Vec<String>),
+    Count(usize),
+}
+
+impl Candidates {
+    pub fn len(&self) -> usize {
+        match self {
+            Candidates::Words(words) => words.len(),
+            Candidates::Count(count) => *count,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// An open row or column of a partial box and the words that may go in it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenSlot {
+    pub line: Line,
+    pub index: usize,
+    pub candidates: Candidates,
+}
+
+/// The open slots of a partial box with their candidates (see `WordBox::candidates`)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SlotCandidates {
+    pub slots: Vec<OpenSlot>,
+}
+
+impl SlotCandidates {
+    /// The candidates of a slot, if it is open
+    pub fn get(&self, line: Line, index: usize) -> Option<&Candidates> {
+        (self.slots.iter())
+            .find(|slot| slot.line == line && slot.index == index)
+            .map(|slot| &slot.candidates)
+    }
+
+    /// Whether some open slot has no candidate left, so the box cannot be completed
+    pub fn is_stuck(&self) -> bool {
+        self.slots.iter().any(|slot| slot.candidates.is_empty())
+    }
+}
+
+/// The candidates of every open slot of `wb` (see `WordBox::candidates`)
+pub(crate) fn slot_candidates<L: Lexicons>(
+    wb: &WordBox,
+    lexicon: &L,
+    config: &SolverConfig,
+    detail: CandidateDetail,
+) -> SlotCandidates {
+    // Either of a box and its transpose is a legal box to build
+    let config = SolverConfig {
+        symmetry_breaking: false,
+        ..config.clone()
+    };
+    let listed = |words: Vec<String>| match detail {
+        CandidateDetail::Words => Candidates::Words(words),
+        CandidateDetail::Counts => Candidates::Count(words.len()),
+    };
+    let mut slots = vec![];
+    let next = wb.rows.len();
+    // The next row as the row search fills it: every word `next_rows` offers that
+    // `child_node` keeps, and that completes an accepted box if it is the last row
+    let by_rows = next < wb.row_dim && (wb.is_symmetric || wb.cols.is_empty());
+    if by_rows {
+        let rows = match start_node(wb.clone(), lexicon, &config) {
+            Some(node) => {
+                let mut stats = SearchStats::default();
+                let rows = next_rows(&node, lexicon, &config, false, &mut None, &mut stats);
+                (rows.into_iter())
+                    .filter(|row| {
+                        child_node(&node, row.clone(), lexicon, &config).is_some_and(|child| {
+                            !child.wb.is_done() || config.accepts(&child.wb, lexicon)
+                        })
+                    })
+                    .collect()
+            }
+            None => vec![],
+        };
+        slots.push(OpenSlot {
+            line: Line::Row,
+            index: next,
+            candidates: listed(rows),
+        });
+    }
+    // The other slots as the most-constrained search fills them, which leaves out boxes
+    // with blocks or that must turn into themselves
+    if !wb.has_blocks() && !wb.is_rotational {
+        let grid = Grid::from_word_box(wb);
+        for slot in grid.open_slots() {
+            if by_rows && slot == Slot::Row(next) {
+                continue;
+            }
+            let words = (grid.candidates(slot, lexicon).into_iter())
+                .filter(|word| {
+                    (grid.place_candidate(slot, word, lexicon, config.distinct_words)).is_ok()
+                })
+                .collect();
+            let (line, index) = match slot {
+                Slot::Row(i) => (Line::Row, i),
+                Slot::Col(j) => (Line::Col, j),
+            };
+            slots.push(OpenSlot {
+                line,
+                index,
+                candidates: listed(words),
+            });
+        }
+    }
+    SlotCandidates { slots }
+}
+
+/// What a search reports once it returns
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchReport {
+    pub solutions: usize, // solutions handed to `on_solution`
+    pub stats: SearchStats,
+    pub outcome: Outcome,
+    /// The deepest partial box expanded before the first solution, if the config asked for it
+    /// (see `SolverConfig::best_partial`)
+    pub best_partial: Option<BestPartial>,
+}
+
+impl SearchReport {
+    /// The report of a search made of this one and `other`, such as two subtrees searched apart
+    pub fn merge(self, other: SearchReport) -> SearchReport {
+        SearchReport {
+            solutions: self.solutions + other.solutions,
+            stats: self.stats.merge(other.stats),
+            outcome: self.outcome.combine(other.outcome),
+            best_partial: BestPartial::better(self.best_partial, other.best_partial),
+        }
+    }
+
+    /// Keep `wb`, which is being expanded, as the best partial box if the config tracks one, no
+    /// solution has been found yet, and it beats the one kept. Only boxes as deep as the one kept
+    /// are scored.
+    fn note_partial<L: Lexicons>(&mut self, wb: &WordBox, lexicon: &L, config: &SolverConfig) {
+        if !config.best_partial || self.solutions > 0 {
+            return;
+        }
+        let depth = wb.rows.len();
+        let kept = self.best_partial.as_ref();
+        if kept.is_some_and(|best| best.word_box.rows.len() > depth) {
+            return;
+        }
+        let score = wb.score(lexicon);
+        if kept.is_none_or(|best| best.rank() < (depth, score)) {
+            self.best_partial = Some(BestPartial {
+                word_box: wb.clone(),
+                score,
+            });
+        }
+    }
+}
+
+/// Run `search`, filling in the time it took and the lexicon queries it made on this thread
+fn measured(search: impl FnOnce() -> SearchReport) -> SearchReport {
+    let start = Instant::now();
+    let queries = lexicon::queries_on_this_thread();
+    let mut report = search();
+    report.stats.elapsed = start.elapsed();
+    report.stats.lexicon_queries = lexicon::queries_on_this_thread() - queries;
+    report
+}
+
+pub fn solve_word_box<L: Lexicons>(wb: WordBox, lexicon: &L) -> Option<WordBox> {
+    let mut first = None;
+    solve_word_box_all(wb, lexicon, &SolverConfig::default(), |solution| {
+        first = Some(solution);
+        ControlFlow::Break(())
+    });
+    first
+}
+
+/// Collect up to `limit` solutions reachable from `wb`. A cancelled search still returns the
+/// solutions found before it was cancelled, with `Outcome::Cancelled` in its report.
+pub fn collect_word_boxes<L: Lexicons>(
+    wb: WordBox,
+    lexicon: &L,
+    config: &SolverConfig,
+    limit: Option<usize>,
+) -> (Vec<WordBox>, SearchReport) {
+    let mut solutions = vec![];
+    let limit = limit.unwrap_or(usize::MAX);
+    if limit == 0 {
+        let report = SearchReport {
+            outcome: Outcome::Stopped,
+            ..SearchReport::default()
+        };
+        return (solutions, report);
+    }
+    let report = solve_word_box_all(wb, lexicon, config, |solution| {
+        solutions.push(solution);
+        if solutions.len() < limit {
+            ControlFlow::Continue(())
+        } else {
+            ControlFlow::Break(())
+        }
+    });
+    (solutions, report)
+}
+
+/// The boxes `depth` rows below `wb` that are still worth searching, in the order the rows would
+/// be tried, so that their subtrees can be searched apart; complete boxes met on the way are
+/// kept as they are. The report counts the boxes expanded to get there.
+pub fn split_word_box<L: Lexicons>(
+    wb: WordBox,
+    lexicon: &L,
+    config: &SolverConfig,
+    depth: usize,
+) -> (Vec<WordBox>, SearchReport) {
+    let mut level = vec![wb];
+    let report = measured(|| {
+        let mut report = SearchReport::default();
+        for _ in 0..depth {
+            let mut next = vec![];
+            for wb in std::mem::take(&mut level) {
+                if wb.is_done() {
+                    next.push(wb);
+                    continue;
+                }
+                report.stats.nodes += 1;
+                report.stats.observe(next.len(), wb.rows.len());
+                let node = Node {
+                    wb,
+                    next_letters: None,
+                };
+                let children = expand(
+                    &node,
+                    lexicon,
+                    config,
+                    false,
+                    &mut config.rng(),
+                    &mut report.stats,
+                );
+                next.extend(children.into_iter().map(|child| child.wb));
+            }
+            level = next;
+        }
+        report
+    });
+    (level, report)
+}
+
+/// How many solutions `estimate_solutions` expects, from how many probes
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Estimate {
+    pub samples: usize, // probes walked
+    pub hits: usize,    // probes that ended in a solution
+    pub solutions: f64, // mean of the probes' estimates
+    pub std_error: f64, // standard error of that mean
+    pub stats: SearchStats,
+}
+
+impl Display for Estimate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let number = |value: f64| match value {
+            _ if value < 100.0 => format!("{:.1}", value),
+            _ if value < 1e7 => format!("{:.0}", value),
+            _ => format!("{:.3e}", value),
+        };
+        write!(
+            f,
+            "about {} solutions (standard error {}, {:.1}%) from {} probes, {} of them reaching a solution, in {:?}",
+            number(self.solutions),
+            number(self.std_error),
+            100.0 * self.std_error / self.solutions.max(f64::MIN_POSITIVE),
+            self.samples,
+            self.hits,
+            self.stats.elapsed
+        )
+    }
+}
+
+/// Estimate how many solutions a row search would find below `wb` without finding them, by
+/// Knuth's random probes. Each probe walks down from `wb` to one child picked at random at every
+/// level, multiplying the numbers of children on the way; the product is its estimate if it ends
+/// in a solution and 0 if it ends in a dead end or a box `config` rejects. The mean over the
+/// probes is unbiased, but converges slowly when a few rare branches hold most of the solutions.
+/// `order`, `heuristic`, `value_order` and `beam_width` are ignored; a cancelled estimate is the
+/// mean of the probes walked so far.
+pub fn estimate_solutions<L: Lexicons>(
+    wb: WordBox,
+    lexicon: &L,
+    config: &SolverConfig,
+    samples: usize,
+    seed: u64,
+) -> Estimate {
+    let start = Instant::now();
+    let queries = lexicon::queries_on_this_thread();
+    let mut estimate = Estimate::default();
+    let mut rng = StdRng::seed_from_u64(seed);
+    let (mut mean, mut spread) = (0.0, 0.0); // running mean and sum of squared deviations
+    let root = start_node(wb, lexicon, config);
+    while estimate.samples < samples && config.interruption(0).is_none() {
+        let mut node = root.clone();
+        let mut weight = 1.0;
+        let value = loop {
+            let Some(current) = node.take() else {
+                break 0.0;
+            };
+            estimate.stats.nodes += 1;
+            estimate.stats.observe(1, current.wb.rows.len());
+            if current.wb.is_done() {
+                let accepted = config.accepts(&current.wb, lexicon);
+                estimate.hits += usize::from(accepted);
+                break if accepted { weight } else { 0.0 };
+            }
+            let mut children = expand(
+                &current,
+                lexicon,
+                config,
+                false,
+                &mut None,
+                &mut estimate.stats,
+            );
+            if !children.is_empty() {
+                weight *= children.len() as f64;
+                node = Some(children.swap_remove(rng.gen_range(0..children.len())));
+            }
+        };
+        // Welford's update, which stays accurate over products spanning many magnitudes
+        estimate.samples += 1;
+        let delta = value - mean;
+        mean += delta / estimate.samples as f64;
+        spread += delta * (value - mean);
+    }
+    estimate.solutions = mean;
+    if estimate.samples > 1 {
+        let variance = spread / (estimate.samples - 1) as f64;
+        estimate.std_error = (variance / estimate.samples as f64).sqrt();
+    }
+    estimate.stats.elapsed = start.elapsed();
+    estimate.stats.lexicon_queries = lexicon::queries_on_this_thread() - queries;
+    estimate
+}
+
+/// Hand every completed box reachable from `wb` to `on_solution` as soon as it is found,
+/// until the callback breaks, the search is cancelled or the search space is exhausted
+pub fn solve_word_box_all<L: Lexicons>(
+    wb: WordBox,
+    lexicon: &L,
+    config: &SolverConfig,
+    on_solution: impl FnMut(WordBox) -> ControlFlow<()>,
+) -> SearchReport {
+    measured(|| search_word_box(wb, lexicon, config, on_solution))
+}
+
+fn search_word_box<L: Lexicons>(
+    wb: WordBox,
+    lexicon: &L,
+    config: &SolverConfig,
+    on_solution: impl FnMut(WordBox) -> ControlFlow<()>,
+) -> SearchReport {
+    let Some(max_entries) = config.query_cache else {
+        return search_lexicons(wb, lexicon, config, on_solution);
+    };
+    let cached = CachedLexicons::new(lexicon, max_entries);
+    let mut report = search_lexicons(wb, &cached, config, on_solution);
+    report.stats.cache_hits = cached.hits();
+    report.stats.cache_misses = cached.misses();
+    report
+}
+
+fn search_lexicons<L: Lexicons>(
+    wb: WordBox,
+    lexicon: &L,
+    config: &SolverConfig,
+    mut on_solution: impl FnMut(WordBox) -> ControlFlow<()>,
+) -> SearchReport {
+    debug_assert!(
+        !wb.is_symmetric || wb.row_dim == wb.col_dim,
+        "symmetric boxes are square"
+    );
+    if config.beam_width.is_none()
+        && config.heuristic == Heuristic::MostConstrained
+        && config.allow_invalid_cols == 0
+        && !wb.has_blocks()
+    {
+        return solve_most_constrained(wb, lexicon, config, on_solution);
+    }
+
+    let Some(node) = start_node(wb, lexicon, config) else {
+        return SearchReport::default();
+    };
+    if let Some(width) = config.beam_width {
+        return search_frontier(node, lexicon, config, Beam::new(width), on_solution);
+    }
+    if let (true, SearchOrder::DepthFirst, true) = (
+        config.nogoods.is_some(),
+        config.order,
+        // The letters a rotational box locks below come from rows the keys leave out
+        config.allows_nogoods() && !node.wb.is_rotational,
+    ) {
+        let mut report = SearchReport::default();
+        let mut walk = Walk::new(config);
+        if let ControlFlow::Break(outcome) = solve_with_nogoods(
+            node,
+            lexicon,
+            config,
+            0,
+            &mut report,
+            &mut walk,
+            &mut on_solution,
+        ) {
+            report.outcome = outcome;
+        }
+        return report;
+    }
+    match config.order {
+        SearchOrder::DepthFirst => search_depth_first(node, lexicon, config, on_solution),
+        SearchOrder::BreadthFirst => match &config.spill {
+            Some(spill) => {
+                let boxes = SpillingBreadthFirst::new(spill.clone());
+                search_frontier(node, lexicon, config, boxes, on_solution)
+            }
+            None => search_frontier(node, lexicon, config, BreadthFirst::default(), on_solution),
+        },
+        SearchOrder::BestFirst => {
+            search_frontier(node, lexicon, config, BestFirst::default(), on_solution)
+        }
+    }
+}
+
+/// Like `solve_word_box_all`, but expanding boxes in the order `frontier` hands them out. Boxes
+/// are filled row by row: `order`, `heuristic`, `beam_width`, `nogoods` and `query_cache` are
+/// ignored.
+pub fn solve_word_box_with<L: Lexicons, F: Frontier>(
+    wb: WordBox,
+    lexicon: &L,
+    config: &SolverConfig,
+    frontier: F,
+    on_solution: impl FnMut(WordBox) -> ControlFlow<()>,
+) -> SearchReport {
+    measured(|| match start_node(wb, lexicon, config) {
+        Some(node) => search_frontier(node, lexicon, config, frontier, on_solution),
+        None => SearchReport::default(),
+    })
+}
+
+/// The node a row search starts from, with its letter sets under forward checking, or `None`
+/// if forward checking already shows the box cannot be completed or its seed rows repeat a
+/// letter of an isogram
+fn start_node<L: Lexicons>(wb: WordBox, lexicon: &L, config: &SolverConfig) -> Option<Node> {
+    if config.shape_conflict(wb.row_dim, wb.col_dim).is_some() {
+        config.note_dead_end(Cause::Constraint(NO_DIAGONAL));
+        return None;
+    }
+    if config.isogram && !wb.is_isogram() {
+        config.note_dead_end(Cause::Constraint(REPEATS_LETTER));
+        return None;
+    }
+    let next_letters = if config.forward_checking && config.looks_ahead() && !wb.has_blocks() {
+        match wb.next_letters(lexicon.cols()) {
+            Ok(letters) => match config.narrow(&wb, letters) {
+                Ok(letters) => Some(letters),
+                Err(reason) => {
+                    config.note_dead_end(Cause::Constraint(reason));
+                    return None;
+                }
+            },
+            Err(_) if wb.is_done() => Some(vec![]),
+            Err(column) => {
+                config.note_dead_end(Cause::Column(column));
+                return None;
+            }
+        }
+    } else {
+        None
+    };
+    Some(Node { wb, next_letters })
+}
+
+/// Add `node` to `frontier`, scoring it only if the frontier uses scores
+fn push_node<L: Lexicons, F: Frontier + ?Sized>(
+    frontier: &mut F,
+    node: Node,
+    lexicon: &L,
+    config: &SolverConfig,
+) {
+    let score = match (frontier.needs_scores(), &config.scorer) {
+        (false, _) => 0.0,
+        (true, Some(scorer)) => rank_value(scorer.score(&node.wb, lexicon.rows())),
+        (true, None) => node.wb.score(lexicon),
+    };
+    frontier.push(node, score);
+}
+
+/// Row search from `node` that expands whichever box `frontier` pops next
+fn search_frontier<L: Lexicons, F: Frontier>(
+    node: Node,
+    lexicon: &L,
+    config: &SolverConfig,
+    mut boxes: F,
+    mut on_solution: impl FnMut(WordBox) -> ControlFlow<()>,
+) -> SearchReport {
+    let mut report = SearchReport::default();
+    let mut walk = Walk::new(config);
+    push_node(&mut boxes, node, lexicon, config);
+    frontier_steps(
+        &mut boxes,
+        lexicon,
+        config,
+        usize::MAX,
+        &mut report,
+        &mut walk,
+        &mut on_solution,
+    );
+    report
+}
+
+/// Expand the boxes `boxes` pops until it runs dry, `on_solution` stops the search, the search
+/// is interrupted or `until` nodes have been expanded in all; returns whether the search is over
+fn frontier_steps<L: Lexicons, F: Frontier + ?Sized>(
+    boxes: &mut F,
+    lexicon: &L,
+    config: &SolverConfig,
+    until: usize,
+    report: &mut SearchReport,
+    walk: &mut Walk,
+    on_solution: &mut impl FnMut(WordBox) -> ControlFlow<()>,
+) -> bool {
+    loop {
+        if report.stats.nodes >= until {
+            return false;
+        }
+        let Some(node) = boxes.pop() else {
+            break;
+        };
+        if let Some(outcome) = config.interruption(report.stats.nodes) {
+            report.outcome = outcome;
+            break;
+        }
+        // The boxes waiting are taken to be about the size of the one at hand
+        config.check_memory(&mut walk.meter, report.stats.nodes, || {
+            (boxes.len() + 1) * node.estimated_bytes()
+        });
+        // A box pushed without its letter sets, or read back from disk without them, gets them
+        // back, or turns out to be a dead end
+        let restore = config.compact_frontier() || config.spill.is_some();
+        let Node { wb, next_letters } = match node.next_letters.is_none() && restore {
+            true => match start_node(node.wb, lexicon, config) {
+                Some(node) => node,
+                None => {
+                    report.stats.rejected += 1;
+                    continue;
+                }
+            },
+            false => node,
+        };
+        report.stats.nodes += 1;
+        report.stats.observe(boxes.len() + 1, wb.rows.len());
+        if wb.is_done() {
+            if !config.accepts_leaf(&wb, lexicon, &mut report.stats) {
+                continue;
+            }
+            report.solutions += 1;
+            if on_solution(wb).is_break() {
+                report.outcome = Outcome::Stopped;
+                break;
+            }
+            continue;
+        }
+
+        let lifo = boxes.is_lifo();
+        report.note_partial(&wb, lexicon, config);
+        let node = Node { wb, next_letters };
+        let compact = config.compact_frontier();
+        let rng = &mut walk.rng;
+        for child in expand(&node, lexicon, config, lifo, rng, &mut report.stats) {
+            let child = if compact { child.compact() } else { child };
+            push_node(boxes, child, lexicon, config);
+        }
+    }
+    (report.stats.spilled, report.stats.spilled_bytes) = boxes.spilled();
+    if boxes.dropped_any() && report.outcome == Outcome::Exhausted {
+        report.outcome = Outcome::Pruned;
+    }
+    true
+}
+
+/// A box being searched depth-first, with the rows still to be tried below it in stack order
+struct Branch {
+    parent: Node,
+    rows: Vec<String>,
+}
+
+/// What a depth-first row search has yet to expand: the box at hand, until it is taken, and a
+/// branch per level above it
+#[derive(Default)]
+struct Stack {
+    next: Option<Node>,
+    branches: Vec<Branch>,
+    waiting: usize, // rows left to try across the branches
+}
+
+/// Row search from `node` that expands the box pushed last first, like `DepthFirst`, but only
+/// builds a child box when it is its turn. Just the rows left to try are kept for the others,
+/// so alongside the box at hand there is one box per level, not one per waiting sibling.
+fn search_depth_first<L: Lexicons>(
+    node: Node,
+    lexicon: &L,
+    config: &SolverConfig,
+    mut on_solution: impl FnMut(WordBox) -> ControlFlow<()>,
+) -> SearchReport {
+    let mut report = SearchReport::default();
+    let mut walk = Walk::new(config);
+    let mut stack = Stack {
+        next: Some(node),
+        ..Stack::default()
+    };
+    stack_steps(
+        &mut stack,
+        lexicon,
+        config,
+        usize::MAX,
+        &mut report,
+        &mut walk,
+        &mut on_solution,
+    );
+    report
+}
+
+/// Expand the boxes of `stack` until none is left, `on_solution` stops the search, the search
+/// is interrupted or `until` nodes have been expanded in all; returns whether the search is over
+fn stack_steps<L: Lexicons>(
+    stack: &mut Stack,
+    lexicon: &L,
+    config: &SolverConfig,
+    until: usize,
+    report: &mut SearchReport,
+    walk: &mut Walk,
+    on_solution: &mut impl FnMut(WordBox) -> ControlFlow<()>,
+) -> bool {
+    loop {
+        if report.stats.nodes >= until {
+            return false;
+        }
+        let Node { wb, next_letters } = match stack.next.take() {
+            Some(node) => node,
+            None => {
+                let Some(branch) = stack.branches.last_mut() else {
+                    break;
+                };
+                let row = branch
+                    .rows
+                    .pop()
+                    .expect("branches with no rows left are dropped");
+                stack.waiting -= 1;
+                let child = child_node(&branch.parent, row, lexicon, config);
+                if branch.rows.is_empty() {
+                    stack.branches.pop();
+                }
+                match child {
+                    Some(child) => child,
+                    None => {
+                        report.stats.rejected += 1;
+                        continue;
+                    }
+                }
+            }
+        };
+        if let Some(outcome) = config.interruption(report.stats.nodes) {
+            report.outcome = outcome;
+            break;
+        }
+        config.check_memory(&mut walk.meter, report.stats.nodes, || {
+            (stack.branches.iter())
+                .map(|branch| branch.parent.estimated_bytes() + branch.rows.estimated_bytes())
+                .sum()
+        });
+        report.stats.nodes += 1;
+        report.stats.observe(stack.waiting + 1, wb.rows.len());
+        if wb.is_done() {
+            if !config.accepts_leaf(&wb, lexicon, &mut report.stats) {
+                continue;
+            }
+            report.solutions += 1;
+            if on_solution(wb).is_break() {
+                report.outcome = Outcome::Stopped;
+                break;
+            }
+            continue;
+        }
+
+        report.note_partial(&wb, lexicon, config);
+        let parent = Node { wb, next_letters };
+        let rows = next_rows(
+            &parent,
+            lexicon,
+            config,
+            true,
+            &mut walk.rng,
+            &mut report.stats,
+        );
+        if !rows.is_empty() {
+            stack.waiting += rows.len();
+            stack.branches.push(Branch { parent, rows });
+        }
+    }
+    true
+}
+
+/// The boxes a `RowSearch` has yet to expand
+enum Waiting<'a> {
+    Stack(Stack),
+    Frontier(Box<dyn Frontier + Send + 'a>),
+}
+
+/// A row search that can stop after any number of nodes and carry on later from where it was,
+/// keeping its frontier in between, so that many searches can take turns (see
+/// `runner::Schedule::Fair`). Taken to the end, it makes the search `solve_word_box_all` makes.
+pub struct RowSearch<'a, L> {
+    lexicon: &'a L,
+    config: &'a SolverConfig,
+    waiting: Waiting<'a>,
+    report: SearchReport,
+    walk: Walk<'a>,
+    finished: bool,
+}
+
+impl<'a, L: Lexicons> RowSearch<'a, L> {
+    /// The search from `wb`, before it has expanded a node
+    ///
+    /// # Panics
+    ///
+    /// If the config's search cannot pause (see `SolverConfig::can_pause`)
+    pub fn new(wb: WordBox, lexicon: &'a L, config: &'a SolverConfig) -> Self {
+        assert!(config.can_pause(), "this search cannot pause");
+        let mut waiting = match (config.beam_width, config.order) {
+            (Some(width), _) => Waiting::Frontier(Box::new(Beam::new(width))),
+            (None, SearchOrder::DepthFirst) => Waiting::Stack(Stack::default()),
+            (None, SearchOrder::BreadthFirst) => match &config.spill {
+                Some(spill) => {
+                    Waiting::Frontier(Box::new(SpillingBreadthFirst::new(spill.clone())))
+                }
+                None => Waiting::Frontier(Box::new(BreadthFirst::default())),
+            },
+            (None, SearchOrder::BestFirst) => Waiting::Frontier(Box::new(BestFirst::default())),
+        };
+        let node = start_node(wb, lexicon, config);
+        let finished = node.is_none();
+        match (&mut waiting, node) {
+            (_, None) => {}
+            (Waiting::Stack(stack), node) => stack.next = node,
+            (Waiting::Frontier(boxes), Some(node)) => {
+                push_node(boxes.as_mut(), node, lexicon, config)
+            }
+        }
+        RowSearch {
+            lexicon,
+            config,
+            waiting,
+            report: SearchReport::default(),
+            walk: Walk::new(config),
+            finished,
+        }
+    }
+
+    /// Expand up to `nodes` more nodes, handing every solution found to `on_solution` as
+    /// `solve_word_box_all` does; returns whether the search is over
+    pub fn resume(
+        &mut self,
+        nodes: usize,
+        mut on_solution: impl FnMut(WordBox) -> ControlFlow<()>,
+    ) -> bool {
+        if self.finished {
+            return true;
+        }
+        let until = self.report.stats.nodes.saturating_add(nodes);
+        let (lexicon, config) = (self.lexicon, self.config);
+        let (report, walk) = (&mut self.report, &mut self.walk);
+        let start = Instant::now();
+        let queries = lexicon::queries_on_this_thread();
+        self.finished = match &mut self.waiting {
+            Waiting::Stack(stack) => stack_steps(
+                stack,
+                lexicon,
+                config,
+                until,
+                report,
+                walk,
+                &mut on_solution,
+            ),
+            Waiting::Frontier(boxes) => {
+                let boxes = boxes.as_mut();
+                frontier_steps(
+                    boxes,
+                    lexicon,
+                    config,
+                    until,
+                    report,
+                    walk,
+                    &mut on_solution,
+                )
+            }
+        };
+        report.stats.elapsed += start.elapsed();
+        report.stats.lexicon_queries += lexicon::queries_on_this_thread() - queries;
+        self.finished
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// What the search has found and taken so far; once it is over, its report
+    pub fn report(&self) -> &SearchReport {
+        &self.report
+    }
+
+    pub fn into_report(self) -> SearchReport {
+        self.report
+    }
+}
+
+/// The boxes reached by placing each valid word as the next row of `node`, in the order they
+/// should be pushed onto a frontier that is or is not `lifo`. Counts the rows generated and
+/// the ones rejected in `stats`.
+fn expand<L: Lexicons>(
+    node: &Node,
+    lexicon: &L,
+    config: &SolverConfig,
+    lifo: bool,
+    rng: &mut Option<StdRng>,
+    stats: &mut SearchStats,
+) -> Vec<Node> {
+    let rows = next_rows(node, lexicon, config, lifo, rng, stats);
+    let tried = rows.len();
+    let children: Vec<Node> = rows
+        .into_iter()
+        .filter_map(|row| child_node(node, row, lexicon, config))
+        .collect();
+    stats.rejected += tried - children.len();
+    children
+}
+
+/// The words that may go in the next row of `node`, in the order their boxes should be pushed
+/// onto a frontier that is or is not `lifo`. Counts the rows generated and the ones rejected
+/// in `stats`; a row can still be rejected by `child_node`.
+///
+/// Rows that are single words are checked by id, borrowing each word from the lexicon, so only
+/// the rows kept are copied out of it.
+fn next_rows<L: Lexicons>(
+    node: &Node,
+    lexicon: &L,
+    config: &SolverConfig,
+    lifo: bool,
+    rng: &mut Option<StdRng>,
+    stats: &mut SearchStats,
+) -> Vec<String> {
+    let wb = &node.wb;
+    let trace = config.open_trace();
+    let mut columns = None;
+    let (mut mismatched, mut forbidden) = (0, 0);
+    // Whether `row` fits the row and the constraints, noting the cause if it does not
+    let mut admits = |row: &str| {
+        if !wb.fits_row(row) || !wb.row_allowed(row) {
+            mismatched += 1;
+            return false;
+        }
+        if config.forbids(row) {
+            forbidden += 1;
+            config.note_dead_end(Cause::Constraint(FORBIDS_WORD));
+            return false;
+        }
+        let column = match &node.next_letters {
+            Some(letters) => (row.chars().zip(letters)).position(|(ch, set)| !set.contains(&ch)),
+            None => {
+                let columns = columns.get_or_insert_with(|| wb.next_columns());
+                blocked_beyond(columns, row, lexicon, config)
+            }
+        };
+        let cause = match column {
+            Some(i) => Cause::Column(i),
+            None if config.distinct_words && wb.repeats_word(row) => {
+                Cause::Constraint(REPEATS_WORD)
+            }
+            None if config.isogram && wb.repeats_letter(row) => Cause::Constraint(REPEATS_LETTER),
+            None => return true,
+        };
+        config.note_dead_end(cause);
+        false
+    };
+    let (generated, candidates, mut choices) = match wb.row_ids(lexicon.rows()) {
+        Some(ids) => {
+            let word = |&id: &WordId| lexicon.rows().word(id, wb.col_dim);
+            let candidates = trace.map(|_| ids.iter().map(|id| word(id).to_string()).collect());
+            let choices: Vec<String> = (ids.iter().map(word))
+                .filter(|row| admits(row))
+                .map(str::to_string)
+                .collect();
+            (ids.len(), candidates, choices)
+        }
+        None => {
+            let rows = wb.row_combinations(lexicon.rows());
+            let candidates: Option<Vec<String>> = trace.map(|_| rows.clone());
+            let generated = rows.len();
+            let choices: Vec<String> = rows.into_iter().filter(|row| admits(row)).collect();
+            (generated, candidates, choices)
+        }
+    };
+    stats.mismatched += mismatched;
+    stats.forbidden += forbidden;
+    if let Some(rng) = rng {
+        choices.shuffle(rng);
+    }
+    if config.value_order == ValueOrder::LeastConstraining {
+        let mut columns = wb.next_columns();
+        choices = sort_least_constraining(
+            choices,
+            |word| columns.completions(word, lexicon.cols()),
+            lifo,
+        );
+    }
+    if let Some(frequency) = config.corpus_order() {
+        let row = wb.rows.len();
+        choices = sort_least_constraining(choices, |word| frequency.row_score(row, word), lifo);
+    }
+    if lexicon.rows().tiers() > 1 {
+        choices = prefer_low_tiers(choices, |word| line_tier(lexicon.rows(), word), lifo);
+    }
+    if config.theme_words.is_some() {
+        choices = prefer_theme_words(choices, |word| config.is_theme(word), lifo);
+    }
+
+    stats.candidates += generated;
+    stats.rejected += generated - choices.len();
+    if let (Some(trace), Some(candidates)) = (trace, candidates) {
+        trace.record(wb, TraceKind::Expanded);
+        let kept: HashSet<&str> = choices.iter().map(String::as_str).collect();
+        let mut reasons: BTreeMap<String, usize> = BTreeMap::new();
+        for row in candidates.iter().filter(|row| !kept.contains(row.as_str())) {
+            *reasons
+                .entry(turned_away(node, row, lexicon, config))
+                .or_default() += 1;
+        }
+        for (reason, count) in reasons {
+            trace.rejected(wb, count, reason);
+        }
+    }
+    choices
+}
+
+/// The column that takes `row` over the number of columns the config allows to have no
+/// completion, if it does; with none allowed, the first column it leaves without one
+fn blocked_beyond<L: Lexicons>(
+    columns: &mut NextColumns,
+    row: &str,
+    lexicon: &L,
+    config: &SolverConfig,
+) -> Option<usize> {
+    match config.allow_invalid_cols {
+        0 => columns.blocked_column(row, lexicon.cols()),
+        allowed => columns.blocked_columns(row, lexicon.cols()).nth(allowed),
+    }
+}
+
+/// Why `next_rows` turned `row` away below `node`
+fn turned_away<L: Lexicons>(node: &Node, row: &str, lexicon: &L, config: &SolverConfig) -> String {
+    let wb = &node.wb;
+    if !wb.fits_row(row) || !wb.row_allowed(row) {
+        return "do not fit the row".to_string();
+    }
+    if config.forbids(row) {
+        return "hold a forbidden word".to_string();
+    }
+    let column = match &node.next_letters {
+        Some(letters) => (row.chars().zip(letters)).position(|(ch, set)| !set.contains(&ch)),
+        None => blocked_beyond(&mut wb.next_columns(), row, lexicon, config),
+    };
+    match column {
+        Some(i) => format!("column {} would have no completion", i),
+        None if wb.repeats_letter(row) => "repeat a letter".to_string(),
+        None => "repeat a word".to_string(),
+    }
+}
+
+/// The box with `row` placed as the next row of `node`, or `None` if the config rules it out
+/// or forward checking shows it cannot be completed
+fn child_node<L: Lexicons>(
+    node: &Node,
+    row: String,
+    lexicon: &L,
+    config: &SolverConfig,
+) -> Option<Node> {
+    let trace = config.open_trace();
+    let child = match node.wb.add_word(row) {
+        Ok(child) => child,
+        Err(err) => {
+            if let Some(trace) = trace {
+                trace.rejected(&node.wb, 1, err.to_string());
+            }
+            return None;
+        }
+    };
+    if let Some(reason) = config.dead_end(&child, lexicon) {
+        config.note_ruled_out(&child, reason, lexicon);
+        if let Some(trace) = trace {
+            trace.record(&child, TraceKind::Pruned(reason.to_string()));
+        }
+        return None;
+    }
+    let next_letters = match node.next_letters {
+        Some(_) if child.is_done() => Some(vec![]),
+        Some(_) => match child.next_letters(lexicon.cols()) {
+            Ok(letters) => match config.narrow(&child, letters) {
+                Ok(letters) => Some(letters),
+                Err(reason) => {
+                    config.note_dead_end(Cause::Constraint(reason));
+                    if let Some(trace) = trace {
+                        trace.record(&child, TraceKind::Pruned(reason.to_string()));
+                    }
+                    return None;
+                }
+            },
+            Err(column) => {
+                config.note_dead_end(Cause::Column(column));
+                if let Some(trace) = trace {
+                    let reason = "a column has no letter left for the next row".to_string();
+                    trace.record(&child, TraceKind::Pruned(reason));
+                }
+                return None;
+            }
+        },
+        None => None,
+    };
+    Some(Node {
+        wb: child,
+        next_letters,
+    })
+}
+
+/// What a search carries from node to node besides its report: the RNG shuffling the
+/// candidates, if any, and its share of the memory gauge's frontier
+struct Walk<'a> {
+    rng: Option<StdRng>,
+    meter: Meter<'a>,
+}
+
+impl<'a> Walk<'a> {
+    fn new(config: &'a SolverConfig) -> Self {
+        Walk {
+            rng: config.rng(),
+            meter: Meter::new(config.memory.as_deref(), Consumer::Frontier),
+        }
+    }
+}
+
+/// Depth-first row search through `node` that skips boxes in the config's nogood cache and adds
+/// every box whose subtree it exhausts without a solution. Returns whether any solution was
+/// found below `node`, or breaks with the outcome as soon as `on_solution` stops the search or
+/// it is interrupted; an interrupted subtree is never recorded. `waiting` counts the siblings
+/// of `node` and of its ancestors still to be searched, the frontier of the iterative search.
+fn solve_with_nogoods<L: Lexicons>(
+    node: Node,
+    lexicon: &L,
+    config: &SolverConfig,
+    waiting: usize,
+    report: &mut SearchReport,
+    walk: &mut Walk,
+    on_solution: &mut impl FnMut(WordBox) -> ControlFlow<()>,
+) -> ControlFlow<Outcome, bool> {
+    if let Some(outcome) = config.interruption(report.stats.nodes) {
+        return ControlFlow::Break(outcome);
+    }
+    // A box per level above this one, and a row for each sibling still to be searched
+    config.check_memory(&mut walk.meter, report.stats.nodes, || {
+        let row = std::mem::size_of::<String>() + node.wb.col_dim;
+        (node.wb.rows.len() + 1) * node.estimated_bytes() + waiting * row
+    });
+    report.stats.nodes += 1;
+    report.stats.observe(waiting + 1, node.wb.rows.len());
+    if node.wb.is_done() {
+        if !config.accepts_leaf(&node.wb, lexicon, &mut report.stats) {
+            return ControlFlow::Continue(false);
+        }
+        report.solutions += 1;
+        if on_solution(node.wb).is_break() {
+            return ControlFlow::Break(Outcome::Stopped);
+        }
+        return ControlFlow::Continue(true);
+    }
+    let key = (
+        node.wb.row_dim - node.wb.rows.len(),
+        node.wb.open_column_prefixes(),
+    );
+    let nogoods = config.nogoods.as_deref();
+    if nogoods.is_some_and(|nogoods| nogoods.contains(&key)) {
+        if let Some(trace) = config.open_trace() {
+            let reason = "already known to be a dead end".to_string();
+            trace.record(&node.wb, TraceKind::Pruned(reason));
+        }
+        return ControlFlow::Continue(false);
+    }
+
+    let mut solved = false;
+    report.note_partial(&node.wb, lexicon, config);
+    let rows = next_rows(
+        &node,
+        lexicon,
+        config,
+        true,
+        &mut walk.rng,
+        &mut report.stats,
+    );
+    let mut left = rows.len();
+    // Rows come in stack push order, so walk them backwards to match the iterative search; each
+    // child is only built once the subtrees of the ones before it have been searched
+    for row in rows.into_iter().rev() {
+        left -= 1;
+        let Some(child) = child_node(&node, row, lexicon, config) else {
+            report.stats.rejected += 1;
+            continue;
+        };
+        let waiting = waiting + left;
+        solved |= solve_with_nogoods(child, lexicon, config, waiting, report, walk, on_solution)?;
+    }
+    if let (false, Some(nogoods)) = (solved, nogoods) {
+        nogoods.insert(key);
+    }
+    ControlFlow::Continue(solved)
+}
+
+/// The dead-end cause of a slot left without a candidate
+fn slot_cause(slot: Slot) -> Cause {
+    match slot {
+        Slot::Row(i) => Cause::Row(i),
+        Slot::Col(i) => Cause::Column(i),
+    }
+}
+
+/// Depth-first search that always branches on the open slot with the fewest candidates
+fn solve_most_constrained<L: Lexicons>(
+    wb: WordBox,
+    lexicon: &L,
+    config: &SolverConfig,
+    mut on_solution: impl FnMut(WordBox) -> ControlFlow<()>,
+) -> SearchReport {
+    let mut report = SearchReport::default();
+    let mut rng = config.rng();
+    let mut meter = Meter::new(config.memory.as_deref(), Consumer::Frontier);
+    let mut grids = vec![Grid::from_word_box(&wb)];
+    while let Some(grid) = grids.pop() {
+        if let Some(outcome) = config.interruption(report.stats.nodes) {
+            report.outcome = outcome;
+            break;
+        }
+        config.check_memory(&mut meter, report.stats.nodes, || {
+            (grids.len() + 1) * grid.estimated_bytes()
+        });
+        report.stats.nodes += 1;
+        report.stats.observe(grids.len() + 1, grid.filled_slots());
+        let open = grid.open_slots();
+        let Some((slot, count)) = open
+            .into_iter()
+            .map(|slot| (slot, grid.count_candidates(slot, lexicon)))
+            .min_by_key(|&(_, count)| count)
+        else {
+            let wb = grid.into_word_box();
+            if !config.accepts(&wb, lexicon) {
+                continue;
+            }
+            report.solutions += 1;
+            if on_solution(wb).is_break() {
+                report.outcome = Outcome::Stopped;
+                break;
+            }
+            continue;
+        };
+        if count == 0 {
+            config.note_dead_end(slot_cause(slot));
+            continue;
+        }
+
+        let mut candidates = grid.candidates(slot, lexicon);
+        if let Some(rng) = &mut rng {
+            candidates.shuffle(rng);
+        }
+        if let Some(frequency) = config.corpus_order() {
+            let score = |word: &String| match slot {
+                Slot::Row(i) => frequency.row_score(i, word),
+                Slot::Col(j) => frequency.col_score(j, word),
+            };
+            candidates = sort_least_constraining(candidates, score, true);
+        }
+        let tiered = Grid::lexicon(slot, lexicon);
+        let tier = |word: &str| match tiered.tiers() {
+            1 => 0,
+            _ => line_tier(tiered, word),
+        };
+        let mut children: Vec<(bool, usize, Grid)> = candidates
+            .iter()
+            .filter_map(|word| {
+                if config.forbids(word) {
+                    report.stats.forbidden += 1;
+                    config.note_dead_end(Cause::Constraint(FORBIDS_WORD));
+                    return None;
+                }
+                match grid.place_candidate(slot, word, lexicon, config.distinct_words) {
+                    Ok(child) => Some((config.is_theme(word), tier(word), child)),
+                    Err(refusal) => {
+                        match refusal {
+                            Refusal::Mismatched => report.stats.mismatched += 1,
+                            Refusal::Repeated => {
+                                config.note_dead_end(Cause::Constraint(REPEATS_WORD))
+                            }
+                            Refusal::DeadEnd(slot) => config.note_dead_end(slot_cause(slot)),
+                        }
+                        None
+                    }
+                }
+            })
+            .collect();
+        report.stats.candidates += candidates.len();
+        report.stats.rejected += candidates.len() - children.len();
+        if config.value_order == ValueOrder::LeastConstraining {
+            children = sort_least_constraining(
+                children,
+                |(_, _, next)| next.open_completions(lexicon),
+                true,
+            );
+        }
+        if tiered.tiers() > 1 {
+            children = prefer_low_tiers(children, |&(_, tier, _)| tier, true);
+        }
+        if config.theme_words.is_some() {
+            children = prefer_theme_words(children, |&(theme, _, _)| theme, true);
+        }
+        grids.extend(children.into_iter().map(|(_, _, child)| child));
+    }
+    report
+}
+
+/// Depth-first search for every complete cube reachable from `cube`, handing each to
+/// `on_solution` until it breaks or the search is cancelled. Only `forward_checking`,
+/// `distinct_words` and `cancellation` apply to cubes; every orientation of a cube is a
+/// separate solution.
+pub fn solve_word_cube<L: Lexicon>(
+    cube: WordCube,
+    lexicon: &L,
+    config: &SolverConfig,
+    on_solution: impl FnMut(WordCube) -> ControlFlow<()>,
+) -> SearchReport {
+    measured(|| search_word_cube(cube, lexicon, config, on_solution))
+}
+
+fn search_word_cube<L: Lexicon>(
+    cube: WordCube,
+    lexicon: &L,
+    config: &SolverConfig,
+    mut on_solution: impl FnMut(WordCube) -> ControlFlow<()>,
+) -> SearchReport {
+    let mut report = SearchReport::default();
+    // Every word of the right length is a candidate for every row
+    let words = lexicon.count_with_prefix("", cube.dim);
+    let mut cubes = vec![cube];
+    while let Some(cube) = cubes.pop() {
+        if let Some(outcome) = config.interruption(report.stats.nodes) {
+            report.outcome = outcome;
+            break;
+        }
+        report.stats.nodes += 1;
+        report.stats.observe(cubes.len() + 1, cube.rows.len());
+        if cube.is_done() {
+            report.solutions += 1;
+            if on_solution(cube).is_break() {
+                report.outcome = Outcome::Stopped;
+                break;
+            }
+            continue;
+        }
+        let before = cubes.len();
+        for word in cube.row_candidates(lexicon, config.forward_checking) {
+            let child = cube.add_word(word);
+            if !config.distinct_words || !child.has_repeated_words() {
+                cubes.push(child);
+            }
+        }
+        report.stats.candidates += words;
+        report.stats.rejected += words - (cubes.len() - before);
+    }
+    report
+}
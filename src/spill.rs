@@ -0,0 +1,203 @@
+use crate::frontier::{Frontier, Node};
+use crate::wordbox::WordBox;
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Spill files written by this process so far, to give each a name of its own
+static FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// When a breadth-first frontier moves boxes to disk, and where to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpillOptions {
+    pub threshold: usize, // boxes kept in memory before the ones pushed later are written out
+    pub dir: PathBuf,     // where the spill files go
+}
+
+/// A file of boxes a frontier wrote out, oldest first
+#[derive(Debug)]
+struct SpillFile {
+    path: PathBuf,
+    boxes: usize,
+}
+
+/// Expands boxes in the order they were pushed, like `BreadthFirst`, but keeps only about
+/// `threshold` of them in memory, twice that at most: once the boxes to be popped next fill
+/// up, the ones pushed after them gather in a batch that is written to a file of its own as
+/// soon as it is as big, and the files are read back in the order they were written once the
+/// boxes before them are gone. A box is written as its number of rows and a number for each
+/// row, standing for a word in a table the frontier keeps of every row it has written; the
+/// words are shared with the boxes in memory, so the table costs no more than the lexicon. The
+/// boxes come back without their letter sets, which the search works out again.
+///
+/// The files are deleted as they are read back, and the rest when the frontier is dropped, as
+/// it is when the search ends for any reason. Should writing a file fail, the frontier says so
+/// once and keeps every box in memory from then on.
+#[derive(Debug)]
+pub struct SpillingBreadthFirst {
+    options: SpillOptions,
+    empty: Option<WordBox>, // the box the boxes read back are built on: the first pushed, without its rows
+    head: VecDeque<Node>,   // popped first
+    files: VecDeque<SpillFile>, // popped next, in order
+    tail: VecDeque<Node>,   // pushed since the last file was written, popped last
+    words: Vec<Arc<str>>,   // every row written, by its number
+    numbers: HashMap<Arc<str>, u32>,
+    spilled: usize,
+    spilled_bytes: usize,
+    failed: bool,
+}
+
+impl SpillingBreadthFirst {
+    pub fn new(options: SpillOptions) -> Self {
+        SpillingBreadthFirst {
+            options: SpillOptions {
+                threshold: options.threshold.max(1),
+                ..options
+            },
+            empty: None,
+            head: VecDeque::new(),
+            files: VecDeque::new(),
+            tail: VecDeque::new(),
+            words: vec![],
+            numbers: HashMap::new(),
+            spilled: 0,
+            spilled_bytes: 0,
+            failed: false,
+        }
+    }
+
+    /// Write the boxes of `tail` to a new file, or keep them if the file cannot be written
+    fn spill_tail(&mut self) {
+        let mut bytes = vec![];
+        for node in &self.tail {
+            let rows = &node.wb.rows;
+            bytes.extend((rows.len() as u16).to_le_bytes());
+            for row in rows {
+                let number = match self.numbers.get(row) {
+                    Some(&number) => number,
+                    None => {
+                        let number = self.words.len() as u32;
+                        self.words.push(row.clone());
+                        self.numbers.insert(row.clone(), number);
+                        number
+                    }
+                };
+                bytes.extend(number.to_le_bytes());
+            }
+        }
+        let name = format!(
+            "wordbox-spill-{}-{}.bin",
+            std::process::id(),
+            FILES.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = self.options.dir.join(name);
+        let written = File::create(&path).and_then(|file| {
+            let mut file = BufWriter::new(file);
+            file.write_all(&bytes)?;
+            file.flush()
+        });
+        if let Err(err) = written {
+            eprintln!(
+                "Could not spill the frontier to {}, keeping it in memory: {}",
+                path.display(),
+                err
+            );
+            std::fs::remove_file(&path).ok();
+            self.failed = true;
+            return;
+        }
+        self.spilled += self.tail.len();
+        self.spilled_bytes += bytes.len();
+        self.files.push_back(SpillFile {
+            path,
+            boxes: self.tail.len(),
+        });
+        self.tail.clear();
+    }
+
+    /// The boxes of `file`, in the order they were written; the file is deleted
+    fn read_back(&self, file: SpillFile) -> VecDeque<Node> {
+        let mut bytes = Vec::new();
+        let read = File::open(&file.path).and_then(|mut f| f.read_to_end(&mut bytes));
+        std::fs::remove_file(&file.path).ok();
+        if let Err(err) = read {
+            panic!(
+                "could not read spilled boxes back from {}: {}",
+                file.path.display(),
+                err
+            );
+        }
+        let empty = self
+            .empty
+            .as_ref()
+            .expect("boxes were pushed before any was spilled");
+        let mut rest = bytes.as_slice();
+        let mut take = |n: usize| {
+            let (taken, after) = rest.split_at(n);
+            rest = after;
+            taken
+        };
+        (0..file.boxes)
+            .map(|_| {
+                let rows = u16::from_le_bytes(take(2).try_into().unwrap());
+                let wb = (0..rows).fold(empty.clone(), |wb, _| {
+                    let number = u32::from_le_bytes(take(4).try_into().unwrap());
+                    wb.with_row(self.words[number as usize].clone())
+                });
+                Node {
+                    wb,
+                    next_letters: None,
+                }
+            })
+            .collect()
+    }
+}
+
+impl Frontier for SpillingBreadthFirst {
+    fn push(&mut self, node: Node, _score: f64) {
+        if self.empty.is_none() {
+            let mut empty = node.wb.clone();
+            while empty.remove_last_word().is_some() {}
+            self.empty = Some(empty);
+        }
+        let threshold = self.options.threshold;
+        if self.files.is_empty() && self.tail.is_empty() && self.head.len() < threshold {
+            self.head.push_back(node);
+            return;
+        }
+        self.tail.push_back(node);
+        if self.tail.len() >= threshold && !self.failed {
+            self.spill_tail();
+        }
+    }
+
+    fn pop(&mut self) -> Option<Node> {
+        if self.head.is_empty() {
+            self.head = match self.files.pop_front() {
+                Some(file) => self.read_back(file),
+                None => std::mem::take(&mut self.tail),
+            };
+        }
+        self.head.pop_front()
+    }
+
+    fn len(&self) -> usize {
+        let spilled: usize = self.files.iter().map(|file| file.boxes).sum();
+        self.head.len() + spilled + self.tail.len()
+    }
+
+    fn spilled(&self) -> (usize, usize) {
+        (self.spilled, self.spilled_bytes)
+    }
+}
+
+impl Drop for SpillingBreadthFirst {
+    fn drop(&mut self) {
+        for file in &self.files {
+            std::fs::remove_file(&file.path).ok();
+        }
+    }
+}
@@ -0,0 +1,296 @@
+use crate::lexicon::{word_length, Lexicon, Lexicons};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+use std::time::Duration;
+
+/// Summary tables describing a filtered word list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LexiconStats {
+    pub total_words: usize,
+    pub length_counts: BTreeMap<usize, usize>, // word length -> number of words
+    pub position_frequency: Vec<BTreeMap<char, usize>>, // position -> letter -> occurrences
+    pub seed_len: usize,
+    pub seed_first_letters: BTreeMap<char, usize>, // first letter -> number of words of seed_len
+}
+
+impl LexiconStats {
+    /// Compute the statistics for `words`, counting first letters only for words of `seed_len`
+    pub fn from_words(words: &[String], seed_len: usize) -> Self {
+        let mut length_counts: BTreeMap<usize, usize> = BTreeMap::new();
+        let mut position_frequency: Vec<BTreeMap<char, usize>> = vec![];
+        let mut seed_first_letters: BTreeMap<char, usize> = BTreeMap::new();
+
+        for word in words {
+            *length_counts.entry(word_length(word)).or_default() += 1;
+            for (i, ch) in word.chars().enumerate() {
+                if position_frequency.len() <= i {
+                    position_frequency.push(BTreeMap::new());
+                }
+                *position_frequency[i].entry(ch).or_default() += 1;
+            }
+            if word_length(word) == seed_len {
+                if let Some(first) = word.chars().next() {
+                    *seed_first_letters.entry(first).or_default() += 1;
+                }
+            }
+        }
+
+        LexiconStats {
+            total_words: words.len(),
+            length_counts,
+            position_frequency,
+            seed_len,
+            seed_first_letters,
+        }
+    }
+
+    /// Number of words with the given length
+    pub fn words_of_length(&self, len: usize) -> usize {
+        self.length_counts.get(&len).copied().unwrap_or(0)
+    }
+}
+
+impl Display for LexiconStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Total words: {}", self.total_words)?;
+
+        writeln!(f, "Words by length:")?;
+        for (len, count) in &self.length_counts {
+            writeln!(f, "  {:>3}: {}", len, count)?;
+        }
+
+        writeln!(f, "Letter frequency by position:")?;
+        for (i, counts) in self.position_frequency.iter().enumerate() {
+            let mut letters: Vec<(&char, &usize)> = counts.iter().collect();
+            letters.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+            let line: Vec<String> = letters
+                .iter()
+                .map(|(ch, count)| format!("{}={}", ch, count))
+                .collect();
+            writeln!(f, "  {:>3}: {}", i, line.join(" "))?;
+        }
+
+        writeln!(f, "{}-letter words by first letter:", self.seed_len)?;
+        for (ch, count) in &self.seed_first_letters {
+            writeln!(f, "  {}: {}", ch, count)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The share of the words of one length that have each letter at each position
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PositionFrequency {
+    shares: Vec<HashMap<char, f64>>, // position -> letter -> share of the words
+}
+
+impl PositionFrequency {
+    /// The shares over the words of `lexicon` with `word_len` letters, each counted as often as
+    /// the lexicon holds it
+    pub fn of<L: Lexicon + ?Sized>(lexicon: &L, word_len: usize) -> Self {
+        let mut shares: Vec<HashMap<char, f64>> = vec![HashMap::new(); word_len];
+        let ids = lexicon.ids_with_prefix("", word_len);
+        for &id in &ids {
+            for (i, ch) in lexicon.word(id, word_len).chars().enumerate() {
+                *shares[i].entry(ch).or_default() += 1.0;
+            }
+        }
+        for position in &mut shares {
+            for share in position.values_mut() {
+                *share /= ids.len() as f64;
+            }
+        }
+        PositionFrequency { shares }
+    }
+
+    /// The share of the words with `ch` at `position`, 0 for a letter never found there
+    pub fn share(&self, position: usize, ch: char) -> f64 {
+        (self.shares.get(position))
+            .and_then(|letters| letters.get(&ch))
+            .copied()
+            .unwrap_or(0.0)
+    }
+}
+
+/// Letter frequencies by position in the row and column words of a box, worked out from the
+/// lexicons once per run and shared by every search (see `ValueOrder::CorpusFrequency`)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CorpusFrequency {
+    pub rows: PositionFrequency, // over the row words, as long as a row
+    pub cols: PositionFrequency, // over the column words, as long as a column
+}
+
+impl CorpusFrequency {
+    pub fn of<L: Lexicons>(lexicons: &L, row_dim: usize, col_dim: usize) -> Self {
+        CorpusFrequency {
+            rows: PositionFrequency::of(lexicons.rows(), col_dim),
+            cols: PositionFrequency::of(lexicons.cols(), row_dim),
+        }
+    }
+
+    /// How common the letters of `word` are where they cross the columns as row `row`: the
+    /// sum of the share of the column words having each letter at that position
+    pub fn row_score(&self, row: usize, word: &str) -> f64 {
+        word.chars().map(|ch| self.cols.share(row, ch)).sum()
+    }
+
+    /// Like `row_score`, for `word` as column `col`
+    pub fn col_score(&self, col: usize, word: &str) -> f64 {
+        word.chars().map(|ch| self.rows.share(col, ch)).sum()
+    }
+}
+
+/// Counters kept by a search as it runs, to see where the time goes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    pub nodes: usize,           // boxes taken off the frontier and expanded or checked
+    pub candidates: usize,      // words generated as possible next rows (or slots, with mcv)
+    pub rejected: usize,        // candidates that did not make it into a child box
+    pub lexicon_queries: usize, // calls into the lexicon on the searching thread
+    pub max_frontier: usize,    // most boxes, or rows yet to be tried, waiting at once
+    pub max_depth: usize,       // most rows (or slots, with mcv) filled in any box reached
+    pub duplicates: usize,      // solutions a run dropped as repeats of ones already reported
+    pub mismatched: usize,      // candidates skipped for not having the length asked for
+    pub forbidden: usize,       // candidates and complete boxes turned away for a forbidden word
+    pub cache_hits: usize,      // lexicon queries answered by the query cache
+    pub cache_misses: usize,    // lexicon queries the query cache passed on
+    pub spilled: usize,         // boxes a frontier wrote to disk to be read back later
+    pub spilled_bytes: usize,   // bytes of those boxes on disk
+    pub elapsed: Duration,      // time spent searching, summed over merged searches
+}
+
+impl SearchStats {
+    /// The counters of this search and `other` together; times add up, as for work split
+    /// across threads, while the maxima are the larger of the two
+    pub fn merge(self, other: SearchStats) -> SearchStats {
+        SearchStats {
+            nodes: self.nodes + other.nodes,
+            candidates: self.candidates + other.candidates,
+            rejected: self.rejected + other.rejected,
+            lexicon_queries: self.lexicon_queries + other.lexicon_queries,
+            max_frontier: self.max_frontier.max(other.max_frontier),
+            max_depth: self.max_depth.max(other.max_depth),
+            duplicates: self.duplicates + other.duplicates,
+            mismatched: self.mismatched + other.mismatched,
+            forbidden: self.forbidden + other.forbidden,
+            cache_hits: self.cache_hits + other.cache_hits,
+            cache_misses: self.cache_misses + other.cache_misses,
+            spilled: self.spilled + other.spilled,
+            spilled_bytes: self.spilled_bytes + other.spilled_bytes,
+            elapsed: self.elapsed + other.elapsed,
+        }
+    }
+
+    /// Note how many boxes are waiting and how deep the box at hand is
+    pub(crate) fn observe(&mut self, frontier: usize, depth: usize) {
+        self.max_frontier = self.max_frontier.max(frontier);
+        self.max_depth = self.max_depth.max(depth);
+    }
+
+    /// The counters as a JSON object, with the elapsed time in seconds
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"nodes\":{},\"candidates\":{},\"rejected\":{},\"lexicon_queries\":{},\"max_frontier\":{},\"max_depth\":{},\"duplicates\":{},\"mismatched\":{},\"forbidden\":{},\"cache_hits\":{},\"cache_misses\":{},\"spilled\":{},\"spilled_bytes\":{},\"elapsed_secs\":{}}}",
+            self.nodes,
+            self.candidates,
+            self.rejected,
+            self.lexicon_queries,
+            self.max_frontier,
+            self.max_depth,
+            self.duplicates,
+            self.mismatched,
+            self.forbidden,
+            self.cache_hits,
+            self.cache_misses,
+            self.spilled,
+            self.spilled_bytes,
+            self.elapsed.as_secs_f64()
+        )
+    }
+}
+
+impl Display for SearchStats {
+    /// The counters on one line
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} nodes, {} candidates ({} rejected), {} lexicon queries, ",
+            self.nodes, self.candidates, self.rejected, self.lexicon_queries
+        )?;
+        let lookups = self.cache_hits + self.cache_misses;
+        if lookups > 0 {
+            write!(
+                f,
+                "{} cache hits in {} lookups ({:.1}%), ",
+                self.cache_hits,
+                lookups,
+                100.0 * self.cache_hits as f64 / lookups as f64
+            )?;
+        }
+        if self.forbidden > 0 {
+            write!(f, "{} turned away for a forbidden word, ", self.forbidden)?;
+        }
+        if self.spilled > 0 {
+            write!(
+                f,
+                "{} boxes spilled to disk ({} bytes), ",
+                self.spilled, self.spilled_bytes
+            )?;
+        }
+        write!(
+            f,
+            "frontier up to {}, depth {}, {} duplicates, {} mismatched, {:?}",
+            self.max_frontier, self.max_depth, self.duplicates, self.mismatched, self.elapsed
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexicon::{BuildLexicon, RowColumnLexicons, VecLexicon};
+
+    fn lexicon(words: &[&str]) -> VecLexicon {
+        let words: Vec<String> = words.iter().map(|word| word.to_string()).collect();
+        let lengths = words.iter().map(|word| word_length(word)).collect();
+        VecLexicon::initialize(words, lengths)
+    }
+
+    #[test]
+    fn letters_are_counted_at_each_position() {
+        let words = lexicon(&["caña", "cama", "mapa", "año"]);
+        let frequency = PositionFrequency::of(&words, 4);
+        assert_eq!(frequency.share(0, 'c'), 2.0 / 3.0);
+        assert_eq!(frequency.share(0, 'm'), 1.0 / 3.0);
+        assert_eq!(frequency.share(1, 'a'), 1.0);
+        assert_eq!(frequency.share(2, 'ñ'), 1.0 / 3.0);
+        assert_eq!(frequency.share(3, 'a'), 1.0);
+        // Words of other lengths do not count, and nothing is found past the last position
+        assert_eq!(frequency.share(0, 'a'), 0.0);
+        assert_eq!(frequency.share(4, 'a'), 0.0);
+    }
+
+    #[test]
+    fn rows_are_scored_by_the_columns_they_cross() {
+        // Rows of four letters cross columns of three
+        let rows = lexicon(&["mapa", "mesa"]);
+        let cols = lexicon(&["sol", "sal", "mar"]);
+        let lexicons = RowColumnLexicons {
+            rows: &rows,
+            cols: &cols,
+        };
+        let frequency = CorpusFrequency::of(&lexicons, 3, 4);
+        assert_eq!(frequency.rows.share(1, 'e'), 0.5);
+        assert_eq!(frequency.cols.share(0, 's'), 2.0 / 3.0);
+        assert_eq!(frequency.cols.share(1, 'a'), 2.0 / 3.0);
+
+        // As the first row each letter counts for the columns starting with it, so only the s
+        // of "sala" does; as the second, the a's of "sala" count twice
+        assert_eq!(frequency.row_score(0, "sala"), 2.0 / 3.0);
+        assert_eq!(frequency.row_score(1, "sala"), 4.0 / 3.0);
+        // And columns by the rows they cross: every row starts with m, half have an a second
+        assert_eq!(frequency.col_score(0, "mar"), 1.0);
+        assert_eq!(frequency.col_score(1, "mar"), 0.5);
+    }
+}
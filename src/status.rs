@@ -0,0 +1,210 @@
+use crate::runner::RunState;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// The counters of a run at one moment, as a status line reports them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatusSnapshot {
+    pub seeds_done: usize,
+    pub seeds_total: usize,
+    pub solutions: usize,
+    pub nodes: usize, // nodes of the seeds done; a seed's nodes count once its search is over
+    pub elapsed: Duration,
+}
+
+impl StatusSnapshot {
+    /// The counters of `state` now, for a run of `seeds_total` seeds started at `start`
+    pub fn of(state: &RunState, seeds_total: usize, start: Instant) -> Self {
+        StatusSnapshot {
+            seeds_done: state.seeds_done(),
+            seeds_total,
+            solutions: state.solutions(),
+            nodes: state.nodes(),
+            elapsed: start.elapsed(),
+        }
+    }
+
+    /// The share of the seeds done, from 0 to 100; seeds searched again count once
+    pub fn percent(&self) -> f64 {
+        match self.seeds_total {
+            0 => 100.0,
+            total => 100.0 * self.seeds_done.min(total) as f64 / total as f64,
+        }
+    }
+
+    pub fn nodes_per_sec(&self) -> f64 {
+        match self.elapsed.as_secs_f64() {
+            secs if secs > 0.0 => self.nodes as f64 / secs,
+            _ => 0.0,
+        }
+    }
+
+    /// The time left if every seed to come takes as long as the seeds done took on average;
+    /// None until a seed is done
+    pub fn eta(&self) -> Option<Duration> {
+        let left = self.seeds_total.saturating_sub(self.seeds_done);
+        (self.seeds_done > 0).then(|| self.elapsed.mul_f64(left as f64 / self.seeds_done as f64))
+    }
+}
+
+impl Display for StatusSnapshot {
+    /// The counters on one line of `key=value` pairs, always in the same order, with times in
+    /// seconds and an ETA of `unknown` until a seed is done
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let eta = match self.eta() {
+            Some(eta) => format!("{:.1}", eta.as_secs_f64()),
+            None => "unknown".to_string(),
+        };
+        write!(
+            f,
+            "status seeds_done={} seeds_total={} percent={:.1} solutions={} nodes={} nodes_per_sec={:.0} elapsed_secs={:.1} eta_secs={}",
+            self.seeds_done,
+            self.seeds_total,
+            self.percent(),
+            self.solutions,
+            self.nodes,
+            self.nodes_per_sec(),
+            self.elapsed.as_secs_f64(),
+            eta
+        )
+    }
+}
+
+/// Where status lines go
+#[derive(Debug, Clone)]
+pub enum StatusSink {
+    /// A line of its own on stderr each time
+    Stderr,
+    /// The file, rewritten each time to hold only the latest line. It is written beside the
+    /// file and renamed over it, so a reader never sees half a line.
+    File(PathBuf),
+}
+
+impl StatusSink {
+    fn write(&self, line: &str) {
+        match self {
+            StatusSink::Stderr => eprintln!("{}", line),
+            StatusSink::File(path) => {
+                let mut temp = path.clone().into_os_string();
+                temp.push(".tmp");
+                let written = std::fs::write(&temp, format!("{}\n", line))
+                    .and_then(|_| std::fs::rename(&temp, path));
+                if let Err(err) = written {
+                    eprintln!("Could not write status file {}: {}", path.display(), err);
+                }
+            }
+        }
+    }
+}
+
+/// Reports the status of a run every `interval` from a thread of its own, reading the run's
+/// counters as the workers update them, and once more when dropped, after the run is over
+pub struct StatusReporter {
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl StatusReporter {
+    pub fn start(
+        state: Arc<RunState>,
+        seeds_total: usize,
+        interval: Duration,
+        sink: StatusSink,
+    ) -> Self {
+        let start = Instant::now();
+        let (stop, stopped) = mpsc::channel::<()>();
+        let thread = std::thread::spawn(move || loop {
+            // The sender is dropped when the run is over, which ends the wait early
+            let over = stopped.recv_timeout(interval) != Err(RecvTimeoutError::Timeout);
+            sink.write(&StatusSnapshot::of(&state, seeds_total, start).to_string());
+            if over {
+                break;
+            }
+        });
+        StatusReporter {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for StatusReporter {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(seeds_done: usize, seeds_total: usize, elapsed_secs: u64) -> StatusSnapshot {
+        StatusSnapshot {
+            seeds_done,
+            seeds_total,
+            solutions: 12,
+            nodes: 50_000,
+            elapsed: Duration::from_secs(elapsed_secs),
+        }
+    }
+
+    #[test]
+    fn status_lines_are_key_value_pairs_in_a_fixed_order() {
+        assert_eq!(
+            snapshot(25, 100, 20).to_string(),
+            "status seeds_done=25 seeds_total=100 percent=25.0 solutions=12 nodes=50000 \
+             nodes_per_sec=2500 elapsed_secs=20.0 eta_secs=60.0"
+        );
+        // Until a seed is done there is nothing to time the rest by
+        assert_eq!(
+            snapshot(0, 100, 0).to_string(),
+            "status seeds_done=0 seeds_total=100 percent=0.0 solutions=12 nodes=50000 \
+             nodes_per_sec=0 elapsed_secs=0.0 eta_secs=unknown"
+        );
+    }
+
+    #[test]
+    fn the_eta_assumes_every_seed_costs_the_same() {
+        assert_eq!(snapshot(1, 4, 10).eta(), Some(Duration::from_secs(30)));
+        assert_eq!(snapshot(4, 4, 10).eta(), Some(Duration::ZERO));
+        assert_eq!(snapshot(0, 4, 10).eta(), None);
+        // Seeds searched again may count past the total
+        assert_eq!(snapshot(5, 4, 10).percent(), 100.0);
+        assert_eq!(snapshot(5, 4, 10).eta(), Some(Duration::ZERO));
+        assert_eq!(snapshot(0, 0, 10).percent(), 100.0);
+    }
+
+    #[test]
+    fn the_status_file_holds_the_last_line() {
+        let dir = std::env::temp_dir().join(format!("wordbox-status-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("status.txt");
+        let state = Arc::new(RunState::default());
+        let reporter = StatusReporter::start(
+            state,
+            7,
+            Duration::from_millis(5),
+            StatusSink::File(path.clone()),
+        );
+        std::thread::sleep(Duration::from_millis(30));
+        drop(reporter);
+        let status = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(status.lines().count(), 1);
+        assert!(
+            status.starts_with("status seeds_done=0 seeds_total=7 percent=0.0 solutions=0 "),
+            "{}",
+            status
+        );
+        let mut temp = path.into_os_string();
+        temp.push(".tmp");
+        assert!(!PathBuf::from(temp).exists());
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+/// Category tags attached to words (e.g. `offensive`, `vulgar`), keyed on the normalized word
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WordTags {
+    tags: HashMap<String, Vec<String>>,
+}
+
+impl WordTags {
+    /// Load tags from a file of `word tag[,tag...]` lines; blank lines and `#` comments are skipped
+    pub fn load(filename: &str) -> Self {
+        let file: File = File::open(filename).expect("Could not open tag file");
+        let reader = BufReader::new(file);
+        Self::from_lines(reader.lines().map_while(Result::ok))
+    }
+
+    pub fn from_lines(lines: impl IntoIterator<Item = String>) -> Self {
+        let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+        for line in lines {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let word = match fields.next() {
+                Some(word) => Self::normalize(word),
+                None => continue,
+            };
+            let entry = tags.entry(word).or_default();
+            for tag in fields.flat_map(|field| field.split(',')) {
+                let tag = Self::normalize(tag);
+                if !tag.is_empty() && !entry.contains(&tag) {
+                    entry.push(tag);
+                }
+            }
+        }
+        WordTags { tags }
+    }
+
+    fn normalize(s: &str) -> String {
+        s.trim().to_lowercase()
+    }
+
+    /// Tags attached to `word`, looked up after normalization
+    pub fn tags_for(&self, word: &str) -> &[String] {
+        self.tags
+            .get(&Self::normalize(word))
+            .map(|tags| tags.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Split off every word carrying a blocked tag, counting removals per tag
+    pub fn remove_blocked(
+        &self,
+        words: Vec<String>,
+        blocked: &[String],
+    ) -> (Vec<String>, BTreeMap<String, usize>) {
+        let blocked: Vec<String> = blocked.iter().map(|tag| Self::normalize(tag)).collect();
+        let mut removed: BTreeMap<String, usize> = BTreeMap::new();
+        let kept = words
+            .into_iter()
+            .filter(|word| {
+                let hits: Vec<&String> = self
+                    .tags_for(word)
+                    .iter()
+                    .filter(|tag| blocked.contains(tag))
+                    .collect();
+                for tag in &hits {
+                    *removed.entry(tag.to_string()).or_default() += 1;
+                }
+                hits.is_empty()
+            })
+            .collect();
+        (kept, removed)
+    }
+}
@@ -0,0 +1,507 @@
+use crate::alphabet::Alphabet;
+use crate::blocks::{BlockMask, BLOCK};
+use crate::digraphs;
+use crate::lexicon::{word_length, WILDCARD};
+use crate::masks::{CellMask, CellMasks, LetterMask};
+use std::fmt::Display;
+
+/// Characters marking a cell the solver is free to fill
+pub(crate) const OPEN_CELLS: [char; 3] = ['.', '_', WILDCARD];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    Empty,
+    RaggedRow {
+        row: usize,
+        len: usize,
+        expected: usize,
+    },
+    UnsupportedCharacter {
+        ch: char,
+        row: usize,
+        col: usize,
+    },
+    FixOutOfRange {
+        fix: FixedWord,
+        lines: usize,
+    },
+    FixLength {
+        fix: FixedWord,
+        expected: usize,
+    },
+    FixUnsupportedCharacter {
+        fix: FixedWord,
+        ch: char,
+    },
+    FixConflict {
+        fix: FixedWord,
+        row: usize,
+        col: usize,
+        locked: char,
+    },
+    MaskOutOfRange {
+        mask: CellMask,
+    },
+    MaskConflict {
+        row: usize,
+        col: usize,
+        locked: char,
+        mask: LetterMask,
+    },
+    MaskEmpty {
+        row: usize,
+        col: usize,
+    },
+}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TemplateError::Empty => write!(f, "the template has no cells"),
+            TemplateError::RaggedRow { row, len, expected } => write!(
+                f,
+                "row {} has {} cells but the first row has {}",
+                row, len, expected
+            ),
+            TemplateError::UnsupportedCharacter { ch, row, col } => write!(
+                f,
+                "unsupported character {:?} at row {}, column {} (use letters of the alphabet, '.' for an open cell or '{}' for a block)",
+                ch, row, col, BLOCK
+            ),
+            TemplateError::FixOutOfRange { fix, lines } => write!(
+                f,
+                "{}: the box only has {} {}s",
+                fix,
+                lines,
+                fix.line.name()
+            ),
+            TemplateError::FixLength { fix, expected } => write!(
+                f,
+                "{}: the word has {} letters but a {} has {} cells",
+                fix,
+                word_length(&fix.word),
+                fix.line.name(),
+                expected
+            ),
+            TemplateError::FixUnsupportedCharacter { fix, ch } => {
+                write!(f, "{}: {:?} is not in the alphabet", fix, ch)
+            }
+            TemplateError::FixConflict {
+                fix,
+                row,
+                col,
+                locked,
+            } => {
+                let letter = fix.letter_at(*row, *col).unwrap_or(WILDCARD);
+                write!(
+                    f,
+                    "{}: the cell at row {}, column {} is already {:?}, so it cannot be {:?}",
+                    fix, row, col, locked, letter
+                )
+            }
+            TemplateError::MaskOutOfRange { mask } => {
+                write!(f, "mask {}: the cell is outside the box", mask)
+            }
+            TemplateError::MaskConflict {
+                row,
+                col,
+                locked,
+                mask,
+            } if *locked == BLOCK => write!(
+                f,
+                "the cell at row {}, column {} is blocked, so it cannot take the letters {}",
+                row, col, mask
+            ),
+            TemplateError::MaskConflict {
+                row,
+                col,
+                locked,
+                mask,
+            } => write!(
+                f,
+                "the cell at row {}, column {} is {:?}, which its mask {} does not allow",
+                row, col, locked, mask
+            ),
+            TemplateError::MaskEmpty { row, col } => write!(
+                f,
+                "the masks on the cell at row {}, column {} leave it no letter",
+                row, col
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// A line of a box: one of its rows or one of its columns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Line {
+    Row,
+    Col,
+}
+
+impl Display for Line {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Line::Row => write!(f, "row"),
+            Line::Col => write!(f, "col"),
+        }
+    }
+}
+
+impl Line {
+    /// The line spelled out, for messages
+    fn name(&self) -> &'static str {
+        match self {
+            Line::Row => "row",
+            Line::Col => "column",
+        }
+    }
+}
+
+/// A word that has to fill a whole row or column, written `row:2:planet` or `col:0:stones`
+/// with lines counted from 0
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixedWord {
+    pub line: Line,
+    pub index: usize,
+    pub word: String,
+}
+
+impl std::str::FromStr for FixedWord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let [line, index, word] = s.splitn(3, ':').collect::<Vec<_>>()[..] else {
+            return Err(format!(
+                "expected LINE:INDEX:WORD such as row:2:planet, found {:?}",
+                s
+            ));
+        };
+        let line = match line {
+            "row" => Line::Row,
+            "col" => Line::Col,
+            _ => return Err(format!("unknown line {:?} (expected row or col)", line)),
+        };
+        let index = index
+            .parse()
+            .map_err(|_| format!("invalid {} number {:?}", line, index))?;
+        if word.is_empty() {
+            return Err(format!("no word given for {} {}", line, index));
+        }
+        Ok(FixedWord {
+            line,
+            index,
+            word: word.to_lowercase(),
+        })
+    }
+}
+
+impl Display for FixedWord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.line, self.index, self.word)
+    }
+}
+
+impl FixedWord {
+    /// The cells the word fills, in reading order
+    fn cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..word_length(&self.word)).map(|k| match self.line {
+            Line::Row => (self.index, k),
+            Line::Col => (k, self.index),
+        })
+    }
+
+    /// The letter the word puts in a cell, if it fills that cell
+    fn letter_at(&self, row: usize, col: usize) -> Option<char> {
+        let k = match self.line {
+            Line::Row if row == self.index => col,
+            Line::Col if col == self.index => row,
+            _ => return None,
+        };
+        self.word.chars().nth(k)
+    }
+}
+
+/// Letters locked into specific cells of a box before solving, the cells that are blocked, and
+/// the letters other cells are limited to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    row_dim: usize,
+    col_dim: usize,
+    cells: Vec<Option<char>>, // row-major, with `BLOCK` for blocked cells
+    masks: Option<Vec<Option<LetterMask>>>, // row-major, `None` unless some cell has a mask
+}
+
+impl Template {
+    /// Read a template with one line per row, such as `c..t\n.#..`; a literal `\n`
+    /// is accepted as a line break so templates can be given on the command line. Letters are
+    /// lowercased and have to belong to `alphabet`.
+    pub fn parse(text: &str, alphabet: &Alphabet) -> Result<Self, TemplateError> {
+        let text = text.replace("\\n", "\n");
+        let lines: Vec<&str> = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        let col_dim = lines.first().map_or(0, |line| line.chars().count());
+        if col_dim == 0 {
+            return Err(TemplateError::Empty);
+        }
+
+        let mut cells = Vec::with_capacity(lines.len() * col_dim);
+        for (row, line) in lines.iter().enumerate() {
+            let len = line.chars().count();
+            if len != col_dim {
+                return Err(TemplateError::RaggedRow {
+                    row,
+                    len,
+                    expected: col_dim,
+                });
+            }
+            for (col, ch) in line.chars().enumerate() {
+                if OPEN_CELLS.contains(&ch) {
+                    cells.push(None);
+                } else if ch == BLOCK {
+                    cells.push(Some(BLOCK));
+                } else {
+                    match ch.to_lowercase().next() {
+                        Some(letter) if alphabet.contains(letter) => cells.push(Some(letter)),
+                        _ => return Err(TemplateError::UnsupportedCharacter { ch, row, col }),
+                    }
+                }
+            }
+        }
+        Ok(Template {
+            row_dim: lines.len(),
+            col_dim,
+            cells,
+            masks: None,
+        })
+    }
+
+    /// A template of the given shape with no cell locked
+    pub fn open(row_dim: usize, col_dim: usize) -> Self {
+        Template {
+            row_dim,
+            col_dim,
+            cells: vec![None; row_dim * col_dim],
+            masks: None,
+        }
+    }
+
+    /// The template with `fix` locked into its row or column. The word has to span the whole
+    /// line, use letters of `alphabet`, and agree with every cell locked already, be it by the
+    /// template or by an earlier fix.
+    pub fn with_fixed(
+        mut self,
+        fix: &FixedWord,
+        alphabet: &Alphabet,
+    ) -> Result<Self, TemplateError> {
+        let (lines, expected) = match fix.line {
+            Line::Row => (self.row_dim, self.col_dim),
+            Line::Col => (self.col_dim, self.row_dim),
+        };
+        if fix.index >= lines {
+            return Err(TemplateError::FixOutOfRange {
+                fix: fix.clone(),
+                lines,
+            });
+        }
+        if word_length(&fix.word) != expected {
+            return Err(TemplateError::FixLength {
+                fix: fix.clone(),
+                expected,
+            });
+        }
+        if let Some(ch) = fix.word.chars().find(|&ch| !alphabet.contains(ch)) {
+            return Err(TemplateError::FixUnsupportedCharacter {
+                fix: fix.clone(),
+                ch,
+            });
+        }
+        for ((row, col), letter) in fix.cells().zip(fix.word.chars()) {
+            let cell = &mut self.cells[row * self.col_dim + col];
+            match *cell {
+                Some(locked) if locked != letter => {
+                    return Err(TemplateError::FixConflict {
+                        fix: fix.clone(),
+                        row,
+                        col,
+                        locked,
+                    })
+                }
+                _ => *cell = Some(letter),
+            }
+        }
+        Ok(self)
+    }
+
+    /// The template with each cell of `masks` limited to the letters its mask allows. A cell
+    /// already locked has to hold one of them, and a cell given several masks takes the
+    /// letters they all allow.
+    pub fn with_masks(mut self, masks: &CellMasks) -> Result<Self, TemplateError> {
+        let cells = self.cells.len();
+        for cell in &masks.masks {
+            let (row, col) = (cell.row, cell.col);
+            if row >= self.row_dim || col >= self.col_dim {
+                return Err(TemplateError::MaskOutOfRange { mask: cell.clone() });
+            }
+            let slot =
+                &mut self.masks.get_or_insert_with(|| vec![None; cells])[row * self.col_dim + col];
+            let mask = match slot.take() {
+                Some(mask) => mask.intersect(&cell.mask),
+                None => cell.mask.clone(),
+            };
+            if mask.is_empty() {
+                return Err(TemplateError::MaskEmpty { row, col });
+            }
+            if let Some(locked) = self.cells[row * self.col_dim + col] {
+                if locked == BLOCK || !mask.allows(locked) {
+                    return Err(TemplateError::MaskConflict {
+                        row,
+                        col,
+                        locked,
+                        mask,
+                    });
+                }
+            }
+            *slot = Some(mask);
+        }
+        Ok(self)
+    }
+
+    pub fn row_dim(&self) -> usize {
+        self.row_dim
+    }
+
+    pub fn col_dim(&self) -> usize {
+        self.col_dim
+    }
+
+    /// The letter locked into a cell, or `BLOCK` if it is blocked
+    pub fn get(&self, row: usize, col: usize) -> Option<char> {
+        self.cells[row * self.col_dim + col]
+    }
+
+    /// Lock a letter, or `BLOCK`, into a cell, or open it again with `None`
+    pub fn set(&mut self, row: usize, col: usize, cell: Option<char>) {
+        self.cells[row * self.col_dim + col] = cell;
+    }
+
+    /// The letters a cell is limited to, if it has a mask
+    pub fn mask(&self, row: usize, col: usize) -> Option<&LetterMask> {
+        self.masks.as_ref()?[row * self.col_dim + col].as_ref()
+    }
+
+    /// Whether any cell has a mask
+    pub fn has_masks(&self) -> bool {
+        self.masks.is_some()
+    }
+
+    /// The masks of the cells that have one, row by row
+    pub fn cell_masks(&self) -> CellMasks {
+        let masks = (0..self.row_dim)
+            .flat_map(|row| (0..self.col_dim).map(move |col| (row, col)))
+            .filter_map(|(row, col)| {
+                let mask = self.mask(row, col)?.clone();
+                Some(CellMask { row, col, mask })
+            })
+            .collect();
+        CellMasks { masks }
+    }
+
+    /// Whether `ch` may go in a cell, as far as its mask goes
+    pub fn allows(&self, row: usize, col: usize, ch: char) -> bool {
+        self.mask(row, col).is_none_or(|mask| mask.allows(ch))
+    }
+
+    pub fn has_blocks(&self) -> bool {
+        self.cells.contains(&Some(BLOCK))
+    }
+
+    pub fn block_mask(&self) -> BlockMask {
+        let blocked = self.cells.iter().map(|&cell| cell == Some(BLOCK)).collect();
+        BlockMask::new(self.row_dim, self.col_dim, blocked)
+    }
+
+    /// Whether every cell is locked, leaving the solver only to check the box
+    pub fn is_full(&self) -> bool {
+        self.cells.iter().all(Option::is_some)
+    }
+
+    /// The first cell whose letter differs from the one mirrored across the main diagonal,
+    /// which no symmetric box can satisfy
+    pub fn mirror_conflict(&self) -> Option<(usize, usize)> {
+        (0..self.row_dim)
+            .flat_map(|row| (0..self.col_dim).map(move |col| (row, col)))
+            .find(|&(row, col)| {
+                let mirrored = (col < self.row_dim && row < self.col_dim)
+                    .then(|| self.get(col, row))
+                    .flatten();
+                matches!((self.get(row, col), mirrored), (Some(a), Some(b)) if a != b)
+            })
+    }
+
+    /// Whether the template reads the same across its main diagonal
+    pub fn is_self_transpose(&self) -> bool {
+        self.row_dim == self.col_dim
+            && (0..self.row_dim)
+                .all(|row| (0..row).all(|col| self.get(row, col) == self.get(col, row)))
+    }
+
+    /// Each cell's letter, or else the letter of the mirrored cell, and the letters both its
+    /// own mask and the mirrored cell's allow (square templates only)
+    pub fn mirrored(&self) -> Template {
+        let cells =
+            || (0..self.row_dim).flat_map(|row| (0..self.col_dim).map(move |col| (row, col)));
+        Template {
+            cells: cells()
+                .map(|(row, col)| self.get(row, col).or_else(|| self.get(col, row)))
+                .collect(),
+            masks: self.masks.as_ref().map(|_| {
+                cells()
+                    .map(
+                        |(row, col)| match (self.mask(row, col), self.mask(col, row)) {
+                            (Some(a), Some(b)) => Some(a.intersect(b)),
+                            (a, b) => a.or(b).cloned(),
+                        },
+                    )
+                    .collect()
+            }),
+            ..self.clone()
+        }
+    }
+
+    pub(crate) fn transpose(&self) -> Template {
+        Template {
+            row_dim: self.col_dim,
+            col_dim: self.row_dim,
+            cells: (0..self.col_dim)
+                .flat_map(|col| (0..self.row_dim).map(move |row| self.get(row, col)))
+                .collect(),
+            masks: self.masks.as_ref().map(|_| {
+                (0..self.col_dim)
+                    .flat_map(|col| (0..self.row_dim).map(move |row| self.mask(row, col).cloned()))
+                    .collect()
+            }),
+        }
+    }
+}
+
+/// The template in the text format `parse` reads, one line per row with `.` for open cells and
+/// tiles spelled out; masks have no place in that format and are left out
+impl Display for Template {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in 0..self.row_dim {
+            for col in 0..self.col_dim {
+                match self.get(row, col) {
+                    Some(cell) => write!(f, "{}", digraphs::cell_text(cell))?,
+                    None => write!(f, ".")?,
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
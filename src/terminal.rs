@@ -0,0 +1,99 @@
+/// A colour a cell of a printed box is painted in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Cyan,
+    Yellow,
+    Green,
+}
+
+/// `text` in bold `color`; as it is without the `tui` feature
+#[cfg(feature = "tui")]
+pub fn bold(text: String, color: Color) -> String {
+    use crossterm::style::Stylize;
+    match color {
+        Color::Cyan => text.cyan().bold().to_string(),
+        Color::Yellow => text.yellow().bold().to_string(),
+        Color::Green => text.green().bold().to_string(),
+    }
+}
+
+#[cfg(not(feature = "tui"))]
+pub fn bold(text: String, _color: Color) -> String {
+    text
+}
+
+/// Clear the terminal, so the next box is drawn at the top; without the `tui` feature nothing is
+/// cleared and each box follows the last
+#[cfg(feature = "tui")]
+pub fn clear() {
+    use crossterm::{execute, terminal};
+    execute!(std::io::stdout(), terminal::Clear(terminal::ClearType::All)).ok();
+}
+
+#[cfg(not(feature = "tui"))]
+pub fn clear() {}
+
+/// Print `text` from the saved cursor position, over whatever was drawn there last; without the
+/// `tui` feature it is printed where the cursor is
+#[cfg(feature = "tui")]
+pub fn redraw(text: &str) {
+    use crossterm::{cursor, execute, style};
+    execute!(
+        std::io::stdout(),
+        cursor::RestorePosition,
+        style::Print(text)
+    )
+    .ok();
+}
+
+#[cfg(not(feature = "tui"))]
+pub fn redraw(text: &str) {
+    print!("{}", text);
+}
+
+/// How far through its seeds a run is, shown as a bar on stderr with the `progress` feature
+/// and otherwise as a line on stderr each time another tenth of the seeds is done
+pub struct Progress {
+    #[cfg(feature = "progress")]
+    bar: tqdm::Pbar,
+    #[cfg(not(feature = "progress"))]
+    total: usize,
+    #[cfg(not(feature = "progress"))]
+    done: usize,
+}
+
+impl Progress {
+    /// Progress through `total` seeds, none of them done yet
+    pub fn new(total: usize) -> Self {
+        Progress {
+            #[cfg(feature = "progress")]
+            bar: tqdm::pbar(Some(total)),
+            #[cfg(not(feature = "progress"))]
+            total,
+            #[cfg(not(feature = "progress"))]
+            done: 0,
+        }
+    }
+
+    /// Count `n` more seeds as done
+    #[cfg(feature = "progress")]
+    pub fn update(&mut self, n: usize) {
+        self.bar.update(n).ok();
+    }
+
+    #[cfg(not(feature = "progress"))]
+    pub fn update(&mut self, n: usize) {
+        let tenth = |done: usize| (done * 10).checked_div(self.total).unwrap_or(10);
+        let before = tenth(self.done);
+        self.done += n;
+        if tenth(self.done) > before {
+            eprintln!("searched {} of {} seeds", self.done, self.total);
+        }
+    }
+}
+
+impl std::fmt::Debug for Progress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Progress").finish_non_exhaustive()
+    }
+}
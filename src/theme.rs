@@ -0,0 +1,78 @@
+use crate::blocks::BLOCK;
+use crate::lexicon::{matches_prefix, word_length, WILDCARD};
+use crate::wordbox::WordBox;
+use std::collections::HashSet;
+
+/// Words solutions should use as many of as they can, e.g. a list of animal names. The search
+/// tries them before other words, and can be made to reject boxes holding too few of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ThemeWords {
+    words: HashSet<String>,
+}
+
+impl ThemeWords {
+    /// The theme words, normalized the way the word list is
+    pub fn new(words: impl IntoIterator<Item = String>) -> Self {
+        ThemeWords {
+            words: words.into_iter().collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.words.contains(word)
+    }
+
+    /// Whether a row or column holds a theme word between its blocks
+    pub fn in_line(&self, line: &str) -> bool {
+        line.split(BLOCK).any(|word| self.contains(word))
+    }
+
+    /// The theme words among the words of a box (see `WordBox::words`), in the order it lists
+    /// them
+    pub fn used(&self, wb: &WordBox) -> Vec<String> {
+        wb.words()
+            .filter(|word| self.contains(word))
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// How many of the words of a box are theme words
+    pub fn count(&self, wb: &WordBox) -> usize {
+        wb.words().filter(|word| self.contains(word)).count()
+    }
+
+    /// Whether a partial box could still hold `min` theme words. Every run of cells between
+    /// blocks is a word once the box is complete; a run counts towards the bound when some
+    /// theme word of its length matches the letters already in it.
+    pub(crate) fn may_reach(&self, wb: &WordBox, min: usize) -> bool {
+        if min == 0 {
+            return true;
+        }
+        let possible = wb
+            .lines()
+            .iter()
+            .flat_map(|line| line.split(BLOCK))
+            .filter(|run| !run.is_empty() && self.may_fill(run))
+            .count();
+        possible >= min
+    }
+
+    /// Whether some theme word fits a run of cells, `?` marking the open ones
+    fn may_fill(&self, run: &str) -> bool {
+        if !run.contains(WILDCARD) {
+            return self.contains(run);
+        }
+        let len = word_length(run);
+        self.words
+            .iter()
+            .any(|word| word_length(word) == len && matches_prefix(word, run))
+    }
+}
@@ -0,0 +1,258 @@
+use crate::blocks::BLOCK;
+use crate::lexicon::{BuildLexicon, Lexicon, LexiconOptions, Lexicons, WordId};
+use crate::wordbox::WordBox;
+use rand::{Rng, RngCore};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::Display;
+
+/// Which of several word lists each word comes from, counted from 0 for the first list, which is
+/// the most preferred: e.g. everyday words, then a full dictionary. A word listed more than once
+/// belongs to the first list that has it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WordTiers {
+    tiers: HashMap<String, usize>,
+    len: usize, // lists given
+}
+
+impl WordTiers {
+    /// The tiers of `lists`, most preferred first, along with the words of every list in that
+    /// order, each word once
+    pub fn new(lists: Vec<Vec<String>>) -> (Self, Vec<String>) {
+        let len = lists.len();
+        let mut tiers = HashMap::new();
+        let mut words = vec![];
+        for (tier, list) in lists.into_iter().enumerate() {
+            for word in list {
+                if !tiers.contains_key(&word) {
+                    tiers.insert(word.clone(), tier);
+                    words.push(word);
+                }
+            }
+        }
+        (WordTiers { tiers, len }, words)
+    }
+
+    /// Number of tiers
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The tier `word` comes from, if some list has it
+    pub fn tier(&self, word: &str) -> Option<usize> {
+        self.tiers.get(word).copied()
+    }
+}
+
+/// A lexicon of several tiers of words, each indexed by its own `L`. Queries run over every tier
+/// and list the words of the most preferred tier first, so each tier counts its own lexicon query.
+/// `tier` tells the searches which tier a candidate comes from: they try the candidates of a
+/// more preferred tier first, reaching for the words of the next one only once those lead
+/// nowhere.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TieredLexicon<L> {
+    tiers: Vec<L>,
+    word_tiers: WordTiers,
+}
+
+impl<L: BuildLexicon> BuildLexicon for TieredLexicon<L> {
+    /// A lexicon of a single tier
+    fn from_words(
+        words: impl IntoIterator<Item = String>,
+        lengths: BTreeSet<usize>,
+        options: &LexiconOptions,
+    ) -> Self {
+        TieredLexicon {
+            tiers: vec![L::from_words(words, lengths, options)],
+            word_tiers: WordTiers::default(),
+        }
+    }
+
+    /// Words `tiers` does not list go in its last tier
+    fn initialize_with_tiers(
+        words: Vec<String>,
+        lengths: BTreeSet<usize>,
+        tiers: &WordTiers,
+        options: &LexiconOptions,
+    ) -> Self {
+        let last = tiers.len().max(1) - 1;
+        let mut split: Vec<Vec<String>> = vec![vec![]; last + 1];
+        for word in words {
+            let tier = tiers.tier(&word).unwrap_or(last).min(last);
+            split[tier].push(word);
+        }
+        TieredLexicon {
+            tiers: (split.into_iter())
+                .map(|words| L::initialize_with_options(words, lengths.clone(), options))
+                .collect(),
+            word_tiers: tiers.clone(),
+        }
+    }
+}
+
+impl<L: Lexicon> Lexicon for TieredLexicon<L> {
+    fn words_with_prefix(&self, prefix: &str, word_len: usize) -> Vec<String> {
+        (self.tiers.iter())
+            .flat_map(|tier| tier.words_with_prefix(prefix, word_len))
+            .collect()
+    }
+
+    /// The ids of each tier come after those of the tiers before it, so they number the words
+    /// in the order `words_with_prefix` lists them
+    fn ids_with_prefix(&self, prefix: &str, word_len: usize) -> Vec<WordId> {
+        let mut ids = vec![];
+        let mut first: WordId = 0;
+        for tier in &self.tiers {
+            let tier_ids = tier.ids_with_prefix(prefix, word_len);
+            ids.extend(tier_ids.into_iter().map(|id| first + id));
+            first += tier.word_count(word_len) as WordId;
+        }
+        ids
+    }
+
+    fn word(&self, mut id: WordId, word_len: usize) -> &str {
+        let (last, earlier) = self
+            .tiers
+            .split_last()
+            .expect("a lexicon has at least one tier");
+        for tier in earlier {
+            let count = tier.word_count(word_len) as WordId;
+            if id < count {
+                return tier.word(id, word_len);
+            }
+            id -= count;
+        }
+        last.word(id, word_len)
+    }
+
+    fn word_count(&self, word_len: usize) -> usize {
+        (self.tiers.iter())
+            .map(|tier| tier.word_count(word_len))
+            .sum()
+    }
+
+    fn count_with_prefix(&self, prefix: &str, word_len: usize) -> usize {
+        (self.tiers.iter())
+            .map(|tier| tier.count_with_prefix(prefix, word_len))
+            .sum()
+    }
+
+    fn has_prefix(&self, prefix: &str, word_len: usize) -> bool {
+        (self.tiers.iter()).any(|tier| tier.has_prefix(prefix, word_len))
+    }
+
+    fn letters_after(&self, prefix: &str, word_len: usize) -> HashSet<char> {
+        (self.tiers.iter())
+            .flat_map(|tier| tier.letters_after(prefix, word_len))
+            .collect()
+    }
+
+    fn words_with_suffix(&self, suffix: &str, word_len: usize) -> Vec<String> {
+        (self.tiers.iter())
+            .flat_map(|tier| tier.words_with_suffix(suffix, word_len))
+            .collect()
+    }
+
+    fn memory_estimate(&self) -> usize {
+        self.tiers.iter().map(Lexicon::memory_estimate).sum()
+    }
+
+    /// Picks a tier with a chance in proportion to its matching words, so every word is as
+    /// likely as in a single lexicon
+    fn sample_with_prefix(
+        &self,
+        prefix: &str,
+        word_len: usize,
+        rng: &mut dyn RngCore,
+    ) -> Option<String> {
+        let counts: Vec<usize> = (self.tiers.iter())
+            .map(|tier| tier.count_with_prefix(prefix, word_len))
+            .collect();
+        let total: usize = counts.iter().sum();
+        if total == 0 {
+            return None;
+        }
+        let mut pick = rng.gen_range(0..total);
+        for (tier, count) in self.tiers.iter().zip(counts) {
+            if pick < count {
+                return tier.sample_with_prefix(prefix, word_len, rng);
+            }
+            pick -= count;
+        }
+        None
+    }
+
+    fn tiers(&self) -> usize {
+        self.tiers.len()
+    }
+
+    fn tier(&self, word: &str) -> usize {
+        self.word_tiers.tier(word).unwrap_or(0)
+    }
+}
+
+/// The least preferred tier among the words of a row or column, which may be split by blocks
+pub fn line_tier<L: Lexicon>(lexicon: &L, line: &str) -> usize {
+    (line.split(BLOCK))
+        .filter(|word| !word.is_empty())
+        .map(|word| lexicon.tier(word))
+        .max()
+        .unwrap_or(0)
+}
+
+/// How many of the words of a box come from each tier, most preferred first
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TierCounts(pub Vec<usize>);
+
+impl TierCounts {
+    /// The counts for the words of `wb` (see `WordBox::words`), rows looked up in the row
+    /// lexicon and columns in the column one; none unless a lexicon has several tiers
+    pub fn of<L: Lexicons>(wb: &WordBox, lexicons: &L) -> Self {
+        let tiers = lexicons.rows().tiers().max(lexicons.cols().tiers());
+        if tiers <= 1 {
+            return TierCounts::default();
+        }
+        let cols = match wb.is_symmetric {
+            true => &[][..],
+            false => &wb.cols[..],
+        };
+        let rows = wb.rows.iter().map(|row| (lexicons.rows(), row));
+        let mut counts = vec![0; tiers];
+        for (lexicon, line) in rows.chain(cols.iter().map(|col| (lexicons.cols(), col))) {
+            for word in line.split(BLOCK).filter(|word| !word.is_empty()) {
+                counts[lexicon.tier(word).min(tiers - 1)] += 1;
+            }
+        }
+        TierCounts(counts)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// How much a box leans on its less preferred tiers, as a score that is 0 for a box of
+    /// first-tier words and lower the more it needs others: fewer words from the last tier
+    /// always ranks higher, then fewer from the one before it, and so on
+    pub fn score(&self) -> f64 {
+        let base = (self.0.iter().sum::<usize>() + 1) as f64;
+        let penalty = (self.0.iter().enumerate().skip(1))
+            .map(|(tier, &count)| count as f64 * base.powi(tier as i32 - 1))
+            .sum::<f64>();
+        -penalty
+    }
+}
+
+/// The counts of the tiers that have any words, e.g. `5 from tier 1, 1 from tier 2`, counting
+/// tiers from 1
+impl Display for TierCounts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let counts: Vec<String> = (self.0.iter().enumerate())
+            .filter(|&(_, &count)| count > 0)
+            .map(|(tier, count)| format!("{} from tier {}", count, tier + 1))
+            .collect();
+        write!(f, "{}", counts.join(", "))
+    }
+}
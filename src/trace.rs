@@ -0,0 +1,210 @@
+use crate::wordbox::WordBox;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// What became of a box in the search tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceKind {
+    /// Its rows were placed and its children tried
+    Expanded,
+    /// It is complete, and a solution
+    Solution,
+    /// It was ruled out, for the reason given
+    Pruned(String),
+    /// This many candidate rows below it were turned away before a box was built for them
+    Rejected { count: usize, reason: String },
+}
+
+#[derive(Debug, Clone)]
+struct TraceNode {
+    label: String, // the box's cells, one line per row
+    kind: TraceKind,
+}
+
+#[derive(Debug, Default)]
+struct TraceGraph {
+    nodes: Vec<TraceNode>,
+    ids: HashMap<String, usize>,
+    edges: Vec<(usize, usize)>,
+    truncated: bool,
+}
+
+/// The search tree of a row search as it unfolds: the boxes expanded, the solutions, and why each
+/// dead end was abandoned, for `to_dot` to draw. Boxes are identified by their rows, so every
+/// seed's tree hangs off the same empty box. Recording stops once `limit` nodes are held.
+pub struct SearchTrace {
+    limit: usize,
+    graph: Mutex<TraceGraph>,
+}
+
+impl std::fmt::Debug for SearchTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SearchTrace")
+            .field("limit", &self.limit)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// The key a box is recorded under: its rows, one per line
+fn key(rows: &[Arc<str>]) -> String {
+    rows.join("\n")
+}
+
+impl TraceGraph {
+    /// The id of the box with these rows, recording it as expanded if it is new, along with
+    /// the boxes above it, which are labelled with just their rows; `None` once the graph is full
+    fn node(&mut self, rows: &[Arc<str>], label: Option<String>, limit: usize) -> Option<usize> {
+        let key = key(rows);
+        if let Some(&id) = self.ids.get(&key) {
+            return Some(id);
+        }
+        let parent = match rows.split_last() {
+            Some((_, above)) => Some(self.node(above, None, limit)?),
+            None => None,
+        };
+        let label = match label {
+            Some(label) => label,
+            None if rows.is_empty() => "(empty box)".to_string(),
+            None => key.clone(),
+        };
+        self.push(key, label, TraceKind::Expanded, parent, limit)
+    }
+
+    fn push(
+        &mut self,
+        key: String,
+        label: String,
+        kind: TraceKind,
+        parent: Option<usize>,
+        limit: usize,
+    ) -> Option<usize> {
+        if self.nodes.len() >= limit {
+            self.truncated = true;
+            return None;
+        }
+        let id = self.nodes.len();
+        self.nodes.push(TraceNode { label, kind });
+        self.ids.insert(key, id);
+        if let Some(parent) = parent {
+            self.edges.push((parent, id));
+        }
+        Some(id)
+    }
+}
+
+impl SearchTrace {
+    pub fn new(limit: usize) -> Self {
+        SearchTrace {
+            limit,
+            graph: Mutex::default(),
+        }
+    }
+
+    /// Nodes recorded
+    pub fn len(&self) -> usize {
+        self.graph.lock().unwrap().nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether the limit was reached and some nodes were left out
+    pub fn is_truncated(&self) -> bool {
+        self.graph.lock().unwrap().truncated
+    }
+
+    /// Whether nothing more will be recorded, so callers can skip working out what to record
+    pub(crate) fn is_full(&self) -> bool {
+        self.graph.lock().unwrap().nodes.len() >= self.limit
+    }
+
+    /// Record what became of `wb`
+    pub(crate) fn record(&self, wb: &WordBox, kind: TraceKind) {
+        let mut graph = self.graph.lock().unwrap();
+        let Some(id) = graph.node(&wb.rows, Some(label(wb)), self.limit) else {
+            return;
+        };
+        graph.nodes[id].label = label(wb);
+        graph.nodes[id].kind = kind;
+    }
+
+    /// Record that `count` candidate rows below `parent` were turned away for `reason`
+    pub(crate) fn rejected(&self, parent: &WordBox, count: usize, reason: String) {
+        let mut graph = self.graph.lock().unwrap();
+        let Some(parent_id) = graph.node(&parent.rows, Some(label(parent)), self.limit) else {
+            return;
+        };
+        let key = format!("{}\n#{}", key(&parent.rows), reason);
+        if graph.ids.contains_key(&key) {
+            return;
+        }
+        let label = match count {
+            1 => "1 row".to_string(),
+            count => format!("{} rows", count),
+        };
+        let kind = TraceKind::Rejected { count, reason };
+        graph.push(key, label, kind, Some(parent_id), self.limit);
+    }
+
+    /// The tree as a Graphviz digraph: expanded boxes in white, solutions in green, boxes pruned
+    /// in red with the reason under them, and groups of rows turned away in grey
+    pub fn to_dot(&self) -> String {
+        let graph = self.graph.lock().unwrap();
+        let mut dot = String::from("digraph search {\n");
+        dot += "    node [shape=box, style=filled, fillcolor=white, fontname=\"monospace\"];\n";
+        for (id, node) in graph.nodes.iter().enumerate() {
+            let (label, attributes) = match &node.kind {
+                TraceKind::Expanded => (node.label.clone(), ""),
+                TraceKind::Solution => (node.label.clone(), ", fillcolor=palegreen"),
+                TraceKind::Pruned(reason) => (
+                    format!("{}\n{}", node.label, reason),
+                    ", fillcolor=lightcoral",
+                ),
+                TraceKind::Rejected { reason, .. } => (
+                    format!("{}\n{}", node.label, reason),
+                    ", fillcolor=lightgrey, style=\"filled,dashed\"",
+                ),
+            };
+            dot += &format!(
+                "    n{} [label={}{}];\n",
+                id,
+                dot_string(&label),
+                attributes
+            );
+        }
+        for (parent, child) in &graph.edges {
+            dot += &format!("    n{} -> n{};\n", parent, child);
+        }
+        if graph.truncated {
+            dot += &format!(
+                "    truncated [label=\"stopped recording at {} nodes\", shape=plaintext, style=\"\"];\n",
+                self.limit
+            );
+        }
+        dot += "}\n";
+        dot
+    }
+}
+
+/// A box's cells, one line per row, with `_` for the open ones
+fn label(wb: &WordBox) -> String {
+    wb.to_string().trim_end().to_string()
+}
+
+/// `s` as a DOT string, its lines centred
+fn dot_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,362 @@
+use crate::blocks::BLOCK;
+use crate::cube::json_string;
+use crate::digraphs::{self, cell_text};
+use crate::masks::CellMasks;
+use crate::template::Line;
+use crate::wordbox::WordBox;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Display;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+const SHARDS: usize = 16;
+
+/// Where a word sits in a box: its row or column, and the cell its run starts at, which is 0
+/// unless blocks split the line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Slot {
+    pub line: Line,
+    pub index: usize,
+    pub start: usize,
+}
+
+/// How often one word was used
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WordCounts {
+    pub solutions: usize,             // solutions holding the word at least once
+    pub slots: BTreeMap<Slot, usize>, // times it filled each slot
+}
+
+/// The words of every solution of a run, counted as workers report them.
+///
+/// Workers record their solutions themselves, so the counts cover runs that only count too.
+/// The table is split into shards like `NogoodCache`, so recording a solution takes a few short
+/// locks that workers rarely contend for.
+pub struct WordUsage {
+    shards: Vec<Mutex<HashMap<String, WordCounts>>>,
+    symmetric: bool,
+    solutions: AtomicUsize,
+}
+
+impl std::fmt::Debug for WordUsage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WordUsage")
+            .field("symmetric", &self.symmetric)
+            .field("solutions", &self.solutions())
+            .finish()
+    }
+}
+
+impl WordUsage {
+    /// Counts for boxes that are all `symmetric` or all not: a symmetric box's columns repeat its
+    /// rows, so only its rows are counted, each standing for the column of the same index too
+    pub fn new(symmetric: bool) -> Self {
+        WordUsage {
+            shards: (0..SHARDS).map(|_| Mutex::default()).collect(),
+            symmetric,
+            solutions: AtomicUsize::new(0),
+        }
+    }
+
+    fn shard(&self, word: &str) -> &Mutex<HashMap<String, WordCounts>> {
+        let mut hasher = DefaultHasher::new();
+        word.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % SHARDS]
+    }
+
+    /// Count the words of a completed box
+    pub fn record(&self, wb: &WordBox) {
+        let cols = if wb.is_symmetric {
+            &[][..]
+        } else {
+            &wb.cols[..]
+        };
+        let lines = (wb
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(i, row)| (Line::Row, i, row)))
+        .chain(cols.iter().enumerate().map(|(i, col)| (Line::Col, i, col)));
+        let mut words: Vec<(&str, Slot)> = vec![];
+        for (line, index, cells) in lines {
+            let mut start = 0;
+            for run in cells.split(BLOCK) {
+                if !run.is_empty() {
+                    words.push((run, Slot { line, index, start }));
+                }
+                start += run.chars().count() + 1;
+            }
+        }
+        for (i, &(word, slot)) in words.iter().enumerate() {
+            let first = !words[..i].iter().any(|&(earlier, _)| earlier == word);
+            let mut shard = self.shard(word).lock().unwrap();
+            let counts = match shard.get_mut(word) {
+                Some(counts) => counts,
+                None => shard.entry(word.to_string()).or_default(),
+            };
+            counts.solutions += usize::from(first);
+            *counts.slots.entry(slot).or_default() += 1;
+        }
+        self.solutions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Solutions recorded so far
+    pub fn solutions(&self) -> usize {
+        self.solutions.load(Ordering::Relaxed)
+    }
+
+    /// Distinct words recorded so far
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.lock().unwrap().len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every word recorded, used by the most solutions first and then alphabetically
+    pub fn report(&self) -> UsageReport {
+        let mut words: Vec<(String, WordCounts)> = self
+            .shards
+            .iter()
+            .flat_map(|shard| shard.lock().unwrap().clone())
+            .collect();
+        words.sort_by(|(a, a_counts), (b, b_counts)| {
+            b_counts.solutions.cmp(&a_counts.solutions).then(a.cmp(b))
+        });
+        UsageReport {
+            words,
+            solutions: self.solutions(),
+            symmetric: self.symmetric,
+        }
+    }
+}
+
+/// The words used across a run's solutions, ranked (see `WordUsage::report`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsageReport {
+    pub words: Vec<(String, WordCounts)>,
+    pub solutions: usize,
+    pub symmetric: bool,
+}
+
+impl UsageReport {
+    /// The share of the solutions that use a word, in percent
+    fn percent(&self, counts: &WordCounts) -> f64 {
+        100.0 * counts.solutions as f64 / self.solutions.max(1) as f64
+    }
+
+    /// Where a word sat, e.g. `row 2`, `col 0 at 3` for a run starting in the fourth cell, or
+    /// `row/col 1` in a symmetric box
+    fn slot_name(&self, slot: &Slot) -> String {
+        let line = match self.symmetric {
+            true => "row/col".to_string(),
+            false => slot.line.to_string(),
+        };
+        match slot.start {
+            0 => format!("{} {}", line, slot.index),
+            start => format!("{} {} at {}", line, slot.index, start),
+        }
+    }
+
+    /// A word's slots, the most used first
+    fn slots(&self, counts: &WordCounts) -> Vec<(String, usize)> {
+        let mut slots: Vec<(String, usize)> = (counts.slots.iter())
+            .map(|(slot, &times)| (self.slot_name(slot), times))
+            .collect();
+        slots.sort_by_key(|&(_, times)| std::cmp::Reverse(times));
+        slots
+    }
+
+    /// The report as CSV, one row per word, its slots written `row 0=12;col 3=5`
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("word,solutions,percent,slots\n");
+        for (word, counts) in &self.words {
+            let slots: Vec<String> = (self.slots(counts).into_iter())
+                .map(|(slot, times)| format!("{}={}", slot, times))
+                .collect();
+            csv += &format!(
+                "{},{},{:.4},{}\n",
+                digraphs::decode(word),
+                counts.solutions,
+                self.percent(counts),
+                slots.join(";")
+            );
+        }
+        csv
+    }
+}
+
+impl Display for UsageReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} distinct words in {} solutions",
+            self.words.len(),
+            self.solutions
+        )?;
+        let width = (self.words.iter())
+            .map(|(word, _)| digraphs::decode(word).chars().count())
+            .fold(4, usize::max);
+        writeln!(
+            f,
+            "{:<width$} {:>10} {:>8}  slots",
+            "word", "solutions", "share"
+        )?;
+        for (word, counts) in &self.words {
+            let slots: Vec<String> = (self.slots(counts).into_iter())
+                .map(|(slot, times)| format!("{} ×{}", slot, times))
+                .collect();
+            writeln!(
+                f,
+                "{:<width$} {:>10} {:>7.2}%  {}",
+                digraphs::decode(word),
+                counts.solutions,
+                self.percent(counts),
+                slots.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// How often each letter filled each cell across a run's solutions, counted as workers report
+/// them like `WordUsage`: a cell where one letter dominates is rigid, one spread over many
+/// letters is free. Blocked cells count nothing.
+pub struct LetterHeatmap {
+    row_dim: usize,
+    col_dim: usize,
+    cells: Vec<Mutex<BTreeMap<char, usize>>>, // row by row
+    solutions: AtomicUsize,
+    masks: CellMasks, // the run's cell constraints, echoed in the JSON
+}
+
+impl std::fmt::Debug for LetterHeatmap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LetterHeatmap")
+            .field("row_dim", &self.row_dim)
+            .field("col_dim", &self.col_dim)
+            .field("solutions", &self.solutions())
+            .finish()
+    }
+}
+
+impl LetterHeatmap {
+    pub fn new(row_dim: usize, col_dim: usize) -> Self {
+        LetterHeatmap {
+            row_dim,
+            col_dim,
+            cells: (0..row_dim * col_dim).map(|_| Mutex::default()).collect(),
+            solutions: AtomicUsize::new(0),
+            masks: CellMasks::default(),
+        }
+    }
+
+    /// Echo the cell masks the run was constrained by in the JSON
+    pub fn with_masks(self, masks: CellMasks) -> Self {
+        LetterHeatmap { masks, ..self }
+    }
+
+    /// Count the letters of a completed box of the heatmap's size
+    pub fn record(&self, wb: &WordBox) {
+        // Each solution starts at another cell, so workers recording at once take different locks
+        let first = self.solutions.fetch_add(1, Ordering::Relaxed) % self.cells.len().max(1);
+        let letters: Vec<char> = wb.rows.iter().flat_map(|row| row.chars()).collect();
+        for cell in (first..letters.len()).chain(0..first) {
+            if letters[cell] != BLOCK {
+                *self.cells[cell]
+                    .lock()
+                    .unwrap()
+                    .entry(letters[cell])
+                    .or_default() += 1;
+            }
+        }
+    }
+
+    /// Solutions recorded so far
+    pub fn solutions(&self) -> usize {
+        self.solutions.load(Ordering::Relaxed)
+    }
+
+    /// How often each letter filled cell (`row`, `col`)
+    pub fn counts(&self, row: usize, col: usize) -> BTreeMap<char, usize> {
+        self.cells[row * self.col_dim + col].lock().unwrap().clone()
+    }
+
+    /// The most common letter in a cell and the share of the solutions it filled the cell in,
+    /// the letter that sorts first winning ties; `None` for a cell nothing filled
+    pub fn commonest(&self, row: usize, col: usize) -> Option<(char, f64)> {
+        let counts = self.counts(row, col);
+        let (&letter, &times) = counts
+            .iter()
+            .max_by(|(a, a_times), (b, b_times)| a_times.cmp(b_times).then(b.cmp(a)))?;
+        Some((letter, times as f64 / self.solutions().max(1) as f64))
+    }
+
+    /// The counts as a JSON object: the letters seen, in order, and a tensor indexed by row,
+    /// column and then letter, along with the cell masks if there were any
+    pub fn to_json(&self) -> String {
+        let counts: Vec<BTreeMap<char, usize>> = (self.cells.iter())
+            .map(|cell| cell.lock().unwrap().clone())
+            .collect();
+        let letters: BTreeSet<char> = counts
+            .iter()
+            .flat_map(|cell| cell.keys().copied())
+            .collect();
+        let rows: Vec<String> = counts
+            .chunks(self.col_dim.max(1))
+            .map(|row| {
+                let cells: Vec<String> = row
+                    .iter()
+                    .map(|cell| {
+                        let counts: Vec<String> = (letters.iter())
+                            .map(|letter| cell.get(letter).copied().unwrap_or(0).to_string())
+                            .collect();
+                        format!("[{}]", counts.join(","))
+                    })
+                    .collect();
+                format!("[{}]", cells.join(","))
+            })
+            .collect();
+        let letters: Vec<String> = (letters.iter())
+            .map(|&letter| json_string(&cell_text(letter)))
+            .collect();
+        let masks = match self.masks.is_empty() {
+            true => String::new(),
+            false => format!(",\"masks\":{}", self.masks.to_json()),
+        };
+        format!(
+            "{{\"rows\":{},\"cols\":{},\"solutions\":{},\"letters\":[{}],\"counts\":[{}]{}}}",
+            self.row_dim,
+            self.col_dim,
+            self.solutions(),
+            letters.join(","),
+            rows.join(","),
+            masks
+        )
+    }
+}
+
+/// A grid of the most common letter in each cell and its share, e.g. `e 41%`, with `#` for
+/// cells nothing filled
+impl Display for LetterHeatmap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in 0..self.row_dim {
+            let cells: Vec<String> = (0..self.col_dim)
+                .map(|col| match self.commonest(row, col) {
+                    Some((letter, share)) => {
+                        format!("{} {:>3.0}%", cell_text(letter), 100.0 * share)
+                    }
+                    None => format!("{:<6}", BLOCK),
+                })
+                .collect();
+            writeln!(f, "{}", cells.join("  ").trim_end())?;
+        }
+        Ok(())
+    }
+}
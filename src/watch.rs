@@ -0,0 +1,113 @@
+use crate::cancel::CancellationToken;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// What a file looked like when last checked: its modification time and length, or `None`
+/// while it cannot be read, as when an editor has moved it aside to save it
+type Stamp = Option<(SystemTime, u64)>;
+
+fn stamp(path: &Path) -> Stamp {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// A hash of the contents of each file, `None` for those that cannot be read
+fn content_hashes(paths: &[PathBuf]) -> Vec<Option<u64>> {
+    (paths.iter())
+        .map(|path| {
+            let bytes = std::fs::read(path).ok()?;
+            let mut hasher = DefaultHasher::new();
+            bytes.hash(&mut hasher);
+            Some(hasher.finish())
+        })
+        .collect()
+}
+
+/// Files to re-run a search for when they change, checked by polling their metadata.
+///
+/// An editor saving a file may write it several times in quick succession, or replace it
+/// through a temporary file, so a change only counts once the files have stayed as they are
+/// for the debounce period; a burst of saves yields one change.
+#[derive(Debug, Clone)]
+pub struct FileWatcher {
+    paths: Vec<PathBuf>,
+    stamps: Vec<Stamp>,
+    hashes: Vec<Option<u64>>, // the contents as of the last search
+    poll: Duration,
+    debounce: Duration,
+}
+
+impl FileWatcher {
+    /// A watcher of `paths` as they are now, checking them every `poll` and reporting a change
+    /// once they have been left alone for `debounce`
+    pub fn new(paths: Vec<PathBuf>, poll: Duration, debounce: Duration) -> Self {
+        let stamps = paths.iter().map(|path| stamp(path)).collect();
+        let hashes = content_hashes(&paths);
+        FileWatcher {
+            paths,
+            stamps,
+            hashes,
+            poll,
+            debounce,
+        }
+    }
+
+    /// The files watched
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Whether the contents of the files differ from when this was last asked, or from when
+    /// the watcher was made, so that a save leaving every file as it was need not run the
+    /// search again
+    pub fn contents_changed(&mut self) -> bool {
+        let hashes = content_hashes(&self.paths);
+        let changed = hashes != self.hashes;
+        self.hashes = hashes;
+        changed
+    }
+
+    /// The files that changed since they were last checked, taking their new state as the one
+    /// to compare against from now on
+    fn changed(&mut self) -> Vec<PathBuf> {
+        let mut changed = vec![];
+        for (path, old) in self.paths.iter().zip(&mut self.stamps) {
+            let new = stamp(path);
+            if new != *old {
+                *old = new;
+                changed.push(path.clone());
+            }
+        }
+        changed
+    }
+
+    /// Block until some files change and then settle, and return them, in the order they are
+    /// watched; `None` if `cancellation` is cancelled first
+    pub fn wait(&mut self, cancellation: &CancellationToken) -> Option<Vec<PathBuf>> {
+        let mut changed: Vec<PathBuf> = vec![];
+        let mut settled = Duration::ZERO;
+        loop {
+            if cancellation.is_cancelled() {
+                return None;
+            }
+            std::thread::sleep(self.poll);
+            let now = self.changed();
+            if now.is_empty() {
+                settled += self.poll;
+            } else {
+                settled = Duration::ZERO;
+                for path in now {
+                    if !changed.contains(&path) {
+                        changed.push(path);
+                    }
+                }
+            }
+            if !changed.is_empty() && settled >= self.debounce {
+                changed.sort_by_key(|path| self.paths.iter().position(|p| p == path));
+                return Some(changed);
+            }
+        }
+    }
+}
@@ -0,0 +1,1233 @@
+use crate::alphabet::Alphabet;
+use crate::blocks::{self, BLOCK};
+use crate::digraphs;
+use crate::frequency::{Difficulty, WordFrequencies};
+use crate::lexicon::{letter_at, word_length, Lexicon, Lexicons, WordId, WILDCARD};
+use crate::masks::LetterMask;
+use crate::memory::{shared_str_bytes, EstimatedSize};
+use crate::scrabble::LetterValues;
+use crate::solver::{self, CandidateDetail, SlotCandidates, SolverConfig};
+use crate::template::Template;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Display;
+use std::sync::Arc;
+use std::vec;
+
+/// Why a word cannot be placed as the next row of a box
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WordBoxError {
+    WrongLength { word: String, expected: usize },
+    OutsideAlphabet { word: String, ch: char },
+    Full { row_dim: usize },
+    NotSquare { row_dim: usize, col_dim: usize },
+    NoCells { row_dim: usize, col_dim: usize },
+}
+
+impl Display for WordBoxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WordBoxError::WrongLength { word, expected } => write!(
+                f,
+                "{:?} has {} letters, but the rows of the box have {}",
+                word,
+                word_length(word),
+                expected
+            ),
+            WordBoxError::OutsideAlphabet { word, ch } => write!(
+                f,
+                "{:?} holds {:?}, which is not in the alphabet of the box",
+                word, ch
+            ),
+            WordBoxError::Full { row_dim } => {
+                write!(f, "the box already has all of its {} rows", row_dim)
+            }
+            WordBoxError::NotSquare { row_dim, col_dim } => write!(
+                f,
+                "a {}x{} box cannot be symmetric: every row is also a column, so symmetric \
+                 boxes must be square (use an asymmetric box, --asymmetric, for rectangles)",
+                row_dim, col_dim
+            ),
+            WordBoxError::NoCells { row_dim, col_dim } => write!(
+                f,
+                "a {}x{} box has no cells: it needs at least one row and one column",
+                row_dim, col_dim
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WordBoxError {}
+
+/// A cell whose letter in its row word differs from the one in its column word
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conflict {
+    pub row: usize,
+    pub col: usize,
+    pub in_row: char,
+    pub in_col: char,
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "row {}, column {}: {:?} in the row but {:?} in the column",
+            self.row, self.col, self.in_row, self.in_col
+        )
+    }
+}
+
+/// The columns of a box as its next row will cross them. Candidates for the row are checked by
+/// writing each letter after its column's prefix, so nothing is read off the rows placed.
+pub(crate) struct NextColumns<'a> {
+    wb: &'a WordBox,
+    line: String, // the column being checked, reused for every candidate
+}
+
+impl<'a> NextColumns<'a> {
+    /// Whether every column can still be completed with words of the column `lexicon` once
+    /// `word`, one letter per column, is placed as the next row
+    pub(crate) fn admit<L: Lexicon>(&mut self, word: &str, lexicon: &L) -> bool {
+        self.blocked_column(word, lexicon).is_none()
+    }
+
+    /// The first column that could no longer be completed once `word` is placed as the next
+    /// row, if there is one
+    pub(crate) fn blocked_column<L: Lexicon>(&mut self, word: &str, lexicon: &L) -> Option<usize> {
+        self.blocked_columns(word, lexicon).next()
+    }
+
+    /// Every column that could no longer be completed once `word` is placed as the next row,
+    /// left to right, checked only as far as they are asked for
+    pub(crate) fn blocked_columns<'b, L: Lexicon>(
+        &'b mut self,
+        word: &'b str,
+        lexicon: &'b L,
+    ) -> impl Iterator<Item = usize> + use<'a, 'b, L> {
+        let wb = self.wb;
+        let masked = wb.has_masks();
+        (word.chars().enumerate()).filter_map(move |(i, ch)| {
+            let line = self.line(i, ch);
+            let feasible = match masked {
+                true => blocks::is_feasible_masked(line, |row| wb.mask(row, i), lexicon),
+                false => blocks::is_feasible(line, lexicon),
+            };
+            (!feasible).then_some(i)
+        })
+    }
+
+    /// Product over all columns of the number of words completing each column once `word`
+    /// is placed as the next row; higher means the word constrains the columns less
+    pub(crate) fn completions<L: Lexicon>(&mut self, word: &str, lexicon: &L) -> f64 {
+        let mut prod = 1.0;
+        for (i, ch) in word.chars().enumerate() {
+            prod *= blocks::completions(self.line(i, ch), lexicon);
+        }
+        prod
+    }
+
+    /// The cells of column `i` with `ch` in the next row (see `WordBox::column_line`)
+    fn line(&mut self, i: usize, ch: char) -> &str {
+        let wb = self.wb;
+        let below = (wb.rows.len() + 1..wb.row_dim).map(|row| wb.locked(row, i));
+        self.line.clear();
+        self.line.extend(wb.column_prefix(i));
+        self.line.push(ch);
+        self.line.extend(below.map(|cell| cell.unwrap_or(WILDCARD)));
+        debug_assert_eq!(
+            word_length(&self.line),
+            wb.row_dim,
+            "a column is as long as the box is tall"
+        );
+        &self.line
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordBox {
+    pub(crate) row_dim: usize,         // number of rows
+    pub(crate) col_dim: usize,         // number of columns
+    pub(crate) rows: Vec<Arc<str>>, // the words for each row, shared with the boxes built on this one
+    pub(crate) cols: Vec<Arc<str>>, // the words for each column
+    pub(crate) col_letters: Vec<char>, // each column's cells in turn, filled down to the rows placed
+    pub(crate) is_symmetric: bool,
+    pub(crate) is_rotational: bool, // every cell holds the letter of the cell opposite it through the centre
+    pub(crate) template: Option<Arc<Template>>, // letters locked into cells, shared by every box of a search
+    pub(crate) alphabet: Option<Arc<Alphabet>>, // letters rows may use, if `add_word` checks them
+}
+
+impl Display for WordBox {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let grid = self.cells();
+        // Multi-letter tiles widen every cell to the widest one, with a space between cells
+        let width = grid
+            .iter()
+            .flatten()
+            .map(|&ch| digraphs::cell_width(ch))
+            .max();
+        if let Some(width) = width.filter(|&width| width > 1) {
+            for row in &grid {
+                let cells: Vec<String> = (row.iter())
+                    .map(|&ch| format!("{:<width$}", digraphs::cell_text(ch)))
+                    .collect();
+                writeln!(f, "{}", cells.join(" ").trim_end())?;
+            }
+            return Ok(());
+        }
+
+        for row in &grid {
+            for ch in row {
+                write!(f, "{}", ch)?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The rows placed before the last are shared with the box it was built on, so only the last
+/// row's letters count here
+impl EstimatedSize for WordBox {
+    fn estimated_bytes(&self) -> usize {
+        std::mem::size_of::<WordBox>()
+            + (self.rows.capacity() + self.cols.capacity()) * std::mem::size_of::<Arc<str>>()
+            + self.rows.last().map_or(0, |row| shared_str_bytes(row))
+            + self
+                .cols
+                .iter()
+                .map(|col| shared_str_bytes(col))
+                .sum::<usize>()
+            + self.col_letters.capacity() * std::mem::size_of::<char>()
+    }
+}
+
+impl WordBox {
+    /// An empty box. In symmetric mode (square boxes only) every row placed is mirrored as the
+    /// matching column; otherwise the column words are read off the rows once the last one is placed.
+    /// Panics if the box has no cells or a symmetric box is not square; see `try_new`.
+    pub fn new(row_dim: usize, col_dim: usize, is_symmetric: bool) -> Self {
+        Self::try_new(row_dim, col_dim, is_symmetric).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// An empty box, or an error if it has no rows or no columns, or is symmetric but not square.
+    /// A box one row tall or one column wide is a box like any other: each cell of its single row
+    /// is a column of its own, so every letter of a 1xN box has to be a one-letter word of the
+    /// column lexicon (and likewise every letter of an Nx1 box one of the row lexicon).
+    pub fn try_new(
+        row_dim: usize,
+        col_dim: usize,
+        is_symmetric: bool,
+    ) -> Result<Self, WordBoxError> {
+        if row_dim == 0 || col_dim == 0 {
+            return Err(WordBoxError::NoCells { row_dim, col_dim });
+        }
+        if is_symmetric && row_dim != col_dim {
+            return Err(WordBoxError::NotSquare { row_dim, col_dim });
+        }
+        Ok(WordBox {
+            row_dim,
+            col_dim,
+            rows: vec![],
+            cols: vec![],
+            col_letters: vec![WILDCARD; row_dim * col_dim],
+            is_symmetric,
+            is_rotational: false,
+            template: None,
+            alphabet: None,
+        })
+    }
+
+    /// Have `add_word` refuse rows with a letter outside `alphabet`
+    pub fn with_alphabet(self, alphabet: &Alphabet) -> Self {
+        WordBox {
+            alphabet: Some(Arc::new(alphabet.clone())),
+            ..self
+        }
+    }
+
+    /// Have the box read the same turned upside down: every row placed locks its letters, in
+    /// reverse, into the row as far from the bottom as it is from the top, so the bottom half of
+    /// the box follows from the top half, and a middle row has to be a palindrome
+    pub fn with_rotation(self) -> Self {
+        WordBox {
+            is_rotational: true,
+            ..self
+        }
+    }
+
+    /// Lock the letters of `template` into the box; a symmetric box also locks each letter
+    /// into the mirrored cell
+    pub fn with_template(self, template: &Template) -> Self {
+        let template = if self.is_symmetric {
+            template.mirrored()
+        } else {
+            template.clone()
+        };
+        WordBox {
+            template: Some(Arc::new(template)),
+            ..self
+        }
+    }
+
+    /// The letter (or block) the template locks into a cell, if any; in a rotational box also
+    /// the letter of the cell opposite, if the template locks it or its row is placed
+    fn locked(&self, row: usize, col: usize) -> Option<char> {
+        let template = |row, col| self.template.as_ref().and_then(|t| t.get(row, col));
+        let locked = template(row, col);
+        if !self.is_rotational || locked.is_some() {
+            return locked;
+        }
+        let (twin_row, twin_col) = (self.row_dim - 1 - row, self.col_dim - 1 - col);
+        match self.rows.get(twin_row) {
+            Some(twin) => letter_at(twin, twin_col, self.col_dim),
+            None => template(twin_row, twin_col),
+        }
+    }
+
+    /// Whether `row`, placed next, reads as the row opposite it turned around, if that row is
+    /// placed, and as itself turned around if it is the middle row (rotational boxes)
+    fn turns_into_twin(&self, row: &str) -> bool {
+        let i = self.rows.len();
+        let twin_row = self.row_dim - 1 - i;
+        match self.rows.get(twin_row) {
+            _ if !self.is_rotational => true,
+            Some(twin) => row.chars().eq(twin.chars().rev()),
+            None if twin_row == i => row.chars().eq(row.chars().rev()),
+            None => true,
+        }
+    }
+
+    /// Whether a complete box reads the same turned upside down, each row being the row
+    /// opposite it backwards
+    pub fn is_rotation_symmetric(&self) -> bool {
+        (self.rows.iter().zip(self.rows.iter().rev()))
+            .all(|(row, twin)| row.chars().eq(twin.chars().rev()))
+    }
+
+    /// The letters the template limits a cell to, if it has a mask
+    pub(crate) fn mask(&self, row: usize, col: usize) -> Option<&LetterMask> {
+        self.template.as_ref()?.mask(row, col)
+    }
+
+    /// Whether the template limits some cells to some letters
+    pub(crate) fn has_masks(&self) -> bool {
+        self.template.as_ref().is_some_and(|t| t.has_masks())
+    }
+
+    /// Whether the masks allow each letter of `row` in its cell of the next row, and a
+    /// rotational box allows the row opposite it
+    pub(crate) fn row_allowed(&self, row: &str) -> bool {
+        let i = self.rows.len();
+        (!self.has_masks()
+            || (row.chars().enumerate())
+                .all(|(j, ch)| ch == BLOCK || self.mask(i, j).is_none_or(|mask| mask.allows(ch))))
+            && self.turns_into_twin(row)
+    }
+
+    /// Whether some cell of column `i` below the rows placed has a mask
+    fn masked_below(&self, i: usize) -> bool {
+        self.has_masks() && (self.rows.len()..self.row_dim).any(|row| self.mask(row, i).is_some())
+    }
+
+    /// Whether some cells are blocked, splitting rows and columns into several words
+    pub fn has_blocks(&self) -> bool {
+        self.template.as_ref().is_some_and(|t| t.has_blocks())
+    }
+
+    /// How promising a partial box is: deeper boxes whose columns can still be completed
+    /// in many ways score higher
+    pub fn score<L: Lexicons>(&self, lexicons: &L) -> f64 {
+        (100 * self.rows.len()) as f64 + self.column_completions(lexicons)
+    }
+
+    /// The product over the columns of the number of words completing each, every column
+    /// counted on its own
+    pub fn column_completions<L: Lexicons>(&self, lexicons: &L) -> f64 {
+        let mut prod = 1.0;
+        for i in 0..self.col_dim {
+            prod *= blocks::completions(&self.column_line(i), lexicons.cols());
+        }
+        prod
+    }
+
+    /// Whether every row and column word has been filled in
+    pub fn is_done(&self) -> bool {
+        self.rows.len() == self.row_dim && (self.is_symmetric || self.cols.len() == self.col_dim)
+    }
+
+    /// The cells where a row word and a column word both have a letter and the letters differ,
+    /// row by row. Boxes built with `add_word` never have any.
+    pub fn check_consistency(&self) -> Vec<Conflict> {
+        let mut conflicts = vec![];
+        for (row, row_word) in self.rows.iter().enumerate() {
+            for (col, in_row) in row_word.chars().enumerate() {
+                let in_col = self.cols.get(col).and_then(|word| word.chars().nth(row));
+                if let Some(in_col) = in_col.filter(|&in_col| in_col != in_row) {
+                    conflicts.push(Conflict {
+                        row,
+                        col,
+                        in_row,
+                        in_col,
+                    });
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Whether every word in the rows and columns of the box is a word of the row or column
+    /// lexicon respectively, and the rows and columns agree on every cell
+    pub fn is_valid<L: Lexicons>(&self, lexicons: &L) -> bool {
+        self.check_consistency().is_empty()
+            && self
+                .rows
+                .iter()
+                .all(|line| blocks::is_feasible(line, lexicons.rows()))
+            && self
+                .cols
+                .iter()
+                .all(|line| blocks::is_feasible(line, lexicons.cols()))
+    }
+
+    /// Whether the box is valid (see `is_valid`) but for at most `allowed` columns that are not
+    /// words (see `invalid_columns`)
+    pub fn is_near_valid<L: Lexicons>(&self, lexicons: &L, allowed: usize) -> bool {
+        self.check_consistency().is_empty()
+            && self
+                .rows
+                .iter()
+                .all(|line| blocks::is_feasible(line, lexicons.rows()))
+            && self.invalid_columns(lexicons).len() <= allowed
+    }
+
+    /// The columns whose cells hold no word of the column lexicon, or in a partial box can no
+    /// longer be completed to one; none in a symmetric box, whose columns are its rows
+    pub fn invalid_columns<L: Lexicons>(&self, lexicons: &L) -> Vec<usize> {
+        if self.is_symmetric {
+            return vec![];
+        }
+        (0..self.col_dim)
+            .filter(|&i| !blocks::is_feasible(&self.column_line(i), lexicons.cols()))
+            .collect()
+    }
+
+    /// The words in the rows, top to bottom
+    pub fn row_words(&self) -> Vec<String> {
+        self.rows.iter().map(|row| row.to_string()).collect()
+    }
+
+    /// The words in the rows, and in the columns too for asymmetric boxes (a symmetric box's
+    /// columns necessarily repeat its rows)
+    pub fn words(&self) -> impl Iterator<Item = &str> {
+        let cols = if self.is_symmetric {
+            &[][..]
+        } else {
+            &self.cols[..]
+        };
+        self.rows
+            .iter()
+            .chain(cols)
+            .flat_map(|line| line.split(BLOCK))
+            .filter(|word| !word.is_empty())
+    }
+
+    /// What each cell shows, row by row: its letter, `_` for an open cell, a block, or `!`
+    /// where the rows and columns disagree. A partial box shows the rows placed so far, and
+    /// any letters the template or the rotational twin of a cell locks in.
+    pub fn cells(&self) -> Vec<Vec<char>> {
+        let mut grid: Vec<Vec<char>> = (0..self.row_dim)
+            .map(|i| {
+                (0..self.col_dim)
+                    .map(|j| self.locked(i, j).unwrap_or('_'))
+                    .collect()
+            })
+            .collect();
+
+        // Either list may be partial (or, for asymmetric boxes still being filled, empty)
+        for (row, word) in grid.iter_mut().zip(&self.rows) {
+            for (cell, ch) in row.iter_mut().zip(word.chars()) {
+                *cell = ch;
+            }
+        }
+
+        for (j, word) in self.cols.iter().enumerate().take(self.col_dim) {
+            for (row, ch) in grid.iter_mut().zip(word.chars()) {
+                row[j] = ch;
+            }
+        }
+
+        // A box whose rows and columns disagree must not look like a valid one
+        for conflict in self.check_consistency() {
+            grid[conflict.row][conflict.col] = '!';
+        }
+        grid
+    }
+
+    /// How obscure the words of the box are by `frequencies` (see `WordFrequencies::difficulty`),
+    /// taking words without a frequency to have the frequency `missing`
+    pub fn difficulty(&self, frequencies: &WordFrequencies, missing: f64) -> Option<Difficulty> {
+        frequencies.difficulty(self.words(), missing)
+    }
+
+    /// Number of different letters in the box's cells, blocked cells left out; for a partial box,
+    /// in the rows placed so far
+    pub fn distinct_letters(&self) -> usize {
+        self.placed_letters().len()
+    }
+
+    /// Whether no letter fills two cells of the rows placed so far; a symmetric box of more
+    /// than one cell never is one, as every letter off its diagonal fills two
+    pub fn is_isogram(&self) -> bool {
+        let mut seen = HashSet::new();
+        (self.rows.iter().flat_map(|row| row.chars()))
+            .filter(|&cell| cell != BLOCK)
+            .all(|cell| seen.insert(cell))
+    }
+
+    /// Whether placing `row` next would repeat a letter already in the rows, or use one twice
+    pub(crate) fn repeats_letter(&self, row: &str) -> bool {
+        let mut seen = self.placed_letters();
+        !row.chars()
+            .filter(|&cell| cell != BLOCK)
+            .all(|cell| seen.insert(cell))
+    }
+
+    /// The next row's letter sets without the letters placed so far, or `None` if a column is
+    /// left with none or two columns are left with the same single letter
+    pub(crate) fn without_placed_letters(
+        &self,
+        mut letters: Vec<HashSet<char>>,
+    ) -> Option<Vec<HashSet<char>>> {
+        let placed = self.placed_letters();
+        let mut forced = HashSet::new();
+        for set in &mut letters {
+            set.retain(|letter| !placed.contains(letter));
+            match set.len() {
+                0 => return None,
+                1 if !forced.insert(*set.iter().next()?) => return None,
+                _ => {}
+            }
+        }
+        Some(letters)
+    }
+
+    fn placed_letters(&self) -> HashSet<char> {
+        (self.rows.iter().flat_map(|row| row.chars()))
+            .filter(|&cell| cell != BLOCK)
+            .collect()
+    }
+
+    /// The most different letters a completion of the box could hold: those of the rows placed
+    /// and of the cells the template locks, plus one for every open cell. A symmetric box fills
+    /// a cell and its mirror image with the same letter, so only the open cells on or above the
+    /// diagonal count; those below it mirror a row already placed or a cell counted above.
+    pub(crate) fn max_distinct_letters(&self) -> usize {
+        let mut letters = self.placed_letters();
+        let mut open = 0;
+        for row in self.rows.len()..self.row_dim {
+            for col in 0..self.col_dim {
+                if self.is_symmetric && col < row {
+                    continue;
+                }
+                match self.locked(row, col) {
+                    Some(BLOCK) => {}
+                    Some(letter) => {
+                        letters.insert(letter);
+                    }
+                    None => open += 1,
+                }
+            }
+        }
+        letters.len() + open
+    }
+
+    /// How many cells hold each letter among the cells already settled: those of the rows
+    /// placed, the cells a symmetric box mirrors from them below the diagonal, and the cells
+    /// the template locks (or, in rotational boxes, a placed row's twin fixes)
+    pub(crate) fn settled_letter_counts(&self) -> BTreeMap<char, usize> {
+        let placed = self.rows.len();
+        let mut counts: BTreeMap<char, usize> = BTreeMap::new();
+        for row in 0..self.row_dim {
+            for col in 0..self.col_dim {
+                let cell = match self.rows.get(row) {
+                    Some(word) => letter_at(word, col, self.col_dim),
+                    None if self.is_symmetric && col < placed => {
+                        letter_at(&self.rows[col], row, self.col_dim)
+                    }
+                    None => self.locked(row, col),
+                };
+                if let Some(letter) = cell.filter(|&cell| cell != BLOCK) {
+                    *counts.entry(letter).or_default() += 1;
+                }
+            }
+        }
+        counts
+    }
+
+    /// The Scrabble score of the box: the values of the tiles in its cells summed, each cell
+    /// counted once however many words cross it, and blocked cells left out
+    pub fn scrabble_score(&self, values: &LetterValues) -> u32 {
+        (self.rows.iter().flat_map(|row| row.chars()))
+            .filter(|&cell| cell != BLOCK)
+            .map(|cell| values.value(cell))
+            .sum()
+    }
+
+    /// Whether some word appears twice in the box (see `words`) or among the `extra` words
+    /// that also have to be distinct, such as its diagonals
+    pub fn has_repeated_words(&self, extra: &[String]) -> bool {
+        let mut seen = HashSet::new();
+        !self
+            .words()
+            .chain(extra.iter().map(String::as_str))
+            .all(|word| seen.insert(word))
+    }
+
+    /// Whether placing `row` next would use a word already in the rows, or use one twice
+    pub(crate) fn repeats_word(&self, row: &str) -> bool {
+        let mut seen: HashSet<&str> = self
+            .rows
+            .iter()
+            .flat_map(|line| line.split(BLOCK))
+            .collect();
+        !row.split(BLOCK)
+            .filter(|word| !word.is_empty())
+            .all(|word| seen.insert(word))
+    }
+
+    /// The cells of the next row: the letters already fixed by the columns in symmetric mode,
+    /// then whatever the template locks into the rest of the row, with `?` for open cells
+    fn next_row_line(&self) -> String {
+        let row = self.rows.len();
+        let known = if self.is_symmetric {
+            // Row `row` mirrors column `row`
+            self.column_prefix(row).iter().collect()
+        } else {
+            String::new()
+        };
+        let locked = (word_length(&known)..self.col_dim).map(|col| self.locked(row, col));
+        known
+            .chars()
+            .chain(locked.map(|cell| cell.unwrap_or(WILDCARD)))
+            .collect()
+    }
+
+    /// The cells of row `i`: the row itself once placed, otherwise the letters the columns and
+    /// the template already fix, with `?` for open cells
+    pub(crate) fn row_line(&self, i: usize) -> String {
+        match self.rows.get(i) {
+            Some(row) => row.to_string(),
+            // Row `i` mirrors column `i`
+            None if self.is_symmetric => self.column_line(i),
+            None => (0..self.col_dim)
+                .map(|col| self.locked(i, col).unwrap_or(WILDCARD))
+                .collect(),
+        }
+    }
+
+    /// The cells of every row and, unless the box is symmetric and its columns repeat its rows,
+    /// of every column (see `row_line` and `column_line`)
+    pub(crate) fn lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = (0..self.row_dim).map(|i| self.row_line(i)).collect();
+        if !self.is_symmetric {
+            lines.extend((0..self.col_dim).map(|i| self.column_line(i)));
+        }
+        lines
+    }
+
+    /// The cells of column `i`: the letters of the rows placed, then whatever the template
+    /// locks into the rows below, with `?` for open cells
+    pub(crate) fn column_line(&self, i: usize) -> String {
+        let mut line: String = self.column_prefix(i).iter().collect();
+        let locked = (self.rows.len()..self.row_dim).map(|row| self.locked(row, i));
+        line.extend(locked.map(|cell| cell.unwrap_or(WILDCARD)));
+        line
+    }
+
+    /// The cells of the main diagonal of a square box, or of the `anti`-diagonal read from the
+    /// top right: the letters of the rows placed, then whatever the template locks into the
+    /// rest, with `?` for open cells
+    pub fn diagonal_line(&self, anti: bool) -> String {
+        let col = |i: usize| if anti { self.col_dim - 1 - i } else { i };
+        (0..self.row_dim.min(self.col_dim))
+            .map(|i| match self.rows.get(i) {
+                Some(row) => letter_at(row, col(i), self.col_dim).unwrap(),
+                None => self.locked(i, col(i)).unwrap_or(WILDCARD),
+            })
+            .collect()
+    }
+
+    /// Every row that can be placed next while keeping all columns completable. With blocks,
+    /// each run of the row takes a word of its own and every combination is tried.
+    pub fn row_candidates<L: Lexicons>(&self, lexicons: &L) -> Vec<String> {
+        let mut rows = self.row_combinations(lexicons.rows());
+        let mut columns = self.next_columns();
+        rows.retain(|row| {
+            self.fits_row(row) && self.row_allowed(row) && columns.admit(row, lexicons.cols())
+        });
+        rows
+    }
+
+    /// Like `row_candidates`, but keeping rows that leave up to `invalid_cols` columns with no
+    /// completion, for searches allowing near misses (see `SolverConfig::allow_invalid_cols`)
+    pub fn near_row_candidates<L: Lexicons>(
+        &self,
+        lexicons: &L,
+        invalid_cols: usize,
+    ) -> Vec<String> {
+        let mut rows = self.row_combinations(lexicons.rows());
+        let mut columns = self.next_columns();
+        rows.retain(|row| {
+            self.fits_row(row)
+                && self.row_allowed(row)
+                && (columns.blocked_columns(row, lexicons.cols()))
+                    .nth(invalid_cols)
+                    .is_none()
+        });
+        rows
+    }
+
+    /// The words that may go in each open slot, for building a box by hand. The next row lists
+    /// the rows the solver would try under `config` and keep, so the template, forward checking
+    /// and every constraint of the config apply. In boxes without blocks every other open row,
+    /// and for asymmetric boxes every open column, is listed too, with the words that fit its
+    /// cells and leave each slot they cross completable. Symmetry breaking is ignored: a box
+    /// and its transpose are both legal to build.
+    pub fn candidates<L: Lexicons>(
+        &self,
+        lexicons: &L,
+        config: &SolverConfig,
+        detail: CandidateDetail,
+    ) -> SlotCandidates {
+        solver::slot_candidates(self, lexicons, config, detail)
+    }
+
+    /// Every row whose runs are words of the row `lexicon` fitting the next row's fixed letters,
+    /// before checking whether the columns can still be completed
+    pub(crate) fn row_combinations<L: Lexicon>(&self, lexicon: &L) -> Vec<String> {
+        let line = self.next_row_line();
+        debug_assert_eq!(
+            word_length(&line),
+            self.col_dim,
+            "a row is as long as the box is wide"
+        );
+        if !line.contains(BLOCK) {
+            return lexicon.words_with_prefix(line.trim_end_matches(WILDCARD), self.col_dim);
+        }
+        let mut rows = vec![String::new()];
+        for (i, run) in line.split(BLOCK).enumerate() {
+            if i > 0 {
+                rows.iter_mut().for_each(|row| row.push(BLOCK));
+            }
+            if run.is_empty() {
+                continue;
+            }
+            let words = lexicon.words_with_prefix(run.trim_end_matches(WILDCARD), word_length(run));
+            rows = rows
+                .iter()
+                .flat_map(|row| words.iter().map(move |word| format!("{}{}", row, word)))
+                .collect();
+        }
+        rows
+    }
+
+    /// The ids in `lexicon` of the words `row_combinations` would list, or `None` if blocks split
+    /// the next row, whose candidates are then not single words
+    pub(crate) fn row_ids<L: Lexicon>(&self, lexicon: &L) -> Option<Vec<WordId>> {
+        let line = self.next_row_line();
+        debug_assert_eq!(
+            word_length(&line),
+            self.col_dim,
+            "a row is as long as the box is wide"
+        );
+        let prefix = line.trim_end_matches(WILDCARD);
+        (!line.contains(BLOCK)).then(|| lexicon.ids_with_prefix(prefix, self.col_dim))
+    }
+
+    /// The rows the lexicons alone allow next, in the order of their ids: the words of
+    /// `row_ids` that keep every column completable, whatever the search would make of them.
+    /// Replaying a run picks its rows from these by their place in the list (see
+    /// `choice_path`), so they depend on the lexicons and nothing else; `None` if blocks split
+    /// the next row.
+    pub(crate) fn row_choices<'l, L: Lexicons>(&self, lexicons: &'l L) -> Option<Vec<&'l str>> {
+        let ids = self.row_ids(lexicons.rows())?;
+        let Ok(letters) = self.next_letters(lexicons.cols()) else {
+            return Some(vec![]);
+        };
+        let word = |id: WordId| lexicons.rows().word(id, self.col_dim);
+        let fits = |row: &&str| (row.chars().zip(&letters)).all(|(ch, set)| set.contains(&ch));
+        Some(ids.into_iter().map(word).filter(fits).collect())
+    }
+
+    /// The place of each row of the box among the `row_choices` of an empty box of its size,
+    /// and of each box built from it a row at a time; `None` if some row is not among them, as
+    /// in a box with blocks, or a near miss. The path leads back to the box through the same
+    /// lexicons without a search.
+    pub fn choice_path<L: Lexicons>(&self, lexicons: &L) -> Option<Vec<usize>> {
+        let mut wb = WordBox::try_new(self.row_dim, self.col_dim, self.is_symmetric).ok()?;
+        let mut path = Vec::with_capacity(self.row_dim);
+        for row in &self.rows {
+            let choices = wb.row_choices(lexicons)?;
+            path.push(choices.iter().position(|choice| **choice == **row)?);
+            wb = wb.add_word(row.to_string()).ok()?;
+        }
+        Some(path)
+    }
+
+    /// Whether `row` has one cell per column. The lexicons only return words of the length asked
+    /// for, but a row that slipped through anyway would break every column read off the rows.
+    pub(crate) fn fits_row(&self, row: &str) -> bool {
+        word_length(row) == self.col_dim
+    }
+
+    /// The columns as the next row will cross them, to check candidates for that row against
+    pub(crate) fn next_columns(&self) -> NextColumns<'_> {
+        NextColumns {
+            wb: self,
+            line: String::with_capacity(self.row_dim),
+        }
+    }
+
+    /// For each column, the letters that can go in the next row's cell while keeping the column
+    /// completable, or the first column with no completion left (boxes without blocks)
+    pub(crate) fn next_letters<L: Lexicon>(
+        &self,
+        lexicon: &L,
+    ) -> Result<Vec<HashSet<char>>, usize> {
+        let position = self.rows.len();
+        (0..self.col_dim)
+            .map(|i| {
+                let line = self.column_line(i);
+                debug_assert_eq!(
+                    word_length(&line),
+                    self.row_dim,
+                    "a column is as long as the box is tall"
+                );
+                let pattern = line.trim_end_matches(WILDCARD);
+                let masked = self.masked_below(i);
+                let mut letters: HashSet<char> = if word_length(pattern) == position && !masked {
+                    lexicon.letters_after(pattern, self.row_dim)
+                } else {
+                    // Locked letters and masks further down the column narrow the words
+                    // to look at
+                    (lexicon.ids_with_prefix(pattern, self.row_dim).into_iter())
+                        .map(|id| lexicon.word(id, self.row_dim))
+                        .filter(|word| {
+                            !masked
+                                || (word.chars().enumerate()).all(|(row, ch)| {
+                                    self.mask(row, i).is_none_or(|mask| mask.allows(ch))
+                                })
+                        })
+                        .filter_map(|word| letter_at(word, position, self.row_dim))
+                        .collect()
+                };
+                if let Some(mask) = self.mask(position, i) {
+                    letters.retain(|&ch| mask.allows(ch));
+                }
+                if letters.is_empty() {
+                    Err(i)
+                } else {
+                    Ok(letters)
+                }
+            })
+            .collect()
+    }
+
+    /// The prefixes of the columns that are not complete yet; in symmetric mode the first
+    /// `rows.len()` columns are the rows already placed
+    pub(crate) fn open_column_prefixes(&self) -> Vec<String> {
+        let first_open = if self.is_symmetric {
+            self.rows.len()
+        } else {
+            0
+        };
+        (first_open..self.col_dim)
+            .map(|i| self.column_prefix(i).iter().collect())
+            .collect()
+    }
+
+    /// The letters of the rows placed so far in column `i`, top to bottom
+    pub(crate) fn column_prefix(&self, i: usize) -> &[char] {
+        let top = i * self.row_dim;
+        &self.col_letters[top..top + self.rows.len()]
+    }
+
+    /// The word in each column, read off the rows placed so far
+    pub(crate) fn column_words(&self) -> Vec<Arc<str>> {
+        (0..self.col_dim)
+            .map(|i| self.column_prefix(i).iter().collect::<String>().into())
+            .collect()
+    }
+
+    /// The box reflected across its main diagonal
+    pub fn transpose(&self) -> WordBox {
+        let rows = self.column_words();
+        let cols = if self.is_symmetric {
+            rows.clone()
+        } else {
+            self.rows.clone()
+        };
+        // Transposing only makes sense for complete boxes, whose columns are their row words
+        let mut col_letters: Vec<char> = self.rows.iter().flat_map(|row| row.chars()).collect();
+        col_letters.resize(self.row_dim * self.col_dim, WILDCARD);
+        WordBox {
+            row_dim: self.col_dim,
+            col_dim: self.row_dim,
+            rows,
+            cols,
+            col_letters,
+            is_symmetric: self.is_symmetric,
+            is_rotational: self.is_rotational,
+            template: self.template.as_ref().map(|t| Arc::new(t.transpose())),
+            alphabet: self.alphabet.clone(),
+        }
+    }
+
+    /// Whether this box and its transpose are both boxes of the same shape and template, so that
+    /// only one of the two needs to be searched for
+    pub fn has_transpose_twin(&self) -> bool {
+        !self.is_symmetric
+            && self.row_dim == self.col_dim
+            && self.template.as_ref().is_none_or(|t| t.is_self_transpose())
+    }
+
+    /// Whether a complete box is the canonical one of itself and its transpose: its rows, read
+    /// top to bottom, are no greater than its columns read left to right
+    pub fn is_canonical(&self) -> bool {
+        !self.has_transpose_twin() || self.rows <= self.column_words()
+    }
+
+    /// The complete box or its transpose, whichever is canonical, so that both orientations of
+    /// a solution map to the same box
+    pub fn canonical(&self) -> WordBox {
+        if self.is_canonical() {
+            self.clone()
+        } else {
+            self.transpose()
+        }
+    }
+
+    /// Whether some completion of this partial box can still be canonical: once the first
+    /// column so far sorts below the start of the first row, every completion would too
+    pub(crate) fn may_be_canonical(&self) -> bool {
+        if !self.has_transpose_twin() || self.rows.is_empty() {
+            return true;
+        }
+        let first_row = self.rows[0].chars().take(self.rows.len());
+        self.column_prefix(0).iter().copied().ge(first_row)
+    }
+
+    /// The boxes one row and one column bigger that hold this complete box in their top left
+    /// corner: every row and column gets one more letter, and a new last row and last column,
+    /// sharing the corner cell, are words of the new lengths. A symmetric box only grows into
+    /// symmetric boxes. Boxes that are not complete, or have blocks, have none.
+    pub fn try_grow<L: Lexicons>(&self, lexicons: &L) -> Vec<WordBox> {
+        if !self.is_done() || self.has_blocks() {
+            return vec![];
+        }
+        let (row_dim, col_dim) = (self.row_dim + 1, self.col_dim + 1);
+        // The letters each row and column can take at its new end
+        let row_ends: Vec<HashSet<char>> = (self.rows.iter())
+            .map(|row| lexicons.rows().letters_after(row, col_dim))
+            .collect();
+        let col_ends: Vec<HashSet<char>> = (self.column_words().iter())
+            .map(|col| lexicons.cols().letters_after(col, row_dim))
+            .collect();
+        if row_ends.iter().chain(&col_ends).any(HashSet::is_empty) {
+            return vec![];
+        }
+        let ending = |lexicon: &L::Lexicon, len: usize, ends: &[HashSet<char>]| -> Vec<String> {
+            let pattern: String = std::iter::repeat_n(WILDCARD, len).collect();
+            let mut words = lexicon.words_with_prefix(&pattern, len);
+            words.retain(|word| word.chars().zip(ends).all(|(ch, set)| set.contains(&ch)));
+            words
+        };
+        let last_rows = ending(lexicons.rows(), col_dim, &col_ends);
+        let last_cols: HashSet<String> = ending(lexicons.cols(), row_dim, &row_ends)
+            .into_iter()
+            .collect();
+
+        let mut grown = vec![];
+        let empty = WordBox {
+            alphabet: self.alphabet.clone(),
+            ..WordBox::new(row_dim, col_dim, self.is_symmetric)
+        };
+        for last_row in &last_rows {
+            let corner = last_row.chars().last();
+            let mut cols: Vec<&String> = match self.is_symmetric {
+                true => last_cols.iter().filter(|&col| col == last_row).collect(),
+                false => (last_cols.iter())
+                    .filter(|col| col.chars().last() == corner)
+                    .collect(),
+            };
+            cols.sort();
+            for last_col in cols {
+                let mut rows = (self.rows.iter().zip(last_col.chars()))
+                    .map(|(row, ch)| format!("{}{}", row, ch))
+                    .chain(std::iter::once(last_row.clone()));
+                let wb = rows.try_fold(empty.clone(), |wb, row| wb.add_word(row));
+                grown.extend(wb.ok());
+            }
+        }
+        grown
+    }
+
+    /// The box with `word` placed as the next row, unless every row is filled already, the word
+    /// has the wrong number of letters or, if the box has an alphabet, a letter outside it
+    pub fn add_word(&self, word: String) -> Result<WordBox, WordBoxError> {
+        if self.rows.len() == self.row_dim {
+            return Err(WordBoxError::Full {
+                row_dim: self.row_dim,
+            });
+        }
+        if !self.fits_row(&word) {
+            return Err(WordBoxError::WrongLength {
+                word,
+                expected: self.col_dim,
+            });
+        }
+        if let Some(alphabet) = &self.alphabet {
+            if let Some(ch) = word
+                .chars()
+                .find(|&ch| ch != BLOCK && !alphabet.contains(ch))
+            {
+                return Err(WordBoxError::OutsideAlphabet { word, ch });
+            }
+        }
+        Ok(self.with_row(word.into()))
+    }
+
+    /// The box with `word` placed as the next row, which the caller knows fits: it was placed
+    /// in a box like this one before, as for the boxes a frontier writes to disk and reads back
+    pub(crate) fn with_row(&self, word: Arc<str>) -> WordBox {
+        // Each column grows by one letter; `fits_row` checked that the word has one per column
+        let mut col_letters = self.col_letters.clone();
+        for (i, ch) in word.chars().enumerate() {
+            col_letters[i * self.row_dim + self.rows.len()] = ch;
+        }
+        // The rows placed so far are shared rather than copied: the search clones a box for
+        // every row it tries, but only ever adds to it
+        let mut rows = self.rows.clone();
+        rows.push(word.clone());
+        let mut cols = self.cols.clone();
+        if self.is_symmetric {
+            cols.push(word.clone());
+        }
+        let mut wb = WordBox {
+            row_dim: self.row_dim,
+            col_dim: self.col_dim,
+            rows,
+            cols,
+            col_letters,
+            is_symmetric: self.is_symmetric,
+            is_rotational: self.is_rotational,
+            template: self.template.clone(),
+            alphabet: self.alphabet.clone(),
+        };
+        if !wb.is_symmetric && wb.rows.len() == wb.row_dim {
+            wb.cols = wb.column_words();
+        }
+        wb
+    }
+
+    /// Take back the last row placed, undoing everything `add_word` did: the row's letters in
+    /// each column, and its mirrored column in a symmetric box or the column words read off a
+    /// full asymmetric box. Returns the word, or `None` with the box unchanged if it is empty.
+    pub fn remove_last_word(&mut self) -> Option<String> {
+        let word = self.rows.pop()?;
+        match self.is_symmetric {
+            true => {
+                self.cols.pop();
+            }
+            false => self.cols.clear(),
+        }
+        let row = self.rows.len();
+        for i in 0..self.col_dim {
+            self.col_letters[i * self.row_dim + row] = WILDCARD;
+        }
+        Some(word.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexicon::{BuildLexicon, VecLexicon};
+    use std::collections::BTreeSet;
+    use std::ops::ControlFlow;
+
+    fn lexicon(words: &[&str]) -> VecLexicon {
+        let words: Vec<String> = words.iter().map(|word| word.to_string()).collect();
+        let lengths = words.iter().map(|word| word_length(word)).collect();
+        VecLexicon::initialize(words, lengths)
+    }
+
+    /// Every solution below `wb`, as its rows one per line
+    fn solutions(wb: WordBox, lexicon: &VecLexicon) -> BTreeSet<String> {
+        let mut found = BTreeSet::new();
+        solver::solve_word_box_all(wb, lexicon, &SolverConfig::default(), |solution| {
+            found.insert(solution.to_string());
+            ControlFlow::Continue(())
+        });
+        found
+    }
+
+    #[test]
+    fn words_of_the_wrong_length_are_rejected() {
+        let wb = WordBox::new(3, 4, false);
+        for word in ["cat", "cattle", ""] {
+            assert_eq!(
+                wb.add_word(word.to_string()),
+                Err(WordBoxError::WrongLength {
+                    word: word.to_string(),
+                    expected: 4
+                })
+            );
+        }
+        assert!(wb.add_word("cats".to_string()).is_ok());
+    }
+
+    #[test]
+    fn letters_outside_the_alphabet_are_rejected() {
+        let wb = WordBox::new(3, 3, true).with_alphabet(&Alphabet::Ascii);
+        assert_eq!(
+            wb.add_word("cañ".to_string()),
+            Err(WordBoxError::OutsideAlphabet {
+                word: "cañ".to_string(),
+                ch: 'ñ'
+            })
+        );
+        // Without an alphabet, any letter goes
+        assert!(WordBox::new(3, 3, true).add_word("cañ".to_string()).is_ok());
+    }
+
+    #[test]
+    fn rows_beyond_the_last_are_rejected() {
+        let mut wb = WordBox::new(2, 2, false);
+        for word in ["ab", "cd"] {
+            wb = wb.add_word(word.to_string()).unwrap();
+        }
+        assert_eq!(
+            wb.add_word("ef".to_string()),
+            Err(WordBoxError::Full { row_dim: 2 })
+        );
+    }
+
+    #[test]
+    fn boxes_without_cells_are_rejected() {
+        for (row_dim, col_dim) in [(0, 3), (3, 0), (0, 0)] {
+            assert_eq!(
+                WordBox::try_new(row_dim, col_dim, false),
+                Err(WordBoxError::NoCells { row_dim, col_dim })
+            );
+        }
+        assert!(WordBox::try_new(1, 1, false).is_ok());
+    }
+
+    #[test]
+    fn a_seeded_box_solves_as_the_unseeded_one_does() {
+        let lexicon = lexicon(&[
+            "cat", "ace", "ate", "tea", "eat", "are", "era", "ran", "ant",
+        ]);
+        let all = solutions(WordBox::new(3, 3, true), &lexicon);
+        let seeded = WordBox::new(3, 3, true)
+            .add_word("eat".to_string())
+            .unwrap();
+        let expected: BTreeSet<String> = (all.into_iter())
+            .filter(|rows| rows.starts_with("eat\n"))
+            .collect();
+        assert_eq!(expected.len(), 3);
+        assert_eq!(solutions(seeded, &lexicon), expected);
+    }
+
+    #[test]
+    fn conflicts_are_reported_where_they_are() {
+        let mut wb = WordBox::new(2, 3, false);
+        for word in ["cat", "ore"] {
+            wb = wb.add_word(word.to_string()).unwrap();
+        }
+        assert_eq!(wb.check_consistency(), []);
+        let lexicon = lexicon(&["cat", "ore", "co", "ar", "te", "xe", "ax"]);
+        assert!(wb.is_valid(&lexicon));
+
+        // Columns as they might come from a file, two of them disagreeing with the rows
+        wb.cols = ["co", "ax", "xe"].map(Arc::from).to_vec();
+        assert_eq!(
+            wb.check_consistency(),
+            [
+                Conflict {
+                    row: 0,
+                    col: 2,
+                    in_row: 't',
+                    in_col: 'x'
+                },
+                Conflict {
+                    row: 1,
+                    col: 1,
+                    in_row: 'r',
+                    in_col: 'x'
+                },
+            ]
+        );
+        assert!(!wb.is_valid(&lexicon));
+        assert_eq!(wb.to_string(), "ca!\no!e\n");
+        assert_eq!(
+            wb.check_consistency()[0].to_string(),
+            "row 0, column 2: 't' in the row but 'x' in the column"
+        );
+    }
+
+    fn filled(row_dim: usize, col_dim: usize, symmetric: bool, rows: &[&str]) -> WordBox {
+        (rows.iter()).fold(WordBox::new(row_dim, col_dim, symmetric), |wb, row| {
+            wb.add_word(row.to_string()).unwrap()
+        })
+    }
+
+    #[test]
+    fn boxes_grow_by_a_row_and_a_column() {
+        let words: Vec<&str> = include_str!("../tests/fixtures/letters.txt")
+            .lines()
+            .collect();
+        let lexicon = lexicon(&words);
+        let grown = |wb: &WordBox| -> Vec<String> {
+            (wb.try_grow(&lexicon).iter())
+                .map(|wb| wb.row_words().join(" "))
+                .collect()
+        };
+        let wb = filled(3, 3, true, &["oat", "are", "tee"]);
+        assert_eq!(grown(&wb), ["oats area teen sand", "oats area teen sane"]);
+        let wb = filled(3, 3, true, &["rat", "are", "tea"]);
+        assert_eq!(grown(&wb), ["rate area tear earn"]);
+        // Its rows all take another letter, but no fourth row fits under them
+        let wb = filled(3, 3, true, &["ant", "nee", "tea"]);
+        assert!(wb.is_valid(&lexicon));
+        assert_eq!(grown(&wb), Vec::<String>::new());
+        // Nor does a box that is not complete grow
+        let wb = filled(3, 3, true, &["oat", "are"]);
+        assert_eq!(grown(&wb), Vec::<String>::new());
+    }
+
+    #[test]
+    fn removing_the_last_word_undoes_adding_it() {
+        for (row_dim, col_dim, symmetric, rows) in [
+            (3, 3, true, ["cat", "ace", "tea"]),
+            (3, 4, false, ["fast", "area", "dean"]),
+        ] {
+            // The box after each row is added, from none to all
+            let mut stages = vec![WordBox::new(row_dim, col_dim, symmetric)];
+            for row in rows {
+                let next = stages.last().unwrap().add_word(row.to_string()).unwrap();
+                stages.push(next);
+            }
+            let mut wb = stages.pop().unwrap();
+            assert!(wb.is_done());
+            for row in rows.iter().rev() {
+                assert_eq!(wb.remove_last_word().as_deref(), Some(*row));
+                assert_eq!(wb, stages.pop().unwrap());
+            }
+            assert_eq!(wb.remove_last_word(), None);
+            assert_eq!(wb, WordBox::new(row_dim, col_dim, symmetric));
+        }
+    }
+}
@@ -0,0 +1,378 @@
+mod common;
+
+use common::{ab_words, counted, fixture, temp_dir, wordbox, wordbox_fails, write_words};
+
+#[test]
+fn retried_seeds_are_not_counted_twice() {
+    let dir = temp_dir("retry-count");
+    // The a/b words are slow seeds, each with many solutions; xyz and qrs are fast ones, whose
+    // columns no word starts like
+    let mut words = ab_words();
+    words.extend(["xyz".to_string(), "qrs".to_string()]);
+    let words: Vec<&str> = words.iter().map(String::as_str).collect();
+    let list = write_words(&dir, "words.txt", &words);
+    let list = list.to_str().unwrap();
+    let count = |budget: &[&str]| {
+        let mut args = vec![
+            "--wordlist",
+            list,
+            "--rows",
+            "3",
+            "--cols",
+            "3",
+            "--asymmetric",
+        ];
+        args.extend(budget);
+        args.push("count");
+        counted(&wordbox(&args))
+    };
+    // 512 boxes, 64 of them their own transposes, counted once with their transposes
+    assert_eq!(count(&[]), 288);
+    let budget = ["--per-seed-max-nodes", "3", "--retry-inconclusive", "1000x"];
+    assert_eq!(count(&budget), 288);
+    let no_dedup = [
+        "--per-seed-max-nodes",
+        "3",
+        "--retry-inconclusive",
+        "1000x",
+        "--no-dedup",
+    ];
+    assert_eq!(count(&no_dedup), 288);
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn symmetric_rectangles_are_refused() {
+    let dir = temp_dir("symmetric-rectangle");
+    let list = write_words(&dir, "words.txt", &["wasted", "worm"]);
+    let list = list.to_str().unwrap();
+    let args = ["--wordlist", list, "--rows", "4", "--cols", "6"];
+    let stderr = wordbox_fails(&args);
+    assert!(
+        stderr.contains("symmetric boxes must be square"),
+        "{}",
+        stderr
+    );
+    let mut args = args.to_vec();
+    args.extend(["--asymmetric", "count"]);
+    assert_eq!(counted(&wordbox(&args)), 0);
+    std::fs::remove_dir_all(dir).ok();
+}
+
+/// The boxes in the output of a search, each as its rows
+fn printed_boxes(stdout: &str) -> Vec<Vec<String>> {
+    stdout
+        .split("\n\n")
+        .map(|block| block.lines().map(str::to_string).collect::<Vec<_>>())
+        .filter(|rows| {
+            !rows.is_empty() && rows.iter().all(|row| row.chars().all(char::is_lowercase))
+        })
+        .collect()
+}
+
+#[test]
+fn the_spine_is_in_every_box() {
+    let list = fixture("letters.txt");
+    let list = list.to_str().unwrap();
+    let args = ["--wordlist", list, "--rows", "4", "--cols", "4", "--all"];
+    let mut asymmetric = args.to_vec();
+    asymmetric.extend(["--asymmetric", "--spine", "sale"]);
+    let found = printed_boxes(&wordbox(&asymmetric));
+    assert!(found.len() > 1);
+    for rows in &found {
+        let spine: String = rows.iter().map(|row| row.chars().next().unwrap()).collect();
+        assert_eq!(spine, "sale", "{:?}", rows);
+    }
+    // In a symmetric box the first column is the first row
+    let mut symmetric = args.to_vec();
+    symmetric.extend(["--spine", "sale"]);
+    let found = printed_boxes(&wordbox(&symmetric));
+    assert!(found.len() > 1);
+    assert!(found.iter().all(|rows| rows[0] == "sale"), "{:?}", found);
+}
+
+#[test]
+fn deterministic_runs_write_identical_output() {
+    let dir = temp_dir("deterministic");
+    let list = fixture("letters.txt");
+    let list = list.to_str().unwrap();
+    let run = |name: &str, threads: &str| {
+        let output = dir.join(name);
+        let stdout = wordbox(&[
+            "--wordlist",
+            list,
+            "--rows",
+            "3",
+            "--cols",
+            "4",
+            "--asymmetric",
+            "--all",
+            "--deterministic",
+            "--threads",
+            threads,
+            "--output",
+            output.to_str().unwrap(),
+        ]);
+        // Everything but the time taken
+        let stdout: Vec<&str> = (stdout.lines())
+            .filter(|line| !line.starts_with("Time Duration"))
+            .collect();
+        (std::fs::read(output).unwrap(), stdout.join("\n"))
+    };
+    let (first, printed) = run("first.txt", "4");
+    assert_eq!(printed_boxes(&printed).len(), 116);
+    assert_eq!(run("second.txt", "4"), (first.clone(), printed.clone()));
+    assert_eq!(run("sequential.txt", "1"), (first, printed));
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn a_search_that_comes_up_empty_shows_the_best_partial_box() {
+    let list = fixture("letters.txt");
+    let stdout = wordbox(&[
+        "--wordlist",
+        list.to_str().unwrap(),
+        "--rows",
+        "3",
+        "--cols",
+        "3",
+        "--asymmetric",
+        "--template",
+        "...\\n...\\n..o",
+    ]);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(
+        lines[..2],
+        ["No solutions found", "Best partial box (2/3 rows):"]
+    );
+    assert!(lines[3].ends_with('o') && lines[4] == "__o", "{}", stdout);
+}
+
+#[test]
+fn symmetric_isograms_are_refused() {
+    let list = fixture("letters.txt");
+    let stderr = wordbox_fails(&["--wordlist", list.to_str().unwrap(), "--isogram"]);
+    assert!(
+        stderr.contains("--isogram needs --asymmetric"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn sizes_without_cells_are_refused() {
+    let list = fixture("letters.txt");
+    let list = list.to_str().unwrap();
+    for size in [["--rows", "0"], ["--cols", "0"]] {
+        let mut args = vec!["--wordlist", list, "--asymmetric"];
+        args.extend(size);
+        let stderr = wordbox_fails(&args);
+        assert!(
+            stderr.contains("a box needs at least one row and one column"),
+            "{}",
+            stderr
+        );
+    }
+    let stderr = wordbox_fails(&["--wordlist", list, "--rows", "0..=3", "count"]);
+    assert!(stderr.contains("at least one row"), "{}", stderr);
+}
+
+#[test]
+fn sizes_the_word_list_has_no_words_for_find_nothing() {
+    let list = fixture("letters.txt");
+    let stdout = wordbox(&[
+        "--wordlist",
+        list.to_str().unwrap(),
+        "--rows",
+        "5",
+        "--cols",
+        "5",
+        "count",
+    ]);
+    assert_eq!(counted(&stdout), 0);
+}
+
+#[test]
+fn merging_overlapping_shards_keeps_each_box_once() {
+    let dir = temp_dir("merge");
+    let list = fixture("letters.txt");
+    let (first, second) = (fixture("shard-1.txt"), fixture("shard-2.txt"));
+    // The first shard has a box of words not in the word list; the second repeats a box of the
+    // first and has the transpose of another, a box with a row too long and a box cut short
+    let merge = |name: &str, options: &[&str]| {
+        let output = dir.join(name);
+        let mut args = vec![
+            "--wordlist",
+            list.to_str().unwrap(),
+            "--output",
+            output.to_str().unwrap(),
+            "merge",
+            first.to_str().unwrap(),
+            second.to_str().unwrap(),
+        ];
+        args.extend(options);
+        let stdout = wordbox(&args);
+        let merged = std::fs::read_to_string(output).unwrap();
+        let summary = stdout.lines().next().unwrap().to_string();
+        (summary, printed_boxes(&merged))
+    };
+    let (summary, boxes) = merge("merged.txt", &[]);
+    assert_eq!(
+        summary,
+        "Merged 2 files: 7 boxes read, 2 records skipped, 2 duplicates removed, 5 unique solutions"
+    );
+    let firsts: Vec<&str> = boxes.iter().map(|rows| rows[0].as_str()).collect();
+    assert_eq!(firsts, ["add", "add", "and", "ant", "zap"]);
+
+    // The box with words the word list does not have is skipped too
+    let (summary, boxes) = merge("checked.txt", &["--check"]);
+    assert_eq!(
+        summary,
+        "Merged 2 files: 6 boxes read, 3 records skipped, 2 duplicates removed, 4 unique solutions"
+    );
+    assert_eq!(boxes.len(), 4);
+
+    let (summary, boxes) = merge("transposes.txt", &["--keep-transposes", "--sort", "input"]);
+    assert_eq!(
+        summary,
+        "Merged 2 files: 7 boxes read, 2 records skipped, 1 duplicates removed, 6 unique solutions"
+    );
+    let firsts: Vec<&str> = boxes.iter().map(|rows| rows[0].as_str()).collect();
+    assert_eq!(firsts, ["add", "add", "and", "zap", "art", "ant"]);
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn near_misses_are_marked_and_no_allowance_changes_nothing() {
+    let dir = temp_dir("near-miss");
+    let list = write_words(&dir, "words.txt", &["abc", "def", "ghi", "adg", "beh"]);
+    let list = list.to_str().unwrap();
+    let args = [
+        "--wordlist",
+        list,
+        "--rows",
+        "3",
+        "--cols",
+        "3",
+        "--asymmetric",
+    ];
+    let search = |allowed: &str| {
+        let mut args = args.to_vec();
+        args.extend(["--allow-invalid-cols", allowed]);
+        wordbox(&args)
+    };
+    assert!(search("0").starts_with("No solutions found"));
+    let near = search("1");
+    assert!(
+        near.contains("abc\ndef\nghi\nnear miss: column 2 reads \"cfi\", not a word\n"),
+        "{}",
+        near
+    );
+
+    // Without near misses the output is exactly as without the option
+    let letters = fixture("letters.txt");
+    let run = |options: &[&str]| {
+        let mut args = vec![
+            "--wordlist",
+            letters.to_str().unwrap(),
+            "--rows",
+            "3",
+            "--cols",
+            "4",
+            "--asymmetric",
+            "--all",
+            "--deterministic",
+        ];
+        args.extend(options);
+        let stdout = wordbox(&args);
+        (stdout.lines())
+            .filter(|line| !line.starts_with("Time Duration"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    assert_eq!(run(&["--allow-invalid-cols", "0"]), run(&[]));
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn forbidding_a_column_word_removes_its_box() {
+    let list = fixture("rectangles.txt");
+    let search = |forbid: &[&str]| {
+        let mut args = vec![
+            "--wordlist",
+            list.to_str().unwrap(),
+            "--rows",
+            "3",
+            "--cols",
+            "4",
+            "--asymmetric",
+            "--all",
+        ];
+        args.extend(forbid);
+        let mut found = printed_boxes(&wordbox(&args));
+        found.sort();
+        found
+    };
+    assert_eq!(
+        search(&[]),
+        [["fast", "area", "dean"], ["fast", "area", "reed"]]
+    );
+    // tan is the last column of fast/area/dean
+    assert_eq!(
+        search(&["--forbid-word", "tan"]),
+        [["fast", "area", "reed"]]
+    );
+}
+
+#[test]
+fn chunked_output_rolls_over_and_merges_back_from_its_index() {
+    let dir = temp_dir("chunks");
+    let list = fixture("letters.txt");
+    let list = list.to_str().unwrap();
+    let index = dir.join("sols.txt");
+    let index = index.to_str().unwrap();
+    let stdout = wordbox(&[
+        "--wordlist",
+        list,
+        "--rows",
+        "3",
+        "--cols",
+        "4",
+        "--asymmetric",
+        "--all",
+        "--deterministic",
+        "--output",
+        index,
+        "--output-chunk-size",
+        "50",
+    ]);
+    let found = printed_boxes(&stdout);
+    assert_eq!(found.len(), 116);
+    assert_eq!(
+        std::fs::read_to_string(index).unwrap(),
+        "# wordbox chunk index\n# chunk first last solutions\n\
+         sols-0001.txt 1 50 50\nsols-0002.txt 51 100 50\nsols-0003.txt 101 116 16\n"
+    );
+    let chunk = std::fs::read_to_string(dir.join("sols-0002.txt")).unwrap();
+    assert_eq!(printed_boxes(&chunk).len(), 50);
+
+    let merged = dir.join("merged.txt");
+    let stdout = wordbox(&[
+        "--wordlist",
+        list,
+        "--output",
+        merged.to_str().unwrap(),
+        "merge",
+        index,
+    ]);
+    assert_eq!(
+        stdout.lines().next().unwrap(),
+        "Merged 1 files: 116 boxes read, 0 records skipped, 0 duplicates removed, 116 unique solutions"
+    );
+    let mut merged = printed_boxes(&std::fs::read_to_string(merged).unwrap());
+    let mut found = found;
+    merged.sort();
+    found.sort();
+    assert_eq!(merged, found);
+    std::fs::remove_dir_all(dir).ok();
+}
@@ -0,0 +1,95 @@
+// Helpers shared by the integration tests; each test crate uses only some of them
+#![allow(dead_code)]
+
+use std::collections::BTreeSet;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use wordbox::lexicon::Lexicons;
+use wordbox::solver::{solve_word_box_all, SolverConfig};
+use wordbox::wordbox::WordBox;
+
+/// An empty directory of its own for the test `name`, under the system's temporary directory
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("wordbox-test-{}-{}", name, std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Write `words`, one per line, to the file `name` in `dir`
+pub fn write_words(dir: &Path, name: &str, words: &[&str]) -> PathBuf {
+    let path = dir.join(name);
+    std::fs::write(&path, words.join("\n") + "\n").unwrap();
+    path
+}
+
+/// Every three-letter string of a and b: any 3x3 box of them is a word box, across and down
+pub fn ab_words() -> Vec<String> {
+    (0..8)
+        .map(|i: u32| {
+            (0..3)
+                .map(|bit| if i >> (2 - bit) & 1 == 0 { 'a' } else { 'b' })
+                .collect()
+        })
+        .collect()
+}
+
+/// Run the wordbox binary with `args`, failing the test if it fails, and return its stdout
+pub fn wordbox(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_wordbox"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "wordbox {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// The number `count` reports, from its "N solutions" line
+pub fn counted(stdout: &str) -> usize {
+    let line = (stdout.lines())
+        .find(|line| line.ends_with(" solutions"))
+        .unwrap_or_else(|| panic!("no count in {:?}", stdout));
+    line.trim_end_matches(" solutions").parse().unwrap()
+}
+
+/// Every solution a search from `wb` finds, each as its rows one per line
+pub fn solutions<L: Lexicons>(
+    wb: WordBox,
+    lexicons: &L,
+    config: &SolverConfig,
+) -> BTreeSet<String> {
+    let mut found = BTreeSet::new();
+    solve_word_box_all(wb, lexicons, config, |solution| {
+        found.insert(solution.to_string());
+        ControlFlow::Continue(())
+    });
+    found
+}
+
+/// The file `name` under `tests/fixtures`
+pub fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+/// Run the wordbox binary with `args`, failing the test unless it fails too, and return its
+/// stderr
+pub fn wordbox_fails(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_wordbox"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        !output.status.success(),
+        "wordbox {} succeeded",
+        args.join(" ")
+    );
+    String::from_utf8(output.stderr).unwrap()
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use common::{counted, fixture, wordbox, wordbox_fails};
+use wordbox::formats::{CsvColumn, FormatError, InputFormat};
+use wordbox::loader::{load_word_list, LoadError, WordFilter};
+
+fn load(
+    name: &str,
+    format: InputFormat,
+    column: &CsvColumn,
+) -> Result<(Vec<String>, InputFormat), LoadError> {
+    let path = fixture(name);
+    let (words, _, format) = load_word_list(
+        path.to_str().unwrap(),
+        format,
+        column,
+        &WordFilter::default(),
+    )?;
+    Ok((words, format))
+}
+
+#[test]
+fn each_format_is_detected_and_read() {
+    let column = CsvColumn::Name("word".to_string());
+    for (name, format) in [
+        ("formats.txt", InputFormat::Text),
+        ("formats.csv", InputFormat::Csv),
+        ("formats.json", InputFormat::Json),
+    ] {
+        let (words, detected) = load(name, InputFormat::Auto, &column).unwrap();
+        assert_eq!(words, ["cat", "dog", "emu"], "{}", name);
+        assert_eq!(detected, format);
+        assert_eq!(load(name, format, &column).unwrap().0, words, "{}", name);
+    }
+    // The default column is the first
+    let (kinds, _) = load("formats.csv", InputFormat::Auto, &CsvColumn::default()).unwrap();
+    assert_eq!(kinds, ["noun", "noun", "verb"]);
+}
+
+#[test]
+fn the_input_format_overrides_detection() {
+    let column = CsvColumn::Name("word".to_string());
+    let err = load("formats-csv.json", InputFormat::Auto, &column).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            LoadError::Format(FormatError::Ambiguous {
+                extension: InputFormat::Json,
+                contents: InputFormat::Csv
+            })
+        ),
+        "{:?}",
+        err
+    );
+    let (words, format) = load("formats-csv.json", InputFormat::Csv, &column).unwrap();
+    assert_eq!(words, ["cat", "dog", "emu"]);
+    assert_eq!(format, InputFormat::Csv);
+
+    let list = fixture("formats-csv.json");
+    let mut args = vec![
+        "--wordlist",
+        list.to_str().unwrap(),
+        "--rows",
+        "3",
+        "--cols",
+        "3",
+    ];
+    assert!(wordbox_fails(&[&args[..], &["count"]].concat()).contains("--input-format"));
+    args.extend(["--input-format", "csv", "--csv-column", "word", "count"]);
+    assert_eq!(counted(&wordbox(&args)), 0);
+}
+
+#[test]
+fn malformed_records_are_named() {
+    let err = load("malformed.json", InputFormat::Auto, &CsvColumn::default()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "read as JSON, element 1 is malformed: expected a string (found \"3, \\\"emu\\\"]\")"
+    );
+}
@@ -0,0 +1,126 @@
+mod common;
+
+use common::{fixture, wordbox_fails};
+use std::io::ErrorKind;
+use wordbox::formats::{CsvColumn, InputFormat};
+use wordbox::loader::{
+    load_word_list, load_words, read_words, stream_words, CaseMode, DropReason, DroppedLine,
+    FilterReport, LoadError, WordFilter,
+};
+
+/// The fixture holding a line dropped for every reason: cat, ice cream, don't, b52, café, two
+/// bytes that are not UTF-8, Paris, NASA, Cat, and dog twice
+fn rejects() -> String {
+    fixture("rejects.txt").to_str().unwrap().to_string()
+}
+
+fn filter(case: CaseMode) -> WordFilter {
+    WordFilter {
+        case,
+        keep_dropped: 20,
+        ..WordFilter::default()
+    }
+}
+
+#[test]
+fn each_reason_is_counted() {
+    let path = rejects();
+    let (words, report) = load_words(&path, &filter(CaseMode::Lowercase));
+    assert_eq!(words, ["cat", "paris", "nasa", "dog", "dog"]);
+    let expected = FilterReport {
+        kept: 5,
+        normalized: 2,
+        collisions: 1,
+        whitespace: 1,
+        punctuation: 1,
+        digits: 1,
+        outside: 1,
+        invalid_utf8: 1,
+        dropped_lines: report.dropped_lines.clone(),
+        ..FilterReport::default()
+    };
+    assert_eq!(report, expected);
+    assert_eq!(report.dropped(), 6);
+    assert_eq!(
+        report.to_string(),
+        "5 words kept (2 lowercased); dropped 1 repeats after lowercasing or stripping, \
+         1 lines with whitespace, 1 lines with punctuation, 1 lines with digits, \
+         1 lines with characters outside the alphabet, 1 lines that are not valid UTF-8"
+    );
+
+    let (words, report) = load_words(&path, &filter(CaseMode::DropProperNouns));
+    assert_eq!(words, ["cat", "nasa", "dog", "dog"]);
+    assert_eq!(
+        (report.proper_nouns, report.normalized, report.collisions),
+        (2, 1, 0)
+    );
+    let (words, report) = load_words(&path, &filter(CaseMode::DropUppercase));
+    assert_eq!(words, ["cat", "dog", "dog"]);
+    assert_eq!((report.uppercase, report.normalized), (3, 0));
+    assert_eq!(report.dropped(), 8);
+}
+
+#[test]
+fn dropped_lines_are_listed_with_their_numbers() {
+    let path = rejects();
+    let (_, report) = read_words(&path, &filter(CaseMode::Lowercase));
+    let listed: Vec<(usize, DropReason)> = (report.dropped_lines.iter())
+        .map(|dropped| (dropped.line, dropped.reason))
+        .collect();
+    assert_eq!(
+        listed,
+        [
+            (2, DropReason::Whitespace),
+            (3, DropReason::Punctuation),
+            (4, DropReason::Digit),
+            (5, DropReason::OutsideAlphabet),
+            (6, DropReason::InvalidUtf8),
+            (9, DropReason::Repeat),
+        ]
+    );
+    assert_eq!(report.dropped_lines[5].text, "Cat");
+    assert_eq!(
+        report.dropped_lines[0].to_string(),
+        "line 2, \"ice cream\", holds whitespace"
+    );
+    // Only as many lines as asked for are listed
+    let few = WordFilter {
+        keep_dropped: 2,
+        ..WordFilter::default()
+    };
+    let (_, report) = read_words(&path, &few);
+    assert_eq!(report.dropped_lines.len(), 2);
+    assert_eq!(report.dropped(), 6);
+}
+
+#[test]
+fn strict_filters_stop_at_the_first_dropped_line() {
+    let path = rejects();
+    let strict = WordFilter {
+        strict: true,
+        ..WordFilter::default()
+    };
+    let first = DroppedLine {
+        line: 2,
+        text: "ice cream".to_string(),
+        reason: DropReason::Whitespace,
+    };
+    let loaded = load_word_list(&path, InputFormat::Auto, &CsvColumn::Index(0), &strict);
+    assert!(
+        matches!(&loaded, Err(LoadError::Dropped(dropped)) if *dropped == first),
+        "{:?}",
+        loaded
+    );
+
+    let bytes = std::fs::read(&path).unwrap();
+    let streamed: Vec<_> = stream_words(&bytes[..], &strict).collect();
+    assert_eq!(streamed.len(), 2);
+    assert_eq!(streamed[0].as_ref().unwrap(), "cat");
+    let err = streamed[1].as_ref().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    let dropped = err.get_ref().unwrap().downcast_ref::<DroppedLine>();
+    assert_eq!(dropped, Some(&first));
+
+    let stderr = wordbox_fails(&["--wordlist", &path, "--strict", "count"]);
+    assert!(stderr.contains("line 2, \"ice cream\""), "{}", stderr);
+}
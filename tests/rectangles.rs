@@ -0,0 +1,105 @@
+mod common;
+
+use common::{fixture, solutions, wordbox_fails};
+use std::collections::BTreeSet;
+use wordbox::lexicon::{BuildLexicon, VecLexicon};
+use wordbox::loader::{load_words, WordFilter};
+use wordbox::solver::SolverConfig;
+use wordbox::wordbox::WordBox;
+
+/// The 3- and 4-letter words of `tests/fixtures/rectangles.txt`
+fn lexicon() -> (Vec<String>, VecLexicon) {
+    let path = fixture("rectangles.txt");
+    let (words, _) = load_words(path.to_str().unwrap(), &WordFilter::default());
+    let lexicon = VecLexicon::initialize(words.clone(), BTreeSet::from([3, 4]));
+    (words, lexicon)
+}
+
+/// The columns of a box printed as its rows one per line, printed the same way
+fn transpose(rows: &str) -> String {
+    let rows: Vec<Vec<char>> = rows.lines().map(|row| row.chars().collect()).collect();
+    (0..rows[0].len())
+        .map(|col| rows.iter().map(|row| row[col]).collect::<String>() + "\n")
+        .collect()
+}
+
+#[test]
+fn every_three_by_four_rectangle_is_found() {
+    let (words, lexicon) = lexicon();
+    let found = solutions(
+        WordBox::new(3, 4, false),
+        &lexicon,
+        &SolverConfig::default(),
+    );
+    let expected: BTreeSet<String> = ["fast\narea\ndean\n", "fast\narea\nreed\n"]
+        .into_iter()
+        .map(String::from)
+        .collect();
+    assert_eq!(found, expected);
+    for rows in &found {
+        assert!(rows.lines().all(|row| words.iter().any(|word| word == row)));
+        let cols = transpose(rows);
+        assert_eq!(cols.lines().count(), 4);
+        assert!(cols.lines().all(|col| words.iter().any(|word| word == col)));
+    }
+
+    // Turned on its side, each is a 4x3 rectangle, and those are all the 4x3 ones
+    let turned = solutions(
+        WordBox::new(4, 3, false),
+        &lexicon,
+        &SolverConfig::default(),
+    );
+    let transposed: BTreeSet<String> = found.iter().map(|rows| transpose(rows)).collect();
+    assert_eq!(turned, transposed);
+
+    // A rectangle's rows and columns differ in length, so none can repeat a word
+    let distinct = SolverConfig {
+        distinct_words: true,
+        ..SolverConfig::default()
+    };
+    assert_eq!(
+        solutions(WordBox::new(3, 4, false), &lexicon, &distinct),
+        found
+    );
+}
+
+#[test]
+fn rectangles_have_no_diagonal_word() {
+    let (_, lexicon) = lexicon();
+    for config in [
+        SolverConfig {
+            diagonal_word: true,
+            ..SolverConfig::default()
+        },
+        SolverConfig {
+            anti_diagonal_word: true,
+            symmetry_breaking: false,
+            ..SolverConfig::default()
+        },
+    ] {
+        assert_eq!(
+            config.shape_conflict(3, 4).as_deref(),
+            Some("a 3x4 box has no diagonal word")
+        );
+        assert_eq!(config.shape_conflict(3, 3), None);
+        assert!(solutions(WordBox::new(3, 4, false), &lexicon, &config).is_empty());
+    }
+    assert_eq!(SolverConfig::default().shape_conflict(3, 4), None);
+
+    let list = fixture("rectangles.txt");
+    let stderr = wordbox_fails(&[
+        "--wordlist",
+        list.to_str().unwrap(),
+        "--rows",
+        "3",
+        "--cols",
+        "4",
+        "--asymmetric",
+        "--diagonal-word",
+    ]);
+    assert!(
+        stderr.contains("Cannot search: a 3x4 box has no diagonal word"),
+        "{}",
+        stderr
+    );
+}
@@ -0,0 +1,144 @@
+mod common;
+
+use common::{ab_words, fixture};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use wordbox::lexicon::{BuildLexicon, Lexicon, VecLexicon};
+use wordbox::loader::{load_words, WordFilter};
+use wordbox::runner::{run_seeds, RunEvent, RunOptions, RunState};
+use wordbox::usage::LetterHeatmap;
+use wordbox::wordbox::WordBox;
+
+/// Solve a `dim`x`dim` asymmetric box from each word of `lexicon` as its first row, returning
+/// the solutions reported, each as its rows one per line, in the order they came
+fn run(lexicon: &VecLexicon, dim: usize, options: &RunOptions) -> (Vec<String>, RunState) {
+    let seeds = lexicon.words_with_prefix("", dim);
+    let build = |seed: &str| {
+        WordBox::new(dim, dim, false)
+            .add_word(seed.to_string())
+            .unwrap()
+    };
+    let state = RunState::default();
+    let mut found = vec![];
+    run_seeds(lexicon, &seeds, build, options, &state, |event| {
+        if let RunEvent::Solution(solution) = event {
+            found.push(solution.word_box.to_string());
+        }
+    });
+    (found, state)
+}
+
+fn ab_lexicon() -> VecLexicon {
+    VecLexicon::initialize(ab_words(), BTreeSet::from([3]))
+}
+
+#[test]
+fn a_global_limit_ends_the_run() {
+    let lexicon = ab_lexicon();
+    for threads in [1, 4] {
+        let options = RunOptions {
+            threads,
+            exhaustive: true,
+            ..RunOptions::default()
+        };
+        // 512 boxes, 64 of them their own transposes, each found once with its transpose
+        let (found, state) = run(&lexicon, 3, &options);
+        assert_eq!(found.len(), 288);
+        assert!(!state.limit_reached());
+        let options = RunOptions {
+            limit: Some(50),
+            ..options
+        };
+        let (found, state) = run(&lexicon, 3, &options);
+        assert_eq!(found.len(), 50, "{} threads", threads);
+        assert_eq!(state.solutions(), 50);
+        assert!(state.limit_reached());
+        if threads == 1 {
+            // The seeds after the one reaching the limit are never searched
+            assert!(state.seeds_done() < 8);
+        }
+    }
+}
+
+#[test]
+fn solutions_found_twice_are_reported_once() {
+    // A word listed twice is two seeds, each finding the same boxes
+    let mut words = ab_words();
+    words.push("aba".to_string());
+    let lexicon = VecLexicon::initialize(words, BTreeSet::from([3]));
+    for threads in [1, 4] {
+        let options = RunOptions {
+            threads,
+            exhaustive: true,
+            dedup: false,
+            ..RunOptions::default()
+        };
+        let (raw, state) = run(&lexicon, 3, &options);
+        let distinct: BTreeSet<&String> = raw.iter().collect();
+        assert!(distinct.len() < raw.len());
+        assert_eq!(state.duplicates(), 0);
+        let options = RunOptions {
+            dedup: true,
+            ..options
+        };
+        let (found, state) = run(&lexicon, 3, &options);
+        assert_eq!(found.iter().collect::<BTreeSet<_>>(), distinct);
+        assert_eq!(found.len(), distinct.len(), "{} threads", threads);
+        assert_eq!(state.duplicates(), raw.len() - found.len());
+        assert_eq!(state.dedup_memory().0, found.len());
+    }
+}
+
+#[test]
+fn the_heatmap_counts_each_solution_once() {
+    // The 3x4 boxes of the fixture are fast/area/dean and fast/area/reed; fast is seeded twice
+    let path = fixture("rectangles.txt");
+    let (words, _) = load_words(path.to_str().unwrap(), &WordFilter::default());
+    let lexicon = VecLexicon::initialize(words, BTreeSet::from([3, 4]));
+    let mut seeds = lexicon.words_with_prefix("", 4);
+    seeds.push("fast".to_string());
+    let build = |seed: &str| {
+        WordBox::new(3, 4, false)
+            .add_word(seed.to_string())
+            .unwrap()
+    };
+    for threads in [1, 4] {
+        let heatmap = Arc::new(LetterHeatmap::new(3, 4));
+        let options = RunOptions {
+            threads,
+            exhaustive: true,
+            letter_heatmap: Some(heatmap.clone()),
+            ..RunOptions::default()
+        };
+        let state = RunState::default();
+        run_seeds(&lexicon, &seeds, build, &options, &state, |_| {});
+        assert_eq!(state.duplicates(), 2);
+        assert_eq!(heatmap.solutions(), 2);
+
+        let expected = [
+            ["f2", "a2", "s2", "t2"],
+            ["a2", "r2", "e2", "a2"],
+            ["d1 r1", "e2", "a1 e1", "d1 n1"],
+        ];
+        for (row, cells) in expected.iter().enumerate() {
+            for (col, cell) in cells.iter().enumerate() {
+                let counts: BTreeMap<char, usize> = (cell.split(' '))
+                    .map(|count| (count.chars().next().unwrap(), count[1..].parse().unwrap()))
+                    .collect();
+                assert_eq!(heatmap.counts(row, col), counts, "cell {}, {}", row, col);
+            }
+        }
+        assert_eq!(heatmap.commonest(2, 0), Some(('d', 0.5)));
+        assert_eq!(
+            heatmap.to_string(),
+            "f 100%  a 100%  s 100%  t 100%\n\
+             a 100%  r 100%  e 100%  a 100%\n\
+             d  50%  e 100%  a  50%  d  50%\n"
+        );
+        assert!(heatmap.to_json().starts_with(
+            "{\"rows\":3,\"cols\":4,\"solutions\":2,\
+             \"letters\":[\"a\",\"d\",\"e\",\"f\",\"n\",\"r\",\"s\",\"t\"],\
+             \"counts\":[[[0,0,0,2,0,0,0,0],[2,0,0,0,0,0,0,0],"
+        ));
+    }
+}
@@ -0,0 +1,977 @@
+mod common;
+
+use common::{fixture, solutions, temp_dir};
+use std::collections::BTreeSet;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use wordbox::letters::{ExcludedLetters, RequiredLetters, VowelRule};
+use wordbox::lexicon::{BuildLexicon, Lexicon, RowColumnLexicons, VecLexicon};
+use wordbox::loader::{load_words, WordFilter};
+use wordbox::scoring::{rank_value, Scorer};
+use wordbox::solver::{
+    collect_word_boxes, estimate_solutions, solve_word_box_all, Heuristic, Outcome, SearchOrder,
+    SolverConfig, ValueOrder,
+};
+use wordbox::spill::SpillOptions;
+use wordbox::stats::CorpusFrequency;
+use wordbox::template::{FixedWord, Line, Template, TemplateError};
+use wordbox::wordbox::{WordBox, WordBoxError};
+
+fn lexicon(words: &[&str]) -> VecLexicon {
+    let words: Vec<String> = words.iter().map(|word| word.to_string()).collect();
+    let lengths = words.iter().map(|word| word.chars().count()).collect();
+    VecLexicon::initialize(words, lengths)
+}
+
+/// The 3- and 4-letter words of `tests/fixtures/letters.txt`, all made of the letters of
+/// "aeorstlnd"
+fn letters() -> VecLexicon {
+    let (words, _) = load_words(
+        fixture("letters.txt").to_str().unwrap(),
+        &WordFilter::default(),
+    );
+    VecLexicon::initialize(words, BTreeSet::from([3, 4]))
+}
+
+/// The boxes the fixture makes, each with its number of rows and columns and whether it is
+/// symmetric, to compare searches on
+const SHAPES: [(usize, usize, bool); 4] =
+    [(3, 3, true), (3, 3, false), (3, 4, false), (4, 4, true)];
+
+/// The words of `tests/fixtures/rectangles.txt`, which make three 4x5 boxes and two 3x4 ones
+fn rectangle_words() -> Vec<String> {
+    let path = fixture("rectangles.txt");
+    load_words(path.to_str().unwrap(), &WordFilter::default()).0
+}
+
+fn boxes(boxes: &[&str]) -> BTreeSet<String> {
+    boxes
+        .iter()
+        .map(|rows| rows.replace(' ', "\n") + "\n")
+        .collect()
+}
+
+#[test]
+fn every_solution_is_found() {
+    let lexicon = lexicon(&["cat", "ace", "ate", "tea"]);
+    let found = solutions(WordBox::new(3, 3, true), &lexicon, &SolverConfig::default());
+    assert_eq!(found, boxes(&["cat ace tea", "cat ate tea"]));
+
+    // The first two are each other's transposes, so both are only listed without symmetry
+    // breaking; the other two are their own
+    let lexicon = self::lexicon(&["ab", "cd", "ac", "bd"]);
+    let config = SolverConfig {
+        symmetry_breaking: false,
+        ..SolverConfig::default()
+    };
+    let found = solutions(WordBox::new(2, 2, false), &lexicon, &config);
+    assert_eq!(found, boxes(&["ab cd", "ac bd", "ab bd", "ac cd"]));
+}
+
+#[test]
+fn a_limit_stops_the_search() {
+    let lexicon = lexicon(&["cat", "ace", "ate", "tea"]);
+    let config = SolverConfig::default();
+    let (found, _) = collect_word_boxes(WordBox::new(3, 3, true), &lexicon, &config, Some(1));
+    assert_eq!(found.len(), 1);
+    let (found, _) = collect_word_boxes(WordBox::new(3, 3, true), &lexicon, &config, None);
+    assert_eq!(found.len(), 2);
+}
+
+#[test]
+fn every_search_order_finds_the_same_solutions() {
+    let lexicon = letters();
+    for (rows, cols, symmetric) in SHAPES {
+        let found: Vec<BTreeSet<String>> = (SearchOrder::ALL.into_iter())
+            .map(|order| {
+                let config = SolverConfig {
+                    order,
+                    ..SolverConfig::default()
+                };
+                solutions(WordBox::new(rows, cols, symmetric), &lexicon, &config)
+            })
+            .collect();
+        assert!(!found[0].is_empty(), "{}x{}", rows, cols);
+        assert!(
+            found.iter().all(|solutions| *solutions == found[0]),
+            "{}x{}",
+            rows,
+            cols
+        );
+    }
+}
+
+#[test]
+fn forward_checking_finds_the_same_solutions() {
+    let lexicon = letters();
+    for (rows, cols, symmetric) in SHAPES {
+        for order in SearchOrder::ALL {
+            let search = |forward_checking| {
+                let config = SolverConfig {
+                    order,
+                    forward_checking,
+                    ..SolverConfig::default()
+                };
+                solutions(WordBox::new(rows, cols, symmetric), &lexicon, &config)
+            };
+            assert_eq!(search(true), search(false), "{}x{}, {}", rows, cols, order);
+        }
+    }
+}
+
+/// A box, as its rows one per line, read down its columns
+fn transpose(rows: &str) -> String {
+    let grid: Vec<Vec<char>> = rows.lines().map(|row| row.chars().collect()).collect();
+    (0..grid[0].len())
+        .map(|col| grid.iter().map(|row| row[col]).collect::<String>() + "\n")
+        .collect()
+}
+
+#[test]
+fn symmetry_breaking_keeps_one_of_each_box_and_its_transpose() {
+    let lexicon = letters();
+    for dim in [3, 4] {
+        let search = |symmetry_breaking| {
+            let config = SolverConfig {
+                symmetry_breaking,
+                ..SolverConfig::default()
+            };
+            solutions(WordBox::new(dim, dim, false), &lexicon, &config)
+        };
+        let all = search(false);
+        let own_transposes = all.iter().filter(|rows| transpose(rows) == **rows).count();
+        let broken = search(true);
+        assert_eq!(
+            broken.len(),
+            (all.len() + own_transposes) / 2,
+            "{}x{}",
+            dim,
+            dim
+        );
+        assert!(broken.is_subset(&all));
+        for rows in &all {
+            assert!(broken.contains(rows) || broken.contains(&transpose(rows)));
+        }
+    }
+    assert_eq!(
+        solutions(
+            WordBox::new(3, 3, false),
+            &lexicon,
+            &SolverConfig::default()
+        )
+        .len(),
+        (406 + 244) / 2
+    );
+}
+
+#[test]
+fn asymmetric_rectangles_are_double_word_rectangles() {
+    let words = rectangle_words();
+    let lexicon = VecLexicon::initialize(words.clone(), BTreeSet::from([4, 5]));
+    let found = solutions(
+        WordBox::new(4, 5, false),
+        &lexicon,
+        &SolverConfig::default(),
+    );
+    assert_eq!(
+        found,
+        boxes(&[
+            "maybe aloud stung horse",
+            "robot adage noble green",
+            "wreak revue elite nylon"
+        ])
+    );
+    for rows in &found {
+        let cols = transpose(rows);
+        assert!(rows.lines().all(|row| words.iter().any(|word| word == row)));
+        assert!(cols.lines().all(|col| words.iter().any(|word| word == col)));
+    }
+    let found = solutions(
+        WordBox::new(5, 4, false),
+        &lexicon,
+        &SolverConfig::default(),
+    );
+    let transposed: BTreeSet<String> = found.iter().map(|rows| transpose(rows)).collect();
+    assert_eq!(
+        transposed,
+        solutions(
+            WordBox::new(4, 5, false),
+            &lexicon,
+            &SolverConfig::default()
+        )
+    );
+}
+
+/// Whether a box, as its rows one per line, uses a word twice across its rows and, unless it is
+/// symmetric, its columns
+fn repeats_a_word(rows: &str, symmetric: bool) -> bool {
+    let cols = match symmetric {
+        true => String::new(),
+        false => transpose(rows),
+    };
+    let words: Vec<&str> = rows.lines().chain(cols.lines()).collect();
+    let distinct: BTreeSet<&str> = words.iter().copied().collect();
+    distinct.len() < words.len()
+}
+
+#[test]
+fn distinct_words_rejects_boxes_using_a_word_twice() {
+    let distinct = SolverConfig {
+        distinct_words: true,
+        symmetry_breaking: false,
+        ..SolverConfig::default()
+    };
+    let repeating = SolverConfig {
+        distinct_words: false,
+        ..distinct.clone()
+    };
+    // Every box these make repeats a row, or a row as a column
+    for (words, symmetric) in [(["aba", "bab"], true), (["ab", "ba"], false)] {
+        let lexicon = lexicon(&words);
+        let dim = words[0].len();
+        let found = solutions(WordBox::new(dim, dim, symmetric), &lexicon, &repeating);
+        assert!(!found.is_empty());
+        assert!(found.iter().all(|rows| repeats_a_word(rows, symmetric)));
+        let found = solutions(WordBox::new(dim, dim, symmetric), &lexicon, &distinct);
+        assert_eq!(found, BTreeSet::new(), "{:?}", words);
+    }
+    let lexicon = letters();
+    for (rows, cols, symmetric) in SHAPES {
+        let all = solutions(WordBox::new(rows, cols, symmetric), &lexicon, &repeating);
+        let expected: BTreeSet<String> = (all.into_iter())
+            .filter(|rows| !repeats_a_word(rows, symmetric))
+            .collect();
+        let found = solutions(WordBox::new(rows, cols, symmetric), &lexicon, &distinct);
+        assert_eq!(found, expected, "{}x{}", rows, cols);
+    }
+}
+
+#[test]
+fn diagonal_word_keeps_the_boxes_whose_diagonal_is_a_word() {
+    let lexicon = lexicon(&[
+        "eat", "are", "tea", "era", "ran", "ant", "cat", "ace", "ate",
+    ]);
+    let wb = || WordBox::new(3, 3, true);
+    assert_eq!(solutions(wb(), &lexicon, &SolverConfig::default()).len(), 9);
+    let config = SolverConfig {
+        diagonal_word: true,
+        ..SolverConfig::default()
+    };
+    // Their diagonals read "era" and "eat"
+    let expected = boxes(&["eat are tea", "era ran ant"]);
+    assert_eq!(solutions(wb(), &lexicon, &config), expected);
+    let config = SolverConfig {
+        distinct_words: true,
+        forward_checking: true,
+        ..config
+    };
+    assert_eq!(solutions(wb(), &lexicon, &config), expected);
+    // No anti-diagonal is a word: "trt", "aaa" and so on
+    let config = SolverConfig {
+        anti_diagonal_word: true,
+        ..SolverConfig::default()
+    };
+    assert_eq!(solutions(wb(), &lexicon, &config), BTreeSet::new());
+}
+
+#[test]
+fn excluded_letters_appear_in_no_box() {
+    let excluded: ExcludedLetters = "s".parse().unwrap();
+    let (words, _) = load_words(
+        fixture("letters.txt").to_str().unwrap(),
+        &WordFilter::default(),
+    );
+    let (kept, removed) = excluded.remove_from(words.clone());
+    assert_eq!(kept.len() + removed, words.len());
+    let all = VecLexicon::initialize(words, BTreeSet::from([3, 4]));
+    let lexicon = VecLexicon::initialize(kept, BTreeSet::from([3, 4]));
+    for (rows, cols, symmetric) in SHAPES {
+        let found = solutions(
+            WordBox::new(rows, cols, symmetric),
+            &lexicon,
+            &SolverConfig::default(),
+        );
+        assert!(!found.is_empty(), "{}x{}", rows, cols);
+        // The columns are read off the rows, so no letter can hide in a column
+        for rows in &found {
+            assert!(!rows.contains('s'), "{}", rows);
+            assert!(!transpose(rows).contains('s'), "{}", rows);
+        }
+        let expected: BTreeSet<String> = (solutions(
+            WordBox::new(rows, cols, symmetric),
+            &all,
+            &SolverConfig::default(),
+        ))
+        .into_iter()
+        .filter(|rows| !rows.contains('s'))
+        .collect();
+        assert_eq!(found, expected, "{}x{}", rows, cols);
+    }
+    let required: RequiredLetters = "at".parse().unwrap();
+    assert_eq!(excluded.conflicts(&required).count(), 0);
+    let required: RequiredLetters = "as".parse().unwrap();
+    assert_eq!(excluded.conflicts(&required).collect::<Vec<_>>(), ['s']);
+}
+
+#[test]
+fn beam_search_finds_a_big_box_in_few_nodes() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/3esl.txt");
+    let (words, _) = load_words(path, &WordFilter::default());
+    let lexicon = VecLexicon::initialize(words, BTreeSet::from([6]));
+    let first = |beam_width| {
+        let config = SolverConfig {
+            beam_width,
+            ..SolverConfig::default()
+        };
+        let mut first = None;
+        let report = solve_word_box_all(WordBox::new(6, 6, true), &lexicon, &config, |wb| {
+            first = Some(wb);
+            ControlFlow::Break(())
+        });
+        (first.expect("a solution"), report)
+    };
+    let (_, depth_first) = first(None);
+    let (solution, beam) = first(Some(30));
+    assert!(solution.is_valid(&lexicon), "{}", solution);
+    assert_eq!(beam.outcome, Outcome::Stopped);
+    assert!(
+        beam.stats.nodes * 5 < depth_first.stats.nodes,
+        "beam search took {} nodes, depth-first search {}",
+        beam.stats.nodes,
+        depth_first.stats.nodes
+    );
+}
+
+#[test]
+fn an_unbounded_beam_finds_every_solution() {
+    let lexicon = letters();
+    for (rows, cols, symmetric) in SHAPES {
+        let config = SolverConfig {
+            beam_width: Some(usize::MAX),
+            ..SolverConfig::default()
+        };
+        assert_eq!(
+            solutions(WordBox::new(rows, cols, symmetric), &lexicon, &config),
+            solutions(
+                WordBox::new(rows, cols, symmetric),
+                &lexicon,
+                &SolverConfig::default()
+            ),
+            "{}x{}",
+            rows,
+            cols
+        );
+    }
+    // A beam too narrow to hold every box says the search may have missed some
+    let config = SolverConfig {
+        beam_width: Some(1),
+        ..SolverConfig::default()
+    };
+    let report = solve_word_box_all(WordBox::new(3, 3, false), &lexicon, &config, |_| {
+        ControlFlow::Continue(())
+    });
+    assert_eq!(report.outcome, Outcome::Pruned);
+}
+
+#[test]
+fn rows_and_columns_can_come_from_different_lexicons() {
+    let config = SolverConfig {
+        symmetry_breaking: false,
+        ..SolverConfig::default()
+    };
+    let cases: [(&[&str], &[&str], &str); 2] = [
+        (&["ab", "cd"], &["ac", "bd"], "ab cd"),
+        (&["bat", "ore"], &["bo", "ar", "te"], "bat ore"),
+    ];
+    for (row_words, col_words, expected) in cases {
+        let (row_lexicon, col_lexicon) = (lexicon(row_words), lexicon(col_words));
+        let wb = || WordBox::new(col_words[0].len(), row_words[0].len(), false);
+        assert_eq!(solutions(wb(), &row_lexicon, &config), BTreeSet::new());
+        assert_eq!(solutions(wb(), &col_lexicon, &config), BTreeSet::new());
+        let lexicons = RowColumnLexicons {
+            rows: &row_lexicon,
+            cols: &col_lexicon,
+        };
+        assert_eq!(solutions(wb(), &lexicons, &config), boxes(&[expected]));
+    }
+}
+
+#[test]
+fn words_shorter_than_the_box_are_never_placed() {
+    let (words, _) = load_words(
+        fixture("letters.txt").to_str().unwrap(),
+        &WordFilter::default(),
+    );
+    let fours: Vec<String> = words
+        .iter()
+        .filter(|word| word.len() == 4)
+        .cloned()
+        .collect();
+    let mixed = VecLexicon::initialize(words.clone(), BTreeSet::from([3, 4]));
+    let threes = VecLexicon::initialize(words, BTreeSet::from([3]));
+    let fours = VecLexicon::initialize(fours, BTreeSet::from([4]));
+    for heuristic in Heuristic::ALL {
+        for forward_checking in [false, true] {
+            let config = SolverConfig {
+                heuristic,
+                forward_checking,
+                ..SolverConfig::default()
+            };
+            for symmetric in [true, false] {
+                let wb = || WordBox::new(4, 4, symmetric);
+                let found = solutions(wb(), &mixed, &config);
+                assert!(!found.is_empty());
+                assert_eq!(found, solutions(wb(), &fours, &config));
+                assert_eq!(solutions(wb(), &threes, &config), BTreeSet::new());
+            }
+        }
+    }
+    let wb = WordBox::new(4, 4, false);
+    assert_eq!(
+        wb.add_word("tea".to_string()),
+        Err(WordBoxError::WrongLength {
+            word: "tea".to_string(),
+            expected: 4
+        })
+    );
+}
+
+#[test]
+fn rectangles_are_solved_asymmetrically() {
+    let lexicon = lexicon(&[
+        "wasted", "oriole", "refuse", "matter", "matted", "warren", "worm", "area", "sift", "tout",
+        "else", "deer", "deed", "rift", "rout", "need",
+    ]);
+    let found = solutions(
+        WordBox::new(4, 6, false),
+        &lexicon,
+        &SolverConfig::default(),
+    );
+    assert_eq!(
+        found,
+        boxes(&[
+            "wasted oriole refuse matter",
+            "wasted oriole refuse matted",
+            "warren oriole refuse matted"
+        ])
+    );
+    assert_eq!(
+        WordBox::try_new(4, 6, true).unwrap_err().to_string(),
+        "a 4x6 box cannot be symmetric: every row is also a column, so symmetric boxes must be \
+         square (use an asymmetric box, --asymmetric, for rectangles)"
+    );
+}
+
+#[test]
+fn fixed_words_keep_the_boxes_with_them_in_place() {
+    let lexicon = letters();
+    let config = SolverConfig {
+        symmetry_breaking: false,
+        ..SolverConfig::default()
+    };
+    let all = solutions(WordBox::new(4, 4, false), &lexicon, &config);
+    let rows_of = |rows: &String| rows.lines().map(str::to_string).collect::<Vec<_>>();
+    // Row 2 and column 0 of a box from the middle of the list
+    let sample = all.iter().nth(all.len() / 2).unwrap();
+    let (row, col) = (&rows_of(sample)[2], &rows_of(&transpose(sample))[0]);
+    let fixes = [
+        vec![format!("row:2:{}", row)],
+        vec![format!("col:0:{}", col)],
+        vec![format!("row:2:{}", row), format!("col:0:{}", col)],
+    ];
+    for fixes in fixes {
+        let fixes: Vec<FixedWord> = fixes.iter().map(|fix| fix.parse().unwrap()).collect();
+        let mut template = Template::open(4, 4);
+        for fix in &fixes {
+            template = template.with_fixed(fix, &Default::default()).unwrap();
+        }
+        let wb = WordBox::new(4, 4, false).with_template(&template);
+        let expected: BTreeSet<String> = (all.iter())
+            .filter(|rows| {
+                let (rows, cols) = (rows_of(rows), rows_of(&transpose(rows)));
+                fixes.iter().all(|fix| match fix.line {
+                    Line::Row => rows[fix.index] == fix.word,
+                    Line::Col => cols[fix.index] == fix.word,
+                })
+            })
+            .cloned()
+            .collect();
+        assert!(!expected.is_empty() && expected.len() < all.len());
+        assert_eq!(solutions(wb, &lexicon, &config), expected, "{:?}", fixes);
+    }
+}
+
+#[test]
+fn conflicting_fixed_words_are_rejected() {
+    let row: FixedWord = "row:0:tale".parse().unwrap();
+    let col: FixedWord = "col:0:sale".parse().unwrap();
+    let template = Template::open(4, 4).with_fixed(&row, &Default::default());
+    let err = (template.unwrap().with_fixed(&col, &Default::default())).unwrap_err();
+    assert_eq!(
+        err,
+        TemplateError::FixConflict {
+            fix: col,
+            row: 0,
+            col: 0,
+            locked: 't'
+        }
+    );
+    let long: FixedWord = "col:1:tales".parse().unwrap();
+    assert!(matches!(
+        Template::open(4, 4).with_fixed(&long, &Default::default()),
+        Err(TemplateError::FixLength { expected: 4, .. })
+    ));
+    let outside: FixedWord = "row:4:tale".parse().unwrap();
+    assert!(matches!(
+        Template::open(4, 4).with_fixed(&outside, &Default::default()),
+        Err(TemplateError::FixOutOfRange { lines: 4, .. })
+    ));
+}
+
+#[test]
+fn estimates_converge_to_the_exact_count() {
+    let lexicon = letters();
+    let wb = || WordBox::new(3, 3, true);
+    let config = SolverConfig::default();
+    let exact = solutions(wb(), &lexicon, &config).len() as f64;
+    let errors: Vec<f64> = [100, 6400]
+        .into_iter()
+        .map(|samples| {
+            let estimate = estimate_solutions(wb(), &lexicon, &config, samples, 7);
+            assert_eq!(estimate.samples, samples);
+            assert!(
+                (estimate.solutions - exact).abs() < 4.0 * estimate.std_error,
+                "{} from {} samples, against {}",
+                estimate.solutions,
+                samples,
+                exact
+            );
+            estimate.std_error / exact
+        })
+        .collect();
+    // Sixty-four times the samples should cut the error eightfold
+    assert!(errors[1] < errors[0] / 4.0, "{:?}", errors);
+    assert!(errors[1] < 0.05, "{:?}", errors);
+}
+
+#[test]
+fn a_template_one_letter_off_reports_how_close_it_got() {
+    let lexicon = letters();
+    let config = SolverConfig {
+        best_partial: true,
+        ..SolverConfig::default()
+    };
+    let solve = |template: &str| {
+        let template = Template::parse(template, &Default::default()).unwrap();
+        let wb = WordBox::new(3, 3, false).with_template(&template);
+        solve_word_box_all(wb, &lexicon, &config, |_| ControlFlow::Continue(()))
+    };
+    assert!(solve("...\n...\n..e").solutions > 0);
+
+    // Words end in o, but no box of the fixture does
+    let report = solve("...\n...\n..o");
+    assert_eq!((report.solutions, report.outcome), (0, Outcome::Exhausted));
+    let best = report.best_partial.unwrap();
+    assert_eq!(best.word_box.row_words().len(), 2);
+    assert!(best.word_box.row_words()[1].ends_with('o'));
+}
+
+#[test]
+fn the_vowel_rule_rejects_lines_without_vowels() {
+    // tab/spa/keg reads tsk down its first column, and its transpose has tsk as a row
+    let lexicon = lexicon(&[
+        "tab", "spa", "keg", "ape", "bag", "tsk", "cat", "ace", "tea", "ate",
+    ]);
+    let search = |vowel_rule: VowelRule| {
+        let config = SolverConfig {
+            vowel_rule,
+            symmetry_breaking: false,
+            ..SolverConfig::default()
+        };
+        solutions(WordBox::new(3, 3, false), &lexicon, &config)
+    };
+    let all = search(VowelRule::default());
+    assert!(all.is_superset(&boxes(&["tab spa keg", "tsk ape bag", "cat ace tea"])));
+    let has_vowels = |line: &str| line.contains(['a', 'e', 'i', 'o', 'u']);
+    let expected: BTreeSet<String> = (all.iter())
+        .filter(|rows| rows.lines().chain(transpose(rows).lines()).all(has_vowels))
+        .cloned()
+        .collect();
+    assert!(!expected.contains("tab\nspa\nkeg\n") && !expected.contains("tsk\nape\nbag\n"));
+    assert!(expected.contains("cat\nace\ntea\n"));
+    let per_line = VowelRule {
+        per_line: true,
+        ..VowelRule::default()
+    };
+    assert_eq!(search(per_line), expected);
+    // tsk is a run of three consonants, however many vowels the rule counts
+    let runs = |max| VowelRule {
+        vowels: "aeiouy".parse().unwrap(),
+        max_consonant_run: Some(max),
+        ..VowelRule::default()
+    };
+    assert_eq!(search(runs(2)), expected);
+    assert_eq!(search(runs(3)), all);
+}
+
+#[test]
+fn isograms_use_every_letter_once() {
+    // wolf/jinx/dusk/pyre holds sixteen letters; ending in pyro instead repeats the o of wolf
+    let lexicon = lexicon(&[
+        "wolf", "jinx", "dusk", "pyre", "pyro", "wjdp", "oiuy", "lnsr", "fxke", "fxko",
+    ]);
+    let search = |isogram| {
+        let config = SolverConfig {
+            isogram,
+            ..SolverConfig::default()
+        };
+        solutions(WordBox::new(4, 4, false), &lexicon, &config)
+    };
+    let all = search(false);
+    assert_eq!(all.len(), 2);
+    let found = search(true);
+    assert_eq!(found.len(), 1);
+    let rows = found.first().unwrap();
+    assert!(rows == "wolf\njinx\ndusk\npyre\n" || transpose(rows) == "wolf\njinx\ndusk\npyre\n");
+    let letters: BTreeSet<char> = rows.chars().filter(|&ch| ch != '\n').collect();
+    assert_eq!(letters.len(), 16);
+
+    // Every letter off the diagonal of a symmetric box fills two cells
+    let lexicon = self::lexicon(&["cat", "ace", "tea"]);
+    let config = SolverConfig {
+        isogram: true,
+        ..SolverConfig::default()
+    };
+    assert_eq!(
+        solutions(WordBox::new(3, 3, true), &lexicon, &SolverConfig::default()).len(),
+        1
+    );
+    assert!(solutions(WordBox::new(3, 3, true), &lexicon, &config).is_empty());
+}
+
+#[test]
+fn a_scorer_of_ones_own_changes_the_ranking() {
+    let lexicon = lexicon(&["cat", "ace", "ate", "tea"]);
+    // The first box best-first search finds, and the one box a beam of one keeps, by `scorer`
+    let first = |scorer: Arc<dyn Scorer>| {
+        let best_first = SolverConfig {
+            order: SearchOrder::BestFirst,
+            scorer: Some(scorer.clone()),
+            ..SolverConfig::default()
+        };
+        let (found, _) =
+            collect_word_boxes(WordBox::new(3, 3, true), &lexicon, &best_first, Some(1));
+        let beam = SolverConfig {
+            beam_width: Some(1),
+            scorer: Some(scorer),
+            ..SolverConfig::default()
+        };
+        let kept = solutions(WordBox::new(3, 3, true), &lexicon, &beam);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept.first(), Some(&found[0].to_string()));
+        found[0].row_words()[1].clone()
+    };
+    let preferring = |word: &'static str| -> Arc<dyn Scorer> {
+        Arc::new(move |wb: &WordBox, _: &dyn Lexicon| {
+            f64::from(u8::from(wb.row_words().iter().any(|row| row == word)))
+        })
+    };
+    assert_eq!(first(preferring("ace")), "ace");
+    assert_eq!(first(preferring("ate")), "ate");
+
+    // A score that is not a number ranks below every other
+    let nan_for = |word: &'static str| -> Arc<dyn Scorer> {
+        Arc::new(move |wb: &WordBox, _: &dyn Lexicon| {
+            match wb.row_words().iter().any(|row| row == word) {
+                true => f64::NAN,
+                false => 0.0,
+            }
+        })
+    };
+    assert_eq!(first(nan_for("ace")), "ate");
+    assert_eq!(first(nan_for("ate")), "ace");
+    assert_eq!(rank_value(f64::INFINITY), f64::NEG_INFINITY);
+}
+
+#[test]
+fn boxes_without_cells_are_refused() {
+    for (row_dim, col_dim) in [(0, 3), (3, 0), (0, 0)] {
+        for symmetric in [false, true] {
+            assert_eq!(
+                WordBox::try_new(row_dim, col_dim, symmetric).err(),
+                Some(WordBoxError::NoCells { row_dim, col_dim })
+            );
+        }
+    }
+}
+
+#[test]
+fn one_row_and_one_column_boxes_need_one_letter_words() {
+    let config = SolverConfig::default();
+    let with_letters = lexicon(&["cat", "act", "c", "a", "t"]);
+    let without = lexicon(&["cat", "act"]);
+    let (across, down) = (WordBox::new(1, 3, false), WordBox::new(3, 1, false));
+    assert_eq!(
+        solutions(across.clone(), &with_letters, &config),
+        BTreeSet::from(["cat\n".to_string(), "act\n".to_string()])
+    );
+    assert_eq!(
+        solutions(down.clone(), &with_letters, &config),
+        BTreeSet::from(["c\na\nt\n".to_string(), "a\nc\nt\n".to_string()])
+    );
+    assert!(solutions(across, &without, &config).is_empty());
+    assert!(solutions(down, &without, &config).is_empty());
+    // A single cell is a one-letter word across and down
+    for symmetric in [false, true] {
+        let found = solutions(WordBox::new(1, 1, symmetric), &with_letters, &config);
+        assert_eq!(
+            found,
+            BTreeSet::from(["a\n", "c\n", "t\n"].map(String::from))
+        );
+    }
+}
+
+#[test]
+fn boxes_longer_than_any_word_have_no_solutions() {
+    let config = SolverConfig::default();
+    assert!(solutions(WordBox::new(5, 5, true), &letters(), &config).is_empty());
+    assert!(solutions(WordBox::new(3, 5, false), &letters(), &config).is_empty());
+}
+
+/// The box whose rows are the lines of `solution`, as `solutions` gives them
+fn box_of(solution: &str) -> WordBox {
+    let rows: Vec<&str> = solution.lines().collect();
+    (rows.iter()).fold(WordBox::new(rows.len(), rows[0].len(), false), |wb, row| {
+        wb.add_word(row.to_string()).unwrap()
+    })
+}
+
+#[test]
+fn near_misses_have_at_most_the_columns_allowed_that_are_not_words() {
+    // The only boxes have a last column, c f i, that is not a word
+    let lexicon = lexicon(&["abc", "def", "ghi", "adg", "beh"]);
+    let near = |allowed: usize| SolverConfig {
+        allow_invalid_cols: allowed,
+        symmetry_breaking: false,
+        ..SolverConfig::default()
+    };
+    let wb = WordBox::new(3, 3, false);
+    assert!(solutions(wb.clone(), &lexicon, &near(0)).is_empty());
+    let found = solutions(wb, &lexicon, &near(1));
+    assert!(found.contains("abc\ndef\nghi\n"), "{:?}", found);
+    for solution in &found {
+        assert_eq!(
+            box_of(solution).invalid_columns(&lexicon).len(),
+            1,
+            "{}",
+            solution
+        );
+    }
+    assert_eq!(box_of("abc\ndef\nghi\n").invalid_columns(&lexicon), [2]);
+}
+
+#[test]
+fn allowing_no_near_misses_is_the_exact_search() {
+    let lexicon = letters();
+    for (rows, cols, symmetric) in SHAPES.into_iter().filter(|&(_, _, symmetric)| !symmetric) {
+        let config = |allowed: usize| SolverConfig {
+            allow_invalid_cols: allowed,
+            symmetry_breaking: false,
+            ..SolverConfig::default()
+        };
+        let wb = WordBox::new(rows, cols, symmetric);
+        let exact = solutions(wb.clone(), &lexicon, &config(0));
+        assert!(!exact.is_empty());
+        let unchanged = SolverConfig {
+            symmetry_breaking: false,
+            ..SolverConfig::default()
+        };
+        assert_eq!(solutions(wb.clone(), &lexicon, &unchanged), exact);
+        let near = solutions(wb, &lexicon, &config(1));
+        let valid: BTreeSet<String> = (near.iter())
+            .filter(|solution| box_of(solution).invalid_columns(&lexicon).is_empty())
+            .cloned()
+            .collect();
+        assert_eq!(valid, exact, "{}x{}", rows, cols);
+        assert!(near.len() > exact.len());
+    }
+}
+
+#[test]
+fn a_spilling_frontier_finds_what_an_in_memory_one_does() {
+    let dir = temp_dir("spill");
+    let lexicon = letters();
+    for (rows, cols, symmetric) in SHAPES {
+        let wb = WordBox::new(rows, cols, symmetric);
+        let in_memory = SolverConfig {
+            order: SearchOrder::BreadthFirst,
+            ..SolverConfig::default()
+        };
+        let spilling = SolverConfig {
+            spill: Some(SpillOptions {
+                threshold: 2,
+                dir: dir.clone(),
+            }),
+            ..in_memory.clone()
+        };
+        let (mut expected, mut found) = (vec![], vec![]);
+        let report = solve_word_box_all(wb.clone(), &lexicon, &in_memory, |solution| {
+            expected.push(solution.to_string());
+            ControlFlow::Continue(())
+        });
+        assert_eq!(report.stats.spilled, 0);
+        let report = solve_word_box_all(wb.clone(), &lexicon, &spilling, |solution| {
+            found.push(solution.to_string());
+            ControlFlow::Continue(())
+        });
+        // Level by level, in the same order
+        assert_eq!(found, expected, "{}x{}", rows, cols);
+        assert!(report.stats.spilled > 0 && report.stats.spilled_bytes > 0);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+        // Files still waiting to be read back when the search stops are deleted too
+        let (first, _) = collect_word_boxes(wb, &lexicon, &spilling, Some(1));
+        assert_eq!(first.len(), 1);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+    }
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn a_forbidden_column_word_rules_out_its_boxes() {
+    let lexicon = VecLexicon::initialize(rectangle_words(), BTreeSet::from([3, 4]));
+    let wb = WordBox::new(3, 4, false);
+    let forbidding = |words: &[&str]| SolverConfig {
+        forbidden_words: Some(Arc::new(
+            words.iter().map(|word| word.to_string()).collect(),
+        )),
+        ..SolverConfig::default()
+    };
+    let all = boxes(&["fast area dean", "fast area reed"]);
+    assert_eq!(
+        solutions(wb.clone(), &lexicon, &SolverConfig::default()),
+        all
+    );
+    // The columns of fast/area/dean are fad, are, sea and tan
+    let mut found = BTreeSet::new();
+    let report = solve_word_box_all(wb.clone(), &lexicon, &forbidding(&["tan"]), |solution| {
+        found.insert(solution.to_string());
+        ControlFlow::Continue(())
+    });
+    assert_eq!(found, boxes(&["fast area reed"]));
+    assert_eq!(report.stats.forbidden, 1);
+    // A row word is never placed, and a word of neither box changes nothing
+    assert!(solutions(wb.clone(), &lexicon, &forbidding(&["area"])).is_empty());
+    assert_eq!(solutions(wb, &lexicon, &forbidding(&["zzz"])), all);
+    let config = forbidding(&["tan"]);
+    assert!(config.forbids("tan") && !config.forbids("tank"));
+}
+
+#[test]
+fn rotational_boxes_read_the_same_upside_down() {
+    // step time emit pets turned upside down is itself, and so is the box of its rows read
+    // from the bottom; the other words only make boxes that are not
+    let fours = lexicon(&[
+        "step", "time", "emit", "pets", "stem", "mite", "item", "spit", "tips", "pest", "sets",
+        "ties", "mist",
+    ]);
+    let found = solutions(
+        WordBox::new(4, 4, false).with_rotation(),
+        &fours,
+        &SolverConfig::default(),
+    );
+    assert_eq!(
+        found,
+        boxes(&["step time emit pets", "pets emit time step"])
+    );
+    assert!(found
+        .iter()
+        .all(|solution| box_of(solution).is_rotation_symmetric()));
+    let plain = solutions(WordBox::new(4, 4, false), &fours, &SolverConfig::default());
+    assert!(plain.len() > found.len());
+
+    // With an odd number of rows the middle row and column are their own twins, so have to
+    // be palindromes
+    let threes = lexicon(&[
+        "ten", "net", "eve", "eye", "tan", "ant", "nee", "tee", "eta", "ate", "tea",
+    ]);
+    let found = solutions(
+        WordBox::new(3, 3, false).with_rotation(),
+        &threes,
+        &SolverConfig::default(),
+    );
+    assert_eq!(
+        found,
+        boxes(&["ten eve net", "ten eye net", "net eve ten", "net eye ten"])
+    );
+    for solution in &found {
+        assert!(box_of(solution).is_rotation_symmetric());
+        for middle in [solution.clone(), transpose(solution)] {
+            let middle = middle.lines().nth(1).unwrap();
+            assert!(middle.chars().eq(middle.chars().rev()), "{}", solution);
+        }
+    }
+
+    // Forward checking and the most-constrained heuristic reject twins in different places,
+    // and neither may lose a box the plain search finds
+    for (lexicon, size) in [(&threes, 3), (&fours, 4)] {
+        for heuristic in Heuristic::ALL {
+            for forward_checking in [false, true] {
+                let config = SolverConfig {
+                    heuristic,
+                    forward_checking,
+                    ..SolverConfig::default()
+                };
+                for symmetric in [true, false] {
+                    let wb = || WordBox::new(size, size, symmetric);
+                    let rotational: BTreeSet<String> = (solutions(wb(), lexicon, &config))
+                        .into_iter()
+                        .filter(|solution| box_of(solution).is_rotation_symmetric())
+                        .collect();
+                    assert!(!rotational.is_empty());
+                    assert_eq!(
+                        solutions(wb().with_rotation(), lexicon, &config),
+                        rotational,
+                        "{}x{} {:?} {} {}",
+                        size,
+                        size,
+                        heuristic,
+                        forward_checking,
+                        symmetric
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn corpus_frequency_order_finds_what_lexicon_order_does() {
+    let lexicon = letters();
+    for (rows, cols, symmetric) in SHAPES {
+        let frequency = Arc::new(CorpusFrequency::of(&lexicon, rows, cols));
+        for heuristic in Heuristic::ALL {
+            for forward_checking in [false, true] {
+                let config = |value_order| SolverConfig {
+                    heuristic,
+                    forward_checking,
+                    value_order,
+                    corpus_frequency: Some(frequency.clone()),
+                    ..SolverConfig::default()
+                };
+                let wb = || WordBox::new(rows, cols, symmetric);
+                let found = solutions(wb(), &lexicon, &config(ValueOrder::Lexicon));
+                assert!(!found.is_empty());
+                assert_eq!(
+                    solutions(wb(), &lexicon, &config(ValueOrder::CorpusFrequency)),
+                    found,
+                    "{}x{} {:?} {}",
+                    rows,
+                    cols,
+                    heuristic,
+                    forward_checking
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,146 @@
+mod common;
+
+use common::{fixture, solutions};
+use std::collections::{BTreeSet, HashSet};
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use wordbox::alphabet::Alphabet;
+use wordbox::lexicon::{BuildLexicon, Lexicon, LexiconKind, LexiconOptions, VecLexicon};
+use wordbox::loader::{load_words, WordFilter};
+use wordbox::solver::{solve_word_box_all, SolverConfig, ValueOrder};
+use wordbox::stats::CorpusFrequency;
+use wordbox::wordbox::WordBox;
+
+fn load(name: &str) -> Vec<String> {
+    let filter = WordFilter {
+        alphabet: Alphabet::Unicode,
+        ..WordFilter::default()
+    };
+    let (words, report) = load_words(fixture(name).to_str().unwrap(), &filter);
+    assert_eq!(report.dropped(), 0, "{:?}", report);
+    words
+}
+
+#[test]
+fn a_spanish_square_solves() {
+    let words = load("spanish.txt");
+    for kind in LexiconKind::ALL {
+        let lexicon = kind.build(
+            words.clone(),
+            BTreeSet::from([4]),
+            &LexiconOptions::default(),
+        );
+        let found = solutions(WordBox::new(4, 4, true), &lexicon, &SolverConfig::default());
+        assert!(
+            found.contains("caña\namor\nñoño\naros\n"),
+            "{} lexicon found {:?}",
+            kind,
+            found
+        );
+        // Asymmetric boxes read their columns off the rows, a letter at a time
+        let found = solutions(
+            WordBox::new(4, 4, false),
+            &lexicon,
+            &SolverConfig::default(),
+        );
+        assert!(
+            found.contains("caña\namor\nñoño\naros\n"),
+            "{} lexicon",
+            kind
+        );
+    }
+}
+
+#[test]
+fn a_russian_square_solves() {
+    let words = load("russian.txt");
+    for kind in LexiconKind::ALL {
+        let lexicon = kind.build(
+            words.clone(),
+            BTreeSet::from([3]),
+            &LexiconOptions::default(),
+        );
+        let found = solutions(WordBox::new(3, 3, true), &lexicon, &SolverConfig::default());
+        assert_eq!(
+            found,
+            BTreeSet::from(["кот\nоса\nтак\n".to_string()]),
+            "{} lexicon",
+            kind
+        );
+    }
+}
+
+#[test]
+fn words_are_as_long_as_their_letters() {
+    let words = load("spanish.txt");
+    for kind in LexiconKind::ALL {
+        let lexicon = kind.build(
+            words.clone(),
+            BTreeSet::from([3, 4]),
+            &LexiconOptions::default(),
+        );
+        let mut threes = lexicon.words_with_prefix("", 3);
+        threes.sort();
+        assert_eq!(threes, ["así", "año", "más"], "{} lexicon", kind);
+        let after = lexicon.letters_after("ca", 4);
+        assert_eq!(after, HashSet::from(['f', 'ñ']), "{} lexicon", kind);
+        assert_eq!(
+            lexicon.words_with_prefix("ñ", 4),
+            ["ñoño"],
+            "{} lexicon",
+            kind
+        );
+    }
+}
+
+/// The nodes searched before the first solution, trying each word of the lexicon in turn as
+/// the first row of a `dim`-letter square
+fn nodes_to_first_solution(
+    words: &[String],
+    dim: usize,
+    symmetric: bool,
+    value_order: ValueOrder,
+) -> usize {
+    let lexicon = VecLexicon::initialize(words.to_vec(), BTreeSet::from([dim]));
+    let config = SolverConfig {
+        value_order,
+        corpus_frequency: Some(Arc::new(CorpusFrequency::of(&lexicon, dim, dim))),
+        ..SolverConfig::default()
+    };
+    let mut nodes = 0;
+    let found = lexicon.words_with_prefix("", dim).iter().any(|seed| {
+        let wb = WordBox::new(dim, dim, symmetric)
+            .add_word(seed.to_string())
+            .unwrap();
+        let report = solve_word_box_all(wb, &lexicon, &config, |_| ControlFlow::Break(()));
+        nodes += report.stats.nodes;
+        report.solutions > 0
+    });
+    assert!(found);
+    nodes
+}
+
+#[test]
+fn corpus_frequency_reaches_a_spanish_box_sooner() {
+    // The small list holds one box, with one way to it, so the order makes no difference
+    let words = load("spanish.txt");
+    for symmetric in [true, false] {
+        assert_eq!(
+            nodes_to_first_solution(&words, 4, symmetric, ValueOrder::CorpusFrequency),
+            nodes_to_first_solution(&words, 4, symmetric, ValueOrder::Lexicon)
+        );
+    }
+    // Over a longer list, in alphabetical order, words of common letters lead to a box sooner
+    let words = load("spanish-4.txt");
+    for (symmetric, corpus, lexicon) in [(true, 42, 43), (false, 84, 91)] {
+        assert_eq!(
+            (
+                nodes_to_first_solution(&words, 4, symmetric, ValueOrder::CorpusFrequency),
+                nodes_to_first_solution(&words, 4, symmetric, ValueOrder::Lexicon)
+            ),
+            (corpus, lexicon),
+            "symmetric: {}",
+            symmetric
+        );
+    }
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use common::{solutions, temp_dir, write_words};
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+use wordbox::cancel::CancellationToken;
+use wordbox::lexicon::{BuildLexicon, VecLexicon};
+use wordbox::loader::{load_words, WordFilter};
+use wordbox::solver::SolverConfig;
+use wordbox::watch::FileWatcher;
+use wordbox::wordbox::WordBox;
+
+const POLL: Duration = Duration::from_millis(10);
+const DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// Reload the word list and search its 3x3 symmetric boxes again, as --watch does on a change
+fn rebuild(path: &Path) -> BTreeSet<String> {
+    let (words, _) = load_words(path.to_str().unwrap(), &WordFilter::default());
+    let lexicon = VecLexicon::initialize(words, BTreeSet::from([3]));
+    solutions(WordBox::new(3, 3, true), &lexicon, &SolverConfig::default())
+}
+
+/// Write `words` to `path` a few times over, as an editor saving in several steps may
+fn save_in_bursts(path: &Path, words: &[&str]) {
+    for _ in 0..3 {
+        std::fs::write(path, words.join("\n") + "\n").unwrap();
+        thread::sleep(POLL);
+    }
+}
+
+#[test]
+fn an_edit_to_the_word_list_runs_the_search_again() {
+    let dir = temp_dir("watch");
+    let list = write_words(&dir, "words.txt", &["cat", "ace"]);
+    let other = write_words(&dir, "other.txt", &["dog"]);
+    let mut watcher = FileWatcher::new(vec![list.clone(), other], POLL, DEBOUNCE);
+    let cancellation = CancellationToken::new();
+    assert!(rebuild(&list).is_empty());
+
+    let saving = list.clone();
+    let editor = thread::spawn(move || save_in_bursts(&saving, &["cat", "ace", "tea"]));
+    let saved = watcher.wait(&cancellation);
+    editor.join().unwrap();
+    assert_eq!(saved, Some(vec![list.clone()]));
+    assert!(watcher.contents_changed());
+    assert_eq!(
+        rebuild(&list),
+        BTreeSet::from(["cat\nace\ntea\n".to_string()])
+    );
+
+    // Saving the file as it is changes nothing to search again for
+    let saving = list.clone();
+    let editor = thread::spawn(move || save_in_bursts(&saving, &["cat", "ace", "tea"]));
+    assert_eq!(watcher.wait(&cancellation), Some(vec![list]));
+    editor.join().unwrap();
+    assert!(!watcher.contents_changed());
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn cancelling_stops_the_wait() {
+    let dir = temp_dir("watch-cancel");
+    let list = write_words(&dir, "words.txt", &["cat"]);
+    let mut watcher = FileWatcher::new(vec![list], POLL, DEBOUNCE);
+    let cancellation = CancellationToken::new();
+    let interrupt = cancellation.clone();
+    let ctrl_c = thread::spawn(move || {
+        thread::sleep(DEBOUNCE);
+        interrupt.cancel();
+    });
+    assert_eq!(watcher.wait(&cancellation), None);
+    ctrl_c.join().unwrap();
+    std::fs::remove_dir_all(dir).ok();
+}